@@ -1,9 +1,93 @@
 use bevy::prelude::*;
 use bevy::window::{PresentMode, WindowMode, WindowPlugin};
 use bevy_framepace::FramepacePlugin;
+use emergence_lib::asset_management::manifest::check::{check_manifests, export_recipe_manifest};
+use emergence_lib::asset_management::manifest::graph::write_manifest_graph;
+use emergence_lib::construction::zoning_snapshot::ZoningSnapshotSettings;
 use emergence_lib::world_gen::GenerationConfig;
+use std::path::{Path, PathBuf};
+
+/// Checks the manifests in the following directory and exits, instead of launching the game.
+const CHECK_MANIFESTS_FLAG: &str = "--check-manifests";
+
+/// Writes the dependency graph of the manifests in the following directory to the following DOT file and exits,
+/// instead of launching the game.
+const MANIFEST_GRAPH_FLAG: &str = "--manifest-graph";
+
+/// Writes the recipes in the following directory to the following JSON file, sorted by name, and exits,
+/// instead of launching the game.
+const EXPORT_RECIPES_FLAG: &str = "--export-recipes";
+
+/// Writes a snapshot of the zoning layer to the following path when the game exits.
+const DUMP_ZONING_FLAG: &str = "--dump-zoning";
 
 fn main() {
+    let args: Vec<String> = std::env::args().collect();
+    if let Some(flag_index) = args.iter().position(|arg| arg == CHECK_MANIFESTS_FLAG) {
+        let Some(dir) = args.get(flag_index + 1) else {
+            eprintln!("Usage: emergence_game {CHECK_MANIFESTS_FLAG} <dir>");
+            std::process::exit(2);
+        };
+
+        let report = check_manifests(Path::new(dir));
+        println!("{report}");
+        std::process::exit(report.exit_code());
+    }
+
+    if let Some(flag_index) = args.iter().position(|arg| arg == MANIFEST_GRAPH_FLAG) {
+        let (Some(dir), Some(output)) = (args.get(flag_index + 1), args.get(flag_index + 2)) else {
+            eprintln!("Usage: emergence_game {MANIFEST_GRAPH_FLAG} <dir> <output.dot>");
+            std::process::exit(2);
+        };
+
+        match write_manifest_graph(Path::new(dir), Path::new(output)) {
+            Ok(unbuildable) => {
+                println!("Wrote manifest graph to {output}");
+                for structure_name in &unbuildable {
+                    println!("Unbuildable structure: {structure_name}");
+                }
+                std::process::exit(if unbuildable.is_empty() { 0 } else { 1 });
+            }
+            Err(io_error) => {
+                eprintln!("Could not write manifest graph: {io_error}");
+                std::process::exit(2);
+            }
+        }
+    }
+
+    if let Some(flag_index) = args.iter().position(|arg| arg == EXPORT_RECIPES_FLAG) {
+        let (Some(dir), Some(output)) = (args.get(flag_index + 1), args.get(flag_index + 2)) else {
+            eprintln!("Usage: emergence_game {EXPORT_RECIPES_FLAG} <dir> <output.json>");
+            std::process::exit(2);
+        };
+
+        match export_recipe_manifest(Path::new(dir), Path::new(output)) {
+            Ok(n_recipes) => {
+                println!("Exported {n_recipes} recipes to {output}");
+                std::process::exit(0);
+            }
+            Err(io_error) => {
+                eprintln!("Could not export recipes: {io_error}");
+                std::process::exit(2);
+            }
+        }
+    }
+
+    let zoning_snapshot_settings = match args.iter().position(|arg| arg == DUMP_ZONING_FLAG) {
+        Some(flag_index) => {
+            let Some(path) = args.get(flag_index + 1) else {
+                eprintln!("Usage: emergence_game {DUMP_ZONING_FLAG} <path>");
+                std::process::exit(2);
+            };
+
+            ZoningSnapshotSettings {
+                path_on_exit: Some(PathBuf::from(path)),
+                ..default()
+            }
+        }
+        None => ZoningSnapshotSettings::default(),
+    };
+
     App::new()
         .add_plugins(DefaultPlugins.set(WindowPlugin {
             primary_window: Some(Window {
@@ -23,5 +107,6 @@ fn main() {
         .add_plugin(emergence_lib::player_interaction::InteractionPlugin)
         .add_plugin(emergence_lib::graphics::GraphicsPlugin)
         .add_plugin(emergence_lib::ui::UiPlugin)
+        .insert_resource(zoning_snapshot_settings)
         .run();
 }
@@ -0,0 +1,64 @@
+use bevy::prelude::*;
+use criterion::{criterion_group, criterion_main, Criterion};
+use emergence_lib::{
+    geometry::{DiscreteHeight, Facing, MapGeometry, PlacementRequirements},
+    structures::Footprint,
+};
+use hexx::Hex;
+
+/// The number of tiles from the center to the edge of the map.
+const MAP_RADIUS: u32 = 50;
+
+/// Creates a map with rolling hills, so that footprints cover a mix of flat and uneven terrain.
+fn setup() -> MapGeometry {
+    let mut world = World::new();
+    let mut map_geometry = MapGeometry::new(&mut world, MAP_RADIUS);
+
+    for hex in map_geometry.all_hexes().copied().collect::<Vec<Hex>>() {
+        let height = DiscreteHeight(((hex.x / 4 + hex.y / 5).rem_euclid(3)) as u8);
+        map_geometry.update_height(hex, height);
+    }
+
+    map_geometry
+}
+
+/// Checks whether a large footprint could be placed centered on every tile of the map.
+fn count_placeable(map_geometry: &MapGeometry, footprint: &Footprint, cached: bool) -> usize {
+    let facing = Facing::default();
+
+    map_geometry
+        .all_hexes()
+        .filter(|&&hex| {
+            let center = map_geometry.on_top_of_terrain(hex);
+            match cached {
+                true => map_geometry.is_placeable(
+                    center,
+                    footprint,
+                    facing,
+                    PlacementRequirements::STRUCTURE,
+                ),
+                false => map_geometry.is_placeable_uncached(
+                    center,
+                    footprint,
+                    facing,
+                    PlacementRequirements::STRUCTURE,
+                ),
+            }
+        })
+        .count()
+}
+
+fn criterion_benchmark(c: &mut Criterion) {
+    let map_geometry = setup();
+    let footprint = Footprint::hexagon(3);
+
+    c.bench_function("placement_cached", |b| {
+        b.iter(|| count_placeable(&map_geometry, &footprint, true))
+    });
+    c.bench_function("placement_uncached", |b| {
+        b.iter(|| count_placeable(&map_geometry, &footprint, false))
+    });
+}
+
+criterion_group!(benches, criterion_benchmark);
+criterion_main!(benches);
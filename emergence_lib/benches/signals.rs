@@ -77,6 +77,33 @@ fn criterion_benchmark(c: &mut Criterion) {
     c.bench_function("signal_diffusion_modest", |b| {
         b.iter(|| modest_signals.diffuse(&modest_map_geometry, DIFFUSION_FRACTION));
     });
+
+    // Compare against the parallel version above to measure the speedup from threading
+    let single_thread_pool = rayon::ThreadPoolBuilder::new()
+        .num_threads(1)
+        .build()
+        .unwrap();
+    let (mut single_threaded_signals, single_threaded_map_geometry) = setup(Settings::MODEST);
+    c.bench_function("signal_diffusion_modest_single_threaded", |b| {
+        b.iter(|| {
+            single_thread_pool.install(|| {
+                single_threaded_signals.diffuse(&single_threaded_map_geometry, DIFFUSION_FRACTION)
+            })
+        });
+    });
+
+    let (mut tick_signals, tick_map_geometry) = setup(Settings::MODEST);
+    c.bench_function("signal_tick_modest", |b| {
+        b.iter(|| {
+            tick_signals.add_signal(
+                SignalType::Pull(ItemKind::Single(Id::from_name("0".to_string()))),
+                VoxelPos::ZERO,
+                SignalStrength::new(1.),
+            );
+            tick_signals.diffuse(&tick_map_geometry, DIFFUSION_FRACTION);
+            tick_signals.degrade();
+        });
+    });
 }
 
 criterion_group!(benches, criterion_benchmark);
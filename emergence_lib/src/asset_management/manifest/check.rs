@@ -0,0 +1,221 @@
+//! Checks manifest files for problems without launching the game.
+//!
+//! This powers the `--check-manifests <dir>` mode of the game binary,
+//! giving modders fast feedback on their changes.
+
+use std::{
+    fmt::Display,
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    crafting::recipe::RawRecipeManifest,
+    items::item_manifest::RawItemManifest,
+    simulation::milestones::RawMilestoneManifest,
+    structures::{custom_kinds::StructureKindRegistry, structure_manifest::RawStructureManifest},
+    terrain::terrain_manifest::RawTerrainManifest,
+    units::unit_manifest::RawUnitManifest,
+};
+
+use super::{
+    load_state::LoadedManifests,
+    loader::{parse_raw_manifest, IsRawManifest, ManifestError},
+    Manifest,
+};
+
+/// A single problem found while checking manifest files.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ManifestProblem {
+    /// The file that contains the problem.
+    pub file: PathBuf,
+    /// The name of the entry that contains the problem, if known.
+    pub entry: Option<String>,
+    /// The field of the entry that contains the problem, if known.
+    pub field: Option<String>,
+    /// A description of what is wrong.
+    pub error: String,
+}
+
+impl ManifestProblem {
+    /// Creates a problem for a [`ManifestError`] found while processing `file`.
+    fn from_manifest_error(file: PathBuf, manifest_error: &ManifestError) -> Self {
+        ManifestProblem {
+            file,
+            entry: Some(manifest_error.entry().to_string()),
+            field: manifest_error.field().map(str::to_string),
+            error: manifest_error.to_string(),
+        }
+    }
+
+    /// Creates a problem that affects `file` as a whole.
+    fn whole_file(file: PathBuf, error: String) -> Self {
+        ManifestProblem {
+            file,
+            entry: None,
+            field: None,
+            error,
+        }
+    }
+}
+
+impl Display for ManifestProblem {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let file = self.file.display();
+        let entry = self.entry.as_deref().unwrap_or("-");
+        let field = self.field.as_deref().unwrap_or("-");
+        let error = &self.error;
+
+        write!(f, "{file} | entry: {entry} | field: {field} | {error}")
+    }
+}
+
+/// The results of checking a directory of manifest files.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ManifestReport {
+    /// Every problem found, in the order they were found.
+    pub problems: Vec<ManifestProblem>,
+}
+
+impl ManifestReport {
+    /// Were the manifests free of problems?
+    pub fn is_ok(&self) -> bool {
+        self.problems.is_empty()
+    }
+
+    /// The exit code that a command-line checker should return: 0 on success, and 1 if any problems were found.
+    pub fn exit_code(&self) -> i32 {
+        match self.is_ok() {
+            true => 0,
+            false => 1,
+        }
+    }
+}
+
+impl Display for ManifestReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.is_ok() {
+            return write!(f, "All manifests are valid.");
+        }
+
+        write!(f, "Found {} manifest problem(s):", self.problems.len())?;
+        for problem in &self.problems {
+            write!(f, "\n{problem}")?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Loads, processes and cross-checks every manifest file in `dir`.
+///
+/// This uses the same parsing, processing and validation steps as the game itself,
+/// so a manifest that passes this check will load without errors.
+pub fn check_manifests(dir: &Path) -> ManifestReport {
+    let mut report = ManifestReport::default();
+
+    let maybe_item_manifest = check_manifest::<RawItemManifest>(dir, &mut report);
+    let maybe_recipe_manifest = check_manifest::<RawRecipeManifest>(dir, &mut report);
+    let maybe_structure_manifest = check_manifest::<RawStructureManifest>(dir, &mut report);
+    check_manifest::<RawTerrainManifest>(dir, &mut report);
+    let maybe_unit_manifest = check_manifest::<RawUnitManifest>(dir, &mut report);
+    let maybe_milestone_manifest = check_manifest::<RawMilestoneManifest>(dir, &mut report);
+
+    // References between manifests can only be checked once each manifest is valid on its own
+    // Only the built-in custom kinds are known outside of the game
+    let built_in_kinds = StructureKindRegistry::default();
+    let loaded_manifests = LoadedManifests {
+        items: maybe_item_manifest.as_ref(),
+        recipes: maybe_recipe_manifest.as_ref(),
+        structures: maybe_structure_manifest.as_ref(),
+        units: maybe_unit_manifest.as_ref(),
+        milestones: maybe_milestone_manifest.as_ref(),
+        structure_kinds: Some(&built_in_kinds),
+    };
+
+    for (path, manifest_error) in loaded_manifests.cross_check() {
+        let file_name = path
+            .file_name()
+            .expect("Manifest paths always end in a file name");
+        report.problems.push(ManifestProblem::from_manifest_error(
+            dir.join(file_name),
+            &manifest_error,
+        ));
+    }
+
+    report
+}
+
+/// Checks the recipe manifest in `dir`, then writes its recipes to `output` using [`RawRecipeManifest::to_json`].
+///
+/// The exported file can be loaded back in place of the original, and is unchanged by a second export.
+/// Returns the number of recipes exported.
+///
+/// Manifest problems are returned as an [`io::ErrorKind::InvalidData`] error.
+pub fn export_recipe_manifest(dir: &Path, output: &Path) -> io::Result<usize> {
+    let mut report = ManifestReport::default();
+    if check_manifest::<RawRecipeManifest>(dir, &mut report).is_none() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            report.to_string(),
+        ));
+    }
+
+    let bytes = fs::read(manifest_path::<RawRecipeManifest>(dir))?;
+    let raw_recipe_manifest = parse_raw_manifest::<RawRecipeManifest>(&bytes)?;
+    fs::write(output, raw_recipe_manifest.to_json()?)?;
+
+    Ok(raw_recipe_manifest.recipes.len())
+}
+
+/// The path to the manifest file of type `M` in `dir`.
+fn manifest_path<M: IsRawManifest>(dir: &Path) -> PathBuf {
+    let file_name = M::path()
+        .file_name()
+        .expect("Manifest paths always end in a file name")
+        .to_owned();
+
+    dir.join(file_name)
+}
+
+/// Reads, parses and processes the manifest file of type `M` in `dir`, recording any problems in the `report`.
+///
+/// Returns the processed manifest if no problems were found.
+pub(super) fn check_manifest<M: IsRawManifest>(
+    dir: &Path,
+    report: &mut ManifestReport,
+) -> Option<Manifest<M::Marker, M::Data>> {
+    let path = manifest_path::<M>(dir);
+
+    let bytes = match fs::read(&path) {
+        Ok(bytes) => bytes,
+        Err(io_error) => {
+            report.problems.push(ManifestProblem::whole_file(
+                path,
+                format!("could not read file: {io_error}"),
+            ));
+            return None;
+        }
+    };
+
+    let raw_manifest = match parse_raw_manifest::<M>(&bytes) {
+        Ok(raw_manifest) => raw_manifest,
+        Err(json_error) => {
+            report.problems.push(ManifestProblem::whole_file(
+                path,
+                format!("could not parse file: {json_error}"),
+            ));
+            return None;
+        }
+    };
+
+    match raw_manifest.process() {
+        Ok(manifest) => Some(manifest),
+        Err(manifest_error) => {
+            report
+                .problems
+                .push(ManifestProblem::from_manifest_error(path, &manifest_error));
+            None
+        }
+    }
+}
@@ -0,0 +1,562 @@
+//! A dependency graph of the structure, item and recipe manifests, for balancing.
+//!
+//! The graph shows which structures craft which recipes, which items those recipes consume and produce,
+//! which items are needed to build each structure, and which structures grow into others.
+//! It can be exported in the DOT format, and checked for structures that could never be built.
+//!
+//! This powers the `--manifest-graph <dir> <output>` mode of the game binary.
+
+use std::{fmt::Write as _, fs, io, path::Path};
+
+use bevy::utils::{HashMap, HashSet};
+
+use crate::{
+    construction::ConstructionStrategy,
+    crafting::{
+        inventories::InputInventory,
+        item_tags::ItemKind,
+        recipe::{RawRecipeManifest, Recipe, RecipeInput, RecipeManifest},
+    },
+    items::item_manifest::{Item, ItemManifest, RawItemManifest},
+    organisms::OrganismId,
+    structures::structure_manifest::{
+        RawStructureManifest, Structure, StructureKind, StructureManifest,
+    },
+};
+
+use super::{
+    check::{check_manifest, ManifestReport},
+    Id,
+};
+
+/// A single entry in one of the manifests.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ManifestNode {
+    /// A structure from the [`StructureManifest`].
+    Structure(Id<Structure>),
+    /// An item from the [`ItemManifest`].
+    Item(Id<Item>),
+    /// A recipe from the [`RecipeManifest`].
+    Recipe(Id<Recipe>),
+}
+
+impl ManifestNode {
+    /// The prefix used for nodes of this type in DOT files, keeping entries that share a name apart.
+    fn prefix(&self) -> &'static str {
+        match self {
+            ManifestNode::Structure(_) => "structure",
+            ManifestNode::Item(_) => "item",
+            ManifestNode::Recipe(_) => "recipe",
+        }
+    }
+
+    /// The shape used to draw nodes of this type in DOT files.
+    fn shape(&self) -> &'static str {
+        match self {
+            ManifestNode::Structure(_) => "box",
+            ManifestNode::Item(_) => "ellipse",
+            ManifestNode::Recipe(_) => "diamond",
+        }
+    }
+}
+
+/// How two [`ManifestNode`]s depend on each other.
+///
+/// Edges always point in the direction that items and structures flow.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum ManifestEdgeKind {
+    /// A structure can craft a recipe.
+    Crafts,
+    /// An item is consumed (or required as a catalyst) by a recipe.
+    Consumes,
+    /// A recipe or structure produces an item.
+    ///
+    /// Structures produce items without recipes by pumping water, leaving remains or starting out full.
+    Produces,
+    /// An item is used to build a structure.
+    Builds,
+    /// A structure grows into another structure, or is the seedling form of it.
+    GrowsInto,
+    /// An item spoils into another item.
+    SpoilsInto,
+}
+
+impl ManifestEdgeKind {
+    /// The label drawn on edges of this kind in DOT files.
+    fn label(&self) -> &'static str {
+        match self {
+            ManifestEdgeKind::Crafts => "crafts",
+            ManifestEdgeKind::Consumes => "consumed by",
+            ManifestEdgeKind::Produces => "produces",
+            ManifestEdgeKind::Builds => "builds",
+            ManifestEdgeKind::GrowsInto => "grows into",
+            ManifestEdgeKind::SpoilsInto => "spoils into",
+        }
+    }
+}
+
+/// A directed edge in a [`ManifestGraph`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ManifestEdge {
+    /// The node that the edge starts at.
+    pub from: ManifestNode,
+    /// The node that the edge ends at.
+    pub to: ManifestNode,
+    /// How the nodes depend on each other.
+    pub kind: ManifestEdgeKind,
+}
+
+/// A set of items that must all be available, where each requirement can be met by any one of several items.
+///
+/// Requirements for a tag can be met by any item with that tag.
+type Requirements = Vec<Vec<Id<Item>>>;
+
+/// Something that makes items, once its source is available and all of its requirements can be met.
+#[derive(Debug, Clone, PartialEq)]
+struct Producer {
+    /// The recipe, structure or item that makes the items.
+    source: ManifestNode,
+    /// The items needed to make the outputs.
+    requires: Requirements,
+    /// The items made.
+    outputs: Vec<Id<Item>>,
+}
+
+/// A typed dependency graph between the entries of the structure, item and recipe manifests.
+#[derive(Debug, Clone, Default)]
+pub struct ManifestGraph {
+    /// The human-readable name of each node.
+    names: HashMap<ManifestNode, String>,
+    /// Every edge in the graph, without duplicates.
+    edges: Vec<ManifestEdge>,
+    /// The items needed to build each structure that is built from materials.
+    construction_requirements: HashMap<Id<Structure>, Requirements>,
+    /// Structures that exist without being built.
+    landmarks: HashSet<Id<Structure>>,
+    /// Everything that makes items.
+    producers: Vec<Producer>,
+}
+
+impl ManifestGraph {
+    /// Builds the dependency graph between the entries of the provided manifests.
+    pub fn build(
+        structure_manifest: &StructureManifest,
+        item_manifest: &ItemManifest,
+        recipe_manifest: &RecipeManifest,
+    ) -> Self {
+        let mut graph = ManifestGraph::default();
+
+        for (&item_id, name) in item_manifest.name_map() {
+            graph
+                .names
+                .insert(ManifestNode::Item(item_id), name.clone());
+        }
+        for (&recipe_id, name) in recipe_manifest.name_map() {
+            graph
+                .names
+                .insert(ManifestNode::Recipe(recipe_id), name.clone());
+        }
+        for (&structure_id, name) in structure_manifest.name_map() {
+            graph
+                .names
+                .insert(ManifestNode::Structure(structure_id), name.clone());
+        }
+
+        for (&item_id, item_data) in item_manifest.data_map() {
+            if let Some(spoiled_id) = item_data.spoils_into {
+                graph.add_edge(
+                    ManifestNode::Item(item_id),
+                    ManifestNode::Item(spoiled_id),
+                    ManifestEdgeKind::SpoilsInto,
+                );
+                graph.producers.push(Producer {
+                    source: ManifestNode::Item(item_id),
+                    requires: Vec::new(),
+                    outputs: vec![spoiled_id],
+                });
+            }
+        }
+
+        for (&recipe_id, recipe_data) in recipe_manifest.data_map() {
+            let recipe_node = ManifestNode::Recipe(recipe_id);
+
+            let mut requires: Requirements = match &recipe_data.inputs {
+                RecipeInput::Exact(inputs) | RecipeInput::Fresh { inputs, .. } => inputs
+                    .iter()
+                    .map(|item_count| vec![item_count.item_id])
+                    .collect(),
+                RecipeInput::Flexible { tag, exclude, .. } => {
+                    let matching = items_matching(ItemKind::Tag(*tag), item_manifest)
+                        .into_iter()
+                        .filter(|item_id| !exclude.contains(item_id))
+                        .collect();
+                    vec![matching]
+                }
+            };
+            requires.extend(
+                recipe_data
+                    .catalysts
+                    .iter()
+                    .map(|&item_kind| items_matching(item_kind, item_manifest)),
+            );
+
+            for &item_id in requires.iter().flatten() {
+                graph.add_edge(
+                    ManifestNode::Item(item_id),
+                    recipe_node,
+                    ManifestEdgeKind::Consumes,
+                );
+            }
+
+            let outputs = recipe_data.outputs.item_ids();
+            for &item_id in &outputs {
+                graph.add_edge(
+                    recipe_node,
+                    ManifestNode::Item(item_id),
+                    ManifestEdgeKind::Produces,
+                );
+            }
+
+            graph.producers.push(Producer {
+                source: recipe_node,
+                requires,
+                outputs,
+            });
+        }
+
+        for (&structure_id, structure_data) in structure_manifest.data_map() {
+            let structure_node = ManifestNode::Structure(structure_id);
+
+            let recipe_choices = std::iter::once(structure_data.starting_recipe())
+                .chain(structure_data.recipe_presets.iter());
+            for active_recipe in recipe_choices {
+                for recipe_id in active_recipe.possible_recipes() {
+                    graph.add_edge(
+                        structure_node,
+                        ManifestNode::Recipe(recipe_id),
+                        ManifestEdgeKind::Crafts,
+                    );
+                }
+            }
+
+            match &structure_data.construction_strategy {
+                ConstructionStrategy::Seedling(seedling_id) => graph.add_edge(
+                    ManifestNode::Structure(*seedling_id),
+                    structure_node,
+                    ManifestEdgeKind::GrowsInto,
+                ),
+                ConstructionStrategy::Direct(construction_data)
+                | ConstructionStrategy::Harvest {
+                    required: construction_data,
+                    ..
+                } => {
+                    let requires: Requirements = match &construction_data.materials {
+                        InputInventory::Exact { inventory } => inventory
+                            .iter()
+                            .map(|item_slot| vec![item_slot.item_id()])
+                            .collect(),
+                        InputInventory::Tagged { tag, .. } => {
+                            vec![items_matching(ItemKind::Tag(*tag), item_manifest)]
+                        }
+                        // Construction materials are never filtered
+                        InputInventory::Filtered { .. } => Vec::new(),
+                    };
+
+                    for &item_id in requires.iter().flatten() {
+                        graph.add_edge(
+                            ManifestNode::Item(item_id),
+                            structure_node,
+                            ManifestEdgeKind::Builds,
+                        );
+                    }
+
+                    graph
+                        .construction_requirements
+                        .insert(structure_id, requires);
+                }
+                ConstructionStrategy::Landmark => {
+                    graph.landmarks.insert(structure_id);
+                }
+            }
+
+            if let Some(organism_variety) = &structure_data.organism_variety {
+                for life_path in organism_variety.lifecycle.life_paths() {
+                    if let OrganismId::Structure(new_form) = life_path.new_form {
+                        graph.add_edge(
+                            structure_node,
+                            ManifestNode::Structure(new_form),
+                            ManifestEdgeKind::GrowsInto,
+                        );
+                    }
+                }
+
+                if let Some(remains) = &organism_variety.remains {
+                    let outputs = remains
+                        .items
+                        .iter()
+                        .map(|item_count| item_count.item_id)
+                        .collect();
+                    graph.add_structure_producer(structure_id, Vec::new(), outputs);
+                }
+            }
+
+            match &structure_data.kind {
+                StructureKind::WellPump { filled_item } => {
+                    // Pumps fill empty containers, which must come from somewhere
+                    let requires = item_manifest
+                        .get(*filled_item)
+                        .water_contents
+                        .as_ref()
+                        .map(|water_contents| vec![vec![water_contents.empty_container]])
+                        .unwrap_or_default();
+                    graph.add_structure_producer(structure_id, requires, vec![*filled_item]);
+                }
+                // Only generated storage starts out full, and landmarks are only ever generated
+                StructureKind::Storage {
+                    starting_inventory, ..
+                } if structure_data.construction_strategy == ConstructionStrategy::Landmark => {
+                    let outputs = starting_inventory
+                        .iter()
+                        .map(|item_count| item_count.item_id)
+                        .collect();
+                    graph.add_structure_producer(structure_id, Vec::new(), outputs);
+                }
+                _ => (),
+            }
+        }
+
+        graph
+    }
+
+    /// Adds an edge, unless an identical one already exists.
+    fn add_edge(&mut self, from: ManifestNode, to: ManifestNode, kind: ManifestEdgeKind) {
+        let edge = ManifestEdge { from, to, kind };
+        if !self.edges.contains(&edge) {
+            self.edges.push(edge);
+        }
+    }
+
+    /// Records that `structure_id` makes the `outputs` without a recipe, once the items it `requires` are available.
+    fn add_structure_producer(
+        &mut self,
+        structure_id: Id<Structure>,
+        requires: Requirements,
+        outputs: Vec<Id<Item>>,
+    ) {
+        let structure_node = ManifestNode::Structure(structure_id);
+        for &item_id in &outputs {
+            self.add_edge(
+                structure_node,
+                ManifestNode::Item(item_id),
+                ManifestEdgeKind::Produces,
+            );
+        }
+
+        self.producers.push(Producer {
+            source: structure_node,
+            requires,
+            outputs,
+        });
+    }
+
+    /// Every edge in the graph.
+    pub fn edges(&self) -> impl Iterator<Item = &ManifestEdge> {
+        self.edges.iter()
+    }
+
+    /// Is there an edge of the provided `kind` between `from` and `to`?
+    pub fn has_edge(&self, from: ManifestNode, to: ManifestNode, kind: ManifestEdgeKind) -> bool {
+        self.edges.contains(&ManifestEdge { from, to, kind })
+    }
+
+    /// The human-readable name of `node`.
+    ///
+    /// Nodes that are referenced but missing from their manifest are named by their [`Id`].
+    pub fn name(&self, node: ManifestNode) -> String {
+        match self.names.get(&node) {
+            Some(name) => name.clone(),
+            None => match node {
+                ManifestNode::Structure(id) => format!("{id:?}"),
+                ManifestNode::Item(id) => format!("{id:?}"),
+                ManifestNode::Recipe(id) => format!("{id:?}"),
+            },
+        }
+    }
+
+    /// The key used for `node` in DOT files.
+    fn dot_key(&self, node: ManifestNode) -> String {
+        let key = format!("{}:{}", node.prefix(), self.name(node));
+        format!("\"{}\"", key.replace('\\', "\\\\").replace('"', "\\\""))
+    }
+
+    /// Serializes the graph in the DOT format, for rendering with Graphviz.
+    ///
+    /// Nodes and edges are sorted by name, so the output is identical for identical manifests.
+    pub fn to_dot(&self) -> String {
+        let mut nodes: Vec<(String, ManifestNode)> = self
+            .names
+            .keys()
+            .chain(self.edges.iter().flat_map(|edge| [&edge.from, &edge.to]))
+            .map(|&node| (self.dot_key(node), node))
+            .collect();
+        nodes.sort_by(|(a, _), (b, _)| a.cmp(b));
+        nodes.dedup_by(|(a, _), (b, _)| a == b);
+
+        let mut edges: Vec<(String, String, ManifestEdgeKind)> = self
+            .edges
+            .iter()
+            .map(|edge| (self.dot_key(edge.from), self.dot_key(edge.to), edge.kind))
+            .collect();
+        edges.sort();
+
+        let mut dot = String::new();
+        writeln!(dot, "digraph manifests {{").unwrap();
+        for (key, node) in &nodes {
+            writeln!(dot, "    {key} [shape={}];", node.shape()).unwrap();
+        }
+        for (from, to, kind) in &edges {
+            writeln!(dot, "    {from} -> {to} [label=\"{}\"];", kind.label()).unwrap();
+        }
+        writeln!(dot, "}}").unwrap();
+
+        dot
+    }
+
+    /// Structures that could never be built, because their construction materials can't be produced.
+    ///
+    /// Only landmarks exist at the start: everything else must be built from materials made by what already exists.
+    /// Structures caught in a cycle, such as an organism built from an item that only it produces,
+    /// are unbuildable unless something outside of the cycle can produce that item.
+    /// Landmarks themselves are never reported. The results are sorted by name.
+    pub fn unbuildable_structures(&self) -> Vec<Id<Structure>> {
+        let mut available_structures: HashSet<Id<Structure>> = self.landmarks.clone();
+        let mut available_items: HashSet<Id<Item>> = HashSet::new();
+
+        let is_met = |requires: &Requirements, available_items: &HashSet<Id<Item>>| {
+            requires.iter().all(|alternatives| {
+                alternatives
+                    .iter()
+                    .any(|item_id| available_items.contains(item_id))
+            })
+        };
+
+        // Keep expanding what can exist until nothing changes
+        loop {
+            let mut changed = false;
+
+            for (structure_id, requires) in &self.construction_requirements {
+                if !available_structures.contains(structure_id)
+                    && is_met(requires, &available_items)
+                {
+                    available_structures.insert(*structure_id);
+                    changed = true;
+                }
+            }
+
+            for edge in &self.edges {
+                if let (
+                    ManifestNode::Structure(from),
+                    ManifestNode::Structure(to),
+                    ManifestEdgeKind::GrowsInto,
+                ) = (edge.from, edge.to, edge.kind)
+                {
+                    if available_structures.contains(&from) && available_structures.insert(to) {
+                        changed = true;
+                    }
+                }
+            }
+
+            for producer in &self.producers {
+                let source_available = match producer.source {
+                    ManifestNode::Structure(structure_id) => {
+                        available_structures.contains(&structure_id)
+                    }
+                    ManifestNode::Item(item_id) => available_items.contains(&item_id),
+                    // Recipes can only be crafted if some available structure can craft them
+                    ManifestNode::Recipe(_) => self.edges.iter().any(|edge| {
+                        edge.kind == ManifestEdgeKind::Crafts
+                            && edge.to == producer.source
+                            && matches!(edge.from, ManifestNode::Structure(structure_id) if available_structures.contains(&structure_id))
+                    }),
+                };
+
+                if source_available && is_met(&producer.requires, &available_items) {
+                    for &item_id in &producer.outputs {
+                        changed |= available_items.insert(item_id);
+                    }
+                }
+            }
+
+            if !changed {
+                break;
+            }
+        }
+
+        let mut unbuildable: Vec<Id<Structure>> = self
+            .names
+            .keys()
+            .filter_map(|node| match node {
+                ManifestNode::Structure(structure_id) => Some(*structure_id),
+                _ => None,
+            })
+            .filter(|structure_id| !available_structures.contains(structure_id))
+            .collect();
+        unbuildable.sort_by_key(|&structure_id| self.name(ManifestNode::Structure(structure_id)));
+
+        unbuildable
+    }
+}
+
+/// Every item in the manifest that matches `item_kind`, sorted for stable output.
+fn items_matching(item_kind: ItemKind, item_manifest: &ItemManifest) -> Vec<Id<Item>> {
+    match item_kind {
+        ItemKind::Single(item_id) => vec![item_id],
+        ItemKind::Tag(_) => {
+            let mut matching: Vec<Id<Item>> = item_manifest
+                .variants()
+                .filter(|&item_id| item_kind.matches(item_id, item_manifest))
+                .collect();
+            matching.sort();
+            matching
+        }
+    }
+}
+
+/// Loads the item, recipe and structure manifests in `dir`, and builds their [`ManifestGraph`].
+///
+/// Fails with a report of every problem found if any of the manifests are invalid.
+pub fn manifest_graph_from_dir(dir: &Path) -> Result<ManifestGraph, ManifestReport> {
+    let mut report = ManifestReport::default();
+
+    let maybe_item_manifest = check_manifest::<RawItemManifest>(dir, &mut report);
+    let maybe_recipe_manifest = check_manifest::<RawRecipeManifest>(dir, &mut report);
+    let maybe_structure_manifest = check_manifest::<RawStructureManifest>(dir, &mut report);
+
+    match (
+        maybe_structure_manifest,
+        maybe_item_manifest,
+        maybe_recipe_manifest,
+    ) {
+        (Some(structure_manifest), Some(item_manifest), Some(recipe_manifest)) => Ok(
+            ManifestGraph::build(&structure_manifest, &item_manifest, &recipe_manifest),
+        ),
+        _ => Err(report),
+    }
+}
+
+/// Writes the [`ManifestGraph::to_dot`] output for the manifests in `dir` to `output`,
+/// listing any structures that can never be built.
+///
+/// Manifest problems are returned as an [`io::ErrorKind::InvalidData`] error.
+pub fn write_manifest_graph(dir: &Path, output: &Path) -> io::Result<Vec<String>> {
+    let graph = manifest_graph_from_dir(dir)
+        .map_err(|report| io::Error::new(io::ErrorKind::InvalidData, report.to_string()))?;
+
+    fs::write(output, graph.to_dot())?;
+
+    Ok(graph
+        .unbuildable_structures()
+        .into_iter()
+        .map(|structure_id| graph.name(ManifestNode::Structure(structure_id)))
+        .collect())
+}
@@ -2,7 +2,11 @@
 
 use bevy::{prelude::Component, reflect::Reflect};
 use serde::{Deserialize, Serialize};
-use std::{fmt::Debug, hash::Hash, marker::PhantomData};
+use std::{
+    fmt::{Debug, Display},
+    hash::Hash,
+    marker::PhantomData,
+};
 
 /// The unique identifier of type `T`.
 ///
@@ -68,6 +72,13 @@ impl<T> Debug for Id<T> {
     }
 }
 
+/// Names are hashed away, so only the numeric value can be shown.
+impl<T> Display for Id<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "#{}", self.value)
+    }
+}
+
 impl<T> PartialEq for Id<T> {
     fn eq(&self, other: &Self) -> bool {
         self.value == other.value
@@ -0,0 +1,529 @@
+//! Tracks whether the manifests loaded successfully, so the game never runs with missing or invalid data.
+//!
+//! Manifests move from [`ManifestLoadState::Loading`] to [`ManifestLoadState::Validating`],
+//! and then on to either [`ManifestLoadState::Ready`] or [`ManifestLoadState::Failed`].
+
+use std::{fmt::Display, path::PathBuf, time::Duration};
+
+use bevy::prelude::*;
+
+use crate::{
+    asset_management::{AssetState, AssetsToLoad},
+    crafting::recipe::{RawRecipeManifest, RecipeManifest},
+    items::item_manifest::ItemManifest,
+    simulation::milestones::{MilestoneManifest, RawMilestoneManifest},
+    structures::{
+        custom_kinds::StructureKindRegistry,
+        structure_manifest::{RawStructureManifest, StructureManifest},
+    },
+    units::unit_manifest::UnitManifest,
+};
+
+use super::{
+    loader::{IsRawManifest, ManifestError},
+    plugin::DetectManifestCreationSet,
+};
+
+/// Drives the [`ManifestLoadState`] state machine.
+pub(crate) struct ManifestLoadStatePlugin;
+
+impl Plugin for ManifestLoadStatePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_state::<ManifestLoadState>()
+            .init_resource::<ManifestLoadErrors>()
+            .init_resource::<ManifestLoadTimeout>()
+            .add_systems(
+                (check_manifests_loaded, time_out_manifest_loading)
+                    .distributive_run_if(in_state(ManifestLoadState::Loading)),
+            )
+            // The manifest resources are created by commands, so they must be applied before they can be cross-checked.
+            // BLOCKED: this can be removed in Bevy 0.11, as schedules will automatically flush the commands.
+            .add_systems(
+                (apply_system_buffers, validate_manifests)
+                    .chain()
+                    .after(DetectManifestCreationSet)
+                    .in_schedule(OnEnter(ManifestLoadState::Validating)),
+            )
+            .add_system(start_loading_assets.in_schedule(OnEnter(ManifestLoadState::Ready)))
+            .add_system(recheck_modified_manifests.run_if(in_state(ManifestLoadState::Ready)))
+            .add_system(report_manifest_errors.in_schedule(OnEnter(ManifestLoadState::Failed)));
+    }
+}
+
+/// The progress of loading the manifests.
+///
+/// Bevy states cannot store data, so the problems that caused [`ManifestLoadState::Failed`]
+/// are stored in the [`ManifestLoadErrors`] resource instead.
+#[derive(States, Default, Clone, Copy, PartialEq, Eq, Debug, Hash)]
+pub enum ManifestLoadState {
+    #[default]
+    /// The manifest files are being read and parsed.
+    Loading,
+    /// Every manifest file has been parsed, and they are being processed and cross-checked.
+    Validating,
+    /// Every manifest is valid, and is available as a resource.
+    Ready,
+    /// At least one manifest could not be loaded or was invalid.
+    ///
+    /// The game will not continue.
+    Failed,
+}
+
+/// A problem that stopped the manifests from loading.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ManifestLoadError {
+    /// The manifest file could not be read or parsed.
+    ///
+    /// The asset server logs the underlying error.
+    Unloadable {
+        /// The path of the manifest file.
+        path: PathBuf,
+    },
+    /// The manifest file was parsed, but its contents are invalid.
+    Invalid {
+        /// The path of the manifest file.
+        path: PathBuf,
+        /// What is wrong with the manifest.
+        error: ManifestError,
+    },
+    /// Some manifests had still not loaded once the [`ManifestLoadTimeout`] expired.
+    TimedOut {
+        /// The assets that were still being waited on.
+        waiting_for: Vec<String>,
+        /// How long we waited for.
+        limit: Duration,
+    },
+}
+
+impl Display for ManifestLoadError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ManifestLoadError::Unloadable { path } => {
+                write!(f, "{}: could not be read or parsed", path.display())
+            }
+            ManifestLoadError::Invalid { path, error } => {
+                write!(f, "{}: {error}", path.display())
+            }
+            ManifestLoadError::TimedOut { waiting_for, limit } => write!(
+                f,
+                "timed out after {limit:?} while waiting for {} to load",
+                waiting_for.join(", ")
+            ),
+        }
+    }
+}
+
+/// Every problem found while loading the manifests.
+///
+/// If this is not empty once loading is complete, we enter [`ManifestLoadState::Failed`].
+#[derive(Resource, Debug, Clone, Default, PartialEq, Deref, DerefMut)]
+pub struct ManifestLoadErrors {
+    /// The problems found, in the order they were found.
+    pub errors: Vec<ManifestLoadError>,
+}
+
+impl Display for ManifestLoadErrors {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Found {} manifest problem(s):", self.errors.len())?;
+        for error in &self.errors {
+            write!(f, "\n{error}")?;
+        }
+
+        Ok(())
+    }
+}
+
+/// How long to wait for the manifest files to load before giving up.
+#[derive(Resource, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ManifestLoadTimeout {
+    /// The longest that loading can take.
+    pub limit: Duration,
+}
+
+impl Default for ManifestLoadTimeout {
+    fn default() -> Self {
+        ManifestLoadTimeout {
+            limit: Duration::from_secs(30),
+        }
+    }
+}
+
+/// Moves on to validation once every manifest has either loaded or failed to load.
+fn check_manifests_loaded(
+    assets_to_load: Res<AssetsToLoad>,
+    errors: Res<ManifestLoadErrors>,
+    mut next_state: ResMut<NextState<ManifestLoadState>>,
+) {
+    if !assets_to_load.remaining.is_empty() {
+        if assets_to_load.is_changed() {
+            info!("Waiting for manifests to load:\n{}", *assets_to_load);
+        }
+        return;
+    }
+
+    match errors.is_empty() {
+        true => {
+            info!("All manifests loaded: transitioning to ManifestLoadState::Validating");
+            next_state.set(ManifestLoadState::Validating);
+        }
+        false => next_state.set(ManifestLoadState::Failed),
+    }
+}
+
+/// Gives up on loading if the manifests take longer than the [`ManifestLoadTimeout`].
+fn time_out_manifest_loading(
+    time: Res<Time>,
+    timeout: Res<ManifestLoadTimeout>,
+    mut elapsed: Local<Duration>,
+    assets_to_load: Res<AssetsToLoad>,
+    mut errors: ResMut<ManifestLoadErrors>,
+    mut next_state: ResMut<NextState<ManifestLoadState>>,
+) {
+    *elapsed += time.delta();
+    if *elapsed < timeout.limit || assets_to_load.remaining.is_empty() {
+        return;
+    }
+
+    let mut waiting_for: Vec<String> = assets_to_load.remaining.values().cloned().collect();
+    waiting_for.sort();
+
+    errors.push(ManifestLoadError::TimedOut {
+        waiting_for,
+        limit: timeout.limit,
+    });
+    next_state.set(ManifestLoadState::Failed);
+}
+
+/// The processed manifests that are checked against each other by [`LoadedManifests::cross_check`].
+///
+/// Manifests that are not available are left as [`None`], and any checks that need them are skipped.
+#[derive(Default, Clone, Copy)]
+pub(crate) struct LoadedManifests<'a> {
+    /// The item manifest.
+    pub(crate) items: Option<&'a ItemManifest>,
+    /// The recipe manifest.
+    pub(crate) recipes: Option<&'a RecipeManifest>,
+    /// The structure manifest.
+    pub(crate) structures: Option<&'a StructureManifest>,
+    /// The unit manifest.
+    pub(crate) units: Option<&'a UnitManifest>,
+    /// The milestone manifest.
+    pub(crate) milestones: Option<&'a MilestoneManifest>,
+    /// The custom structure kinds that have been registered.
+    pub(crate) structure_kinds: Option<&'a StructureKindRegistry>,
+}
+
+impl<'a> LoadedManifests<'a> {
+    /// Checks the references between manifests, which cannot be done while processing each manifest on its own.
+    ///
+    /// Every problem found is returned, along with the path of the manifest that contains it.
+    pub(crate) fn cross_check(&self) -> Vec<(PathBuf, ManifestError)> {
+        let mut problems = Vec::new();
+
+        if let Some(structure_manifest) = self.structures {
+            let mut structure_results = Vec::new();
+
+            if let Some(item_manifest) = self.items {
+                structure_results
+                    .push(structure_manifest.validate_starting_inventories(item_manifest));
+                structure_results.push(structure_manifest.validate_remains(item_manifest));
+                structure_results.push(structure_manifest.validate_logistic_items(item_manifest));
+            }
+
+            if let Some(unit_manifest) = self.units {
+                structure_results
+                    .push(structure_manifest.validate_pollination_partners(unit_manifest));
+            }
+
+            if let Some(recipe_manifest) = self.recipes {
+                structure_results.push(structure_manifest.validate_recipe_choices(recipe_manifest));
+            }
+
+            if let Some(registry) = self.structure_kinds {
+                structure_results.push(structure_manifest.validate_custom_kinds(registry));
+            }
+
+            if let Some(milestone_manifest) = self.milestones {
+                structure_results.push(
+                    milestone_manifest
+                        .validate_required_milestones(structure_manifest, |structure_data| {
+                            structure_data.required_milestone
+                        }),
+                );
+            }
+
+            problems.extend(
+                structure_results
+                    .into_iter()
+                    .filter_map(Result::err)
+                    .map(|error| (RawStructureManifest::path(), error)),
+            );
+        }
+
+        if let (Some(recipe_manifest), Some(item_manifest)) = (self.recipes, self.items) {
+            if let Err(error) = recipe_manifest.validate_item_references(item_manifest) {
+                problems.push((RawRecipeManifest::path(), error));
+            }
+        }
+
+        if let (Some(recipe_manifest), Some(milestone_manifest)) = (self.recipes, self.milestones) {
+            if let Err(error) = milestone_manifest
+                .validate_required_milestones(recipe_manifest, |recipe_data| {
+                    recipe_data.required_milestone
+                })
+            {
+                problems.push((RawRecipeManifest::path(), error));
+            }
+        }
+
+        if let (Some(milestone_manifest), Some(item_manifest), Some(structure_manifest)) =
+            (self.milestones, self.items, self.structures)
+        {
+            if let Err(error) =
+                milestone_manifest.validate_conditions(item_manifest, structure_manifest)
+            {
+                problems.push((RawMilestoneManifest::path(), error));
+            }
+        }
+
+        problems
+    }
+}
+
+/// Checks the references between manifests, once each manifest has been processed on its own.
+fn validate_manifests(
+    maybe_item_manifest: Option<Res<ItemManifest>>,
+    maybe_recipe_manifest: Option<Res<RecipeManifest>>,
+    maybe_structure_manifest: Option<Res<StructureManifest>>,
+    maybe_unit_manifest: Option<Res<UnitManifest>>,
+    maybe_milestone_manifest: Option<Res<MilestoneManifest>>,
+    maybe_structure_kind_registry: Option<Res<StructureKindRegistry>>,
+    mut errors: ResMut<ManifestLoadErrors>,
+    mut next_state: ResMut<NextState<ManifestLoadState>>,
+) {
+    // Invalid manifests are replaced by empty ones, which would only cause spurious errors here
+    if errors.is_empty() {
+        let loaded_manifests = LoadedManifests {
+            items: maybe_item_manifest.as_deref(),
+            recipes: maybe_recipe_manifest.as_deref(),
+            structures: maybe_structure_manifest.as_deref(),
+            units: maybe_unit_manifest.as_deref(),
+            milestones: maybe_milestone_manifest.as_deref(),
+            structure_kinds: maybe_structure_kind_registry.as_deref(),
+        };
+
+        errors.extend(
+            loaded_manifests
+                .cross_check()
+                .into_iter()
+                .map(|(path, error)| ManifestLoadError::Invalid { path, error }),
+        );
+    }
+
+    match errors.is_empty() {
+        true => {
+            info!("All manifests are valid: transitioning to ManifestLoadState::Ready");
+            next_state.set(ManifestLoadState::Ready);
+        }
+        false => next_state.set(ManifestLoadState::Failed),
+    }
+}
+
+/// Checks the references between manifests again whenever one of them is modified on disk.
+///
+/// The modified manifests are already in use, so problems are only logged:
+/// fixing the file and saving it again will reload it.
+fn recheck_modified_manifests(
+    maybe_item_manifest: Option<Res<ItemManifest>>,
+    maybe_recipe_manifest: Option<Res<RecipeManifest>>,
+    maybe_structure_manifest: Option<Res<StructureManifest>>,
+    maybe_unit_manifest: Option<Res<UnitManifest>>,
+    maybe_milestone_manifest: Option<Res<MilestoneManifest>>,
+    maybe_structure_kind_registry: Option<Res<StructureKindRegistry>>,
+) {
+    let modified = maybe_item_manifest
+        .as_ref()
+        .map_or(false, |manifest| manifest.is_changed())
+        || maybe_recipe_manifest
+            .as_ref()
+            .map_or(false, |manifest| manifest.is_changed())
+        || maybe_structure_manifest
+            .as_ref()
+            .map_or(false, |manifest| manifest.is_changed())
+        || maybe_unit_manifest
+            .as_ref()
+            .map_or(false, |manifest| manifest.is_changed())
+        || maybe_milestone_manifest
+            .as_ref()
+            .map_or(false, |manifest| manifest.is_changed());
+    if !modified {
+        return;
+    }
+
+    let loaded_manifests = LoadedManifests {
+        items: maybe_item_manifest.as_deref(),
+        recipes: maybe_recipe_manifest.as_deref(),
+        structures: maybe_structure_manifest.as_deref(),
+        units: maybe_unit_manifest.as_deref(),
+        milestones: maybe_milestone_manifest.as_deref(),
+        structure_kinds: maybe_structure_kind_registry.as_deref(),
+    };
+
+    for (path, error) in loaded_manifests.cross_check() {
+        error!("Modified manifest {} is invalid: {error}", path.display());
+    }
+}
+
+/// Begins loading the rest of the assets, which may depend on the contents of the manifests.
+fn start_loading_assets(mut next_state: ResMut<NextState<AssetState>>) {
+    info!("Transitioning to AssetState::LoadAssets");
+    next_state.set(AssetState::LoadAssets);
+}
+
+/// Reports every problem found while loading the manifests.
+fn report_manifest_errors(errors: Res<ManifestLoadErrors>) {
+    error!("The game could not start.\n{}", *errors);
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{any::TypeId, fs, path::Path};
+
+    use super::*;
+    use crate::{
+        asset_management::{manifest::plugin::ManifestPlugin, AssetManagementPlugin},
+        crafting::recipe::RawRecipeManifest,
+        items::item_manifest::RawItemManifest,
+        terrain::terrain_manifest::RawTerrainManifest,
+        units::unit_manifest::RawUnitManifest,
+    };
+    use bevy::asset::AssetPlugin;
+
+    /// Copies the fixture manifests called `name` into an asset folder, laid out just like the game's assets.
+    fn asset_folder(name: &str) -> PathBuf {
+        let fixture_dir = Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("tests/fixtures/manifests")
+            .join(name);
+        let asset_folder = std::env::temp_dir().join(format!("emergence_load_state_{name}"));
+        let manifest_dir = asset_folder.join("manifests");
+        fs::create_dir_all(&manifest_dir).unwrap();
+
+        for entry in fs::read_dir(fixture_dir).unwrap() {
+            let path = entry.unwrap().path();
+            fs::copy(&path, manifest_dir.join(path.file_name().unwrap())).unwrap();
+        }
+
+        asset_folder
+    }
+
+    fn manifest_app(asset_folder: PathBuf) -> App {
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins)
+            .add_plugin(AssetPlugin {
+                asset_folder: asset_folder.to_string_lossy().to_string(),
+                watch_for_changes: false,
+            })
+            .add_plugin(AssetManagementPlugin)
+            .add_plugin(ManifestPlugin::<RawItemManifest>::new())
+            .add_plugin(ManifestPlugin::<RawRecipeManifest>::new())
+            .add_plugin(ManifestPlugin::<RawStructureManifest>::new())
+            .add_plugin(ManifestPlugin::<RawTerrainManifest>::new())
+            .add_plugin(ManifestPlugin::<RawUnitManifest>::new())
+            .add_plugin(ManifestPlugin::<RawMilestoneManifest>::new());
+
+        app
+    }
+
+    /// Updates the `app` until the manifests are either ready or have failed to load.
+    fn settle(app: &mut App) -> ManifestLoadState {
+        for _ in 0..500 {
+            app.update();
+
+            let state = app.world.resource::<State<ManifestLoadState>>().0;
+            if matches!(state, ManifestLoadState::Ready | ManifestLoadState::Failed) {
+                return state;
+            }
+
+            // Give the asset server a chance to read the files
+            std::thread::sleep(Duration::from_millis(10));
+        }
+
+        panic!("Manifests never finished loading");
+    }
+
+    #[test]
+    fn valid_manifests_become_ready() {
+        let mut app = manifest_app(asset_folder("valid"));
+
+        assert_eq!(settle(&mut app), ManifestLoadState::Ready);
+        assert!(app.world.resource::<ManifestLoadErrors>().is_empty());
+        assert!(!app.world.resource::<ItemManifest>().data_map().is_empty());
+
+        app.update();
+        assert_ne!(
+            app.world.resource::<State<AssetState>>().0,
+            AssetState::LoadManifests
+        );
+    }
+
+    #[test]
+    fn invalid_manifests_fail_with_their_errors() {
+        let mut app = manifest_app(asset_folder("unknown_spoilage_product"));
+
+        assert_eq!(settle(&mut app), ManifestLoadState::Failed);
+        let errors = app.world.resource::<ManifestLoadErrors>();
+        assert_eq!(errors.len(), 1);
+        assert!(matches!(
+            &errors[0],
+            ManifestLoadError::Invalid {
+                path,
+                error: ManifestError::UnknownReference { .. },
+            } if *path == RawItemManifest::path()
+        ));
+
+        // The rest of the game's assets are never loaded
+        app.update();
+        assert_eq!(
+            app.world.resource::<State<AssetState>>().0,
+            AssetState::LoadManifests
+        );
+    }
+
+    #[test]
+    fn unparsable_manifests_fail() {
+        let mut app = manifest_app(asset_folder("malformed_json"));
+
+        assert_eq!(settle(&mut app), ManifestLoadState::Failed);
+        let errors = app.world.resource::<ManifestLoadErrors>();
+        assert_eq!(
+            errors.errors,
+            vec![ManifestLoadError::Unloadable {
+                path: RawItemManifest::path()
+            }]
+        );
+    }
+
+    #[test]
+    fn loading_times_out_if_an_asset_never_resolves() {
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins)
+            .add_plugin(AssetManagementPlugin)
+            .insert_resource(ManifestLoadTimeout {
+                limit: Duration::ZERO,
+            });
+        app.world
+            .resource_mut::<AssetsToLoad>()
+            .remaining
+            .insert(TypeId::of::<()>(), "NeverLoads".to_string());
+
+        assert_eq!(settle(&mut app), ManifestLoadState::Failed);
+        let errors = app.world.resource::<ManifestLoadErrors>();
+        assert_eq!(
+            errors.errors,
+            vec![ManifestLoadError::TimedOut {
+                waiting_for: vec!["NeverLoads".to_string()],
+                limit: Duration::ZERO,
+            }]
+        );
+    }
+}
@@ -1,17 +1,19 @@
 //! A loader for manifest assets.
 
 use std::{
+    collections::BTreeMap,
+    fmt::Display,
     marker::PhantomData,
     path::{Path, PathBuf},
 };
 
 use bevy::{
     asset::{AssetLoader, LoadContext, LoadedAsset},
-    utils::BoxedFuture,
+    utils::{BoxedFuture, HashMap},
 };
 
 use bevy::reflect::TypeUuid;
-use serde::Deserialize;
+use serde::{Deserialize, Serialize, Serializer};
 
 use super::Manifest;
 
@@ -40,7 +42,175 @@ pub trait IsRawManifest:
     }
 
     /// Process the raw manifest from the asset file to the manifest data used in-game.
-    fn process(&self) -> Manifest<Self::Marker, Self::Data>;
+    ///
+    /// If the raw data is invalid, a [`ManifestError`] describing the first problem found is returned instead.
+    fn process(&self) -> Result<Manifest<Self::Marker, Self::Data>, ManifestError>;
+}
+
+/// Serializes the entries of a raw manifest in order of their names.
+///
+/// Raw manifests store their entries in a [`HashMap`] for fast lookup, but its iteration order is arbitrary.
+/// Sorting by name keeps the output stable, so that diffs of serialized manifests stay readable.
+/// Use this with `#[serde(serialize_with = "serialize_sorted")]`: deserialization accepts entries in any order.
+pub fn serialize_sorted<V, S>(
+    entries: &HashMap<String, V>,
+    serializer: S,
+) -> Result<S::Ok, S::Error>
+where
+    V: Serialize,
+    S: Serializer,
+{
+    let sorted_entries: BTreeMap<&String, &V> = entries.iter().collect();
+    sorted_entries.serialize(serializer)
+}
+
+/// A problem found while processing a raw manifest.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ManifestError {
+    /// Two entries have names that resolve to the same [`Id`](super::Id).
+    DuplicateName {
+        /// The name of the entry that was already in the manifest.
+        existing: String,
+        /// The name of the entry that could not be added.
+        duplicate: String,
+    },
+    /// An entry refers to another entry that does not exist.
+    UnknownReference {
+        /// The name of the entry containing the reference.
+        entry: String,
+        /// The field of the entry that contains the reference.
+        field: String,
+        /// The name that could not be found.
+        reference: String,
+    },
+    /// The starting items for an entry do not fit in its inventory.
+    InventoryOverflow {
+        /// The name of the entry with the inventory.
+        entry: String,
+        /// The number of items that could not fit.
+        excess: u32,
+    },
+    /// A recipe contains values that cannot be used.
+    MalformedRecipe {
+        /// The name of the recipe.
+        recipe: String,
+        /// What is wrong with the recipe.
+        reason: String,
+    },
+    /// An item contains values that cannot be used.
+    MalformedItem {
+        /// The name of the item.
+        item: String,
+        /// What is wrong with the item.
+        reason: String,
+    },
+    /// A structure contains values that cannot be used.
+    MalformedStructure {
+        /// The name of the structure.
+        structure: String,
+        /// What is wrong with the structure.
+        reason: String,
+    },
+    /// A unit contains values that cannot be used.
+    MalformedUnit {
+        /// The name of the unit.
+        unit: String,
+        /// What is wrong with the unit.
+        reason: String,
+    },
+    /// A tag in the item tag hierarchy is its own ancestor.
+    CyclicTagHierarchy {
+        /// The name of the tag that implies itself.
+        tag: String,
+    },
+}
+
+impl ManifestError {
+    /// The name of the entry that caused this error.
+    pub fn entry(&self) -> &str {
+        match self {
+            ManifestError::DuplicateName { duplicate, .. } => duplicate,
+            ManifestError::UnknownReference { entry, .. } => entry,
+            ManifestError::InventoryOverflow { entry, .. } => entry,
+            ManifestError::MalformedRecipe { recipe, .. } => recipe,
+            ManifestError::MalformedItem { item, .. } => item,
+            ManifestError::MalformedStructure { structure, .. } => structure,
+            ManifestError::MalformedUnit { unit, .. } => unit,
+            ManifestError::CyclicTagHierarchy { tag } => tag,
+        }
+    }
+
+    /// The field of the entry that caused this error, if the error is tied to a single field.
+    pub fn field(&self) -> Option<&str> {
+        match self {
+            ManifestError::DuplicateName { .. } => None,
+            ManifestError::UnknownReference { field, .. } => Some(field),
+            ManifestError::InventoryOverflow { .. } => Some("starting_inventory"),
+            ManifestError::MalformedRecipe { .. } => None,
+            ManifestError::MalformedItem { .. } => None,
+            ManifestError::MalformedStructure { .. } => None,
+            ManifestError::MalformedUnit { .. } => None,
+            ManifestError::CyclicTagHierarchy { .. } => Some("tag_hierarchy"),
+        }
+    }
+}
+
+impl Display for ManifestError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ManifestError::DuplicateName {
+                existing,
+                duplicate,
+            } => write!(
+                f,
+                "the name \"{duplicate}\" has the same ID as \"{existing}\": rename one of them"
+            ),
+            ManifestError::UnknownReference {
+                entry,
+                field,
+                reference,
+            } => {
+                write!(
+                    f,
+                    "the {field} of \"{entry}\" refers to \"{reference}\", which does not exist"
+                )
+            }
+            ManifestError::InventoryOverflow { entry, excess } => {
+                write!(
+                    f,
+                    "the starting inventory of \"{entry}\" is too large: {excess} items do not fit"
+                )
+            }
+            ManifestError::MalformedRecipe { recipe, reason } => {
+                write!(f, "the recipe \"{recipe}\" is malformed: {reason}")
+            }
+            ManifestError::MalformedItem { item, reason } => {
+                write!(f, "the item \"{item}\" is malformed: {reason}")
+            }
+            ManifestError::MalformedStructure { structure, reason } => {
+                write!(f, "the structure \"{structure}\" is malformed: {reason}")
+            }
+            ManifestError::MalformedUnit { unit, reason } => {
+                write!(f, "the unit \"{unit}\" is malformed: {reason}")
+            }
+            ManifestError::CyclicTagHierarchy { tag } => {
+                write!(
+                    f,
+                    "the tag hierarchy is cyclic: the tag \"{tag}\" is one of its own parents"
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for ManifestError {}
+
+/// Parses the contents of a `.manifest.json` file into a raw manifest, ready to be processed.
+pub fn parse_raw_manifest<M>(bytes: &[u8]) -> Result<M, serde_json::Error>
+where
+    M: IsRawManifest,
+{
+    serde_json::from_slice::<M>(bytes)
 }
 
 /// A loader for `.manifest.json` files.
@@ -67,7 +237,7 @@ where
         load_context: &'a mut LoadContext,
     ) -> BoxedFuture<'a, anyhow::Result<(), anyhow::Error>> {
         Box::pin(async move {
-            let raw_manifest = serde_json::from_slice::<M>(bytes)?;
+            let raw_manifest = parse_raw_manifest::<M>(bytes)?;
             load_context.set_default_asset(LoadedAsset::<M>::new(raw_manifest));
             Ok(())
         })
@@ -84,3 +254,218 @@ where
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use bevy::utils::HashMap;
+
+    use super::*;
+    use crate::{
+        asset_management::manifest::Id,
+        crafting::{
+            inventories::InputInventory,
+            item_tags::{ItemKind, ItemTag},
+            recipe::{RawRecipeData, RawRecipeInput, RawRecipeManifest},
+        },
+        items::{
+            inventory::Inventory,
+            item_manifest::{Item, RawItemData, RawItemManifest},
+        },
+        organisms::RawOrganismId,
+    };
+
+    fn raw_item(spoils_into: Option<&str>) -> RawItemData {
+        RawItemData {
+            stack_size: 10,
+            compostable: true,
+            fluid: false,
+            buoyant: true,
+            fragile: false,
+            seed: None,
+            shelf_life: spoils_into.map(|_| 10.),
+            spoils_into: spoils_into.map(str::to_string),
+            water_contents: None,
+            substitutes: Vec::new(),
+        }
+    }
+
+    fn raw_recipe(craft_time: f32) -> RawRecipeData {
+        RawRecipeData {
+            inputs: RawRecipeInput::single("leaf", 1),
+            catalysts: Vec::new(),
+            outputs: HashMap::from_iter([("compost".to_string(), 1.)]),
+            effects: Vec::new(),
+            craft_time,
+            conditions: None,
+            energy: None,
+            required_milestone: None,
+            draw_from_litter: false,
+        }
+    }
+
+    #[test]
+    fn valid_manifest_is_processed() {
+        let raw_item_manifest = RawItemManifest {
+            items: HashMap::from_iter([
+                ("leaf".to_string(), raw_item(Some("compost"))),
+                ("compost".to_string(), raw_item(None)),
+            ]),
+            tag_hierarchy: HashMap::default(),
+        };
+
+        let item_manifest = raw_item_manifest.process().unwrap();
+        assert_eq!(item_manifest.data_map().len(), 2);
+    }
+
+    #[test]
+    fn names_with_the_same_id_are_duplicates() {
+        // These two names hash to the same ID
+        assert_eq!(
+            Id::<Item>::from_name("!b".to_string()),
+            Id::<Item>::from_name("Va".to_string())
+        );
+
+        let raw_item_manifest = RawItemManifest {
+            items: HashMap::from_iter([
+                ("!b".to_string(), raw_item(None)),
+                ("Va".to_string(), raw_item(None)),
+            ]),
+            tag_hierarchy: HashMap::default(),
+        };
+
+        let result = raw_item_manifest.process();
+        assert!(matches!(result, Err(ManifestError::DuplicateName { .. })));
+    }
+
+    #[test]
+    fn references_to_missing_entries_are_unknown() {
+        let raw_item_manifest = RawItemManifest {
+            items: HashMap::from_iter([("leaf".to_string(), raw_item(Some("compost")))]),
+            tag_hierarchy: HashMap::default(),
+        };
+
+        assert_eq!(
+            raw_item_manifest.process().err(),
+            Some(ManifestError::UnknownReference {
+                entry: "leaf".to_string(),
+                field: "spoils_into".to_string(),
+                reference: "compost".to_string(),
+            })
+        );
+    }
+
+    #[test]
+    fn items_cannot_substitute_for_themselves() {
+        let mut leaf = raw_item(None);
+        leaf.substitutes = vec![("leaf".to_string(), 2)];
+        let raw_item_manifest = RawItemManifest {
+            items: HashMap::from_iter([("leaf".to_string(), leaf)]),
+            tag_hierarchy: HashMap::default(),
+        };
+
+        let result = raw_item_manifest.process();
+        assert!(matches!(
+            result,
+            Err(ManifestError::MalformedItem { item, .. }) if item == "leaf"
+        ));
+    }
+
+    #[test]
+    fn shelf_life_must_be_finite_and_non_negative() {
+        for shelf_life in [-1., f32::NAN, f32::INFINITY] {
+            let mut leaf = raw_item(None);
+            leaf.shelf_life = Some(shelf_life);
+            let raw_item_manifest = RawItemManifest {
+                items: HashMap::from_iter([("leaf".to_string(), leaf)]),
+                tag_hierarchy: HashMap::default(),
+            };
+
+            let result = raw_item_manifest.process();
+            assert!(matches!(
+                result,
+                Err(ManifestError::MalformedItem { item, .. }) if item == "leaf"
+            ));
+        }
+    }
+
+    #[test]
+    fn items_match_tags_implied_by_their_own_tags() {
+        let mut acorn = raw_item(None);
+        acorn.compostable = false;
+        acorn.buoyant = false;
+        acorn.seed = Some(RawOrganismId::Structure("oak".to_string()));
+        let raw_item_manifest = RawItemManifest {
+            items: HashMap::from_iter([("acorn".to_string(), acorn)]),
+            // Seeds are buoyant, and buoyant items are compostable
+            tag_hierarchy: HashMap::from_iter([
+                (ItemTag::Seed, vec![ItemTag::Buoyant]),
+                (ItemTag::Buoyant, vec![ItemTag::Compostable]),
+            ]),
+        };
+
+        let item_manifest = raw_item_manifest.process().unwrap();
+        let acorn = Id::from_name("acorn".to_string());
+        assert!(item_manifest.has_tag(acorn, ItemTag::Compostable));
+        assert!(!item_manifest.has_tag(acorn, ItemTag::Fluid));
+        assert!(item_manifest
+            .kinds_with_tag(ItemTag::Compostable)
+            .contains(&ItemKind::Single(acorn)));
+
+        // Pulls for the parent tag accept the child-tagged item
+        let input_inventory = InputInventory::Tagged {
+            tag: ItemTag::Compostable,
+            exclude: Vec::new(),
+            inventory: Inventory::new(1, None),
+        };
+        assert!(input_inventory.currently_accepts(acorn, &item_manifest));
+    }
+
+    #[test]
+    fn cyclic_tag_hierarchy_is_rejected() {
+        let raw_item_manifest = RawItemManifest {
+            items: HashMap::from_iter([("leaf".to_string(), raw_item(None))]),
+            tag_hierarchy: HashMap::from_iter([
+                (ItemTag::Seed, vec![ItemTag::Compostable]),
+                (ItemTag::Compostable, vec![ItemTag::Buoyant]),
+                (ItemTag::Buoyant, vec![ItemTag::Seed]),
+            ]),
+        };
+
+        let result = raw_item_manifest.process();
+        assert!(matches!(
+            result,
+            Err(ManifestError::CyclicTagHierarchy { .. })
+        ));
+    }
+
+    #[test]
+    fn negative_craft_time_is_malformed() {
+        let raw_recipe_manifest = RawRecipeManifest {
+            recipes: HashMap::from_iter([("composting".to_string(), raw_recipe(-1.))]),
+        };
+
+        let result = raw_recipe_manifest.process();
+        assert!(matches!(
+            result,
+            Err(ManifestError::MalformedRecipe { recipe, .. }) if recipe == "composting"
+        ));
+    }
+
+    #[test]
+    fn out_of_range_freshness_is_malformed() {
+        let mut raw_recipe = raw_recipe(1.);
+        raw_recipe.inputs = RawRecipeInput::Fresh {
+            inputs: HashMap::from_iter([("leaf".to_string(), 1)]),
+            min_freshness: 1.5,
+        };
+        let raw_recipe_manifest = RawRecipeManifest {
+            recipes: HashMap::from_iter([("composting".to_string(), raw_recipe)]),
+        };
+
+        let result = raw_recipe_manifest.process();
+        assert!(matches!(
+            result,
+            Err(ManifestError::MalformedRecipe { recipe, .. }) if recipe == "composting"
+        ));
+    }
+}
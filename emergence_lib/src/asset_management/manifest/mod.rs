@@ -1,15 +1,21 @@
 //! Read-only definitions for game objects.
 //!
 //! These are intended to be loaded from a file or dynamically generated via gameplay.
+//! Manifests can also be constructed directly in code using a [`ManifestBuilder`],
+//! which is useful for tests and mods that do not want to ship asset files.
 //! Other systems should look up the data contained here,
 //! in order to populate the properties of in-game entities.
 
 mod identifier;
 
 pub use self::identifier::*;
+pub mod check;
+pub mod graph;
+pub mod load_state;
 pub mod loader;
 pub mod plugin;
 
+use self::loader::ManifestError;
 use bevy::{prelude::*, utils::HashMap};
 use std::{any::type_name, fmt::Debug};
 
@@ -63,10 +69,48 @@ where
     pub fn insert(&mut self, name: String, data: Data) {
         let id = Id::from_name(name.clone());
 
+        self.insert_with_id(id, name, data);
+    }
+
+    /// Adds an entry to the manifest under an explicitly chosen `id`, rather than one derived from the `name`.
+    ///
+    /// This is useful in tests, where the [`Id`] of an entry needs to be known in advance.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use emergence_lib::asset_management::manifest::{Id, Manifest};
+    ///
+    /// let id = Id::from_name("acacia".to_string());
+    /// let mut manifest: Manifest<(), u32> = Manifest::new();
+    /// manifest.insert_with_id(id, "acacia".to_string(), 3);
+    ///
+    /// assert_eq!(*manifest.get(id), 3);
+    /// assert_eq!(manifest.name(id), "acacia");
+    /// ```
+    pub fn insert_with_id(&mut self, id: Id<T>, name: String, data: Data) {
         self.data_map.insert(id, data);
         self.name_map.insert(id, name);
     }
 
+    /// Adds an entry to the manifest, like [`Manifest::insert`].
+    ///
+    /// Unlike [`Manifest::insert`], this will never overwrite existing data:
+    /// if an entry with the same [`Id`] already exists, a [`ManifestError::DuplicateName`] is returned instead.
+    pub fn try_insert(&mut self, name: String, data: Data) -> Result<(), ManifestError> {
+        let id = Id::from_name(name.clone());
+
+        if let Some(existing) = self.name_map.get(&id) {
+            return Err(ManifestError::DuplicateName {
+                existing: existing.clone(),
+                duplicate: name,
+            });
+        }
+
+        self.insert(name, data);
+        Ok(())
+    }
+
     /// Get the data entry for the given ID.
     ///
     /// # Panics
@@ -95,6 +139,13 @@ where
         })
     }
 
+    /// Returns the human-readable name associated with the provided `id`, if it is in the manifest.
+    ///
+    /// Unlike [`Manifest::name`], this does not panic when the `id` is unknown.
+    pub fn try_name(&self, id: Id<T>) -> Option<&str> {
+        self.name_map.get(&id).map(String::as_str)
+    }
+
     /// Returns the complete list of names of the loaded options.
     ///
     /// The order is arbitrary.
@@ -106,9 +157,139 @@ where
     /// The complete list of loaded options.
     ///
     /// The order is arbitrary.
-    pub fn variants(&self) -> impl IntoIterator<Item = Id<T>> + '_ {
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use emergence_lib::asset_management::manifest::{Id, Manifest};
+    ///
+    /// let manifest: Manifest<(), u32> = [("acacia".to_string(), 3)].into_iter().collect();
+    /// let ids: Vec<Id<()>> = manifest.variants().collect();
+    ///
+    /// assert_eq!(ids, vec![Id::from_name("acacia".to_string())]);
+    /// ```
+    pub fn variants(&self) -> impl Iterator<Item = Id<T>> + '_ {
         self.data_map.keys().copied()
     }
+
+    /// The [`Id`] of every loaded option.
+    ///
+    /// This is the same as [`Manifest::variants`], and the order is arbitrary.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use emergence_lib::asset_management::manifest::{Id, Manifest};
+    ///
+    /// let manifest: Manifest<(), u32> = [("acacia".to_string(), 3)].into_iter().collect();
+    /// let ids: Vec<Id<()>> = manifest.ids().collect();
+    ///
+    /// assert_eq!(ids, vec![Id::from_name("acacia".to_string())]);
+    /// ```
+    pub fn ids(&self) -> impl Iterator<Item = Id<T>> + '_ {
+        self.variants()
+    }
+
+    /// The number of entries in the manifest.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use emergence_lib::asset_management::manifest::Manifest;
+    ///
+    /// let manifest: Manifest<(), u32> = [("acacia".to_string(), 3), ("leuco".to_string(), 1)]
+    ///     .into_iter()
+    ///     .collect();
+    ///
+    /// assert_eq!(manifest.len(), 2);
+    /// ```
+    pub fn len(&self) -> usize {
+        self.data_map.len()
+    }
+
+    /// Does this manifest contain no entries?
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use emergence_lib::asset_management::manifest::Manifest;
+    ///
+    /// let manifest: Manifest<(), u32> = Manifest::new();
+    ///
+    /// assert!(manifest.is_empty());
+    /// ```
+    pub fn is_empty(&self) -> bool {
+        self.data_map.is_empty()
+    }
+}
+
+/// Collects `(name, data)` pairs into a manifest, as if each was added via [`Manifest::insert`].
+///
+/// # Examples
+///
+/// ```
+/// use emergence_lib::asset_management::manifest::{Id, Manifest};
+///
+/// let manifest: Manifest<(), u32> = [("acacia".to_string(), 3)].into_iter().collect();
+///
+/// assert_eq!(*manifest.get(Id::from_name("acacia".to_string())), 3);
+/// ```
+impl<T, Data> FromIterator<(String, Data)> for Manifest<T, Data>
+where
+    Data: Debug,
+{
+    fn from_iter<I: IntoIterator<Item = (String, Data)>>(iter: I) -> Self {
+        let mut manifest = Manifest::new();
+        for (name, data) in iter {
+            manifest.insert(name, data);
+        }
+        manifest
+    }
+}
+
+/// Constructs a [`Manifest`] in code, one entry at a time.
+///
+/// Call [`ManifestBuilder::build`] once all entries have been added.
+#[derive(Debug)]
+pub struct ManifestBuilder<T, Data>
+where
+    T: 'static,
+    Data: Debug,
+{
+    /// The manifest under construction.
+    manifest: Manifest<T, Data>,
+}
+
+impl<T: 'static, Data: Debug> Default for ManifestBuilder<T, Data> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T, Data> ManifestBuilder<T, Data>
+where
+    Data: Debug,
+{
+    /// Creates a new builder for an empty manifest.
+    pub fn new() -> Self {
+        Self {
+            manifest: Manifest::new(),
+        }
+    }
+
+    /// Adds an entry with the provided `name` to the manifest.
+    ///
+    /// If an entry with the same name was already added, it is overwritten.
+    #[must_use]
+    pub fn add(mut self, name: impl Into<String>, data: Data) -> Self {
+        self.manifest.insert(name.into(), data);
+        self
+    }
+
+    /// Completes construction, returning the finished [`Manifest`].
+    pub fn build(self) -> Manifest<T, Data> {
+        self.manifest
+    }
 }
 
 /// A plugin that adds the default manifests to the app.
@@ -121,6 +302,7 @@ impl Plugin for DummyManifestPlugin {
         use crate::{
             crafting::recipe::RecipeManifest,
             items::item_manifest::ItemManifest,
+            simulation::milestones::MilestoneManifest,
             structures::structure_manifest::{StructureData, StructureManifest},
             terrain::terrain_manifest::{TerrainData, TerrainManifest},
             units::basic_needs::Diet,
@@ -153,5 +335,8 @@ impl Plugin for DummyManifestPlugin {
 
         let recipe_manifest = RecipeManifest::default();
         app.insert_resource(recipe_manifest);
+
+        let milestone_manifest = MilestoneManifest::default();
+        app.insert_resource(milestone_manifest);
     }
 }
@@ -2,11 +2,12 @@
 
 use std::marker::PhantomData;
 
-use bevy::prelude::*;
+use bevy::{asset::LoadState, prelude::*};
 
-use crate::asset_management::{AssetCollectionExt, AssetState, Loadable};
+use crate::asset_management::{AssetCollectionExt, AssetState, AssetsToLoad, Loadable};
 
 use super::{
+    load_state::{ManifestLoadError, ManifestLoadErrors, ManifestLoadState},
     loader::{IsRawManifest, RawManifestLoader},
     Manifest,
 };
@@ -46,10 +47,13 @@ where
         app.init_asset_loader::<RawManifestLoader<M>>()
             .add_asset::<M>()
             .add_asset_collection::<RawManifestHandle<M>>()
+            .add_system(
+                detect_manifest_load_failure::<M>.run_if(in_state(ManifestLoadState::Loading)),
+            )
             .add_system(
                 detect_manifest_creation::<M>
                     .in_set(DetectManifestCreationSet)
-                    .in_schedule(OnExit(AssetState::LoadManifests)),
+                    .in_schedule(OnEnter(ManifestLoadState::Validating)),
             )
             .add_system(
                 detect_manifest_modification::<M>
@@ -94,23 +98,69 @@ where
     }
 }
 
+/// Records manifests that could not be loaded, so that loading can fail instead of waiting forever.
+fn detect_manifest_load_failure<M>(
+    raw_manifest_handle: Res<RawManifestHandle<M>>,
+    asset_server: Res<AssetServer>,
+    mut assets_to_load: ResMut<AssetsToLoad>,
+    mut errors: ResMut<ManifestLoadErrors>,
+) where
+    M: IsRawManifest,
+{
+    if !assets_to_load.contains::<RawManifestHandle<M>>() {
+        return;
+    }
+
+    if raw_manifest_handle.load_state(&asset_server) == LoadState::Failed {
+        error!(
+            "Manifest asset {} could not be loaded!",
+            M::path().display()
+        );
+
+        // This manifest will never load, so stop waiting for it
+        assets_to_load.remove::<RawManifestHandle<M>>();
+        errors.push(ManifestLoadError::Unloadable { path: M::path() });
+    }
+}
+
 /// Wait for the manifest to be fully loaded and then process it.
+///
+/// Any problems are recorded in [`ManifestLoadErrors`].
 pub fn detect_manifest_creation<M>(
     mut commands: Commands,
     raw_manifest_handle: Res<RawManifestHandle<M>>,
     raw_manifests: Res<Assets<M>>,
+    mut errors: ResMut<ManifestLoadErrors>,
 ) where
     M: IsRawManifest,
 {
     let Some(raw_manifest) = raw_manifests.get(&raw_manifest_handle.handle) else {
-        error!("Raw manifest for {} created, but asset not available!", M::path().display());
+        error!(
+            "Raw manifest for {} created, but asset not available!",
+            M::path().display()
+        );
+        errors.push(ManifestLoadError::Unloadable { path: M::path() });
         return;
     };
 
     info!("Manifest asset {} loaded!", M::path().display());
 
     // Create the manifest and insert it as a resource
-    commands.insert_resource(raw_manifest.process());
+    match raw_manifest.process() {
+        Ok(manifest) => commands.insert_resource(manifest),
+        Err(manifest_error) => {
+            error!(
+                "Could not process manifest {}: {manifest_error}.",
+                M::path().display()
+            );
+            errors.push(ManifestLoadError::Invalid {
+                path: M::path(),
+                error: manifest_error,
+            });
+            // Insert an empty manifest so systems that read it don't panic: the simulation won't start anyway
+            commands.insert_resource(Manifest::<M::Marker, M::Data>::default());
+        }
+    }
 }
 
 /// Update the manifest after the asset has been changed.
@@ -130,8 +180,14 @@ fn detect_manifest_modification<M>(
 
             debug!("Manifest asset {} modified.", M::path().display());
 
-            // Update the manifest resource
-            *manifest = raw_manifest.process();
+            // Update the manifest resource, keeping the previous version if the new one is invalid
+            match raw_manifest.process() {
+                Ok(processed_manifest) => *manifest = processed_manifest,
+                Err(manifest_error) => error!(
+                    "Could not process modified manifest {}: {manifest_error}. Keeping the previous version.",
+                    M::path().display()
+                ),
+            }
         }
     }
 }
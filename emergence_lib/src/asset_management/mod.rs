@@ -15,7 +15,7 @@ use std::{
     fmt::{Display, Formatter},
 };
 
-use self::manifest::plugin::DetectManifestCreationSet;
+use self::manifest::load_state::ManifestLoadStatePlugin;
 use bevy::{
     asset::LoadState,
     prelude::*,
@@ -31,15 +31,8 @@ impl Plugin for AssetManagementPlugin {
     fn build(&self, app: &mut App) {
         app.add_state::<AssetState>()
             .init_resource::<AssetsToLoad>()
-            .add_system(check_manifests_loaded.run_if(in_state(AssetState::LoadManifests)))
-            .add_system(check_assets_loaded.run_if(in_state(AssetState::LoadAssets)))
-            // This is needed to ensure that the manifest resources are actually created in time for AssetState::Loading
-            // BLOCKED: this can be removed in Bevy 0.11, as schedules will automatically flush the commands.
-            .add_system(
-                apply_system_buffers
-                    .after(DetectManifestCreationSet)
-                    .in_schedule(OnExit(AssetState::LoadManifests)),
-            );
+            .add_plugin(ManifestLoadStatePlugin)
+            .add_system(check_assets_loaded.run_if(in_state(AssetState::LoadAssets)));
     }
 }
 
@@ -48,6 +41,8 @@ impl Plugin for AssetManagementPlugin {
 pub enum AssetState {
     #[default]
     /// Load manifests.
+    ///
+    /// The progress of this stage is tracked in more detail by [`ManifestLoadState`](manifest::load_state::ManifestLoadState).
     LoadManifests,
     /// Assets still need to be loaded.
     LoadAssets,
@@ -94,20 +89,6 @@ impl AssetsToLoad {
     }
 }
 
-/// A system that checks if all manifests are loaded.
-fn check_manifests_loaded(
-    assets_to_load: Res<AssetsToLoad>,
-    mut next_state: ResMut<NextState<AssetState>>,
-) {
-    if assets_to_load.remaining.is_empty() {
-        info!("All manifests loaded: transitioning to AssetState::LoadAssets");
-
-        next_state.set(AssetState::LoadAssets);
-    } else if assets_to_load.is_changed() {
-        info!("Waiting for manifests to load:\n{}", *assets_to_load);
-    }
-}
-
 /// A system that checks if all assets are loaded.
 fn check_assets_loaded(
     assets_to_load: Res<AssetsToLoad>,
@@ -5,6 +5,7 @@ use bevy::{ecs::system::SystemParam, prelude::*};
 use crate::{
     asset_management::manifest::Id,
     geometry::{Height, MapGeometry, VoxelPos},
+    items::ItemCount,
     signals::{Emitter, SignalStrength, SignalType},
     structures::structure_manifest::Structure,
 };
@@ -13,6 +14,16 @@ use crate::{
 #[derive(Component, Debug)]
 pub(crate) struct MarkedForDemolition;
 
+/// Items that are recovered when this structure is demolished.
+///
+/// Structures with salvage can be demolished even if they are [`Landmark`](crate::structures::Landmark)s,
+/// such as the remains left behind by dead organisms.
+#[derive(Component, Debug, Clone, PartialEq)]
+pub(crate) struct Salvage {
+    /// The items dropped as litter on and around the structure's footprint when it is demolished.
+    pub(crate) items: Vec<ItemCount>,
+}
+
 /// A query for the structures that need to be demolished.
 #[derive(SystemParam)]
 pub(crate) struct DemolitionQuery<'w, 's> {
@@ -14,20 +14,23 @@ use crate::simulation::SimulationSet;
 use crate::structures::commands::StructureCommandsExt;
 use crate::structures::structure_manifest::{Structure, StructureManifest};
 use crate::terrain::terrain_manifest::TerrainManifest;
+use crate::units::item_interaction::{count_in_flight_items, InFlightItems};
 use crate::{self as emergence_lib, graphics::InheritedMaterial};
 use bevy::prelude::*;
 use bevy::utils::{Duration, HashMap};
 use bevy_mod_raycast::RaycastMesh;
 use emergence_macros::IterableEnum;
+use std::fmt::Display;
 
 use crate::{
     asset_management::manifest::Id,
     crafting::inventories::{CraftingState, InputInventory},
     geometry::{Facing, VoxelPos},
     player_interaction::clipboard::ClipboardData,
-    signals::{Emitter, SignalStrength, SignalType},
+    signals::{Emitter, LogisticsCategory, LogisticsPriorities, SignalType},
 };
 
+use super::harvest::HarvestsMaterials;
 use super::terraform::TerraformingAction;
 use super::ConstructionStrategy;
 
@@ -39,8 +42,12 @@ impl Plugin for GhostPlugin {
         app.init_resource::<GhostHandles>().add_systems(
             (
                 validate_ghost_structures,
-                ghost_structure_signals.after(validate_ghost_structures),
-                ghost_structure_lifecycle.after(validate_ghost_structures),
+                ghost_terraforming.after(validate_ghost_structures),
+                ghost_structure_signals
+                    .after(ghost_terraforming)
+                    // Changes to in-flight items are only reported for a single tick
+                    .after(count_in_flight_items),
+                ghost_structure_lifecycle.after(ghost_terraforming),
             )
                 .in_set(SimulationSet)
                 .in_schedule(CoreSchedule::FixedUpdate),
@@ -123,6 +130,29 @@ impl GhostBundle {
     }
 }
 
+/// The stage of construction that a ghost structure has reached.
+#[derive(Component, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub(crate) enum ConstructionPhase {
+    /// The terrain beneath the ghost is being raised or lowered to the height required by the structure.
+    ///
+    /// No construction materials are accepted until this is complete.
+    Terraforming,
+    /// Construction materials are being delivered and work is being done, as tracked by the ghost's [`CraftingState`].
+    #[default]
+    Building,
+}
+
+impl Display for ConstructionPhase {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let str = match self {
+            ConstructionPhase::Terraforming => "Terraforming",
+            ConstructionPhase::Building => "Building",
+        };
+
+        write!(f, "{str}")
+    }
+}
+
 /// The set of components needed to spawn a ghost of a [`Structure`].
 #[derive(Bundle)]
 pub(crate) struct GhostStructureBundle {
@@ -142,14 +172,19 @@ pub(crate) struct GhostStructureBundle {
     workers_present: WorkersPresent,
     /// Tracks work that needs to be done on this building
     crafting_state: CraftingState,
+    /// Is the terrain still being prepared, or can construction begin?
+    construction_phase: ConstructionPhase,
 }
 
 impl GhostStructureBundle {
     /// Creates a new [`GhostStructureBundle`].
+    ///
+    /// Ghosts that start in the [`ConstructionPhase::Terraforming`] phase do not accept any construction materials until the terrain is ready.
     pub(crate) fn new(
         voxel_pos: VoxelPos,
         clipboard_data: ClipboardData,
         structure_manifest: &StructureManifest,
+        construction_phase: ConstructionPhase,
         picking_mesh: Handle<Mesh>,
         scene_handle: Handle<Scene>,
         inherited_material: InheritedMaterial,
@@ -157,7 +192,10 @@ impl GhostStructureBundle {
     ) -> Self {
         let structure_id = clipboard_data.structure_id;
         let construction_strategy = structure_manifest.construction_data(structure_id);
-        let construction_materials = construction_strategy.unwrap().materials.clone();
+        let construction_materials = match construction_phase {
+            ConstructionPhase::Terraforming => InputInventory::NULL,
+            ConstructionPhase::Building => construction_strategy.unwrap().materials.clone(),
+        };
 
         GhostStructureBundle {
             ghost_bundle: GhostBundle::new(
@@ -174,6 +212,7 @@ impl GhostStructureBundle {
             picking_mesh,
             workers_present: WorkersPresent::new(6),
             crafting_state: CraftingState::NeedsInput,
+            construction_phase,
         }
     }
 }
@@ -189,13 +228,20 @@ pub(crate) enum GhostKind {
     Preview,
     /// A preview that cannot be built in its current location
     ForbiddenPreview,
+    /// A preview that cannot be built because its tiles are too wet or too dry
+    WrongMoisturePreview,
+    /// A preview that will cancel and replace existing ghosts
+    ReplacePreview,
+    /// A preview that will demolish existing structures, then be built in their place
+    RebuildPreview,
 }
 
 impl GhostKind {
     /// The material associated with each variety of ghost.
     pub(crate) fn material(&self) -> StandardMaterial {
         use crate::graphics::palette::infovis::{
-            FORBIDDEN_PREVIEW_COLOR, GHOST_COLOR, PREVIEW_COLOR, SELECTED_GHOST_COLOR,
+            FORBIDDEN_PREVIEW_COLOR, GHOST_COLOR, PREVIEW_COLOR, REBUILD_PREVIEW_COLOR,
+            REPLACE_PREVIEW_COLOR, SELECTED_GHOST_COLOR, WRONG_MOISTURE_PREVIEW_COLOR,
         };
 
         let base_color = match self {
@@ -203,6 +249,9 @@ impl GhostKind {
             GhostKind::SelectedGhost => SELECTED_GHOST_COLOR,
             GhostKind::Preview => PREVIEW_COLOR,
             GhostKind::ForbiddenPreview => FORBIDDEN_PREVIEW_COLOR,
+            GhostKind::WrongMoisturePreview => WRONG_MOISTURE_PREVIEW_COLOR,
+            GhostKind::ReplacePreview => REPLACE_PREVIEW_COLOR,
+            GhostKind::RebuildPreview => REBUILD_PREVIEW_COLOR,
         };
 
         StandardMaterial {
@@ -268,46 +317,86 @@ impl StructurePreviewBundle {
 }
 
 /// Computes the correct signals for ghost structures to send throughout their lifecycle
-pub(super) fn ghost_structure_signals(
+pub(crate) fn ghost_structure_signals(
     mut ghost_query: Query<
         (
+            Entity,
             &Id<Structure>,
             &mut Emitter,
             Ref<CraftingState>,
-            &InputInventory,
+            Ref<ConstructionPhase>,
+            Ref<InputInventory>,
             &WorkersPresent,
+            Option<&HarvestsMaterials>,
         ),
         With<Ghost>,
     >,
+    priorities: Res<LogisticsPriorities>,
+    in_flight_items: Res<InFlightItems>,
 ) {
     /// Controls how strong the signals that are emitted by ghosts are
     const GHOST_SIGNAL_STRENGTH: f32 = 100.;
 
-    for (&structure_id, mut emitter, crafting_state, input_inventory, workers_present) in
-        ghost_query.iter_mut()
+    for (
+        ghost_entity,
+        &structure_id,
+        mut emitter,
+        crafting_state,
+        construction_phase,
+        input_inventory,
+        workers_present,
+        maybe_harvests_materials,
+    ) in ghost_query.iter_mut()
     {
-        if crafting_state.is_changed() {
+        if crafting_state.is_changed()
+            || construction_phase.is_changed()
+            || input_inventory.is_changed()
+            || priorities.is_changed()
+            || in_flight_items.claims_changed(ghost_entity)
+        {
             // Reset any signals.
             emitter.signals.clear();
 
+            // The terrain beneath the ghost sends its own signals while it is being terraformed
+            if *construction_phase == ConstructionPhase::Terraforming {
+                continue;
+            }
+
             match *crafting_state {
+                // These ghosts gather their own materials, so haulers aren't needed
+                CraftingState::NeedsInput if maybe_harvests_materials.is_some() => (),
                 CraftingState::NeedsInput => {
-                    match input_inventory {
+                    match &*input_inventory {
                         InputInventory::Exact { inventory } => {
                             // Emit signals to cause workers to bring the correct item to this ghost
                             for item_slot in inventory.iter() {
+                                // Items that are already being carried here shouldn't attract more haulers
+                                let claimed =
+                                    in_flight_items.claimed(ghost_entity, item_slot.item_id());
+                                if claimed >= item_slot.remaining_space() {
+                                    continue;
+                                }
+
                                 let signal_type =
                                     SignalType::Pull(ItemKind::Single(item_slot.item_id()));
-                                let signal_strength = SignalStrength::new(GHOST_SIGNAL_STRENGTH);
+                                let signal_strength = priorities.signal_strength(
+                                    LogisticsCategory::Construction,
+                                    GHOST_SIGNAL_STRENGTH,
+                                );
                                 emitter.signals.push((signal_type, signal_strength))
                             }
                         }
                         InputInventory::Tagged { tag, .. } => {
                             // Emit signals to cause workers to bring the correct item to this ghost
                             let signal_type = SignalType::Pull(ItemKind::Tag(*tag));
-                            let signal_strength = SignalStrength::new(GHOST_SIGNAL_STRENGTH);
+                            let signal_strength = priorities.signal_strength(
+                                LogisticsCategory::Construction,
+                                GHOST_SIGNAL_STRENGTH,
+                            );
                             emitter.signals.push((signal_type, signal_strength))
                         }
+                        // Construction materials are never filtered
+                        InputInventory::Filtered { .. } => {}
                     }
                 }
                 CraftingState::InProgress {
@@ -318,7 +407,10 @@ pub(super) fn ghost_structure_signals(
                         let workplace_id = WorkplaceId::structure(structure_id);
 
                         let signal_type = SignalType::Work(workplace_id);
-                        let signal_strength = SignalStrength::new(GHOST_SIGNAL_STRENGTH);
+                        let signal_strength = priorities.signal_strength(
+                            LogisticsCategory::Construction,
+                            GHOST_SIGNAL_STRENGTH,
+                        );
                         emitter.signals.push((signal_type, signal_strength))
                     }
                 }
@@ -393,6 +485,7 @@ pub(super) fn ghost_structure_lifecycle(
             &Facing,
             &ActiveRecipe,
             &WorkersPresent,
+            &ConstructionPhase,
         ),
         With<Ghost>,
     >,
@@ -408,8 +501,14 @@ pub(super) fn ghost_structure_lifecycle(
         &facing,
         active_recipe,
         workers_present,
+        construction_phase,
     ) in ghost_query.iter_mut()
     {
+        // Construction can't begin until the terrain is ready
+        if *construction_phase == ConstructionPhase::Terraforming {
+            continue;
+        }
+
         let construction_data = structure_manifest.construction_data(structure_id);
 
         match *crafting_state {
@@ -475,9 +574,22 @@ pub(super) fn ghost_structure_lifecycle(
 }
 
 /// Ensures that all ghosts can be built.
+///
+/// Ghosts in the [`ConstructionPhase::Terraforming`] phase follow the terrain beneath them as it is raised or lowered,
+/// and are only removed if they no longer fit.
 pub(super) fn validate_ghost_structures(
-    map_geometry: Res<MapGeometry>,
-    ghost_query: Query<(&VoxelPos, &Id<Structure>, &Facing), With<Ghost>>,
+    mut map_geometry: ResMut<MapGeometry>,
+    mut ghost_query: Query<
+        (
+            Entity,
+            &mut VoxelPos,
+            &mut Transform,
+            &Id<Structure>,
+            &Facing,
+            &ConstructionPhase,
+        ),
+        With<Ghost>,
+    >,
     structure_manifest: Res<StructureManifest>,
     mut commands: Commands,
 ) {
@@ -486,14 +598,118 @@ pub(super) fn validate_ghost_structures(
         return;
     }
 
-    for (&voxel_pos, &structure_id, &facing) in ghost_query.iter() {
-        let structure_details = structure_manifest.get(structure_id);
+    for (ghost_entity, mut voxel_pos, mut transform, &structure_id, &facing, construction_phase) in
+        ghost_query.iter_mut()
+    {
+        let footprint = structure_manifest.footprint(structure_id);
+
+        if *construction_phase == ConstructionPhase::Terraforming {
+            let resting_pos = map_geometry.on_top_of_terrain(voxel_pos.hex);
+            // Changes to the terrain may have overwritten some of the voxels claimed by this ghost
+            let in_place = *voxel_pos == resting_pos
+                && footprint
+                    .normalized(facing, resting_pos)
+                    .iter()
+                    .all(|&ghost_voxel| {
+                        map_geometry.get_ghost_structure(ghost_voxel) == Some(ghost_entity)
+                    });
+
+            if !in_place {
+                match map_geometry.move_ghost_structure(
+                    ghost_entity,
+                    *voxel_pos,
+                    resting_pos,
+                    footprint,
+                    facing,
+                ) {
+                    Ok(()) => {
+                        *voxel_pos = resting_pos;
+                        transform.translation = footprint
+                            .world_pos(facing, resting_pos, &map_geometry)
+                            .unwrap_or_default();
+                    }
+                    // No materials are delivered before terraforming is complete, so there is nothing to refund
+                    Err(_) => {
+                        map_geometry.release_ghost_voxels(
+                            ghost_entity,
+                            *voxel_pos,
+                            footprint,
+                            facing,
+                        );
+                        commands.entity(ghost_entity).despawn_recursive();
+                    }
+                }
+            }
 
-        if map_geometry
-            .is_space_available(voxel_pos, &structure_details.footprint, facing)
-            .is_ok()
-        {
-            commands.despawn_ghost_structure(voxel_pos);
+            continue;
+        }
+
+        let voxel_pos = *voxel_pos;
+
+        // Ghosts only ever overlap other ghosts: anything else means that this ghost has been blocked
+        let blocked = map_geometry
+            .is_space_available_for_ghost(voxel_pos, footprint, facing)
+            .is_err();
+        // If the terrain beneath the ghost has been raised or lowered, it is no longer resting on the ground
+        let displaced = voxel_pos != map_geometry.on_top_of_terrain(voxel_pos.hex);
+
+        if blocked || displaced {
+            commands.cancel_ghost_structure(voxel_pos);
+        }
+    }
+}
+
+/// Raises or lowers the terrain beneath ghosts in the [`ConstructionPhase::Terraforming`] phase.
+///
+/// Each tile of the wrong height is given a [`TerraformingAction`], which is then carried out by units like any other terraforming.
+/// Once every tile is at the required height, the ghost moves on to [`ConstructionPhase::Building`] and starts accepting construction materials.
+pub(super) fn ghost_terraforming(
+    mut ghost_query: Query<
+        (
+            &mut ConstructionPhase,
+            &mut InputInventory,
+            &VoxelPos,
+            &Id<Structure>,
+            &Facing,
+        ),
+        With<Ghost>,
+    >,
+    terraforming_query: Query<(), With<TerraformingAction>>,
+    structure_manifest: Res<StructureManifest>,
+    map_geometry: Res<MapGeometry>,
+    mut commands: Commands,
+) {
+    for (mut construction_phase, mut input_inventory, &center, &structure_id, &facing) in
+        ghost_query.iter_mut()
+    {
+        if *construction_phase != ConstructionPhase::Terraforming {
+            continue;
+        }
+
+        let Some(construction_data) = structure_manifest.construction_data(structure_id) else { continue };
+        let footprint = structure_manifest.footprint(structure_id);
+        let terraforming_needed =
+            construction_data.terraforming_needed(footprint, facing, center, &map_geometry);
+
+        if terraforming_needed.is_empty() {
+            *construction_phase = ConstructionPhase::Building;
+            *input_inventory = construction_data.materials.clone();
+            continue;
+        }
+
+        for (hex, terraforming_action) in terraforming_needed {
+            let Ok(terrain_entity) = map_geometry.get_terrain(hex) else { continue };
+
+            // Each step must be finished before the next one can be started
+            if terraforming_query.contains(terrain_entity) {
+                continue;
+            }
+
+            commands.entity(terrain_entity).insert((
+                terraforming_action,
+                terraforming_action.input_inventory(),
+                terraforming_action.output_inventory(),
+            ));
         }
     }
 }
@@ -0,0 +1,229 @@
+//! Structures built with [`ConstructionStrategy::Harvest`](super::ConstructionStrategy::Harvest) gather their own materials.
+//!
+//! Rather than waiting for haulers, their ghosts take the materials they need straight from the litter nearby.
+//! Materials are only taken once everything that is still missing can be found in range,
+//! so a ghost that can never be finished does not strip the surrounding tiles bare.
+
+use bevy::prelude::*;
+use hexx::shapes::hexagon;
+
+use crate::{
+    crafting::inventories::{CraftingState, InputInventory},
+    geometry::{MapGeometry, VoxelPos},
+    items::{item_manifest::ItemManifest, ItemCount},
+    litter::{gather_litter, Litter},
+};
+
+use super::ghosts::{ConstructionPhase, Ghost};
+
+/// A ghost that gathers its construction materials from the litter around it.
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct HarvestsMaterials {
+    /// Litter within this many tiles of the center of the ghost is used.
+    pub(crate) radius: u32,
+}
+
+/// Moves construction materials from the litter around harvesting ghosts into their input inventories.
+pub(super) fn harvest_construction_materials(
+    mut ghost_query: Query<
+        (
+            &VoxelPos,
+            &HarvestsMaterials,
+            &CraftingState,
+            &ConstructionPhase,
+            &mut InputInventory,
+        ),
+        With<Ghost>,
+    >,
+    mut litter_query: Query<&mut Litter>,
+    item_manifest: Res<ItemManifest>,
+    map_geometry: Res<MapGeometry>,
+) {
+    for (center, harvests_materials, crafting_state, construction_phase, mut input_inventory) in
+        ghost_query.iter_mut()
+    {
+        // Materials can't be gathered until the terrain is ready
+        if *construction_phase == ConstructionPhase::Terraforming
+            || *crafting_state != CraftingState::NeedsInput
+        {
+            continue;
+        }
+
+        let missing: Vec<ItemCount> = input_inventory
+            .iter()
+            .filter(|item_slot| !item_slot.is_full())
+            .map(|item_slot| ItemCount::new(item_slot.item_id(), item_slot.remaining_space()))
+            .collect();
+        if missing.is_empty() {
+            continue;
+        }
+
+        // Only mark the inventory as changed if something was gathered
+        let gathered = gather_litter(
+            &missing,
+            hexagon(center.hex, harvests_materials.radius),
+            &mut litter_query,
+            input_inventory.bypass_change_detection().inventory_mut(),
+            &map_geometry,
+            &item_manifest,
+        );
+        if gathered {
+            input_inventory.set_changed();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        asset_management::manifest::{Id, Manifest},
+        construction::{ghosts::ghost_structure_lifecycle, ConstructionData, ConstructionStrategy},
+        crafting::{inventories::StorageInventory, recipe::ActiveRecipe, workers::WorkersPresent},
+        geometry::Facing,
+        items::{
+            inventory::Inventory,
+            item_manifest::{Item, ItemData},
+        },
+        structures::structure_manifest::{Structure, StructureData, StructureManifest},
+    };
+    use bevy::utils::Duration;
+    use hexx::Hex;
+
+    fn stone() -> Id<Item> {
+        Id::from_name("stone".to_string())
+    }
+
+    fn dam() -> Id<Structure> {
+        Id::from_name("dam".to_string())
+    }
+
+    fn item_manifest() -> ItemManifest {
+        let mut manifest = Manifest::new();
+        manifest.insert("stone".to_string(), ItemData::basic(10));
+        manifest
+    }
+
+    /// Creates an app with a ghost of a dam at the origin of a radius 3 map, which needs 5 stones gathered from within 1 tile.
+    fn dam_app() -> (App, Entity) {
+        let mut app = App::new();
+        let map_geometry = MapGeometry::new(&mut app.world, 3);
+        for &hex in map_geometry.all_hexes() {
+            let terrain_entity = map_geometry.get_terrain(hex).unwrap();
+            app.world.entity_mut(terrain_entity).insert(Litter {
+                contents: StorageInventory::new(1, None),
+            });
+        }
+
+        let construction_data = ConstructionData {
+            work: None,
+            materials: InputInventory::Exact {
+                inventory: Inventory::empty_from_item(stone(), 5),
+            },
+            ..Default::default()
+        };
+        let mut structure_manifest = StructureManifest::new();
+        structure_manifest.insert(
+            "dam".to_string(),
+            StructureData {
+                construction_strategy: ConstructionStrategy::Harvest {
+                    radius: 1,
+                    required: construction_data.clone(),
+                },
+                ..StructureData::impassable()
+            },
+        );
+
+        let ghost_entity = app
+            .world
+            .spawn((
+                Ghost,
+                dam(),
+                VoxelPos::ZERO,
+                Facing::default(),
+                ActiveRecipe::Fixed(None),
+                WorkersPresent::new(1),
+                CraftingState::NeedsInput,
+                ConstructionPhase::Building,
+                construction_data.materials,
+                HarvestsMaterials { radius: 1 },
+            ))
+            .id();
+
+        app.insert_resource(map_geometry)
+            .insert_resource(item_manifest())
+            .insert_resource(structure_manifest)
+            .insert_resource(FixedTime::new(Duration::from_secs(1)))
+            .add_systems((harvest_construction_materials, ghost_structure_lifecycle).chain());
+
+        (app, ghost_entity)
+    }
+
+    fn add_stones(app: &mut App, hex: Hex, count: u32) {
+        let terrain_entity = app
+            .world
+            .resource::<MapGeometry>()
+            .get_terrain(hex)
+            .unwrap();
+        app.world
+            .get_mut::<Litter>(terrain_entity)
+            .unwrap()
+            .contents
+            .add_item_all_or_nothing(&ItemCount::new(stone(), count), &item_manifest())
+            .unwrap();
+    }
+
+    fn stones(app: &App, hex: Hex) -> u32 {
+        let terrain_entity = app
+            .world
+            .resource::<MapGeometry>()
+            .get_terrain(hex)
+            .unwrap();
+        app.world
+            .get::<Litter>(terrain_entity)
+            .unwrap()
+            .contents
+            .item_count(stone())
+    }
+
+    #[test]
+    fn harvest_built_structures_complete_from_nearby_litter() {
+        let (mut app, ghost_entity) = dam_app();
+        add_stones(&mut app, Hex::new(1, 0), 3);
+        add_stones(&mut app, Hex::new(0, 1), 4);
+
+        app.update();
+        app.update();
+
+        assert_eq!(
+            app.world.get::<CraftingState>(ghost_entity),
+            Some(&CraftingState::RecipeComplete)
+        );
+        assert_eq!(
+            stones(&app, Hex::new(1, 0)) + stones(&app, Hex::new(0, 1)),
+            2
+        );
+    }
+
+    #[test]
+    fn harvest_built_structures_wait_when_there_is_too_little_nearby() {
+        let (mut app, ghost_entity) = dam_app();
+        add_stones(&mut app, Hex::new(1, 0), 3);
+        // Out of range
+        add_stones(&mut app, Hex::new(3, 0), 10);
+
+        for _ in 0..5 {
+            app.update();
+        }
+
+        assert_eq!(
+            app.world.get::<CraftingState>(ghost_entity),
+            Some(&CraftingState::NeedsInput)
+        );
+        // Nothing is taken until the whole request can be met
+        assert_eq!(stones(&app, Hex::new(1, 0)), 3);
+        assert_eq!(stones(&app, Hex::new(3, 0)), 10);
+        let input_inventory = app.world.get::<InputInventory>(ghost_entity).unwrap();
+        assert_eq!(input_inventory.inventory().item_count(stone()), 0);
+    }
+}
@@ -1,22 +1,31 @@
 //! Tools and systems for constructing structures and terraforming the world.
 
+use std::cmp::Ordering;
+
 use bevy::utils::{Duration, HashMap};
 
 use bevy::prelude::*;
+use hexx::Hex;
 use serde::{Deserialize, Serialize};
 
 use crate::crafting::inventories::InputInventory;
+use crate::geometry::{DiscreteHeight, Facing, Height, MapGeometry, VoxelPos};
 use crate::items::slot::ItemSlot;
 use crate::simulation::SimulationSet;
+use crate::structures::Footprint;
 use crate::{asset_management::manifest::Id, structures::structure_manifest::Structure};
 
 use self::demolition::set_emitter_for_structures_to_be_demolished;
-use self::terraform::{terraforming_lifecycle, terraforming_signals};
+use self::ghosts::{ghost_structure_lifecycle, ghost_terraforming};
+use self::harvest::harvest_construction_materials;
+use self::terraform::{terraforming_lifecycle, terraforming_signals, TerraformingAction};
 
 pub(crate) mod demolition;
 pub(crate) mod ghosts;
+pub(crate) mod harvest;
 pub(crate) mod terraform;
 pub(crate) mod zoning;
+pub mod zoning_snapshot;
 
 /// Systems and resources for constructing structures and terraforming the world.
 pub(crate) struct ConstructionPlugin;
@@ -36,6 +45,13 @@ impl Plugin for ConstructionPlugin {
                 (terraforming_lifecycle, terraforming_signals)
                     .in_set(SimulationSet)
                     .in_schedule(CoreSchedule::FixedUpdate),
+            )
+            .add_system(
+                harvest_construction_materials
+                    .after(ghost_terraforming)
+                    .before(ghost_structure_lifecycle)
+                    .in_set(SimulationSet)
+                    .in_schedule(CoreSchedule::FixedUpdate),
             );
     }
 }
@@ -49,11 +65,18 @@ pub enum ConstructionStrategy {
     Seedling(Id<Structure>),
     /// This structure can be built directly.
     Direct(ConstructionData),
+    /// This structure is built from materials gathered from the litter around it, rather than delivered by haulers.
+    Harvest {
+        /// Litter within this many tiles of the center of the structure is used.
+        radius: u32,
+        /// The materials and work required to build this structure.
+        required: ConstructionData,
+    },
     /// A landmark, which cannot be built.
     Landmark,
 }
 
-/// The data contained in a [`ConstructionStrategy::Direct`] variant.
+/// The data contained in a [`ConstructionStrategy::Direct`] or [`ConstructionStrategy::Harvest`] variant.
 #[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
 pub struct ConstructionData {
     /// The amount of work by units required to complete the construction of this building.
@@ -62,6 +85,51 @@ pub struct ConstructionData {
     pub work: Option<Duration>,
     /// The set of items needed to create a new copy of this structure
     pub materials: InputInventory,
+    /// The height that the terrain beneath this structure must be at before it can be built.
+    ///
+    /// If this is [`None`], the structure can be built at any height.
+    pub required_terrain_height: Option<Height>,
+    /// Can this structure be zoned on terrain of the wrong height?
+    ///
+    /// If so, the terrain beneath its ghost will be terraformed to the [`required_terrain_height`](Self::required_terrain_height)
+    /// before any construction materials are delivered.
+    pub allow_terraform: bool,
+}
+
+impl ConstructionData {
+    /// Returns the terraforming that is still needed beneath a structure with the provided `footprint` centered at `center`,
+    /// before construction can begin.
+    ///
+    /// Each tile that is not at the [`required_terrain_height`](Self::required_terrain_height) is paired with the next step needed to fix it.
+    pub(crate) fn terraforming_needed(
+        &self,
+        footprint: &Footprint,
+        facing: Facing,
+        center: VoxelPos,
+        map_geometry: &MapGeometry,
+    ) -> Vec<(Hex, TerraformingAction)> {
+        let Some(required_terrain_height) = self.required_terrain_height else {
+            return Vec::new();
+        };
+        let required_terrain_height = DiscreteHeight::from(required_terrain_height);
+
+        let mut terraforming_needed = Vec::new();
+        for voxel_pos in footprint.normalized(facing, center) {
+            let Ok(terrain_height) = map_geometry.get_height(voxel_pos.hex) else { continue };
+
+            match terrain_height.cmp(&required_terrain_height) {
+                Ordering::Less => {
+                    terraforming_needed.push((voxel_pos.hex, TerraformingAction::Raise))
+                }
+                Ordering::Equal => (),
+                Ordering::Greater => {
+                    terraforming_needed.push((voxel_pos.hex, TerraformingAction::Lower))
+                }
+            }
+        }
+
+        terraforming_needed
+    }
 }
 
 /// The unprocessed equivalent of [`ConstructionStrategy`].
@@ -77,29 +145,88 @@ pub enum RawConstructionStrategy {
         work: Option<f32>,
         /// The set of items needed to create a new copy of this structure
         materials: HashMap<String, u32>,
+        /// The height that the terrain beneath this structure must be at before it can be built.
+        #[serde(default)]
+        required_terrain_height: Option<Height>,
+        /// Can this structure be zoned on terrain of the wrong height, terraforming it first?
+        #[serde(default)]
+        allow_terraform: bool,
+    },
+    /// This structure is built from materials gathered from the litter around it.
+    Harvest {
+        /// Litter within this many tiles of the center of the structure is used.
+        radius: u32,
+        /// The amount of work (in seconds) by units required to complete the construction of this building.
+        ///
+        /// If this is [`None`], no work will be needed at all.
+        work: Option<f32>,
+        /// The set of items gathered to create a new copy of this structure
+        materials: HashMap<String, u32>,
+        /// The height that the terrain beneath this structure must be at before it can be built.
+        #[serde(default)]
+        required_terrain_height: Option<Height>,
+        /// Can this structure be zoned on terrain of the wrong height, terraforming it first?
+        #[serde(default)]
+        allow_terraform: bool,
     },
     /// A landmark, which cannot be built.
     Landmark,
 }
 
+impl ConstructionData {
+    /// Processes the fields shared by [`RawConstructionStrategy::Direct`] and [`RawConstructionStrategy::Harvest`].
+    fn from_raw(
+        work: Option<f32>,
+        materials: HashMap<String, u32>,
+        required_terrain_height: Option<Height>,
+        allow_terraform: bool,
+    ) -> Self {
+        let inventory = materials
+            .into_iter()
+            .map(|(item_name, count)| ItemSlot::empty(Id::from_name(item_name), count))
+            .collect();
+
+        ConstructionData {
+            work: work.map(Duration::from_secs_f32),
+            materials: InputInventory::Exact { inventory },
+            required_terrain_height,
+            allow_terraform,
+        }
+    }
+}
+
 impl From<RawConstructionStrategy> for ConstructionStrategy {
     fn from(raw: RawConstructionStrategy) -> Self {
         match raw {
             RawConstructionStrategy::Seedling(seedling_name) => {
                 ConstructionStrategy::Seedling(Id::from_name(seedling_name))
             }
-            RawConstructionStrategy::Direct { work, materials } => {
-                let inventory = materials
-                    .into_iter()
-                    .map(|(item_name, count)| ItemSlot::empty(Id::from_name(item_name), count))
-                    .collect();
-
-                let materials = InputInventory::Exact { inventory };
-                ConstructionStrategy::Direct(ConstructionData {
-                    work: work.map(Duration::from_secs_f32),
+            RawConstructionStrategy::Direct {
+                work,
+                materials,
+                required_terrain_height,
+                allow_terraform,
+            } => ConstructionStrategy::Direct(ConstructionData::from_raw(
+                work,
+                materials,
+                required_terrain_height,
+                allow_terraform,
+            )),
+            RawConstructionStrategy::Harvest {
+                radius,
+                work,
+                materials,
+                required_terrain_height,
+                allow_terraform,
+            } => ConstructionStrategy::Harvest {
+                radius,
+                required: ConstructionData::from_raw(
+                    work,
                     materials,
-                })
-            }
+                    required_terrain_height,
+                    allow_terraform,
+                ),
+            },
             RawConstructionStrategy::Landmark => ConstructionStrategy::Landmark,
         }
     }
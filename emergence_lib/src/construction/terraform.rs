@@ -10,7 +10,7 @@ use crate::{
     },
     geometry::VoxelPos,
     items::{inventory::Inventory, item_manifest::Item},
-    signals::{Emitter, SignalStrength, SignalType},
+    signals::{Emitter, LogisticsCategory, LogisticsPriorities, SignalType},
     terrain::{
         commands::TerrainCommandsExt,
         terrain_manifest::{Terrain, TerrainManifest},
@@ -137,6 +137,7 @@ pub(super) fn terraforming_lifecycle(
 /// Computes the correct signals for terraformed terrain to send throughout their lifecycle
 pub(super) fn terraforming_signals(
     mut query: Query<(&InputInventory, &OutputInventory, &mut Emitter), With<TerraformingAction>>,
+    priorities: Res<LogisticsPriorities>,
 ) {
     /// The signal strength for terraforming signals
     const TERRAFORMING_SIGNAL_STRENGTH: f32 = 20.;
@@ -151,22 +152,33 @@ pub(super) fn terraforming_signals(
                 // Emit signals to cause workers to bring the correct item to this ghost
                 for item_slot in inventory.iter() {
                     let signal_type = SignalType::Pull(ItemKind::Single(item_slot.item_id()));
-                    let signal_strength = SignalStrength::new(TERRAFORMING_SIGNAL_STRENGTH);
+                    let signal_strength = priorities.signal_strength(
+                        LogisticsCategory::Construction,
+                        TERRAFORMING_SIGNAL_STRENGTH,
+                    );
                     emitter.signals.push((signal_type, signal_strength))
                 }
             }
             InputInventory::Tagged { tag, .. } => {
                 // Emit signals to cause workers to bring the correct item to this ghost
                 let signal_type = SignalType::Pull(ItemKind::Tag(*tag));
-                let signal_strength = SignalStrength::new(TERRAFORMING_SIGNAL_STRENGTH);
+                let signal_strength = priorities.signal_strength(
+                    LogisticsCategory::Construction,
+                    TERRAFORMING_SIGNAL_STRENGTH,
+                );
                 emitter.signals.push((signal_type, signal_strength))
             }
+            // Terraforming materials are never filtered
+            InputInventory::Filtered { .. } => {}
         }
 
         // If the output inventory is not empty, emit a push signal for the item
         for item_slot in output_inventory.iter() {
             let signal_type = SignalType::Push(ItemKind::Single(item_slot.item_id()));
-            let signal_strength = SignalStrength::new(TERRAFORMING_SIGNAL_STRENGTH);
+            let signal_strength = priorities.signal_strength(
+                LogisticsCategory::Construction,
+                TERRAFORMING_SIGNAL_STRENGTH,
+            );
             emitter.signals.push((signal_type, signal_strength))
         }
     }
@@ -1,48 +1,84 @@
 //! Zoning is used to indicate that a tile should contain the specified structure.
 
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
 use bevy::prelude::*;
 use leafwing_input_manager::prelude::ActionState;
+use serde::{Deserialize, Serialize};
 
 use crate::{
-    asset_management::AssetState,
-    construction::{demolition::MarkedForDemolition, ghosts::Preview},
-    geometry::{MapGeometry, VoxelPos},
+    asset_management::{manifest::Id, AssetState},
+    construction::{
+        demolition::{MarkedForDemolition, Salvage},
+        ghosts::Preview,
+    },
+    crafting::recipe::RecipeManifest,
+    geometry::{Facing, MapGeometry, VoxelPos},
+    items::item_manifest::ItemManifest,
     player_interaction::{
         clipboard::{ClipboardData, Tool},
+        keybindings::config_dir,
         picking::CursorPos,
         selection::CurrentSelection,
         InteractionSystem, PlayerAction, PlayerModifiesWorld,
     },
-    structures::{commands::StructureCommandsExt, structure_manifest::StructureManifest, Landmark},
+    signals::Signals,
+    simulation::milestones::Milestones,
+    structures::{
+        commands::StructureCommandsExt,
+        logistic_buildings::face_strongest_pull,
+        placement_rules::PlacementRules,
+        structure_manifest::{Structure, StructureKind, StructureManifest},
+        Footprint, Landmark,
+    },
     terrain::terrain_manifest::TerrainManifest,
+    water::WaterDepth,
 };
 
-use super::terraform::TerraformingAction;
+use super::{terraform::TerraformingAction, zoning_snapshot::ZoningSnapshotPlugin};
 
 /// Code and data for setting zoning of areas for construction.
 pub(super) struct ZoningPlugin;
 
 impl Plugin for ZoningPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(
-            (mark_for_demolition, set_zoning)
-                .in_set(InteractionSystem::ApplyZoning)
-                .in_set(PlayerModifiesWorld)
-                .after(InteractionSystem::SelectTiles)
-                .after(InteractionSystem::SetClipboard),
-        )
-        .add_system(cleanup_previews.after(set_zoning))
-        .add_system(
-            mark_based_on_zoning
-                .in_set(InteractionSystem::ManagePreviews)
-                .run_if(in_state(AssetState::FullyLoaded))
-                .after(InteractionSystem::ApplyZoning),
-        );
+        app.add_plugin(ZoningSnapshotPlugin)
+            .add_systems(
+                (
+                    mark_for_demolition,
+                    set_zoning,
+                    deselect_after_zoning.after(set_zoning),
+                )
+                    .in_set(InteractionSystem::ApplyZoning)
+                    .in_set(PlayerModifiesWorld)
+                    .after(InteractionSystem::SelectTiles)
+                    .after(InteractionSystem::SetClipboard),
+            )
+            .add_system(cleanup_previews.after(set_zoning))
+            .add_systems(
+                (resume_rebuild_orders, mark_based_on_zoning)
+                    .chain()
+                    .in_set(InteractionSystem::ManagePreviews)
+                    .distributive_run_if(in_state(AssetState::FullyLoaded))
+                    .after(InteractionSystem::ApplyZoning),
+            )
+            .init_resource::<ZoningSettings>()
+            .init_resource::<ZoningSettingsConfig>()
+            .add_startup_system(load_saved_zoning_settings)
+            .add_system(
+                save_zoning_settings_on_change.run_if(
+                    resource_changed::<ZoningSettings>()
+                        .and_then(not(resource_added::<ZoningSettings>())),
+                ),
+            );
     }
 }
 
 /// The zoning of a given tile, which specifies which structure *should* be built there.
-#[derive(Component, PartialEq, Eq, Clone, Debug)]
+#[derive(Component, PartialEq, Clone, Debug)]
 pub(crate) enum Zoning {
     /// The provided structure should be built on this tile.
     Structure(ClipboardData),
@@ -69,6 +105,202 @@ impl Zoning {
     }
 }
 
+/// Tracks a tile zoned for a structure that can only be built once the existing structures in its way are demolished.
+///
+/// Once all of them are gone, the zoning is applied again, placing a ghost as usual.
+#[derive(Component, Debug, Clone, PartialEq, Eq)]
+pub(crate) struct RebuildOrder {
+    /// The structures that have been marked for demolition to make room.
+    pub(crate) blocking_structures: Vec<Entity>,
+}
+
+/// What is in the way of a structure that is being placed, and what will happen to it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum Obstruction {
+    /// Nothing is in the way.
+    Clear,
+    /// Existing ghosts are in the way, and will be cancelled and replaced.
+    Ghosts,
+    /// Completed structures are in the way, and will be demolished before the new structure is built.
+    Structures(Vec<Entity>),
+    /// Something is in the way that cannot be removed.
+    Blocked,
+}
+
+impl Obstruction {
+    /// Determines what is in the way of placing `clipboard_data` at `center`.
+    ///
+    /// `can_demolish` is called for each completed structure in the way.
+    /// Ghosts only count as the obstruction if replacing them would leave space for the new ghost:
+    /// anything else that stops the structure from being placed here blocks it.
+    pub(crate) fn new(
+        center: VoxelPos,
+        footprint: &Footprint,
+        facing: Facing,
+        map_geometry: &MapGeometry,
+        can_demolish: impl Fn(Entity) -> bool,
+    ) -> Self {
+        if map_geometry
+            .is_space_available(center, footprint, facing)
+            .is_ok()
+        {
+            return Obstruction::Clear;
+        }
+
+        match map_geometry.structures_in_the_way(center, footprint, facing) {
+            Some(structures) if structures.is_empty() => {
+                let ghost_in_the_way = footprint
+                    .normalized(facing, center)
+                    .into_iter()
+                    .any(|voxel_pos| map_geometry.get_ghost_structure(voxel_pos).is_some());
+                let ghost_fits = map_geometry
+                    .is_space_available_for_ghost(center, footprint, facing)
+                    .is_ok();
+
+                match ghost_in_the_way && ghost_fits {
+                    true => Obstruction::Ghosts,
+                    false => Obstruction::Blocked,
+                }
+            }
+            Some(structures) if structures.iter().all(|&entity| can_demolish(entity)) => {
+                Obstruction::Structures(structures)
+            }
+            _ => Obstruction::Blocked,
+        }
+    }
+
+    /// Can a structure of type `structure_id` at `center` be demolished to make room for `clipboard_data`, zoned at `new_center`?
+    ///
+    /// Landmarks can't be demolished, unless there's something to salvage,
+    /// and structures are never rebuilt as exactly what they already are.
+    pub(crate) fn can_demolish(
+        structure_id: Id<Structure>,
+        center: VoxelPos,
+        facing: Facing,
+        is_protected_landmark: bool,
+        clipboard_data: &ClipboardData,
+        new_center: VoxelPos,
+    ) -> bool {
+        let identical = structure_id == clipboard_data.structure_id
+            && center == new_center
+            && facing == clipboard_data.facing;
+
+        !is_protected_landmark && !identical
+    }
+}
+
+/// Player preferences that control how zoning behaves.
+#[derive(Resource, Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub(crate) struct ZoningSettings {
+    /// Should [`PlayerAction::ZoneAndClear`] also clear the structure selected for zoning?
+    ///
+    /// Other tools, such as terraforming, are always kept.
+    #[serde(default)]
+    pub(crate) clear_selected_structure: bool,
+    /// Should structures zoned onto a tile where they can't be built move to the nearest tile where they can?
+    ///
+    /// Only tiles within [`ZoningSettings::SNAP_RADIUS`] are considered.
+    /// If none of them are suitable, the structure is not placed at all.
+    #[serde(default)]
+    pub(crate) snap_to_nearest_valid: bool,
+    /// Should newly zoned releasers be turned to face whatever is pulling hardest for the items they release?
+    ///
+    /// The facing chosen by the player is kept if nothing nearby wants the items.
+    #[serde(default)]
+    pub(crate) auto_face_logistics: bool,
+}
+
+impl ZoningSettings {
+    /// The furthest distance, in tiles, that structures can be moved when [`ZoningSettings::snap_to_nearest_valid`] is set.
+    pub(crate) const SNAP_RADIUS: u32 = 2;
+}
+
+/// Controls where the player's [`ZoningSettings`] are persisted.
+///
+/// Insert this before the [`ZoningPlugin`] is added to change where the settings are kept.
+#[derive(Resource, Debug, Clone, PartialEq, Eq)]
+pub(crate) struct ZoningSettingsConfig {
+    /// The path of the config file that the [`ZoningSettings`] are loaded from and saved to.
+    ///
+    /// If this is [`None`], the default settings are used and changes are not saved.
+    pub(crate) path: Option<PathBuf>,
+}
+
+impl ZoningSettingsConfig {
+    /// The default settings are used each time the app is run, and changes are kept in memory only.
+    pub(crate) fn disabled() -> Self {
+        ZoningSettingsConfig { path: None }
+    }
+}
+
+impl Default for ZoningSettingsConfig {
+    fn default() -> Self {
+        ZoningSettingsConfig {
+            path: Some(config_dir().join("zoning_settings.json")),
+        }
+    }
+}
+
+/// Loads the zoning settings stored at `path`.
+///
+/// Falls back to the defaults if the file does not exist or cannot be parsed.
+pub(crate) fn load_zoning_settings(path: &Path) -> ZoningSettings {
+    let bytes = match fs::read(path) {
+        Ok(bytes) => bytes,
+        Err(_) => return ZoningSettings::default(),
+    };
+
+    match serde_json::from_slice(&bytes) {
+        Ok(zoning_settings) => zoning_settings,
+        Err(json_error) => {
+            warn!(
+                "Could not parse zoning settings at {}: {json_error}. Falling back to the defaults.",
+                path.display()
+            );
+            ZoningSettings::default()
+        }
+    }
+}
+
+/// Saves the zoning settings to `path`, so they can be restored with [`load_zoning_settings`].
+pub(crate) fn save_zoning_settings(
+    zoning_settings: &ZoningSettings,
+    path: &Path,
+) -> io::Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    let bytes = serde_json::to_vec_pretty(zoning_settings)?;
+    fs::write(path, bytes)
+}
+
+/// Replaces the default zoning settings with those saved at [`ZoningSettingsConfig::path`].
+fn load_saved_zoning_settings(
+    config: Res<ZoningSettingsConfig>,
+    mut zoning_settings: ResMut<ZoningSettings>,
+) {
+    if let Some(path) = &config.path {
+        // Loading is not a change made by the player, so there is nothing new to save
+        *zoning_settings.bypass_change_detection() = load_zoning_settings(path);
+    }
+}
+
+/// Persists the zoning settings whenever the player changes them.
+fn save_zoning_settings_on_change(
+    zoning_settings: Res<ZoningSettings>,
+    config: Res<ZoningSettingsConfig>,
+) {
+    let Some(path) = &config.path else { return };
+
+    if let Err(io_error) = save_zoning_settings(&zoning_settings, path) {
+        warn!(
+            "Could not save zoning settings to {}: {io_error}",
+            path.display()
+        );
+    }
+}
+
 /// Cleans up all old previews.
 ///
 /// We're just using an immediate mode system for this, since it's much easier to ensure correctness.
@@ -118,6 +350,7 @@ fn set_zoning(
 
     // Apply zoning
     let apply_zoning = actions.pressed(PlayerAction::Paste)
+        || actions.pressed(PlayerAction::ZoneAndClear)
         || actions.pressed(PlayerAction::UseTool) && !tool.is_empty();
 
     match &*tool {
@@ -181,18 +414,41 @@ fn set_zoning(
     }
 }
 
-/// Mark the selected structure for deletion.
+/// Clears the tile selection once zoning has been applied by [`PlayerAction::ZoneAndClear`].
+///
+/// Plain [`PlayerAction::Paste`] keeps the selection, so the same tiles can be zoned repeatedly.
+/// The structure selected for zoning is cleared too if [`ZoningSettings::clear_selected_structure`] is set.
+fn deselect_after_zoning(
+    actions: Res<ActionState<PlayerAction>>,
+    zoning_settings: Res<ZoningSettings>,
+    mut current_selection: ResMut<CurrentSelection>,
+    mut tool: ResMut<Tool>,
+) {
+    if !actions.pressed(PlayerAction::ZoneAndClear) {
+        return;
+    }
+
+    if matches!(*current_selection, CurrentSelection::Terrain(_)) {
+        *current_selection = CurrentSelection::None;
+    }
+
+    if zoning_settings.clear_selected_structure && matches!(*tool, Tool::Structures(_)) {
+        *tool = Tool::None;
+    }
+}
+
+/// Mark the selected structure(s) for deletion.
 fn mark_for_demolition(
     player_actions: Res<ActionState<PlayerAction>>,
     current_selection: Res<CurrentSelection>,
-    landmark_query: Query<&Landmark>,
+    landmark_query: Query<&Landmark, Without<Salvage>>,
     mut commands: Commands,
 ) {
     if player_actions.just_pressed(PlayerAction::ClearZoning) {
-        if let CurrentSelection::Structure(structure_entity) = *current_selection {
-            // Landmarks can't be demolished
+        for structure_entity in current_selection.selected_structures() {
+            // Landmarks can't be demolished, unless there's something to salvage
             if landmark_query.contains(structure_entity) {
-                return;
+                continue;
             }
 
             commands
@@ -202,28 +458,223 @@ fn mark_for_demolition(
     }
 }
 
+/// Applies zoning again once every structure in the way of a [`RebuildOrder`] has been demolished.
+///
+/// If the demolition of any of those structures is cancelled, the zoning is dropped instead,
+/// which also removes the [`RebuildOrder`] and spares the other structures.
+fn resume_rebuild_orders(
+    mut terrain_query: Query<(&RebuildOrder, &mut Zoning)>,
+    structure_query: Query<Option<&MarkedForDemolition>, With<Id<Structure>>>,
+) {
+    for (rebuild_order, mut zoning) in terrain_query.iter_mut() {
+        let demolition_cancelled = rebuild_order
+            .blocking_structures
+            .iter()
+            .any(|&structure_entity| matches!(structure_query.get(structure_entity), Ok(None)));
+
+        if demolition_cancelled {
+            *zoning = Zoning::None;
+        } else if rebuild_order
+            .blocking_structures
+            .iter()
+            .all(|&structure_entity| !structure_query.contains(structure_entity))
+        {
+            zoning.set_changed();
+        }
+    }
+}
+
 /// Spawn and despawn ghosts and apply other markings based on zoning.
+///
+/// Zoning over existing ghosts cancels and replaces them,
+/// while zoning over completed structures marks them for demolition and records a [`RebuildOrder`].
+/// Structures that can't be placed at all are dropped, or moved to a nearby tile if [`ZoningSettings::snap_to_nearest_valid`] is set.
+/// Structures and recipes that are still locked behind a milestone are always dropped, even when pasted.
+/// Releasers are turned toward the strongest pull for their items first, if [`ZoningSettings::auto_face_logistics`] is set.
 fn mark_based_on_zoning(
-    mut terrain_query: Query<(Entity, &mut Zoning, &VoxelPos), Changed<Zoning>>,
+    mut terrain_query: Query<
+        (Entity, &mut Zoning, &VoxelPos, Option<&RebuildOrder>),
+        Changed<Zoning>,
+    >,
+    water_depth_query: Query<&WaterDepth>,
+    structure_query: Query<(&Id<Structure>, &VoxelPos, &Facing)>,
+    protected_landmark_query: Query<(), (With<Landmark>, Without<Salvage>)>,
     structure_manifest: Res<StructureManifest>,
+    recipe_manifest: Res<RecipeManifest>,
+    milestones: Res<Milestones>,
+    placement_rules: Res<PlacementRules>,
+    zoning_settings: Res<ZoningSettings>,
+    item_manifest: Res<ItemManifest>,
+    signals: Res<Signals>,
     mut commands: Commands,
     map_geometry: Res<MapGeometry>,
 ) {
-    for (terrain_entity, mut zoning, &voxel_pos) in terrain_query.iter_mut() {
+    for (terrain_entity, mut zoning, &voxel_pos, maybe_rebuild_order) in terrain_query.iter_mut() {
+        // Structures (and their ghosts) are placed on top of the zoned terrain
+        let structure_pos = voxel_pos.above();
+
+        // Any rebuild order is superseded by the new zoning, and will be recreated if it is still needed
+        if maybe_rebuild_order.is_some() {
+            commands.entity(terrain_entity).remove::<RebuildOrder>();
+        }
+
         // Reborrowing here would trigger change detection, causing this system to constantly check
         match zoning.bypass_change_detection() {
             Zoning::Structure(clipboard_data) => {
-                let footprint = structure_manifest.footprint(clipboard_data.structure_id);
-
-                if map_geometry
-                    .is_space_available(voxel_pos, footprint, clipboard_data.facing)
-                    .is_ok()
+                let structure_id = clipboard_data.structure_id;
+                if !milestones.structure_unlocked(structure_id, &structure_manifest)
+                    || !milestones.recipe_unlocked(&clipboard_data.active_recipe, &recipe_manifest)
                 {
-                    commands.spawn_ghost_structure(voxel_pos, clipboard_data.clone())
-                } else {
                     *zoning = Zoning::None;
                     // We bypassed change detection above, so need to manually trigger it here.
                     zoning.set_changed();
+                    continue;
+                }
+
+                let footprint = structure_manifest.footprint(structure_id);
+
+                if zoning_settings.auto_face_logistics {
+                    if let StructureKind::Releaser { released_item, .. } =
+                        structure_manifest.get(structure_id).kind
+                    {
+                        clipboard_data.facing = face_strongest_pull(
+                            released_item,
+                            structure_pos,
+                            footprint,
+                            clipboard_data.facing,
+                            &signals,
+                            &item_manifest,
+                        );
+                    }
+                }
+
+                // Existing structures are never demolished to make way for copies of themselves, or if they are protected landmarks
+                let can_demolish = |structure_entity: Entity, structure_pos: VoxelPos| {
+                    structure_query.get(structure_entity).map_or(
+                        false,
+                        |(&existing_id, &center, &facing)| {
+                            Obstruction::can_demolish(
+                                existing_id,
+                                center,
+                                facing,
+                                protected_landmark_query.contains(structure_entity),
+                                clipboard_data,
+                                structure_pos,
+                            )
+                        },
+                    )
+                };
+
+                // Works out what would be in the way of placing the structure at `structure_pos`,
+                // treating any other reason that it can't be built there as an impassable obstruction.
+                let obstruction_at = |structure_pos: VoxelPos| -> Obstruction {
+                    // Terrain of the wrong height can only be used if it can be terraformed first
+                    let can_be_constructed =
+                        match structure_manifest.construction_data(structure_id) {
+                            Some(construction_data) => {
+                                construction_data.allow_terraform
+                                    || construction_data
+                                        .terraforming_needed(
+                                            footprint,
+                                            clipboard_data.facing,
+                                            structure_pos,
+                                            &map_geometry,
+                                        )
+                                        .is_empty()
+                            }
+                            None => false,
+                        };
+                    let moisture_permits = structure_manifest.moisture_permits(
+                        structure_id,
+                        structure_pos,
+                        clipboard_data.facing,
+                        &map_geometry,
+                        |terrain_entity| water_depth_query.get(terrain_entity).ok().copied(),
+                    );
+                    let rules_permit = placement_rules
+                        .check_structure(
+                            &structure_manifest,
+                            structure_id,
+                            structure_pos,
+                            clipboard_data.facing,
+                            &map_geometry,
+                            |terrain_entity| water_depth_query.get(terrain_entity).ok().copied(),
+                        )
+                        .is_ok();
+
+                    if !(can_be_constructed && moisture_permits && rules_permit) {
+                        return Obstruction::Blocked;
+                    }
+
+                    Obstruction::new(
+                        structure_pos,
+                        footprint,
+                        clipboard_data.facing,
+                        &map_geometry,
+                        |structure_entity| can_demolish(structure_entity, structure_pos),
+                    )
+                };
+
+                match obstruction_at(structure_pos) {
+                    Obstruction::Clear | Obstruction::Ghosts => {
+                        commands.spawn_ghost_structure(structure_pos, clipboard_data.clone())
+                    }
+                    Obstruction::Structures(blocking_structures) => {
+                        for &structure_entity in &blocking_structures {
+                            commands
+                                .entity(structure_entity)
+                                .insert(MarkedForDemolition);
+                        }
+
+                        commands.entity(terrain_entity).insert(RebuildOrder {
+                            blocking_structures,
+                        });
+                    }
+                    Obstruction::Blocked => {
+                        // Tiles that already hold this structure, or a landmark, are left alone rather than snapped away from,
+                        // so that dragging over existing structures doesn't scatter copies of them
+                        let kept_in_place = map_geometry
+                            .structures_in_the_way(structure_pos, footprint, clipboard_data.facing)
+                            .map_or(false, |structures| {
+                                structures.iter().any(|&structure_entity| {
+                                    !can_demolish(structure_entity, structure_pos)
+                                })
+                            });
+
+                        // Move the zoning to the closest tile where the structure fits, if there is one
+                        if zoning_settings.snap_to_nearest_valid && !kept_in_place {
+                            let already_placed = |candidate_pos: VoxelPos| {
+                                map_geometry
+                                    .get_ghost_structure(candidate_pos)
+                                    .and_then(|ghost_entity| structure_query.get(ghost_entity).ok())
+                                    == Some((&structure_id, &candidate_pos, &clipboard_data.facing))
+                            };
+
+                            let maybe_snapped_pos = (1..=ZoningSettings::SNAP_RADIUS)
+                                .flat_map(|radius| structure_pos.hex.ring(radius))
+                                .filter(|&hex| map_geometry.is_valid(hex))
+                                .map(|hex| map_geometry.on_top_of_terrain(hex))
+                                .find(|&candidate_pos| match obstruction_at(candidate_pos) {
+                                    Obstruction::Clear => true,
+                                    Obstruction::Ghosts => already_placed(candidate_pos),
+                                    _ => false,
+                                });
+
+                            if let Some(snapped_pos) = maybe_snapped_pos {
+                                if let Ok(snapped_terrain_entity) =
+                                    map_geometry.get_terrain(snapped_pos.hex)
+                                {
+                                    commands
+                                        .entity(snapped_terrain_entity)
+                                        .insert(Zoning::Structure(clipboard_data.clone()));
+                                }
+                            }
+                        }
+
+                        *zoning = Zoning::None;
+                        // We bypassed change detection above, so need to manually trigger it here.
+                        zoning.set_changed();
+                    }
                 }
             }
             Zoning::Terraform(terraforming_action) => {
@@ -237,7 +688,7 @@ fn mark_based_on_zoning(
 
                 // Mark any structures that are here as needing to be demolished
                 // Terraforming can't be done with roots growing into stuff!
-                if let Some(structure_entity) = map_geometry.get_structure(voxel_pos) {
+                if let Some(structure_entity) = map_geometry.get_structure(structure_pos) {
                     commands
                         .entity(structure_entity)
                         .insert(MarkedForDemolition);
@@ -245,8 +696,936 @@ fn mark_based_on_zoning(
             }
             Zoning::None => {
                 // TODO: make sure to remove any terraforming previews
-                commands.despawn_ghost_structure(voxel_pos);
+                commands.cancel_ghost_structure(structure_pos);
+
+                // Structures that were only being demolished to make way for a rebuild are spared
+                if let Some(rebuild_order) = maybe_rebuild_order {
+                    for &structure_entity in &rebuild_order.blocking_structures {
+                        if structure_query.contains(structure_entity) {
+                            commands
+                                .entity(structure_entity)
+                                .remove::<MarkedForDemolition>();
+                        }
+                    }
+                }
+            }
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::utils::HashSet;
+
+    use super::*;
+    use crate::{
+        asset_management::manifest::{Id, Manifest},
+        construction::{
+            ghosts::{
+                ghost_structure_lifecycle, ghost_terraforming, validate_ghost_structures,
+                ConstructionPhase, Ghost,
+            },
+            terraform::TerraformingTool,
+            ConstructionData, ConstructionStrategy,
+        },
+        crafting::{
+            inventories::{InputInventory, OutputInventory},
+            item_tags::ItemKind,
+            recipe::ActiveRecipe,
+        },
+        geometry::{DiscreteHeight, Facing, Height},
+        items::{
+            inventory::Inventory,
+            item_manifest::{Item, ItemData, ItemManifest},
+            ItemCount,
+        },
+        litter::Litter,
+        player_interaction::selection::SelectedTiles,
+        signals::{SignalStrength, SignalType},
+        structures::structure_manifest::{MoistureRange, Structure, StructureData},
+    };
+    use hexx::{Direction, Hex};
+
+    fn leaf() -> Id<Item> {
+        Id::from_name("leaf".to_string())
+    }
+
+    fn item_manifest() -> ItemManifest {
+        let mut item_manifest: ItemManifest = Manifest::new();
+        item_manifest.insert(
+            "leaf".to_string(),
+            ItemData {
+                compostable: true,
+                buoyant: true,
+                ..ItemData::basic(10)
+            },
+        );
+        item_manifest
+    }
+
+    /// Creates an app where walls and fences can be zoned, each needing `material_count` leaves to construct.
+    fn zoning_app(material_count: u32) -> App {
+        let mut structure_data = StructureData::impassable();
+        structure_data.construction_strategy = ConstructionStrategy::Direct(ConstructionData {
+            work: None,
+            materials: InputInventory::Exact {
+                inventory: Inventory::empty_from_item(leaf(), material_count),
+            },
+            ..Default::default()
+        });
+
+        let mut structure_manifest: StructureManifest = Manifest::new();
+        structure_manifest.insert("wall".to_string(), structure_data.clone());
+        structure_manifest.insert("fence".to_string(), structure_data);
+
+        let mut app = App::new();
+        let map_geometry = MapGeometry::new(&mut app.world, 1);
+        for &hex in map_geometry.all_hexes() {
+            let terrain_entity = map_geometry.get_terrain(hex).unwrap();
+            app.world
+                .entity_mut(terrain_entity)
+                .insert((Zoning::None, Litter::default()));
+        }
+
+        app.insert_resource(map_geometry)
+            .insert_resource(structure_manifest)
+            .insert_resource(item_manifest())
+            .insert_resource(FixedTime::new_from_secs(1.0 / 30.))
+            .init_resource::<RecipeManifest>()
+            .init_resource::<Milestones>()
+            .init_resource::<PlacementRules>()
+            .init_resource::<ZoningSettings>()
+            .init_resource::<Signals>()
+            .add_systems(
+                (
+                    resume_rebuild_orders,
+                    mark_based_on_zoning,
+                    validate_ghost_structures,
+                    ghost_terraforming,
+                    ghost_structure_lifecycle,
+                )
+                    .chain(),
+            );
+
+        // Clear the initial change detection on zoning
+        app.update();
+
+        app
+    }
+
+    fn zone_wall(app: &mut App, terrain_pos: VoxelPos) {
+        zone_structure(app, terrain_pos, "wall");
+    }
+
+    fn zone_structure(app: &mut App, terrain_pos: VoxelPos, name: &str) {
+        let terrain_entity = app
+            .world
+            .resource::<MapGeometry>()
+            .get_terrain(terrain_pos.hex)
+            .unwrap();
+
+        *app.world.get_mut::<Zoning>(terrain_entity).unwrap() = Zoning::Structure(ClipboardData {
+            structure_id: Id::from_name(name.to_string()),
+            facing: Facing::default(),
+            active_recipe: ActiveRecipe::NONE,
+        });
+    }
+
+    fn ghosts(app: &mut App) -> HashSet<(Entity, VoxelPos)> {
+        let mut query = app
+            .world
+            .query_filtered::<(Entity, &VoxelPos), (With<Id<Structure>>, With<Ghost>)>();
+        query
+            .iter(&app.world)
+            .map(|(entity, &voxel_pos)| (entity, voxel_pos))
+            .collect()
+    }
+
+    fn structures(app: &mut App) -> HashSet<(Entity, VoxelPos)> {
+        let mut query = app
+            .world
+            .query_filtered::<(Entity, &VoxelPos), (With<Id<Structure>>, Without<Ghost>)>();
+        query
+            .iter(&app.world)
+            .map(|(entity, &voxel_pos)| (entity, voxel_pos))
+            .collect()
+    }
+
+    #[test]
+    fn zoning_creates_ghost_that_is_replaced_by_structure_when_complete() {
+        let mut app = zoning_app(1);
+        let terrain_pos = VoxelPos::ZERO;
+        let structure_pos = terrain_pos.above();
+
+        zone_wall(&mut app, terrain_pos);
+        app.update();
+
+        let ghosts_after_zoning = ghosts(&mut app);
+        assert_eq!(ghosts_after_zoning.len(), 1);
+        let (ghost_entity, ghost_pos) = *ghosts_after_zoning.iter().next().unwrap();
+        assert_eq!(ghost_pos, structure_pos);
+        assert!(structures(&mut app).is_empty());
+
+        // Without materials, the ghost should stay a ghost
+        for _ in 0..3 {
+            app.update();
+        }
+        assert_eq!(ghosts(&mut app).len(), 1);
+        assert!(structures(&mut app).is_empty());
+
+        // Deliver the construction materials
+        *app.world.get_mut::<InputInventory>(ghost_entity).unwrap() = InputInventory::Exact {
+            inventory: Inventory::full_from_item(leaf(), 1),
+        };
+
+        for _ in 0..3 {
+            app.update();
+        }
+
+        assert!(ghosts(&mut app).is_empty());
+        assert!(app.world.get_entity(ghost_entity).is_none());
+
+        let structures = structures(&mut app);
+        assert_eq!(structures.len(), 1);
+        let (structure_entity, built_pos) = *structures.iter().next().unwrap();
+        assert_eq!(built_pos, structure_pos);
+
+        let map_geometry = app.world.resource::<MapGeometry>();
+        assert_eq!(
+            map_geometry.get_structure(structure_pos),
+            Some(structure_entity)
+        );
+        assert_eq!(map_geometry.get_ghost_structure(structure_pos), None);
+    }
+
+    #[test]
+    fn clearing_zoning_despawns_ghost() {
+        let mut app = zoning_app(1);
+        let terrain_pos = VoxelPos::ZERO;
+        let structure_pos = terrain_pos.above();
+
+        zone_wall(&mut app, terrain_pos);
+        app.update();
+        let (ghost_entity, _) = *ghosts(&mut app).iter().next().unwrap();
+
+        let terrain_entity = app
+            .world
+            .resource::<MapGeometry>()
+            .get_terrain(terrain_pos.hex)
+            .unwrap();
+        *app.world.get_mut::<Zoning>(terrain_entity).unwrap() = Zoning::None;
+        app.update();
+
+        assert!(ghosts(&mut app).is_empty());
+        assert!(app.world.get_entity(ghost_entity).is_none());
+        assert_eq!(
+            app.world
+                .resource::<MapGeometry>()
+                .get_ghost_structure(structure_pos),
+            None
+        );
+    }
+
+    #[test]
+    fn cancelling_ghost_refunds_delivered_materials() {
+        let mut app = zoning_app(5);
+        let terrain_pos = VoxelPos::ZERO;
+        let structure_pos = terrain_pos.above();
+
+        zone_wall(&mut app, terrain_pos);
+        app.update();
+        let (ghost_entity, _) = *ghosts(&mut app).iter().next().unwrap();
+
+        // Deliver 3 of the 5 required leaves
+        app.world
+            .get_mut::<InputInventory>(ghost_entity)
+            .unwrap()
+            .fill_with_items(&ItemCount::new(leaf(), 3), &item_manifest())
+            .unwrap();
+        app.update();
+        assert_eq!(ghosts(&mut app).len(), 1);
+
+        let terrain_entity = app
+            .world
+            .resource::<MapGeometry>()
+            .get_terrain(terrain_pos.hex)
+            .unwrap();
+        *app.world.get_mut::<Zoning>(terrain_entity).unwrap() = Zoning::None;
+        app.update();
+
+        assert!(ghosts(&mut app).is_empty());
+        assert!(app.world.get_entity(ghost_entity).is_none());
+
+        let mut litter_query = app.world.query::<&Litter>();
+        let littered_leaves: u32 = litter_query
+            .iter(&app.world)
+            .map(|litter| litter.contents.item_count(leaf()))
+            .sum();
+        assert_eq!(littered_leaves, 3);
+
+        // The leaves are kept together, on the tile the ghost was on
+        let litter = app.world.get::<Litter>(terrain_entity).unwrap();
+        assert_eq!(litter.contents.item_count(leaf()), 3);
+
+        let map_geometry = app.world.resource::<MapGeometry>();
+        let structure_manifest = app.world.resource::<StructureManifest>();
+        assert_eq!(map_geometry.get_ghost_structure(structure_pos), None);
+        assert!(map_geometry
+            .is_space_available_for_ghost(
+                structure_pos,
+                structure_manifest.footprint(Id::from_name("wall".to_string())),
+                Facing::default(),
+            )
+            .is_ok());
+    }
+
+    #[test]
+    fn rezoning_over_ghost_refunds_materials_and_replaces_it() {
+        let mut app = zoning_app(5);
+        let terrain_pos = VoxelPos::ZERO;
+        let structure_pos = terrain_pos.above();
+        let terrain_entity = app
+            .world
+            .resource::<MapGeometry>()
+            .get_terrain(terrain_pos.hex)
+            .unwrap();
+        let littered_leaves = |app: &App| {
+            app.world
+                .get::<Litter>(terrain_entity)
+                .unwrap()
+                .contents
+                .item_count(leaf())
+        };
+
+        zone_wall(&mut app, terrain_pos);
+        app.update();
+        let (wall_ghost, _) = *ghosts(&mut app).iter().next().unwrap();
+
+        // Deliver 3 of the 5 required leaves
+        app.world
+            .get_mut::<InputInventory>(wall_ghost)
+            .unwrap()
+            .fill_with_items(&ItemCount::new(leaf(), 3), &item_manifest())
+            .unwrap();
+
+        // Zoning the same structure again changes nothing
+        zone_wall(&mut app, terrain_pos);
+        app.update();
+        assert_eq!(
+            ghosts(&mut app),
+            HashSet::from_iter([(wall_ghost, structure_pos)])
+        );
+        assert_eq!(littered_leaves(&app), 0);
+
+        zone_structure(&mut app, terrain_pos, "fence");
+        app.update();
+
+        assert!(app.world.get_entity(wall_ghost).is_none());
+        assert_eq!(littered_leaves(&app), 3);
+
+        let ghosts = ghosts(&mut app);
+        assert_eq!(ghosts.len(), 1);
+        let (fence_ghost, ghost_pos) = *ghosts.iter().next().unwrap();
+        assert_eq!(ghost_pos, structure_pos);
+        assert_eq!(
+            app.world.get::<Id<Structure>>(fence_ghost),
+            Some(&Id::from_name("fence".to_string()))
+        );
+        assert_eq!(
+            app.world
+                .resource::<MapGeometry>()
+                .get_ghost_structure(structure_pos),
+            Some(fence_ghost)
+        );
+    }
+
+    #[test]
+    fn rezoning_over_completed_structure_demolishes_then_rebuilds() {
+        let mut app = zoning_app(1);
+        let terrain_pos = VoxelPos::ZERO;
+        let structure_pos = terrain_pos.above();
+        let terrain_entity = app
+            .world
+            .resource::<MapGeometry>()
+            .get_terrain(terrain_pos.hex)
+            .unwrap();
+
+        zone_wall(&mut app, terrain_pos);
+        app.update();
+        let (wall_ghost, _) = *ghosts(&mut app).iter().next().unwrap();
+        *app.world.get_mut::<InputInventory>(wall_ghost).unwrap() = InputInventory::Exact {
+            inventory: Inventory::full_from_item(leaf(), 1),
+        };
+        for _ in 0..3 {
+            app.update();
+        }
+        let (wall_entity, _) = *structures(&mut app).iter().next().unwrap();
+
+        zone_structure(&mut app, terrain_pos, "fence");
+        app.update();
+
+        // The wall must be demolished before the fence can be built
+        assert!(app.world.get::<MarkedForDemolition>(wall_entity).is_some());
+        assert_eq!(
+            app.world.get::<RebuildOrder>(terrain_entity),
+            Some(&RebuildOrder {
+                blocking_structures: vec![wall_entity],
+            })
+        );
+        assert!(ghosts(&mut app).is_empty());
+
+        // Pretend that units have demolished the wall
+        let mut command_queue = bevy::ecs::system::CommandQueue::default();
+        let mut commands = Commands::new(&mut command_queue, &app.world);
+        commands.despawn_structure(structure_pos);
+        command_queue.apply(&mut app.world);
+        app.update();
+
+        assert!(app.world.get::<RebuildOrder>(terrain_entity).is_none());
+        let ghosts = ghosts(&mut app);
+        assert_eq!(ghosts.len(), 1);
+        let (fence_ghost, _) = *ghosts.iter().next().unwrap();
+        assert_eq!(
+            app.world.get::<Id<Structure>>(fence_ghost),
+            Some(&Id::from_name("fence".to_string()))
+        );
+    }
+
+    #[test]
+    fn cancelling_demolition_drops_the_rebuild_order() {
+        let mut app = zoning_app(1);
+        let terrain_pos = VoxelPos::ZERO;
+        let terrain_entity = app
+            .world
+            .resource::<MapGeometry>()
+            .get_terrain(terrain_pos.hex)
+            .unwrap();
+
+        zone_wall(&mut app, terrain_pos);
+        app.update();
+        let (wall_ghost, _) = *ghosts(&mut app).iter().next().unwrap();
+        *app.world.get_mut::<InputInventory>(wall_ghost).unwrap() = InputInventory::Exact {
+            inventory: Inventory::full_from_item(leaf(), 1),
+        };
+        for _ in 0..3 {
+            app.update();
+        }
+        let (wall_entity, _) = *structures(&mut app).iter().next().unwrap();
+
+        zone_structure(&mut app, terrain_pos, "fence");
+        app.update();
+        assert!(app.world.get::<RebuildOrder>(terrain_entity).is_some());
+
+        app.world
+            .entity_mut(wall_entity)
+            .remove::<MarkedForDemolition>();
+        app.update();
+        app.update();
+
+        assert!(app.world.get::<RebuildOrder>(terrain_entity).is_none());
+        assert_eq!(app.world.get::<Zoning>(terrain_entity), Some(&Zoning::None));
+        assert!(app.world.get_entity(wall_entity).is_some());
+        assert!(ghosts(&mut app).is_empty());
+    }
+
+    #[test]
+    fn zoning_respects_allowed_moisture_at_boundary_depths() {
+        let flooded = MoistureRange {
+            min_depth: Height(0.5),
+            max_depth: Height(1.),
+            max_water_table_depth: None,
+        };
+        let moist_soil = MoistureRange {
+            min_depth: Height::ZERO,
+            max_depth: Height::ZERO,
+            max_water_table_depth: Some(Height(0.5)),
+        };
+
+        for (allowed_moisture, water_depth, accepted) in [
+            (flooded, WaterDepth::Dry, false),
+            (flooded, WaterDepth::Underground(Height(0.5)), false),
+            (flooded, WaterDepth::Flooded(Height(0.4)), false),
+            (flooded, WaterDepth::Flooded(Height(0.5)), true),
+            (flooded, WaterDepth::Flooded(Height(1.)), true),
+            (flooded, WaterDepth::Flooded(Height(1.1)), false),
+            (moist_soil, WaterDepth::Dry, false),
+            (moist_soil, WaterDepth::Underground(Height(0.6)), false),
+            (moist_soil, WaterDepth::Underground(Height(0.5)), true),
+            (moist_soil, WaterDepth::Underground(Height::ZERO), true),
+            (moist_soil, WaterDepth::Flooded(Height(0.1)), false),
+        ] {
+            let mut app = zoning_app(1);
+
+            let wall_id = Id::from_name("wall".to_string());
+            let mut structure_manifest = app.world.resource_mut::<StructureManifest>();
+            let wall = StructureData {
+                allowed_moisture: Some(allowed_moisture),
+                ..structure_manifest.get(wall_id).clone()
+            };
+            structure_manifest.insert("wall".to_string(), wall);
+
+            let terrain_pos = VoxelPos::ZERO;
+            let terrain_entity = app
+                .world
+                .resource::<MapGeometry>()
+                .get_terrain(terrain_pos.hex)
+                .unwrap();
+            app.world.entity_mut(terrain_entity).insert(water_depth);
+
+            zone_wall(&mut app, terrain_pos);
+            app.update();
+
+            assert_eq!(ghosts(&mut app).len() == 1, accepted, "{water_depth}");
+        }
+    }
+
+    /// Fills the voxel on top of the terrain at each of the `hexes` with litter, so that nothing can be built there.
+    fn block_with_litter(app: &mut App, hexes: impl IntoIterator<Item = Hex>) {
+        for hex in hexes {
+            let litter_entity = app.world.spawn_empty().id();
+            let mut map_geometry = app.world.resource_mut::<MapGeometry>();
+            let litter_pos = map_geometry.on_top_of_terrain(hex);
+            assert_eq!(
+                map_geometry.drop_litter(litter_pos, litter_entity),
+                litter_pos
+            );
+        }
+    }
+
+    fn zoning_of(app: &App, hex: Hex) -> Zoning {
+        let terrain_entity = app
+            .world
+            .resource::<MapGeometry>()
+            .get_terrain(hex)
+            .unwrap();
+        app.world.get::<Zoning>(terrain_entity).unwrap().clone()
+    }
+
+    #[test]
+    fn blocked_zoning_snaps_to_adjacent_free_tile() {
+        let mut app = zoning_app(1);
+        app.world
+            .resource_mut::<ZoningSettings>()
+            .snap_to_nearest_valid = true;
+        block_with_litter(&mut app, [Hex::ZERO]);
+
+        zone_wall(&mut app, VoxelPos::ZERO);
+        for _ in 0..3 {
+            app.update();
+        }
+
+        let ghosts = ghosts(&mut app);
+        assert_eq!(ghosts.len(), 1);
+        let (_, ghost_pos) = *ghosts.iter().next().unwrap();
+        assert_eq!(ghost_pos.hex.unsigned_distance_to(Hex::ZERO), 1);
+        assert_eq!(
+            ghost_pos,
+            app.world
+                .resource::<MapGeometry>()
+                .on_top_of_terrain(ghost_pos.hex)
+        );
+
+        // The zoning moves along with the structure
+        assert_eq!(zoning_of(&app, Hex::ZERO), Zoning::None);
+        assert!(matches!(
+            zoning_of(&app, ghost_pos.hex),
+            Zoning::Structure(_)
+        ));
+    }
+
+    #[test]
+    fn zoning_over_an_identical_structure_does_not_snap() {
+        let mut app = zoning_app(1);
+        app.world
+            .resource_mut::<ZoningSettings>()
+            .snap_to_nearest_valid = true;
+
+        zone_wall(&mut app, VoxelPos::ZERO);
+        app.update();
+        let (wall_ghost, _) = *ghosts(&mut app).iter().next().unwrap();
+        *app.world.get_mut::<InputInventory>(wall_ghost).unwrap() = InputInventory::Exact {
+            inventory: Inventory::full_from_item(leaf(), 1),
+        };
+        for _ in 0..3 {
+            app.update();
+        }
+        assert_eq!(structures(&mut app).len(), 1);
+
+        // Dragging over the same wall again leaves it alone, rather than building a copy next door
+        zone_wall(&mut app, VoxelPos::ZERO);
+        for _ in 0..3 {
+            app.update();
+        }
+
+        assert!(ghosts(&mut app).is_empty());
+        assert_eq!(structures(&mut app).len(), 1);
+        assert_eq!(zoning_of(&app, Hex::ZERO), Zoning::None);
+    }
+
+    /// Zones a releaser of leaves at the origin, with something pulling for leaves on the tile in `pull_direction`.
+    ///
+    /// Returns the facing of the resulting ghost.
+    fn zone_releaser_near_puller(auto_face_logistics: bool, pull_direction: Direction) -> Facing {
+        let mut app = zoning_app(1);
+        app.world
+            .resource_mut::<ZoningSettings>()
+            .auto_face_logistics = auto_face_logistics;
+
+        let mut structure_manifest = app.world.resource_mut::<StructureManifest>();
+        let mut releaser_data = structure_manifest
+            .get(Id::from_name("wall".to_string()))
+            .clone();
+        releaser_data.kind = StructureKind::Releaser {
+            release_threshold: None,
+            released_item: leaf(),
+        };
+        structure_manifest.insert("releaser".to_string(), releaser_data);
+
+        app.world.resource_mut::<Signals>().add_signal(
+            SignalType::Pull(ItemKind::Single(leaf())),
+            VoxelPos::ZERO.neighbor(pull_direction),
+            SignalStrength::new(5.),
+        );
+
+        zone_structure(&mut app, VoxelPos::ZERO, "releaser");
+        app.update();
+
+        let mut facing_query = app
+            .world
+            .query_filtered::<&Facing, (With<Id<Structure>>, With<Ghost>)>();
+        *facing_query.single(&app.world)
+    }
+
+    #[test]
+    fn auto_faced_releasers_face_toward_pullers() {
+        for direction in Direction::ALL_DIRECTIONS {
+            assert_eq!(
+                zone_releaser_near_puller(true, direction),
+                Facing {
+                    direction,
+                    mirrored: false
+                }
+            );
+        }
+
+        // Without auto-facing, the zoned facing is kept
+        assert_eq!(
+            zone_releaser_near_puller(false, Direction::Bottom),
+            Facing::default()
+        );
+    }
+
+    #[test]
+    fn blocked_zoning_is_dropped_without_snapping_or_free_tiles() {
+        // Without snapping, the structure is not placed at all
+        let mut app = zoning_app(1);
+        block_with_litter(&mut app, [Hex::ZERO]);
+
+        zone_wall(&mut app, VoxelPos::ZERO);
+        for _ in 0..3 {
+            app.update();
+        }
+
+        assert!(ghosts(&mut app).is_empty());
+        assert_eq!(zoning_of(&app, Hex::ZERO), Zoning::None);
+
+        // Snapping can't help if every tile within range is blocked
+        let mut app = zoning_app(1);
+        app.world
+            .resource_mut::<ZoningSettings>()
+            .snap_to_nearest_valid = true;
+        let all_hexes: Vec<Hex> = app
+            .world
+            .resource::<MapGeometry>()
+            .all_hexes()
+            .copied()
+            .collect();
+        block_with_litter(&mut app, all_hexes.clone());
+
+        zone_wall(&mut app, VoxelPos::ZERO);
+        for _ in 0..3 {
+            app.update();
+        }
+
+        assert!(ghosts(&mut app).is_empty());
+        for hex in all_hexes {
+            assert_eq!(zoning_of(&app, hex), Zoning::None);
+        }
+    }
+
+    #[test]
+    fn locked_structures_are_not_zoned_until_their_milestone_is_complete() {
+        let mut app = zoning_app(1);
+        let milestone = Id::from_name("masonry".to_string());
+        let mut gate_data = app
+            .world
+            .resource::<StructureManifest>()
+            .get(Id::from_name("wall".to_string()))
+            .clone();
+        gate_data.required_milestone = Some(milestone);
+        app.world
+            .resource_mut::<StructureManifest>()
+            .insert("gate".to_string(), gate_data);
+
+        zone_structure(&mut app, VoxelPos::ZERO, "gate");
+        for _ in 0..3 {
+            app.update();
+        }
+
+        assert!(ghosts(&mut app).is_empty());
+        assert_eq!(zoning_of(&app, Hex::ZERO), Zoning::None);
+
+        app.world.resource_mut::<Milestones>().complete(milestone);
+        zone_structure(&mut app, VoxelPos::ZERO, "gate");
+        app.update();
+
+        assert_eq!(ghosts(&mut app).len(), 1);
+    }
+
+    #[test]
+    fn ghosts_on_uneven_terrain_wait_for_terraforming_before_accepting_materials() {
+        let mut app = zoning_app(1);
+
+        let wall_id = Id::from_name("wall".to_string());
+        let mut structure_manifest = app.world.resource_mut::<StructureManifest>();
+        let mut wall = structure_manifest.get(wall_id).clone();
+        if let ConstructionStrategy::Direct(construction_data) = &mut wall.construction_strategy {
+            construction_data.required_terrain_height = Some(Height(1.));
+            construction_data.allow_terraform = true;
+        }
+        structure_manifest.insert("wall".to_string(), wall);
+
+        let terrain_pos = VoxelPos::ZERO;
+        let terrain_entity = app
+            .world
+            .resource::<MapGeometry>()
+            .get_terrain(terrain_pos.hex)
+            .unwrap();
+
+        zone_wall(&mut app, terrain_pos);
+        app.update();
+        app.update();
+
+        let (ghost_entity, _) = *ghosts(&mut app).iter().next().unwrap();
+        assert_eq!(
+            app.world.get::<ConstructionPhase>(ghost_entity),
+            Some(&ConstructionPhase::Terraforming)
+        );
+        assert_eq!(
+            app.world.get::<TerraformingAction>(terrain_entity),
+            Some(&TerraformingAction::Raise)
+        );
+
+        // The terrain is too low, so the ghost refuses its construction materials
+        assert!(app
+            .world
+            .get_mut::<InputInventory>(ghost_entity)
+            .unwrap()
+            .fill_with_items(&ItemCount::new(leaf(), 1), &item_manifest())
+            .is_err());
+
+        // Pretend that units have finished raising the terrain
+        app.world
+            .entity_mut(terrain_entity)
+            .remove::<(TerraformingAction, InputInventory, OutputInventory)>();
+        app.world
+            .get_mut::<VoxelPos>(terrain_entity)
+            .unwrap()
+            .height = DiscreteHeight(1);
+        app.world
+            .resource_mut::<MapGeometry>()
+            .update_height(terrain_pos.hex, DiscreteHeight(1));
+        app.update();
+
+        // The ghost rests on the raised terrain, and is now ready for construction
+        let structure_pos = app
+            .world
+            .resource::<MapGeometry>()
+            .on_top_of_terrain(terrain_pos.hex);
+        assert_eq!(
+            app.world.get::<VoxelPos>(ghost_entity),
+            Some(&structure_pos)
+        );
+        assert_eq!(
+            app.world.get::<ConstructionPhase>(ghost_entity),
+            Some(&ConstructionPhase::Building)
+        );
+        assert!(app
+            .world
+            .get_mut::<InputInventory>(ghost_entity)
+            .unwrap()
+            .fill_with_items(&ItemCount::new(leaf(), 1), &item_manifest())
+            .is_ok());
+
+        for _ in 0..3 {
+            app.update();
+        }
+
+        assert!(ghosts(&mut app).is_empty());
+        let structures = structures(&mut app);
+        assert_eq!(structures.len(), 1);
+        let (_, built_pos) = *structures.iter().next().unwrap();
+        assert_eq!(built_pos, structure_pos);
+    }
+
+    /// The structure that [`selection_app`] is ready to zone.
+    fn selected_structure() -> ClipboardData {
+        ClipboardData {
+            structure_id: Id::from_name("acacia".to_string()),
+            facing: Facing::default(),
+            active_recipe: ActiveRecipe::NONE,
+        }
+    }
+
+    /// Creates an app with two tiles selected and the provided `tool` ready to zone them.
+    fn selection_app(tool: Tool, clear_selected_structure: bool) -> App {
+        let mut app = App::new();
+        let map_geometry = MapGeometry::new(&mut app.world, 1);
+        for &hex in map_geometry.all_hexes() {
+            let terrain_entity = map_geometry.get_terrain(hex).unwrap();
+            app.world.entity_mut(terrain_entity).insert(Zoning::None);
+        }
+
+        let mut selected_tiles = SelectedTiles::default();
+        for hex in [Hex::ZERO, Hex::new(1, 0)] {
+            selected_tiles.add_tile(map_geometry.on_top_of_terrain(hex));
+        }
+
+        app.insert_resource(map_geometry)
+            .insert_resource(CurrentSelection::Terrain(selected_tiles))
+            .insert_resource(tool)
+            .insert_resource(ZoningSettings {
+                clear_selected_structure,
+                ..Default::default()
+            })
+            .init_resource::<CursorPos>()
+            .init_resource::<ActionState<PlayerAction>>()
+            .add_systems((set_zoning, deselect_after_zoning).chain());
+
+        app
+    }
+
+    /// Presses only the provided action, and runs the app once.
+    fn press(app: &mut App, action: PlayerAction) {
+        let mut actions = app.world.resource_mut::<ActionState<PlayerAction>>();
+        actions.release_all();
+        actions.press(action);
+
+        app.update();
+    }
+
+    /// Returns the zoning of the tile at `hex`, resetting it to [`Zoning::None`].
+    fn take_zoning(app: &mut App, hex: Hex) -> Zoning {
+        let terrain_entity = app
+            .world
+            .resource::<MapGeometry>()
+            .get_terrain(hex)
+            .unwrap();
+        let mut zoning = app.world.get_mut::<Zoning>(terrain_entity).unwrap();
+        std::mem::replace(&mut *zoning, Zoning::None)
+    }
+
+    #[test]
+    fn zoning_keeps_selection_but_zone_and_clear_clears_it() {
+        for clear_selected_structure in [false, true] {
+            let mut tool = Tool::None;
+            tool.set_to_structure(Some(selected_structure()));
+            let mut app = selection_app(tool, clear_selected_structure);
+            let zoned = Zoning::Structure(selected_structure());
+
+            // Repeated stamping keeps both the selected tiles and the selected structure
+            for _ in 0..2 {
+                press(&mut app, PlayerAction::Paste);
+                assert_eq!(take_zoning(&mut app, Hex::ZERO), zoned);
+                assert_eq!(take_zoning(&mut app, Hex::new(1, 0)), zoned);
+
+                let current_selection = app.world.resource::<CurrentSelection>();
+                assert!(
+                    matches!(current_selection, CurrentSelection::Terrain(selected_tiles) if selected_tiles.len() == 2)
+                );
+                assert!(!app.world.resource::<Tool>().is_empty());
             }
+
+            press(&mut app, PlayerAction::ZoneAndClear);
+            assert_eq!(take_zoning(&mut app, Hex::ZERO), zoned);
+            assert_eq!(take_zoning(&mut app, Hex::new(1, 0)), zoned);
+
+            let current_selection = app.world.resource::<CurrentSelection>();
+            assert!(matches!(current_selection, CurrentSelection::None));
+            assert_eq!(
+                app.world.resource::<Tool>().is_empty(),
+                clear_selected_structure
+            );
+        }
+    }
+
+    #[test]
+    fn zone_and_clear_keeps_terraforming_tools() {
+        let mut app = selection_app(Tool::Terraform(TerraformingTool::Raise), true);
+
+        press(&mut app, PlayerAction::ZoneAndClear);
+        assert_eq!(
+            take_zoning(&mut app, Hex::ZERO),
+            Zoning::Terraform(TerraformingAction::Raise)
+        );
+
+        let current_selection = app.world.resource::<CurrentSelection>();
+        assert!(matches!(current_selection, CurrentSelection::None));
+        assert!(!app.world.resource::<Tool>().is_empty());
+    }
+
+    #[test]
+    fn zoning_settings_survive_a_save_and_load() {
+        let path = std::env::temp_dir().join(format!(
+            "emergence_zoning_settings_{}.json",
+            std::process::id()
+        ));
+        let zoning_settings = ZoningSettings {
+            clear_selected_structure: true,
+            snap_to_nearest_valid: true,
+            auto_face_logistics: false,
         };
+
+        save_zoning_settings(&zoning_settings, &path).unwrap();
+        let loaded = load_zoning_settings(&path);
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(loaded, zoning_settings);
+    }
+
+    #[test]
+    fn zoning_settings_are_loaded_from_the_configured_path_on_startup() {
+        let path = std::env::temp_dir().join(format!(
+            "emergence_zoning_settings_{}_startup.json",
+            std::process::id()
+        ));
+        let zoning_settings = ZoningSettings {
+            clear_selected_structure: true,
+            snap_to_nearest_valid: false,
+            auto_face_logistics: true,
+        };
+        save_zoning_settings(&zoning_settings, &path).unwrap();
+
+        let startup_app = |config: ZoningSettingsConfig| {
+            let mut app = App::new();
+            app.insert_resource(config)
+                .init_resource::<ZoningSettings>()
+                .add_startup_system(load_saved_zoning_settings);
+            app.update();
+            app
+        };
+
+        let app = startup_app(ZoningSettingsConfig {
+            path: Some(path.clone()),
+        });
+        assert_eq!(*app.world.resource::<ZoningSettings>(), zoning_settings);
+
+        let app = startup_app(ZoningSettingsConfig::disabled());
+        assert_eq!(
+            *app.world.resource::<ZoningSettings>(),
+            ZoningSettings::default()
+        );
+
+        fs::remove_file(&path).unwrap();
     }
 }
@@ -0,0 +1,330 @@
+//! Renders the zoning layer and placed structures as an ASCII map, to attach to bug reports.
+//!
+//! Each hex is drawn as a single character, with every other row offset by half a cell so that neighbors line up.
+//! Structures are drawn with a letter shared by every tile of their footprint:
+//! uppercase for completed structures and lowercase for ghosts.
+
+use std::{
+    fmt::Write as _,
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use bevy::{app::AppExit, prelude::*, utils::HashMap};
+use hexx::Hex;
+use leafwing_input_manager::prelude::ActionState;
+
+use crate::{
+    asset_management::manifest::Id,
+    geometry::MapGeometry,
+    player_interaction::PlayerAction,
+    structures::structure_manifest::{Structure, StructureManifest},
+};
+
+use super::{terraform::TerraformingAction, zoning::Zoning};
+
+/// Controls where zoning snapshots are written.
+#[derive(Resource, Debug, Clone, PartialEq, Eq)]
+pub struct ZoningSnapshotSettings {
+    /// The file written to when [`PlayerAction::DumpZoningSnapshot`] is pressed.
+    pub path: PathBuf,
+    /// If set, a snapshot is written to this file when the game exits.
+    pub path_on_exit: Option<PathBuf>,
+}
+
+impl Default for ZoningSnapshotSettings {
+    fn default() -> Self {
+        ZoningSnapshotSettings {
+            path: PathBuf::from("zoning_snapshot.txt"),
+            path_on_exit: None,
+        }
+    }
+}
+
+/// Writes zoning snapshots on request, and when the game exits.
+pub(super) struct ZoningSnapshotPlugin;
+
+impl Plugin for ZoningSnapshotPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ZoningSnapshotSettings>()
+            .add_system(dump_zoning_snapshot.run_if(snapshot_requested))
+            .add_system(
+                dump_zoning_snapshot_on_exit
+                    .run_if(on_event::<AppExit>())
+                    .in_base_set(CoreSet::Last),
+            );
+    }
+}
+
+/// The character drawn for tiles with nothing of interest on them.
+const EMPTY: char = '.';
+/// The character drawn for tiles zoned for a structure whose ghost has not yet been placed.
+const ZONED: char = '+';
+/// The character drawn for structure types beyond the 26th, once every letter has been used.
+const OVERFLOW: char = '*';
+
+/// Renders the zoning layer and structures of the map in `world` as ASCII art, followed by a legend.
+///
+/// Structure letters are assigned alphabetically by name, so snapshots of the same map are always identical.
+pub(crate) fn zoning_snapshot(world: &World) -> String {
+    let map_geometry = world.resource::<MapGeometry>();
+    let structure_manifest = world.resource::<StructureManifest>();
+    let structure_id = |entity: Entity| world.get::<Id<Structure>>(entity).copied();
+
+    // First pass: find the contents of each tile
+    let mut contents: HashMap<Hex, TileContents> = HashMap::default();
+    for &hex in map_geometry.all_hexes() {
+        let voxel_pos = map_geometry.on_top_of_terrain(hex);
+        let tile_contents = if let Some(id) =
+            map_geometry.get_structure(voxel_pos).and_then(structure_id)
+        {
+            TileContents::Built(id)
+        } else if let Some(id) = map_geometry
+            .get_ghost_structure(voxel_pos)
+            .and_then(structure_id)
+        {
+            TileContents::Ghost(id)
+        } else {
+            let zoning = map_geometry
+                .get_terrain(hex)
+                .ok()
+                .and_then(|terrain_entity| world.get::<Zoning>(terrain_entity));
+            match zoning {
+                Some(Zoning::Structure(_)) => TileContents::Symbol(ZONED),
+                Some(Zoning::Terraform(TerraformingAction::Raise)) => TileContents::Symbol('^'),
+                Some(Zoning::Terraform(TerraformingAction::Lower)) => TileContents::Symbol('v'),
+                Some(Zoning::Terraform(TerraformingAction::Change(_))) => TileContents::Symbol('~'),
+                Some(Zoning::None) | None => TileContents::Symbol(EMPTY),
+            }
+        };
+
+        contents.insert(hex, tile_contents);
+    }
+
+    // Assign a letter to each structure type that appears
+    let structure_name = |id: Id<Structure>| {
+        structure_manifest
+            .try_name(id)
+            .map(str::to_string)
+            .unwrap_or_else(|| format!("{id:?}"))
+    };
+    let mut names: Vec<(String, Id<Structure>)> = contents
+        .values()
+        .filter_map(TileContents::structure_id)
+        .map(|id| (structure_name(id), id))
+        .collect();
+    names.sort();
+    names.dedup();
+    let letters: HashMap<Id<Structure>, char> = names
+        .iter()
+        .enumerate()
+        .map(|(i, &(_, id))| {
+            let letter = match i < 26 {
+                true => (b'A' + i as u8) as char,
+                false => OVERFLOW,
+            };
+            (id, letter)
+        })
+        .collect();
+
+    // Second pass: draw the map, one row of hexes at a time
+    let radius = map_geometry.radius as i32;
+    let mut snapshot = String::new();
+    for y in -radius..=radius {
+        let x_range = (-radius).max(-y - radius)..=radius.min(-y + radius);
+        let row: Vec<String> = x_range
+            .map(|x| match contents.get(&Hex::new(x, y)) {
+                Some(TileContents::Built(id)) => letters[id].to_string(),
+                Some(TileContents::Ghost(id)) => letters[id].to_ascii_lowercase().to_string(),
+                Some(TileContents::Symbol(symbol)) => symbol.to_string(),
+                None => " ".to_string(),
+            })
+            .collect();
+
+        let indent = " ".repeat(y.unsigned_abs() as usize);
+        writeln!(snapshot, "{indent}{}", row.join(" ")).unwrap();
+    }
+
+    writeln!(snapshot).unwrap();
+    writeln!(snapshot, "Legend (uppercase: built, lowercase: ghost)").unwrap();
+    for (name, id) in &names {
+        let letter = letters[id];
+        writeln!(snapshot, "{letter}/{}: {name}", letter.to_ascii_lowercase()).unwrap();
+    }
+    writeln!(snapshot, "{ZONED}: zoned for a structure").unwrap();
+    writeln!(snapshot, "^: raise terrain").unwrap();
+    writeln!(snapshot, "v: lower terrain").unwrap();
+    writeln!(snapshot, "~: change terrain").unwrap();
+    writeln!(snapshot, "{EMPTY}: nothing").unwrap();
+
+    snapshot
+}
+
+/// What is drawn on a single tile of a zoning snapshot.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum TileContents {
+    /// A completed structure.
+    Built(Id<Structure>),
+    /// A ghost of a structure that has not yet been built.
+    Ghost(Id<Structure>),
+    /// Anything else, drawn with the provided character.
+    Symbol(char),
+}
+
+impl TileContents {
+    /// The structure on this tile, if any.
+    fn structure_id(&self) -> Option<Id<Structure>> {
+        match *self {
+            TileContents::Built(id) | TileContents::Ghost(id) => Some(id),
+            TileContents::Symbol(_) => None,
+        }
+    }
+}
+
+/// Writes a [`zoning_snapshot`] of `world` to `path`.
+///
+/// Fails without writing anything if the map has not yet been generated.
+pub(crate) fn write_zoning_snapshot(world: &World, path: &Path) -> io::Result<()> {
+    if !world.contains_resource::<MapGeometry>() || !world.contains_resource::<StructureManifest>()
+    {
+        return Err(io::Error::new(
+            io::ErrorKind::Other,
+            "the map has not been generated yet",
+        ));
+    }
+
+    fs::write(path, zoning_snapshot(world))
+}
+
+/// Has the player asked for a zoning snapshot?
+fn snapshot_requested(player_actions: Res<ActionState<PlayerAction>>) -> bool {
+    player_actions.just_pressed(PlayerAction::DumpZoningSnapshot)
+}
+
+/// Writes a zoning snapshot to [`ZoningSnapshotSettings::path`].
+fn dump_zoning_snapshot(world: &World) {
+    let path = &world.resource::<ZoningSnapshotSettings>().path;
+    match write_zoning_snapshot(world, path) {
+        Ok(()) => info!("Wrote zoning snapshot to {}", path.display()),
+        Err(io_error) => warn!(
+            "Could not write zoning snapshot to {}: {io_error}",
+            path.display()
+        ),
+    }
+}
+
+/// Writes a zoning snapshot to [`ZoningSnapshotSettings::path_on_exit`] as the game exits, if set.
+fn dump_zoning_snapshot_on_exit(world: &World) {
+    let Some(path) = &world.resource::<ZoningSnapshotSettings>().path_on_exit else { return };
+    if let Err(io_error) = write_zoning_snapshot(world, path) {
+        warn!(
+            "Could not write zoning snapshot to {}: {io_error}",
+            path.display()
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        crafting::recipe::ActiveRecipe,
+        geometry::Facing,
+        player_interaction::clipboard::ClipboardData,
+        structures::{structure_manifest::StructureData, Footprint},
+    };
+
+    /// The expected snapshot of the map built by [`tiny_map`].
+    const EXPECTED: &str = include_str!("../../tests/fixtures/zoning/tiny_map.txt");
+
+    /// Builds a radius 2 map with a built three-tile-wide hut, a built and a ghostly bin, and some zoning.
+    fn tiny_map() -> World {
+        let mut world = World::new();
+        let mut map_geometry = MapGeometry::new(&mut world, 2);
+
+        let mut structure_manifest = StructureManifest::new();
+        for name in ["bin", "hut"] {
+            structure_manifest.insert(name.to_string(), StructureData::impassable());
+        }
+        let bin: Id<Structure> = Id::from_name("bin".to_string());
+        let hut: Id<Structure> = Id::from_name("hut".to_string());
+
+        let hut_entity = world.spawn(hut).id();
+        map_geometry
+            .add_structure(
+                map_geometry.on_top_of_terrain(Hex::ZERO),
+                Facing::default(),
+                &Footprint::hexagon(1),
+                false,
+                false,
+                hut_entity,
+            )
+            .unwrap();
+
+        let bin_entity = world.spawn(bin).id();
+        map_geometry
+            .add_structure(
+                map_geometry.on_top_of_terrain(Hex::new(-2, 0)),
+                Facing::default(),
+                &Footprint::single(),
+                false,
+                false,
+                bin_entity,
+            )
+            .unwrap();
+
+        let ghost_entity = world.spawn(bin).id();
+        map_geometry
+            .add_ghost_structure(
+                Facing::default(),
+                map_geometry.on_top_of_terrain(Hex::new(2, 0)),
+                &Footprint::single(),
+                ghost_entity,
+            )
+            .unwrap();
+
+        let zone = |world: &mut World, hex: Hex, zoning: Zoning| {
+            let terrain_entity = map_geometry.get_terrain(hex).unwrap();
+            world.entity_mut(terrain_entity).insert(zoning);
+        };
+        zone(
+            &mut world,
+            Hex::new(2, 0),
+            Zoning::Structure(ClipboardData {
+                structure_id: bin,
+                facing: Facing::default(),
+                active_recipe: ActiveRecipe::Fixed(None),
+            }),
+        );
+        zone(
+            &mut world,
+            Hex::new(-2, 2),
+            Zoning::Structure(ClipboardData {
+                structure_id: hut,
+                facing: Facing::default(),
+                active_recipe: ActiveRecipe::Fixed(None),
+            }),
+        );
+        zone(
+            &mut world,
+            Hex::new(0, -2),
+            Zoning::Terraform(TerraformingAction::Raise),
+        );
+        zone(
+            &mut world,
+            Hex::new(0, 2),
+            Zoning::Terraform(TerraformingAction::Lower),
+        );
+        zone(&mut world, Hex::new(1, 1), Zoning::None);
+
+        world.insert_resource(map_geometry);
+        world.insert_resource(structure_manifest);
+        world
+    }
+
+    #[test]
+    fn tiny_map_matches_checked_in_snapshot() {
+        let world = tiny_map();
+        assert_eq!(zoning_snapshot(&world), EXPECTED);
+    }
+}
@@ -0,0 +1,426 @@
+//! Tracks the rate at which structures produce and consume items.
+
+use std::fmt::Display;
+use std::time::Duration;
+
+use bevy::{prelude::*, utils::HashMap};
+
+use crate::asset_management::manifest::Id;
+use crate::items::{item_manifest::Item, ItemCount};
+
+/// The length of time summed into each sample of a [`RollingWindow`].
+pub(crate) const THROUGHPUT_BUCKET: Duration = Duration::from_secs(1);
+
+/// The number of samples remembered by a [`RollingWindow`].
+///
+/// Together with [`THROUGHPUT_BUCKET`], this covers about a minute of gameplay.
+pub(crate) const THROUGHPUT_SAMPLES: usize = 60;
+
+/// An event sent whenever a structure produces items into its output inventory.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct ItemProduced {
+    /// The structure that produced the items.
+    pub(crate) structure: Entity,
+    /// The type and number of items produced.
+    pub(crate) item_count: ItemCount,
+}
+
+/// An event sent whenever a structure consumes items from its input inventory.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct ItemConsumed {
+    /// The structure that consumed the items.
+    pub(crate) structure: Entity,
+    /// The type and number of items consumed.
+    pub(crate) item_count: ItemCount,
+}
+
+/// The number of items counted during each of the last [`THROUGHPUT_SAMPLES`] [`THROUGHPUT_BUCKET`]s.
+///
+/// Ticks are summed into buckets, so that structures that only move an item every few seconds still show a steady rate.
+/// The samples are stored in a fixed-size ring buffer, so recording a tick never allocates.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct RollingWindow {
+    /// The items counted during each completed bucket.
+    ///
+    /// Once full, the oldest sample is stored at `next`.
+    samples: [i32; THROUGHPUT_SAMPLES],
+    /// The index that the next sample will be written to.
+    next: usize,
+    /// The number of samples recorded so far, up to [`THROUGHPUT_SAMPLES`].
+    len: usize,
+    /// The items counted during the bucket that is currently being filled.
+    pending: i32,
+    /// The time covered by the bucket that is currently being filled.
+    pending_time: Duration,
+}
+
+impl Default for RollingWindow {
+    fn default() -> Self {
+        RollingWindow {
+            samples: [0; THROUGHPUT_SAMPLES],
+            next: 0,
+            len: 0,
+            pending: 0,
+            pending_time: Duration::ZERO,
+        }
+    }
+}
+
+impl RollingWindow {
+    /// Records the number of items counted over a tick of length `elapsed`.
+    ///
+    /// Once a full [`THROUGHPUT_BUCKET`] has been recorded, it is stored as a sample, overwriting the oldest sample once full.
+    pub(crate) fn record(&mut self, elapsed: Duration, count: i32) {
+        self.pending += count;
+        self.pending_time += elapsed;
+
+        if self.pending_time >= THROUGHPUT_BUCKET {
+            self.samples[self.next] = self.pending;
+            self.next = (self.next + 1) % THROUGHPUT_SAMPLES;
+            self.len = (self.len + 1).min(THROUGHPUT_SAMPLES);
+            self.pending = 0;
+            self.pending_time -= THROUGHPUT_BUCKET;
+        }
+    }
+
+    /// The recorded samples, from oldest to newest.
+    fn samples(&self) -> impl Iterator<Item = i32> + '_ {
+        let start = (self.next + THROUGHPUT_SAMPLES - self.len) % THROUGHPUT_SAMPLES;
+        (0..self.len).map(move |offset| self.samples[(start + offset) % THROUGHPUT_SAMPLES])
+    }
+
+    /// The total time covered by the window, including the bucket that is currently being filled.
+    fn elapsed(&self) -> Duration {
+        THROUGHPUT_BUCKET * self.len as u32 + self.pending_time
+    }
+
+    /// The average number of items counted each minute, given that `count` items were counted over `elapsed`.
+    fn rate(count: i32, elapsed: Duration) -> f32 {
+        let minutes = elapsed.as_secs_f32() / 60.;
+        if minutes > 0. {
+            count as f32 / minutes
+        } else {
+            0.
+        }
+    }
+
+    /// The average number of items counted each minute.
+    ///
+    /// Until the window has filled up, this is averaged over the time tracked so far.
+    /// The bucket that is currently being filled is included.
+    pub(crate) fn per_minute(&self) -> f32 {
+        Self::rate(self.samples().sum::<i32>() + self.pending, self.elapsed())
+    }
+}
+
+/// The rate at which a structure produces and consumes items, averaged over the last minute.
+#[derive(Component, Debug, Clone, Default, PartialEq)]
+pub(crate) struct FlowStats {
+    /// The items produced during the window.
+    produced: RollingWindow,
+    /// The items consumed during the window.
+    consumed: RollingWindow,
+    /// The total number of items destroyed outright, rather than used up in a recipe.
+    pub(crate) destroyed: u64,
+}
+
+impl FlowStats {
+    /// The average number of items produced each minute.
+    pub(crate) fn produced_per_min(&self) -> f32 {
+        self.produced.per_minute()
+    }
+
+    /// The average number of items consumed each minute.
+    pub(crate) fn consumed_per_min(&self) -> f32 {
+        self.consumed.per_minute()
+    }
+
+    /// The average number of items gained (or lost, if negative) each minute.
+    pub(crate) fn net_per_min(&self) -> f32 {
+        self.produced_per_min() - self.consumed_per_min()
+    }
+
+    /// Counts items that were destroyed outright.
+    ///
+    /// Destroyed items should also be reported as [`ItemConsumed`], so that they show up in the per-minute rates.
+    pub(crate) fn record_destroyed(&mut self, count: u32) {
+        self.destroyed += count as u64;
+    }
+
+    /// Records the items produced and consumed over the last `elapsed` time.
+    fn record(&mut self, elapsed: Duration, produced: u32, consumed: u32) {
+        self.produced.record(elapsed, produced as i32);
+        self.consumed.record(elapsed, consumed as i32);
+    }
+}
+
+impl Display for FlowStats {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{:.1}/min produced, {:.1}/min consumed ({:+.1}/min net)",
+            self.produced_per_min(),
+            self.consumed_per_min(),
+            self.net_per_min()
+        )
+    }
+}
+
+/// The rate at which each item is produced and consumed across all structures, averaged over the last minute.
+#[derive(Resource, Debug, Clone, Default, PartialEq)]
+pub(crate) struct ItemFlowStats {
+    /// The flow of each item that has been produced or consumed at least once.
+    per_item: HashMap<Id<Item>, FlowStats>,
+}
+
+impl ItemFlowStats {
+    /// The flow of `item_id`, or [`None`] if it has never been produced or consumed.
+    pub(crate) fn get(&self, item_id: Id<Item>) -> Option<&FlowStats> {
+        self.per_item.get(&item_id)
+    }
+
+    /// Iterates over the flow of every item that has been produced or consumed, in an arbitrary order.
+    pub(crate) fn iter(&self) -> impl Iterator<Item = (Id<Item>, &FlowStats)> + '_ {
+        self.per_item
+            .iter()
+            .map(|(&item_id, flow_stats)| (item_id, flow_stats))
+    }
+}
+
+/// Is the net flow of items through a structure speeding up or slowing down?
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum FlowTrend {
+    /// More items are being gained each minute than before.
+    Rising,
+    /// The flow of items has not changed much.
+    Steady,
+    /// Fewer items are being gained each minute than before.
+    Falling,
+}
+
+impl Display for FlowTrend {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let label = match self {
+            FlowTrend::Rising => "rising",
+            FlowTrend::Steady => "steady",
+            FlowTrend::Falling => "falling",
+        };
+
+        write!(f, "{label}")
+    }
+}
+
+/// The net number of items gained by a structure over the last minute.
+///
+/// This is cleared whenever the structure is relocated or its recipe changes, as the old rate no longer applies.
+#[derive(Component, Debug, Clone, Default, PartialEq)]
+pub(crate) struct ThroughputTracker {
+    /// The items gained (or lost, if negative) during the window.
+    window: RollingWindow,
+}
+
+impl ThroughputTracker {
+    /// The change in the per-minute rate below which the flow is considered [`FlowTrend::Steady`].
+    const TREND_TOLERANCE: f32 = 1.0;
+
+    /// Records the net number of items gained over a tick of length `elapsed`.
+    pub(crate) fn record(&mut self, elapsed: Duration, net: i32) {
+        self.window.record(elapsed, net);
+    }
+
+    /// Forgets every recorded sample.
+    pub(crate) fn reset(&mut self) {
+        *self = ThroughputTracker::default();
+    }
+
+    /// The average number of items gained (or lost, if negative) each minute over the recorded ticks.
+    pub(crate) fn per_minute(&self) -> f32 {
+        self.window.per_minute()
+    }
+
+    /// Compares the rate over the newer half of the completed buckets to the rate over the older half.
+    pub(crate) fn trend(&self) -> FlowTrend {
+        let len = self.window.len;
+        let half = len / 2;
+        let half_time = THROUGHPUT_BUCKET * half as u32;
+        let older = RollingWindow::rate(self.window.samples().take(half).sum(), half_time);
+        let newer = RollingWindow::rate(self.window.samples().skip(len - half).sum(), half_time);
+
+        if newer - older > Self::TREND_TOLERANCE {
+            FlowTrend::Rising
+        } else if older - newer > Self::TREND_TOLERANCE {
+            FlowTrend::Falling
+        } else {
+            FlowTrend::Steady
+        }
+    }
+}
+
+impl Display for ThroughputTracker {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:+.1}/min net ({})", self.per_minute(), self.trend())
+    }
+}
+
+/// Updates the [`FlowStats`] and [`ThroughputTracker`] of each structure based on the items they produced and consumed this tick.
+///
+/// The [`ItemFlowStats`] of each item are updated in the same way.
+pub(crate) fn record_item_flow(
+    fixed_time: Res<FixedTime>,
+    mut produced_events: EventReader<ItemProduced>,
+    mut consumed_events: EventReader<ItemConsumed>,
+    mut flow_query: Query<(Entity, &mut FlowStats)>,
+    mut throughput_query: Query<(Entity, &mut ThroughputTracker)>,
+    mut item_flow_stats: ResMut<ItemFlowStats>,
+) {
+    let mut produced: HashMap<Entity, u32> = HashMap::new();
+    let mut produced_items: HashMap<Id<Item>, u32> = HashMap::new();
+    for event in produced_events.iter() {
+        *produced.entry(event.structure).or_default() += event.item_count.count;
+        *produced_items.entry(event.item_count.item_id).or_default() += event.item_count.count;
+    }
+
+    let mut consumed: HashMap<Entity, u32> = HashMap::new();
+    let mut consumed_items: HashMap<Id<Item>, u32> = HashMap::new();
+    for event in consumed_events.iter() {
+        *consumed.entry(event.structure).or_default() += event.item_count.count;
+        *consumed_items.entry(event.item_count.item_id).or_default() += event.item_count.count;
+    }
+
+    for &item_id in produced_items.keys().chain(consumed_items.keys()) {
+        item_flow_stats.per_item.entry(item_id).or_default();
+    }
+
+    for (item_id, flow_stats) in item_flow_stats.per_item.iter_mut() {
+        flow_stats.record(
+            fixed_time.period,
+            produced_items.get(item_id).copied().unwrap_or_default(),
+            consumed_items.get(item_id).copied().unwrap_or_default(),
+        );
+    }
+
+    for (entity, mut flow_stats) in flow_query.iter_mut() {
+        flow_stats.record(
+            fixed_time.period,
+            produced.get(&entity).copied().unwrap_or_default(),
+            consumed.get(&entity).copied().unwrap_or_default(),
+        );
+    }
+
+    for (entity, mut throughput_tracker) in throughput_query.iter_mut() {
+        let net = produced.get(&entity).copied().unwrap_or_default() as i32
+            - consumed.get(&entity).copied().unwrap_or_default() as i32;
+        throughput_tracker.record(fixed_time.period, net);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rates_are_averaged_over_the_time_tracked_so_far() {
+        let mut flow_stats = FlowStats::default();
+
+        // 3 items in 30 seconds is 6 items per minute
+        for _ in 0..30 {
+            flow_stats.record(Duration::from_secs(1), 0, 0);
+        }
+        flow_stats.record(Duration::ZERO, 3, 1);
+
+        assert_eq!(flow_stats.produced_per_min(), 6.);
+        assert_eq!(flow_stats.consumed_per_min(), 2.);
+        assert_eq!(flow_stats.net_per_min(), 4.);
+    }
+
+    #[test]
+    fn old_samples_leave_the_window() {
+        let mut flow_stats = FlowStats::default();
+
+        flow_stats.record(Duration::from_secs(1), 60, 0);
+        for _ in 0..THROUGHPUT_SAMPLES {
+            flow_stats.record(THROUGHPUT_BUCKET, 0, 0);
+        }
+
+        assert_eq!(
+            flow_stats.produced.elapsed(),
+            THROUGHPUT_BUCKET * THROUGHPUT_SAMPLES as u32
+        );
+        assert_eq!(flow_stats.produced_per_min(), 0.);
+    }
+
+    /// A tick length that divides evenly into a [`THROUGHPUT_BUCKET`].
+    const TICK: Duration = Duration::from_millis(50);
+
+    /// The number of ticks in a full [`ThroughputTracker`] window.
+    const TICKS_PER_WINDOW: usize = THROUGHPUT_SAMPLES * 20;
+
+    #[test]
+    fn throughput_matches_scripted_absorption() {
+        let mut tracker = ThroughputTracker::default();
+
+        // An absorber that picks up an item every third tick absorbs 400 items per minute
+        for tick_number in 0..TICKS_PER_WINDOW {
+            tracker.record(TICK, (tick_number % 3 == 0) as i32);
+        }
+
+        assert!((tracker.per_minute() - 400.).abs() < 5.);
+        assert_eq!(tracker.trend(), FlowTrend::Steady);
+
+        // Then it starts keeping up with a steady stream of two items every tick
+        for _ in 0..TICKS_PER_WINDOW / 2 {
+            tracker.record(TICK, 2);
+        }
+
+        // Half of the window is still the old pattern
+        assert!((tracker.per_minute() - 1400.).abs() < 15.);
+        assert_eq!(tracker.trend(), FlowTrend::Rising);
+
+        // Releasing items counts against the net flow
+        for _ in 0..TICKS_PER_WINDOW {
+            tracker.record(TICK, -1);
+        }
+
+        assert!((tracker.per_minute() + 1200.).abs() < 5.);
+        assert_eq!(tracker.trend(), FlowTrend::Steady);
+    }
+
+    #[test]
+    fn slow_throughput_is_averaged_over_the_whole_window() {
+        let mut tracker = ThroughputTracker::default();
+
+        // One item every ten seconds is six items per minute
+        for tick_number in 0..TICKS_PER_WINDOW {
+            tracker.record(TICK, (tick_number % 200 == 0) as i32);
+        }
+        assert!((tracker.per_minute() - 6.).abs() < 0.5);
+        assert_eq!(tracker.trend(), FlowTrend::Steady);
+
+        // Between items, the rate barely moves
+        for _ in 0..100 {
+            tracker.record(TICK, 0);
+        }
+        assert!((tracker.per_minute() - 5.).abs() < 0.5);
+
+        // And the next item brings it back up
+        tracker.record(TICK, 1);
+        assert!((tracker.per_minute() - 6.).abs() < 0.5);
+    }
+
+    #[test]
+    fn throughput_trackers_can_be_reset() {
+        let mut tracker = ThroughputTracker::default();
+        assert_eq!(tracker.per_minute(), 0.);
+        assert_eq!(tracker.trend(), FlowTrend::Steady);
+
+        for _ in 0..THROUGHPUT_SAMPLES {
+            tracker.record(Duration::from_secs(1), 1);
+        }
+        tracker.record(Duration::from_secs(1), 0);
+        assert!(tracker.per_minute() > 0.);
+
+        tracker.reset();
+        assert_eq!(tracker, ThroughputTracker::default());
+        assert_eq!(tracker.per_minute(), 0.);
+    }
+}
@@ -1,7 +1,7 @@
 //! Various inventory types used in crafting.
 
 use super::{
-    item_tags::ItemTag,
+    item_tags::{ItemKind, ItemTag},
     recipe::{RecipeData, RecipeInput, RecipeOutput},
 };
 
@@ -9,16 +9,20 @@ use crate::{
     asset_management::manifest::Id,
     items::{
         errors::{AddManyItemsError, AddOneItemError},
-        inventory::Inventory,
+        inventory::{Inventory, InventorySnapshot},
         item_manifest::{Item, ItemManifest},
         slot::ItemSlot,
         ItemCount,
     },
 };
 
-use std::{fmt::Display, time::Duration};
+use std::{
+    fmt::Display,
+    ops::{Deref, DerefMut},
+    time::Duration,
+};
 
-use bevy::prelude::*;
+use bevy::{prelude::*, utils::HashMap};
 use rand::{distributions::Uniform, prelude::Distribution, rngs::ThreadRng};
 use serde::{Deserialize, Serialize};
 
@@ -90,6 +94,18 @@ pub enum InputInventory {
     Tagged {
         /// The required tag to use this recipe.
         tag: ItemTag,
+        /// Items that meet the tag, but are never accepted.
+        #[serde(default)]
+        exclude: Vec<Id<Item>>,
+        /// The currently stored items
+        inventory: Inventory,
+    },
+    /// Accepts any input that matches `accepts`, unless it carries the `refuses` tag
+    Filtered {
+        /// The kind of item accepted, or [`None`] if any item is accepted.
+        accepts: Option<ItemKind>,
+        /// Items with this tag are never accepted, even if they match `accepts`.
+        refuses: Option<ItemTag>,
         /// The currently stored items
         inventory: Inventory,
     },
@@ -114,6 +130,7 @@ impl InputInventory {
         match self {
             InputInventory::Exact { inventory } => inventory,
             InputInventory::Tagged { inventory, .. } => inventory,
+            InputInventory::Filtered { inventory, .. } => inventory,
         }
     }
 
@@ -122,6 +139,7 @@ impl InputInventory {
         match self {
             InputInventory::Exact { inventory } => inventory,
             InputInventory::Tagged { inventory, .. } => inventory,
+            InputInventory::Filtered { inventory, .. } => inventory,
         }
     }
 
@@ -130,6 +148,36 @@ impl InputInventory {
         self.inventory().iter()
     }
 
+    /// Returns a read-only copy of the items currently in this inventory.
+    pub fn snapshot(&self) -> InventorySnapshot {
+        self.inventory().snapshot()
+    }
+
+    /// Returns the total number of items of each kind in this inventory.
+    ///
+    /// Tagged inventories form a single group for their tag, which counts every item they hold.
+    /// See [`Inventory::grouped`] for how exact inventories are grouped.
+    pub fn grouped(&self) -> impl Iterator<Item = (ItemKind, u32)> {
+        let groups: Vec<(ItemKind, u32)> = match self {
+            InputInventory::Exact { inventory } => inventory.grouped().collect(),
+            InputInventory::Tagged { tag, inventory, .. } => {
+                let count = inventory.iter().map(|item_slot| item_slot.count()).sum();
+                vec![(ItemKind::Tag(*tag), count)]
+            }
+            InputInventory::Filtered {
+                accepts: Some(kind),
+                inventory,
+                ..
+            } => {
+                let count = inventory.iter().map(|item_slot| item_slot.count()).sum();
+                vec![(*kind, count)]
+            }
+            InputInventory::Filtered { inventory, .. } => inventory.grouped().collect(),
+        };
+
+        groups.into_iter()
+    }
+
     /// Returns the number of items in this inventory.
     pub fn len(&self) -> usize {
         // PERF: this is slow and lazy
@@ -147,16 +195,49 @@ impl InputInventory {
         item_id: Id<Item>,
         item_manifest: &ItemManifest,
     ) -> bool {
-        match self {
-            InputInventory::Exact { .. } => {
-                self.inventory()
-                    .remaining_space_for_item(item_id, item_manifest)
-                    > 0
+        self.remaining_space_for_item(item_id, item_manifest) > 0
+    }
+
+    /// The number of items with the provided `item_id` that can still be added to this inventory.
+    pub(crate) fn remaining_space_for_item(
+        &self,
+        item_id: Id<Item>,
+        item_manifest: &ItemManifest,
+    ) -> u32 {
+        let accepted = match self {
+            InputInventory::Exact { .. } => true,
+            InputInventory::Tagged { tag, exclude, .. } => {
+                item_manifest.has_tag(item_id, *tag) && !exclude.contains(&item_id)
             }
-            InputInventory::Tagged { tag, inventory } => {
-                item_manifest.has_tag(item_id, *tag)
-                    && inventory.remaining_space_for_item(item_id, item_manifest) > 0
+            InputInventory::Filtered { .. } => self.filter_allows(item_id, item_manifest),
+        };
+
+        if accepted {
+            self.inventory()
+                .remaining_space_for_item(item_id, item_manifest)
+        } else {
+            0
+        }
+    }
+
+    /// Does the filter of this inventory let through items with the provided `item_id`?
+    ///
+    /// Only [`InputInventory::Filtered`] inventories have a filter: all other inventories let everything through.
+    pub(crate) fn filter_allows(&self, item_id: Id<Item>, item_manifest: &ItemManifest) -> bool {
+        match self {
+            InputInventory::Filtered {
+                accepts, refuses, ..
+            } => {
+                let accepted = accepts
+                    .map(|kind| kind.matches(item_id, item_manifest))
+                    .unwrap_or(true);
+                let refused = refuses
+                    .map(|tag| item_manifest.has_tag(item_id, tag))
+                    .unwrap_or(false);
+
+                accepted && !refused
             }
+            _ => true,
         }
     }
 
@@ -166,12 +247,18 @@ impl InputInventory {
         item_count: &ItemCount,
         item_manifest: &ItemManifest,
     ) -> Result<(), AddToInputError> {
-        if let InputInventory::Tagged { tag, .. } = self {
-            if !item_manifest.has_tag(item_count.item_id, *tag) {
+        if let InputInventory::Tagged { tag, exclude, .. } = self {
+            if !item_manifest.has_tag(item_count.item_id, *tag)
+                || exclude.contains(&item_count.item_id)
+            {
                 return Err(AddToInputError::IncorrectItemTags);
             }
         };
 
+        if !self.filter_allows(item_count.item_id, item_manifest) {
+            return Err(AddToInputError::IncorrectItemTags);
+        }
+
         match self
             .inventory_mut()
             .add_item_all_or_nothing(item_count, item_manifest)
@@ -184,23 +271,84 @@ impl InputInventory {
     }
 
     /// Try to remove the items specified by `recipe` from the inventory.
+    ///
+    /// Each of the `catalysts` must be present for anything to be removed, but they are never consumed.
+    ///
+    /// Returns the items that were actually removed.
     pub fn consume_items(
         &mut self,
         recipe_input: &RecipeInput,
+        catalysts: &[ItemKind],
         item_manifest: &ItemManifest,
-    ) -> Result<(), ConsumeInputError> {
+    ) -> Result<Vec<ItemCount>, ConsumeInputError> {
         let inventory = self.inventory_mut();
 
+        if !catalysts
+            .iter()
+            .all(|&catalyst| inventory.contains_kind(catalyst, item_manifest))
+        {
+            return Err(ConsumeInputError::MissingCatalyst);
+        }
+
         match recipe_input {
             RecipeInput::Exact(item_counts) => {
-                match inventory.remove_items_all_or_nothing(item_counts) {
-                    Ok(()) => Ok(()),
-                    Err(_) => Err(ConsumeInputError::NotEnoughItems),
+                if inventory.remove_items_all_or_nothing(item_counts).is_ok() {
+                    return Ok(item_counts.clone());
                 }
+
+                let Some(proposed_removal) =
+                    substitute_missing_items(inventory, item_counts, item_manifest)
+                else {
+                    return Err(ConsumeInputError::NotEnoughItems);
+                };
+
+                // Cannot fail: every item was checked to be available
+                inventory
+                    .remove_items_all_or_nothing(&proposed_removal)
+                    .unwrap();
+                Ok(proposed_removal)
             }
-            RecipeInput::Flexible { tag, count } => {
+            RecipeInput::Fresh {
+                inputs,
+                min_freshness,
+            } => {
+                let is_fresh_slot = |slot: &ItemSlot, item_id: Id<Item>| {
+                    slot.is_for_item(item_id) && slot.freshness() >= *min_freshness
+                };
+
+                // Check that everything is available before removing anything
+                for item_count in inputs {
+                    let fresh_count: u32 = inventory
+                        .iter()
+                        .filter(|slot| is_fresh_slot(slot, item_count.item_id))
+                        .map(|slot| slot.count())
+                        .sum();
+
+                    if fresh_count < item_count.count {
+                        return match inventory.has_count_of_item(item_count) {
+                            true => Err(ConsumeInputError::NotFreshEnough),
+                            false => Err(ConsumeInputError::NotEnoughItems),
+                        };
+                    }
+                }
+
+                for item_count in inputs {
+                    // Cannot fail: we checked that there are enough fresh items above
+                    inventory
+                        .try_remove_item_with_freshness(item_count, *min_freshness)
+                        .unwrap();
+                }
+
+                Ok(inputs.clone())
+            }
+            RecipeInput::Flexible {
+                tag,
+                count,
+                exclude,
+            } => {
                 let mut remaining_to_remove = *count;
                 let mut proposed_removal: Vec<ItemCount> = Vec::new();
+                let mut unreserved_catalysts = catalysts.to_vec();
 
                 for item_slot in inventory.iter() {
                     // Verify that all items in the inventory are correct
@@ -208,8 +356,23 @@ impl InputInventory {
                         return Err(ConsumeInputError::IncorrectItemTags);
                     }
 
+                    // Set aside one item for each catalyst that this stack can supply
+                    let mut n = item_slot.count();
+                    unreserved_catalysts.retain(|catalyst| {
+                        let reserved =
+                            n > 0 && catalyst.matches(item_slot.item_id(), item_manifest);
+                        if reserved {
+                            n -= 1;
+                        }
+                        !reserved
+                    });
+
+                    // Excluded items are left in place, and never drawn on
+                    if exclude.contains(&item_slot.item_id()) {
+                        continue;
+                    }
+
                     // Remove items from the inventory, beginning at the start of the inventory
-                    let n = item_slot.count();
                     let removed_from_this_stack = std::cmp::min(n, remaining_to_remove);
                     proposed_removal.push(ItemCount {
                         item_id: item_slot.item_id(),
@@ -227,7 +390,7 @@ impl InputInventory {
                 }
 
                 match inventory.remove_items_all_or_nothing(&proposed_removal) {
-                    Ok(()) => Ok(()),
+                    Ok(()) => Ok(proposed_removal),
                     Err(_) => panic!("Inventory should have had enough items to remove"),
                 }
             }
@@ -239,6 +402,14 @@ impl InputInventory {
         self.inventory_mut().clear_empty_slots();
     }
 
+    /// Ages the stored items by `elapsed`, destroying any that are fully spoiled.
+    ///
+    /// Spoiled inputs are never converted, as the slots are fixed by the recipe.
+    /// Returns `true` if any items spoiled.
+    pub(crate) fn spoil(&mut self, elapsed: Duration, item_manifest: &ItemManifest) -> bool {
+        self.inventory_mut().discard_spoiled(elapsed, item_manifest)
+    }
+
     /// Randomizes the contents of this inventory so that each slot is somewhere between empty and full.
     ///
     /// Note that this only works for [`InputInventory::Exact`].
@@ -254,11 +425,89 @@ impl InputInventory {
     pub fn display(&self, item_manifest: &ItemManifest) -> String {
         match self {
             InputInventory::Exact { inventory } => inventory.display(item_manifest),
-            InputInventory::Tagged { tag, inventory } => {
+            InputInventory::Tagged { tag, inventory, .. } => {
                 format!("{}: {}", tag, inventory.display(item_manifest))
             }
+            InputInventory::Filtered { inventory, .. } => inventory.display(item_manifest),
+        }
+    }
+}
+
+/// Works out which items to remove from `inventory` to pay for `item_counts`,
+/// making up any shortfall with the [`Substitute`](crate::items::item_manifest::Substitute)s of the missing items.
+///
+/// Only the original inputs are ever substituted: substitutes are never replaced in turn,
+/// so chains of substitutions cannot loop.
+///
+/// Returns [`None`] if the inputs cannot be paid for, even with substitutes.
+fn substitute_missing_items(
+    inventory: &Inventory,
+    item_counts: &[ItemCount],
+    item_manifest: &ItemManifest,
+) -> Option<Vec<ItemCount>> {
+    let mut proposed_removal: Vec<ItemCount> = Vec::new();
+
+    for item_count in item_counts {
+        let mut shortfall = item_count.count;
+        shortfall -= propose_removal(
+            &mut proposed_removal,
+            inventory,
+            item_count.item_id,
+            shortfall,
+            1,
+        );
+
+        for substitute in &item_manifest.get(item_count.item_id).substitutes {
+            if shortfall == 0 {
+                break;
+            }
+
+            shortfall -= propose_removal(
+                &mut proposed_removal,
+                inventory,
+                substitute.item_id,
+                shortfall,
+                substitute.ratio,
+            );
+        }
+
+        if shortfall > 0 {
+            return None;
         }
     }
+
+    Some(proposed_removal)
+}
+
+/// Adds up to `wanted` batches of `ratio` items of `item_id` to `proposed_removal`,
+/// using only the items in `inventory` that have not already been proposed for removal.
+///
+/// Returns the number of batches added.
+fn propose_removal(
+    proposed_removal: &mut Vec<ItemCount>,
+    inventory: &Inventory,
+    item_id: Id<Item>,
+    wanted: u32,
+    ratio: u32,
+) -> u32 {
+    let already_proposed: u32 = proposed_removal
+        .iter()
+        .filter(|item_count| item_count.item_id == item_id)
+        .map(|item_count| item_count.count)
+        .sum();
+    let batches = wanted.min((inventory.item_count(item_id) - already_proposed) / ratio);
+
+    if batches > 0 {
+        match proposed_removal
+            .iter_mut()
+            .find(|item_count| item_count.item_id == item_id)
+        {
+            Some(item_count) => item_count.count += batches * ratio,
+            None => proposed_removal.push(ItemCount::new(item_id, batches * ratio)),
+        }
+    }
+
+    batches
 }
 
 /// An error that can occur when trying to consume items from an [`InputInventory`].
@@ -268,6 +517,10 @@ pub enum ConsumeInputError {
     NotEnoughItems,
     /// The items in the inventory did not match the provided recipe.
     IncorrectItemTags,
+    /// There are enough items in the inventory, but too few of them are fresh enough.
+    NotFreshEnough,
+    /// One of the catalysts needed by the recipe is missing.
+    MissingCatalyst,
 }
 
 /// An error that can occur when trying to add items to an [`InputInventory`].
@@ -302,6 +555,14 @@ impl OutputInventory {
         }
     }
 
+    /// Ages the stored items by `elapsed`, destroying any that are fully spoiled.
+    ///
+    /// Spoiled outputs are never converted, as the slots are fixed by the recipe.
+    /// Returns `true` if any items spoiled.
+    pub(crate) fn spoil(&mut self, elapsed: Duration, item_manifest: &ItemManifest) -> bool {
+        self.inventory.discard_spoiled(elapsed, item_manifest)
+    }
+
     /// Produces the items specified by `recipe` and adds them to the inventory.
     pub(super) fn craft(
         &mut self,
@@ -353,10 +614,29 @@ impl OutputInventory {
 }
 
 /// An inventory that simply stores items
-#[derive(Component, Clone, Debug, Default, Deref, DerefMut)]
+#[derive(Component, Clone, Debug, Default, Serialize, Deserialize)]
 pub(crate) struct StorageInventory {
     /// Inner storage
     pub(crate) inventory: Inventory,
+    /// The number of each item that haulers must leave behind when withdrawing from this storage.
+    ///
+    /// Items without an entry can be withdrawn entirely.
+    #[serde(default)]
+    pub(crate) stock_floor: HashMap<Id<Item>, u32>,
+}
+
+impl Deref for StorageInventory {
+    type Target = Inventory;
+
+    fn deref(&self) -> &Self::Target {
+        &self.inventory
+    }
+}
+
+impl DerefMut for StorageInventory {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.inventory
+    }
 }
 
 impl StorageInventory {
@@ -366,6 +646,49 @@ impl StorageInventory {
     pub(crate) fn new(max_slot_count: usize, reserved_for: Option<Id<Item>>) -> Self {
         StorageInventory {
             inventory: Inventory::new(max_slot_count, reserved_for),
+            stock_floor: HashMap::default(),
+        }
+    }
+
+    /// The number of `item_id` that must be left in this storage.
+    pub(crate) fn stock_floor(&self, item_id: Id<Item>) -> u32 {
+        self.stock_floor.get(&item_id).copied().unwrap_or_default()
+    }
+
+    /// Sets the number of `item_id` that must be left in this storage.
+    ///
+    /// A floor of 0 removes the limit entirely.
+    pub(crate) fn set_stock_floor(&mut self, item_id: Id<Item>, floor: u32) {
+        if floor == 0 {
+            self.stock_floor.remove(&item_id);
+        } else {
+            self.stock_floor.insert(item_id, floor);
+        }
+    }
+
+    /// The number of `item_id` that can be taken out of this storage without dipping below its stock floor.
+    pub(crate) fn withdrawable_count(&self, item_id: Id<Item>) -> u32 {
+        self.item_count(item_id)
+            .saturating_sub(self.stock_floor(item_id))
+    }
+
+    /// Returns the first [`Id<Item>`] that matches the given [`ItemKind`] and can be withdrawn, if any.
+    pub(crate) fn withdrawable_item_id(
+        &self,
+        item_kind: ItemKind,
+        item_manifest: &ItemManifest,
+    ) -> Option<Id<Item>> {
+        match item_kind {
+            ItemKind::Single(item_id) => {
+                Some(item_id).filter(|&item_id| self.withdrawable_count(item_id) > 0)
+            }
+            ItemKind::Tag(tag) => {
+                self.iter()
+                    .map(|item_slot| item_slot.item_id())
+                    .find(|&item_id| {
+                        item_manifest.has_tag(item_id, tag) && self.withdrawable_count(item_id) > 0
+                    })
+            }
         }
     }
 
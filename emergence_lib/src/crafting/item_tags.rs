@@ -1,6 +1,7 @@
 //! Item tags are used to group items together for crafting recipes.
 //!
 //! Items can belong to multiple tags, and correspond to fields on [`ItemData`](crate::items::item_manifest::ItemData).
+//! Tags can also imply other tags, as set out in the [`RawItemManifest::tag_hierarchy`](crate::items::item_manifest::RawItemManifest::tag_hierarchy).
 
 use serde::{Deserialize, Serialize};
 use std::fmt::{Display, Formatter};
@@ -21,6 +22,8 @@ pub enum ItemTag {
     Fluid,
     /// Items that float
     Buoyant,
+    /// Items that break when dropped from a height.
+    Fragile,
 }
 
 impl ItemTag {
@@ -31,6 +34,7 @@ impl ItemTag {
             ItemTag::Seed => "Seed",
             ItemTag::Fluid => "Fluid",
             ItemTag::Buoyant => "Buoyant",
+            ItemTag::Fragile => "Fragile",
         }
     }
 }
@@ -75,3 +79,21 @@ impl ItemKind {
         }
     }
 }
+
+/// The unprocessed equivalent of [`ItemKind`].
+#[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize)]
+pub enum RawItemKind {
+    /// Exactly one type of item, referred to by name.
+    Single(String),
+    /// Any item that matches the given tag.
+    Tag(ItemTag),
+}
+
+impl From<RawItemKind> for ItemKind {
+    fn from(raw: RawItemKind) -> Self {
+        match raw {
+            RawItemKind::Single(item_name) => ItemKind::Single(Id::from_name(item_name)),
+            RawItemKind::Tag(tag) => ItemKind::Tag(tag),
+        }
+    }
+}
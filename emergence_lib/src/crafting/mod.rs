@@ -5,34 +5,69 @@ use recipe::{RawRecipeManifest, RecipeManifest};
 
 use crate::{
     asset_management::manifest::{plugin::ManifestPlugin, Id},
-    construction::{demolition::MarkedForDemolition, ghosts::WorkplaceId},
-    geometry::{MapGeometry, VoxelPos},
+    construction::{
+        demolition::MarkedForDemolition,
+        ghosts::{Ghost, WorkplaceId},
+    },
+    geometry::{Facing, MapGeometry, VoxelPos},
     items::{
         inventory::Inventory,
-        item_manifest::{ItemManifest, RawItemManifest},
+        item_manifest::{Item, ItemManifest, RawItemManifest},
+        totals::{update_resource_totals, ResourceTotals},
+        ItemCount,
     },
     light::shade::ReceivedLight,
-    organisms::{energy::EnergyPool, lifecycle::Lifecycle, Organism},
-    player_interaction::InteractionSystem,
-    signals::{Emitter, SignalStrength, SignalType},
-    simulation::SimulationSet,
-    structures::structure_manifest::{Structure, StructureManifest},
+    litter::{gather_litter, Litter, LitterCommandsExt},
+    organisms::{
+        energy::EnergyPool, lifecycle::Lifecycle, pollination::PollinationState, Organism,
+    },
+    player_interaction::{
+        selection::CurrentSelection, InteractionSystem, PlayerAction, PlayerModifiesWorld,
+    },
+    signals::{Avoidance, Emitter, LogisticsCategory, LogisticsPriorities, SignalType},
+    simulation::{
+        alerts::{AlertCategory, AlertKind, AlertLog},
+        milestones::Milestones,
+        rng::GlobalRng,
+        time::InGameTime,
+        SimulationSet,
+    },
+    structures::{
+        commands::StructureCommandsExt,
+        storage_groups::StorageGroups,
+        structure_manifest::{Structure, StructureKind, StructureManifest},
+        Footprint, StructureActivity,
+    },
+    terrain::fertility::deplete_soil_fertility,
+    units::item_interaction::InFlightItems,
+    water::roots::WaterReceived,
 };
 
 use std::time::Duration;
 
 use bevy::{ecs::query::WorldQuery, prelude::*};
+use hexx::Hex;
+use leafwing_input_manager::prelude::ActionState;
 
 use self::{
+    flow::{
+        record_item_flow, FlowStats, ItemConsumed, ItemFlowStats, ItemProduced, ThroughputTracker,
+    },
     inventories::{CraftingState, InputInventory, OutputInventory, StorageInventory},
     item_tags::{ItemKind, ItemTag},
-    recipe::{ActiveRecipe, RecipeInput},
+    power::{solve_power_grid, PowerGrid},
+    recipe::{ActiveRecipe, Recipe, RecipeData, RecipeInput},
+    speed::{update_craft_speed, CraftSpeed},
     workers::WorkersPresent,
 };
 
+pub(crate) mod flow;
 pub mod inventories;
 pub mod item_tags;
+pub mod power;
 pub mod recipe;
+pub mod recipe_graph;
+pub(crate) mod speed;
 pub mod workers;
 
 /// Add crafting capabilities to structures.
@@ -42,16 +77,55 @@ impl Plugin for CraftingPlugin {
     fn build(&self, app: &mut App) {
         app.add_plugin(ManifestPlugin::<RawItemManifest>::new())
             .add_plugin(ManifestPlugin::<RawRecipeManifest>::new())
+            .add_plugin(CraftingSimulationPlugin)
+            .add_event::<ToggleCraftingPaused>()
+            .add_system(
+                cycle_recipe_presets
+                    .in_set(PlayerModifiesWorld)
+                    .after(InteractionSystem::SelectTiles),
+            )
+            .add_systems(
+                (toggle_selected_crafting_paused, toggle_crafting_paused)
+                    .chain()
+                    .in_set(PlayerModifiesWorld)
+                    .after(InteractionSystem::SelectTiles),
+            );
+    }
+}
+
+/// The crafting systems that run each simulation tick.
+///
+/// Unlike [`CraftingPlugin`], this neither loads manifests nor responds to player input,
+/// so it can be run headlessly with manifests that were built in memory.
+pub(crate) struct CraftingSimulationPlugin;
+
+impl Plugin for CraftingSimulationPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ResourceTotals>()
+            .init_resource::<ItemFlowStats>()
+            .init_resource::<PowerGrid>()
+            .add_event::<ItemProduced>()
+            .add_event::<ItemConsumed>()
+            .add_event::<RecipeChanged>()
             .add_systems(
                 (
+                    detect_recipe_changes.before(progress_crafting),
+                    solve_power_grid.before(update_craft_speed),
+                    update_craft_speed.before(progress_crafting),
                     progress_crafting,
                     gain_energy_when_crafting_completes.after(progress_crafting),
+                    deplete_soil_fertility.after(progress_crafting),
+                    apply_recipe_effects.after(progress_crafting),
                     set_crafting_emitter
                         .after(progress_crafting)
                         // This must run before zoning, to avoid wiping out the destruction signal
                         .before(InteractionSystem::ApplyZoning),
                     set_storage_emitter.before(InteractionSystem::ApplyZoning),
-                    clear_empty_storage_slots,
+                    spoil_items.before(progress_crafting),
+                    clear_empty_storage_slots.after(spoil_items),
+                    update_resource_totals.after(progress_crafting),
+                    record_item_flow.after(progress_crafting),
+                    raise_starved_crafter_alerts.after(progress_crafting),
                 )
                     .in_set(SimulationSet)
                     .in_schedule(CoreSchedule::FixedUpdate),
@@ -59,6 +133,55 @@ impl Plugin for CraftingPlugin {
     }
 }
 
+/// Raises alerts for crafting structures that have been waiting for their inputs for too long,
+/// and resolves them once the inputs arrive.
+///
+/// Organisms are excluded, as they routinely wait on the environment rather than on the player's logistics.
+fn raise_starved_crafter_alerts(
+    crafter_query: Query<
+        (
+            Entity,
+            &CraftingState,
+            &InputInventory,
+            Option<&StructureActivity>,
+        ),
+        (With<Id<Structure>>, Without<Organism>, Without<Ghost>),
+    >,
+    in_game_time: Res<InGameTime>,
+    mut alert_log: ResMut<AlertLog>,
+) {
+    for (entity, crafting_state, input_inventory, maybe_activity) in crafter_query.iter() {
+        let missing = match input_inventory {
+            InputInventory::Exact { inventory } => inventory
+                .iter()
+                .find(|item_slot| !item_slot.is_full())
+                .map(|item_slot| ItemKind::Single(item_slot.item_id())),
+            InputInventory::Tagged { tag, inventory, .. } => {
+                (!inventory.is_full()).then_some(ItemKind::Tag(*tag))
+            }
+            InputInventory::Filtered {
+                accepts, inventory, ..
+            } => accepts.filter(|_| !inventory.is_full()),
+        };
+
+        let starved = *crafting_state == CraftingState::NeedsInput
+            && !StructureActivity::is_paused(maybe_activity)
+            && missing.is_some();
+
+        alert_log.observe(
+            entity,
+            AlertCategory::CrafterStarved,
+            starved,
+            &in_game_time,
+            // Alerts are only raised for starved crafters, which are always missing something
+            |_| AlertKind::CrafterStarved {
+                entity,
+                missing: missing.unwrap(),
+            },
+        );
+    }
+}
+
 /// All components needed to craft stuff.
 #[derive(Debug, Bundle)]
 pub(crate) struct CraftingBundle {
@@ -79,6 +202,20 @@ pub(crate) struct CraftingBundle {
 
     /// The number of workers present / allowed at this structure
     workers_present: WorkersPresent,
+
+    /// How quickly this structure is crafting, and why
+    craft_speed: CraftSpeed,
+
+    /// Tracks how quickly items are being crafted and consumed
+    flow_stats: FlowStats,
+    /// Tracks the recent net flow of items, for display to the player
+    throughput_tracker: ThroughputTracker,
+
+    /// The water drawn up by this structure's roots, if it has any
+    water_received: WaterReceived,
+
+    /// The recipe as of the last time changes were checked for
+    previous_recipe: PreviousRecipe,
 }
 
 impl CraftingBundle {
@@ -90,42 +227,169 @@ impl CraftingBundle {
         item_manifest: &ItemManifest,
         structure_manifest: &StructureManifest,
     ) -> Self {
-        let max_workers = structure_manifest.get(structure_id).max_workers;
-
-        if let Some(recipe_id) = starting_recipe.0 {
-            let recipe = recipe_manifest.get(recipe_id);
-
-            Self {
-                input_inventory: recipe.input_inventory(item_manifest),
-                output_inventory: recipe.output_inventory(item_manifest),
-                active_recipe: ActiveRecipe(Some(recipe_id)),
-                craft_state: CraftingState::NeedsInput,
-                emitter: Emitter::default(),
-                workers_present: WorkersPresent::new(max_workers),
-            }
-        } else {
-            Self {
-                input_inventory: InputInventory::Exact {
+        let structure_data = structure_manifest.get(structure_id);
+        let max_workers = structure_data.max_workers;
+
+        let (mut input_inventory, mut output_inventory) = match starting_recipe.possible_recipes()[..]
+        {
+            [] => (
+                InputInventory::Exact {
                     inventory: Inventory::new(0, None),
                 },
-                output_inventory: OutputInventory {
+                OutputInventory {
                     inventory: Inventory::new(1, None),
                 },
-                active_recipe: ActiveRecipe(None),
-                craft_state: CraftingState::NeedsInput,
-                emitter: Emitter::default(),
-                workers_present: WorkersPresent::new(max_workers),
+            ),
+            [recipe_id] => {
+                let recipe = recipe_manifest.get(recipe_id);
+                (
+                    recipe.input_inventory(item_manifest),
+                    recipe.output_inventory(item_manifest),
+                )
+            }
+            ref recipe_ids => merged_inventories(recipe_ids, recipe_manifest, item_manifest),
+        };
+
+        if let StructureKind::Crafting {
+            input_buffer_slots,
+            output_buffer_slots,
+            ..
+        } = structure_data.kind
+        {
+            if let Some(slot_count) = input_buffer_slots {
+                expand_buffer(input_inventory.inventory_mut(), slot_count, item_manifest);
+            }
+
+            if let Some(slot_count) = output_buffer_slots {
+                expand_buffer(&mut output_inventory.inventory, slot_count, item_manifest);
             }
         }
+
+        Self {
+            input_inventory,
+            output_inventory,
+            previous_recipe: PreviousRecipe(starting_recipe.clone()),
+            active_recipe: starting_recipe,
+            craft_state: CraftingState::NeedsInput,
+            emitter: Emitter::default(),
+            workers_present: WorkersPresent::new(max_workers),
+            craft_speed: CraftSpeed::default(),
+            flow_stats: FlowStats::default(),
+            throughput_tracker: ThroughputTracker::default(),
+            water_received: WaterReceived::default(),
+        }
     }
 }
 
+/// Grows `inventory` to `slot_count` slots, giving the extra slots to the items it already holds in turn.
+///
+/// Catalysts only ever need a single item, so they are never given extra slots.
+/// Inventories that need more slots than `slot_count` are left as they are.
+fn expand_buffer(inventory: &mut Inventory, slot_count: usize, item_manifest: &ItemManifest) {
+    let buffered_items: Vec<(Id<Item>, u32)> = inventory
+        .iter()
+        .filter(|item_slot| {
+            item_slot.max_item_count() == item_manifest.get(item_slot.item_id()).stack_size
+        })
+        .map(|item_slot| (item_slot.item_id(), item_slot.max_item_count()))
+        .collect();
+
+    let missing_slots = slot_count.saturating_sub(inventory.iter().count());
+    inventory.expand_to(slot_count);
+
+    for &(item_id, capacity) in buffered_items.iter().cycle().take(missing_slots) {
+        inventory.add_empty_slot_with_capacity(item_id, capacity, item_manifest);
+    }
+}
+
+/// Combines the inventories needed by each of `recipe_ids`,
+/// so that a crafter that picks its recipe at random can hold the items for any of them.
+///
+/// Recipes with flexible inputs share a single inventory for those inputs, sized for the largest of them.
+/// The `recipe_ids` must be able to share their inputs: see [`RecipeManifest::can_share_inputs`],
+/// which is checked when the structure manifest is loaded.
+fn merged_inventories(
+    recipe_ids: &[Id<Recipe>],
+    recipe_manifest: &RecipeManifest,
+    item_manifest: &ItemManifest,
+) -> (InputInventory, OutputInventory) {
+    /// Adds a slot for each item in `inventory` that is not yet in `slots`.
+    fn merge_slots(slots: &mut Vec<(Id<Item>, u32)>, inventory: &Inventory) {
+        for item_slot in inventory.iter() {
+            match slots
+                .iter_mut()
+                .find(|(item_id, _)| *item_id == item_slot.item_id())
+            {
+                Some((_, capacity)) => *capacity = (*capacity).max(item_slot.max_item_count()),
+                None => slots.push((item_slot.item_id(), item_slot.max_item_count())),
+            }
+        }
+    }
+
+    /// Creates an inventory with exactly the provided `slots`.
+    fn inventory_from_slots(
+        slots: Vec<(Id<Item>, u32)>,
+        item_manifest: &ItemManifest,
+    ) -> Inventory {
+        let mut inventory = Inventory::new(slots.len(), None);
+        for (item_id, capacity) in slots {
+            inventory.add_empty_slot_with_capacity(item_id, capacity, item_manifest);
+        }
+        inventory
+    }
+
+    let mut input_slots = Vec::new();
+    let mut flexible_inputs: Option<(ItemTag, Vec<Id<Item>>, usize)> = None;
+    let mut output_slots = Vec::new();
+    for &recipe_id in recipe_ids {
+        let recipe = recipe_manifest.get(recipe_id);
+        match recipe.input_inventory(item_manifest) {
+            InputInventory::Exact { inventory } => merge_slots(&mut input_slots, &inventory),
+            InputInventory::Tagged {
+                tag,
+                exclude,
+                inventory,
+            } => {
+                let slot_count = inventory.max_slot_count();
+                match flexible_inputs {
+                    Some((_, _, ref mut max_slot_count)) => {
+                        *max_slot_count = (*max_slot_count).max(slot_count)
+                    }
+                    None => flexible_inputs = Some((tag, exclude, slot_count)),
+                }
+            }
+            InputInventory::Filtered { .. } => (),
+        }
+        merge_slots(&mut output_slots, &recipe.output_inventory(item_manifest));
+    }
+
+    let input_inventory = match flexible_inputs {
+        Some((tag, exclude, slot_count)) => InputInventory::Tagged {
+            tag,
+            exclude,
+            inventory: Inventory::new(slot_count, None),
+        },
+        None => InputInventory::Exact {
+            inventory: inventory_from_slots(input_slots, item_manifest),
+        },
+    };
+
+    (
+        input_inventory,
+        OutputInventory {
+            inventory: inventory_from_slots(output_slots, item_manifest),
+        },
+    )
+}
+
 /// Data needed for [`progress_crafting`].
 #[derive(WorldQuery)]
 #[world_query(mutable)]
-struct CraftingQuery {
+pub(crate) struct CraftingQuery {
+    /// The crafting structure
+    entity: Entity,
     /// The recipe of the crafter
-    active_recipe: &'static ActiveRecipe,
+    active_recipe: &'static mut ActiveRecipe,
     /// The status of crafting
     state: &'static mut CraftingState,
     /// The inputs
@@ -134,35 +398,102 @@ struct CraftingQuery {
     output: &'static mut OutputInventory,
     /// The number of workers present
     workers_present: &'static WorkersPresent,
+    /// How quickly the crafter is working
+    craft_speed: &'static CraftSpeed,
     /// The current position of the crafter
     voxel_pos: &'static VoxelPos,
+    /// The direction the crafter is facing
+    maybe_facing: Option<&'static Facing>,
+    /// The tiles covered by the crafter
+    maybe_footprint: Option<&'static Footprint>,
     /// Is the structure an organism?
     maybe_organism: Option<&'static Organism>,
+    /// Does this organism need to be pollinated to set seed?
+    maybe_pollination: Option<&'static PollinationState>,
+    /// Has the crafter been paused?
+    maybe_activity: Option<&'static StructureActivity>,
+    /// Has crafting been paused at this crafter?
+    maybe_crafting_paused: Option<&'static CraftingPaused>,
 }
 
 /// Progress the state of recipes that are being crafted.
-fn progress_crafting(
+pub(crate) fn progress_crafting(
     time: Res<FixedTime>,
     recipe_manifest: Res<RecipeManifest>,
     item_manifest: Res<ItemManifest>,
     terrain_query: Query<&ReceivedLight>,
     mut crafting_query: Query<CraftingQuery>,
+    mut litter_query: Query<&mut Litter>,
     map_geometry: Res<MapGeometry>,
+    mut produced_events: EventWriter<ItemProduced>,
+    mut consumed_events: EventWriter<ItemConsumed>,
+    mut global_rng: ResMut<GlobalRng>,
 ) {
     let rng = &mut rand::thread_rng();
 
     for mut crafter in crafting_query.iter_mut() {
+        // Paused crafters keep their progress, but make no more
+        if StructureActivity::is_paused(crafter.maybe_activity)
+            || crafter.maybe_crafting_paused.is_some()
+        {
+            continue;
+        }
+
         *crafter.state = match *crafter.state {
-            CraftingState::NoRecipe => match crafter.active_recipe.recipe_id() {
-                Some(_) => CraftingState::NeedsInput,
-                None => CraftingState::NoRecipe,
-            },
+            CraftingState::NoRecipe => {
+                // Random crafters may have rolled nothing at all, so they try again
+                if crafter.active_recipe.is_random() {
+                    crafter.active_recipe.reroll(global_rng.get_mut());
+                }
+
+                match crafter.active_recipe.recipe_id() {
+                    Some(_) => CraftingState::NeedsInput,
+                    None => CraftingState::NoRecipe,
+                }
+            }
             CraftingState::NeedsInput | CraftingState::Overproduction => {
                 if let Some(recipe_id) = crafter.active_recipe.recipe_id() {
                     let recipe = recipe_manifest.get(*recipe_id);
+
+                    // Hauled inputs are used up first
+                    if recipe.draw_from_litter && crafter.input.inventory().is_empty() {
+                        let mut tiles: Vec<Hex> =
+                            match (crafter.maybe_footprint, crafter.maybe_facing) {
+                                (Some(footprint), Some(&facing)) => footprint
+                                    .normalized(facing, *crafter.voxel_pos)
+                                    .into_iter()
+                                    .map(|voxel_pos| voxel_pos.hex)
+                                    .collect(),
+                                _ => vec![crafter.voxel_pos.hex],
+                            };
+                        // Drain the tiles in a fixed order, so the same litter is always taken first
+                        tiles.sort_by_key(|hex| (hex.x, hex.y));
+                        tiles.dedup();
+
+                        draw_inputs_from_litter(
+                            recipe,
+                            &tiles,
+                            &mut crafter.input,
+                            &mut litter_query,
+                            &map_geometry,
+                            &item_manifest,
+                        );
+                    }
+
                     // Check if we have enough items, and if so, start crafting
-                    match crafter.input.consume_items(&recipe.inputs, &item_manifest) {
-                        Ok(()) => {
+                    match crafter.input.consume_items(
+                        &recipe.inputs,
+                        &recipe.catalysts,
+                        &item_manifest,
+                    ) {
+                        Ok(consumed) => {
+                            consumed_events.send_batch(consumed.into_iter().map(|item_count| {
+                                ItemConsumed {
+                                    structure: crafter.entity,
+                                    item_count,
+                                }
+                            }));
+
                             // If this is crafting with flexible inputs, clear the input slots
                             if matches!(recipe.inputs, RecipeInput::Flexible { .. }) {
                                 crafter.input.clear_empty_slots();
@@ -189,16 +520,7 @@ fn progress_crafting(
 
                     // Check if we can make progress
                     if recipe.satisfied(crafter.workers_present.current(), received_light) {
-                        // Many hands make light work!
-                        if recipe.workers_required() > 0 {
-                            updated_progress += Duration::from_secs_f32(
-                                time.period.as_secs_f32()
-                                    * crafter.workers_present.effective_workers()
-                                    / recipe.workers_required() as f32,
-                            );
-                        } else {
-                            updated_progress += time.period;
-                        }
+                        updated_progress += crafter.craft_speed.progress(time.period);
 
                         if updated_progress >= required {
                             CraftingState::RecipeComplete
@@ -217,9 +539,29 @@ fn progress_crafting(
             }
             CraftingState::RecipeComplete => {
                 if let Some(recipe_id) = crafter.active_recipe.recipe_id() {
-                    let recipe = recipe_manifest.get(*recipe_id);
+                    let mut recipe = recipe_manifest.get(*recipe_id);
+
+                    // Organisms that have not been pollinated still grow, but set no seed
+                    let seedless_recipe;
+                    if crafter
+                        .maybe_pollination
+                        .map_or(false, |pollination_state| !pollination_state.can_set_seed())
+                    {
+                        seedless_recipe = RecipeData {
+                            outputs: recipe.outputs.without_seeds(&item_manifest),
+                            ..recipe.clone()
+                        };
+                        recipe = &seedless_recipe;
+                    }
+
+                    let output_ids = recipe.outputs.item_ids();
+                    let counts_before: Vec<u32> = output_ids
+                        .iter()
+                        .map(|&item_id| crafter.output.item_count(item_id))
+                        .collect();
+
                     // Actually produce the items
-                    match crafter.maybe_organism {
+                    let new_state = match crafter.maybe_organism {
                         Some(_) => {
                             match crafter.output.craft(recipe, &item_manifest, rng) {
                                 Ok(_) => CraftingState::NeedsInput,
@@ -231,7 +573,25 @@ fn progress_crafting(
                             Ok(()) => CraftingState::NeedsInput,
                             Err(_) => CraftingState::FullAndBlocked,
                         },
+                    };
+
+                    // Even a blocked crafter may have produced some of its outputs
+                    for (&item_id, count_before) in output_ids.iter().zip(counts_before) {
+                        let count = crafter.output.item_count(item_id) - count_before;
+                        if count > 0 {
+                            produced_events.send(ItemProduced {
+                                structure: crafter.entity,
+                                item_count: ItemCount::new(item_id, count),
+                            });
+                        }
+                    }
+
+                    // Each completion rolls the recipe that will be crafted next
+                    if crafter.active_recipe.is_random() {
+                        crafter.active_recipe.reroll(global_rng.get_mut());
                     }
+
+                    new_state
                 } else {
                     CraftingState::NoRecipe
                 }
@@ -247,6 +607,233 @@ fn progress_crafting(
     }
 }
 
+/// Moves the inputs of `recipe` from the litter on the `tiles` covered by a crafter into its `input` inventory.
+///
+/// Items are only taken once every input can be found, so litter is never taken for a craft that can't start.
+/// Only recipes with exact inputs can draw from litter: flexible inputs must still be hauled in.
+///
+/// Returns `true` if the inputs were taken.
+fn draw_inputs_from_litter(
+    recipe: &RecipeData,
+    tiles: &[Hex],
+    input: &mut InputInventory,
+    litter_query: &mut Query<&mut Litter>,
+    map_geometry: &MapGeometry,
+    item_manifest: &ItemManifest,
+) -> bool {
+    let (RecipeInput::Exact(inputs) | RecipeInput::Fresh { inputs, .. }) = &recipe.inputs else {
+        return false;
+    };
+
+    // The input inventory was empty, and has room for a full batch of inputs
+    gather_litter(
+        inputs,
+        tiles.iter().copied(),
+        litter_query,
+        input.inventory_mut(),
+        map_geometry,
+        item_manifest,
+    )
+}
+
+/// Marks a crafting structure whose crafting has been paused by the player.
+///
+/// Unlike a [`StructureActivity::Paused`] structure, inputs are still delivered:
+/// only crafting progress is frozen, and it picks up where it left off once resumed.
+#[derive(Component, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CraftingPaused;
+
+/// An event sent whenever the [`ActiveRecipe`] of a crafting structure changes.
+///
+/// Rerolling a random recipe is not a change: the structure is still following the same random preset.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct RecipeChanged {
+    /// The crafting structure whose recipe changed.
+    pub(crate) structure: Entity,
+    /// The recipe that was set before.
+    pub(crate) old: ActiveRecipe,
+    /// The recipe that is set now.
+    pub(crate) new: ActiveRecipe,
+}
+
+/// The [`ActiveRecipe`] of a crafting structure as of the last time [`detect_recipe_changes`] ran.
+#[derive(Component, Debug, Clone, PartialEq)]
+struct PreviousRecipe(ActiveRecipe);
+
+/// Sends a [`RecipeChanged`] event whenever the [`ActiveRecipe`] of a crafting structure is set to a new value.
+///
+/// Any progress made on the old recipe is discarded, and the structure must gather the inputs for the new one.
+/// The recorded throughput is cleared too, as it describes the old recipe.
+fn detect_recipe_changes(
+    mut crafting_query: Query<(
+        Entity,
+        Ref<ActiveRecipe>,
+        &mut PreviousRecipe,
+        &mut CraftingState,
+        Option<&mut ThroughputTracker>,
+    )>,
+    mut recipe_changed_events: EventWriter<RecipeChanged>,
+) {
+    for (structure, active_recipe, mut previous_recipe, mut crafting_state, maybe_throughput) in
+        crafting_query.iter_mut()
+    {
+        if !active_recipe.is_changed() {
+            continue;
+        }
+
+        // Random recipes compare equal regardless of what they rolled,
+        // so rerolling after each completion keeps the progress and throughput of the preset
+        if *active_recipe == previous_recipe.0 {
+            previous_recipe.bypass_change_detection().0 = active_recipe.clone();
+            continue;
+        }
+
+        let old = std::mem::replace(&mut previous_recipe.0, active_recipe.clone());
+
+        if matches!(*crafting_state, CraftingState::InProgress { .. }) {
+            *crafting_state = match active_recipe.recipe_id() {
+                Some(_) => CraftingState::NeedsInput,
+                None => CraftingState::NoRecipe,
+            };
+        }
+
+        if let Some(mut throughput_tracker) = maybe_throughput {
+            throughput_tracker.reset();
+        }
+
+        recipe_changed_events.send(RecipeChanged {
+            structure,
+            old,
+            new: active_recipe.clone(),
+        });
+    }
+}
+
+/// An event that pauses or resumes crafting at a single structure, by toggling its [`CraftingPaused`] marker.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct ToggleCraftingPaused {
+    /// The crafting structure to pause or resume.
+    pub(crate) structure: Entity,
+}
+
+/// Sends a [`ToggleCraftingPaused`] event for each selected structure when the player asks for it.
+fn toggle_selected_crafting_paused(
+    player_actions: Res<ActionState<PlayerAction>>,
+    current_selection: Res<CurrentSelection>,
+    mut toggle_events: EventWriter<ToggleCraftingPaused>,
+) {
+    if !player_actions.just_pressed(PlayerAction::ToggleCraftingPaused) {
+        return;
+    }
+
+    toggle_events.send_batch(
+        current_selection
+            .selected_structures()
+            .into_iter()
+            .map(|structure| ToggleCraftingPaused { structure }),
+    );
+}
+
+/// Pauses or resumes crafting in response to [`ToggleCraftingPaused`] events.
+///
+/// Structures that do not craft are ignored.
+fn toggle_crafting_paused(
+    mut toggle_events: EventReader<ToggleCraftingPaused>,
+    crafter_query: Query<Option<&CraftingPaused>, With<CraftingState>>,
+    mut commands: Commands,
+) {
+    for event in toggle_events.iter() {
+        match crafter_query.get(event.structure) {
+            Ok(Some(_)) => {
+                commands.entity(event.structure).remove::<CraftingPaused>();
+            }
+            Ok(None) => {
+                commands.entity(event.structure).insert(CraftingPaused);
+            }
+            Err(_) => (),
+        }
+    }
+}
+
+/// Switches the selected structures to their next recipe preset.
+///
+/// Any items held for the previous recipe are dropped as litter, and crafting starts over.
+fn cycle_recipe_presets(
+    player_actions: Res<ActionState<PlayerAction>>,
+    current_selection: Res<CurrentSelection>,
+    mut crafting_query: Query<(
+        &Id<Structure>,
+        &VoxelPos,
+        &mut ActiveRecipe,
+        &mut InputInventory,
+        &mut OutputInventory,
+        &mut CraftingState,
+    )>,
+    structure_manifest: Res<StructureManifest>,
+    recipe_manifest: Res<RecipeManifest>,
+    item_manifest: Res<ItemManifest>,
+    milestones: Res<Milestones>,
+    mut commands: Commands,
+) {
+    if !player_actions.just_pressed(PlayerAction::CycleRecipePreset) {
+        return;
+    }
+
+    for structure_entity in current_selection.selected_structures() {
+        let (
+            &structure_id,
+            &voxel_pos,
+            mut active_recipe,
+            mut input_inventory,
+            mut output_inventory,
+            mut crafting_state,
+        ) = match crafting_query.get_mut(structure_entity) {
+            Ok(query_item) => query_item,
+            // Only crafting structures have recipes to cycle through
+            Err(_) => continue,
+        };
+
+        let structure_data = structure_manifest.get(structure_id);
+        // Presets whose recipe is still locked behind a milestone are skipped
+        let mut candidate = active_recipe.clone();
+        let mut maybe_next_recipe = None;
+        for _ in 0..structure_data.recipe_presets.len() {
+            let Some(preset) = structure_data.next_recipe_preset(&candidate) else {
+                break;
+            };
+            if milestones.recipe_unlocked(preset, &recipe_manifest) {
+                maybe_next_recipe = Some(preset);
+                break;
+            }
+            candidate = preset.clone();
+        }
+        let Some(next_recipe) = maybe_next_recipe else { continue };
+
+        if *active_recipe == *next_recipe {
+            continue;
+        }
+
+        for item_slot in input_inventory.iter().chain(output_inventory.iter()) {
+            for _ in 0..item_slot.count() {
+                commands.spawn_litter(voxel_pos, item_slot.item_id());
+            }
+        }
+
+        let fresh_bundle = CraftingBundle::new(
+            structure_id,
+            next_recipe.clone(),
+            &recipe_manifest,
+            &item_manifest,
+            &structure_manifest,
+        );
+
+        *active_recipe = fresh_bundle.active_recipe;
+        *input_inventory = fresh_bundle.input_inventory;
+        *output_inventory = fresh_bundle.output_inventory;
+        *crafting_state = fresh_bundle.craft_state;
+    }
+}
+
 /// Sessile organisms gain energy when they finish crafting recipes.
 fn gain_energy_when_crafting_completes(
     mut sessile_query: Query<(
@@ -272,10 +859,29 @@ fn gain_energy_when_crafting_completes(
     }
 }
 
+/// Applies the [`RecipeEffect`](recipe::RecipeEffect)s of recipes to the world when crafting completes.
+fn apply_recipe_effects(
+    crafting_query: Query<(Entity, &CraftingState, &ActiveRecipe)>,
+    recipe_manifest: Res<RecipeManifest>,
+    mut commands: Commands,
+) {
+    for (structure_entity, crafting_state, active_recipe) in crafting_query.iter() {
+        if matches!(crafting_state, CraftingState::RecipeComplete) {
+            if let Some(recipe_id) = active_recipe.recipe_id() {
+                let recipe = recipe_manifest.get(*recipe_id);
+                for &effect in recipe.effects.iter() {
+                    commands.apply_recipe_effect(structure_entity, effect);
+                }
+            }
+        }
+    }
+}
+
 /// Causes crafting structures to emit signals based on the items they have and need.
 pub(crate) fn set_crafting_emitter(
     mut crafting_query: Query<
         (
+            Entity,
             &mut Emitter,
             &InputInventory,
             &OutputInventory,
@@ -283,13 +889,18 @@ pub(crate) fn set_crafting_emitter(
             &Id<Structure>,
             &WorkersPresent,
             &ActiveRecipe,
+            Option<&StructureActivity>,
+            Option<&CraftingPaused>,
         ),
         Without<MarkedForDemolition>,
     >,
     recipe_manifest: Res<RecipeManifest>,
+    priorities: Res<LogisticsPriorities>,
     item_manifest: Res<ItemManifest>,
+    in_flight_items: Res<InFlightItems>,
 ) {
     for (
+        crafter_entity,
         mut emitter,
         input_inventory,
         output_inventory,
@@ -297,13 +908,25 @@ pub(crate) fn set_crafting_emitter(
         &structure_id,
         workers_present,
         active_recipe,
+        maybe_activity,
+        maybe_crafting_paused,
     ) in crafting_query.iter_mut()
     {
         // Reset and recompute all signals
         emitter.signals.clear();
 
+        if StructureActivity::is_paused(maybe_activity) {
+            continue;
+        }
+
+        // Recipes that draw their inputs from litter don't need them hauled in
+        let draws_from_litter = active_recipe.recipe_id().map_or(false, |recipe_id| {
+            recipe_manifest.get(recipe_id).draw_from_litter
+        });
+
         // Input signals
         match input_inventory {
+            _ if draws_from_litter => {}
             InputInventory::Exact { inventory } => {
                 for item_slot in inventory.iter() {
                     let item_id = item_slot.item_id();
@@ -312,41 +935,66 @@ pub(crate) fn set_crafting_emitter(
                         continue;
                     }
 
-                    if !item_slot.is_full() {
+                    // Items that are already being carried here shouldn't attract more haulers
+                    let remaining_space = item_slot.remaining_space();
+                    if in_flight_items.claimed(crafter_entity, item_id) < remaining_space {
                         let signal_type = SignalType::Pull(ItemKind::Single(item_id));
-                        let signal_strength = SignalStrength::new(10.);
+                        let signal_strength =
+                            priorities.signal_strength(LogisticsCategory::Crafting, 10.);
                         emitter.signals.push((signal_type, signal_strength));
                     }
                 }
             }
-            InputInventory::Tagged { tag, inventory } => {
+            InputInventory::Tagged {
+                tag,
+                exclude,
+                inventory,
+            } => {
                 if !inventory.is_full() {
-                    let signal_type = SignalType::Pull(ItemKind::Tag(*tag));
-                    let signal_strength = SignalStrength::new(10.);
-                    emitter.signals.push((signal_type, signal_strength));
+                    let signal_strength =
+                        priorities.signal_strength(LogisticsCategory::Crafting, 10.);
+
+                    // A pull for the whole tag would attract excluded items too, so pull for each usable item instead
+                    if exclude.is_empty() {
+                        let signal_type = SignalType::Pull(ItemKind::Tag(*tag));
+                        emitter.signals.push((signal_type, signal_strength));
+                    } else {
+                        for item_id in item_manifest.variants() {
+                            if item_manifest.has_tag(item_id, *tag)
+                                && !exclude.contains(&item_id)
+                                && !item_manifest.has_tag(item_id, ItemTag::Fluid)
+                            {
+                                let signal_type = SignalType::Pull(ItemKind::Single(item_id));
+                                emitter.signals.push((signal_type, signal_strength));
+                            }
+                        }
+                    }
                 }
             }
+            // Filtered inventories belong to incinerators, which send their own signals
+            InputInventory::Filtered { .. } => {}
         }
 
         // Output signals
         for item_slot in output_inventory.iter() {
             if item_slot.is_full() {
                 let signal_type = SignalType::Push(ItemKind::Single(item_slot.item_id()));
-                let signal_strength = SignalStrength::new(10.);
+                let signal_strength = priorities.signal_strength(LogisticsCategory::Crafting, 10.);
                 emitter.signals.push((signal_type, signal_strength));
             } else if !item_slot.is_empty() {
                 let signal_type = SignalType::Contains(ItemKind::Single(item_slot.item_id()));
-                let signal_strength = SignalStrength::new(10.);
+                let signal_strength = priorities.signal_strength(LogisticsCategory::Crafting, 10.);
                 emitter.signals.push((signal_type, signal_strength));
             }
         }
 
-        // Work signals
-        if let CraftingState::InProgress { .. } = crafting_state {
+        // Work signals: paused crafting makes no progress, so there is no work to be done
+        if let (CraftingState::InProgress { .. }, None) = (crafting_state, maybe_crafting_paused) {
             if let Some(recipe_id) = active_recipe.recipe_id() {
                 let recipe = recipe_manifest.get(*recipe_id);
                 if workers_present.needs_more() && recipe.needs_workers() {
-                    let signal_strength = SignalStrength::new(100.);
+                    let signal_strength =
+                        priorities.signal_strength(LogisticsCategory::Crafting, 100.);
                     emitter.signals.push((
                         SignalType::Work(WorkplaceId::structure(structure_id)),
                         signal_strength,
@@ -358,28 +1006,70 @@ pub(crate) fn set_crafting_emitter(
 }
 
 /// Causes storage structures to emit signals based on the items they have and accept.
+///
+/// Members of a [`StorageGroup`](crate::structures::storage_groups::StorageGroup) advertise the space left in the whole group,
+/// as deliveries overflow into the other members.
 pub(crate) fn set_storage_emitter(
-    mut crafting_query: Query<(&mut Emitter, &StorageInventory), With<Id<Structure>>>,
+    mut crafting_query: Query<
+        (
+            Entity,
+            &mut Emitter,
+            &StorageInventory,
+            Option<&StructureActivity>,
+        ),
+        With<Id<Structure>>,
+    >,
+    storage_inventory_query: Query<&StorageInventory>,
+    storage_groups: Res<StorageGroups>,
+    priorities: Res<LogisticsPriorities>,
     item_manifest: Res<ItemManifest>,
+    in_flight_items: Res<InFlightItems>,
 ) {
-    for (mut emitter, storage_inventory) in crafting_query.iter_mut() {
+    for (storage_entity, mut emitter, storage_inventory, maybe_activity) in
+        crafting_query.iter_mut()
+    {
         // Reset and recompute all signals
         emitter.signals.clear();
 
+        if StructureActivity::is_paused(maybe_activity) {
+            continue;
+        }
+
+        // Items that are already being carried to this group shouldn't attract more haulers
+        let has_space_for = |item_id| {
+            let remaining_space = storage_groups.remaining_space_for_item(
+                storage_entity,
+                item_id,
+                &item_manifest,
+                |entity| storage_inventory_query.get(entity).ok(),
+            );
+            let claimed: u32 = storage_groups
+                .deposit_order(storage_entity)
+                .into_iter()
+                .map(|entity| in_flight_items.claimed(entity, item_id))
+                .sum();
+
+            claimed < remaining_space
+        };
+
         match storage_inventory.reserved_for() {
             // Item-specific storage
             Some(item_id) => {
                 // If there's space, signal that
-                if storage_inventory.remaining_space_for_item(item_id, &item_manifest) > 0 {
-                    let signal_type = SignalType::Stores(ItemKind::Single(item_id));
-                    let signal_strength = SignalStrength::new(10.);
-                    emitter.signals.push((signal_type, signal_strength));
-                }
+                // Otherwise, ask haulers to take their deliveries elsewhere
+                let signal_type = if has_space_for(item_id) {
+                    SignalType::Stores(ItemKind::Single(item_id))
+                } else {
+                    SignalType::Avoid(Avoidance::Item(ItemKind::Single(item_id)))
+                };
+                let signal_strength = priorities.signal_strength(LogisticsCategory::Storage, 10.);
+                emitter.signals.push((signal_type, signal_strength));
 
-                // If there's any inventory, signal that
-                if storage_inventory.item_count(item_id) > 0 {
+                // If there's any inventory above the stock floor, signal that
+                if storage_inventory.withdrawable_count(item_id) > 0 {
                     let signal_type = SignalType::Contains(ItemKind::Single(item_id));
-                    let signal_strength = SignalStrength::new(10.);
+                    let signal_strength =
+                        priorities.signal_strength(LogisticsCategory::Storage, 10.);
                     emitter.signals.push((signal_type, signal_strength));
                 }
             }
@@ -388,16 +1078,21 @@ pub(crate) fn set_storage_emitter(
                 // You could put anything in here!
                 for item_id in item_manifest.variants() {
                     // If there's space, signal that
-                    if storage_inventory.remaining_space_for_item(item_id, &item_manifest) > 0 {
-                        let signal_type = SignalType::Stores(ItemKind::Single(item_id));
-                        let signal_strength = SignalStrength::new(10.);
-                        emitter.signals.push((signal_type, signal_strength));
-                    }
+                    // Otherwise, ask haulers to take their deliveries elsewhere
+                    let signal_type = if has_space_for(item_id) {
+                        SignalType::Stores(ItemKind::Single(item_id))
+                    } else {
+                        SignalType::Avoid(Avoidance::Item(ItemKind::Single(item_id)))
+                    };
+                    let signal_strength =
+                        priorities.signal_strength(LogisticsCategory::Storage, 10.);
+                    emitter.signals.push((signal_type, signal_strength));
 
-                    // If there's any inventory, signal that
-                    if storage_inventory.item_count(item_id) > 0 {
+                    // If there's any inventory above the stock floor, signal that
+                    if storage_inventory.withdrawable_count(item_id) > 0 {
                         let signal_type = SignalType::Contains(ItemKind::Single(item_id));
-                        let signal_strength = SignalStrength::new(10.);
+                        let signal_strength =
+                            priorities.signal_strength(LogisticsCategory::Storage, 10.);
                         emitter.signals.push((signal_type, signal_strength));
                     }
                 }
@@ -412,3 +1107,1405 @@ fn clear_empty_storage_slots(mut query: Query<&mut StorageInventory>) {
         storage_inventory.clear_empty_slots();
     }
 }
+
+/// Ages all perishable items stored in inventories and litter, spoiling them once their shelf life runs out.
+fn spoil_items(
+    fixed_time: Res<FixedTime>,
+    item_manifest: Res<ItemManifest>,
+    mut input_query: Query<&mut InputInventory>,
+    mut output_query: Query<&mut OutputInventory>,
+    mut storage_query: Query<(&VoxelPos, &mut StorageInventory)>,
+    mut litter_query: Query<(&VoxelPos, &mut Litter)>,
+    mut commands: Commands,
+) {
+    let elapsed = fixed_time.period;
+
+    // Freshness changes every tick, so change detection is only triggered when items actually spoil
+    for mut input in input_query.iter_mut() {
+        if input
+            .bypass_change_detection()
+            .spoil(elapsed, &item_manifest)
+        {
+            input.set_changed();
+        }
+    }
+
+    for mut output in output_query.iter_mut() {
+        if output
+            .bypass_change_detection()
+            .spoil(elapsed, &item_manifest)
+        {
+            output.set_changed();
+        }
+    }
+
+    for (&voxel_pos, mut storage) in storage_query.iter_mut() {
+        let spoilage = storage
+            .bypass_change_detection()
+            .spoil(elapsed, &item_manifest);
+        if spoilage.any_spoiled {
+            storage.set_changed();
+        }
+        spill_overflow(&mut commands, voxel_pos, spoilage.overflow);
+    }
+
+    for (&voxel_pos, mut litter) in litter_query.iter_mut() {
+        let spoilage = litter
+            .bypass_change_detection()
+            .contents
+            .spoil(elapsed, &item_manifest);
+        if spoilage.any_spoiled {
+            litter.set_changed();
+        }
+        spill_overflow(&mut commands, voxel_pos, spoilage.overflow);
+    }
+}
+
+/// Drops spoilage products that no longer fit in their inventory as litter next to `voxel_pos`.
+fn spill_overflow(commands: &mut Commands, voxel_pos: VoxelPos, overflow: Vec<ItemCount>) {
+    for item_count in overflow {
+        for _ in 0..item_count.count {
+            commands.spawn_litter(voxel_pos, item_count.item_id);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        asset_management::manifest::{loader::ManifestError, Manifest, ManifestBuilder},
+        crafting::{
+            inventories::ConsumeInputError,
+            recipe::{RecipeConditions, RecipeData, RecipeEffect, RecipeOutput},
+        },
+        geometry::{DiscreteHeight, Facing},
+        items::item_manifest::{ItemData, Substitute},
+        structures::{
+            set_stock_floor,
+            structure_manifest::{StructureData, StructureKind},
+            SetStockFloor,
+        },
+        testing::crafter_app::{crafter_app, spawn_crafter},
+        units::{
+            goals::Goal,
+            item_interaction::{count_in_flight_items, UnitInventory},
+        },
+    };
+    use hexx::Hex;
+
+    /// Sets up a world with a single dredger standing on terrain of the provided height.
+    ///
+    /// Returns the app, the dredger entity and the terrain entity beneath it.
+    fn dredging_app(starting_height: DiscreteHeight) -> (App, Entity, Entity) {
+        let recipe_manifest = ManifestBuilder::new()
+            .add(
+                "dredge",
+                RecipeData {
+                    effects: vec![RecipeEffect::LowerTerrain],
+                    ..RecipeData::basic(RecipeInput::EMPTY, RecipeOutput::EMPTY)
+                },
+            )
+            .build();
+        let structure_manifest = ManifestBuilder::new()
+            .add("dredger", StructureData::impassable())
+            .build();
+        let mut app = crafter_app(0, recipe_manifest, structure_manifest, Manifest::new());
+
+        let mut map_geometry = app.world.resource_mut::<MapGeometry>();
+        map_geometry.update_height(Hex::ZERO, starting_height);
+        let terrain_entity = map_geometry.get_terrain(Hex::ZERO).unwrap();
+        app.world.entity_mut(terrain_entity).insert(VoxelPos {
+            hex: Hex::ZERO,
+            height: starting_height,
+        });
+
+        let structure_id = Id::from_name("dredger".to_string());
+        let dredger_entity = spawn_crafter(
+            &mut app,
+            structure_id,
+            ActiveRecipe::new(Id::from_name("dredge".to_string())),
+            Hex::ZERO,
+        );
+
+        let center = *app.world.get::<VoxelPos>(dredger_entity).unwrap();
+        let footprint = app
+            .world
+            .resource::<StructureManifest>()
+            .get(structure_id)
+            .footprint
+            .clone();
+        app.world
+            .resource_mut::<MapGeometry>()
+            .add_structure(
+                center,
+                Facing::default(),
+                &footprint,
+                false,
+                false,
+                dredger_entity,
+            )
+            .unwrap();
+
+        app.add_systems((progress_crafting, apply_recipe_effects).chain());
+
+        (app, dredger_entity, terrain_entity)
+    }
+
+    /// Runs the app until the dredger has completed its recipe exactly once more.
+    fn complete_recipe(app: &mut App, dredger_entity: Entity) {
+        loop {
+            app.update();
+            let crafting_state = app.world.get::<CraftingState>(dredger_entity).unwrap();
+            if matches!(crafting_state, CraftingState::RecipeComplete) {
+                break;
+            }
+        }
+    }
+
+    #[test]
+    fn dredging_lowers_terrain_by_one_per_completion() {
+        let (mut app, dredger_entity, terrain_entity) = dredging_app(DiscreteHeight(3));
+
+        for expected_height in [DiscreteHeight(2), DiscreteHeight(1), DiscreteHeight(0)] {
+            complete_recipe(&mut app, dredger_entity);
+
+            let map_geometry = app.world.resource::<MapGeometry>();
+            assert_eq!(map_geometry.get_height(Hex::ZERO), Ok(expected_height));
+
+            let terrain_pos = *app.world.get::<VoxelPos>(terrain_entity).unwrap();
+            assert_eq!(terrain_pos.height, expected_height);
+
+            // The dredger should sink along with the terrain it is standing on
+            let dredger_pos = *app.world.get::<VoxelPos>(dredger_entity).unwrap();
+            assert_eq!(dredger_pos, map_geometry.on_top_of_terrain(Hex::ZERO));
+            assert_eq!(
+                map_geometry.get_structure(dredger_pos),
+                Some(dredger_entity)
+            );
+        }
+    }
+
+    #[test]
+    fn dredging_stops_at_the_bottom_of_the_world() {
+        let (mut app, dredger_entity, _terrain_entity) = dredging_app(DiscreteHeight::ZERO);
+
+        complete_recipe(&mut app, dredger_entity);
+
+        let map_geometry = app.world.resource::<MapGeometry>();
+        assert_eq!(map_geometry.get_height(Hex::ZERO), Ok(DiscreteHeight::ZERO));
+    }
+
+    #[test]
+    fn changing_recipe_emits_event_once_and_resets_progress() {
+        let item_manifest: ItemManifest = Manifest::new();
+        let mut recipe_manifest: RecipeManifest = Manifest::new();
+        for name in ["smelt_copper", "smelt_tin"] {
+            recipe_manifest.insert(
+                name.to_string(),
+                RecipeData {
+                    craft_time: Duration::from_secs(10),
+                    ..RecipeData::basic(
+                        RecipeInput::Exact(Vec::new()),
+                        RecipeOutput::Deterministic(Vec::new()),
+                    )
+                },
+            );
+        }
+        let mut structure_manifest: StructureManifest = Manifest::new();
+        structure_manifest.insert("furnace".to_string(), StructureData::impassable());
+
+        let copper = ActiveRecipe::new(Id::from_name("smelt_copper".to_string()));
+        let tin = ActiveRecipe::new(Id::from_name("smelt_tin".to_string()));
+
+        let mut app = App::new();
+        app.add_event::<RecipeChanged>()
+            .add_system(detect_recipe_changes);
+
+        let furnace_entity = app
+            .world
+            .spawn(CraftingBundle::new(
+                Id::from_name("furnace".to_string()),
+                copper.clone(),
+                &recipe_manifest,
+                &item_manifest,
+                &structure_manifest,
+            ))
+            .id();
+        *app.world.get_mut::<CraftingState>(furnace_entity).unwrap() = CraftingState::InProgress {
+            progress: Duration::from_secs(7),
+            required: Duration::from_secs(10),
+        };
+        app.world
+            .get_mut::<ThroughputTracker>(furnace_entity)
+            .unwrap()
+            .record(Duration::from_secs(1), 3);
+
+        let mut reader = app.world.resource::<Events<RecipeChanged>>().get_reader();
+        let mut received_events = |app: &App| -> Vec<RecipeChanged> {
+            reader
+                .iter(app.world.resource::<Events<RecipeChanged>>())
+                .cloned()
+                .collect()
+        };
+
+        // Spawning a crafter is not a change of recipe
+        app.update();
+        assert_eq!(received_events(&app), Vec::new());
+
+        *app.world.get_mut::<ActiveRecipe>(furnace_entity).unwrap() = tin.clone();
+        app.update();
+        assert_eq!(
+            received_events(&app),
+            vec![RecipeChanged {
+                structure: furnace_entity,
+                old: copper,
+                new: tin.clone(),
+            }]
+        );
+        // The copper that was being smelted is lost, and the furnace starts over
+        assert_eq!(
+            app.world.get::<CraftingState>(furnace_entity),
+            Some(&CraftingState::NeedsInput)
+        );
+        assert_eq!(
+            app.world.get::<ThroughputTracker>(furnace_entity),
+            Some(&ThroughputTracker::default())
+        );
+
+        // Setting the same recipe again is not a change
+        *app.world.get_mut::<ActiveRecipe>(furnace_entity).unwrap() = tin;
+        app.update();
+        app.update();
+        assert_eq!(received_events(&app), Vec::new());
+
+        // Switching to a random preset is a change, but rerolling within it is not
+        let preset = ActiveRecipe::random(vec![
+            (
+                ActiveRecipe::new(Id::from_name("smelt_copper".to_string())),
+                1.,
+            ),
+            (
+                ActiveRecipe::new(Id::from_name("smelt_tin".to_string())),
+                1.,
+            ),
+        ]);
+        *app.world.get_mut::<ActiveRecipe>(furnace_entity).unwrap() = preset;
+        app.update();
+        assert_eq!(received_events(&app).len(), 1);
+
+        app.world
+            .get_mut::<ThroughputTracker>(furnace_entity)
+            .unwrap()
+            .record(Duration::from_secs(1), 3);
+        let mut rng = GlobalRng::new(0);
+        for _ in 0..10 {
+            app.world
+                .get_mut::<ActiveRecipe>(furnace_entity)
+                .unwrap()
+                .reroll(rng.get_mut());
+            app.update();
+        }
+        assert_eq!(received_events(&app), Vec::new());
+        assert!(
+            app.world
+                .get::<ThroughputTracker>(furnace_entity)
+                .unwrap()
+                .per_minute()
+                > 0.
+        );
+    }
+
+    #[test]
+    fn flow_stats_match_crafting_rate() {
+        let acorn = Id::from_name("acorn".to_string());
+        let leaf = Id::from_name("leaf".to_string());
+        let item_manifest = oak_item_manifest();
+
+        let (mut app, crafter_entity) = single_recipe_app(RecipeData::basic(
+            RecipeInput::Exact(vec![ItemCount::new(acorn, 2)]),
+            RecipeOutput::Deterministic(vec![ItemCount::one(leaf)]),
+        ));
+
+        // Three minutes of one-second ticks, keeping the crafter supplied with acorns
+        for _ in 0..180 {
+            let mut input = app.world.get_mut::<InputInventory>(crafter_entity).unwrap();
+            if input.inventory().item_count(acorn) == 0 {
+                input
+                    .fill_with_items(&ItemCount::new(acorn, 2), &item_manifest)
+                    .unwrap();
+            }
+
+            app.update();
+        }
+
+        // Each cycle takes three ticks: starting, progressing and completing the recipe
+        let flow_stats = app.world.get::<FlowStats>(crafter_entity).unwrap();
+        assert!((flow_stats.produced_per_min() - 20.).abs() < 0.5);
+        assert!((flow_stats.consumed_per_min() - 40.).abs() < 1.);
+        assert!((flow_stats.net_per_min() + 20.).abs() < 1.5);
+
+        // The same items are counted towards the flow of each item
+        let acorn_flow = app.world.resource::<ItemFlowStats>().get(acorn).unwrap();
+        assert!((acorn_flow.consumed_per_min() - 40.).abs() < 1.);
+        assert_eq!(acorn_flow.produced_per_min(), 0.);
+    }
+
+    /// Acorns and leaves, with large enough stacks that crafters are never blocked.
+    fn oak_item_manifest() -> ItemManifest {
+        let mut item_manifest: ItemManifest = Manifest::new();
+        for name in ["acorn", "leaf"] {
+            item_manifest.insert(name.to_string(), ItemData::basic(1000));
+        }
+        item_manifest
+    }
+
+    /// Sets up a world with a single crafter that is always crafting `recipe`, advancing by one second each update.
+    ///
+    /// Returns the app and the crafter entity.
+    fn single_recipe_app(recipe: RecipeData) -> (App, Entity) {
+        let recipe_manifest = ManifestBuilder::new().add("recipe", recipe).build();
+        let structure_manifest = ManifestBuilder::new()
+            .add("crafter", StructureData::impassable())
+            .build();
+        let mut app = crafter_app(0, recipe_manifest, structure_manifest, oak_item_manifest());
+
+        let crafter_entity = spawn_crafter(
+            &mut app,
+            Id::from_name("crafter".to_string()),
+            ActiveRecipe::new(Id::from_name("recipe".to_string())),
+            Hex::ZERO,
+        );
+        app.add_systems((progress_crafting, record_item_flow).chain());
+
+        (app, crafter_entity)
+    }
+
+    #[test]
+    fn generators_produce_from_nothing() {
+        let leaf = Id::from_name("leaf".to_string());
+        let (mut app, crafter_entity) = single_recipe_app(RecipeData::basic(
+            RecipeInput::EMPTY,
+            RecipeOutput::Deterministic(vec![ItemCount::one(leaf)]),
+        ));
+
+        // Each cycle takes three ticks: starting, progressing and completing the recipe
+        for expected_leaves in 1..=10 {
+            for _ in 0..3 {
+                app.update();
+            }
+
+            let output = app.world.get::<OutputInventory>(crafter_entity).unwrap();
+            assert_eq!(output.item_count(leaf), expected_leaves);
+        }
+
+        // Nothing needs to be delivered, so the generator never waits for inputs
+        let input = app.world.get::<InputInventory>(crafter_entity).unwrap();
+        assert!(input.is_empty());
+        assert_eq!(input.inventory().iter().count(), 0);
+    }
+
+    #[test]
+    fn sinks_destroy_their_inputs() {
+        let acorn = Id::from_name("acorn".to_string());
+        let (mut app, crafter_entity) = single_recipe_app(RecipeData::basic(
+            RecipeInput::Exact(vec![ItemCount::new(acorn, 2)]),
+            RecipeOutput::EMPTY,
+        ));
+
+        app.world
+            .get_mut::<InputInventory>(crafter_entity)
+            .unwrap()
+            .fill_with_items(&ItemCount::new(acorn, 6), &oak_item_manifest())
+            .unwrap();
+
+        // Three full cycles use up every acorn
+        for _ in 0..9 {
+            app.update();
+            assert_ne!(
+                *app.world.get::<CraftingState>(crafter_entity).unwrap(),
+                CraftingState::FullAndBlocked
+            );
+        }
+
+        let input = app.world.get::<InputInventory>(crafter_entity).unwrap();
+        assert_eq!(input.inventory().item_count(acorn), 0);
+        let output = app.world.get::<OutputInventory>(crafter_entity).unwrap();
+        assert!(output.is_empty());
+        assert_eq!(
+            *app.world.get::<CraftingState>(crafter_entity).unwrap(),
+            CraftingState::NeedsInput
+        );
+
+        // Without any more acorns, the sink simply waits
+        app.update();
+        assert_eq!(
+            *app.world.get::<CraftingState>(crafter_entity).unwrap(),
+            CraftingState::NeedsInput
+        );
+    }
+
+    /// An item manifest with berries that spoil into compost after two seconds.
+    fn perishable_item_manifest() -> ItemManifest {
+        let mut item_manifest: ItemManifest = Manifest::new();
+        item_manifest.insert(
+            "berry".to_string(),
+            ItemData {
+                compostable: true,
+                buoyant: true,
+                shelf_life: Some(Duration::from_secs(2)),
+                spoils_into: Some(Id::from_name("compost".to_string())),
+                ..ItemData::basic(10)
+            },
+        );
+        item_manifest.insert("compost".to_string(), ItemData::basic(5));
+        item_manifest
+    }
+
+    #[test]
+    fn items_spoil_into_compost_after_their_shelf_life() {
+        let mut app = App::new();
+        let item_manifest = perishable_item_manifest();
+        let berries = ItemCount::new(Id::from_name("berry".to_string()), 2);
+
+        let mut storage = StorageInventory::new(1, None);
+        storage
+            .add_item_all_or_nothing(&berries, &item_manifest)
+            .unwrap();
+        let storage_entity = app.world.spawn((VoxelPos::default(), storage)).id();
+
+        let mut litter = Litter::default();
+        litter
+            .contents
+            .add_item_all_or_nothing(&berries, &item_manifest)
+            .unwrap();
+        let litter_entity = app.world.spawn((VoxelPos::default(), litter)).id();
+
+        app.insert_resource(item_manifest)
+            .insert_resource(FixedTime::new_from_secs(1.))
+            .add_system(spoil_items);
+
+        // Not yet spoiled
+        app.update();
+        let storage = app.world.get::<StorageInventory>(storage_entity).unwrap();
+        assert_eq!(storage.item_count(berries.item_id), 2);
+        assert_eq!(storage.freshness_of_item(berries.item_id), 0.5);
+
+        app.update();
+        let compost = Id::from_name("compost".to_string());
+
+        let storage = app.world.get::<StorageInventory>(storage_entity).unwrap();
+        assert_eq!(storage.item_count(berries.item_id), 0);
+        assert_eq!(storage.item_count(compost), 2);
+
+        let litter = app.world.get::<Litter>(litter_entity).unwrap();
+        assert_eq!(litter.contents.item_count(berries.item_id), 0);
+        assert_eq!(litter.contents.item_count(compost), 2);
+    }
+
+    #[test]
+    fn freshness_gated_recipe_rejects_stale_input() {
+        let item_manifest = perishable_item_manifest();
+        let berry = Id::from_name("berry".to_string());
+        let recipe = RecipeData::basic(
+            RecipeInput::Fresh {
+                inputs: vec![ItemCount::new(berry, 2)],
+                min_freshness: 0.75,
+            },
+            RecipeOutput::EMPTY,
+        );
+
+        let mut input_inventory = recipe.input_inventory(&item_manifest);
+        input_inventory
+            .fill_with_items(&ItemCount::new(berry, 2), &item_manifest)
+            .unwrap();
+        // Half of the shelf life has passed
+        input_inventory.spoil(Duration::from_secs(1), &item_manifest);
+
+        assert_eq!(
+            input_inventory.consume_items(&recipe.inputs, &recipe.catalysts, &item_manifest),
+            Err(ConsumeInputError::NotFreshEnough)
+        );
+        assert_eq!(input_inventory.inventory().item_count(berry), 2);
+
+        let mut fresh_input_inventory = recipe.input_inventory(&item_manifest);
+        fresh_input_inventory
+            .fill_with_items(&ItemCount::new(berry, 2), &item_manifest)
+            .unwrap();
+
+        assert_eq!(
+            fresh_input_inventory.consume_items(&recipe.inputs, &recipe.catalysts, &item_manifest),
+            Ok(vec![ItemCount::new(berry, 2)])
+        );
+        assert_eq!(fresh_input_inventory.inventory().item_count(berry), 0);
+    }
+
+    /// Item data for a plain compostable item with the given stack size.
+    fn compostable(stack_size: u32) -> ItemData {
+        ItemData {
+            compostable: true,
+            buoyant: true,
+            ..ItemData::basic(stack_size)
+        }
+    }
+
+    #[test]
+    fn flexible_recipes_skip_excluded_items() {
+        let item_manifest: ItemManifest = [
+            ("leaf".to_string(), compostable(10)),
+            ("acacia_seed".to_string(), compostable(10)),
+        ]
+        .into_iter()
+        .collect();
+        let leaf = Id::from_name("leaf".to_string());
+        let seed = Id::from_name("acacia_seed".to_string());
+
+        let mut input_inventory = InputInventory::Tagged {
+            tag: ItemTag::Compostable,
+            exclude: Vec::new(),
+            inventory: Inventory::new(2, None),
+        };
+        input_inventory
+            .fill_with_items(&ItemCount::new(seed, 3), &item_manifest)
+            .unwrap();
+        input_inventory
+            .fill_with_items(&ItemCount::new(leaf, 3), &item_manifest)
+            .unwrap();
+
+        // Any compostable item, as long as it isn't a seed
+        let recipe_input = RecipeInput::Flexible {
+            tag: ItemTag::Compostable,
+            count: 2,
+            exclude: vec![seed],
+        };
+
+        assert_eq!(
+            input_inventory.consume_items(&recipe_input, &[], &item_manifest),
+            Ok(vec![ItemCount::new(leaf, 2)])
+        );
+        assert_eq!(input_inventory.inventory().item_count(leaf), 1);
+        assert_eq!(input_inventory.inventory().item_count(seed), 3);
+
+        // Only one usable item remains
+        assert_eq!(
+            input_inventory.consume_items(&recipe_input, &[], &item_manifest),
+            Err(ConsumeInputError::NotEnoughItems)
+        );
+        assert_eq!(input_inventory.inventory().item_count(seed), 3);
+
+        // Excluded items are never accepted by the recipe's own input inventory
+        let recipe_inventory = InputInventory::Tagged {
+            tag: ItemTag::Compostable,
+            exclude: vec![seed],
+            inventory: Inventory::new(1, None),
+        };
+        assert!(recipe_inventory.currently_accepts(leaf, &item_manifest));
+        assert!(!recipe_inventory.currently_accepts(seed, &item_manifest));
+    }
+
+    #[test]
+    fn excluded_items_must_exist() {
+        let item_manifest: ItemManifest = [("leaf".to_string(), compostable(10))]
+            .into_iter()
+            .collect();
+
+        let mut recipe_manifest = RecipeManifest::new();
+        recipe_manifest.insert(
+            "compost".to_string(),
+            RecipeData::basic(
+                RecipeInput::Flexible {
+                    tag: ItemTag::Compostable,
+                    count: 1,
+                    exclude: vec![Id::from_name("acacia_seed".to_string())],
+                },
+                RecipeOutput::EMPTY,
+            ),
+        );
+
+        assert!(matches!(
+            recipe_manifest.validate_item_references(&item_manifest),
+            Err(ManifestError::UnknownReference { field, .. }) if field == "exclude"
+        ));
+    }
+
+    #[test]
+    fn catalysts_must_exist() {
+        let item_manifest: ItemManifest = [("leaf".to_string(), compostable(10))]
+            .into_iter()
+            .collect();
+        let leaf = Id::from_name("leaf".to_string());
+
+        let mut recipe_manifest = RecipeManifest::new();
+        recipe_manifest.insert(
+            "compost".to_string(),
+            RecipeData {
+                catalysts: vec![
+                    ItemKind::Single(leaf),
+                    ItemKind::Single(Id::from_name("shovel".to_string())),
+                ],
+                ..RecipeData::basic(
+                    RecipeInput::Exact(vec![ItemCount::new(leaf, 1)]),
+                    RecipeOutput::EMPTY,
+                )
+            },
+        );
+
+        assert!(matches!(
+            recipe_manifest.validate_item_references(&item_manifest),
+            Err(ManifestError::UnknownReference { field, reference, .. })
+                if field == "catalysts" && reference == Id::<Item>::from_name("shovel".to_string()).to_string()
+        ));
+    }
+
+    #[test]
+    fn substitutes_cover_missing_inputs_at_their_ratio() {
+        let wood = |substitutes| ItemData {
+            substitutes,
+            ..compostable(10)
+        };
+        let log = Id::from_name("log".to_string());
+        let twig = Id::from_name("twig".to_string());
+        let item_manifest: ItemManifest = [
+            (
+                "log".to_string(),
+                wood(vec![Substitute {
+                    item_id: twig,
+                    ratio: 2,
+                }]),
+            ),
+            ("twig".to_string(), wood(Vec::new())),
+        ]
+        .into_iter()
+        .collect();
+
+        let recipe_input = RecipeInput::Exact(vec![ItemCount::new(log, 1)]);
+        let recipe_data = RecipeData::basic(
+            recipe_input.clone(),
+            RecipeOutput::Deterministic(Vec::new()),
+        );
+
+        // The crafter has room for twigs as well as logs, so they can be delivered instead
+        let mut input_inventory = recipe_data.input_inventory(&item_manifest);
+        assert!(input_inventory.currently_accepts(twig, &item_manifest));
+        input_inventory
+            .fill_with_items(&ItemCount::new(twig, 3), &item_manifest)
+            .unwrap();
+
+        // No logs at all, but two twigs will do instead
+        assert_eq!(
+            input_inventory.consume_items(&recipe_input, &[], &item_manifest),
+            Ok(vec![ItemCount::new(twig, 2)])
+        );
+        assert_eq!(input_inventory.inventory().item_count(twig), 1);
+
+        // A single twig is not enough to replace a log
+        assert_eq!(
+            input_inventory.consume_items(&recipe_input, &[], &item_manifest),
+            Err(ConsumeInputError::NotEnoughItems)
+        );
+        assert_eq!(input_inventory.inventory().item_count(twig), 1);
+    }
+
+    #[test]
+    fn catalysts_are_required_but_not_consumed() {
+        /// Milk and a culture that turns it into something better.
+        fn fermentation_manifest() -> ItemManifest {
+            let ingredient = ItemData::basic(10);
+            ManifestBuilder::new()
+                .add("milk", ingredient.clone())
+                .add("culture", ingredient)
+                .build()
+        }
+        let item_manifest = fermentation_manifest();
+        let milk = Id::from_name("milk".to_string());
+        let culture = Id::from_name("culture".to_string());
+
+        let recipe_manifest = ManifestBuilder::new()
+            .add(
+                "ferment",
+                RecipeData {
+                    catalysts: vec![ItemKind::Single(culture)],
+                    ..RecipeData::basic(
+                        RecipeInput::Exact(vec![ItemCount::new(milk, 2)]),
+                        RecipeOutput::EMPTY,
+                    )
+                },
+            )
+            .build();
+        let structure_manifest = ManifestBuilder::new()
+            .add("vat", StructureData::impassable())
+            .build();
+
+        let mut app = crafter_app(
+            0,
+            recipe_manifest,
+            structure_manifest,
+            fermentation_manifest(),
+        );
+        let crafter_entity = spawn_crafter(
+            &mut app,
+            Id::from_name("vat".to_string()),
+            ActiveRecipe::new(Id::from_name("ferment".to_string())),
+            Hex::ZERO,
+        );
+        app.add_system(progress_crafting);
+
+        let mut input = app.world.get_mut::<InputInventory>(crafter_entity).unwrap();
+        input
+            .fill_with_items(&ItemCount::new(milk, 2), &item_manifest)
+            .unwrap();
+
+        // Without the catalyst, crafting stalls and the inputs are left alone
+        for _ in 0..3 {
+            app.update();
+        }
+        assert_eq!(
+            *app.world.get::<CraftingState>(crafter_entity).unwrap(),
+            CraftingState::NeedsInput
+        );
+        let input = app.world.get::<InputInventory>(crafter_entity).unwrap();
+        assert_eq!(input.inventory().item_count(milk), 2);
+
+        let mut input = app.world.get_mut::<InputInventory>(crafter_entity).unwrap();
+        input
+            .fill_with_items(&ItemCount::one(culture), &item_manifest)
+            .unwrap();
+
+        app.update();
+        assert!(matches!(
+            *app.world.get::<CraftingState>(crafter_entity).unwrap(),
+            CraftingState::InProgress { .. }
+        ));
+        let input = app.world.get::<InputInventory>(crafter_entity).unwrap();
+        assert_eq!(input.inventory().item_count(milk), 0);
+        assert_eq!(input.inventory().item_count(culture), 1);
+    }
+
+    #[test]
+    fn cycling_recipe_presets_wraps_around() {
+        let mut app = App::new();
+
+        let recipe_names = ["grind", "press", "dry"];
+        let mut recipe_manifest: RecipeManifest = Manifest::new();
+        for name in recipe_names {
+            recipe_manifest.insert(
+                name.to_string(),
+                RecipeData::basic(RecipeInput::EMPTY, RecipeOutput::EMPTY),
+            );
+        }
+        let presets: Vec<ActiveRecipe> = recipe_names
+            .iter()
+            .map(|name| ActiveRecipe::new(Id::from_name(name.to_string())))
+            .collect();
+
+        let structure_id = Id::from_name("mill".to_string());
+        let mut structure_manifest: StructureManifest = Manifest::new();
+        structure_manifest.insert(
+            "mill".to_string(),
+            StructureData {
+                kind: StructureKind::Crafting {
+                    starting_recipe: presets[0].clone(),
+                    input_buffer_slots: None,
+                    output_buffer_slots: None,
+                },
+                recipe_presets: presets.clone(),
+                ..StructureData::impassable()
+            },
+        );
+        let item_manifest: ItemManifest = Manifest::new();
+
+        let crafting_bundle = CraftingBundle::new(
+            structure_id,
+            presets[0].clone(),
+            &recipe_manifest,
+            &item_manifest,
+            &structure_manifest,
+        );
+        let mill_entity = app
+            .world
+            .spawn((structure_id, VoxelPos::default(), crafting_bundle))
+            .id();
+
+        app.insert_resource(recipe_manifest)
+            .insert_resource(structure_manifest)
+            .insert_resource(item_manifest)
+            .insert_resource(CurrentSelection::Structure(mill_entity))
+            .init_resource::<ActionState<PlayerAction>>()
+            .init_resource::<Milestones>()
+            .add_system(cycle_recipe_presets);
+
+        for expected in [&presets[1], &presets[2], &presets[0], &presets[1]] {
+            let mut player_actions = app.world.resource_mut::<ActionState<PlayerAction>>();
+            player_actions.release(PlayerAction::CycleRecipePreset);
+            player_actions.press(PlayerAction::CycleRecipePreset);
+
+            app.update();
+
+            let active_recipe = app.world.get::<ActiveRecipe>(mill_entity).unwrap();
+            assert_eq!(active_recipe, expected);
+        }
+    }
+
+    #[test]
+    fn pausing_crafting_freezes_progress_until_resumed() {
+        let recipe_manifest = ManifestBuilder::new()
+            .add(
+                "smelt",
+                RecipeData {
+                    craft_time: Duration::from_secs(10),
+                    ..RecipeData::basic(RecipeInput::EMPTY, RecipeOutput::EMPTY)
+                },
+            )
+            .build();
+        let structure_manifest = ManifestBuilder::new()
+            .add("furnace", StructureData::impassable())
+            .build();
+        let mut app = crafter_app(0, recipe_manifest, structure_manifest, Manifest::new());
+
+        let furnace_entity = spawn_crafter(
+            &mut app,
+            Id::from_name("furnace".to_string()),
+            ActiveRecipe::new(Id::from_name("smelt".to_string())),
+            Hex::ZERO,
+        );
+        *app.world.get_mut::<CraftingState>(furnace_entity).unwrap() = CraftingState::InProgress {
+            progress: Duration::ZERO,
+            required: Duration::from_secs(10),
+        };
+
+        app.add_event::<ToggleCraftingPaused>()
+            .add_systems((toggle_crafting_paused, progress_crafting).chain());
+
+        fn progress(app: &App, entity: Entity) -> Duration {
+            match app.world.get::<CraftingState>(entity).unwrap() {
+                CraftingState::InProgress { progress, .. } => *progress,
+                other => panic!("expected crafting to be in progress, but it was {other:?}"),
+            }
+        }
+
+        app.update();
+        assert_eq!(progress(&app, furnace_entity), Duration::from_secs(1));
+
+        // The marker is added at the end of the tick in which the event is handled
+        app.world.send_event(ToggleCraftingPaused {
+            structure: furnace_entity,
+        });
+        app.update();
+        assert!(app.world.get::<CraftingPaused>(furnace_entity).is_some());
+
+        let frozen = progress(&app, furnace_entity);
+        for _ in 0..5 {
+            app.update();
+        }
+        assert_eq!(progress(&app, furnace_entity), frozen);
+
+        app.world.send_event(ToggleCraftingPaused {
+            structure: furnace_entity,
+        });
+        app.update();
+        assert!(app.world.get::<CraftingPaused>(furnace_entity).is_none());
+        assert_eq!(progress(&app, furnace_entity), frozen);
+
+        // Progress picks up where it left off, rather than restarting the recipe
+        app.update();
+        assert_eq!(
+            progress(&app, furnace_entity),
+            frozen + Duration::from_secs(1)
+        );
+    }
+
+    #[test]
+    fn paused_crafters_do_not_ask_for_workers() {
+        let recipe_manifest = ManifestBuilder::new()
+            .add(
+                "smelt",
+                RecipeData {
+                    craft_time: Duration::from_secs(10),
+                    conditions: RecipeConditions {
+                        workers_required: 1,
+                        allowable_light_range: None,
+                    },
+                    ..RecipeData::basic(RecipeInput::EMPTY, RecipeOutput::EMPTY)
+                },
+            )
+            .build();
+        let structure_manifest = ManifestBuilder::new()
+            .add("furnace", StructureData::impassable())
+            .build();
+        let mut app = crafter_app(0, recipe_manifest, structure_manifest, Manifest::new());
+
+        let furnace_entity = spawn_crafter(
+            &mut app,
+            Id::from_name("furnace".to_string()),
+            ActiveRecipe::new(Id::from_name("smelt".to_string())),
+            Hex::ZERO,
+        );
+        *app.world.get_mut::<CraftingState>(furnace_entity).unwrap() = CraftingState::InProgress {
+            progress: Duration::ZERO,
+            required: Duration::from_secs(10),
+        };
+
+        app.init_resource::<LogisticsPriorities>()
+            .init_resource::<InFlightItems>()
+            .add_system(set_crafting_emitter);
+
+        fn asks_for_work(app: &App, entity: Entity) -> bool {
+            app.world
+                .get::<Emitter>(entity)
+                .unwrap()
+                .signals
+                .iter()
+                .any(|(signal_type, _)| matches!(signal_type, SignalType::Work(..)))
+        }
+
+        app.update();
+        assert!(asks_for_work(&app, furnace_entity));
+
+        app.world.entity_mut(furnace_entity).insert(CraftingPaused);
+        app.update();
+        assert!(!asks_for_work(&app, furnace_entity));
+
+        app.world
+            .entity_mut(furnace_entity)
+            .remove::<CraftingPaused>();
+        app.update();
+        assert!(asks_for_work(&app, furnace_entity));
+    }
+
+    #[test]
+    fn haulers_carrying_inputs_only_satisfy_the_crafter_they_are_headed_for() {
+        let milk = Id::from_name("milk".to_string());
+        let item_manifest = ManifestBuilder::new()
+            .add("milk", ItemData::basic(10))
+            .build();
+        let recipe_manifest = ManifestBuilder::new()
+            .add(
+                "churn",
+                RecipeData::basic(
+                    RecipeInput::Exact(vec![ItemCount::new(milk, 2)]),
+                    RecipeOutput::EMPTY,
+                ),
+            )
+            .build();
+        let structure_manifest = ManifestBuilder::new()
+            .add("churn", StructureData::impassable())
+            .build();
+        let mut app = crafter_app(5, recipe_manifest, structure_manifest, item_manifest);
+
+        let mut spawn_churn = |hex: Hex| {
+            spawn_crafter(
+                &mut app,
+                Id::from_name("churn".to_string()),
+                ActiveRecipe::new(Id::from_name("churn".to_string())),
+                hex,
+            )
+        };
+        let near_churn = spawn_churn(Hex::new(1, 0));
+        let far_churn = spawn_churn(Hex::new(5, 0));
+
+        // This hauler is carrying enough milk to fill the nearest churn
+        let hauler_pos = app
+            .world
+            .resource::<MapGeometry>()
+            .on_top_of_terrain(Hex::ZERO);
+        app.world.spawn((
+            hauler_pos,
+            Goal::Deliver(ItemKind::Single(milk)),
+            UnitInventory::holding(ItemCount::new(milk, 10)),
+        ));
+
+        app.init_resource::<LogisticsPriorities>()
+            .init_resource::<InFlightItems>()
+            .add_systems((count_in_flight_items, set_crafting_emitter).chain());
+
+        app.update();
+
+        let pulls_milk = |entity: Entity| {
+            app.world
+                .get::<Emitter>(entity)
+                .unwrap()
+                .signals
+                .iter()
+                .any(|(signal_type, _)| *signal_type == SignalType::Pull(ItemKind::Single(milk)))
+        };
+        assert!(!pulls_milk(near_churn));
+        assert!(pulls_milk(far_churn));
+    }
+
+    #[test]
+    fn random_recipes_are_chosen_in_proportion_to_their_weights() {
+        let loot = ItemData::basic(10);
+        let item_manifest = ManifestBuilder::new()
+            .add("gem", loot.clone())
+            .add("pebble", loot)
+            .build();
+        let gem = Id::from_name("gem".to_string());
+        let pebble = Id::from_name("pebble".to_string());
+
+        let mut recipe_manifest: RecipeManifest = Manifest::new();
+        for (name, item_id) in [("find_gem", gem), ("find_pebble", pebble)] {
+            recipe_manifest.insert(
+                name.to_string(),
+                RecipeData {
+                    craft_time: Duration::ZERO,
+                    ..RecipeData::basic(
+                        RecipeInput::EMPTY,
+                        RecipeOutput::Deterministic(vec![ItemCount::one(item_id)]),
+                    )
+                },
+            );
+        }
+        let structure_manifest = ManifestBuilder::new()
+            .add("loot_box", StructureData::impassable())
+            .build();
+        let mut app = crafter_app(0, recipe_manifest, structure_manifest, item_manifest);
+
+        // Weights don't need to sum to one
+        let random_recipe = ActiveRecipe::random(vec![
+            (ActiveRecipe::new(Id::from_name("find_gem".to_string())), 6.),
+            (
+                ActiveRecipe::new(Id::from_name("find_pebble".to_string())),
+                2.,
+            ),
+        ]);
+        let loot_box_entity = spawn_crafter(
+            &mut app,
+            Id::from_name("loot_box".to_string()),
+            random_recipe,
+            Hex::ZERO,
+        );
+        let empty_output = app
+            .world
+            .get::<OutputInventory>(loot_box_entity)
+            .unwrap()
+            .clone();
+
+        app.insert_resource(GlobalRng::new(42))
+            .add_system(progress_crafting);
+
+        const COMPLETIONS: u32 = 2000;
+        let mut gems = 0;
+        let mut pebbles = 0;
+        while gems + pebbles < COMPLETIONS {
+            app.update();
+
+            let mut output = app
+                .world
+                .get_mut::<OutputInventory>(loot_box_entity)
+                .unwrap();
+            gems += output.item_count(gem);
+            pebbles += output.item_count(pebble);
+            *output = empty_output.clone();
+        }
+
+        let gem_fraction = gems as f32 / COMPLETIONS as f32;
+        assert!(
+            (gem_fraction - 0.75).abs() < 0.04,
+            "{gems} gems and {pebbles} pebbles were found"
+        );
+    }
+
+    #[test]
+    fn single_choice_random_recipes_are_deterministic() {
+        let recipe_id = Id::from_name("only_choice".to_string());
+        let mut active_recipe = ActiveRecipe::random(vec![(ActiveRecipe::new(recipe_id), 0.5)]);
+        assert_eq!(active_recipe.probabilities(), vec![(Some(recipe_id), 1.)]);
+
+        let mut rng = GlobalRng::new(0);
+        for _ in 0..10 {
+            active_recipe.reroll(rng.get_mut());
+            assert_eq!(*active_recipe.recipe_id(), Some(recipe_id));
+        }
+
+        // No randomness was drawn, so other random events are unaffected
+        assert_eq!(rng, GlobalRng::new(0));
+    }
+
+    #[test]
+    fn random_crafters_keep_flexible_inputs() {
+        let item_manifest: ItemManifest = [
+            ("leaf".to_string(), compostable(10)),
+            ("acacia_seed".to_string(), compostable(10)),
+        ]
+        .into_iter()
+        .collect();
+        let leaf = Id::from_name("leaf".to_string());
+        let seed = Id::from_name("acacia_seed".to_string());
+
+        let compost_recipe = |count, exclude| {
+            RecipeData::basic(
+                RecipeInput::Flexible {
+                    tag: ItemTag::Compostable,
+                    count,
+                    exclude,
+                },
+                RecipeOutput::EMPTY,
+            )
+        };
+
+        let mut recipe_manifest = RecipeManifest::new();
+        recipe_manifest.insert("compost_small".to_string(), compost_recipe(1, vec![seed]));
+        recipe_manifest.insert("compost_large".to_string(), compost_recipe(3, vec![seed]));
+        recipe_manifest.insert("compost_seeds".to_string(), compost_recipe(1, Vec::new()));
+        let small = Id::from_name("compost_small".to_string());
+        let large = Id::from_name("compost_large".to_string());
+        let seeds = Id::from_name("compost_seeds".to_string());
+
+        assert!(recipe_manifest.can_share_inputs(&[small, large]));
+        assert!(!recipe_manifest.can_share_inputs(&[small, seeds]));
+
+        let (input_inventory, _) =
+            merged_inventories(&[small, large], &recipe_manifest, &item_manifest);
+        let large_slot_count = recipe_manifest
+            .get(large)
+            .input_inventory(&item_manifest)
+            .inventory()
+            .max_slot_count();
+
+        assert!(matches!(
+            input_inventory,
+            InputInventory::Tagged { tag: ItemTag::Compostable, ref exclude, .. } if *exclude == vec![seed]
+        ));
+        assert_eq!(
+            input_inventory.inventory().max_slot_count(),
+            large_slot_count
+        );
+        assert!(input_inventory.currently_accepts(leaf, &item_manifest));
+        assert!(!input_inventory.currently_accepts(seed, &item_manifest));
+    }
+
+    #[test]
+    fn litter_drawing_recipes_consume_litter_on_their_footprint() {
+        let item_manifest = perishable_item_manifest();
+        let berry = Id::from_name("berry".to_string());
+        let compost = Id::from_name("compost".to_string());
+
+        let recipe_manifest = ManifestBuilder::new()
+            .add(
+                "rake",
+                RecipeData {
+                    draw_from_litter: true,
+                    ..RecipeData::basic(
+                        RecipeInput::Exact(vec![ItemCount::new(berry, 2)]),
+                        RecipeOutput::Deterministic(vec![ItemCount::new(compost, 1)]),
+                    )
+                },
+            )
+            .build();
+        let structure_manifest = ManifestBuilder::new()
+            .add(
+                "field",
+                StructureData {
+                    footprint: Footprint::hexagon(1),
+                    ..StructureData::passable()
+                },
+            )
+            .build();
+        let mut app = crafter_app(
+            2,
+            recipe_manifest,
+            structure_manifest,
+            perishable_item_manifest(),
+        );
+
+        let map_geometry = app.world.resource::<MapGeometry>();
+        let terrain_entities: Vec<Entity> = map_geometry
+            .all_hexes()
+            .map(|&hex| map_geometry.get_terrain(hex).unwrap())
+            .collect();
+        for terrain_entity in terrain_entities {
+            app.world
+                .entity_mut(terrain_entity)
+                .insert(Litter::default());
+        }
+        let mut drop_berries = |hex: Hex, count: u32| {
+            let terrain_entity = app
+                .world
+                .resource::<MapGeometry>()
+                .get_terrain(hex)
+                .unwrap();
+            app.world
+                .get_mut::<Litter>(terrain_entity)
+                .unwrap()
+                .contents
+                .add_item_all_or_nothing(&ItemCount::new(berry, count), &item_manifest)
+                .unwrap();
+        };
+        drop_berries(Hex::new(1, 0), 1);
+        drop_berries(Hex::new(0, 1), 1);
+        // Outside of the field
+        drop_berries(Hex::new(2, 0), 5);
+
+        let field_entity = spawn_crafter(
+            &mut app,
+            Id::from_name("field".to_string()),
+            ActiveRecipe::new(Id::from_name("rake".to_string())),
+            Hex::ZERO,
+        );
+        app.world
+            .entity_mut(field_entity)
+            .insert(Footprint::hexagon(1));
+        app.add_system(progress_crafting);
+
+        for _ in 0..5 {
+            app.update();
+        }
+
+        let output = app.world.get::<OutputInventory>(field_entity).unwrap();
+        assert_eq!(output.item_count(compost), 1);
+
+        let map_geometry = app.world.resource::<MapGeometry>();
+        let berries_at = |hex: Hex| {
+            let terrain_entity = map_geometry.get_terrain(hex).unwrap();
+            app.world
+                .get::<Litter>(terrain_entity)
+                .unwrap()
+                .contents
+                .item_count(berry)
+        };
+        assert_eq!(berries_at(Hex::new(1, 0)), 0);
+        assert_eq!(berries_at(Hex::new(0, 1)), 0);
+        assert_eq!(berries_at(Hex::new(2, 0)), 5);
+    }
+
+    #[test]
+    fn stock_floors_limit_advertised_and_withdrawable_items() {
+        let seed = Id::from_name("seed".to_string());
+        let mut item_manifest: ItemManifest = Manifest::new();
+        item_manifest.insert(
+            "seed".to_string(),
+            ItemData {
+                compostable: true,
+                ..ItemData::basic(10)
+            },
+        );
+
+        let mut storage_inventory = StorageInventory::new(1, Some(seed));
+        storage_inventory
+            .add_item_all_or_nothing(&ItemCount::new(seed, 7), &item_manifest)
+            .unwrap();
+
+        let mut app = App::new();
+        let storage_entity = app
+            .world
+            .spawn((
+                Id::<Structure>::from_name("granary".to_string()),
+                Emitter::default(),
+                storage_inventory,
+            ))
+            .id();
+        app.insert_resource(item_manifest)
+            .init_resource::<StorageGroups>()
+            .init_resource::<LogisticsPriorities>()
+            .init_resource::<InFlightItems>()
+            .add_event::<SetStockFloor>()
+            .add_systems((set_stock_floor, set_storage_emitter).chain());
+
+        let advertises_seeds = |app: &App| {
+            app.world
+                .get::<Emitter>(storage_entity)
+                .unwrap()
+                .signals
+                .iter()
+                .any(|(signal_type, _)| {
+                    *signal_type == SignalType::Contains(ItemKind::Single(seed))
+                })
+        };
+
+        app.world.send_event(SetStockFloor {
+            structure: storage_entity,
+            item_id: seed,
+            floor: 5,
+        });
+        app.update();
+
+        let storage_inventory = app.world.get::<StorageInventory>(storage_entity).unwrap();
+        assert_eq!(storage_inventory.withdrawable_count(seed), 2);
+        assert!(advertises_seeds(&app));
+
+        // Raising the floor to everything in stock leaves nothing to withdraw
+        app.world.send_event(SetStockFloor {
+            structure: storage_entity,
+            item_id: seed,
+            floor: 7,
+        });
+        app.update();
+
+        let storage_inventory = app.world.get::<StorageInventory>(storage_entity).unwrap();
+        let item_manifest = app.world.resource::<ItemManifest>();
+        assert_eq!(
+            storage_inventory.withdrawable_item_id(ItemKind::Single(seed), item_manifest),
+            None
+        );
+
+        // Once the reserve is all that's left, it is no longer offered
+        app.update();
+        assert!(!advertises_seeds(&app));
+    }
+
+    #[test]
+    fn declared_buffer_slots_set_inventory_capacity() {
+        let ingredient = ItemData::basic(10);
+        let item_manifest: ItemManifest = [
+            ("grain".to_string(), ingredient.clone()),
+            ("flour".to_string(), ingredient),
+        ]
+        .into_iter()
+        .collect();
+        let grain = Id::from_name("grain".to_string());
+        let flour = Id::from_name("flour".to_string());
+
+        let mut recipe_manifest: RecipeManifest = Manifest::new();
+        recipe_manifest.insert(
+            "grind".to_string(),
+            RecipeData::basic(
+                RecipeInput::Exact(vec![ItemCount::new(grain, 1)]),
+                RecipeOutput::Deterministic(vec![ItemCount::new(flour, 1)]),
+            ),
+        );
+        let grind = ActiveRecipe::new(Id::from_name("grind".to_string()));
+
+        let mut structure_manifest: StructureManifest = Manifest::new();
+        for (name, input_buffer_slots) in [("mill", None), ("granary_mill", Some(4))] {
+            structure_manifest.insert(
+                name.to_string(),
+                StructureData {
+                    kind: StructureKind::Crafting {
+                        starting_recipe: grind.clone(),
+                        input_buffer_slots,
+                        output_buffer_slots: None,
+                    },
+                    ..StructureData::impassable()
+                },
+            );
+        }
+
+        let crafting_bundle = |name: &str| {
+            CraftingBundle::new(
+                Id::from_name(name.to_string()),
+                grind.clone(),
+                &recipe_manifest,
+                &item_manifest,
+                &structure_manifest,
+            )
+        };
+
+        // By default, there's one slot per input
+        let mill = crafting_bundle("mill");
+        assert_eq!(mill.input_inventory.inventory().max_slot_count(), 1);
+        assert_eq!(mill.output_inventory.max_slot_count(), 1);
+
+        let granary_mill = crafting_bundle("granary_mill");
+        let input_inventory = granary_mill.input_inventory.inventory();
+        assert_eq!(input_inventory.max_slot_count(), 4);
+        assert_eq!(input_inventory.iter().count(), 4);
+        assert!(input_inventory
+            .iter()
+            .all(|item_slot| item_slot.item_id() == grain));
+        assert_eq!(
+            input_inventory.remaining_space_for_item(grain, &item_manifest),
+            40
+        );
+        assert_eq!(granary_mill.output_inventory.max_slot_count(), 1);
+    }
+}
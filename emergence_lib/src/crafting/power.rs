@@ -0,0 +1,177 @@
+//! Some crafting structures need power to make progress, which is supplied by generators.
+//!
+//! All producers and consumers share a single grid.
+//! When demand outstrips supply, every consumer is browned out by the same proportion.
+
+use bevy::prelude::*;
+
+use crate::structures::StructureActivity;
+
+use super::{inventories::CraftingState, CraftingPaused};
+
+/// A structure that needs power while it is crafting.
+///
+/// Consumers only draw power while their recipe is in progress and their crafting is not paused.
+#[derive(Component, Debug, Clone, Copy, PartialEq)]
+pub struct EnergyConsumer {
+    /// The power drawn from the grid.
+    pub watts: f32,
+}
+
+/// A structure that supplies power to the grid.
+#[derive(Component, Debug, Clone, Copy, PartialEq)]
+pub struct EnergyProducer {
+    /// The power supplied to the grid.
+    pub watts: f32,
+}
+
+/// The total power supplied and demanded across the grid this tick.
+#[derive(Resource, Debug, Default, Clone, Copy, PartialEq)]
+pub struct PowerGrid {
+    /// The power supplied by every active [`EnergyProducer`], in watts.
+    pub supply: f32,
+    /// The power drawn by every active [`EnergyConsumer`], in watts.
+    pub demand: f32,
+}
+
+impl PowerGrid {
+    /// The fraction of demand that is met, between 0 and 1.
+    ///
+    /// Consumers craft at this fraction of their usual speed.
+    pub fn satisfaction(&self) -> f32 {
+        if self.demand <= 0. {
+            1.
+        } else {
+            (self.supply / self.demand).min(1.)
+        }
+    }
+}
+
+/// Balances the power supplied and demanded by all active structures.
+pub(super) fn solve_power_grid(
+    producer_query: Query<(&EnergyProducer, Option<&StructureActivity>)>,
+    consumer_query: Query<
+        (
+            &EnergyConsumer,
+            Option<&CraftingState>,
+            Option<&StructureActivity>,
+        ),
+        Without<CraftingPaused>,
+    >,
+    mut power_grid: ResMut<PowerGrid>,
+) {
+    let supply = producer_query
+        .iter()
+        .filter(|(_, maybe_activity)| !StructureActivity::is_paused(*maybe_activity))
+        .map(|(producer, _)| producer.watts)
+        .sum();
+
+    let demand = consumer_query
+        .iter()
+        .filter(|(_, maybe_state, maybe_activity)| {
+            matches!(maybe_state, Some(CraftingState::InProgress { .. }))
+                && !StructureActivity::is_paused(*maybe_activity)
+        })
+        .map(|(consumer, ..)| consumer.watts)
+        .sum();
+
+    power_grid.set_if_neq(PowerGrid { supply, demand });
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::{
+        asset_management::manifest::{Id, Manifest, ManifestBuilder},
+        crafting::{
+            progress_crafting,
+            recipe::{ActiveRecipe, RecipeData, RecipeInput, RecipeOutput},
+            speed::update_craft_speed,
+        },
+        structures::structure_manifest::StructureData,
+        testing::crafter_app::{crafter_app, spawn_crafter},
+    };
+    use hexx::Hex;
+
+    /// How long the test recipe takes to craft at full power.
+    const CRAFT_TIME: Duration = Duration::from_secs(10);
+
+    /// Sets up a world with a generator producing the provided power, but nothing to power.
+    fn power_app(generator_watts: f32) -> App {
+        let recipe_manifest = ManifestBuilder::new()
+            .add(
+                "smelt",
+                RecipeData {
+                    craft_time: CRAFT_TIME,
+                    ..RecipeData::basic(RecipeInput::EMPTY, RecipeOutput::EMPTY)
+                },
+            )
+            .build();
+        let structure_manifest = ManifestBuilder::new()
+            .add("smelter", StructureData::impassable())
+            .build();
+        let mut app = crafter_app(0, recipe_manifest, structure_manifest, Manifest::new());
+
+        app.world.spawn(EnergyProducer {
+            watts: generator_watts,
+        });
+        app.add_systems((solve_power_grid, update_craft_speed, progress_crafting).chain());
+
+        app
+    }
+
+    /// Spawns a crafting structure that draws the provided power, and is partway through its recipe.
+    fn spawn_consumer(app: &mut App, watts: f32) -> Entity {
+        let consumer_entity = spawn_crafter(
+            app,
+            Id::from_name("smelter".to_string()),
+            ActiveRecipe::new(Id::from_name("smelt".to_string())),
+            Hex::ZERO,
+        );
+        app.world
+            .entity_mut(consumer_entity)
+            .insert(EnergyConsumer { watts });
+        *app.world.get_mut::<CraftingState>(consumer_entity).unwrap() = CraftingState::InProgress {
+            progress: Duration::ZERO,
+            required: CRAFT_TIME,
+        };
+
+        consumer_entity
+    }
+
+    /// How far through its recipe the provided crafter is, in seconds.
+    fn progress(app: &App, entity: Entity) -> f32 {
+        match app.world.get::<CraftingState>(entity).unwrap() {
+            CraftingState::InProgress { progress, .. } => progress.as_secs_f32(),
+            other => panic!("expected crafting to be in progress, but it was {other:?}"),
+        }
+    }
+
+    #[test]
+    fn short_supply_browns_out_every_consumer_proportionally() {
+        let mut app = power_app(100.);
+        let first = spawn_consumer(&mut app, 50.);
+        let second = spawn_consumer(&mut app, 50.);
+
+        // The generator covers both consumers exactly
+        app.update();
+        assert_eq!(app.world.resource::<PowerGrid>().satisfaction(), 1.);
+        assert_eq!(progress(&app, first), 1.);
+        assert_eq!(progress(&app, second), 1.);
+
+        // A third consumer pushes demand to 150 W, so everyone runs at two thirds speed
+        let third = spawn_consumer(&mut app, 50.);
+        app.update();
+
+        let power_grid = *app.world.resource::<PowerGrid>();
+        assert_eq!(power_grid.supply, 100.);
+        assert_eq!(power_grid.demand, 150.);
+        assert!((power_grid.satisfaction() - 2. / 3.).abs() < 1e-6);
+
+        for (entity, expected) in [(first, 5. / 3.), (second, 5. / 3.), (third, 2. / 3.)] {
+            assert!((progress(&app, entity) - expected).abs() < 1e-3);
+        }
+    }
+}
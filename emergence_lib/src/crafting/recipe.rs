@@ -1,11 +1,16 @@
 //! Instructions to craft items.
 
-use crate::asset_management::manifest::loader::IsRawManifest;
+use crate::asset_management::manifest::loader::{serialize_sorted, IsRawManifest, ManifestError};
 use crate::asset_management::manifest::{Id, Manifest};
+use crate::geometry::Volume;
 use crate::items::item_manifest::{Item, ItemManifest};
-use crate::items::{inventory::Inventory, ItemCount};
+use crate::items::{
+    inventory::{ConsumptionOrder, Inventory},
+    ItemCount,
+};
 use crate::light::shade::ReceivedLight;
 use crate::light::Illuminance;
+use crate::simulation::milestones::Milestone;
 use crate::{
     crafting::inventories::{InputInventory, OutputInventory},
     organisms::energy::Energy,
@@ -14,10 +19,11 @@ use bevy::prelude::*;
 use bevy::reflect::{FromReflect, Reflect, TypeUuid};
 use bevy::utils::HashMap;
 use itertools::Itertools;
+use rand::Rng;
 use serde::{Deserialize, Serialize};
 use std::{fmt::Display, time::Duration};
 
-use super::item_tags::ItemTag;
+use super::item_tags::{ItemKind, ItemTag, RawItemKind};
 
 /// The marker type for [`Id<Recipe>`](super::Id).
 #[derive(Reflect, FromReflect, Clone, Copy, PartialEq, Eq)]
@@ -26,15 +32,83 @@ pub struct Recipe;
 /// Stores the read-only definitions for all recipes.
 pub type RecipeManifest = Manifest<Recipe, RecipeData>;
 
+impl RecipeManifest {
+    /// Checks that every item that a recipe refers to by name exists in the `item_manifest`.
+    pub(crate) fn validate_item_references(
+        &self,
+        item_manifest: &ItemManifest,
+    ) -> Result<(), ManifestError> {
+        for (&recipe_id, recipe_data) in self.data_map() {
+            for &catalyst in &recipe_data.catalysts {
+                if let ItemKind::Single(item_id) = catalyst {
+                    if !item_manifest.data_map().contains_key(&item_id) {
+                        return Err(ManifestError::UnknownReference {
+                            entry: self.name(recipe_id).to_string(),
+                            field: "catalysts".to_string(),
+                            reference: item_id.to_string(),
+                        });
+                    }
+                }
+            }
+
+            if let RecipeInput::Flexible { ref exclude, .. } = recipe_data.inputs {
+                for &item_id in exclude {
+                    if !item_manifest.data_map().contains_key(&item_id) {
+                        return Err(ManifestError::UnknownReference {
+                            entry: self.name(recipe_id).to_string(),
+                            field: "exclude".to_string(),
+                            reference: item_id.to_string(),
+                        });
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Can all of the `recipe_ids` share a single input inventory, as needed by a crafter that picks between them at random?
+    ///
+    /// This is the case if none of them have flexible inputs, or if all of them accept the same flexible inputs.
+    pub(crate) fn can_share_inputs(&self, recipe_ids: &[Id<Recipe>]) -> bool {
+        let mut flexible_inputs =
+            recipe_ids
+                .iter()
+                .map(|&recipe_id| match self.get(recipe_id).inputs {
+                    RecipeInput::Flexible {
+                        tag, ref exclude, ..
+                    } => Some((tag, exclude)),
+                    RecipeInput::Exact(_) | RecipeInput::Fresh { .. } => None,
+                });
+
+        match flexible_inputs.next() {
+            Some(first) => flexible_inputs.all(|inputs| inputs == first),
+            None => true,
+        }
+    }
+}
+
 /// A recipe to turn a set of items into different items.
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct RecipeData {
     /// The inputs needed to craft the recipe.
+    ///
+    /// Recipes with [`RecipeInput::EMPTY`] inputs are generators, which start again as soon as they complete.
     pub inputs: RecipeInput,
 
+    /// Items that must be present in the input inventory to craft the recipe, but are not used up.
+    ///
+    /// Catalysts of recipes with flexible inputs must also match the input tag, or they can never be delivered.
+    pub catalysts: Vec<ItemKind>,
+
     /// The outputs generated by crafting.
+    ///
+    /// Recipes with [`RecipeOutput::EMPTY`] outputs are sinks, which destroy their inputs and can never be blocked by a full output inventory.
     pub outputs: RecipeOutput,
 
+    /// The changes made to the world around the structure when the recipe is completed.
+    pub effects: Vec<RecipeEffect>,
+
     /// The time needed to craft the recipe.
     pub craft_time: Duration,
 
@@ -45,12 +119,24 @@ pub struct RecipeData {
     ///
     /// This is only relevant to living structures.
     pub energy: Option<Energy>,
+
+    /// The milestone that must be completed before this recipe can be selected.
+    ///
+    /// If this is [`None`], the recipe is available from the start.
+    pub required_milestone: Option<Id<Milestone>>,
+
+    /// Can the inputs be taken straight from the litter on the crafter's tiles, rather than being hauled in?
+    ///
+    /// Litter is only used while the input inventory is empty, and only for recipes with exact inputs.
+    pub draw_from_litter: bool,
 }
 
 /// The items needed to craft a recipe.
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum RecipeInput {
     /// The recipe requires exactly the provided number of each input.
+    ///
+    /// Missing inputs may be made up with their [`Substitute`](crate::items::item_manifest::Substitute)s.
     Exact(Vec<ItemCount>),
     /// The recipe requires a fixed number of inputs that meet the provided conditions.
     Flexible {
@@ -58,6 +144,15 @@ pub enum RecipeInput {
         tag: ItemTag,
         /// The number of inputs that must meet the tag.
         count: u32,
+        /// Items that meet the tag, but cannot be used for this recipe.
+        exclude: Vec<Id<Item>>,
+    },
+    /// The recipe requires exactly the provided number of each input, and each input must be at least `min_freshness` fresh.
+    Fresh {
+        /// The required items.
+        inputs: Vec<ItemCount>,
+        /// The minimum freshness of the consumed items, between 0 and 1.
+        min_freshness: f32,
     },
 }
 
@@ -68,7 +163,7 @@ impl RecipeInput {
     /// The number of slots needed to craft this recipe.
     pub fn len(&self) -> usize {
         match self {
-            Self::Exact(inputs) => inputs.len(),
+            Self::Exact(inputs) | Self::Fresh { inputs, .. } => inputs.len(),
             Self::Flexible { .. } => 1,
         }
     }
@@ -76,7 +171,7 @@ impl RecipeInput {
     /// Is anything needed to craft this recipe?
     pub fn is_empty(&self) -> bool {
         match self {
-            Self::Exact(inputs) => inputs.is_empty(),
+            Self::Exact(inputs) | Self::Fresh { inputs, .. } => inputs.is_empty(),
             Self::Flexible { .. } => false,
         }
     }
@@ -86,13 +181,24 @@ impl RecipeInput {
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum RawRecipeInput {
     /// The recipe requires exactly the provided number of each input.
-    Exact(HashMap<String, u32>),
+    Exact(#[serde(serialize_with = "serialize_sorted")] HashMap<String, u32>),
     /// The recipe requires a fixed number of inputs that meet the provided conditions.
     Flexible {
         /// The conditions that inputs must meet.
         tag: ItemTag,
         /// The number of inputs that must meet the tag.
         count: u32,
+        /// The names of items that meet the tag, but cannot be used for this recipe.
+        #[serde(default)]
+        exclude: Vec<String>,
+    },
+    /// The recipe requires exactly the provided number of each input, and each input must be at least `min_freshness` fresh.
+    Fresh {
+        /// The required items.
+        #[serde(serialize_with = "serialize_sorted")]
+        inputs: HashMap<String, u32>,
+        /// The minimum freshness of the consumed items, between 0 and 1.
+        min_freshness: f32,
     },
 }
 
@@ -112,17 +218,35 @@ impl RawRecipeInput {
 
 impl From<RawRecipeInput> for RecipeInput {
     fn from(raw_input: RawRecipeInput) -> Self {
+        /// Converts the raw item names into [`ItemCount`]s.
+        fn item_counts(raw_data: HashMap<String, u32>) -> Vec<ItemCount> {
+            raw_data
+                .into_iter()
+                .map(|(item_name, count)| ItemCount {
+                    item_id: Id::from_name(item_name),
+                    count,
+                })
+                .collect()
+        }
+
         match raw_input {
-            RawRecipeInput::Exact(raw_data) => Self::Exact(
-                raw_data
-                    .into_iter()
-                    .map(|(item_name, count)| ItemCount {
-                        item_id: Id::from_name(item_name),
-                        count,
-                    })
-                    .collect(),
-            ),
-            RawRecipeInput::Flexible { tag, count } => Self::Flexible { tag, count },
+            RawRecipeInput::Exact(raw_data) => Self::Exact(item_counts(raw_data)),
+            RawRecipeInput::Flexible {
+                tag,
+                count,
+                exclude,
+            } => Self::Flexible {
+                tag,
+                count,
+                exclude: exclude.into_iter().map(Id::from_name).collect(),
+            },
+            RawRecipeInput::Fresh {
+                inputs,
+                min_freshness,
+            } => Self::Fresh {
+                inputs: item_counts(inputs),
+                min_freshness,
+            },
         }
     }
 }
@@ -186,17 +310,76 @@ impl RecipeOutput {
             Self::Stochastic(outputs) => outputs.iter().map(|(item_id, _)| *item_id).collect(),
         }
     }
+
+    /// The same outputs, without any items that are [seeds](crate::items::item_manifest::ItemData::seed).
+    pub fn without_seeds(&self, item_manifest: &ItemManifest) -> RecipeOutput {
+        let is_seed = |item_id: Id<Item>| item_manifest.get(item_id).seed.is_some();
+
+        match self {
+            Self::Deterministic(outputs) => Self::Deterministic(
+                outputs
+                    .iter()
+                    .filter(|output| !is_seed(output.item_id))
+                    .cloned()
+                    .collect(),
+            ),
+            Self::Stochastic(outputs) => Self::Stochastic(
+                outputs
+                    .iter()
+                    .filter(|(item_id, _)| !is_seed(*item_id))
+                    .copied()
+                    .collect(),
+            ),
+        }
+    }
+}
+
+/// A change to the world made when a recipe is completed.
+///
+/// Unlike [`RecipeOutput`], these alter the tile that the crafting structure is on, rather than producing items.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum RecipeEffect {
+    /// Lowers the height of the terrain by one step.
+    LowerTerrain,
+    /// Raises the height of the terrain by one step.
+    RaiseTerrain,
+    /// Adds the provided volume of water to the tile.
+    AddWater(Volume),
+}
+
+impl Display for RecipeEffect {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RecipeEffect::LowerTerrain => write!(f, "Lower terrain"),
+            RecipeEffect::RaiseTerrain => write!(f, "Raise terrain"),
+            RecipeEffect::AddWater(volume) => write!(f, "{:.2} water", volume.0),
+        }
+    }
 }
 
 /// The unprocessed equivalent of [`RecipeData`].
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct RawRecipeData {
     /// The inputs needed to craft the recipe.
+    ///
+    /// Generators, which produce their outputs from nothing, can leave this out.
+    #[serde(default = "RawRecipeInput::empty")]
     pub inputs: RawRecipeInput,
 
+    /// Items that must be present in the input inventory to craft the recipe, but are not used up.
+    #[serde(default)]
+    pub catalysts: Vec<RawItemKind>,
+
     /// The outputs generated by crafting.
+    ///
+    /// Sinks, which simply use up their inputs, can leave this out.
+    #[serde(default, serialize_with = "serialize_sorted")]
     pub outputs: HashMap<String, f32>,
 
+    /// The changes made to the world around the structure when the recipe is completed.
+    #[serde(default)]
+    pub effects: Vec<RecipeEffect>,
+
     /// The time needed to craft the recipe.
     pub craft_time: f32,
 
@@ -207,16 +390,80 @@ pub struct RawRecipeData {
     ///
     /// This is only relevant to living structures.
     pub energy: Option<Energy>,
+
+    /// The name of the milestone that must be completed before this recipe can be selected.
+    #[serde(default)]
+    pub required_milestone: Option<String>,
+
+    /// Can the inputs be taken straight from the litter on the crafter's tiles, rather than being hauled in?
+    #[serde(default)]
+    pub draw_from_litter: bool,
+}
+
+impl RawRecipeData {
+    /// Checks that this recipe can be safely converted into a [`RecipeData`].
+    ///
+    /// Returns a description of the first problem found, if any.
+    fn validate(&self) -> Result<(), String> {
+        if !self.craft_time.is_finite() || self.craft_time < 0. {
+            return Err(format!(
+                "craft time must be a non-negative number of seconds, but was {}",
+                self.craft_time
+            ));
+        }
+
+        for (item_name, &count) in self.outputs.iter() {
+            if !count.is_finite() || count < 0. {
+                return Err(format!(
+                    "output count of {item_name} must be non-negative, but was {count}"
+                ));
+            }
+        }
+
+        if let RawRecipeInput::Fresh { min_freshness, .. } = self.inputs {
+            if !(0.0..=1.0).contains(&min_freshness) {
+                return Err(format!(
+                    "minimum freshness must be between 0 and 1, but was {min_freshness}"
+                ));
+            }
+        }
+
+        // Exact inputs only have slots for specific items, so catalysts must be specific items that are never consumed
+        if let RawRecipeInput::Exact(inputs) | RawRecipeInput::Fresh { inputs, .. } = &self.inputs {
+            for catalyst in self.catalysts.iter() {
+                match catalyst {
+                    RawItemKind::Single(item_name) => {
+                        if inputs.contains_key(item_name) {
+                            return Err(format!(
+                                "catalyst {item_name} cannot also be consumed as an input"
+                            ));
+                        }
+                    }
+                    RawItemKind::Tag(tag) => {
+                        return Err(format!(
+                            "catalyst {tag} is a tag, which can only be used with flexible inputs"
+                        ));
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
 }
 
 impl From<RawRecipeData> for RecipeData {
     fn from(raw: RawRecipeData) -> Self {
         Self {
             inputs: raw.inputs.into(),
+            catalysts: raw.catalysts.into_iter().map(ItemKind::from).collect(),
             outputs: RecipeOutput::from_raw(raw.outputs),
+            effects: raw.effects,
             craft_time: Duration::from_secs_f32(raw.craft_time),
             conditions: raw.conditions.unwrap_or_default(),
             energy: raw.energy,
+            required_milestone: raw.required_milestone.map(Id::from_name),
+            draw_from_litter: raw.draw_from_litter,
         }
     }
 }
@@ -227,21 +474,57 @@ impl RecipeData {
         self.conditions.satisfied(workers, received_light)
     }
 
-    /// An inventory with empty slots for all of the inputs of this recipe.
+    /// An inventory with empty slots for all of the inputs and catalysts of this recipe.
+    ///
+    /// Exact inputs also get a slot for each of their [`Substitute`](crate::items::item_manifest::Substitute)s,
+    /// so that substitutes can be delivered in their place.
+    /// If any of the inputs are perishable, the stacks closest to spoiling are used first.
     pub(crate) fn input_inventory(&self, item_manifest: &ItemManifest) -> InputInventory {
         match self.inputs {
-            RecipeInput::Exact(ref inputs) => {
-                let mut inventory = Inventory::new(self.inputs.len(), None);
+            RecipeInput::Exact(ref inputs) | RecipeInput::Fresh { ref inputs, .. } => {
+                // Only exact inputs can be paid for with substitutes
+                let substitutes = inputs
+                    .iter()
+                    .filter(|_| matches!(self.inputs, RecipeInput::Exact(_)))
+                    .flat_map(|item_count| &item_manifest.get(item_count.item_id).substitutes)
+                    .map(|substitute| substitute.item_id);
+                let input_item_ids: Vec<Id<Item>> = inputs
+                    .iter()
+                    .map(|item_count| item_count.item_id)
+                    .chain(substitutes)
+                    .unique()
+                    .collect();
+
+                let mut inventory =
+                    Inventory::new(input_item_ids.len() + self.catalysts.len(), None);
+
+                for &item_id in &input_item_ids {
+                    inventory.add_empty_slot(item_id, item_manifest);
+                }
 
-                for item_count in inputs.iter() {
-                    inventory.add_empty_slot(item_count.item_id, item_manifest);
+                // A single catalyst is all that is ever needed
+                for catalyst in self.catalysts.iter() {
+                    if let ItemKind::Single(item_id) = *catalyst {
+                        inventory.add_empty_slot_with_capacity(item_id, 1, item_manifest);
+                    }
+                }
+
+                // Use up perishable inputs before they go bad
+                if inputs
+                    .iter()
+                    .any(|item_count| item_manifest.get(item_count.item_id).shelf_life.is_some())
+                {
+                    inventory.set_consumption_order(ConsumptionOrder::Spoilage);
                 }
 
                 InputInventory::Exact { inventory }
             }
-            RecipeInput::Flexible { tag, .. } => InputInventory::Tagged {
+            RecipeInput::Flexible {
+                tag, ref exclude, ..
+            } => InputInventory::Tagged {
                 tag,
-                inventory: Inventory::new(1, None),
+                exclude: exclude.clone(),
+                inventory: Inventory::new(1 + self.catalysts.len(), None),
             },
         }
     }
@@ -265,6 +548,24 @@ impl RecipeData {
         self.conditions.workers_required > 0
     }
 
+    /// A recipe that turns `inputs` into `outputs` in one second, with no conditions, catalysts or effects.
+    ///
+    /// Tests override the properties that they care about using struct update syntax.
+    #[cfg(test)]
+    pub(crate) fn basic(inputs: RecipeInput, outputs: RecipeOutput) -> Self {
+        RecipeData {
+            inputs,
+            catalysts: Vec::new(),
+            outputs,
+            effects: Vec::new(),
+            craft_time: Duration::from_secs(1),
+            conditions: RecipeConditions::NONE,
+            energy: None,
+            required_milestone: None,
+            draw_from_litter: false,
+        }
+    }
+
     /// The pretty formatting of this type
     pub(crate) fn display(&self, item_manifest: &ItemManifest) -> String {
         let input_str: String = match self.inputs {
@@ -272,7 +573,45 @@ impl RecipeData {
                 .iter()
                 .map(|input| input.display(item_manifest))
                 .join(", "),
-            RecipeInput::Flexible { tag, count } => format!("{count}x {tag}"),
+            RecipeInput::Flexible {
+                tag,
+                count,
+                ref exclude,
+            } => match exclude.is_empty() {
+                true => format!("{count}x {tag}"),
+                false => format!(
+                    "{count}x {tag} (except {})",
+                    exclude
+                        .iter()
+                        .map(|&item_id| item_manifest.name(item_id))
+                        .join(", ")
+                ),
+            },
+            RecipeInput::Fresh {
+                ref inputs,
+                min_freshness,
+            } => format!(
+                "{} (at least {:.0}% fresh)",
+                inputs
+                    .iter()
+                    .map(|input| input.display(item_manifest))
+                    .join(", "),
+                min_freshness * 100.
+            ),
+        };
+
+        let input_str = match self.catalysts.is_empty() {
+            true => input_str,
+            false => format!(
+                "{input_str} (with {})",
+                self.catalysts
+                    .iter()
+                    .map(|catalyst| match *catalyst {
+                        ItemKind::Single(item_id) => item_manifest.name(item_id).to_string(),
+                        ItemKind::Tag(tag) => tag.to_string(),
+                    })
+                    .join(", ")
+            ),
         };
 
         let output_strings: Vec<String> = self
@@ -280,6 +619,7 @@ impl RecipeData {
             .item_ids()
             .iter()
             .map(|output_id| item_manifest.name(*output_id).to_string())
+            .chain(self.effects.iter().map(|effect| effect.to_string()))
             .collect();
         let output_str = output_strings.join(", ");
 
@@ -376,69 +716,247 @@ impl<T: Display + PartialOrd> Display for Threshold<T> {
 #[derive(Debug, Clone, Serialize, Deserialize, TypeUuid, PartialEq)]
 #[uuid = "c711b30c-c3ff-4b86-92d0-f1aff2ec7818"]
 pub struct RawRecipeManifest {
-    /// The data for each item.
+    /// The data for each recipe.
+    ///
+    /// These are serialized in order of their names, to keep the output stable.
+    #[serde(serialize_with = "serialize_sorted")]
     pub recipes: HashMap<String, RawRecipeData>,
 }
 
+impl RawRecipeManifest {
+    /// Exports these recipes as pretty-printed JSON, in the same format that `recipe_manifest.json` files are read from.
+    ///
+    /// Recipes and their items are written in order of their names,
+    /// so exporting the same recipes always produces the same text.
+    pub fn to_json(&self) -> Result<String, serde_json::Error> {
+        serde_json::to_string_pretty(self)
+    }
+}
+
 impl IsRawManifest for RawRecipeManifest {
     const EXTENSION: &'static str = "recipe_manifest.json";
 
     type Marker = Recipe;
     type Data = RecipeData;
 
-    fn process(&self) -> Manifest<Self::Marker, Self::Data> {
+    fn process(&self) -> Result<Manifest<Self::Marker, Self::Data>, ManifestError> {
         let mut manifest = Manifest::new();
 
         for (raw_id, raw_data) in self.recipes.clone() {
+            if let Err(reason) = raw_data.validate() {
+                return Err(ManifestError::MalformedRecipe {
+                    recipe: raw_id,
+                    reason,
+                });
+            }
+
             let data = raw_data.into();
 
-            manifest.insert(raw_id, data)
+            manifest.try_insert(raw_id, data)?;
         }
 
-        manifest
+        Ok(manifest)
     }
 }
 
 /// The recipe that is currently being crafted, if any.
-#[derive(Component, Debug, Default, PartialEq, Eq, Clone, Serialize, Deserialize)]
-pub struct ActiveRecipe(pub(super) Option<Id<Recipe>>);
+#[derive(Component, Debug, Clone, Serialize, Deserialize)]
+pub enum ActiveRecipe {
+    /// Always crafts the same recipe, or nothing at all.
+    Fixed(Option<Id<Recipe>>),
+    /// Picks among several recipes by weight, rolling again each time a recipe is completed.
+    Random {
+        /// The possible choices, and their relative weights.
+        ///
+        /// Weights do not need to sum to one: they are normalized when a choice is made.
+        choices: Vec<(ActiveRecipe, f32)>,
+        /// The recipe that was most recently rolled, if any.
+        rolled: Option<Id<Recipe>>,
+    },
+}
+
+impl Default for ActiveRecipe {
+    fn default() -> Self {
+        ActiveRecipe::NONE
+    }
+}
+
+/// Two random recipes are equal if they choose between the same options,
+/// regardless of which recipe they happen to have rolled.
+impl PartialEq for ActiveRecipe {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (ActiveRecipe::Fixed(a), ActiveRecipe::Fixed(b)) => a == b,
+            (ActiveRecipe::Random { choices: a, .. }, ActiveRecipe::Random { choices: b, .. }) => {
+                a == b
+            }
+            _ => false,
+        }
+    }
+}
 
 /// The raw version of [`ActiveRecipe`].
-#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
-pub struct RawActiveRecipe(Option<String>);
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum RawActiveRecipe {
+    /// The name of the recipe to always craft, or `null` for none.
+    Fixed(Option<String>),
+    /// Picks among several recipes by weight.
+    Random {
+        /// The possible choices, and their relative weights.
+        random: Vec<(RawActiveRecipe, f32)>,
+    },
+}
 
 impl RawActiveRecipe {
     /// Creates a new [`RawActiveRecipe`], set to `recipe_name`.
     pub fn new(recipe_name: &str) -> Self {
-        RawActiveRecipe(Some(recipe_name.to_string()))
+        RawActiveRecipe::Fixed(Some(recipe_name.to_string()))
     }
 }
 
 impl From<RawActiveRecipe> for ActiveRecipe {
     fn from(raw: RawActiveRecipe) -> Self {
-        ActiveRecipe(raw.0.map(Id::from_name))
+        match raw {
+            RawActiveRecipe::Fixed(maybe_name) => {
+                ActiveRecipe::Fixed(maybe_name.map(Id::from_name))
+            }
+            RawActiveRecipe::Random { random } => ActiveRecipe::random(
+                random
+                    .into_iter()
+                    .map(|(choice, weight)| (choice.into(), weight))
+                    .collect(),
+            ),
+        }
     }
 }
 
 impl ActiveRecipe {
     /// The un-set [`ActiveRecipe`].
-    pub const NONE: ActiveRecipe = ActiveRecipe(None);
+    pub const NONE: ActiveRecipe = ActiveRecipe::Fixed(None);
 
     /// Creates a new [`ActiveRecipe`], set to `recipe_id`
     pub fn new(recipe_id: Id<Recipe>) -> Self {
-        ActiveRecipe(Some(recipe_id))
+        ActiveRecipe::Fixed(Some(recipe_id))
+    }
+
+    /// Creates a new [`ActiveRecipe`] that picks among `choices` by weight.
+    ///
+    /// No recipe is active until the first roll.
+    pub fn random(choices: Vec<(ActiveRecipe, f32)>) -> Self {
+        ActiveRecipe::Random {
+            choices,
+            rolled: None,
+        }
     }
 
     /// The ID of the currently active recipe, if one has been selected.
+    ///
+    /// For random recipes, this is the recipe that was most recently rolled.
     pub fn recipe_id(&self) -> &Option<Id<Recipe>> {
-        &self.0
+        match self {
+            ActiveRecipe::Fixed(maybe_recipe_id) => maybe_recipe_id,
+            ActiveRecipe::Random { rolled, .. } => rolled,
+        }
+    }
+
+    /// Is a new recipe chosen each time the current one completes?
+    pub(crate) fn is_random(&self) -> bool {
+        matches!(self, ActiveRecipe::Random { .. })
+    }
+
+    /// The probability of each possible outcome, with nested choices flattened out.
+    ///
+    /// Weights are normalized so that the probabilities sum to one.
+    /// Negative or non-finite weights are treated as zero,
+    /// and if no choice has a positive weight, all choices are equally likely.
+    /// The same recipe may appear more than once.
+    pub(crate) fn probabilities(&self) -> Vec<(Option<Id<Recipe>>, f32)> {
+        match self {
+            ActiveRecipe::Fixed(maybe_recipe_id) => vec![(*maybe_recipe_id, 1.)],
+            ActiveRecipe::Random { choices, .. } => {
+                let sanitized = |weight: f32| match weight.is_finite() && weight > 0. {
+                    true => weight,
+                    false => 0.,
+                };
+                let total: f32 = choices.iter().map(|(_, weight)| sanitized(*weight)).sum();
+
+                choices
+                    .iter()
+                    .flat_map(|(choice, weight)| {
+                        let probability = match total > 0. {
+                            true => sanitized(*weight) / total,
+                            false => 1. / choices.len() as f32,
+                        };
+
+                        choice
+                            .probabilities()
+                            .into_iter()
+                            .map(move |(recipe_id, nested)| (recipe_id, probability * nested))
+                    })
+                    .collect()
+            }
+        }
+    }
+
+    /// Every recipe that could be crafted, without duplicates.
+    pub(crate) fn possible_recipes(&self) -> Vec<Id<Recipe>> {
+        let mut recipe_ids: Vec<Id<Recipe>> = Vec::new();
+        for (maybe_recipe_id, probability) in self.probabilities() {
+            if let Some(recipe_id) = maybe_recipe_id {
+                if probability > 0. && !recipe_ids.contains(&recipe_id) {
+                    recipe_ids.push(recipe_id);
+                }
+            }
+        }
+
+        recipe_ids
+    }
+
+    /// Picks a new recipe at random, if this is a random recipe.
+    ///
+    /// If only one outcome is possible, it is always picked and no randomness is drawn.
+    pub(crate) fn reroll(&mut self, rng: &mut impl Rng) {
+        let probabilities = self.probabilities();
+        let ActiveRecipe::Random { rolled, .. } = self else { return };
+
+        let Some(&(first, _)) = probabilities.first() else {
+            *rolled = None;
+            return;
+        };
+
+        if probabilities
+            .iter()
+            .all(|(recipe_id, probability)| *recipe_id == first || *probability == 0.)
+        {
+            *rolled = first;
+            return;
+        }
+
+        let mut remaining: f32 = rng.gen();
+        // Rounding errors may leave a tiny sliver of probability unclaimed, which goes to the last outcome
+        let mut choice = probabilities.last().unwrap().0;
+        for (recipe_id, probability) in probabilities {
+            if remaining < probability {
+                choice = recipe_id;
+                break;
+            }
+            remaining -= probability;
+        }
+
+        *rolled = choice;
     }
 
     /// The pretty formatting for this type
     pub(crate) fn display(&self, recipe_manifest: &RecipeManifest) -> String {
-        match self.0 {
-            Some(recipe_id) => recipe_manifest.name(recipe_id).to_string(),
+        let current = match self.recipe_id() {
+            Some(recipe_id) => recipe_manifest.name(*recipe_id).to_string(),
             None => "None".to_string(),
+        };
+
+        match self {
+            ActiveRecipe::Fixed(_) => current,
+            ActiveRecipe::Random { .. } => format!("{current} (random)"),
         }
     }
 }
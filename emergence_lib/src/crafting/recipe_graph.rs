@@ -0,0 +1,567 @@
+//! Answers "what do I need to make this item?" by following recipes back to their raw inputs.
+//!
+//! Unlike the [`ManifestGraph`](crate::asset_management::manifest::graph::ManifestGraph),
+//! which is built for balancing the manifests as a whole, this is focused on the production chain of a single item.
+
+use std::{fmt::Display, sync::Arc};
+
+use bevy::utils::{HashMap, HashSet};
+
+use crate::{
+    asset_management::manifest::Id,
+    items::item_manifest::{Item, ItemManifest},
+    structures::structure_manifest::{Structure, StructureManifest},
+};
+
+use super::{
+    item_tags::{ItemKind, ItemTag},
+    recipe::{Recipe, RecipeInput, RecipeManifest},
+};
+
+/// Which recipes make each item, and what they need, for resolving production chains.
+#[derive(Debug, Clone, Default)]
+pub struct RecipeGraph {
+    /// The recipes that produce each item, sorted for stable output.
+    producers: HashMap<Id<Item>, Vec<Id<Recipe>>>,
+    /// What each recipe needs, and which structures can craft it.
+    recipes: HashMap<Id<Recipe>, RecipeNode>,
+    /// The name of each item, used to report cycles.
+    item_names: HashMap<Id<Item>, String>,
+}
+
+/// The parts of a recipe that matter for its production chain.
+#[derive(Debug, Clone, Default)]
+struct RecipeNode {
+    /// The items consumed by each craft.
+    inputs: Vec<RecipeGraphInput>,
+    /// Items that must be present to craft the recipe, but are not used up.
+    catalysts: Vec<ItemKind>,
+    /// The structures that can craft this recipe, sorted for stable output.
+    crafted_by: Vec<Id<Structure>>,
+}
+
+/// An input to a recipe, before it has been resolved.
+#[derive(Debug, Clone, Copy)]
+enum RecipeGraphInput {
+    /// A specific item.
+    Item(Id<Item>, u32),
+    /// Any items with the provided tag.
+    Tagged(ItemTag, u32),
+}
+
+/// The full production chain of an item, as returned by [`RecipeGraph::dependencies`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum DependencyTree {
+    /// No recipe makes this item: it must be gathered from the world.
+    Raw(Id<Item>),
+    /// This item can be crafted.
+    Crafted {
+        /// The item that is crafted.
+        item_id: Id<Item>,
+        /// Each recipe that makes the item, any one of which is enough.
+        steps: Vec<RecipeStep>,
+    },
+}
+
+/// A single recipe in a [`DependencyTree`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct RecipeStep {
+    /// The recipe that is crafted.
+    pub recipe_id: Id<Recipe>,
+    /// The structures that can craft this recipe.
+    ///
+    /// If this is empty, nothing can currently craft this step.
+    pub crafted_by: Vec<Id<Structure>>,
+    /// The items consumed by each craft.
+    pub inputs: Vec<StepInput>,
+    /// Items that must be present to craft the recipe, but are not used up.
+    ///
+    /// These are not resolved any further.
+    pub catalysts: Vec<ItemKind>,
+}
+
+/// An input consumed by a [`RecipeStep`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum StepInput {
+    /// A specific item, along with its own production chain.
+    Item {
+        /// The number of items consumed by each craft.
+        count: u32,
+        /// How the item is made.
+        ///
+        /// This is shared with every other step that needs the same item.
+        tree: Arc<DependencyTree>,
+    },
+    /// Any items with the provided tag.
+    ///
+    /// These are not resolved any further, as many different items may qualify.
+    Tagged {
+        /// The tag that the items must have.
+        tag: ItemTag,
+        /// The number of items consumed by each craft.
+        count: u32,
+    },
+}
+
+impl DependencyTree {
+    /// The item at the root of this tree.
+    pub fn item_id(&self) -> Id<Item> {
+        match self {
+            DependencyTree::Raw(item_id) => *item_id,
+            DependencyTree::Crafted { item_id, .. } => *item_id,
+        }
+    }
+
+    /// Every raw item anywhere in this tree, sorted and without duplicates.
+    pub fn raw_inputs(&self) -> Vec<Id<Item>> {
+        let mut raw_inputs = Vec::new();
+        self.collect_raw_inputs(&mut raw_inputs, &mut HashSet::default());
+        raw_inputs.sort();
+        raw_inputs.dedup();
+        raw_inputs
+    }
+
+    /// Adds every raw item in this tree to `raw_inputs`.
+    ///
+    /// Crafted items in `visited` have already been searched, and are skipped.
+    fn collect_raw_inputs(&self, raw_inputs: &mut Vec<Id<Item>>, visited: &mut HashSet<Id<Item>>) {
+        match self {
+            DependencyTree::Raw(item_id) => raw_inputs.push(*item_id),
+            DependencyTree::Crafted { item_id, steps } => {
+                if !visited.insert(*item_id) {
+                    return;
+                }
+
+                for input in steps.iter().flat_map(|step| &step.inputs) {
+                    if let StepInput::Item { tree, .. } = input {
+                        tree.collect_raw_inputs(raw_inputs, visited);
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// The progress of a single [`RecipeGraph::dependencies`] query.
+#[derive(Debug, Default)]
+struct DependencySearch {
+    /// The items that are currently being resolved, starting from the root.
+    path: Vec<Id<Item>>,
+    /// The items whose trees are the same no matter where they are needed from.
+    resolved: HashMap<Id<Item>, Arc<DependencyTree>>,
+    /// The cycles found so far, whose recipes were left out of the tree.
+    cycles: Vec<DependencyError>,
+}
+
+/// The outcome of resolving a single item in a [`DependencySearch`].
+#[derive(Debug)]
+enum Resolution {
+    /// The item was resolved.
+    Resolved {
+        /// How the item is made.
+        tree: Arc<DependencyTree>,
+        /// The position in the path of the outermost item that a pruned cycle led back to, if any.
+        ///
+        /// Trees that led back to an item further up cannot be reused elsewhere, as that cycle may not be pruned there.
+        loops_back_to: Option<usize>,
+    },
+    /// The item is already being resolved at this position in the path, so needing it again forms a cycle.
+    Cyclic(usize),
+}
+
+/// A problem found while resolving a [`DependencyTree`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DependencyError {
+    /// The item is needed, directly or indirectly, to craft itself.
+    Cycle {
+        /// The names of the items in the cycle, starting and ending with the same item.
+        items: Vec<String>,
+    },
+}
+
+impl Display for DependencyError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DependencyError::Cycle { items } => {
+                write!(f, "Recipes form a cycle: {}", items.join(" -> "))
+            }
+        }
+    }
+}
+
+impl std::error::Error for DependencyError {}
+
+impl RecipeGraph {
+    /// Builds the graph from the recipes in the provided manifests.
+    pub fn build(
+        recipe_manifest: &RecipeManifest,
+        structure_manifest: &StructureManifest,
+        item_manifest: &ItemManifest,
+    ) -> Self {
+        let mut graph = RecipeGraph {
+            item_names: item_manifest.name_map().clone(),
+            ..Default::default()
+        };
+
+        for (&recipe_id, recipe_data) in recipe_manifest.data_map() {
+            let inputs = match &recipe_data.inputs {
+                RecipeInput::Exact(inputs) | RecipeInput::Fresh { inputs, .. } => inputs
+                    .iter()
+                    .map(|item_count| RecipeGraphInput::Item(item_count.item_id, item_count.count))
+                    .collect(),
+                RecipeInput::Flexible { tag, count, .. } => {
+                    vec![RecipeGraphInput::Tagged(*tag, *count)]
+                }
+            };
+
+            for item_id in recipe_data.outputs.item_ids() {
+                graph.producers.entry(item_id).or_default().push(recipe_id);
+            }
+
+            graph.recipes.insert(
+                recipe_id,
+                RecipeNode {
+                    inputs,
+                    catalysts: recipe_data.catalysts.clone(),
+                    crafted_by: Vec::new(),
+                },
+            );
+        }
+
+        for (&structure_id, structure_data) in structure_manifest.data_map() {
+            let recipe_choices = std::iter::once(structure_data.starting_recipe())
+                .chain(structure_data.recipe_presets.iter());
+            for active_recipe in recipe_choices {
+                for recipe_id in active_recipe.possible_recipes() {
+                    if let Some(recipe_node) = graph.recipes.get_mut(&recipe_id) {
+                        if !recipe_node.crafted_by.contains(&structure_id) {
+                            recipe_node.crafted_by.push(structure_id);
+                        }
+                    }
+                }
+            }
+        }
+
+        for recipe_ids in graph.producers.values_mut() {
+            recipe_ids.sort();
+            recipe_ids.dedup();
+        }
+        for recipe_node in graph.recipes.values_mut() {
+            recipe_node.crafted_by.sort();
+        }
+
+        graph
+    }
+
+    /// Resolves everything needed to make `item_id`, following each recipe back to its raw inputs.
+    ///
+    /// Every recipe that makes an item is included as an alternative step.
+    /// Recipes that need an item to craft itself are left out, and each such cycle is returned alongside the tree.
+    /// If every recipe for an item is left out, it is treated as raw: some must be gathered before any can be crafted.
+    ///
+    /// Each item is only resolved once, and its tree is shared by every step that needs it.
+    pub fn dependencies(&self, item_id: Id<Item>) -> (DependencyTree, Vec<DependencyError>) {
+        let mut search = DependencySearch::default();
+        let tree = match self.resolve(item_id, &mut search) {
+            Resolution::Resolved { tree, .. } => DependencyTree::clone(&tree),
+            // The path starts out empty, so the root cannot be part of a cycle yet
+            Resolution::Cyclic(_) => unreachable!(),
+        };
+
+        (tree, search.cycles)
+    }
+
+    /// Resolves the tree for `item_id`, pruning any recipe that needs an item that is already being resolved above it.
+    fn resolve(&self, item_id: Id<Item>, search: &mut DependencySearch) -> Resolution {
+        /// Records that a pruned cycle led back to the item at position `ancestor` in the path.
+        fn note_loop(ancestor: usize, loops_back_to: &mut Option<usize>) {
+            *loops_back_to =
+                Some(loops_back_to.map_or(ancestor, |outermost| outermost.min(ancestor)));
+        }
+
+        if let Some(start) = search.path.iter().position(|&ancestor| ancestor == item_id) {
+            let items = search.path[start..]
+                .iter()
+                .chain(std::iter::once(&item_id))
+                .map(|&item_id| self.item_name(item_id))
+                .collect();
+            let cycle = DependencyError::Cycle { items };
+            if !search.cycles.contains(&cycle) {
+                search.cycles.push(cycle);
+            }
+
+            return Resolution::Cyclic(start);
+        }
+
+        if let Some(tree) = search.resolved.get(&item_id) {
+            return Resolution::Resolved {
+                tree: tree.clone(),
+                loops_back_to: None,
+            };
+        }
+
+        let Some(recipe_ids) = self.producers.get(&item_id) else {
+            return Resolution::Resolved {
+                tree: Arc::new(DependencyTree::Raw(item_id)),
+                loops_back_to: None,
+            };
+        };
+
+        let depth = search.path.len();
+        let mut loops_back_to: Option<usize> = None;
+
+        search.path.push(item_id);
+        let mut steps = Vec::with_capacity(recipe_ids.len());
+        'recipes: for recipe_id in recipe_ids {
+            let recipe_node = &self.recipes[recipe_id];
+
+            let mut inputs = Vec::with_capacity(recipe_node.inputs.len());
+            for &input in &recipe_node.inputs {
+                inputs.push(match input {
+                    RecipeGraphInput::Item(input_id, count) => match self.resolve(input_id, search)
+                    {
+                        Resolution::Resolved {
+                            tree,
+                            loops_back_to: nested_loop,
+                        } => {
+                            if let Some(ancestor) = nested_loop {
+                                note_loop(ancestor, &mut loops_back_to);
+                            }
+                            StepInput::Item { count, tree }
+                        }
+                        Resolution::Cyclic(ancestor) => {
+                            note_loop(ancestor, &mut loops_back_to);
+                            continue 'recipes;
+                        }
+                    },
+                    RecipeGraphInput::Tagged(tag, count) => StepInput::Tagged { tag, count },
+                });
+            }
+
+            steps.push(RecipeStep {
+                recipe_id: *recipe_id,
+                crafted_by: recipe_node.crafted_by.clone(),
+                inputs,
+                catalysts: recipe_node.catalysts.clone(),
+            });
+        }
+        search.path.pop();
+
+        let tree = Arc::new(match steps.is_empty() {
+            true => DependencyTree::Raw(item_id),
+            false => DependencyTree::Crafted { item_id, steps },
+        });
+
+        // Cycles that lead back to this item are pruned in the same way wherever it is needed from
+        let loops_back_to = loops_back_to.filter(|&ancestor| ancestor < depth);
+        if loops_back_to.is_none() {
+            search.resolved.insert(item_id, tree.clone());
+        }
+
+        Resolution::Resolved {
+            tree,
+            loops_back_to,
+        }
+    }
+
+    /// The human-readable name of `item_id`, falling back to its [`Id`] for unknown items.
+    fn item_name(&self, item_id: Id<Item>) -> String {
+        match self.item_names.get(&item_id) {
+            Some(name) => name.clone(),
+            None => format!("{item_id:?}"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        asset_management::manifest::Manifest,
+        crafting::recipe::{ActiveRecipe, RecipeData, RecipeOutput},
+        items::{item_manifest::ItemData, ItemCount},
+        structures::structure_manifest::{StructureData, StructureKind},
+    };
+
+    fn item(name: &str) -> Id<Item> {
+        Id::from_name(name.to_string())
+    }
+
+    fn item_manifest(names: &[&str]) -> ItemManifest {
+        let mut item_manifest: ItemManifest = Manifest::new();
+        for name in names {
+            item_manifest.insert(name.to_string(), ItemData::basic(10));
+        }
+        item_manifest
+    }
+
+    /// A recipe that turns `inputs` into a single `output`.
+    fn recipe(inputs: &[(&str, u32)], output: &str) -> RecipeData {
+        RecipeData::basic(
+            RecipeInput::Exact(
+                inputs
+                    .iter()
+                    .map(|&(name, count)| ItemCount::new(item(name), count))
+                    .collect(),
+            ),
+            RecipeOutput::Deterministic(vec![ItemCount::new(item(output), 1)]),
+        )
+    }
+
+    /// A structure that crafts `recipe_name`.
+    fn crafter(recipe_name: &str) -> StructureData {
+        StructureData {
+            kind: StructureKind::Crafting {
+                starting_recipe: ActiveRecipe::new(Id::from_name(recipe_name.to_string())),
+                input_buffer_slots: None,
+                output_buffer_slots: None,
+            },
+            ..StructureData::impassable()
+        }
+    }
+
+    #[test]
+    fn two_step_chain_resolves_to_its_raw_inputs() {
+        let mut recipe_manifest: RecipeManifest = Manifest::new();
+        recipe_manifest.insert("smelting".to_string(), recipe(&[("ore", 2)], "ingot"));
+        recipe_manifest.insert("smithing".to_string(), recipe(&[("ingot", 3)], "tool"));
+
+        let mut structure_manifest: StructureManifest = Manifest::new();
+        structure_manifest.insert("furnace".to_string(), crafter("smelting"));
+        structure_manifest.insert("anvil".to_string(), crafter("smithing"));
+
+        let graph = RecipeGraph::build(
+            &recipe_manifest,
+            &structure_manifest,
+            &item_manifest(&["ore", "ingot", "tool"]),
+        );
+
+        let expected = DependencyTree::Crafted {
+            item_id: item("tool"),
+            steps: vec![RecipeStep {
+                recipe_id: Id::from_name("smithing".to_string()),
+                crafted_by: vec![Id::from_name("anvil".to_string())],
+                inputs: vec![StepInput::Item {
+                    count: 3,
+                    tree: Arc::new(DependencyTree::Crafted {
+                        item_id: item("ingot"),
+                        steps: vec![RecipeStep {
+                            recipe_id: Id::from_name("smelting".to_string()),
+                            crafted_by: vec![Id::from_name("furnace".to_string())],
+                            inputs: vec![StepInput::Item {
+                                count: 2,
+                                tree: Arc::new(DependencyTree::Raw(item("ore"))),
+                            }],
+                            catalysts: Vec::new(),
+                        }],
+                    }),
+                }],
+                catalysts: Vec::new(),
+            }],
+        };
+
+        let (tree, cycles) = graph.dependencies(item("tool"));
+        assert_eq!(tree, expected);
+        assert!(cycles.is_empty());
+        assert_eq!(tree.raw_inputs(), vec![item("ore")]);
+        assert_eq!(
+            graph.dependencies(item("ore")),
+            (DependencyTree::Raw(item("ore")), Vec::new())
+        );
+    }
+
+    #[test]
+    fn cyclic_recipes_are_pruned_and_reported() {
+        let mut recipe_manifest: RecipeManifest = Manifest::new();
+        recipe_manifest.insert("composting".to_string(), recipe(&[("leaf", 1)], "soil"));
+        recipe_manifest.insert("growing".to_string(), recipe(&[("soil", 1)], "leaf"));
+        // An alternative that does not loop back
+        recipe_manifest.insert("gathering".to_string(), recipe(&[("twig", 2)], "leaf"));
+
+        let graph = RecipeGraph::build(
+            &recipe_manifest,
+            &Manifest::new(),
+            &item_manifest(&["leaf", "soil", "twig"]),
+        );
+
+        let (tree, cycles) = graph.dependencies(item("soil"));
+        assert_eq!(
+            cycles,
+            vec![DependencyError::Cycle {
+                items: vec!["soil".to_string(), "leaf".to_string(), "soil".to_string()],
+            }]
+        );
+        assert_eq!(tree.raw_inputs(), vec![item("twig")]);
+
+        let DependencyTree::Crafted { steps, .. } = tree else {
+            panic!("soil should still be craftable");
+        };
+        let StepInput::Item {
+            tree: leaf_tree, ..
+        } = &steps[0].inputs[0]
+        else {
+            panic!("composting should need leaves");
+        };
+        let DependencyTree::Crafted {
+            steps: leaf_steps, ..
+        } = &**leaf_tree
+        else {
+            panic!("leaves should still be craftable");
+        };
+        let leaf_recipes: Vec<Id<Recipe>> = leaf_steps.iter().map(|step| step.recipe_id).collect();
+        assert_eq!(leaf_recipes, vec![Id::from_name("gathering".to_string())]);
+
+        // Without the alternative, leaves can only be gathered from the world
+        let mut recipe_manifest: RecipeManifest = Manifest::new();
+        recipe_manifest.insert("composting".to_string(), recipe(&[("leaf", 1)], "soil"));
+        recipe_manifest.insert("growing".to_string(), recipe(&[("soil", 1)], "leaf"));
+        let graph = RecipeGraph::build(
+            &recipe_manifest,
+            &Manifest::new(),
+            &item_manifest(&["leaf", "soil"]),
+        );
+
+        let (tree, cycles) = graph.dependencies(item("soil"));
+        assert_eq!(cycles.len(), 1);
+        assert_eq!(tree.raw_inputs(), vec![item("leaf")]);
+    }
+
+    #[test]
+    fn shared_subtrees_are_resolved_once() {
+        // Each item can be made in two ways from the next, which doubles the size of a naively expanded tree
+        let names: Vec<String> = (0..=40).map(|level| format!("item_{level}")).collect();
+        let mut recipe_manifest: RecipeManifest = Manifest::new();
+        for level in 0..40 {
+            for variant in ["a", "b"] {
+                recipe_manifest.insert(
+                    format!("recipe_{level}_{variant}"),
+                    recipe(&[(names[level + 1].as_str(), 1)], &names[level]),
+                );
+            }
+        }
+
+        let name_refs: Vec<&str> = names.iter().map(String::as_str).collect();
+        let graph = RecipeGraph::build(
+            &recipe_manifest,
+            &Manifest::new(),
+            &item_manifest(&name_refs),
+        );
+
+        let (tree, cycles) = graph.dependencies(item("item_0"));
+        assert!(cycles.is_empty());
+        assert_eq!(tree.raw_inputs(), vec![item("item_40")]);
+
+        let DependencyTree::Crafted { steps, .. } = tree else {
+            panic!("item_0 should be craftable");
+        };
+        let shared_trees: Vec<&Arc<DependencyTree>> = steps
+            .iter()
+            .filter_map(|step| match &step.inputs[0] {
+                StepInput::Item { tree, .. } => Some(tree),
+                StepInput::Tagged { .. } => None,
+            })
+            .collect();
+        assert_eq!(shared_trees.len(), 2);
+        assert!(Arc::ptr_eq(shared_trees[0], shared_trees[1]));
+    }
+}
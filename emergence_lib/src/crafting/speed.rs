@@ -0,0 +1,263 @@
+//! Collects everything that speeds up or slows down crafting into a single multiplier.
+//!
+//! Each source registers a [`CraftSpeedModifier`] on the crafter's [`CraftSpeed`] every tick,
+//! rather than adjusting crafting progress directly.
+//! Modifiers compose multiplicatively, and are kept around so that players can see why a structure is fast or slow.
+
+use std::{fmt::Display, time::Duration};
+
+use bevy::prelude::*;
+
+use crate::{
+    geometry::{MapGeometry, VoxelPos},
+    organisms::Organism,
+    structures::flood_damage::{FloodDamaged, FLOOD_DAMAGE_CRAFT_SPEED},
+    terrain::fertility::SoilFertility,
+};
+
+use super::{
+    power::{EnergyConsumer, PowerGrid},
+    recipe::{ActiveRecipe, RecipeManifest},
+    workers::WorkersPresent,
+};
+
+/// A single reason that a structure is crafting faster or slower than usual.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum CraftSpeedModifier {
+    /// Workers are present at a recipe that requires them.
+    ///
+    /// Crafting speed is proportional to the fraction of the required workers present.
+    Workers {
+        /// The number of workers present, discounted by their fatigue.
+        effective_workers: f32,
+        /// The number of workers that the recipe requires to craft at full speed.
+        workers_required: u8,
+    },
+    /// The power grid cannot meet the demand of all of its consumers.
+    Brownout {
+        /// The fraction of demand that is met, between 0 and 1.
+        satisfaction: f32,
+    },
+    /// An organism is growing in soil that is more or less fertile than usual.
+    Fertility {
+        /// The [`SoilFertility`] of the tile the organism is growing on.
+        fertility: f32,
+    },
+    /// The structure was damaged by sustained flooding.
+    FloodDamage,
+}
+
+impl CraftSpeedModifier {
+    /// The factor by which this modifier scales crafting speed.
+    pub(crate) fn multiplier(&self) -> f32 {
+        match *self {
+            CraftSpeedModifier::Workers {
+                effective_workers,
+                workers_required,
+            } => effective_workers / workers_required as f32,
+            CraftSpeedModifier::Brownout { satisfaction } => satisfaction,
+            CraftSpeedModifier::Fertility { fertility } => fertility,
+            CraftSpeedModifier::FloodDamage => FLOOD_DAMAGE_CRAFT_SPEED,
+        }
+    }
+}
+
+impl Display for CraftSpeedModifier {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let multiplier = self.multiplier();
+        match self {
+            CraftSpeedModifier::Workers {
+                effective_workers,
+                workers_required,
+            } => write!(
+                f,
+                "{effective_workers:.1}/{workers_required} workers ×{multiplier:.2}"
+            ),
+            CraftSpeedModifier::Brownout { .. } => write!(f, "brownout ×{multiplier:.2}"),
+            CraftSpeedModifier::Fertility { .. } => {
+                write!(f, "soil fertility ×{multiplier:.2}")
+            }
+            CraftSpeedModifier::FloodDamage => write!(f, "flood damage ×{multiplier:.2}"),
+        }
+    }
+}
+
+/// How quickly a structure is currently crafting, relative to the base craft time of its recipe.
+///
+/// This is rebuilt from scratch by [`update_craft_speed`] every tick.
+#[derive(Component, Debug, Clone, Default, PartialEq)]
+pub(crate) struct CraftSpeed {
+    /// The modifiers currently affecting this structure, in the order they were registered.
+    modifiers: Vec<CraftSpeedModifier>,
+}
+
+impl CraftSpeed {
+    /// Removes all modifiers, returning to the base crafting speed.
+    pub(crate) fn clear(&mut self) {
+        self.modifiers.clear();
+    }
+
+    /// Adds a modifier, which is combined multiplicatively with any others.
+    pub(crate) fn register(&mut self, modifier: CraftSpeedModifier) {
+        self.modifiers.push(modifier);
+    }
+
+    /// The combined factor by which crafting speed is scaled.
+    pub(crate) fn multiplier(&self) -> f32 {
+        self.modifiers
+            .iter()
+            .map(CraftSpeedModifier::multiplier)
+            .product()
+    }
+
+    /// The progress made during a tick lasting `period`.
+    pub(crate) fn progress(&self, period: Duration) -> Duration {
+        period.mul_f32(self.multiplier())
+    }
+
+    /// How long a recipe that usually takes `craft_time` will take to craft at this speed.
+    ///
+    /// Returns [`None`] if crafting has stalled completely.
+    pub(crate) fn craft_duration(&self, craft_time: Duration) -> Option<Duration> {
+        let multiplier = self.multiplier();
+        if multiplier > 0. {
+            Some(craft_time.div_f32(multiplier))
+        } else {
+            None
+        }
+    }
+}
+
+impl Display for CraftSpeed {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "×{:.2}", self.multiplier())?;
+
+        if !self.modifiers.is_empty() {
+            let breakdown: Vec<String> = self.modifiers.iter().map(ToString::to_string).collect();
+            write!(f, " ({})", breakdown.join(", "))?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Collects the [`CraftSpeedModifier`]s that apply to each crafting structure this tick.
+pub(super) fn update_craft_speed(
+    mut crafting_query: Query<(
+        &mut CraftSpeed,
+        &ActiveRecipe,
+        &WorkersPresent,
+        &VoxelPos,
+        Option<&EnergyConsumer>,
+        Option<&Organism>,
+        Option<&FloodDamaged>,
+    )>,
+    terrain_query: Query<&SoilFertility>,
+    recipe_manifest: Res<RecipeManifest>,
+    power_grid: Res<PowerGrid>,
+    map_geometry: Res<MapGeometry>,
+) {
+    for (
+        mut craft_speed,
+        active_recipe,
+        workers_present,
+        voxel_pos,
+        maybe_energy_consumer,
+        maybe_organism,
+        maybe_flood_damaged,
+    ) in crafting_query.iter_mut()
+    {
+        craft_speed.clear();
+
+        // Many hands make light work!
+        if let Some(recipe_id) = active_recipe.recipe_id() {
+            let workers_required = recipe_manifest.get(*recipe_id).workers_required();
+            if workers_required > 0 {
+                craft_speed.register(CraftSpeedModifier::Workers {
+                    effective_workers: workers_present.effective_workers(),
+                    workers_required,
+                });
+            }
+        }
+
+        // Browned-out crafters slow down in proportion to the missing power
+        if maybe_energy_consumer.is_some() && power_grid.satisfaction() < 1. {
+            craft_speed.register(CraftSpeedModifier::Brownout {
+                satisfaction: power_grid.satisfaction(),
+            });
+        }
+
+        // Organisms draw their nutrients from the soil they are growing in
+        if maybe_organism.is_some() {
+            let fertility = map_geometry
+                .get_terrain(voxel_pos.hex)
+                .ok()
+                .and_then(|terrain_entity| terrain_query.get(terrain_entity).ok())
+                .map_or(SoilFertility::BASELINE, SoilFertility::value);
+
+            if fertility != SoilFertility::BASELINE {
+                craft_speed.register(CraftSpeedModifier::Fertility { fertility });
+            }
+        }
+
+        if maybe_flood_damaged.is_some() {
+            craft_speed.register(CraftSpeedModifier::FloodDamage);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn modifiers_stack_multiplicatively() {
+        let mut craft_speed = CraftSpeed::default();
+        assert_eq!(craft_speed.multiplier(), 1.);
+        assert_eq!(craft_speed.to_string(), "×1.00");
+
+        craft_speed.register(CraftSpeedModifier::Workers {
+            effective_workers: 3.,
+            workers_required: 2,
+        });
+        craft_speed.register(CraftSpeedModifier::Brownout { satisfaction: 0.5 });
+
+        assert_eq!(craft_speed.multiplier(), 0.75);
+        assert_eq!(
+            craft_speed.craft_duration(Duration::from_secs(9)),
+            Some(Duration::from_secs(12))
+        );
+        assert_eq!(
+            craft_speed.progress(Duration::from_secs(4)),
+            Duration::from_secs(3)
+        );
+        assert_eq!(
+            craft_speed.to_string(),
+            "×0.75 (3.0/2 workers ×1.50, brownout ×0.50)"
+        );
+
+        craft_speed.clear();
+        assert_eq!(craft_speed, CraftSpeed::default());
+    }
+
+    #[test]
+    fn stalled_crafting_never_finishes() {
+        let mut craft_speed = CraftSpeed::default();
+        craft_speed.register(CraftSpeedModifier::Workers {
+            effective_workers: 0.,
+            workers_required: 1,
+        });
+
+        assert_eq!(craft_speed.craft_duration(Duration::from_secs(1)), None);
+        assert_eq!(craft_speed.progress(Duration::from_secs(1)), Duration::ZERO);
+    }
+
+    #[test]
+    fn flood_damage_slows_crafting() {
+        let mut craft_speed = CraftSpeed::default();
+        craft_speed.register(CraftSpeedModifier::FloodDamage);
+
+        assert_eq!(craft_speed.multiplier(), FLOOD_DAMAGE_CRAFT_SPEED);
+        assert_eq!(craft_speed.to_string(), "×0.50 (flood damage ×0.50)");
+    }
+}
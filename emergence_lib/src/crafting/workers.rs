@@ -1,14 +1,16 @@
 //! Code for allowing workers to help with crafting.
 
-use bevy::{prelude::*, utils::HashSet};
+use bevy::{prelude::*, utils::HashMap};
 
 use std::fmt::Display;
 
 /// The number of workers present / allowed at this structure.
 #[derive(Component, Debug, Clone, PartialEq)]
 pub(crate) struct WorkersPresent {
-    /// The list of workers present
-    workers: HashSet<Entity>,
+    /// The workers present, and how effectively each of them is working.
+    ///
+    /// A fully rested worker has an efficiency of 1.
+    workers: HashMap<Entity, f32>,
 
     /// The maximum number of workers allowed
     allowed: u8,
@@ -18,7 +20,7 @@ impl WorkersPresent {
     /// Create a new [`WorkersPresent`] with the provided maximum number of workers allowed.
     pub(crate) fn new(allowed: u8) -> Self {
         Self {
-            workers: HashSet::new(),
+            workers: HashMap::new(),
             allowed,
         }
     }
@@ -28,26 +30,63 @@ impl WorkersPresent {
         self.current() < self.allowed
     }
 
+    /// The maximum number of workers allowed.
+    pub(crate) fn allowed(&self) -> u8 {
+        self.allowed
+    }
+
+    /// Changes the maximum number of workers allowed.
+    ///
+    /// Workers who are already present are not sent away.
+    pub(crate) fn set_allowed(&mut self, allowed: u8) {
+        self.allowed = allowed;
+    }
+
     /// The current number of workers present.
     pub(crate) fn current(&self) -> u8 {
         self.workers.len() as u8
     }
 
     /// The current number of effective workers present.
+    ///
+    /// Tired workers count for less than rested ones.
     pub(crate) fn effective_workers(&self) -> f32 {
-        self.workers.len() as f32
+        self.workers.values().sum()
     }
 
     /// Adds a worker to this structure if there is room.
+    ///
+    /// New workers start out working at full efficiency.
     pub(crate) fn add_worker(&mut self, worker_entity: Entity) -> Result<(), ()> {
         if self.needs_more() {
-            self.workers.insert(worker_entity);
+            self.workers.entry(worker_entity).or_insert(1.);
             Ok(())
         } else {
             Err(())
         }
     }
 
+    /// Updates how effectively each worker is working, using the efficiency returned by `efficiency_of`.
+    ///
+    /// Workers for which `efficiency_of` returns [`None`] are left unchanged.
+    /// Returns `true` if any efficiency changed.
+    pub(crate) fn update_efficiencies(
+        &mut self,
+        efficiency_of: impl Fn(Entity) -> Option<f32>,
+    ) -> bool {
+        let mut changed = false;
+        for (&worker_entity, efficiency) in self.workers.iter_mut() {
+            if let Some(new_efficiency) = efficiency_of(worker_entity) {
+                if *efficiency != new_efficiency {
+                    *efficiency = new_efficiency;
+                    changed = true;
+                }
+            }
+        }
+
+        changed
+    }
+
     /// Removes a worker from this structure
     pub(crate) fn remove_worker(&mut self, worker_entity: Entity) {
         self.workers.remove(&worker_entity);
@@ -58,7 +97,7 @@ impl Display for WorkersPresent {
     fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(
             f,
-            "{present} ({effective_workers}) / {allowed}",
+            "{present} ({effective_workers:.1}) / {allowed}",
             present = self.current(),
             effective_workers = self.effective_workers(),
             allowed = self.allowed
@@ -1,5 +1,7 @@
 //! Tracks the location of key entities on the map, and caches information about the map for faster access.
 
+use std::sync::atomic::{AtomicU64, Ordering};
+
 use bevy::{
     prelude::*,
     utils::{HashMap, HashSet},
@@ -11,7 +13,10 @@ use crate::{
     items::inventory::InventoryState, structures::Footprint, units::actions::DeliveryMode,
 };
 
-use super::{DiscreteHeight, Facing, Height, VoxelKind, VoxelObject, VoxelPos};
+use super::{
+    DiscreteHeight, Facing, Height, PlacementBitfield, PlacementRequirements, VoxelKind,
+    VoxelObject, VoxelPos,
+};
 
 /// The overall size and arrangement of the map.
 #[derive(Debug, Resource, Clone)]
@@ -32,10 +37,36 @@ pub struct MapGeometry {
     ///
     /// The set of keys is the set of all non-empty [`VoxelPos`] positions on the map.
     voxel_index: HashMap<VoxelPos, VoxelObject>,
+    /// The heights of the voxels occupied by structures at each tile position.
+    ///
+    /// Tile positions without any structures are not stored.
+    structure_columns: HashMap<Hex, Vec<DiscreteHeight>>,
+    /// The voxels occupied by each structure entity.
+    ///
+    /// This allows structures to be removed without knowing where they were, or scanning the whole voxel index.
+    structure_voxels: HashMap<Entity, Vec<VoxelPos>>,
     /// The list of all passable neighbors for each tile position.
     ///
     /// The set of keys is the set of all [`VoxelPos`] that units could be found.
     walkable_neighbors: HashMap<VoxelPos, Neighbors>,
+    /// Identifies the current contents of `walkable_neighbors`.
+    ///
+    /// See [`MapGeometry::walkability_version`].
+    walkability_version: u64,
+    /// The maximum number of units that can share a tile of each structure, for structures with such a limit.
+    occupant_limits: HashMap<Entity, u8>,
+    /// The number of units standing in each voxel.
+    ///
+    /// Voxels without any units are not stored.
+    occupants: HashMap<VoxelPos, u8>,
+    /// The facts that affect placement of structures on top of the terrain at each tile position.
+    ///
+    /// This is updated incrementally whenever the voxel or height indexes change.
+    /// [`PlacementBitfield::UNDERWATER`] and [`PlacementBitfield::DEEP_WATER`] are the exceptions:
+    /// they are set by the water simulation, using [`MapGeometry::set_underwater`] and [`MapGeometry::set_deep_water`].
+    ///
+    /// The set of keys is the set of all valid [`Hex`] positions on the map.
+    placement_index: HashMap<Hex, PlacementBitfield>,
 }
 
 /// The six neighbors of a voxel position.
@@ -126,6 +157,11 @@ impl MapGeometry {
 
         let mut terrain_index = HashMap::default();
         let mut voxel_index = HashMap::default();
+        // The map starts out flat, dry and empty
+        let placement_index = hexes
+            .iter()
+            .map(|hex| (*hex, PlacementBitfield::EMPTY))
+            .collect();
 
         for hex in hexes {
             let voxel_pos = VoxelPos {
@@ -149,7 +185,13 @@ impl MapGeometry {
             terrain_index,
             height_index,
             voxel_index,
+            structure_columns: HashMap::default(),
+            structure_voxels: HashMap::default(),
             walkable_neighbors: HashMap::default(),
+            walkability_version: 0,
+            occupant_limits: HashMap::default(),
+            occupants: HashMap::default(),
+            placement_index,
         };
 
         map_geometry.recompute_walkable_neighbors();
@@ -182,14 +224,26 @@ impl MapGeometry {
     /// Gets the voxel object at the provided `voxel_pos`.
     #[inline]
     #[must_use]
-    pub(crate) fn get_voxel(&self, voxel_pos: VoxelPos) -> Option<&VoxelObject> {
+    pub(crate) fn get_voxel_object(&self, voxel_pos: VoxelPos) -> Option<&VoxelObject> {
         self.voxel_index.get(&voxel_pos)
     }
 
+    /// Gets the [`Entity`] that occupies exactly the provided `voxel_pos`, if any.
+    ///
+    /// Unlike [`MapGeometry::get_terrain`], this respects the height of `voxel_pos`,
+    /// so objects stacked on top of each other (like litter on a roof) can be told apart.
+    #[inline]
+    #[must_use]
+    pub fn get_voxel(&self, voxel_pos: VoxelPos) -> Option<Entity> {
+        self.voxel_index
+            .get(&voxel_pos)
+            .map(|voxel_object| voxel_object.entity)
+    }
+
     /// Returns the voxel position directly above the terrain at `hex`
     #[inline]
     #[must_use]
-    pub(crate) fn on_top_of_terrain(&self, hex: Hex) -> VoxelPos {
+    pub fn on_top_of_terrain(&self, hex: Hex) -> VoxelPos {
         let terrain_height: DiscreteHeight = self.get_height(hex).unwrap_or_default();
         VoxelPos {
             hex,
@@ -229,7 +283,7 @@ impl MapGeometry {
             return false;
         }
 
-        if let Some(voxel_data) = self.get_voxel(starting_pos) {
+        if let Some(voxel_data) = self.get_voxel_object(starting_pos) {
             if !voxel_data.object_kind.can_walk_through() {
                 return false;
             }
@@ -246,16 +300,223 @@ impl MapGeometry {
         footprint: &Footprint,
         facing: Facing,
     ) -> Result<(), AdditionError> {
-        match footprint
-            .normalized(facing, center)
-            .iter()
-            .all(|voxel_pos| self.is_voxel_clear(*voxel_pos).is_ok())
-        {
+        match self.is_placeable(center, footprint, facing, PlacementRequirements::STRUCTURE) {
+            true => Ok(()),
+            false => Err(AdditionError::AlreadyOccupied),
+        }
+    }
+
+    /// Is there enough space for a ghost of a structure with the provided `footprint` located at the `center` tile?
+    ///
+    /// Unlike [`MapGeometry::is_space_available`], existing ghost structures do not block placement,
+    /// as they will be replaced by the new ghost.
+    #[inline]
+    pub(crate) fn is_space_available_for_ghost(
+        &self,
+        center: VoxelPos,
+        footprint: &Footprint,
+        facing: Facing,
+    ) -> Result<(), AdditionError> {
+        match self.is_placeable(center, footprint, facing, PlacementRequirements::GHOST) {
             true => Ok(()),
             false => Err(AdditionError::AlreadyOccupied),
         }
     }
 
+    /// Can a structure with the provided `footprint` be placed at the `center` tile, meeting the `requirements`?
+    ///
+    /// When every voxel of the footprint sits directly on top of the terrain,
+    /// this only needs to combine the cached [`PlacementBitfield`] of each tile.
+    /// Otherwise, this falls back to [`MapGeometry::is_placeable_uncached`].
+    #[must_use]
+    pub fn is_placeable(
+        &self,
+        center: VoxelPos,
+        footprint: &Footprint,
+        facing: Facing,
+        requirements: PlacementRequirements,
+    ) -> bool {
+        let voxels = footprint.normalized(facing, center);
+
+        let mut combined_facts = PlacementBitfield::EMPTY;
+        for voxel_pos in voxels.iter() {
+            match self.cached_placement_facts(*voxel_pos) {
+                Some(facts) => combined_facts |= facts,
+                None => return self.is_placeable_uncached(center, footprint, facing, requirements),
+            }
+        }
+
+        let placeable = requirements.permits(combined_facts);
+
+        #[cfg(test)]
+        assert_eq!(
+            placeable,
+            self.is_placeable_uncached(center, footprint, facing, requirements),
+            "Cached placement check disagrees with the uncached check at {center} ({combined_facts})"
+        );
+
+        placeable
+    }
+
+    /// Can a structure with the provided `footprint` be placed at the `center` tile, meeting the `requirements`?
+    ///
+    /// This is the slow equivalent of [`MapGeometry::is_placeable`],
+    /// which re-derives the facts for each voxel from the voxel and height indexes instead of relying on the cache.
+    #[must_use]
+    pub fn is_placeable_uncached(
+        &self,
+        center: VoxelPos,
+        footprint: &Footprint,
+        facing: Facing,
+        requirements: PlacementRequirements,
+    ) -> bool {
+        footprint
+            .normalized(facing, center)
+            .into_iter()
+            .all(|voxel_pos| requirements.permits(self.placement_facts(voxel_pos)))
+    }
+
+    /// The cached [`PlacementBitfield`] for the tile at `hex`.
+    ///
+    /// Tiles outside of the map have no facts set.
+    #[inline]
+    #[must_use]
+    pub fn placement_flags(&self, hex: Hex) -> PlacementBitfield {
+        self.placement_index.get(&hex).copied().unwrap_or_default()
+    }
+
+    /// Records whether or not the tile at `hex` is covered by surface water.
+    ///
+    /// Tiles outside of the map are ignored.
+    pub(crate) fn set_underwater(&mut self, hex: Hex, underwater: bool) {
+        if let Some(facts) = self.placement_index.get_mut(&hex) {
+            facts.set(PlacementBitfield::UNDERWATER, underwater);
+        }
+    }
+
+    /// Records whether or not the tile at `hex` is covered by surface water too deep to wade through.
+    ///
+    /// Tiles outside of the map are ignored.
+    pub(crate) fn set_deep_water(&mut self, hex: Hex, deep_water: bool) {
+        if let Some(facts) = self.placement_index.get_mut(&hex) {
+            facts.set(PlacementBitfield::DEEP_WATER, deep_water);
+        }
+    }
+
+    /// Is the tile at `hex` covered by surface water too deep to wade through?
+    #[inline]
+    #[must_use]
+    pub(crate) fn is_deep_water(&self, hex: Hex) -> bool {
+        self.placement_flags(hex)
+            .contains(PlacementBitfield::DEEP_WATER)
+    }
+
+    /// The cached facts for `voxel_pos`, if it is directly on top of the terrain.
+    ///
+    /// Returns [`None`] for any other voxel, as only the top of the terrain is cached.
+    #[inline]
+    fn cached_placement_facts(&self, voxel_pos: VoxelPos) -> Option<PlacementBitfield> {
+        let terrain_height = self.height_index.get(&voxel_pos.hex)?;
+        if voxel_pos.height != terrain_height.above() {
+            return None;
+        }
+
+        self.placement_index.get(&voxel_pos.hex).copied()
+    }
+
+    /// Derives the facts that affect placement at `voxel_pos` from scratch.
+    ///
+    /// Surface water is tracked by the water simulation rather than the [`MapGeometry`],
+    /// so [`PlacementBitfield::UNDERWATER`] and [`PlacementBitfield::DEEP_WATER`] are always read from the cache.
+    fn placement_facts(&self, voxel_pos: VoxelPos) -> PlacementBitfield {
+        let mut facts = PlacementBitfield::EMPTY;
+
+        match self
+            .voxel_index
+            .get(&voxel_pos)
+            .map(|voxel_object| voxel_object.object_kind)
+        {
+            None => (),
+            Some(VoxelKind::GhostStructure) => facts.insert(PlacementBitfield::RESERVED),
+            Some(_) => facts.insert(PlacementBitfield::OCCUPIED),
+        }
+
+        if self.is_too_steep(voxel_pos.hex) {
+            facts.insert(PlacementBitfield::TOO_STEEP);
+        }
+
+        let cached_facts = self.placement_flags(voxel_pos.hex);
+        for water_fact in [PlacementBitfield::UNDERWATER, PlacementBitfield::DEEP_WATER] {
+            if cached_facts.contains(water_fact) {
+                facts.insert(water_fact);
+            }
+        }
+
+        facts
+    }
+
+    /// Is any tile adjacent to `hex` more than [`Height::MAX_STEP`] higher or lower than it?
+    ///
+    /// Tiles outside of the map are never too steep.
+    fn is_too_steep(&self, hex: Hex) -> bool {
+        let Ok(height) = self.get_height(hex) else {
+            return false;
+        };
+        let terrain_pos = VoxelPos { hex, height };
+
+        self.adjacent_hexes(hex)
+            .into_iter()
+            .flatten()
+            .any(|neighbor| match self.get_height(neighbor) {
+                Ok(neighbor_height) => {
+                    let neighbor_pos = VoxelPos {
+                        hex: neighbor,
+                        height: neighbor_height,
+                    };
+                    terrain_pos.abs_height_diff(neighbor_pos) > Height::MAX_STEP
+                }
+                Err(_) => false,
+            })
+    }
+
+    /// Updates the cached [`PlacementBitfield`] for the tile at `hex`.
+    ///
+    /// This must be called whenever the voxel on top of the terrain at `hex` or the height of any of its neighbors changes.
+    fn refresh_placement_flags(&mut self, hex: Hex) {
+        if !self.placement_index.contains_key(&hex) {
+            return;
+        }
+
+        let facts = self.placement_facts(self.on_top_of_terrain(hex));
+        self.placement_index.insert(hex, facts);
+    }
+
+    /// Returns the completed structures that would need to be removed to place a ghost with the provided `footprint` at the `center` tile.
+    ///
+    /// Each structure is only returned once, even if it covers several of the tiles.
+    /// Returns [`None`] if the space is blocked by anything other than structures and ghosts.
+    pub(crate) fn structures_in_the_way(
+        &self,
+        center: VoxelPos,
+        footprint: &Footprint,
+        facing: Facing,
+    ) -> Option<Vec<Entity>> {
+        let mut structures = Vec::new();
+        for voxel_pos in footprint.normalized(facing, center) {
+            if self.is_voxel_clear(voxel_pos).is_ok()
+                || self.get_ghost_structure(voxel_pos).is_some()
+            {
+                continue;
+            }
+
+            structures.push(self.get_structure(voxel_pos)?);
+        }
+
+        structures.sort();
+        structures.dedup();
+        Some(structures)
+    }
+
     /// Is there space in a single voxel?
     #[inline]
     pub fn is_voxel_clear(&self, voxel_pos: VoxelPos) -> Result<(), AdditionError> {
@@ -365,7 +626,7 @@ impl MapGeometry {
         voxel_pos: VoxelPos,
         delivery_mode: DeliveryMode,
     ) -> Option<Entity> {
-        if let Some(voxel_data) = self.get_voxel(voxel_pos) {
+        if let Some(voxel_data) = self.get_voxel_object(voxel_pos) {
             match delivery_mode {
                 DeliveryMode::DropOff => {
                     if voxel_data.object_kind.can_drop_off() {
@@ -393,7 +654,7 @@ impl MapGeometry {
     #[inline]
     #[must_use]
     pub(crate) fn get_workplace(&self, voxel_pos: VoxelPos) -> Option<Entity> {
-        if let Some(voxel_data) = self.get_voxel(voxel_pos) {
+        if let Some(voxel_data) = self.get_voxel_object(voxel_pos) {
             if voxel_data.object_kind.can_work_at() {
                 Some(voxel_data.entity)
             } else {
@@ -404,7 +665,9 @@ impl MapGeometry {
         }
     }
 
-    /// Gets the terrain [`Entity`] at the provided `voxel_pos`, if any.
+    /// Gets the terrain [`Entity`] at the provided `hex`, if any.
+    ///
+    /// This ignores height entirely: use [`MapGeometry::get_voxel`] to look up what is stored in a specific voxel.
     #[inline]
     pub fn get_terrain(&self, hex: Hex) -> Result<Entity, IndexError> {
         match self.terrain_index.get(&hex).copied() {
@@ -439,6 +702,12 @@ impl MapGeometry {
             },
         );
 
+        // Changing the height moves the top of the terrain, and changes the steepness of the neighbors too
+        self.refresh_placement_flags(hex);
+        for neighbor in self.adjacent_hexes(hex).into_iter().flatten() {
+            self.refresh_placement_flags(neighbor);
+        }
+
         self.recompute_walkable_neighbors();
 
         #[cfg(test)]
@@ -449,13 +718,55 @@ impl MapGeometry {
     #[inline]
     #[must_use]
     pub(crate) fn get_structure(&self, voxel_pos: VoxelPos) -> Option<Entity> {
-        let voxel_data = self.get_voxel(voxel_pos)?;
+        let voxel_data = self.get_voxel_object(voxel_pos)?;
         match voxel_data.object_kind {
             VoxelKind::Structure { .. } => Some(voxel_data.entity),
             _ => None,
         }
     }
 
+    /// Returns all structures with at least one voxel within `radius` hexes of `center`.
+    ///
+    /// Height is ignored when measuring distance.
+    /// Structures that take up multiple tiles are only returned once,
+    /// paired with whichever of their voxels is closest to `center`.
+    /// The results are sorted from nearest to farthest.
+    pub fn structures_in_radius(&self, center: VoxelPos, radius: u32) -> Vec<(VoxelPos, Entity)> {
+        let mut closest_voxels: HashMap<Entity, (u32, VoxelPos)> = HashMap::new();
+
+        // No tile on the map is further away than the diameter of the map
+        let radius = radius.min(2 * self.radius);
+        for hex in hexagon(center.hex, radius) {
+            let Some(heights) = self.structure_columns.get(&hex) else { continue };
+            let distance = center.hex.unsigned_distance_to(hex);
+
+            for &height in heights {
+                let voxel_pos = VoxelPos { hex, height };
+                let Some(entity) = self.get_structure(voxel_pos) else { continue };
+
+                closest_voxels
+                    .entry(entity)
+                    .and_modify(|closest| {
+                        if (distance, voxel_pos.height) < (closest.0, closest.1.height) {
+                            *closest = (distance, voxel_pos);
+                        }
+                    })
+                    .or_insert((distance, voxel_pos));
+            }
+        }
+
+        let mut structures: Vec<(u32, VoxelPos, Entity)> = closest_voxels
+            .into_iter()
+            .map(|(entity, (distance, voxel_pos))| (distance, voxel_pos, entity))
+            .collect();
+        structures.sort_by_key(|&(distance, _, entity)| (distance, entity));
+
+        structures
+            .into_iter()
+            .map(|(_, voxel_pos, entity)| (voxel_pos, entity))
+            .collect()
+    }
+
     /// Adds the provided `structure_entity` to the voxel index at the provided `center`.
     #[inline]
     pub(crate) fn add_structure(
@@ -478,6 +789,15 @@ impl MapGeometry {
                 },
             };
             self.voxel_index.insert(voxel_pos, voxel_data);
+            self.structure_columns
+                .entry(voxel_pos.hex)
+                .or_default()
+                .push(voxel_pos.height);
+            self.structure_voxels
+                .entry(structure_entity)
+                .or_default()
+                .push(voxel_pos);
+            self.refresh_placement_flags(voxel_pos.hex);
 
             self.recompute_walkable_neighbors();
         }
@@ -504,9 +824,12 @@ impl MapGeometry {
         }
 
         let entity = tentative_entry.entity;
+        self.structure_voxels.remove(&entity);
 
         for voxel_pos in footprint.normalized(facing, center) {
             self.voxel_index.remove(&voxel_pos);
+            self.remove_from_structure_column(voxel_pos);
+            self.refresh_placement_flags(voxel_pos.hex);
         }
 
         self.recompute_walkable_neighbors();
@@ -517,6 +840,42 @@ impl MapGeometry {
         Some(entity)
     }
 
+    /// Removes every voxel occupied by the provided `structure_entity` from the voxel index.
+    ///
+    /// Unlike [`MapGeometry::remove_structure`], this does not need to know where the structure was,
+    /// which makes it suitable for cleaning up after structures that have already been despawned.
+    /// Returns `true` if any voxels were removed.
+    pub(crate) fn remove_structure_entity(&mut self, structure_entity: Entity) -> bool {
+        self.occupant_limits.remove(&structure_entity);
+
+        let Some(removed_voxels) = self.structure_voxels.remove(&structure_entity) else {
+            return false;
+        };
+
+        for voxel_pos in removed_voxels {
+            self.voxel_index.remove(&voxel_pos);
+            self.remove_from_structure_column(voxel_pos);
+            self.refresh_placement_flags(voxel_pos.hex);
+        }
+
+        self.recompute_walkable_neighbors();
+
+        #[cfg(test)]
+        self.validate();
+
+        true
+    }
+
+    /// Records that the structure voxel at `voxel_pos` has been removed from the voxel index.
+    fn remove_from_structure_column(&mut self, voxel_pos: VoxelPos) {
+        let Some(heights) = self.structure_columns.get_mut(&voxel_pos.hex) else { return };
+
+        heights.retain(|&height| height != voxel_pos.height);
+        if heights.is_empty() {
+            self.structure_columns.remove(&voxel_pos.hex);
+        }
+    }
+
     /// Adds the provided `litter_entity` to the voxel index at the provided `center`.
     ///
     /// If the voxel is not clear, an [`AdditionError`] will be returned instead.
@@ -539,6 +898,7 @@ impl MapGeometry {
         // FIXME: This overwrites the existing entry
         // Instead, litter should be placed in the nearest empty voxel on the ground
         self.voxel_index.insert(voxel_pos, voxel_data);
+        self.refresh_placement_flags(voxel_pos.hex);
         self.recompute_walkable_neighbors();
 
         #[cfg(test)]
@@ -577,6 +937,7 @@ impl MapGeometry {
 
         let entity = tentative_entry.entity;
         self.voxel_index.remove(&voxel_pos);
+        self.refresh_placement_flags(voxel_pos.hex);
 
         self.recompute_walkable_neighbors();
 
@@ -625,7 +986,7 @@ impl MapGeometry {
     #[inline]
     #[must_use]
     pub(crate) fn get_ghost_structure(&self, voxel_pos: VoxelPos) -> Option<Entity> {
-        let voxel_data = self.get_voxel(voxel_pos)?;
+        let voxel_data = self.get_voxel_object(voxel_pos)?;
         match voxel_data.object_kind {
             VoxelKind::GhostStructure => Some(voxel_data.entity),
             _ => None,
@@ -650,6 +1011,7 @@ impl MapGeometry {
             };
 
             self.voxel_index.insert(voxel_pos, voxel_data);
+            self.refresh_placement_flags(voxel_pos.hex);
         }
 
         // We do not need to update the passable neighbors, as ghost structures never block movement
@@ -660,6 +1022,71 @@ impl MapGeometry {
         Ok(())
     }
 
+    /// Moves the provided `ghost_structure_entity` from `old_center` so that it is centered at `new_center`.
+    ///
+    /// Only the voxels of the old footprint that are still claimed by the ghost are released.
+    /// If there is not enough space at `new_center`, those voxels are claimed again and an [`AdditionError`] is returned.
+    pub(crate) fn move_ghost_structure(
+        &mut self,
+        ghost_structure_entity: Entity,
+        old_center: VoxelPos,
+        new_center: VoxelPos,
+        footprint: &Footprint,
+        facing: Facing,
+    ) -> Result<(), AdditionError> {
+        let released_voxels =
+            self.release_ghost_voxels(ghost_structure_entity, old_center, footprint, facing);
+
+        let result =
+            self.add_ghost_structure(facing, new_center, footprint, ghost_structure_entity);
+        if result.is_err() {
+            for voxel_pos in released_voxels {
+                let voxel_data = VoxelObject {
+                    entity: ghost_structure_entity,
+                    object_kind: VoxelKind::GhostStructure,
+                };
+
+                self.voxel_index.insert(voxel_pos, voxel_data);
+                self.refresh_placement_flags(voxel_pos.hex);
+            }
+
+            #[cfg(test)]
+            self.validate();
+        }
+
+        result
+    }
+
+    /// Releases the voxels of the footprint centered at `center` that are still claimed by `ghost_structure_entity`.
+    ///
+    /// Voxels that have since been claimed by anything else are left alone.
+    /// Returns the released voxels.
+    pub(crate) fn release_ghost_voxels(
+        &mut self,
+        ghost_structure_entity: Entity,
+        center: VoxelPos,
+        footprint: &Footprint,
+        facing: Facing,
+    ) -> Vec<VoxelPos> {
+        let mut released_voxels = Vec::new();
+
+        for voxel_pos in footprint.normalized(facing, center) {
+            let claimed = matches!(
+                self.voxel_index.get(&voxel_pos),
+                Some(voxel_object) if voxel_object.entity == ghost_structure_entity
+                    && matches!(voxel_object.object_kind, VoxelKind::GhostStructure)
+            );
+
+            if claimed {
+                self.voxel_index.remove(&voxel_pos);
+                self.refresh_placement_flags(voxel_pos.hex);
+                released_voxels.push(voxel_pos);
+            }
+        }
+
+        released_voxels
+    }
+
     /// Removes any ghost structure entity found at the provided `voxel_pos` from the voxel index.
     ///
     /// Returns the removed entity, if any.
@@ -679,6 +1106,7 @@ impl MapGeometry {
 
         for voxel_pos in footprint.normalized(facing, center) {
             self.voxel_index.remove(&voxel_pos);
+            self.refresh_placement_flags(voxel_pos.hex);
         }
 
         self.recompute_walkable_neighbors();
@@ -730,6 +1158,23 @@ impl MapGeometry {
         }
     }
 
+    /// Returns an iterator over every [`VoxelPos`] that units could be found in.
+    ///
+    /// Pair this with [`MapGeometry::walkable_neighbors`] to walk the full navigation graph.
+    #[inline]
+    pub(crate) fn walkable_positions(&self) -> impl Iterator<Item = VoxelPos> + '_ {
+        self.walkable_neighbors.keys().copied()
+    }
+
+    /// Identifies the current navigation graph, as returned by [`MapGeometry::walkable_neighbors`].
+    ///
+    /// This changes whenever any walkable neighbor changes, and is never shared between different graphs,
+    /// even across maps. Caches derived from the navigation graph can be reused until it changes.
+    #[inline]
+    pub(crate) fn walkability_version(&self) -> u64 {
+        self.walkability_version
+    }
+
     /// Returns the walkable neighbor in the provided direction from `voxel_pos`, if any.
     #[inline]
     #[must_use]
@@ -745,6 +1190,96 @@ impl MapGeometry {
         neighbors.in_direction(direction)
     }
 
+    /// Sets the maximum number of units that can share each tile of `entity`, which may be a structure or a terrain tile.
+    ///
+    /// If `max_occupants` is [`None`], any number of units can share its tiles.
+    pub(crate) fn set_occupant_limit(&mut self, entity: Entity, max_occupants: Option<u8>) {
+        match max_occupants {
+            Some(max_occupants) => self.occupant_limits.insert(entity, max_occupants),
+            None => self.occupant_limits.remove(&entity),
+        };
+    }
+
+    /// The maximum number of units that can stand in `voxel_pos` at once, if limited.
+    ///
+    /// Units stand inside of structures they can walk through, and on top of structures with walkable roofs.
+    /// Otherwise, units standing directly on the terrain are limited by the terrain tile.
+    #[must_use]
+    pub(crate) fn max_occupants(&self, voxel_pos: VoxelPos) -> Option<u8> {
+        let structure_entity = match self.get_voxel_object(voxel_pos) {
+            Some(VoxelObject {
+                entity,
+                object_kind:
+                    VoxelKind::Structure {
+                        can_walk_through: true,
+                        ..
+                    },
+            }) => *entity,
+            _ => match self.get_voxel_object(voxel_pos.below()) {
+                Some(VoxelObject {
+                    entity,
+                    object_kind:
+                        VoxelKind::Structure {
+                            can_walk_on_roof: true,
+                            ..
+                        },
+                }) => *entity,
+                _ => return self.terrain_occupant_limit(voxel_pos),
+            },
+        };
+
+        self.occupant_limits.get(&structure_entity).copied()
+    }
+
+    /// The maximum number of units that can stand on the terrain tile directly below `voxel_pos`, if limited.
+    fn terrain_occupant_limit(&self, voxel_pos: VoxelPos) -> Option<u8> {
+        let terrain_entity = self.get_terrain(voxel_pos.hex).ok()?;
+        if voxel_pos != self.on_top_of_terrain(voxel_pos.hex) {
+            return None;
+        }
+
+        self.occupant_limits.get(&terrain_entity).copied()
+    }
+
+    /// The number of units standing in `voxel_pos`.
+    #[must_use]
+    pub(crate) fn occupants(&self, voxel_pos: VoxelPos) -> u8 {
+        self.occupants.get(&voxel_pos).copied().unwrap_or_default()
+    }
+
+    /// Can another unit enter `voxel_pos` without going over its [`MapGeometry::max_occupants`]?
+    ///
+    /// Full tiles are treated as blocked for pathing, until one of their occupants leaves.
+    #[must_use]
+    pub(crate) fn has_room_for_unit(&self, voxel_pos: VoxelPos) -> bool {
+        match self.max_occupants(voxel_pos) {
+            Some(max_occupants) => self.occupants(voxel_pos) < max_occupants,
+            None => true,
+        }
+    }
+
+    /// Records that a unit has moved from `from` to `to`.
+    pub(crate) fn move_occupant(&mut self, from: VoxelPos, to: VoxelPos) {
+        if let Some(count) = self.occupants.get_mut(&from) {
+            *count = count.saturating_sub(1);
+            if *count == 0 {
+                self.occupants.remove(&from);
+            }
+        }
+
+        let count = self.occupants.entry(to).or_default();
+        *count = count.saturating_add(1);
+    }
+
+    /// Recounts the units standing in each voxel, given the position of every unit.
+    pub(crate) fn count_occupants(&mut self, unit_positions: impl IntoIterator<Item = VoxelPos>) {
+        self.occupants.clear();
+        for voxel_pos in unit_positions {
+            let count = self.occupants.entry(voxel_pos).or_default();
+            *count = count.saturating_add(1);
+        }
+    }
+
     /// Returns an iterator over the set of empty voxels that are walkalbe from `voxel_pos`.
     pub(crate) fn empty_neighbors(
         &self,
@@ -760,7 +1295,7 @@ impl MapGeometry {
 
         for (voxel_pos, voxel_data) in self.voxel_index.iter() {
             if voxel_data.object_kind.can_walk_on_roof() {
-                let can_walk_through = match self.get_voxel(voxel_pos.above()) {
+                let can_walk_through = match self.get_voxel_object(voxel_pos.above()) {
                     Some(voxel_data) => voxel_data.object_kind.can_walk_through(),
                     None => true,
                 };
@@ -779,8 +1314,11 @@ impl MapGeometry {
     /// This will update the entire map at once.
     // PERF: only update the neighborhood of the provided `voxel_pos`
     fn recompute_walkable_neighbors(&mut self) {
+        /// The next unused [`MapGeometry::walkability_version`], shared by all maps.
+        static NEXT_WALKABILITY_VERSION: AtomicU64 = AtomicU64::new(1);
+
         let walkable_voxels = self.walkable_voxels();
-        self.walkable_neighbors.clear();
+        let mut walkable_neighbors = HashMap::with_capacity(walkable_voxels.len());
 
         for walkable_voxel in &walkable_voxels {
             let mut local_neighbors = Neighbors::NONE;
@@ -807,8 +1345,12 @@ impl MapGeometry {
                 }
             }
 
-            self.walkable_neighbors
-                .insert(*walkable_voxel, local_neighbors);
+            walkable_neighbors.insert(*walkable_voxel, local_neighbors);
+        }
+
+        if walkable_neighbors != self.walkable_neighbors {
+            self.walkable_neighbors = walkable_neighbors;
+            self.walkability_version = NEXT_WALKABILITY_VERSION.fetch_add(1, Ordering::Relaxed);
         }
 
         #[cfg(test)]
@@ -825,6 +1367,67 @@ impl MapGeometry {
         self.ensure_hex_keys_match();
         self.ensure_height_and_voxel_indexes_match();
         self.validate_walkable_voxels();
+        self.validate_placement_index();
+        self.validate_structure_columns();
+    }
+
+    /// Asserts that the structure columns list exactly the voxels occupied by structures.
+    fn validate_structure_columns(&self) {
+        let structure_voxels: HashSet<VoxelPos> = self
+            .voxel_index
+            .iter()
+            .filter(|(_, voxel_object)| {
+                matches!(voxel_object.object_kind, VoxelKind::Structure { .. })
+            })
+            .map(|(&voxel_pos, _)| voxel_pos)
+            .collect();
+
+        let column_voxels: HashSet<VoxelPos> = self
+            .structure_columns
+            .iter()
+            .flat_map(|(&hex, heights)| heights.iter().map(move |&height| VoxelPos { hex, height }))
+            .collect();
+
+        assert_eq!(
+            structure_voxels, column_voxels,
+            "Structure columns do not match the structures in the voxel index"
+        );
+
+        for (structure_entity, voxels) in self.structure_voxels.iter() {
+            for voxel_pos in voxels {
+                let voxel_object = self.voxel_index.get(voxel_pos);
+                assert!(
+                    matches!(voxel_object, Some(VoxelObject { entity, object_kind: VoxelKind::Structure { .. } }) if entity == structure_entity),
+                    "Structure {structure_entity:?} is recorded at {voxel_pos:?}, but the voxel index holds {voxel_object:?}"
+                );
+            }
+        }
+
+        let indexed_voxel_count: usize = self.structure_voxels.values().map(Vec::len).sum();
+        assert_eq!(
+            indexed_voxel_count,
+            structure_voxels.len(),
+            "Structure voxels do not cover every structure in the voxel index"
+        );
+    }
+
+    /// Asserts that the cached placement facts for each tile match the facts derived from scratch.
+    fn validate_placement_index(&self) {
+        assert_eq!(
+            self.placement_index.keys().collect::<HashSet<_>>(),
+            self.terrain_index.keys().collect::<HashSet<_>>(),
+            "Placement index keys do not match terrain index keys"
+        );
+
+        for &hex in self.all_hexes() {
+            let cached = self.placement_flags(hex);
+            let derived = self.placement_facts(self.on_top_of_terrain(hex));
+
+            assert_eq!(
+                cached, derived,
+                "Cached placement facts at {hex:?} are {cached}, but should be {derived}"
+            );
+        }
     }
 
     /// Asserts that all of the heights in the map are between `Height::ZERO` and `Height::MAX`.
@@ -1005,6 +1608,48 @@ mod tests {
         map_geometry.validate();
     }
 
+    #[test]
+    fn structures_in_radius_are_found_exactly_once() {
+        let mut world = World::new();
+        let mut map_geometry = MapGeometry::new(&mut world, 5);
+        let facing = Facing::default();
+
+        let mut add_structure = |hex: Hex, footprint: &Footprint| {
+            let entity = world.spawn_empty().id();
+            let center = map_geometry.on_top_of_terrain(hex);
+            map_geometry
+                .add_structure(center, facing, footprint, false, false, entity)
+                .unwrap();
+            entity
+        };
+
+        let at_center = add_structure(Hex::ZERO, &Footprint::single());
+        let at_edge = add_structure(Hex::new(0, 2), &Footprint::single());
+        let too_far = add_structure(Hex::new(-3, 0), &Footprint::single());
+        // Centered outside the radius, but its footprint reaches inside it
+        let overlapping = add_structure(Hex::new(3, 0), &Footprint::hexagon(1));
+
+        let center = map_geometry.on_top_of_terrain(Hex::ZERO);
+        let found = map_geometry.structures_in_radius(center, 2);
+        let found_entities: Vec<Entity> = found.iter().map(|&(_, entity)| entity).collect();
+
+        assert_eq!(found.len(), 3);
+        assert!(found_entities.contains(&at_center));
+        assert!(found_entities.contains(&at_edge));
+        assert!(found_entities.contains(&overlapping));
+        assert!(!found_entities.contains(&too_far));
+
+        // The nearest structure comes first
+        assert_eq!(found[0], (center, at_center));
+
+        // Multi-tile structures are reported at their closest voxel
+        let (overlapping_pos, _) = found
+            .iter()
+            .find(|&&(_, entity)| entity == overlapping)
+            .unwrap();
+        assert_eq!(Hex::ZERO.unsigned_distance_to(overlapping_pos.hex), 2);
+    }
+
     #[test]
     fn walkable_voxels_respond_to_changes_correctly() {
         let mut map_geometry = MapGeometry::new(&mut World::new(), 0);
@@ -1213,6 +1858,85 @@ mod tests {
         assert_eq!(map_geometry.get_ghost_structure(voxel_pos), None);
     }
 
+    #[test]
+    fn ghosts_that_cannot_move_keep_their_old_placement() {
+        let mut world = World::new();
+        let mut map_geometry = MapGeometry::new(&mut world, 1);
+        let facing = Facing::default();
+        let footprint = Footprint::default();
+        let ghost_entity = Entity::from_bits(42);
+
+        let origin = map_geometry.on_top_of_terrain(Hex::ZERO);
+        let blocked = map_geometry.on_top_of_terrain(Hex::new(1, 0));
+        let free = map_geometry.on_top_of_terrain(Hex::new(0, 1));
+        map_geometry
+            .add_structure(
+                blocked,
+                facing,
+                &footprint,
+                false,
+                false,
+                Entity::from_bits(7),
+            )
+            .unwrap();
+        map_geometry
+            .add_ghost_structure(facing, origin, &footprint, ghost_entity)
+            .unwrap();
+
+        assert!(map_geometry
+            .move_ghost_structure(ghost_entity, origin, blocked, &footprint, facing)
+            .is_err());
+        assert_eq!(map_geometry.get_ghost_structure(origin), Some(ghost_entity));
+        assert_eq!(map_geometry.get_ghost_structure(blocked), None);
+
+        assert_eq!(
+            map_geometry.move_ghost_structure(ghost_entity, origin, free, &footprint, facing),
+            Ok(())
+        );
+        assert_eq!(map_geometry.get_ghost_structure(origin), None);
+        assert_eq!(map_geometry.get_ghost_structure(free), Some(ghost_entity));
+    }
+
+    #[test]
+    fn voxel_lookups_distinguish_ground_from_roof() {
+        let mut world = World::new();
+        let mut map_geometry = MapGeometry::new(&mut world, 0);
+        let terrain_entity = map_geometry.get_terrain(Hex::ZERO).unwrap();
+        let ground_pos = VoxelPos {
+            hex: Hex::ZERO,
+            height: DiscreteHeight::ZERO,
+        };
+        let structure_pos = ground_pos.above();
+        let roof_pos = structure_pos.above();
+
+        let structure_entity = Entity::from_bits(42);
+        map_geometry
+            .add_structure(
+                structure_pos,
+                Facing::default(),
+                &Footprint::default(),
+                true,
+                false,
+                structure_entity,
+            )
+            .unwrap();
+
+        let litter_entity = Entity::from_bits(43);
+        let litter_pos = map_geometry.drop_litter(roof_pos, litter_entity);
+        assert_eq!(litter_pos, roof_pos);
+
+        assert_eq!(map_geometry.get_voxel(ground_pos), Some(terrain_entity));
+        assert_eq!(
+            map_geometry.get_voxel(structure_pos),
+            Some(structure_entity)
+        );
+        assert_eq!(map_geometry.get_voxel(roof_pos), Some(litter_entity));
+        assert_eq!(map_geometry.get_voxel(roof_pos.above()), None);
+
+        // Terrain lookups ignore height entirely
+        assert_eq!(map_geometry.get_terrain(roof_pos.hex), Ok(terrain_entity));
+    }
+
     #[test]
     fn can_change_height_of_terrain() {
         let mut world = World::new();
@@ -1300,4 +2024,238 @@ mod tests {
             assert_eq!(None, map_geometry.get_structure(voxel_pos));
         }
     }
+
+    #[test]
+    fn paths_at_capacity_are_blocked_for_additional_units() {
+        let mut world = World::new();
+        let mut map_geometry = MapGeometry::new(&mut world, 1);
+        let path_entity = world.spawn_empty().id();
+        let path_pos = map_geometry.on_top_of_terrain(Hex::ZERO);
+        let neighbor_pos = map_geometry.on_top_of_terrain(Hex::new(1, 0));
+
+        map_geometry
+            .add_structure(
+                path_pos,
+                Facing::default(),
+                &Footprint::single(),
+                false,
+                true,
+                path_entity,
+            )
+            .unwrap();
+        map_geometry.set_occupant_limit(path_entity, Some(2));
+        assert_eq!(map_geometry.max_occupants(path_pos), Some(2));
+
+        map_geometry.count_occupants([path_pos, neighbor_pos]);
+        assert!(map_geometry.has_room_for_unit(path_pos));
+
+        map_geometry.move_occupant(neighbor_pos, path_pos);
+        assert_eq!(map_geometry.occupants(path_pos), 2);
+        assert_eq!(map_geometry.occupants(neighbor_pos), 0);
+        assert!(!map_geometry.has_room_for_unit(path_pos));
+
+        // Plain terrain is never full
+        map_geometry.count_occupants([neighbor_pos; 10]);
+        assert!(map_geometry.has_room_for_unit(neighbor_pos));
+
+        // Unless its terrain type is limited
+        let neighbor_terrain = map_geometry.get_terrain(neighbor_pos.hex).unwrap();
+        map_geometry.set_occupant_limit(neighbor_terrain, Some(3));
+        assert_eq!(map_geometry.max_occupants(neighbor_pos), Some(3));
+        assert!(!map_geometry.has_room_for_unit(neighbor_pos));
+        // The limit only applies to units standing on the terrain itself
+        assert_eq!(map_geometry.max_occupants(neighbor_pos.above()), None);
+
+        // Once a unit leaves, there's room again
+        map_geometry.count_occupants([path_pos]);
+        assert!(map_geometry.has_room_for_unit(path_pos));
+    }
+
+    #[test]
+    fn placement_flags_follow_terraforming_and_construction() {
+        let mut world = World::new();
+        let mut map_geometry = MapGeometry::new(&mut world, 1);
+        let neighbor = Hex::ZERO.neighbor(hexx::Direction::Top);
+        assert_eq!(
+            map_geometry.placement_flags(Hex::ZERO),
+            PlacementBitfield::EMPTY
+        );
+
+        // A cliff is too steep on both sides
+        map_geometry.update_height(Hex::ZERO, DiscreteHeight(3));
+        assert!(map_geometry
+            .placement_flags(Hex::ZERO)
+            .contains(PlacementBitfield::TOO_STEEP));
+        assert!(map_geometry
+            .placement_flags(neighbor)
+            .contains(PlacementBitfield::TOO_STEEP));
+
+        map_geometry.update_height(Hex::ZERO, DiscreteHeight::ONE);
+        assert_eq!(
+            map_geometry.placement_flags(neighbor),
+            PlacementBitfield::EMPTY
+        );
+
+        // Structures sit on top of the terrain, wherever it is
+        let center = map_geometry.on_top_of_terrain(Hex::ZERO);
+        let facing = Facing::default();
+        let footprint = Footprint::single();
+        map_geometry
+            .add_ghost_structure(facing, center, &footprint, Entity::from_bits(1))
+            .unwrap();
+        assert_eq!(
+            map_geometry.placement_flags(Hex::ZERO),
+            PlacementBitfield::RESERVED
+        );
+        assert!(map_geometry.is_placeable(
+            center,
+            &footprint,
+            facing,
+            PlacementRequirements::GHOST
+        ));
+        assert!(!map_geometry.is_placeable(
+            center,
+            &footprint,
+            facing,
+            PlacementRequirements::STRUCTURE
+        ));
+
+        map_geometry.remove_ghost_structure(center, &footprint, facing);
+        map_geometry
+            .add_structure(
+                center,
+                facing,
+                &footprint,
+                false,
+                false,
+                Entity::from_bits(2),
+            )
+            .unwrap();
+        assert_eq!(
+            map_geometry.placement_flags(Hex::ZERO),
+            PlacementBitfield::OCCUPIED
+        );
+
+        assert!(map_geometry.remove_structure_entity(Entity::from_bits(2)));
+        map_geometry.set_underwater(Hex::ZERO, true);
+        assert_eq!(
+            map_geometry.placement_flags(Hex::ZERO),
+            PlacementBitfield::UNDERWATER
+        );
+        assert!(map_geometry.is_placeable(
+            center,
+            &footprint,
+            facing,
+            PlacementRequirements::STRUCTURE
+        ));
+        assert!(!map_geometry.is_placeable(
+            center,
+            &footprint,
+            facing,
+            PlacementRequirements::STRUCTURE.forbid(PlacementBitfield::UNDERWATER)
+        ));
+    }
+
+    #[test]
+    fn cached_placement_checks_match_uncached_checks_on_randomized_maps() {
+        use rand::{rngs::SmallRng, Rng, SeedableRng};
+
+        let mut rng = SmallRng::seed_from_u64(378);
+        let footprints = [
+            Footprint::single(),
+            Footprint::hexagon(1),
+            Footprint::line(3),
+        ];
+        let facings = [
+            Facing::default(),
+            Facing {
+                direction: hexx::Direction::BottomRight,
+                mirrored: true,
+            },
+        ];
+        let requirements = [
+            PlacementRequirements::STRUCTURE,
+            PlacementRequirements::GHOST,
+            PlacementRequirements::GHOST
+                .forbid(PlacementBitfield::UNDERWATER | PlacementBitfield::TOO_STEEP),
+        ];
+
+        for _ in 0..5 {
+            let mut world = World::new();
+            let mut map_geometry = MapGeometry::new(&mut world, 4);
+            let hexes: Vec<Hex> = map_geometry.all_hexes().copied().collect();
+
+            for &hex in &hexes {
+                map_geometry.update_height(hex, DiscreteHeight(rng.gen_range(0..4)));
+            }
+
+            // Scatter objects of every kind across the map, ignoring any that do not fit
+            for (i, &hex) in hexes.iter().enumerate() {
+                let center = map_geometry.on_top_of_terrain(hex);
+                let footprint = &footprints[rng.gen_range(0..footprints.len())];
+                let facing = facings[rng.gen_range(0..facings.len())];
+                let entity = Entity::from_bits(i as u64 + 1);
+
+                match rng.gen_range(0..6) {
+                    0 => {
+                        let _ = map_geometry
+                            .add_structure(center, facing, footprint, false, false, entity);
+                    }
+                    1 => {
+                        let _ = map_geometry.add_ghost_structure(facing, center, footprint, entity);
+                    }
+                    2 => {
+                        let _ = map_geometry.drop_litter(center, entity);
+                    }
+                    3 => map_geometry.set_underwater(hex, true),
+                    _ => (),
+                }
+            }
+
+            // Then change the map underneath them
+            for (i, &hex) in hexes.iter().enumerate() {
+                match rng.gen_range(0..4) {
+                    0 => {
+                        map_geometry.remove_structure_entity(Entity::from_bits(i as u64 + 1));
+                    }
+                    1 => {
+                        // Terrain is never reshaped underneath structures
+                        let height = DiscreteHeight(rng.gen_range(0..4));
+                        if !map_geometry.structure_columns.contains_key(&hex) {
+                            map_geometry.update_height(hex, height);
+                        }
+                    }
+                    2 => map_geometry.set_underwater(hex, false),
+                    _ => (),
+                }
+            }
+
+            for &hex in &hexes {
+                let surface = map_geometry.on_top_of_terrain(hex);
+                for center in [surface, surface.above()] {
+                    for footprint in &footprints {
+                        for &facing in &facings {
+                            for &requirements in &requirements {
+                                assert_eq!(
+                                    map_geometry.is_placeable(
+                                        center,
+                                        footprint,
+                                        facing,
+                                        requirements
+                                    ),
+                                    map_geometry.is_placeable_uncached(
+                                        center,
+                                        footprint,
+                                        facing,
+                                        requirements
+                                    ),
+                                    "Placement checks disagree at {center}"
+                                );
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
 }
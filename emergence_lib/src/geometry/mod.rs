@@ -7,11 +7,15 @@ pub use indexing::MapGeometry;
 mod meshes;
 pub(crate) use meshes::hexagonal_column;
 
+mod placement;
+pub use placement::{PlacementBitfield, PlacementRequirements};
+
 mod position;
 pub use position::{DiscreteHeight, Height, Volume, VoxelPos};
 
 mod rotation;
-pub(crate) use rotation::{sync_rotation_to_facing, Facing, RotationDirection};
+pub use rotation::Facing;
+pub(crate) use rotation::{sync_rotation_to_facing, RotationDirection};
 
 mod voxels;
 pub(crate) use voxels::{VoxelKind, VoxelObject};
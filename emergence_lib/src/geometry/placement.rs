@@ -0,0 +1,173 @@
+//! Compact, per-tile summaries of the facts that decide whether structures can be placed.
+//!
+//! These are cached in the [`MapGeometry`](super::MapGeometry) and kept up to date as the map changes,
+//! so that checking a large footprint only needs a handful of bitwise operations.
+
+use core::fmt::Display;
+use core::ops::{BitOr, BitOrAssign};
+
+/// The facts about a single tile that can prevent structures from being placed there.
+///
+/// Each fact is stored as a single bit, so several can be combined with `|` and checked at once.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct PlacementBitfield(u8);
+
+impl PlacementBitfield {
+    /// None of the facts apply.
+    pub const EMPTY: PlacementBitfield = PlacementBitfield(0);
+
+    /// The voxel on top of the terrain contains a structure or litter.
+    pub const OCCUPIED: PlacementBitfield = PlacementBitfield(1 << 0);
+
+    /// The tile is covered by surface water.
+    pub const UNDERWATER: PlacementBitfield = PlacementBitfield(1 << 1);
+
+    /// At least one neighboring tile is more than [`Height::MAX_STEP`](super::Height::MAX_STEP) higher or lower than this one.
+    pub const TOO_STEEP: PlacementBitfield = PlacementBitfield(1 << 2);
+
+    /// The voxel on top of the terrain has been claimed by a ghost structure.
+    pub const RESERVED: PlacementBitfield = PlacementBitfield(1 << 3);
+
+    /// The tile is covered by surface water too deep to wade through.
+    ///
+    /// This is always set alongside [`PlacementBitfield::UNDERWATER`].
+    pub const DEEP_WATER: PlacementBitfield = PlacementBitfield(1 << 4);
+
+    /// Does this bitfield have every fact in `other` set?
+    #[inline]
+    #[must_use]
+    pub const fn contains(self, other: PlacementBitfield) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    /// Does this bitfield share any facts with `other`?
+    #[inline]
+    #[must_use]
+    pub const fn intersects(self, other: PlacementBitfield) -> bool {
+        self.0 & other.0 != 0
+    }
+
+    /// Sets every fact in `other`.
+    #[inline]
+    pub fn insert(&mut self, other: PlacementBitfield) {
+        self.0 |= other.0;
+    }
+
+    /// Clears every fact in `other`.
+    #[inline]
+    pub fn remove(&mut self, other: PlacementBitfield) {
+        self.0 &= !other.0;
+    }
+
+    /// Sets or clears every fact in `other`, depending on `value`.
+    #[inline]
+    pub fn set(&mut self, other: PlacementBitfield, value: bool) {
+        match value {
+            true => self.insert(other),
+            false => self.remove(other),
+        }
+    }
+}
+
+impl BitOr for PlacementBitfield {
+    type Output = PlacementBitfield;
+
+    fn bitor(self, rhs: PlacementBitfield) -> PlacementBitfield {
+        PlacementBitfield(self.0 | rhs.0)
+    }
+}
+
+impl BitOrAssign for PlacementBitfield {
+    fn bitor_assign(&mut self, rhs: PlacementBitfield) {
+        self.insert(rhs);
+    }
+}
+
+impl Display for PlacementBitfield {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let names = [
+            (PlacementBitfield::OCCUPIED, "occupied"),
+            (PlacementBitfield::UNDERWATER, "underwater"),
+            (PlacementBitfield::TOO_STEEP, "too steep"),
+            (PlacementBitfield::RESERVED, "reserved"),
+            (PlacementBitfield::DEEP_WATER, "deep water"),
+        ];
+
+        let set_names: Vec<&str> = names
+            .into_iter()
+            .filter(|(flag, _)| self.contains(*flag))
+            .map(|(_, name)| name)
+            .collect();
+
+        match set_names.is_empty() {
+            true => write!(f, "placeable"),
+            false => write!(f, "{}", set_names.join(", ")),
+        }
+    }
+}
+
+/// The facts that must *not* apply to any tile of a footprint for it to be placed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct PlacementRequirements {
+    /// The facts that block placement.
+    forbidden: PlacementBitfield,
+}
+
+impl PlacementRequirements {
+    /// Structures need tiles that are free of other structures, litter and ghosts.
+    pub const STRUCTURE: PlacementRequirements = PlacementRequirements {
+        forbidden: PlacementBitfield(PlacementBitfield::OCCUPIED.0 | PlacementBitfield::RESERVED.0),
+    };
+
+    /// Ghosts replace any existing ghosts, so only need tiles that are free of structures and litter.
+    pub const GHOST: PlacementRequirements = PlacementRequirements {
+        forbidden: PlacementBitfield::OCCUPIED,
+    };
+
+    /// Also forbids placement on any tile where the `facts` apply.
+    #[inline]
+    #[must_use]
+    pub const fn forbid(self, facts: PlacementBitfield) -> PlacementRequirements {
+        PlacementRequirements {
+            forbidden: PlacementBitfield(self.forbidden.0 | facts.0),
+        }
+    }
+
+    /// Can a tile with the provided `facts` be used?
+    #[inline]
+    #[must_use]
+    pub const fn permits(&self, facts: PlacementBitfield) -> bool {
+        !facts.intersects(self.forbidden)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn requirements_only_reject_forbidden_facts() {
+        let flooded_ghost = PlacementBitfield::RESERVED | PlacementBitfield::UNDERWATER;
+
+        assert!(PlacementRequirements::GHOST.permits(flooded_ghost));
+        assert!(!PlacementRequirements::STRUCTURE.permits(flooded_ghost));
+        assert!(!PlacementRequirements::GHOST
+            .forbid(PlacementBitfield::UNDERWATER)
+            .permits(flooded_ghost));
+        assert!(PlacementRequirements::STRUCTURE.permits(PlacementBitfield::TOO_STEEP));
+    }
+
+    #[test]
+    fn facts_can_be_set_and_cleared() {
+        let mut facts = PlacementBitfield::EMPTY;
+        facts.set(PlacementBitfield::OCCUPIED, true);
+        facts |= PlacementBitfield::TOO_STEEP;
+        assert!(facts.contains(PlacementBitfield::OCCUPIED | PlacementBitfield::TOO_STEEP));
+        assert_eq!(facts.to_string(), "occupied, too steep");
+
+        facts.set(PlacementBitfield::OCCUPIED, false);
+        facts.remove(PlacementBitfield::TOO_STEEP);
+        assert_eq!(facts, PlacementBitfield::EMPTY);
+        assert_eq!(facts.to_string(), "placeable");
+    }
+}
@@ -10,7 +10,7 @@ use std::{
     ops::{Add, AddAssign, Div, Mul, Sub, SubAssign},
 };
 
-use super::{Facing, MAP_LAYOUT};
+use super::{rotation::mirror_hex, Facing, MAP_LAYOUT};
 
 /// The discretized height of this tile
 ///
@@ -440,6 +440,16 @@ impl VoxelPos {
             height: self.height,
         }
     }
+
+    /// Returns the [`VoxelPos`] reflected across the line through the origin along the `axis` direction.
+    #[inline]
+    #[must_use]
+    pub(crate) fn mirrored(&self, axis: Direction) -> Self {
+        VoxelPos {
+            hex: mirror_hex(self.hex, axis),
+            height: self.height,
+        }
+    }
 }
 
 /// A volume of space, in tile units.
@@ -5,7 +5,7 @@ use std::f32::consts::PI;
 use bevy::prelude::*;
 use core::fmt::Display;
 use derive_more::Display;
-use hexx::Direction;
+use hexx::{Direction, Hex};
 use rand::{rngs::ThreadRng, seq::SliceRandom, Rng};
 
 use super::MAP_LAYOUT;
@@ -13,22 +13,47 @@ use super::MAP_LAYOUT;
 /// The hex direction that this entity is facing.
 ///
 /// Stored as a component on each entity with a grid-aligned rotation.
-#[derive(Component, Clone, Copy, Debug, PartialEq, Eq, Deref, DerefMut)]
-pub(crate) struct Facing {
+#[derive(Component, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Facing {
     /// The desired direction.
     ///
     /// Defaults to [`Direction::Top`].
     pub direction: Direction,
+    /// Is this entity mirrored across [`Facing::MIRROR_AXIS`] before being rotated to face its `direction`?
+    ///
+    /// Defaults to `false`.
+    pub mirrored: bool,
 }
 
 impl Facing {
+    /// The axis that mirrored entities are reflected across, before they are rotated.
+    ///
+    /// This is the unrotated direction, so mirroring never changes which way an entity faces.
+    pub(crate) const MIRROR_AXIS: Direction = Direction::Top;
+
     /// Generates a random facing.
     #[inline]
     #[must_use]
     pub(crate) fn random(rng: &mut impl Rng) -> Self {
         let direction = *Direction::ALL_DIRECTIONS.choose(rng).unwrap();
 
-        Self { direction }
+        Self {
+            direction,
+            mirrored: false,
+        }
+    }
+
+    /// Mirrors this facing across [`Facing::MIRROR_AXIS`].
+    ///
+    /// This reflects the direction, and toggles whether or not the entity is mirrored.
+    #[inline]
+    pub(crate) fn mirror(&mut self) {
+        let mirrored_hex = mirror_hex(Hex::ZERO.neighbor(self.direction), Self::MIRROR_AXIS);
+        self.direction = Direction::ALL_DIRECTIONS
+            .into_iter()
+            .find(|&direction| Hex::ZERO.neighbor(direction) == mirrored_hex)
+            .unwrap();
+        self.mirrored = !self.mirrored;
     }
 
     /// Rotates this facing one 60 degree step counterclockwise.
@@ -63,6 +88,7 @@ impl Default for Facing {
     fn default() -> Self {
         Facing {
             direction: Direction::Top,
+            mirrored: false,
         }
     }
 }
@@ -78,7 +104,29 @@ impl Display for Facing {
             Direction::BottomRight => "Bottom-right",
         };
 
-        write!(f, "{str}")
+        match self.mirrored {
+            true => write!(f, "{str} (mirrored)"),
+            false => write!(f, "{str}"),
+        }
+    }
+}
+
+/// Reflects the `hex` across the line through the origin that runs along the `axis` direction.
+///
+/// Hexes on the axis (and on its opposite direction) are unchanged.
+#[inline]
+#[must_use]
+pub(crate) fn mirror_hex(hex: Hex, axis: Direction) -> Hex {
+    let axis_hex = Hex::ZERO.neighbor(axis);
+    let Hex { x, y } = hex;
+
+    // Negates the cube coordinate that is zero along the axis, and swaps the other two (negated)
+    if axis_hex.x == 0 {
+        Hex::new(-x, x + y)
+    } else if axis_hex.y == 0 {
+        Hex::new(x + y, -y)
+    } else {
+        Hex::new(-y, -x)
     }
 }
 
@@ -31,6 +31,15 @@ pub(crate) mod infovis {
     /// The hue used to indicate that an action is forbidden.
     pub(crate) const FORBIDDEN_HUE: f32 = 0.;
 
+    /// The hue used to indicate that an action is forbidden because of the local water level.
+    pub(crate) const WRONG_MOISTURE_HUE: f32 = 210.;
+
+    /// The hue used to indicate that existing ghosts will be replaced.
+    pub(crate) const REPLACE_HUE: f32 = 30.;
+
+    /// The hue used to indicate that existing structures will be demolished and rebuilt.
+    pub(crate) const REBUILD_HUE: f32 = 280.;
+
     /// The hue of selected objects
     pub(crate) const HOVER_HUE: f32 = 55.;
     /// The saturation of selected objects
@@ -71,6 +80,19 @@ pub(crate) mod infovis {
         HOVER_LIGHTNESS,
         GHOST_ALPHA,
     );
+    /// The color used to tint previews that will replace existing ghosts
+    pub(crate) const REPLACE_PREVIEW_COLOR: Color =
+        Color::hsla(REPLACE_HUE, HOVER_SATURATION, HOVER_LIGHTNESS, GHOST_ALPHA);
+    /// The color used to tint previews that will demolish existing structures before they are built
+    pub(crate) const REBUILD_PREVIEW_COLOR: Color =
+        Color::hsla(REBUILD_HUE, HOVER_SATURATION, HOVER_LIGHTNESS, GHOST_ALPHA);
+    /// The color used to tint previews that cannot be built because their tiles are too wet or too dry
+    pub(crate) const WRONG_MOISTURE_PREVIEW_COLOR: Color = Color::hsla(
+        WRONG_MOISTURE_HUE,
+        HOVER_SATURATION,
+        HOVER_LIGHTNESS,
+        GHOST_ALPHA,
+    );
 
     /// The color used to tint objects that are both selected and hovered.
     pub(crate) const SELECTION_AND_HOVER_COLOR: Color = Color::hsla(
@@ -112,6 +134,10 @@ pub(crate) mod infovis {
                 SignalKind::Demolish => 0.,
                 // Blue
                 SignalKind::Unit => 220.,
+                // Indigo
+                SignalKind::Avoid => 250.,
+                // Lime
+                SignalKind::Rest => 100.,
             }
         }
 
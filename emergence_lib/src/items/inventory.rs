@@ -1,5 +1,7 @@
 //! Storage of multiple items with a capacity.
 
+use std::time::Duration;
+
 use bevy::prelude::warn;
 use itertools::rev;
 use serde::{Deserialize, Serialize};
@@ -31,6 +33,22 @@ pub struct Inventory {
 
     /// The maximum number of item slots this inventory can hold.
     max_slot_count: usize,
+
+    /// The order in which stacks of the same item are drawn from when removing items.
+    #[serde(default)]
+    consumption_order: ConsumptionOrder,
+}
+
+/// The order in which items are taken from an [`Inventory`] that holds several stacks of the same item.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum ConsumptionOrder {
+    /// First in, first out: the oldest stack is used first.
+    Fifo,
+    /// Last in, first out: the newest stack is used first.
+    #[default]
+    Lifo,
+    /// The stack that is closest to spoiling is used first.
+    Spoilage,
 }
 
 impl Default for Inventory {
@@ -39,6 +57,15 @@ impl Default for Inventory {
     }
 }
 
+/// The outcome of [`Inventory::spoil`].
+#[derive(Debug, Default, Clone, PartialEq)]
+pub(crate) struct Spoilage {
+    /// Did any items spoil?
+    pub(crate) any_spoiled: bool,
+    /// Spoilage products that did not fit back into the inventory.
+    pub(crate) overflow: Vec<ItemCount>,
+}
+
 /// The fullness of an inventory
 #[derive(Debug, PartialEq, Eq, Hash, Default, Clone, Copy)]
 pub enum InventoryState {
@@ -74,6 +101,7 @@ impl Inventory {
         reserved_for: None,
         slots: Vec::new(),
         max_slot_count: 0,
+        consumption_order: ConsumptionOrder::Lifo,
     };
 
     /// Create an empty inventory with the given amount of slots.
@@ -82,6 +110,7 @@ impl Inventory {
             reserved_for,
             slots: Vec::new(),
             max_slot_count,
+            consumption_order: ConsumptionOrder::default(),
         }
     }
 
@@ -91,6 +120,7 @@ impl Inventory {
             reserved_for: Some(item_id),
             slots: vec![ItemSlot::empty(item_id, max)],
             max_slot_count: 1,
+            consumption_order: ConsumptionOrder::default(),
         }
     }
 
@@ -100,6 +130,7 @@ impl Inventory {
             reserved_for: Some(item_id),
             slots: vec![ItemSlot::full(item_id, max)],
             max_slot_count: 1,
+            consumption_order: ConsumptionOrder::default(),
         }
     }
 
@@ -109,6 +140,7 @@ impl Inventory {
             reserved_for: Some(item_id),
             slots: vec![ItemSlot::empty(item_id, max)],
             max_slot_count: 1,
+            consumption_order: ConsumptionOrder::default(),
         }
     }
 
@@ -122,6 +154,73 @@ impl Inventory {
         self.slots.iter_mut()
     }
 
+    /// Returns the total number of items of each type in the inventory.
+    ///
+    /// Slots holding the same item are combined into a single group, in the order that the item first appears.
+    /// Empty slots are included with a count of 0, as they still show which items this inventory holds.
+    pub fn grouped(&self) -> impl Iterator<Item = (ItemKind, u32)> {
+        let mut groups: Vec<(ItemKind, u32)> = Vec::new();
+
+        // There are only ever a handful of slots, so a linear search beats hashing
+        for slot in &self.slots {
+            let item_kind = ItemKind::Single(slot.item_id());
+            match groups.iter_mut().find(|(kind, _)| *kind == item_kind) {
+                Some((_, count)) => *count += slot.count(),
+                None => groups.push((item_kind, slot.count())),
+            }
+        }
+
+        groups.into_iter()
+    }
+
+    /// The order in which stacks of the same item are drawn from when removing items.
+    pub(crate) fn consumption_order(&self) -> ConsumptionOrder {
+        self.consumption_order
+    }
+
+    /// Sets the order in which stacks of the same item are drawn from when removing items.
+    pub(crate) fn set_consumption_order(&mut self, consumption_order: ConsumptionOrder) {
+        self.consumption_order = consumption_order;
+    }
+
+    /// Returns the indexes of the slots that hold `item_id`, in the order they should be drawn from.
+    ///
+    /// The age of each stack is tracked by [`ItemSlot::stocked_at`],
+    /// as slots that are emptied and refilled keep their place in the inventory.
+    fn slots_in_consumption_order(&self, item_id: Id<Item>) -> Vec<usize> {
+        let mut indexes: Vec<usize> = self
+            .slots
+            .iter()
+            .enumerate()
+            .filter(|(_, slot)| slot.is_for_item(item_id))
+            .map(|(index, _)| index)
+            .collect();
+
+        // Oldest first; this sort is stable, so stacks of the same age are kept in slot order
+        indexes.sort_by_key(|&index| self.slots[index].stocked_at());
+
+        match self.consumption_order {
+            ConsumptionOrder::Fifo => (),
+            ConsumptionOrder::Lifo => indexes.reverse(),
+            ConsumptionOrder::Spoilage => indexes.sort_by(|&a, &b| {
+                self.slots[a]
+                    .freshness()
+                    .total_cmp(&self.slots[b].freshness())
+            }),
+        }
+
+        indexes
+    }
+
+    /// The [`ItemSlot::stocked_at`] value for stacks added now, which is newer than any stack already in the inventory.
+    fn next_stock_stamp(&self) -> u64 {
+        self.slots
+            .iter()
+            .map(ItemSlot::stocked_at)
+            .max()
+            .map_or(0, |newest| newest + 1)
+    }
+
     /// Which type of item is this inventory reserved for, if any?
     pub(crate) fn reserved_for(&self) -> Option<Id<Item>> {
         self.reserved_for
@@ -173,6 +272,28 @@ impl Inventory {
             .sum()
     }
 
+    /// The average freshness of all items of the given type in the inventory, weighted by their count.
+    ///
+    /// If no such items are stored, they are considered to be perfectly fresh.
+    pub(crate) fn freshness_of_item(&self, item_id: Id<Item>) -> f32 {
+        let (total_count, total_freshness) = self
+            .slots
+            .iter()
+            .filter(|slot| slot.is_for_item(item_id))
+            .fold((0, 0.), |(count, freshness), slot| {
+                (
+                    count + slot.count(),
+                    freshness + slot.freshness() * slot.count() as f32,
+                )
+            });
+
+        if total_count == 0 {
+            ItemSlot::FRESH
+        } else {
+            total_freshness / total_count as f32
+        }
+    }
+
     /// Determine if the inventory holds enough of the given item.
     pub(crate) fn has_count_of_item(&self, item_count: &ItemCount) -> bool {
         self.item_count(item_count.item_id) >= item_count.count
@@ -224,6 +345,18 @@ impl Inventory {
         self.slots.len() == self.max_slot_count && self.slots.iter().all(|slot| slot.is_full())
     }
 
+    /// The maximum number of item slots this inventory can hold.
+    pub(crate) fn max_slot_count(&self) -> usize {
+        self.max_slot_count
+    }
+
+    /// Grows this inventory so that it can hold at least `max_slot_count` item slots.
+    ///
+    /// Inventories are never shrunk by this method.
+    pub(crate) fn expand_to(&mut self, max_slot_count: usize) {
+        self.max_slot_count = self.max_slot_count.max(max_slot_count);
+    }
+
     /// The number of slots that don't have an item in them.
     pub(crate) fn free_slot_count(&self) -> usize {
         self.max_slot_count - self.slots.len()
@@ -283,6 +416,41 @@ impl Inventory {
         }
     }
 
+    /// Merges partially filled slots of the same item together, up to their capacity.
+    ///
+    /// Items are moved from later slots into earlier ones, blending their freshness.
+    /// Any slots that are emptied by this process are removed.
+    pub(crate) fn merge_identical_slots(&mut self) {
+        for target in 0..self.slots.len() {
+            for source in (target + 1)..self.slots.len() {
+                if self.slots[target].is_full() {
+                    break;
+                }
+
+                if !self.slots[source].is_for_item(self.slots[target].item_id())
+                    || self.slots[source].is_empty()
+                {
+                    continue;
+                }
+
+                let moved = self.slots[source]
+                    .count()
+                    .min(self.slots[target].remaining_space());
+                let freshness = self.slots[source].freshness();
+                if self.slots[target].is_empty() {
+                    let stocked_at = self.slots[source].stocked_at();
+                    self.slots[target].set_stocked_at(stocked_at);
+                }
+
+                // Both operations are bounded by `moved`, so they cannot fail
+                let _ = self.slots[target].add_until_full_with_freshness(moved, freshness);
+                let _ = self.slots[source].remove_until_empty(moved);
+            }
+        }
+
+        self.clear_empty_slots();
+    }
+
     /// Adds an empty slot that is reserved for the provided `item_id`.
     ///
     /// # Warning
@@ -290,6 +458,21 @@ impl Inventory {
     /// This operation is infallible: if there are not enough slots available, the inventory size will be expanded.
     /// If the inventory is reserved for a different item, the reservation will be cleared.
     pub(crate) fn add_empty_slot(&mut self, item_id: Id<Item>, item_manifest: &ItemManifest) {
+        let stack_size = item_manifest.get(item_id).stack_size;
+        self.add_empty_slot_with_capacity(item_id, stack_size, item_manifest);
+    }
+
+    /// Adds an empty slot that is reserved for the provided `item_id`, and holds at most `max_item_count` items.
+    ///
+    /// # Warning
+    ///
+    /// Like [`Inventory::add_empty_slot`], this operation is infallible and will expand the inventory if needed.
+    pub(crate) fn add_empty_slot_with_capacity(
+        &mut self,
+        item_id: Id<Item>,
+        max_item_count: u32,
+        item_manifest: &ItemManifest,
+    ) {
         if !self.permits(item_id) {
             warn!(
                 "A reserved inventory was expanded to create an empty slot for {}",
@@ -298,17 +481,17 @@ impl Inventory {
             self.reserved_for = None;
         }
 
-        let stack_size = item_manifest.get(item_id).stack_size;
-        let empty_stack = ItemSlot::empty(item_id, stack_size);
+        let empty_stack = ItemSlot::empty(item_id, max_item_count);
 
         // Suppose we have a 3 slot inventory, of which 2 are filled.
         // The length is 2, and the max slot count is 3.
         // We want to add a new slot, so we need to find the first unallocated slot.
         // In this case, it's the 3rd slot, which is at index 2.
         // This is always equal to the length, as the 0-indexing and "1 past the end" behavior cancel out.
+        // If the inventory is already full, it must grow by one slot to keep `slots.len() <= max_slot_count`.
         let first_unallocated_slot = self.slots.len();
         if first_unallocated_slot >= self.max_slot_count {
-            self.max_slot_count = first_unallocated_slot;
+            self.max_slot_count = first_unallocated_slot + 1;
         }
 
         // By definition we're at the end of the slots, so we need to add a new one.
@@ -329,6 +512,18 @@ impl Inventory {
         &mut self,
         item_count: &ItemCount,
         item_manifest: &ItemManifest,
+    ) -> Result<(), AddOneItemError> {
+        self.try_add_item_with_freshness(item_count, ItemSlot::FRESH, item_manifest)
+    }
+
+    /// Try to add as many items with the given `freshness` to the inventory as possible, up to the given count.
+    ///
+    /// Works like [`Inventory::try_add_item`], but blends the `freshness` into any existing stacks.
+    pub(crate) fn try_add_item_with_freshness(
+        &mut self,
+        item_count: &ItemCount,
+        freshness: f32,
+        item_manifest: &ItemManifest,
     ) -> Result<(), AddOneItemError> {
         if !self.permits(item_count.item_id) {
             return Err(AddOneItemError {
@@ -337,6 +532,7 @@ impl Inventory {
         }
 
         let mut items_to_add = item_count.count;
+        let stock_stamp = self.next_stock_stamp();
 
         // Fill up the slots of this item
         for slot in self
@@ -344,7 +540,16 @@ impl Inventory {
             .iter_mut()
             .filter(|slot| slot.is_for_item(item_count.item_id))
         {
-            match slot.add_until_full(items_to_add) {
+            if items_to_add == 0 {
+                break;
+            }
+
+            // Emptied slots are reused, and hold the newest stack once refilled
+            if slot.is_empty() {
+                slot.set_stocked_at(stock_stamp);
+            }
+
+            match slot.add_until_full_with_freshness(items_to_add, freshness) {
                 Ok(_) => {
                     items_to_add = 0;
                     break;
@@ -359,8 +564,9 @@ impl Inventory {
                 item_count.item_id,
                 item_manifest.get(item_count.item_id).stack_size,
             );
+            new_slot.set_stocked_at(stock_stamp);
 
-            match new_slot.add_until_full(items_to_add) {
+            match new_slot.add_until_full_with_freshness(items_to_add, freshness) {
                 Ok(_) => {
                     items_to_add = 0;
                 }
@@ -506,15 +712,29 @@ impl Inventory {
     ///
     /// - If the slot has enough items, they are all removed and `Ok` is returned.
     /// - Otherwise, all items that are included are removed and `Err` is returned.
+    ///
+    /// Stacks are drawn from in the inventory's [`ConsumptionOrder`].
     pub fn try_remove_item(&mut self, item_count: &ItemCount) -> Result<(), RemoveOneItemError> {
+        self.try_remove_item_with_freshness(item_count, 0.)
+    }
+
+    /// Try to remove as many items that are at least `min_freshness` fresh from the inventory as possible,
+    /// up to the given count.
+    ///
+    /// Works like [`Inventory::try_remove_item`], but skips any stacks that are too stale.
+    pub(crate) fn try_remove_item_with_freshness(
+        &mut self,
+        item_count: &ItemCount,
+        min_freshness: f32,
+    ) -> Result<(), RemoveOneItemError> {
         let mut items_to_remove = item_count.count;
 
-        for slot in self
-            .slots
-            .iter_mut()
-            .filter(|slot| slot.is_for_item(item_count.item_id))
-            .rev()
-        {
+        for index in self.slots_in_consumption_order(item_count.item_id) {
+            let slot = &mut self.slots[index];
+            if slot.freshness() < min_freshness {
+                continue;
+            }
+
             match slot.remove_until_empty(items_to_remove) {
                 Ok(_) => {
                     items_to_remove = 0;
@@ -620,11 +840,12 @@ impl Inventory {
         // Skip the expensive work if there's nothing to move
         if actual > 0 {
             let actual_count = ItemCount::new(item_id, actual);
+            let freshness = self.freshness_of_item(item_id);
 
             // Unwraps are being used as assertions here: if this is panicking, this method is broken
             self.remove_item_all_or_nothing(&actual_count).unwrap();
             other
-                .add_item_all_or_nothing(&actual_count, item_manifest)
+                .try_add_item_with_freshness(&actual_count, freshness, item_manifest)
                 .unwrap();
         }
 
@@ -669,6 +890,96 @@ impl Inventory {
         result
     }
 
+    /// Ages all perishable items in this inventory by `elapsed`.
+    ///
+    /// Fully spoiled items are converted in place into the item they spoil into,
+    /// or destroyed if they have no spoilage product or this inventory is reserved for another item.
+    ///
+    /// If the product has a smaller stack size, the surplus is moved into other slots where possible;
+    /// whatever still does not fit is returned in [`Spoilage::overflow`] so the caller can spill it.
+    pub(crate) fn spoil(&mut self, elapsed: Duration, item_manifest: &ItemManifest) -> Spoilage {
+        let reserved_for = self.reserved_for;
+        let mut any_spoiled = false;
+        let mut surplus: Vec<ItemCount> = Vec::new();
+
+        for slot in self.slots.iter_mut() {
+            if !Self::decay_slot(slot, elapsed, item_manifest) {
+                continue;
+            }
+
+            any_spoiled = true;
+
+            let product = item_manifest
+                .get(slot.item_id())
+                .spoils_into
+                .filter(|&product_id| reserved_for.map_or(true, |id| id == product_id));
+
+            match product {
+                Some(product_id) => {
+                    let excess =
+                        slot.convert_into(product_id, item_manifest.get(product_id).stack_size);
+                    if excess > 0 {
+                        surplus.push(ItemCount::new(product_id, excess));
+                    }
+                }
+                None => {
+                    // Cannot fail: we're removing exactly the number of items in the slot
+                    slot.remove_until_empty(slot.count()).unwrap();
+                }
+            }
+        }
+
+        let mut overflow = Vec::new();
+        for item_count in surplus {
+            if let Err(AddOneItemError { excess_count }) =
+                self.try_add_item(&item_count, item_manifest)
+            {
+                overflow.push(excess_count);
+            }
+        }
+
+        Spoilage {
+            any_spoiled,
+            overflow,
+        }
+    }
+
+    /// Ages all perishable items in this inventory by `elapsed`, destroying any that are fully spoiled.
+    ///
+    /// Unlike [`Inventory::spoil`], this never changes which item each slot is for.
+    /// This is used for inventories whose layout is fixed by a recipe.
+    ///
+    /// Returns `true` if any items spoiled.
+    pub(crate) fn discard_spoiled(
+        &mut self,
+        elapsed: Duration,
+        item_manifest: &ItemManifest,
+    ) -> bool {
+        let mut any_spoiled = false;
+
+        for slot in self.slots.iter_mut() {
+            if Self::decay_slot(slot, elapsed, item_manifest) {
+                // Cannot fail: we're removing exactly the number of items in the slot
+                slot.remove_until_empty(slot.count()).unwrap();
+                any_spoiled = true;
+            }
+        }
+
+        any_spoiled
+    }
+
+    /// Ages the items in `slot` by `elapsed`, returning `true` if the slot contains fully spoiled items.
+    fn decay_slot(slot: &mut ItemSlot, elapsed: Duration, item_manifest: &ItemManifest) -> bool {
+        if slot.is_empty() {
+            return false;
+        }
+
+        match item_manifest.get(slot.item_id()).shelf_life {
+            Some(shelf_life) => slot.decay(elapsed, shelf_life),
+            None => false,
+        }
+    }
+
     /// The pretty formatting for this type
     pub fn display(&self, item_manifest: &ItemManifest) -> String {
         let slot_strings: Vec<String> = self
@@ -682,6 +993,51 @@ impl Inventory {
 
         format!("[{}]", slot_strings.join(", "))
     }
+
+    /// Returns a read-only copy of the items in this inventory.
+    ///
+    /// The snapshot does not borrow the inventory, so it can be held onto after the inventory changes.
+    pub fn snapshot(&self) -> InventorySnapshot {
+        let items = self
+            .grouped()
+            // Inventories only ever hold specific items, so there are no tags to skip in practice
+            .filter_map(|(item_kind, count)| match item_kind {
+                ItemKind::Single(item_id) => Some(ItemCount::new(item_id, count)),
+                ItemKind::Tag(_) => None,
+            })
+            .collect();
+
+        InventorySnapshot { items }
+    }
+}
+
+/// A read-only copy of the contents of an [`Inventory`], taken by [`Inventory::snapshot`].
+///
+/// This is decoupled from the ECS, and is useful for UI and tests that need a stable view of a structure's contents.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct InventorySnapshot {
+    /// The total number of each item, in the order that their slots appear.
+    pub items: Vec<ItemCount>,
+}
+
+impl InventorySnapshot {
+    /// The number of items matching `item_id` at the time the snapshot was taken.
+    pub fn item_count(&self, item_id: Id<Item>) -> u32 {
+        self.items
+            .iter()
+            .find(|item_count| item_count.item_id == item_id)
+            .map(|item_count| item_count.count)
+            .unwrap_or_default()
+    }
+
+    /// The pretty formatting for this type
+    pub fn display(&self, item_manifest: &ItemManifest) -> String {
+        self.items
+            .iter()
+            .map(|item_count| item_count.display(item_manifest))
+            .collect::<Vec<String>>()
+            .join("\n")
+    }
 }
 
 impl FromIterator<ItemSlot> for Inventory {
@@ -690,6 +1046,7 @@ impl FromIterator<ItemSlot> for Inventory {
             reserved_for: None,
             slots: iter.into_iter().collect(),
             max_slot_count: 0,
+            consumption_order: ConsumptionOrder::default(),
         };
 
         inventory.max_slot_count = inventory.slots.len();
@@ -708,21 +1065,24 @@ mod tests {
         manifest.insert(
             "leaf".to_string(),
             ItemData {
-                stack_size: 10,
                 compostable: true,
-                fluid: false,
                 buoyant: true,
-                seed: None,
+                ..ItemData::basic(10)
             },
         );
         manifest.insert(
             "mushroom".to_string(),
             ItemData {
-                stack_size: 10,
-                compostable: false,
-                fluid: false,
                 buoyant: true,
-                seed: None,
+                ..ItemData::basic(10)
+            },
+        );
+        manifest.insert(
+            "log".to_string(),
+            ItemData {
+                compostable: true,
+                buoyant: true,
+                ..ItemData::basic(50)
             },
         );
         manifest
@@ -732,6 +1092,7 @@ mod tests {
         Inventory {
             reserved_for: None,
             max_slot_count: 1,
+            consumption_order: ConsumptionOrder::default(),
             slots: vec![ItemSlot::new_with_count(
                 Id::from_name("mushroom".to_string()),
                 10,
@@ -744,6 +1105,7 @@ mod tests {
         Inventory {
             reserved_for: None,
             max_slot_count: 1,
+            consumption_order: ConsumptionOrder::default(),
             slots: vec![ItemSlot::new_with_count(
                 Id::from_name("mushroom".to_string()),
                 10,
@@ -756,6 +1118,7 @@ mod tests {
         Inventory {
             reserved_for: None,
             max_slot_count: 1,
+            consumption_order: ConsumptionOrder::default(),
             slots: vec![],
         }
     }
@@ -772,6 +1135,7 @@ mod tests {
         let inventory = Inventory {
             reserved_for: None,
             max_slot_count: 4,
+            consumption_order: ConsumptionOrder::default(),
             slots: vec![
                 ItemSlot::new_with_count(Id::from_name("leaf".to_string()), 10, 10),
                 ItemSlot::new_with_count(Id::from_name("leaf".to_string()), 10, 5),
@@ -787,6 +1151,7 @@ mod tests {
         let inventory = Inventory {
             reserved_for: None,
             max_slot_count: 4,
+            consumption_order: ConsumptionOrder::default(),
             slots: vec![
                 ItemSlot::new_with_count(Id::from_name("leaf".to_string()), 10, 10),
                 ItemSlot::new_with_count(Id::from_name("leaf".to_string()), 10, 5),
@@ -802,6 +1167,7 @@ mod tests {
         let inventory = Inventory {
             reserved_for: None,
             max_slot_count: 4,
+            consumption_order: ConsumptionOrder::default(),
             slots: vec![
                 ItemSlot::new_with_count(Id::from_name("leaf".to_string()), 10, 10),
                 ItemSlot::new_with_count(Id::from_name("leaf".to_string()), 10, 5),
@@ -826,6 +1192,7 @@ mod tests {
         let inventory = Inventory {
             reserved_for: None,
             max_slot_count: 4,
+            consumption_order: ConsumptionOrder::default(),
             slots: vec![
                 ItemSlot::new_with_count(Id::from_name("leaf".to_string()), 10, 10),
                 ItemSlot::new_with_count(Id::from_name("leaf".to_string()), 10, 5),
@@ -841,6 +1208,7 @@ mod tests {
         let inventory = Inventory {
             reserved_for: None,
             max_slot_count: 4,
+            consumption_order: ConsumptionOrder::default(),
             slots: vec![
                 ItemSlot::new_with_count(Id::from_name("leaf".to_string()), 10, 10),
                 ItemSlot::new_with_count(Id::from_name("leaf".to_string()), 10, 10),
@@ -857,6 +1225,7 @@ mod tests {
         let inventory = Inventory {
             reserved_for: None,
             max_slot_count: 4,
+            consumption_order: ConsumptionOrder::default(),
             slots: vec![
                 ItemSlot::new_with_count(Id::from_name("leaf".to_string()), 10, 10),
                 ItemSlot::new_with_count(Id::from_name("leaf".to_string()), 10, 5),
@@ -872,6 +1241,7 @@ mod tests {
         let inventory = Inventory {
             reserved_for: None,
             max_slot_count: 4,
+            consumption_order: ConsumptionOrder::default(),
             slots: vec![
                 ItemSlot::new_with_count(Id::from_name("leaf".to_string()), 10, 10),
                 ItemSlot::new_with_count(Id::from_name("leaf".to_string()), 10, 5),
@@ -887,6 +1257,7 @@ mod tests {
         let inventory = Inventory {
             reserved_for: None,
             max_slot_count: 4,
+            consumption_order: ConsumptionOrder::default(),
             slots: vec![
                 ItemSlot::new_with_count(Id::from_name("leaf".to_string()), 10, 10),
                 ItemSlot::new_with_count(Id::from_name("leaf".to_string()), 10, 5),
@@ -900,6 +1271,28 @@ mod tests {
         );
     }
 
+    #[test]
+    fn empty_slots_expand_full_inventories_by_one_slot() {
+        let leaf = Id::from_name("leaf".to_string());
+        let mut inventory = full_inventory();
+
+        inventory.add_empty_slot_with_capacity(leaf, 1, &item_manifest());
+        assert_eq!(inventory.max_slot_count(), 2);
+        assert_eq!(inventory.iter().count(), 2);
+        assert_eq!(inventory.free_slot_count(), 0);
+
+        // The new slot is usable straight away
+        inventory
+            .add_item_all_or_nothing(&ItemCount::new(leaf, 1), &item_manifest())
+            .unwrap();
+        assert_eq!(inventory.item_count(leaf), 1);
+
+        // Inventories with room to spare are not expanded
+        let mut inventory = empty_inventory();
+        inventory.add_empty_slot_with_capacity(leaf, 1, &item_manifest());
+        assert_eq!(inventory.max_slot_count(), 1);
+    }
+
     mod add {
         mod until_full_one_item {
             use super::super::item_manifest;
@@ -911,6 +1304,7 @@ mod tests {
                 let mut inventory = Inventory {
                     reserved_for: None,
                     max_slot_count: 4,
+                    consumption_order: ConsumptionOrder::default(),
                     slots: vec![
                         ItemSlot::new_with_count(Id::from_name("leaf".to_string()), 10, 10),
                         ItemSlot::new_with_count(Id::from_name("leaf".to_string()), 10, 5),
@@ -937,6 +1331,7 @@ mod tests {
                 let mut inventory = Inventory {
                     reserved_for: None,
                     max_slot_count: 4,
+                    consumption_order: ConsumptionOrder::default(),
                     slots: vec![
                         ItemSlot::new_with_count(Id::from_name("leaf".to_string()), 10, 10),
                         ItemSlot::new_with_count(Id::from_name("leaf".to_string()), 10, 5),
@@ -973,6 +1368,7 @@ mod tests {
                 let mut inventory = Inventory {
                     reserved_for: None,
                     max_slot_count: 4,
+                    consumption_order: ConsumptionOrder::default(),
                     slots: vec![
                         ItemSlot::new_with_count(Id::from_name("leaf".to_string()), 10, 10),
                         ItemSlot::new_with_count(Id::from_name("leaf".to_string()), 10, 5),
@@ -1008,6 +1404,23 @@ mod tests {
                 assert_eq!(inventory.item_count(Id::from_name("leaf".to_string())), 1);
             }
 
+            #[test]
+            fn large_counts_are_split_into_stacks_of_the_item_stack_size() {
+                let mut inventory = Inventory::new(4, None);
+
+                assert_eq!(
+                    inventory.add_item_all_or_nothing(
+                        &ItemCount::new(Id::from_name("log".to_string()), 120),
+                        &item_manifest()
+                    ),
+                    Ok(())
+                );
+
+                let slot_counts: Vec<u32> = inventory.iter().map(ItemSlot::count).collect();
+                assert_eq!(slot_counts, vec![50, 50, 20]);
+                assert_eq!(inventory.free_slot_count(), 1);
+            }
+
             #[test]
             fn adding_to_an_inventory_full_of_something_else_fails() {
                 let mut inventory = Inventory::new(1, None);
@@ -1039,6 +1452,7 @@ mod tests {
                 let mut inventory = Inventory {
                     reserved_for: None,
                     max_slot_count: 4,
+                    consumption_order: ConsumptionOrder::default(),
                     slots: vec![
                         ItemSlot::new_with_count(Id::from_name("leaf".to_string()), 10, 10),
                         ItemSlot::new_with_count(Id::from_name("leaf".to_string()), 10, 5),
@@ -1072,6 +1486,7 @@ mod tests {
                 let mut inventory = Inventory {
                     reserved_for: None,
                     max_slot_count: 4,
+                    consumption_order: ConsumptionOrder::default(),
                     slots: vec![
                         ItemSlot::new_with_count(Id::from_name("leaf".to_string()), 10, 10),
                         ItemSlot::new_with_count(Id::from_name("leaf".to_string()), 10, 5),
@@ -1101,6 +1516,7 @@ mod tests {
                 let mut inventory = Inventory {
                     reserved_for: None,
                     max_slot_count: 4,
+                    consumption_order: ConsumptionOrder::default(),
                     slots: vec![
                         ItemSlot::new_with_count(Id::from_name("leaf".to_string()), 10, 10),
                         ItemSlot::new_with_count(Id::from_name("leaf".to_string()), 10, 5),
@@ -1141,6 +1557,7 @@ mod tests {
                 let mut inventory = Inventory {
                     reserved_for: None,
                     max_slot_count: 4,
+                    consumption_order: ConsumptionOrder::default(),
                     slots: vec![
                         ItemSlot::new_with_count(Id::from_name("leaf".to_string()), 10, 10),
                         ItemSlot::new_with_count(Id::from_name("leaf".to_string()), 10, 5),
@@ -1165,6 +1582,7 @@ mod tests {
                 let mut inventory = Inventory {
                     reserved_for: None,
                     max_slot_count: 4,
+                    consumption_order: ConsumptionOrder::default(),
                     slots: vec![
                         ItemSlot::new_with_count(Id::from_name("leaf".to_string()), 10, 10),
                         ItemSlot::new_with_count(Id::from_name("leaf".to_string()), 10, 5),
@@ -1195,6 +1613,7 @@ mod tests {
                 let mut inventory = Inventory {
                     reserved_for: None,
                     max_slot_count: 4,
+                    consumption_order: ConsumptionOrder::default(),
                     slots: vec![
                         ItemSlot::new_with_count(Id::from_name("leaf".to_string()), 10, 10),
                         ItemSlot::new_with_count(Id::from_name("leaf".to_string()), 10, 5),
@@ -1221,6 +1640,7 @@ mod tests {
                 let mut inventory = Inventory {
                     reserved_for: None,
                     max_slot_count: 4,
+                    consumption_order: ConsumptionOrder::default(),
                     slots: vec![
                         ItemSlot::new_with_count(Id::from_name("leaf".to_string()), 10, 10),
                         ItemSlot::new_with_count(Id::from_name("leaf".to_string()), 10, 5),
@@ -1253,6 +1673,7 @@ mod tests {
                 let mut inventory = Inventory {
                     reserved_for: None,
                     max_slot_count: 4,
+                    consumption_order: ConsumptionOrder::default(),
                     slots: vec![
                         ItemSlot::new_with_count(Id::from_name("leaf".to_string()), 10, 10),
                         ItemSlot::new_with_count(Id::from_name("leaf".to_string()), 10, 5),
@@ -1279,6 +1700,7 @@ mod tests {
                 let mut inventory = Inventory {
                     reserved_for: None,
                     max_slot_count: 4,
+                    consumption_order: ConsumptionOrder::default(),
                     slots: vec![
                         ItemSlot::new_with_count(Id::from_name("leaf".to_string()), 10, 10),
                         ItemSlot::new_with_count(Id::from_name("leaf".to_string()), 10, 5),
@@ -1305,6 +1727,102 @@ mod tests {
                 );
             }
         }
+
+        mod consumption_order {
+            use super::super::super::*;
+
+            /// Two stacks of leaves: an older, partly spoiled stack of 4 and a newer, fresh stack of 6.
+            fn two_leaf_stacks(consumption_order: ConsumptionOrder) -> Inventory {
+                let leaf = Id::from_name("leaf".to_string());
+                let mut older = ItemSlot::new_with_count(leaf, 10, 4);
+                older.decay(Duration::from_secs(5), Duration::from_secs(10));
+
+                Inventory {
+                    reserved_for: None,
+                    max_slot_count: 2,
+                    consumption_order,
+                    slots: vec![older, ItemSlot::new_with_count(leaf, 10, 6)],
+                }
+            }
+
+            fn slot_counts(inventory: &Inventory) -> Vec<u32> {
+                inventory.iter().map(|slot| slot.count()).collect()
+            }
+
+            #[test]
+            fn fifo_consumes_the_oldest_stack_first() {
+                let mut inventory = two_leaf_stacks(ConsumptionOrder::Fifo);
+                inventory
+                    .try_remove_item(&ItemCount::new(Id::from_name("leaf".to_string()), 3))
+                    .unwrap();
+
+                assert_eq!(slot_counts(&inventory), vec![1, 6]);
+            }
+
+            #[test]
+            fn refilled_slots_hold_the_newest_stack() {
+                let leaf = Id::from_name("leaf".to_string());
+                let item_manifest = super::super::item_manifest();
+                let mut inventory = two_leaf_stacks(ConsumptionOrder::Fifo);
+
+                // Empty the older stack, then refill its slot, which is kept in place
+                inventory.try_remove_item(&ItemCount::new(leaf, 4)).unwrap();
+                assert_eq!(slot_counts(&inventory), vec![0, 6]);
+                inventory
+                    .try_add_item(&ItemCount::new(leaf, 5), &item_manifest)
+                    .unwrap();
+                assert_eq!(slot_counts(&inventory), vec![5, 6]);
+
+                inventory.try_remove_item(&ItemCount::new(leaf, 3)).unwrap();
+                assert_eq!(slot_counts(&inventory), vec![5, 3]);
+
+                inventory.set_consumption_order(ConsumptionOrder::Lifo);
+                inventory.try_remove_item(&ItemCount::new(leaf, 2)).unwrap();
+                assert_eq!(slot_counts(&inventory), vec![3, 3]);
+            }
+
+            #[test]
+            fn lifo_consumes_the_newest_stack_first() {
+                let mut inventory = two_leaf_stacks(ConsumptionOrder::Lifo);
+                inventory
+                    .try_remove_item(&ItemCount::new(Id::from_name("leaf".to_string()), 3))
+                    .unwrap();
+
+                assert_eq!(slot_counts(&inventory), vec![4, 3]);
+            }
+
+            #[test]
+            fn spoilage_consumes_the_nearest_to_expiry_first() {
+                let mut inventory = two_leaf_stacks(ConsumptionOrder::Spoilage);
+                // Make the newer stack the staler one, so that the order differs from FIFO
+                for slot in inventory.iter_mut() {
+                    let elapsed = match slot.count() {
+                        6 => Duration::from_secs(8),
+                        _ => Duration::ZERO,
+                    };
+                    slot.decay(elapsed, Duration::from_secs(10));
+                }
+
+                inventory
+                    .try_remove_item(&ItemCount::new(Id::from_name("leaf".to_string()), 3))
+                    .unwrap();
+
+                assert_eq!(slot_counts(&inventory), vec![4, 3]);
+            }
+
+            #[test]
+            fn stale_stacks_are_skipped_when_freshness_is_required() {
+                let mut inventory = two_leaf_stacks(ConsumptionOrder::Spoilage);
+                inventory
+                    .try_remove_item_with_freshness(
+                        &ItemCount::new(Id::from_name("leaf".to_string()), 3),
+                        0.9,
+                    )
+                    .unwrap();
+
+                assert_eq!(slot_counts(&inventory), vec![4, 3]);
+            }
+        }
     }
 
     mod transfer_item {
@@ -1391,4 +1909,85 @@ mod tests {
             );
         }
     }
+
+    #[test]
+    fn grouped_sums_slots_of_the_same_item() {
+        let leaf = Id::from_name("leaf".to_string());
+        let mushroom = Id::from_name("mushroom".to_string());
+        let inventory: Inventory = [
+            ItemSlot::new_with_count(leaf, 10, 10),
+            ItemSlot::new_with_count(mushroom, 10, 2),
+            ItemSlot::new_with_count(leaf, 10, 3),
+        ]
+        .into_iter()
+        .collect();
+
+        let groups: Vec<(ItemKind, u32)> = inventory.grouped().collect();
+        assert_eq!(
+            groups,
+            vec![
+                (ItemKind::Single(leaf), 13),
+                (ItemKind::Single(mushroom), 2)
+            ]
+        );
+    }
+
+    #[test]
+    fn snapshots_are_unaffected_by_later_changes() {
+        let item_manifest = item_manifest();
+        let leaf = Id::from_name("leaf".to_string());
+        let mushroom = Id::from_name("mushroom".to_string());
+        let mut inventory: Inventory = [
+            ItemSlot::new_with_count(leaf, 10, 4),
+            ItemSlot::new_with_count(mushroom, 10, 2),
+            ItemSlot::new_with_count(leaf, 10, 3),
+        ]
+        .into_iter()
+        .collect();
+
+        let snapshot = inventory.snapshot();
+        assert_eq!(
+            snapshot.items,
+            vec![ItemCount::new(leaf, 7), ItemCount::new(mushroom, 2)]
+        );
+
+        inventory.try_remove_item(&ItemCount::new(leaf, 5)).unwrap();
+        inventory
+            .try_add_item(&ItemCount::new(mushroom, 3), &item_manifest)
+            .unwrap();
+
+        assert_eq!(snapshot.item_count(leaf), 7);
+        assert_eq!(snapshot.item_count(mushroom), 2);
+        assert_eq!(inventory.item_count(leaf), 2);
+        assert_ne!(inventory.snapshot(), snapshot);
+    }
+
+    #[test]
+    fn spoilage_surplus_moves_into_free_slots_and_overflows() {
+        let mut item_manifest = item_manifest();
+        let leaf = Id::from_name("leaf".to_string());
+        let log = Id::from_name("log".to_string());
+        item_manifest.insert(
+            "log".to_string(),
+            ItemData {
+                compostable: true,
+                buoyant: true,
+                shelf_life: Some(Duration::from_secs(1)),
+                spoils_into: Some(leaf),
+                ..ItemData::basic(50)
+            },
+        );
+
+        let mut inventory = Inventory::new(2, None);
+        inventory
+            .try_add_item(&ItemCount::new(log, 25), &item_manifest)
+            .unwrap();
+
+        let spoilage = inventory.spoil(Duration::from_secs(1), &item_manifest);
+
+        assert!(spoilage.any_spoiled);
+        assert_eq!(inventory.item_count(log), 0);
+        assert_eq!(inventory.item_count(leaf), 20);
+        assert_eq!(spoilage.overflow, vec![ItemCount::new(leaf, 5)]);
+    }
 }
@@ -2,13 +2,17 @@
 
 use bevy::{
     reflect::{FromReflect, Reflect, TypeUuid},
-    utils::HashMap,
+    utils::{Duration, HashMap},
 };
 use serde::{Deserialize, Serialize};
 
 use crate::{
-    asset_management::manifest::{loader::IsRawManifest, Id, Manifest},
+    asset_management::manifest::{
+        loader::{IsRawManifest, ManifestError},
+        Id, Manifest,
+    },
     crafting::item_tags::{ItemKind, ItemTag},
+    geometry::Volume,
     organisms::{OrganismId, RawOrganismId},
 };
 
@@ -20,15 +24,12 @@ pub type ItemManifest = Manifest<Item, ItemData>;
 
 impl ItemManifest {
     /// Does the provided `item_id` meet the requirements of the given `tag`?
+    ///
+    /// This includes tags implied by the item's other tags through the tag hierarchy.
     pub fn has_tag(&self, item_id: Id<Item>, tag: ItemTag) -> bool {
         let data = self.get(item_id);
 
-        match tag {
-            ItemTag::Compostable => data.compostable,
-            ItemTag::Seed => data.seed.is_some(),
-            ItemTag::Fluid => data.fluid,
-            ItemTag::Buoyant => data.buoyant,
-        }
+        data.has_direct_tag(tag) || data.implied_tags.contains(&tag)
     }
 
     /// Returns the complete list of tags that the given item belongs to.
@@ -41,6 +42,12 @@ impl ItemManifest {
             tags.push(ItemTag::Compostable);
         }
 
+        for &implied_tag in &data.implied_tags {
+            if !tags.contains(&implied_tag) {
+                tags.push(implied_tag);
+            }
+        }
+
         tags
     }
 
@@ -71,6 +78,17 @@ impl ItemManifest {
         kinds
     }
 
+    /// Returns every [`ItemKind`] that refers to the same items as `item_kind`.
+    ///
+    /// For a single item, this is the item and each of its tags.
+    /// For a tag, this is every item with that tag, and the tag itself.
+    pub fn matching_kinds(&self, item_kind: ItemKind) -> Vec<ItemKind> {
+        match item_kind {
+            ItemKind::Single(item_id) => self.kinds(item_id),
+            ItemKind::Tag(tag) => self.kinds_with_tag(tag),
+        }
+    }
+
     /// Returns the human-readable name associated with the provided `item_kind`.
     ///
     /// # Panics
@@ -85,7 +103,7 @@ impl ItemManifest {
 }
 
 /// The data associated with each item.
-#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct ItemData {
     /// The number of items that can fit in a single item slot.
     pub stack_size: u32,
@@ -95,10 +113,94 @@ pub struct ItemData {
     pub fluid: bool,
     /// Does this item float?
     pub buoyant: bool,
+    /// Does this item break when dropped from a height?
+    pub fragile: bool,
     /// Is this item a seed?
     ///
     /// If so, what does it grow into when left as litter?
     pub seed: Option<OrganismId>,
+    /// How long does it take for this item to fully spoil?
+    ///
+    /// If this is `None`, the item never spoils.
+    pub shelf_life: Option<Duration>,
+    /// What does this item turn into once it has fully spoiled?
+    ///
+    /// If this is `None`, spoiled items are destroyed.
+    pub spoils_into: Option<Id<Item>>,
+    /// The water held by this item, if it is a filled water container.
+    pub water_contents: Option<WaterContents>,
+    /// Other items that recipes may consume in place of this item when they are short of it.
+    ///
+    /// Substitutes are never themselves substituted, so chains of substitutions cannot occur.
+    pub substitutes: Vec<Substitute>,
+    /// The tags that this item belongs to only because they are implied by its other tags.
+    ///
+    /// These are computed from the [`RawItemManifest::tag_hierarchy`] when the manifest is processed.
+    pub implied_tags: Vec<ItemTag>,
+}
+
+impl ItemData {
+    /// Does this item have the provided `tag` because of its own properties?
+    ///
+    /// Tags implied through the tag hierarchy are not considered.
+    fn has_direct_tag(&self, tag: ItemTag) -> bool {
+        match tag {
+            ItemTag::Compostable => self.compostable,
+            ItemTag::Seed => self.seed.is_some(),
+            ItemTag::Fluid => self.fluid,
+            ItemTag::Buoyant => self.buoyant,
+            ItemTag::Fragile => self.fragile,
+        }
+    }
+
+    /// A sturdy, non-perishable item with no special properties, which stacks up to `stack_size`.
+    ///
+    /// Tests override the properties that they care about using struct update syntax.
+    #[cfg(test)]
+    pub(crate) fn basic(stack_size: u32) -> Self {
+        ItemData {
+            stack_size,
+            compostable: false,
+            fluid: false,
+            buoyant: false,
+            fragile: false,
+            seed: None,
+            shelf_life: None,
+            spoils_into: None,
+            water_contents: None,
+            substitutes: Vec::new(),
+            implied_tags: Vec::new(),
+        }
+    }
+}
+
+/// An item that can stand in for another item as a recipe input.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Substitute {
+    /// The item that can be used instead.
+    pub item_id: Id<Item>,
+    /// How many of the substitute are needed to replace a single item.
+    pub ratio: u32,
+}
+
+/// The water held by a filled water container, such as a water pouch.
+///
+/// Pumps fill empty containers with surface water, and releasers pour filled containers out onto dry ground.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct WaterContents {
+    /// The volume of water held by each item.
+    pub volume: Volume,
+    /// The item that is left behind once the water has been poured out.
+    pub empty_container: Id<Item>,
+}
+
+/// The unprocessed equivalent of [`WaterContents`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RawWaterContents {
+    /// The volume of water held by each item.
+    pub volume: Volume,
+    /// The name of the item that is left behind once the water has been poured out.
+    pub empty_container: String,
 }
 
 /// The unprocessed [`ItemData`] as seen in the manifest file.
@@ -112,10 +214,32 @@ pub struct RawItemData {
     pub fluid: bool,
     /// Does this item float?
     pub buoyant: bool,
+    /// Does this item break when dropped from a height?
+    ///
+    /// If this is omitted, the item is sturdy.
+    #[serde(default)]
+    pub fragile: bool,
     /// Is this item a seed?
     ///
     /// If so, what does it grow into when left as litter?
     pub seed: Option<RawOrganismId>,
+    /// How long does it take for this item to fully spoil, in seconds?
+    ///
+    /// If this is `None`, the item never spoils.
+    #[serde(default)]
+    pub shelf_life: Option<f32>,
+    /// The name of the item that this item turns into once it has fully spoiled.
+    ///
+    /// If this is `None`, spoiled items are destroyed.
+    #[serde(default)]
+    pub spoils_into: Option<String>,
+    /// The water held by this item, if it is a filled water container.
+    #[serde(default)]
+    pub water_contents: Option<RawWaterContents>,
+    /// The names of items that can replace this item as a recipe input,
+    /// and how many of each are needed to replace a single item.
+    #[serde(default)]
+    pub substitutes: Vec<(String, u32)>,
 }
 
 impl From<RawItemData> for ItemData {
@@ -125,7 +249,24 @@ impl From<RawItemData> for ItemData {
             compostable: raw.compostable,
             fluid: raw.fluid,
             buoyant: raw.buoyant,
+            fragile: raw.fragile,
             seed: raw.seed.map(OrganismId::from),
+            shelf_life: raw.shelf_life.map(Duration::from_secs_f32),
+            spoils_into: raw.spoils_into.map(Id::from_name),
+            water_contents: raw.water_contents.map(|raw_contents| WaterContents {
+                volume: raw_contents.volume,
+                empty_container: Id::from_name(raw_contents.empty_container),
+            }),
+            substitutes: raw
+                .substitutes
+                .into_iter()
+                .map(|(name, ratio)| Substitute {
+                    item_id: Id::from_name(name),
+                    ratio,
+                })
+                .collect(),
+            // Filled in once the tag hierarchy has been resolved
+            implied_tags: Vec::new(),
         }
     }
 }
@@ -136,6 +277,47 @@ impl From<RawItemData> for ItemData {
 pub struct RawItemManifest {
     /// The data for each item.
     pub items: HashMap<String, RawItemData>,
+    /// The parent tags of each tag.
+    ///
+    /// Items with a tag also count as having each of its parent tags, and their parents in turn.
+    #[serde(default)]
+    pub tag_hierarchy: HashMap<ItemTag, Vec<ItemTag>>,
+}
+
+impl RawItemManifest {
+    /// Computes every tag implied by each tag in the [`tag_hierarchy`](Self::tag_hierarchy),
+    /// following parents transitively.
+    ///
+    /// Returns an error if any tag implies itself.
+    fn resolve_tag_hierarchy(&self) -> Result<HashMap<ItemTag, Vec<ItemTag>>, ManifestError> {
+        let mut resolved = HashMap::default();
+
+        for &tag in self.tag_hierarchy.keys() {
+            let mut implied: Vec<ItemTag> = Vec::new();
+            let mut to_visit: Vec<ItemTag> = self.tag_hierarchy[&tag].clone();
+
+            while let Some(parent) = to_visit.pop() {
+                if parent == tag {
+                    return Err(ManifestError::CyclicTagHierarchy {
+                        tag: tag.name().to_string(),
+                    });
+                }
+
+                if implied.contains(&parent) {
+                    continue;
+                }
+
+                implied.push(parent);
+                if let Some(grandparents) = self.tag_hierarchy.get(&parent) {
+                    to_visit.extend(grandparents.iter().copied());
+                }
+            }
+
+            resolved.insert(tag, implied);
+        }
+
+        Ok(resolved)
+    }
 }
 
 impl IsRawManifest for RawItemManifest {
@@ -144,15 +326,76 @@ impl IsRawManifest for RawItemManifest {
     type Marker = Item;
     type Data = ItemData;
 
-    fn process(&self) -> Manifest<Self::Marker, Self::Data> {
+    fn process(&self) -> Result<Manifest<Self::Marker, Self::Data>, ManifestError> {
         let mut manifest = Manifest::new();
+        let implied_tags = self.resolve_tag_hierarchy()?;
 
         for (raw_id, raw_data) in self.items.clone() {
-            let data = ItemData::from(raw_data);
+            if let Some(ref spoils_into) = raw_data.spoils_into {
+                if !self.items.contains_key(spoils_into) {
+                    return Err(ManifestError::UnknownReference {
+                        entry: raw_id,
+                        field: "spoils_into".to_string(),
+                        reference: spoils_into.clone(),
+                    });
+                }
+            }
+
+            if let Some(ref water_contents) = raw_data.water_contents {
+                if !self.items.contains_key(&water_contents.empty_container) {
+                    return Err(ManifestError::UnknownReference {
+                        entry: raw_id,
+                        field: "water_contents".to_string(),
+                        reference: water_contents.empty_container.clone(),
+                    });
+                }
+            }
+
+            if let Some(shelf_life) = raw_data.shelf_life {
+                if !shelf_life.is_finite() || shelf_life < 0. {
+                    return Err(ManifestError::MalformedItem {
+                        item: raw_id,
+                        reason: format!(
+                            "shelf life must be a finite, non-negative number of seconds, not {shelf_life}"
+                        ),
+                    });
+                }
+            }
+
+            for (substitute, ratio) in &raw_data.substitutes {
+                if !self.items.contains_key(substitute) {
+                    return Err(ManifestError::UnknownReference {
+                        entry: raw_id,
+                        field: "substitutes".to_string(),
+                        reference: substitute.clone(),
+                    });
+                }
+
+                if *substitute == raw_id || *ratio == 0 {
+                    let reason = format!(
+                        "{ratio} {substitute} cannot be used as a substitute for a single {raw_id}"
+                    );
+                    return Err(ManifestError::MalformedItem {
+                        item: raw_id,
+                        reason,
+                    });
+                }
+            }
+
+            let mut data = ItemData::from(raw_data);
+            for (&tag, parents) in implied_tags.iter() {
+                if data.has_direct_tag(tag) {
+                    for &parent in parents {
+                        if !data.has_direct_tag(parent) && !data.implied_tags.contains(&parent) {
+                            data.implied_tags.push(parent);
+                        }
+                    }
+                }
+            }
 
-            manifest.insert(raw_id, data)
+            manifest.try_insert(raw_id, data)?;
         }
 
-        manifest
+        Ok(manifest)
     }
 }
@@ -10,6 +10,7 @@ pub mod errors;
 pub mod inventory;
 pub mod item_manifest;
 pub mod slot;
+pub mod totals;
 
 /// A specific amount of a given item.
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
@@ -1,5 +1,7 @@
 //! A container for a single item type, with a capacity.
 
+use std::time::Duration;
+
 use rand::{distributions::Uniform, prelude::Distribution, rngs::ThreadRng};
 use serde::{Deserialize, Serialize};
 
@@ -13,7 +15,7 @@ use super::{
 };
 
 /// Multiple items of the same type.
-#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ItemSlot {
     /// The unique identifier of the item that occupies the slot.
     item_id: Id<Item>,
@@ -25,16 +27,50 @@ pub struct ItemSlot {
     ///
     /// This is guaranteed to be smaller than or equal to the `max_item_count`.
     count: u32,
+
+    /// How fresh the items in this slot are, on average.
+    ///
+    /// Freshly produced items have a freshness of [`ItemSlot::FRESH`], and items are fully spoiled at 0.
+    /// Older saves do not store this value, so items loaded from them are treated as fresh.
+    #[serde(default = "ItemSlot::fresh")]
+    freshness: f32,
+
+    /// When this slot was last stocked from empty, relative to the other slots of its inventory.
+    ///
+    /// Larger values are newer stacks, which lets [`Inventory`](super::inventory::Inventory) withdraw items in the order they arrived,
+    /// even after emptied slots are reused.
+    #[serde(default)]
+    stocked_at: u64,
+}
+
+/// Slots are compared by their contents: when they were stocked is bookkeeping for their inventory.
+impl PartialEq for ItemSlot {
+    fn eq(&self, other: &Self) -> bool {
+        self.item_id == other.item_id
+            && self.max_item_count == other.max_item_count
+            && self.count == other.count
+            && self.freshness == other.freshness
+    }
 }
 
 #[allow(dead_code)]
 impl ItemSlot {
+    /// The freshness of newly produced items.
+    pub const FRESH: f32 = 1.0;
+
+    /// The default freshness of items, used when deserializing.
+    const fn fresh() -> f32 {
+        Self::FRESH
+    }
+
     /// Create an empty slot for the given item.
     pub const fn empty(item_id: Id<Item>, max_item_count: u32) -> Self {
         Self {
             item_id,
             max_item_count,
             count: 0,
+            freshness: Self::FRESH,
+            stocked_at: 0,
         }
     }
 
@@ -44,6 +80,8 @@ impl ItemSlot {
             item_id,
             max_item_count,
             count: max_item_count,
+            freshness: Self::FRESH,
+            stocked_at: 0,
         }
     }
 
@@ -60,6 +98,8 @@ impl ItemSlot {
             item_id,
             max_item_count,
             count,
+            freshness: Self::FRESH,
+            stocked_at: 0,
         }
     }
 
@@ -89,6 +129,21 @@ impl ItemSlot {
         self.count
     }
 
+    /// How fresh the items in this slot are, between 0 (fully spoiled) and [`ItemSlot::FRESH`].
+    pub fn freshness(&self) -> f32 {
+        self.freshness
+    }
+
+    /// When this slot was last stocked from empty: larger values are newer stacks.
+    pub(crate) fn stocked_at(&self) -> u64 {
+        self.stocked_at
+    }
+
+    /// Records that this slot was stocked from empty at `stocked_at`.
+    pub(crate) fn set_stocked_at(&mut self, stocked_at: u64) {
+        self.stocked_at = stocked_at;
+    }
+
     /// The maximum number of items that can fit in this slot.
     pub fn max_item_count(&self) -> u32 {
         self.max_item_count
@@ -119,15 +174,31 @@ impl ItemSlot {
     /// - If all items can fit in the slot, they are all added and `Ok` is returned.
     /// - Otherwise, all items that can fit are added and `Err` is returned.
     pub fn add_until_full(&mut self, count: u32) -> Result<(), AddOneItemError> {
+        self.add_until_full_with_freshness(count, Self::FRESH)
+    }
+
+    /// Try to add as many items of the given `freshness` to the inventory as possible, up to the given count.
+    ///
+    /// The freshness of the slot becomes the weighted average of the stored and added items.
+    ///
+    /// - If all items can fit in the slot, they are all added and `Ok` is returned.
+    /// - Otherwise, all items that can fit are added and `Err` is returned.
+    pub fn add_until_full_with_freshness(
+        &mut self,
+        count: u32,
+        freshness: f32,
+    ) -> Result<(), AddOneItemError> {
         let new_count = self.count + count;
 
         if new_count > self.max_item_count {
+            self.blend_freshness(self.remaining_space(), freshness);
             self.count = self.max_item_count;
 
             Err(AddOneItemError {
                 excess_count: ItemCount::new(self.item_id, new_count - self.max_item_count),
             })
         } else {
+            self.blend_freshness(count, freshness);
             self.count = new_count;
             Ok(())
         }
@@ -138,6 +209,20 @@ impl ItemSlot {
     /// - If the items can fit in the slot, they are all added and `Ok` is returned.
     /// - If at least one of the items does not fit, _no_ items are added and `Err` is returned.
     pub fn add_all_or_nothing(&mut self, count: u32) -> Result<(), AddOneItemError> {
+        self.add_all_or_nothing_with_freshness(count, Self::FRESH)
+    }
+
+    /// Try to add the given count of items with the given `freshness` to the inventory, together.
+    ///
+    /// The freshness of the slot becomes the weighted average of the stored and added items.
+    ///
+    /// - If the items can fit in the slot, they are all added and `Ok` is returned.
+    /// - If at least one of the items does not fit, _no_ items are added and `Err` is returned.
+    pub fn add_all_or_nothing_with_freshness(
+        &mut self,
+        count: u32,
+        freshness: f32,
+    ) -> Result<(), AddOneItemError> {
         if self.remaining_space() < count {
             Err(AddOneItemError {
                 excess_count: ItemCount::new(
@@ -146,11 +231,52 @@ impl ItemSlot {
                 ),
             })
         } else {
+            self.blend_freshness(count, freshness);
             self.count += count;
             Ok(())
         }
     }
 
+    /// Mixes `count` items of the given `freshness` into the freshness of this slot.
+    ///
+    /// This must be called before the item count is updated.
+    fn blend_freshness(&mut self, count: u32, freshness: f32) {
+        let total = self.count + count;
+        if total > 0 {
+            self.freshness =
+                (self.freshness * self.count as f32 + freshness * count as f32) / total as f32;
+        }
+    }
+
+    /// Ages the items in this slot by `elapsed`, given the `shelf_life` of the item.
+    ///
+    /// Returns `true` if the items are now fully spoiled.
+    pub fn decay(&mut self, elapsed: Duration, shelf_life: Duration) -> bool {
+        if shelf_life.is_zero() {
+            self.freshness = 0.;
+        } else {
+            let lost = elapsed.as_secs_f32() / shelf_life.as_secs_f32();
+            self.freshness = (self.freshness - lost).max(0.);
+        }
+
+        self.freshness <= 0.
+    }
+
+    /// Replaces the contents of this slot with the same number of fresh `item_id`, capped at its `max_item_count`.
+    ///
+    /// Returns the number of items that no longer fit in the slot.
+    #[must_use]
+    pub fn convert_into(&mut self, item_id: Id<Item>, max_item_count: u32) -> u32 {
+        let surplus = self.count.saturating_sub(max_item_count);
+
+        self.item_id = item_id;
+        self.max_item_count = max_item_count;
+        self.count -= surplus;
+        self.freshness = Self::FRESH;
+
+        surplus
+    }
+
     /// Try to remove as many items from the slot as possible, up to the given count.
     ///
     /// - If the slot has enough items, they are all removed and `Ok` is returned.
@@ -191,12 +317,17 @@ impl ItemSlot {
 
     /// The pretty formatting for this type
     pub fn display(&self, item_manifest: &ItemManifest) -> String {
-        format!(
+        let base = format!(
             "{} ({}/{})",
             item_manifest.name(self.item_id),
             self.count,
             self.max_item_count
-        )
+        );
+
+        match item_manifest.get(self.item_id).shelf_life {
+            Some(_) => format!("{base} [{:.0}% fresh]", self.freshness * 100.),
+            None => base,
+        }
     }
 }
 
@@ -210,6 +341,8 @@ mod tests {
             item_id: Id::from_name("leaf".to_string()),
             max_item_count: 10,
             count: 0,
+            freshness: ItemSlot::FRESH,
+            stocked_at: 0,
         };
 
         assert!(item_slot.is_empty());
@@ -221,6 +354,8 @@ mod tests {
             item_id: Id::from_name("leaf".to_string()),
             max_item_count: 10,
             count: 1,
+            freshness: ItemSlot::FRESH,
+            stocked_at: 0,
         };
 
         assert!(!item_slot.is_empty());
@@ -232,6 +367,8 @@ mod tests {
             item_id: Id::from_name("leaf".to_string()),
             max_item_count: 10,
             count: 10,
+            freshness: ItemSlot::FRESH,
+            stocked_at: 0,
         };
 
         assert!(item_slot.is_full());
@@ -243,6 +380,8 @@ mod tests {
             item_id: Id::from_name("leaf".to_string()),
             max_item_count: 10,
             count: 9,
+            freshness: ItemSlot::FRESH,
+            stocked_at: 0,
         };
 
         assert!(!item_slot.is_full());
@@ -254,6 +393,8 @@ mod tests {
             item_id: Id::from_name("leaf".to_string()),
             max_item_count: 10,
             count: 0,
+            freshness: ItemSlot::FRESH,
+            stocked_at: 0,
         };
 
         assert_eq!(item_slot.remaining_space(), 10);
@@ -265,6 +406,8 @@ mod tests {
             item_id: Id::from_name("leaf".to_string()),
             max_item_count: 10,
             count: 5,
+            freshness: ItemSlot::FRESH,
+            stocked_at: 0,
         };
 
         assert_eq!(item_slot.remaining_space(), 5);
@@ -280,6 +423,8 @@ mod tests {
                     item_id: Id::from_name("leaf".to_string()),
                     max_item_count: 10,
                     count: 0,
+                    freshness: ItemSlot::FRESH,
+                    stocked_at: 0,
                 };
 
                 assert_eq!(item_slot.add_until_full(10), Ok(()));
@@ -292,6 +437,8 @@ mod tests {
                     item_id: Id::from_name("leaf".to_string()),
                     max_item_count: 10,
                     count: 5,
+                    freshness: ItemSlot::FRESH,
+                    stocked_at: 0,
                 };
 
                 assert_eq!(
@@ -313,6 +460,8 @@ mod tests {
                     item_id: Id::from_name("leaf".to_string()),
                     max_item_count: 10,
                     count: 0,
+                    freshness: ItemSlot::FRESH,
+                    stocked_at: 0,
                 };
 
                 assert_eq!(item_slot.add_all_or_nothing(10), Ok(()));
@@ -325,6 +474,8 @@ mod tests {
                     item_id: Id::from_name("leaf".to_string()),
                     max_item_count: 10,
                     count: 5,
+                    freshness: ItemSlot::FRESH,
+                    stocked_at: 0,
                 };
 
                 assert_eq!(
@@ -348,6 +499,8 @@ mod tests {
                     item_id: Id::from_name("leaf".to_string()),
                     max_item_count: 10,
                     count: 10,
+                    freshness: ItemSlot::FRESH,
+                    stocked_at: 0,
                 };
 
                 assert_eq!(item_slot.remove_until_empty(10), Ok(()));
@@ -360,6 +513,8 @@ mod tests {
                     item_id: Id::from_name("leaf".to_string()),
                     max_item_count: 10,
                     count: 5,
+                    freshness: ItemSlot::FRESH,
+                    stocked_at: 0,
                 };
 
                 assert_eq!(
@@ -381,6 +536,8 @@ mod tests {
                     item_id: Id::from_name("leaf".to_string()),
                     max_item_count: 10,
                     count: 10,
+                    freshness: ItemSlot::FRESH,
+                    stocked_at: 0,
                 };
 
                 assert_eq!(item_slot.remove_all_or_nothing(10), Ok(()));
@@ -393,6 +550,8 @@ mod tests {
                     item_id: Id::from_name("leaf".to_string()),
                     max_item_count: 10,
                     count: 5,
+                    freshness: ItemSlot::FRESH,
+                    stocked_at: 0,
                 };
 
                 assert_eq!(
@@ -405,4 +564,56 @@ mod tests {
             }
         }
     }
+
+    mod freshness {
+        use super::*;
+
+        #[test]
+        fn should_average_freshness_when_stacking() {
+            let mut item_slot = ItemSlot::new_with_count(Id::from_name("leaf".to_string()), 10, 3);
+
+            assert_eq!(item_slot.add_until_full_with_freshness(1, 0.), Ok(()));
+            assert_eq!(item_slot.freshness(), 0.75);
+        }
+
+        #[test]
+        fn should_spoil_after_shelf_life() {
+            let mut item_slot = ItemSlot::new_with_count(Id::from_name("leaf".to_string()), 10, 3);
+
+            assert!(!item_slot.decay(Duration::from_secs(1), Duration::from_secs(2)));
+            assert!(item_slot.decay(Duration::from_secs(1), Duration::from_secs(2)));
+            assert_eq!(item_slot.freshness(), 0.);
+        }
+
+        #[test]
+        fn should_return_surplus_when_converting_into_smaller_stacks() {
+            let mut item_slot = ItemSlot::new_with_count(Id::from_name("log".to_string()), 50, 30);
+            let leaf = Id::from_name("leaf".to_string());
+
+            assert_eq!(item_slot.convert_into(leaf, 10), 20);
+            assert_eq!(item_slot.item_id(), leaf);
+            assert_eq!(item_slot.count(), 10);
+            assert_eq!(item_slot.freshness(), ItemSlot::FRESH);
+        }
+
+        #[test]
+        fn should_survive_serialization() {
+            let mut item_slot = ItemSlot::new_with_count(Id::from_name("leaf".to_string()), 10, 3);
+            item_slot.decay(Duration::from_secs(1), Duration::from_secs(4));
+
+            let serialized = serde_json::to_string(&item_slot).unwrap();
+            let deserialized: ItemSlot = serde_json::from_str(&serialized).unwrap();
+            assert_eq!(deserialized, item_slot);
+        }
+
+        #[test]
+        fn should_treat_slots_without_freshness_as_fresh() {
+            let item_slot = ItemSlot::new_with_count(Id::from_name("leaf".to_string()), 10, 3);
+            let mut value = serde_json::to_value(&item_slot).unwrap();
+            value.as_object_mut().unwrap().remove("freshness");
+
+            let deserialized: ItemSlot = serde_json::from_value(value).unwrap();
+            assert_eq!(deserialized, item_slot);
+        }
+    }
 }
@@ -0,0 +1,213 @@
+//! Tracks the total quantity of each item that exists in the world.
+
+use bevy::{prelude::*, utils::HashMap};
+
+use crate::{
+    asset_management::manifest::Id,
+    crafting::inventories::{InputInventory, OutputInventory, StorageInventory},
+    litter::Litter,
+    units::item_interaction::UnitInventory,
+};
+
+use super::item_manifest::{Item, ItemManifest};
+
+/// The total number of items of each type, summed across every inventory in the world.
+///
+/// This includes the input, output and storage inventories of structures,
+/// the items held by units and any litter lying around.
+///
+/// This is recomputed by [`update_resource_totals`] whenever any inventory changes.
+#[derive(Debug, Resource, Default, Clone, PartialEq)]
+pub(crate) struct ResourceTotals {
+    /// The number of items of each type
+    map: HashMap<Id<Item>, u32>,
+}
+
+impl ResourceTotals {
+    /// The total number of items of the provided type that exist in the world.
+    pub(crate) fn total(&self, item_id: Id<Item>) -> u32 {
+        self.map.get(&item_id).copied().unwrap_or_default()
+    }
+
+    /// Iterates over all item types that are present, and their total counts.
+    pub(crate) fn iter(&self) -> impl Iterator<Item = (Id<Item>, u32)> + '_ {
+        self.map.iter().map(|(&item_id, &count)| (item_id, count))
+    }
+
+    /// Adds `count` items of type `item_id` to the tally.
+    fn add(&mut self, item_id: Id<Item>, count: u32) {
+        if count > 0 {
+            *self.map.entry(item_id).or_default() += count;
+        }
+    }
+
+    /// Returns a human-readable string representation of the item totals.
+    pub(crate) fn display(&self, item_manifest: &ItemManifest) -> String {
+        let mut string = String::new();
+
+        for (item_id, count) in self.iter() {
+            let name = item_manifest.name(item_id);
+            string.push_str(&format!("{name}: {count}\n"));
+        }
+
+        string
+    }
+}
+
+/// Recounts the total number of items across all inventories of each type.
+///
+/// The full recount is skipped unless an inventory was added, changed or removed since the last run.
+pub(crate) fn update_resource_totals(
+    mut resource_totals: ResMut<ResourceTotals>,
+    input_inventory_query: Query<&InputInventory>,
+    output_inventory_query: Query<&OutputInventory>,
+    storage_inventory_query: Query<&StorageInventory>,
+    unit_inventory_query: Query<&UnitInventory>,
+    litter_query: Query<&Litter>,
+    changed_query: Query<
+        (),
+        Or<(
+            Changed<InputInventory>,
+            Changed<OutputInventory>,
+            Changed<StorageInventory>,
+            Changed<UnitInventory>,
+            Changed<Litter>,
+        )>,
+    >,
+    mut removed_input_inventories: RemovedComponents<InputInventory>,
+    mut removed_output_inventories: RemovedComponents<OutputInventory>,
+    mut removed_storage_inventories: RemovedComponents<StorageInventory>,
+    mut removed_unit_inventories: RemovedComponents<UnitInventory>,
+    mut removed_litter: RemovedComponents<Litter>,
+) {
+    // Each of these iterators must be drained, so we can't short-circuit here
+    let any_removed = removed_input_inventories.iter().count()
+        + removed_output_inventories.iter().count()
+        + removed_storage_inventories.iter().count()
+        + removed_unit_inventories.iter().count()
+        + removed_litter.iter().count()
+        > 0;
+
+    if !any_removed && changed_query.is_empty() {
+        return;
+    }
+
+    let mut totals = ResourceTotals::default();
+
+    for inventory in input_inventory_query.iter() {
+        for item_slot in inventory.iter() {
+            totals.add(item_slot.item_id(), item_slot.count());
+        }
+    }
+
+    for inventory in output_inventory_query.iter() {
+        for item_slot in inventory.iter() {
+            totals.add(item_slot.item_id(), item_slot.count());
+        }
+    }
+
+    for inventory in storage_inventory_query.iter() {
+        for item_slot in inventory.iter() {
+            totals.add(item_slot.item_id(), item_slot.count());
+        }
+    }
+
+    for inventory in unit_inventory_query.iter() {
+        if let Some(held_items) = inventory.held_items() {
+            totals.add(held_items.item_id, held_items.count);
+        }
+    }
+
+    for litter in litter_query.iter() {
+        for item_slot in litter.contents.iter() {
+            totals.add(item_slot.item_id(), item_slot.count());
+        }
+    }
+
+    // Avoid triggering change detection when nothing has actually changed
+    resource_totals.set_if_neq(totals);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        asset_management::manifest::Manifest,
+        items::{item_manifest::ItemData, ItemCount},
+    };
+
+    fn item_manifest() -> ItemManifest {
+        let mut manifest = Manifest::new();
+        for name in ["leaf", "mushroom"] {
+            manifest.insert(name.to_string(), ItemData::basic(10));
+        }
+        manifest
+    }
+
+    fn stocked_storage(item_count: &ItemCount, item_manifest: &ItemManifest) -> StorageInventory {
+        let mut storage = StorageInventory::new(3, None);
+        storage
+            .add_item_all_or_nothing(item_count, item_manifest)
+            .unwrap();
+        storage
+    }
+
+    #[test]
+    fn resource_totals_match_manual_summation() {
+        let item_manifest = item_manifest();
+        let leaf = Id::from_name("leaf".to_string());
+        let mushroom = Id::from_name("mushroom".to_string());
+
+        let mut app = App::new();
+        app.init_resource::<ResourceTotals>()
+            .add_system(update_resource_totals);
+
+        app.world
+            .spawn(stocked_storage(&ItemCount::new(leaf, 7), &item_manifest));
+        app.world
+            .spawn(stocked_storage(&ItemCount::new(leaf, 15), &item_manifest));
+        app.world.spawn(stocked_storage(
+            &ItemCount::new(mushroom, 4),
+            &item_manifest,
+        ));
+        app.world.spawn(OutputInventory {
+            inventory: stocked_storage(&ItemCount::new(mushroom, 2), &item_manifest).inventory,
+        });
+        app.world.spawn(Litter {
+            contents: stocked_storage(&ItemCount::new(leaf, 1), &item_manifest),
+        });
+        app.world
+            .spawn(UnitInventory::holding(ItemCount::new(mushroom, 3)));
+
+        app.update();
+
+        let resource_totals = app.world.resource::<ResourceTotals>();
+        assert_eq!(resource_totals.total(leaf), 7 + 15 + 1);
+        assert_eq!(resource_totals.total(mushroom), 4 + 2 + 3);
+        assert_eq!(resource_totals.total(Id::from_name("acorn".to_string())), 0);
+    }
+
+    #[test]
+    fn resource_totals_update_when_inventories_are_removed() {
+        let item_manifest = item_manifest();
+        let leaf = Id::from_name("leaf".to_string());
+
+        let mut app = App::new();
+        app.init_resource::<ResourceTotals>()
+            .add_system(update_resource_totals);
+
+        app.world
+            .spawn(stocked_storage(&ItemCount::new(leaf, 3), &item_manifest));
+        let entity = app
+            .world
+            .spawn(stocked_storage(&ItemCount::new(leaf, 5), &item_manifest))
+            .id();
+
+        app.update();
+        assert_eq!(app.world.resource::<ResourceTotals>().total(leaf), 8);
+
+        app.world.despawn(entity);
+        app.update();
+        assert_eq!(app.world.resource::<ResourceTotals>().total(leaf), 3);
+    }
+}
@@ -35,9 +35,19 @@ pub mod world_gen;
 /// Importing between files shared in the `tests` directory appears to be broken with this workspace config?
 /// Followed directions from <https://doc.rust-lang.org/rust-by-example/testing/integration_testing.html>
 pub mod testing {
-    use crate::{simulation::SimulationPlugin, world_gen::GenerationConfig};
+    use crate::{
+        construction::zoning::ZoningSettingsConfig,
+        player_interaction::keybindings::KeybindingsConfig,
+        simulation::{milestones::MilestoneSaveConfig, SimulationPlugin},
+        world_gen::GenerationConfig,
+    };
     use bevy::prelude::*;
 
+    #[cfg(test)]
+    pub(crate) mod crafter_app;
+    #[cfg(test)]
+    pub(crate) mod harness;
+
     /// Just [`MinimalPlugins`].
     pub fn minimal_app() -> App {
         let mut app = App::new();
@@ -48,16 +58,23 @@ pub mod testing {
     }
 
     /// Just the game logic and simulation
+    ///
+    /// Milestone progress and zoning settings are not saved, so tests never touch the player's files.
     pub fn simulation_app(gen_config: GenerationConfig) -> App {
         let mut app = minimal_app();
-        app.add_plugin(SimulationPlugin { gen_config });
+        app.insert_resource(MilestoneSaveConfig::disabled())
+            .insert_resource(ZoningSettingsConfig::disabled())
+            .add_plugin(SimulationPlugin { gen_config });
         app
     }
 
     /// Test users interacting with the app
+    ///
+    /// The default keybindings are always used, and are never saved.
     pub fn interaction_app(gen_config: GenerationConfig) -> App {
         let mut app = simulation_app(gen_config);
-        app.add_plugin(bevy::input::InputPlugin)
+        app.insert_resource(KeybindingsConfig::disabled())
+            .add_plugin(bevy::input::InputPlugin)
             .add_plugin(crate::player_interaction::InteractionPlugin);
         app
     }
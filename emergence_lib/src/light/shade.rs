@@ -105,7 +105,7 @@ pub(super) fn compute_shade(
                 height: current_height,
             };
 
-            if let Some(voxel_data) = map_geometry.get_voxel(shaded_voxel) {
+            if let Some(voxel_data) = map_geometry.get_voxel_object(shaded_voxel) {
                 let entity = voxel_data.entity;
                 if let Ok(mut shade) = shade_query.get_mut(entity) {
                     shade.add_shade();
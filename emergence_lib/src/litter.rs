@@ -2,20 +2,22 @@
 
 use std::f32::consts::TAU;
 
-use bevy::utils::Duration;
+use bevy::utils::{Duration, HashMap, HashSet};
 use bevy::{ecs::system::Command, prelude::*};
-use hexx::Direction;
+use hexx::{Direction, Hex};
+use itertools::Itertools;
 use rand::thread_rng;
 use rand_distr::{Distribution, Normal};
 
 use crate::asset_management::manifest::Id;
 use crate::geometry::MAP_LAYOUT;
-use crate::items::inventory::InventoryState;
+use crate::items::errors::AddOneItemError;
+use crate::items::inventory::{Inventory, InventoryState};
 use crate::items::item_manifest::Item;
 use crate::items::ItemCount;
 use crate::terrain::terrain_assets::TerrainHandles;
 use crate::{
-    crafting::{inventories::StorageInventory, item_tags::ItemKind},
+    crafting::inventories::StorageInventory,
     geometry::{DiscreteHeight, Height, MapGeometry, VoxelPos},
     items::item_manifest::ItemManifest,
     signals::{Emitter, SignalStrength, SignalType},
@@ -61,12 +63,180 @@ impl Litter {
 
         Litter { contents }
     }
+
+    /// Adds as many of the provided items to this litter as will fit, then [compacts](Litter::compact) the pile.
+    ///
+    /// Returns the items that could not be added as an error.
+    pub(crate) fn try_add_item(
+        &mut self,
+        item_count: &ItemCount,
+        item_manifest: &ItemManifest,
+    ) -> Result<(), AddOneItemError> {
+        let result = self.contents.try_add_item(item_count, item_manifest);
+        self.compact();
+        result
+    }
+
+    /// Adds all of the provided items to this litter, or none of them, then [compacts](Litter::compact) the pile.
+    pub(crate) fn add_item_all_or_nothing(
+        &mut self,
+        item_count: &ItemCount,
+        item_manifest: &ItemManifest,
+    ) -> Result<(), AddOneItemError> {
+        let result = self
+            .contents
+            .add_item_all_or_nothing(item_count, item_manifest);
+        self.compact();
+        result
+    }
+
+    /// Merges slots that hold the same item, up to their stack size.
+    ///
+    /// Items dropped by several sources can otherwise fragment across partially filled slots.
+    pub(crate) fn compact(&mut self) {
+        self.contents.merge_identical_slots();
+    }
 }
 
 /// Is this litter currently floating?
 #[derive(Component, Clone, Copy, Debug, PartialEq, Eq)]
 pub(crate) struct Floating(bool);
 
+/// Tracks which tiles currently have litter on them.
+///
+/// Systems that act on litter can use this to skip the (many) tiles with nothing on them.
+/// This is kept up to date by [`update_litter_index`].
+#[derive(Resource, Debug, Default)]
+pub(crate) struct LitterIndex {
+    /// The entities with non-empty [`Litter`] on each tile.
+    tiles: HashMap<Hex, HashSet<Entity>>,
+    /// The tile that each entity with non-empty [`Litter`] is on.
+    locations: HashMap<Entity, Hex>,
+}
+
+impl LitterIndex {
+    /// Is there any litter on the provided tile?
+    pub(crate) fn has_litter(&self, hex: Hex) -> bool {
+        self.tiles.contains_key(&hex)
+    }
+
+    /// Returns an iterator over every tile that has litter on it, in an arbitrary order.
+    pub(crate) fn tiles(&self) -> impl Iterator<Item = Hex> + '_ {
+        self.tiles.keys().copied()
+    }
+
+    /// The number of tiles that have litter on them.
+    #[cfg(test)]
+    pub(crate) fn len(&self) -> usize {
+        self.tiles.len()
+    }
+
+    /// Records that `entity` holds litter on the provided tile, or no litter at all if `hex` is [`None`].
+    fn set(&mut self, entity: Entity, hex: Option<Hex>) {
+        if let Some(old_hex) = self.locations.remove(&entity) {
+            if let Some(entities) = self.tiles.get_mut(&old_hex) {
+                entities.remove(&entity);
+                if entities.is_empty() {
+                    self.tiles.remove(&old_hex);
+                }
+            }
+        }
+
+        if let Some(hex) = hex {
+            self.locations.insert(entity, hex);
+            self.tiles.entry(hex).or_default().insert(entity);
+        }
+    }
+}
+
+/// Moves all of the `items` out of the litter on the tiles at `hexes` and into the `destination`, keeping their freshness.
+///
+/// Tiles are drained in the order that they are provided, so this should be deterministic.
+/// Nothing is moved unless every item can be found, so litter is never taken for a job that can't be done.
+/// The `destination` must have room for all of the `items`.
+///
+/// Returns `true` if the items were moved.
+pub(crate) fn gather_litter(
+    items: &[ItemCount],
+    hexes: impl IntoIterator<Item = Hex>,
+    litter_query: &mut Query<&mut Litter>,
+    destination: &mut Inventory,
+    map_geometry: &MapGeometry,
+    item_manifest: &ItemManifest,
+) -> bool {
+    let litter_entities: Vec<Entity> = hexes
+        .into_iter()
+        .filter_map(|hex| map_geometry.get_terrain(hex).ok())
+        .filter(|&terrain_entity| litter_query.contains(terrain_entity))
+        .collect();
+
+    let enough_litter = items.iter().all(|item_count| {
+        let available: u32 = litter_entities
+            .iter()
+            .map(|&terrain_entity| {
+                let litter = litter_query.get(terrain_entity).unwrap();
+                litter.contents.item_count(item_count.item_id)
+            })
+            .sum();
+
+        available >= item_count.count
+    });
+    if !enough_litter {
+        return false;
+    }
+
+    for item_count in items {
+        let mut still_needed = item_count.count;
+
+        for &terrain_entity in &litter_entities {
+            if still_needed == 0 {
+                break;
+            }
+
+            let mut litter = litter_query.get_mut(terrain_entity).unwrap();
+            let taken = litter
+                .contents
+                .item_count(item_count.item_id)
+                .min(still_needed);
+            if taken == 0 {
+                continue;
+            }
+
+            // We just checked that these items are here, and the destination has room for them
+            litter
+                .contents
+                .transfer_item(
+                    &ItemCount::new(item_count.item_id, taken),
+                    destination,
+                    item_manifest,
+                )
+                .unwrap();
+            still_needed -= taken;
+        }
+    }
+
+    true
+}
+
+/// Keeps the [`LitterIndex`] in sync as litter is added, removed or moved.
+pub(crate) fn update_litter_index(
+    litter_query: Query<(Entity, &VoxelPos, &Litter), Or<(Changed<Litter>, Changed<VoxelPos>)>>,
+    mut removed_litter: RemovedComponents<Litter>,
+    mut litter_index: ResMut<LitterIndex>,
+) {
+    for entity in removed_litter.iter() {
+        litter_index.set(entity, None);
+    }
+
+    for (entity, voxel_pos, litter) in litter_query.iter() {
+        let maybe_hex = match litter.contents.is_empty() {
+            true => None,
+            false => Some(voxel_pos.hex),
+        };
+        litter_index.set(entity, maybe_hex);
+    }
+}
+
 impl Litter {
     /// The pretty formatting for the litter stored here.
     #[allow(dead_code)]
@@ -90,18 +260,34 @@ impl Default for Litter {
 }
 
 /// Updates the signals produced by litter.
-pub(super) fn set_litter_emitters(mut query: Query<(&mut Emitter, Ref<Litter>)>) {
-    for (mut emitter, litter) in query.iter_mut() {
-        if litter.is_changed() {
+///
+/// Each stack of litter asks to be cleaned up, with a strength that scales with the size of the pile.
+/// Floating litter is harder to reach, and so only signals at half strength.
+pub(super) fn set_litter_emitters(
+    mut query: Query<(&mut Emitter, Ref<Litter>, Option<Ref<Floating>>)>,
+) {
+    for (mut emitter, litter, maybe_floating) in query.iter_mut() {
+        let floating_changed = maybe_floating
+            .as_ref()
+            .map(|floating| floating.is_changed())
+            .unwrap_or_default();
+
+        if litter.is_changed() || floating_changed {
+            let is_floating = maybe_floating
+                .map(|floating| floating.0)
+                .unwrap_or_default();
+
             emitter.signals.clear();
-            for item_slot in litter.contents.iter() {
-                let item_kind = ItemKind::Single(item_slot.item_id());
+            for (item_kind, count) in litter.contents.grouped() {
+                if count == 0 {
+                    continue;
+                }
 
-                let signal_type = match litter.contents.is_full() {
-                    true => SignalType::Push(item_kind),
-                    false => SignalType::Contains(item_kind),
-                };
-                let signal_strength = SignalStrength::new(10.);
+                let signal_type = SignalType::Push(item_kind);
+                let mut signal_strength = SignalStrength::from_count(count);
+                if is_floating {
+                    signal_strength /= 2.;
+                }
 
                 emitter.signals.push((signal_type, signal_strength));
             }
@@ -122,6 +308,92 @@ pub(super) fn clear_empty_litter(query: Query<(Entity, &Litter)>, mut commands:
     }
 }
 
+/// The fraction of a tile's litter capacity for an item above which that item begins to spill onto neighboring tiles.
+const LITTER_SPREAD_THRESHOLD: f32 = 0.5;
+
+/// Spreads litter from over-full tiles onto the least-full adjacent tile that can be walked to.
+///
+/// Each tick, every item that fills more than [`LITTER_SPREAD_THRESHOLD`] of its tile's capacity
+/// sheds half of the difference to the chosen neighbor, so piles gradually even out.
+pub(super) fn spread_litter(
+    mut litter_query: Query<&mut Litter>,
+    item_manifest: Res<ItemManifest>,
+    map_geometry: Res<MapGeometry>,
+    litter_index: Res<LitterIndex>,
+) {
+    let mut proposed_transfers: Vec<(Entity, Entity, ItemCount)> = Vec::new();
+
+    // Empty tiles have nothing to shed
+    for hex in litter_index.tiles() {
+        let Ok(source_entity) = map_geometry.get_terrain(hex) else { continue };
+        let Ok(source_litter) = litter_query.get(source_entity) else { continue };
+
+        for item_id in source_litter
+            .contents
+            .iter()
+            .map(|item_slot| item_slot.item_id())
+            .unique()
+        {
+            let source_count = source_litter.contents.item_count(item_id);
+            let source_capacity = source_count
+                + source_litter
+                    .contents
+                    .remaining_space_for_item(item_id, &item_manifest);
+
+            if source_count as f32 <= source_capacity as f32 * LITTER_SPREAD_THRESHOLD {
+                continue;
+            }
+
+            // Walkable neighbors already account for structures that cannot be walked through
+            let least_full_neighbor = map_geometry
+                .walkable_neighbors(map_geometry.on_top_of_terrain(hex))
+                .filter_map(|neighbor| {
+                    let target_entity = map_geometry.get_terrain(neighbor.hex).ok()?;
+                    let target_litter = litter_query.get(target_entity).ok()?;
+                    let target_count = target_litter.contents.item_count(item_id);
+                    let remaining_space = target_litter
+                        .contents
+                        .remaining_space_for_item(item_id, &item_manifest);
+
+                    if remaining_space == 0 || target_count >= source_count {
+                        return None;
+                    }
+
+                    let fullness = target_count as f32 / (target_count + remaining_space) as f32;
+                    Some((target_entity, target_count, fullness))
+                })
+                .min_by(|(.., a), (.., b)| a.total_cmp(b));
+
+            if let Some((target_entity, target_count, _)) = least_full_neighbor {
+                // Moving a single item between piles that differ by one would just swap them back and forth
+                let count = (source_count - target_count) / 2;
+                if count == 0 {
+                    continue;
+                }
+
+                proposed_transfers.push((
+                    source_entity,
+                    target_entity,
+                    ItemCount::new(item_id, count),
+                ));
+            }
+        }
+    }
+
+    for (source_entity, target_entity, item_count) in proposed_transfers {
+        let Ok([mut source_litter, mut target_litter]) =
+            litter_query.get_many_mut([source_entity, target_entity]) else { continue };
+
+        // Earlier transfers this tick may have filled the target, so this is allowed to partially fail
+        let _ = source_litter.contents.transfer_item(
+            &item_count,
+            &mut target_litter.contents,
+            &item_manifest,
+        );
+        target_litter.compact();
+    }
+}
+
 /// Make litter in tiles submerged by water float (and stop it from floating when there's no water).
 pub(super) fn make_litter_float(
     mut query: Query<(&mut Floating, &mut VoxelPos), With<Litter>>,
@@ -347,3 +619,387 @@ impl Command for SpawnLitterCommand {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        asset_management::manifest::Manifest, crafting::item_tags::ItemKind,
+        items::item_manifest::ItemData,
+    };
+    use bevy::ecs::system::SystemState;
+
+    fn leaf() -> Id<Item> {
+        Id::from_name("leaf".to_string())
+    }
+
+    fn item_manifest() -> ItemManifest {
+        let mut manifest = Manifest::new();
+        manifest.insert(
+            "leaf".to_string(),
+            ItemData {
+                compostable: true,
+                buoyant: true,
+                ..ItemData::basic(10)
+            },
+        );
+        manifest.insert("pebble".to_string(), ItemData::basic(100));
+        manifest
+    }
+
+    /// Runs [`set_litter_emitters`] on a pile of `count` pebbles, and returns the strength of the emitted signal.
+    fn litter_signal_strength(count: u32, floating: bool) -> SignalStrength {
+        let mut app = App::new();
+        let item_manifest = item_manifest();
+        let pebble = Id::from_name("pebble".to_string());
+
+        let mut litter = Litter::default();
+        litter
+            .contents
+            .add_item_all_or_nothing(&ItemCount::new(pebble, count), &item_manifest)
+            .unwrap();
+        let litter_entity = app
+            .world
+            .spawn((litter, Floating(floating), Emitter::default()))
+            .id();
+
+        app.add_system(set_litter_emitters);
+        app.update();
+
+        let emitter = app.world.get::<Emitter>(litter_entity).unwrap();
+        assert_eq!(emitter.signals.len(), 1);
+        let (signal_type, signal_strength) = emitter.signals[0];
+        assert_eq!(signal_type, SignalType::Push(ItemKind::Single(pebble)));
+
+        signal_strength
+    }
+
+    #[test]
+    fn litter_signals_grow_logarithmically_with_pile_size() {
+        let one = litter_signal_strength(1, false);
+        let ten = litter_signal_strength(10, false);
+        let hundred = litter_signal_strength(100, false);
+
+        assert_eq!(one, SignalStrength::from_count(1));
+        assert!(one < ten);
+        assert!(ten < hundred);
+
+        // Each tenfold increase adds the same amount of strength
+        let first_step = (ten - one).value();
+        let second_step = (hundred - ten).value();
+        assert!((first_step - second_step).abs() < 1e-2);
+        // Which is much less than a linear increase
+        assert!(hundred.value() < one.value() * 10.);
+    }
+
+    #[test]
+    fn floating_litter_signals_at_half_strength() {
+        let on_ground = litter_signal_strength(10, false);
+        let floating = litter_signal_strength(10, true);
+
+        assert_eq!(floating, on_ground / 2.);
+    }
+
+    #[test]
+    fn adding_litter_merges_identical_slots() {
+        let item_manifest = item_manifest();
+        let mut litter = Litter {
+            contents: StorageInventory::new(3, None),
+        };
+
+        // Simulate two releasers that each left a partial stack in their own slot
+        litter.contents.add_empty_slot(leaf(), &item_manifest);
+        litter.contents.add_empty_slot(leaf(), &item_manifest);
+        for item_slot in litter.contents.iter_mut() {
+            item_slot.add_until_full(4).unwrap();
+        }
+
+        litter
+            .try_add_item(&ItemCount::new(leaf(), 1), &item_manifest)
+            .unwrap();
+        let counts: Vec<u32> = litter.contents.iter().map(|slot| slot.count()).collect();
+        assert_eq!(counts, vec![9]);
+
+        // Merged slots never exceed the stack size
+        litter
+            .try_add_item(&ItemCount::new(leaf(), 3), &item_manifest)
+            .unwrap();
+        let counts: Vec<u32> = litter.contents.iter().map(|slot| slot.count()).collect();
+        assert_eq!(counts, vec![10, 2]);
+    }
+
+    #[test]
+    fn gathered_litter_keeps_its_freshness_and_drains_tiles_in_order() {
+        let mut world = World::new();
+        let map_geometry = MapGeometry::new(&mut world, 1);
+        let item_manifest = item_manifest();
+        let stale_hex = Hex::new(1, 0);
+        let fresh_hex = Hex::new(0, 1);
+
+        for (hex, freshness) in [(stale_hex, 0.25), (fresh_hex, 1.)] {
+            let mut litter = Litter::default();
+            litter
+                .contents
+                .try_add_item_with_freshness(&ItemCount::new(leaf(), 3), freshness, &item_manifest)
+                .unwrap();
+            let terrain_entity = map_geometry.get_terrain(hex).unwrap();
+            world.entity_mut(terrain_entity).insert(litter);
+        }
+
+        let mut destination = Inventory::new(1, None);
+        let mut system_state: SystemState<Query<&mut Litter>> = SystemState::new(&mut world);
+        let mut litter_query = system_state.get_mut(&mut world);
+
+        // Nothing is taken unless everything can be found
+        assert!(!gather_litter(
+            &[ItemCount::new(leaf(), 7)],
+            [stale_hex, fresh_hex],
+            &mut litter_query,
+            &mut destination,
+            &map_geometry,
+            &item_manifest,
+        ));
+        assert_eq!(destination.item_count(leaf()), 0);
+
+        assert!(gather_litter(
+            &[ItemCount::new(leaf(), 3)],
+            [stale_hex, fresh_hex],
+            &mut litter_query,
+            &mut destination,
+            &map_geometry,
+            &item_manifest,
+        ));
+        assert_eq!(destination.item_count(leaf()), 3);
+        // The stale tile came first, and its leaves did not become fresh by being picked up
+        assert_eq!(destination.freshness_of_item(leaf()), 0.25);
+
+        let leaves_at = |hex: Hex| {
+            let terrain_entity = map_geometry.get_terrain(hex).unwrap();
+            world
+                .get::<Litter>(terrain_entity)
+                .unwrap()
+                .contents
+                .item_count(leaf())
+        };
+        assert_eq!(leaves_at(stale_hex), 0);
+        assert_eq!(leaves_at(fresh_hex), 3);
+    }
+
+    #[test]
+    fn overfull_litter_sheds_to_neighbor_until_balanced() {
+        let mut app = App::new();
+        let map_geometry = MapGeometry::new(&mut app.world, 1);
+        let item_manifest = item_manifest();
+
+        let terrain_entities: Vec<Entity> = map_geometry
+            .all_hexes()
+            .map(|hex| map_geometry.get_terrain(*hex).unwrap())
+            .collect();
+        for &terrain_entity in &terrain_entities {
+            app.world
+                .entity_mut(terrain_entity)
+                .insert(Litter::default());
+        }
+
+        let center_entity = map_geometry.get_terrain(Hex::ZERO).unwrap();
+        app.world
+            .get_mut::<Litter>(center_entity)
+            .unwrap()
+            .contents
+            .add_item_all_or_nothing(&ItemCount::new(leaf(), 10), &item_manifest)
+            .unwrap();
+
+        app.insert_resource(map_geometry)
+            .insert_resource(item_manifest)
+            .init_resource::<LitterIndex>()
+            .add_systems((
+                update_litter_index,
+                spread_litter.after(update_litter_index),
+            ));
+
+        for _ in 0..5 {
+            app.update();
+        }
+
+        let counts: Vec<u32> = terrain_entities
+            .iter()
+            .map(|&entity| {
+                let litter = app.world.get::<Litter>(entity).unwrap();
+                litter.contents.item_count(leaf())
+            })
+            .collect();
+
+        // No items are created or destroyed
+        assert_eq!(counts.iter().sum::<u32>(), 10);
+
+        let center_count = app
+            .world
+            .get::<Litter>(center_entity)
+            .unwrap()
+            .contents
+            .item_count(leaf());
+        assert_eq!(center_count, 5);
+
+        // The shed items all ended up on a single neighbor, which is now balanced with the center
+        assert_eq!(counts.iter().filter(|&&count| count == 5).count(), 2);
+    }
+
+    #[test]
+    fn nearly_balanced_litter_stays_put() {
+        let mut app = App::new();
+        let map_geometry = MapGeometry::new(&mut app.world, 1);
+        let item_manifest = item_manifest();
+
+        // Seven leaves in the center, surrounded by piles of six
+        let center_entity = map_geometry.get_terrain(Hex::ZERO).unwrap();
+        let terrain_entities: Vec<Entity> = map_geometry
+            .all_hexes()
+            .map(|hex| map_geometry.get_terrain(*hex).unwrap())
+            .collect();
+        for &terrain_entity in &terrain_entities {
+            let count = if terrain_entity == center_entity {
+                7
+            } else {
+                6
+            };
+            let mut litter = Litter::default();
+            litter
+                .contents
+                .add_item_all_or_nothing(&ItemCount::new(leaf(), count), &item_manifest)
+                .unwrap();
+            app.world.entity_mut(terrain_entity).insert(litter);
+        }
+
+        app.insert_resource(map_geometry)
+            .insert_resource(item_manifest)
+            .init_resource::<LitterIndex>()
+            .add_systems((
+                update_litter_index,
+                spread_litter.after(update_litter_index),
+            ));
+
+        for _ in 0..5 {
+            app.update();
+
+            for &terrain_entity in &terrain_entities {
+                let expected = if terrain_entity == center_entity {
+                    7
+                } else {
+                    6
+                };
+                let litter = app.world.get::<Litter>(terrain_entity).unwrap();
+                assert_eq!(litter.contents.item_count(leaf()), expected);
+            }
+        }
+    }
+
+    /// Sets up a map of the provided `radius`, with an empty pile of litter on every tile.
+    ///
+    /// Returns the app and the terrain entity on each tile.
+    fn litter_index_app(radius: u32) -> (App, HashMap<Hex, Entity>) {
+        let mut app = App::new();
+        let map_geometry = MapGeometry::new(&mut app.world, radius);
+
+        let mut terrain_entities = HashMap::default();
+        for &hex in map_geometry.all_hexes() {
+            let terrain_entity = map_geometry.get_terrain(hex).unwrap();
+            app.world
+                .entity_mut(terrain_entity)
+                .insert(Litter::default());
+            terrain_entities.insert(hex, terrain_entity);
+        }
+
+        app.insert_resource(map_geometry)
+            .init_resource::<LitterIndex>()
+            .add_system(update_litter_index);
+
+        (app, terrain_entities)
+    }
+
+    /// Finds every tile with litter on it by checking every litter component in the world.
+    fn brute_force_litter_scan(world: &mut World) -> HashSet<Hex> {
+        world
+            .query::<(&VoxelPos, &Litter)>()
+            .iter(world)
+            .filter(|(_, litter)| !litter.contents.is_empty())
+            .map(|(voxel_pos, _)| voxel_pos.hex)
+            .collect()
+    }
+
+    /// The set of tiles currently recorded in the [`LitterIndex`].
+    fn indexed_tiles(world: &World) -> HashSet<Hex> {
+        world.resource::<LitterIndex>().tiles().collect()
+    }
+
+    #[test]
+    fn litter_index_skips_empty_tiles() {
+        let (mut app, terrain_entities) = litter_index_app(20);
+        let item_manifest = item_manifest();
+
+        for hex in [Hex::ZERO, Hex::new(5, -3), Hex::new(-20, 10)] {
+            app.world
+                .get_mut::<Litter>(terrain_entities[&hex])
+                .unwrap()
+                .contents
+                .add_item_all_or_nothing(&ItemCount::new(leaf(), 1), &item_manifest)
+                .unwrap();
+        }
+
+        app.update();
+
+        // Only the three littered tiles out of more than a thousand need to be visited
+        assert!(terrain_entities.len() > 1000);
+        let litter_index = app.world.resource::<LitterIndex>();
+        assert_eq!(litter_index.len(), 3);
+        assert!(litter_index.has_litter(Hex::new(5, -3)));
+        assert!(!litter_index.has_litter(Hex::new(1, 0)));
+    }
+
+    #[test]
+    fn litter_index_matches_brute_force_scan() {
+        let (mut app, terrain_entities) = litter_index_app(3);
+        let item_manifest = item_manifest();
+
+        let littered = [Hex::ZERO, Hex::new(1, 0), Hex::new(-2, 3), Hex::new(0, -3)];
+        for hex in littered {
+            app.world
+                .get_mut::<Litter>(terrain_entities[&hex])
+                .unwrap()
+                .contents
+                .add_item_all_or_nothing(&ItemCount::new(leaf(), 2), &item_manifest)
+                .unwrap();
+        }
+
+        app.update();
+        assert_eq!(indexed_tiles(&app.world), HashSet::from_iter(littered));
+        assert_eq!(
+            indexed_tiles(&app.world),
+            brute_force_litter_scan(&mut app.world)
+        );
+
+        // Clean up one pile, add to another and despawn a third
+        app.world
+            .get_mut::<Litter>(terrain_entities[&Hex::ZERO])
+            .unwrap()
+            .contents
+            .try_remove_item(&ItemCount::new(leaf(), 2))
+            .unwrap();
+        app.world
+            .get_mut::<Litter>(terrain_entities[&Hex::new(2, -1)])
+            .unwrap()
+            .contents
+            .add_item_all_or_nothing(&ItemCount::new(leaf(), 1), &item_manifest)
+            .unwrap();
+        app.world.despawn(terrain_entities[&Hex::new(-2, 3)]);
+
+        app.update();
+        assert_eq!(
+            indexed_tiles(&app.world),
+            HashSet::from_iter([Hex::new(1, 0), Hex::new(0, -3), Hex::new(2, -1)])
+        );
+        assert_eq!(
+            indexed_tiles(&app.world),
+            brute_force_litter_scan(&mut app.world)
+        );
+    }
+}
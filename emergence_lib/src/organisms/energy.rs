@@ -202,6 +202,8 @@ pub(super) fn consume_energy(fixed_time: Res<FixedTime>, mut energy_query: Query
 }
 
 /// Despawns organisms when they run out of energy
+///
+/// Structure organisms leave their [`Remains`](super::life_stages::Remains) behind.
 pub(super) fn kill_organisms_when_out_of_energy(
     organism_query: Query<(Entity, &EnergyPool, &VoxelPos, Option<&Id<Structure>>)>,
     mut commands: Commands,
@@ -209,7 +211,7 @@ pub(super) fn kill_organisms_when_out_of_energy(
     for (entity, energy_pool, voxel_pos, maybe_structure) in organism_query.iter() {
         if energy_pool.is_empty() {
             match maybe_structure {
-                Some(_) => commands.despawn_structure(*voxel_pos),
+                Some(_) => commands.kill_organism_structure(*voxel_pos),
                 None => commands.entity(entity).despawn_recursive(),
             }
         }
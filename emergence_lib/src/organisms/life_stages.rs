@@ -0,0 +1,419 @@
+//! Organisms are born, mature, and eventually die of old age.
+//!
+//! Growing into a different form, such as a sapling becoming a tree, is handled by the organism's [`Lifecycle`](super::lifecycle::Lifecycle).
+//! Dead organisms leave their [`Remains`] behind,
+//! and any resources they were holding onto are returned to the world.
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    asset_management::manifest::Id,
+    geometry::VoxelPos,
+    items::ItemCount,
+    simulation::time::{Days, InGameTime},
+    structures::{
+        commands::StructureCommandsExt,
+        structure_manifest::{Structure, StructureManifest},
+    },
+    units::age::Age,
+};
+
+/// The stages of life that an organism passes through as it ages.
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum LifeStage {
+    /// The organism is young, and has not yet reached its full size.
+    Seedling,
+    /// The organism has grown into its adult form.
+    Mature,
+    /// The organism has died of old age, and is about to be removed.
+    Dead,
+}
+
+/// How long an organism takes to mature, and how long it lives.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LifeSpan {
+    /// The age at which the organism becomes [`LifeStage::Mature`].
+    pub maturity: Days,
+    /// The age at which the organism dies.
+    pub max_age: Days,
+}
+
+impl LifeSpan {
+    /// The [`LifeStage`] of an organism with this life span at the provided `age`.
+    pub fn stage(&self, age: Days) -> LifeStage {
+        if age > self.max_age {
+            LifeStage::Dead
+        } else if age >= self.maturity {
+            LifeStage::Mature
+        } else {
+            LifeStage::Seedling
+        }
+    }
+}
+
+/// The unprocessed form of a [`LifeSpan`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RawLifeSpan {
+    /// The age in days at which the organism becomes [`LifeStage::Mature`].
+    pub maturity: f32,
+    /// The age in days at which the organism dies.
+    pub max_age: f32,
+}
+
+impl From<RawLifeSpan> for LifeSpan {
+    fn from(raw: RawLifeSpan) -> Self {
+        LifeSpan {
+            maturity: Days(raw.maturity),
+            max_age: Days(raw.max_age),
+        }
+    }
+}
+
+/// What an organism leaves behind when it dies, whether of old age or from running out of energy.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Remains {
+    /// The structure spawned in place of the dead organism, if any.
+    ///
+    /// This is placed at the organism's center with the same facing, and may be a kind that players cannot build, such as a landmark.
+    pub structure: Option<Id<Structure>>,
+    /// The items scattered as litter over the organism's footprint.
+    pub items: Vec<ItemCount>,
+    /// The items recovered when the remains [`structure`](Self::structure) is demolished.
+    pub salvage: Vec<ItemCount>,
+}
+
+/// The unprocessed form of [`Remains`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RemainsData {
+    /// The name of the structure spawned in place of the dead organism, if any.
+    #[serde(default)]
+    pub structure: Option<String>,
+    /// The names and counts of the items scattered as litter over the organism's footprint.
+    #[serde(default)]
+    pub items: Vec<(String, u32)>,
+    /// The names and counts of the items recovered when the remains structure is demolished.
+    #[serde(default)]
+    pub salvage: Vec<(String, u32)>,
+}
+
+impl From<RemainsData> for Remains {
+    fn from(raw: RemainsData) -> Self {
+        /// Converts a list of item names and counts into [`ItemCount`]s.
+        fn item_counts(raw_items: Vec<(String, u32)>) -> Vec<ItemCount> {
+            raw_items
+                .into_iter()
+                .map(|(item_name, count)| ItemCount::new(Id::from_name(item_name), count))
+                .collect()
+        }
+
+        Remains {
+            structure: raw.structure.map(Id::from_name),
+            items: item_counts(raw.items),
+            salvage: item_counts(raw.salvage),
+        }
+    }
+}
+
+/// Ages structure organisms, killing them once they are too old.
+///
+/// Dead organisms leave behind their [`Remains`], as described by their [`OrganismVariety`](super::OrganismVariety).
+pub(super) fn advance_life_stages(
+    mut organism_query: Query<(&Id<Structure>, &VoxelPos, &mut Age, &mut LifeStage)>,
+    structure_manifest: Res<StructureManifest>,
+    fixed_time: Res<FixedTime>,
+    in_game_time: Res<InGameTime>,
+    mut commands: Commands,
+) {
+    let delta_days = Days(fixed_time.period.as_secs_f32() / in_game_time.seconds_per_day());
+
+    for (&structure_id, &voxel_pos, mut age, mut life_stage) in organism_query.iter_mut() {
+        let Some(life_span) = structure_manifest
+            .get(structure_id)
+            .organism_variety
+            .as_ref()
+            .and_then(|organism_variety| organism_variety.life_span.as_ref())
+        else { continue };
+
+        age.advance(delta_days);
+        life_stage.set_if_neq(life_span.stage(age.current()));
+
+        if *life_stage == LifeStage::Dead {
+            commands.kill_organism_structure(voxel_pos);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        asset_management::manifest::{Manifest, ManifestBuilder},
+        construction::{demolition::Salvage, ConstructionStrategy},
+        geometry::MapGeometry,
+        items::item_manifest::{Item, ItemData, ItemManifest},
+        litter::Litter,
+        organisms::{
+            energy::StartingEnergy,
+            lifecycle::{transform_when_lifecycle_complete, LifePath, Lifecycle},
+            OrganismId, OrganismVariety,
+        },
+        player_interaction::clipboard::ClipboardData,
+        simulation::time::TimePool,
+        structures::{
+            structure_manifest::{StructureData, StructureKind},
+            Footprint, Landmark,
+        },
+        units::{unit_assets::UnitHandles, unit_manifest::UnitManifest},
+        water::WaterConfig,
+    };
+    use bevy::utils::HashMap;
+    use hexx::Hex;
+
+    fn leaf() -> Id<Item> {
+        Id::from_name("leaf".to_string())
+    }
+
+    fn item_manifest() -> ItemManifest {
+        ManifestBuilder::new()
+            .add(
+                "leaf",
+                ItemData {
+                    compostable: true,
+                    buoyant: true,
+                    ..ItemData::basic(10)
+                },
+            )
+            .build()
+    }
+
+    /// A plant that matures after one day and dies after three.
+    fn plant(name: &str, footprint: Footprint) -> StructureData {
+        let mut organism_variety = OrganismVariety::simple(name);
+        organism_variety.life_span = Some(LifeSpan {
+            maturity: Days(1.),
+            max_age: Days(3.),
+        });
+        organism_variety.remains = Some(Remains {
+            structure: None,
+            items: vec![ItemCount::new(leaf(), 2)],
+            salvage: Vec::new(),
+        });
+
+        StructureData {
+            organism_variety: Some(organism_variety),
+            footprint,
+            ..StructureData::organism(name)
+        }
+    }
+
+    /// Creates an app where every update advances time by a quarter of a day.
+    fn life_stage_app(structure_manifest: StructureManifest) -> App {
+        let mut app = App::new();
+        let map_geometry = MapGeometry::new(&mut app.world, 2);
+        for &hex in map_geometry.all_hexes() {
+            let terrain_entity = map_geometry.get_terrain(hex).unwrap();
+            app.world
+                .entity_mut(terrain_entity)
+                .insert(Litter::default());
+        }
+
+        let in_game_time = InGameTime::default();
+        app.insert_resource(FixedTime::new_from_secs(
+            in_game_time.seconds_per_day() / 4.,
+        ))
+        .insert_resource(in_game_time)
+        .insert_resource(map_geometry)
+        .insert_resource(structure_manifest)
+        .insert_resource(item_manifest())
+        .insert_resource(WaterConfig::IN_GAME)
+        .insert_resource::<UnitManifest>(Manifest::new())
+        .insert_resource(UnitHandles {
+            scenes: HashMap::new(),
+            picking_mesh: Handle::default(),
+        })
+        .add_systems(
+            (
+                record_quarter_day,
+                advance_life_stages,
+                transform_when_lifecycle_complete,
+            )
+                .chain(),
+        );
+
+        app
+    }
+
+    /// Advances every [`Lifecycle`] by the quarter of a day that passes each update.
+    fn record_quarter_day(mut lifecycle_query: Query<&mut Lifecycle>) {
+        for mut lifecycle in lifecycle_query.iter_mut() {
+            lifecycle.record_elapsed_time(Days(0.25));
+        }
+    }
+
+    fn spawn_plant(app: &mut App, name: &str) {
+        let structure_manifest = app.world.resource::<StructureManifest>();
+        let data =
+            ClipboardData::generate_from_id(Id::from_name(name.to_string()), structure_manifest);
+        let voxel_pos = app
+            .world
+            .resource::<MapGeometry>()
+            .on_top_of_terrain(Hex::ZERO);
+
+        let mut command_queue = bevy::ecs::system::CommandQueue::default();
+        let mut commands = Commands::new(&mut command_queue, &app.world);
+        commands.spawn_structure(voxel_pos, data, StartingEnergy::Full);
+        command_queue.apply(&mut app.world);
+    }
+
+    fn life_stages(app: &mut App) -> Vec<(Id<Structure>, LifeStage)> {
+        app.world
+            .query::<(&Id<Structure>, &LifeStage)>()
+            .iter(&app.world)
+            .map(|(&structure_id, &life_stage)| (structure_id, life_stage))
+            .collect()
+    }
+
+    #[test]
+    fn plants_grow_from_seedling_to_death_and_leave_litter() {
+        let structure_manifest: StructureManifest = ManifestBuilder::new()
+            .add("shrub", plant("shrub", Footprint::single()))
+            .build();
+        let shrub = Id::from_name("shrub".to_string());
+
+        let mut app = life_stage_app(structure_manifest);
+        spawn_plant(&mut app, "shrub");
+        assert_eq!(life_stages(&mut app), vec![(shrub, LifeStage::Seedling)]);
+
+        // One day old
+        for _ in 0..4 {
+            app.update();
+        }
+        assert_eq!(life_stages(&mut app), vec![(shrub, LifeStage::Mature)]);
+
+        // Past three days old
+        for _ in 0..9 {
+            app.update();
+        }
+        assert!(life_stages(&mut app).is_empty());
+
+        let map_geometry = app.world.resource::<MapGeometry>();
+        assert_eq!(
+            map_geometry.get_structure(map_geometry.on_top_of_terrain(Hex::ZERO)),
+            None
+        );
+        let terrain_entity = map_geometry.get_terrain(Hex::ZERO).unwrap();
+        let litter = app.world.get::<Litter>(terrain_entity).unwrap();
+        assert_eq!(litter.contents.item_count(leaf()), 2);
+    }
+
+    #[test]
+    fn seedlings_change_footprint_when_they_mature() {
+        let tree = Id::from_name("tree".to_string());
+        // Saplings grow into trees through their lifecycle, on the same day that they mature
+        let mut sapling = plant("sapling", Footprint::single());
+        sapling.organism_variety.as_mut().unwrap().lifecycle = Lifecycle::new(vec![LifePath {
+            new_form: OrganismId::Structure(tree),
+            energy_required: None,
+            time_required: Some(TimePool::simple(1.)),
+        }]);
+        let structure_manifest: StructureManifest = ManifestBuilder::new()
+            .add("sapling", sapling)
+            .add("tree", plant("tree", Footprint::hexagon(1)))
+            .build();
+
+        let mut app = life_stage_app(structure_manifest);
+        spawn_plant(&mut app, "sapling");
+
+        for _ in 0..4 {
+            app.update();
+        }
+
+        // The tree is a fresh organism, and so starts its own life as a seedling
+        assert_eq!(life_stages(&mut app), vec![(tree, LifeStage::Seedling)]);
+        let map_geometry = app.world.resource::<MapGeometry>();
+        let neighbor = map_geometry.on_top_of_terrain(Hex::new(1, 0));
+        assert!(map_geometry.get_structure(neighbor).is_some());
+    }
+
+    #[test]
+    fn dead_organisms_leave_a_remains_structure_and_litter() {
+        let mut tree = plant("tree", Footprint::hexagon(1));
+        tree.organism_variety.as_mut().unwrap().remains = Some(Remains {
+            structure: Some(Id::from_name("dead_tree".to_string())),
+            items: vec![ItemCount::new(leaf(), 14)],
+            salvage: vec![ItemCount::new(leaf(), 5)],
+        });
+        // Players can't build dead trees
+        let dead_tree = StructureData {
+            kind: StructureKind::Landmark,
+            construction_strategy: ConstructionStrategy::Landmark,
+            footprint: Footprint::hexagon(1),
+            ..StructureData::passable()
+        };
+        let structure_manifest: StructureManifest = ManifestBuilder::new()
+            .add("tree", tree)
+            .add("dead_tree", dead_tree)
+            .build();
+
+        let mut app = life_stage_app(structure_manifest);
+        spawn_plant(&mut app, "tree");
+
+        // Past three days old
+        for _ in 0..13 {
+            app.update();
+        }
+        assert!(life_stages(&mut app).is_empty());
+
+        let map_geometry = app.world.resource::<MapGeometry>();
+        let center = map_geometry.on_top_of_terrain(Hex::ZERO);
+        let neighbor = map_geometry.on_top_of_terrain(Hex::new(1, 0));
+        let remains_entity = map_geometry.get_structure(center).unwrap();
+        assert_eq!(map_geometry.get_structure(neighbor), Some(remains_entity));
+
+        let remains = app.world.entity(remains_entity);
+        assert_eq!(
+            remains.get::<Id<Structure>>(),
+            Some(&Id::from_name("dead_tree".to_string()))
+        );
+        assert!(remains.contains::<Landmark>());
+        assert_eq!(
+            remains.get::<Salvage>().unwrap().items,
+            vec![ItemCount::new(leaf(), 5)]
+        );
+
+        let total_litter = |app: &mut App| -> u32 {
+            app.world
+                .query::<&Litter>()
+                .iter(&app.world)
+                .map(|litter| litter.contents.item_count(leaf()))
+                .sum()
+        };
+        // The leaves don't fit in a single tile's litter, so they spill over the rest of the footprint
+        let terrain_entity = app
+            .world
+            .resource::<MapGeometry>()
+            .get_terrain(Hex::ZERO)
+            .unwrap();
+        assert_eq!(
+            app.world
+                .get::<Litter>(terrain_entity)
+                .unwrap()
+                .contents
+                .item_count(leaf()),
+            10
+        );
+        assert_eq!(total_litter(&mut app), 14);
+
+        // Demolishing the remains recovers the salvage
+        let mut command_queue = bevy::ecs::system::CommandQueue::default();
+        let mut commands = Commands::new(&mut command_queue, &app.world);
+        commands.demolish_structure(center);
+        command_queue.apply(&mut app.world);
+
+        let map_geometry = app.world.resource::<MapGeometry>();
+        assert_eq!(map_geometry.get_structure(center), None);
+        assert_eq!(map_geometry.get_structure(neighbor), None);
+        assert_eq!(total_litter(&mut app), 19);
+    }
+}
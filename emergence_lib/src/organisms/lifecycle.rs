@@ -47,6 +47,11 @@ impl Lifecycle {
         Lifecycle { life_paths }
     }
 
+    /// The forms that this organism can turn into, and their triggering conditions.
+    pub(crate) fn life_paths(&self) -> &[LifePath] {
+        &self.life_paths
+    }
+
     /// Returns the [`OrganismId`] the list of completed [`LifePath`], if any.
     ///
     /// These are prioritized in the order they were added to the lifecycle.
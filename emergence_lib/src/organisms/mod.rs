@@ -1,25 +1,30 @@
 //! Models organisms, which have two primary types: units (organisms that can move around freely)
 //! and structures (organisms that are fixed in place).
-use bevy::prelude::*;
+use bevy::{prelude::*, utils::HashMap};
 use serde::{Deserialize, Serialize};
+use std::fmt::Display;
 
 use crate::{
     asset_management::manifest::Id,
-    simulation::SimulationSet,
+    simulation::{time::Season, SimulationSet},
     structures::structure_manifest::{Structure, StructureManifest},
     units::unit_manifest::{Unit, UnitManifest},
 };
 
 use self::{
     energy::{consume_energy, kill_organisms_when_out_of_energy, EnergyPool},
+    life_stages::{advance_life_stages, LifeSpan, RawLifeSpan, Remains, RemainsData},
     lifecycle::{sprout_seeds, transform_when_lifecycle_complete, Lifecycle, RawLifecycle},
     oxygen::{manage_oxygen, Oxygen, OxygenPool},
+    pollination::{pollinate, Pollination, RawPollination},
     vegetative_reproduction::vegetative_spread,
 };
 
 pub mod energy;
+pub mod life_stages;
 pub mod lifecycle;
 pub mod oxygen;
+pub mod pollination;
 pub mod vegetative_reproduction;
 
 /// The [`Id`] of an organism.
@@ -31,6 +36,15 @@ pub enum OrganismId {
     Unit(Id<Unit>),
 }
 
+impl Display for OrganismId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            OrganismId::Structure(structure_id) => write!(f, "structure {structure_id}"),
+            OrganismId::Unit(unit_id) => write!(f, "unit {unit_id}"),
+        }
+    }
+}
+
 /// The unprocessed equivalent of [`OrganismId`].
 #[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
 pub enum RawOrganismId {
@@ -114,6 +128,20 @@ pub struct OrganismVariety {
     pub lifecycle: Lifecycle,
     /// Controls the maximum energy, and the rate at which it drains.
     pub energy_pool: EnergyPool,
+    /// How this organism's vital processes change with the seasons.
+    pub seasonal_modifiers: SeasonalModifiers,
+    /// How long this organism takes to mature, and how long it lives.
+    ///
+    /// If this is [`None`], the organism never dies of old age.
+    pub life_span: Option<LifeSpan>,
+    /// What this organism leaves behind when it dies.
+    ///
+    /// If this is [`None`], only the items it was holding are dropped.
+    pub remains: Option<Remains>,
+    /// Which partners can pollinate this organism, and whether it needs them to set seed.
+    ///
+    /// If this is [`None`], this organism is never pollinated.
+    pub pollination: Option<Pollination>,
 }
 
 impl OrganismVariety {
@@ -126,6 +154,10 @@ impl OrganismVariety {
             prototypical_form: OrganismId::Unit(Id::from_name(name.to_string())),
             lifecycle: Lifecycle::default(),
             energy_pool: EnergyPool::default(),
+            seasonal_modifiers: SeasonalModifiers::default(),
+            life_span: None,
+            remains: None,
+            pollination: None,
         }
     }
 }
@@ -139,6 +171,40 @@ pub struct RawOrganismVariety {
     pub lifecycle: RawLifecycle,
     /// Controls the maximum energy, and the rate at which it drains.
     pub energy_pool: EnergyPool,
+    /// How this organism's vital processes change with the seasons.
+    ///
+    /// If this is omitted, the organism is unaffected by the seasons.
+    #[serde(default)]
+    pub seasonal_modifiers: SeasonalModifiers,
+    /// How long this organism takes to mature, and how long it lives.
+    ///
+    /// If this is omitted, the organism never dies of old age.
+    #[serde(default)]
+    pub life_span: Option<RawLifeSpan>,
+    /// What this organism leaves behind when it dies.
+    ///
+    /// If this is omitted, only the items it was holding are dropped.
+    #[serde(default)]
+    pub remains: Option<RemainsData>,
+    /// Which partners can pollinate this organism, and whether it needs them to set seed.
+    ///
+    /// If this is omitted, this organism is never pollinated.
+    #[serde(default)]
+    pub pollination: Option<RawPollination>,
+}
+
+impl RawOrganismVariety {
+    /// Resolves the [`Pollination`] partners of this variety.
+    ///
+    /// See [`RawPollination::resolve`] for how names are assigned to structures or units.
+    pub(crate) fn resolve_pollination(
+        &self,
+        is_structure: impl Fn(&str) -> bool,
+    ) -> Option<Pollination> {
+        self.pollination
+            .as_ref()
+            .map(|raw_pollination| raw_pollination.resolve(is_structure))
+    }
 }
 
 impl From<RawOrganismVariety> for OrganismVariety {
@@ -147,6 +213,66 @@ impl From<RawOrganismVariety> for OrganismVariety {
             prototypical_form: raw.prototypical_form.into(),
             lifecycle: raw.lifecycle.into(),
             energy_pool: raw.energy_pool,
+            seasonal_modifiers: raw.seasonal_modifiers,
+            life_span: raw.life_span.map(Into::into),
+            remains: raw.remains.map(Into::into),
+            // Partners may be structures or units, so they can only be resolved by the manifest that owns this variety
+            pollination: None,
+        }
+    }
+}
+
+/// Multipliers applied to the rate of an organism's vital processes during a single [`Season`].
+///
+/// A value of 1.0 leaves the process unchanged, while 0.0 halts it entirely.
+/// These values should never be negative.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct SeasonalMultipliers {
+    /// Scales the rate at which time-based lifecycle progress accumulates.
+    pub growth: f32,
+    /// Scales the rate at which vegetative reproduction is attempted.
+    pub reproduction_chance: f32,
+    /// Scales the rate at which water is drawn up through the roots.
+    pub water_consumption: f32,
+}
+
+impl SeasonalMultipliers {
+    /// Multipliers that have no effect.
+    pub const NEUTRAL: SeasonalMultipliers = SeasonalMultipliers {
+        growth: 1.0,
+        reproduction_chance: 1.0,
+        water_consumption: 1.0,
+    };
+}
+
+impl Default for SeasonalMultipliers {
+    fn default() -> Self {
+        SeasonalMultipliers::NEUTRAL
+    }
+}
+
+/// The [`SeasonalMultipliers`] used by an organism for each [`Season`].
+///
+/// Seasons that are not listed use [`SeasonalMultipliers::NEUTRAL`].
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+#[serde(transparent)]
+pub struct SeasonalModifiers {
+    /// The multipliers for each season with non-default behavior.
+    multipliers: HashMap<Season, SeasonalMultipliers>,
+}
+
+impl SeasonalModifiers {
+    /// Returns the multipliers that should be used during the provided `season`.
+    pub fn get(&self, season: Season) -> SeasonalMultipliers {
+        self.multipliers.get(&season).copied().unwrap_or_default()
+    }
+}
+
+impl FromIterator<(Season, SeasonalMultipliers)> for SeasonalModifiers {
+    fn from_iter<T: IntoIterator<Item = (Season, SeasonalMultipliers)>>(iter: T) -> Self {
+        SeasonalModifiers {
+            multipliers: iter.into_iter().collect(),
         }
     }
 }
@@ -165,8 +291,10 @@ impl Plugin for OrganismPlugin {
                 consume_energy,
                 kill_organisms_when_out_of_energy,
                 transform_when_lifecycle_complete,
+                advance_life_stages,
                 vegetative_spread,
                 sprout_seeds,
+                pollinate,
                 manage_oxygen,
             )
                 .in_set(SimulationSet)
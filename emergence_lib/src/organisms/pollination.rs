@@ -0,0 +1,360 @@
+//! Some organisms can only set seed once they have been pollinated by a compatible partner.
+//!
+//! Partners pollinate an organism simply by spending time nearby.
+//! Pollination wears off after a while, so partners must keep visiting to keep the seeds coming.
+
+use bevy::{prelude::*, utils::HashMap};
+use hexx::{shapes::hexagon, Hex};
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+use crate::{
+    asset_management::manifest::Id,
+    construction::ghosts::{Ghost, Preview},
+    geometry::{MapGeometry, VoxelPos},
+    structures::structure_manifest::{Structure, StructureManifest},
+    units::unit_manifest::Unit,
+};
+
+use super::OrganismId;
+
+/// Which organisms can pollinate a variety, and how close they need to be.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Pollination {
+    /// The organisms that can pollinate this one.
+    pub partners: Vec<OrganismId>,
+    /// The maximum distance in tiles that a partner can be from this organism to pollinate it.
+    pub radius: u32,
+    /// Does this organism need to be pollinated to produce seeds?
+    ///
+    /// If `false`, pollination is tracked but seeds are produced regardless.
+    pub required: bool,
+}
+
+/// The unprocessed equivalent of [`Pollination`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RawPollination {
+    /// The names of the structures or units that can pollinate this organism.
+    pub partners: Vec<String>,
+    /// The maximum distance in tiles that a partner can be from this organism to pollinate it.
+    pub radius: u32,
+    /// Does this organism need to be pollinated to produce seeds?
+    pub required: bool,
+}
+
+impl RawPollination {
+    /// Converts the partner names into [`OrganismId`]s.
+    ///
+    /// Partners can be either structures or units, but each manifest only knows its own names.
+    /// Names for which `is_structure` returns `true` become structures, and all others become units:
+    /// references to entries that do not exist in either manifest are caught by
+    /// [`StructureManifest::validate_pollination_partners`].
+    pub(crate) fn resolve(&self, is_structure: impl Fn(&str) -> bool) -> Pollination {
+        Pollination {
+            partners: self
+                .partners
+                .iter()
+                .map(|name| match is_structure(name) {
+                    true => OrganismId::Structure(Id::from_name(name.clone())),
+                    false => OrganismId::Unit(Id::from_name(name.clone())),
+                })
+                .collect(),
+            radius: self.radius,
+            required: self.required,
+        }
+    }
+}
+
+/// Tracks whether an organism with a [`Pollination`] has been visited by one of its partners recently.
+#[derive(Component, Debug, Clone, PartialEq, Eq)]
+pub(crate) struct PollinationState {
+    /// Does this organism need to be pollinated to produce seeds?
+    required: bool,
+    /// How much longer the most recent pollination lasts.
+    ///
+    /// This is reset every tick that a partner is nearby, and decays otherwise.
+    remaining: Duration,
+}
+
+impl PollinationState {
+    /// How long a single pollination lasts after the partner has left.
+    pub(crate) const DURATION: Duration = Duration::from_secs(30);
+
+    /// Creates a new, unpollinated [`PollinationState`].
+    pub(crate) fn new(pollination: &Pollination) -> Self {
+        PollinationState {
+            required: pollination.required,
+            remaining: Duration::ZERO,
+        }
+    }
+
+    /// Has this organism been pollinated recently?
+    pub(crate) fn is_pollinated(&self) -> bool {
+        self.remaining > Duration::ZERO
+    }
+
+    /// Can this organism produce seeds right now?
+    pub(crate) fn can_set_seed(&self) -> bool {
+        !self.required || self.is_pollinated()
+    }
+}
+
+/// Pollinates organisms whose partners are within range, and lets older pollination wear off.
+pub(super) fn pollinate(
+    mut pollinated_query: Query<(Entity, &VoxelPos, &Id<Structure>, &mut PollinationState)>,
+    structure_query: Query<&Id<Structure>, (Without<Ghost>, Without<Preview>)>,
+    unit_query: Query<(&VoxelPos, &Id<Unit>)>,
+    structure_manifest: Res<StructureManifest>,
+    map_geometry: Res<MapGeometry>,
+    fixed_time: Res<FixedTime>,
+) {
+    // Units are not indexed by the map geometry, so they are grouped by tile once per tick instead
+    let mut units_by_hex: HashMap<Hex, Vec<Id<Unit>>> = HashMap::new();
+    for (voxel_pos, &unit_id) in unit_query.iter() {
+        units_by_hex.entry(voxel_pos.hex).or_default().push(unit_id);
+    }
+
+    for (entity, voxel_pos, &structure_id, mut pollination_state) in pollinated_query.iter_mut() {
+        let Some(pollination) = structure_manifest
+            .get(structure_id)
+            .organism_variety
+            .as_ref()
+            .and_then(|organism_variety| organism_variety.pollination.as_ref())
+        else { continue };
+
+        // Organisms never pollinate themselves, even if they are their own partner
+        let structure_partner_nearby = map_geometry
+            .structures_in_radius(*voxel_pos, pollination.radius)
+            .into_iter()
+            .filter(|&(_, partner_entity)| partner_entity != entity)
+            .filter_map(|(_, partner_entity)| structure_query.get(partner_entity).ok())
+            .any(|&partner_id| {
+                pollination
+                    .partners
+                    .contains(&OrganismId::Structure(partner_id))
+            });
+
+        let partner_nearby = structure_partner_nearby
+            || hexagon(voxel_pos.hex, pollination.radius)
+                .filter_map(|hex| units_by_hex.get(&hex))
+                .flatten()
+                .any(|&partner_id| pollination.partners.contains(&OrganismId::Unit(partner_id)));
+
+        pollination_state.remaining = match partner_nearby {
+            true => PollinationState::DURATION,
+            false => pollination_state
+                .remaining
+                .saturating_sub(fixed_time.period),
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        asset_management::manifest::{Manifest, ManifestBuilder},
+        crafting::{
+            inventories::{CraftingState, OutputInventory},
+            progress_crafting,
+            recipe::{ActiveRecipe, RecipeData, RecipeInput, RecipeOutput},
+        },
+        geometry::{Facing, MapGeometry},
+        items::{
+            item_manifest::{Item, ItemData, ItemManifest},
+            ItemCount,
+        },
+        organisms::{Organism, OrganismVariety},
+        structures::{structure_manifest::StructureData, Footprint},
+        testing::crafter_app::{crafter_app, spawn_crafter},
+    };
+
+    fn seed() -> Id<Item> {
+        Id::from_name("flower_seed".to_string())
+    }
+
+    fn petal() -> Id<Item> {
+        Id::from_name("petal".to_string())
+    }
+
+    fn item_manifest() -> ItemManifest {
+        let item = |seed| ItemData {
+            compostable: true,
+            buoyant: true,
+            seed,
+            ..ItemData::basic(10)
+        };
+
+        let mut item_manifest: ItemManifest = Manifest::new();
+        item_manifest.insert(
+            "flower_seed".to_string(),
+            item(Some(OrganismId::Structure(Id::from_name(
+                "flower".to_string(),
+            )))),
+        );
+        item_manifest.insert("petal".to_string(), item(None));
+        item_manifest
+    }
+
+    /// A flower that must be pollinated by a hive within two tiles to set seed.
+    fn structure_manifest() -> StructureManifest {
+        let mut organism_variety = OrganismVariety::simple("flower");
+        organism_variety.pollination = Some(Pollination {
+            partners: vec![OrganismId::Structure(Id::from_name("hive".to_string()))],
+            radius: 2,
+            required: true,
+        });
+
+        let mut structure_manifest: StructureManifest = Manifest::new();
+        structure_manifest.insert(
+            "flower".to_string(),
+            StructureData {
+                organism_variety: Some(organism_variety),
+                ..StructureData::organism("flower")
+            },
+        );
+        structure_manifest.insert("hive".to_string(), StructureData::impassable());
+        structure_manifest
+    }
+
+    /// Sets up a world with a single flower in the center, which blooms once per update.
+    fn pollination_app() -> (App, Entity) {
+        let recipe_manifest = ManifestBuilder::new()
+            .add(
+                "bloom",
+                RecipeData::basic(
+                    RecipeInput::EMPTY,
+                    RecipeOutput::Deterministic(vec![
+                        ItemCount::new(seed(), 1),
+                        ItemCount::new(petal(), 1),
+                    ]),
+                ),
+            )
+            .build();
+        let mut app = crafter_app(3, recipe_manifest, structure_manifest(), item_manifest());
+
+        let flower_id = Id::from_name("flower".to_string());
+        let pollination = app
+            .world
+            .resource::<StructureManifest>()
+            .get(flower_id)
+            .organism_variety
+            .as_ref()
+            .unwrap()
+            .pollination
+            .clone()
+            .unwrap();
+        let flower_entity = spawn_crafter(
+            &mut app,
+            flower_id,
+            ActiveRecipe::new(Id::from_name("bloom".to_string())),
+            Hex::ZERO,
+        );
+        app.world
+            .entity_mut(flower_entity)
+            .insert((Organism, PollinationState::new(&pollination)));
+        app.add_systems((pollinate, progress_crafting).chain());
+
+        (app, flower_entity)
+    }
+
+    /// Runs the app until the flower has bloomed `n` times, and returns its output inventory.
+    fn bloom(app: &mut App, flower_entity: Entity, n: usize) -> OutputInventory {
+        for _ in 0..n {
+            loop {
+                app.update();
+                let crafting_state = app.world.get::<CraftingState>(flower_entity).unwrap();
+                if matches!(crafting_state, CraftingState::RecipeComplete) {
+                    break;
+                }
+            }
+        }
+        // Let the final completion deliver its outputs
+        app.update();
+
+        app.world
+            .get::<OutputInventory>(flower_entity)
+            .unwrap()
+            .clone()
+    }
+
+    fn spawn_hive(app: &mut App, hex: Hex) {
+        let voxel_pos = app.world.resource::<MapGeometry>().on_top_of_terrain(hex);
+        let hive_entity = app
+            .world
+            .spawn((voxel_pos, Id::<Structure>::from_name("hive".to_string())))
+            .id();
+        app.world
+            .resource_mut::<MapGeometry>()
+            .add_structure(
+                voxel_pos,
+                Facing::default(),
+                &Footprint::single(),
+                false,
+                false,
+                hive_entity,
+            )
+            .unwrap();
+    }
+
+    #[test]
+    fn required_pollination_flower_sets_no_seed_alone() {
+        let (mut app, flower_entity) = pollination_app();
+
+        let output = bloom(&mut app, flower_entity, 2);
+
+        assert_eq!(output.item_count(seed()), 0);
+        assert_eq!(output.item_count(petal()), 2);
+        assert!(!app
+            .world
+            .get::<PollinationState>(flower_entity)
+            .unwrap()
+            .is_pollinated());
+    }
+
+    #[test]
+    fn required_pollination_flower_sets_seed_with_partner_in_range() {
+        let (mut app, flower_entity) = pollination_app();
+        spawn_hive(&mut app, Hex::new(2, 0));
+
+        let output = bloom(&mut app, flower_entity, 2);
+
+        assert_eq!(output.item_count(seed()), 2);
+        assert_eq!(output.item_count(petal()), 2);
+    }
+
+    #[test]
+    fn partners_out_of_range_do_not_pollinate() {
+        let (mut app, flower_entity) = pollination_app();
+        spawn_hive(&mut app, Hex::new(3, 0));
+
+        let output = bloom(&mut app, flower_entity, 1);
+
+        assert_eq!(output.item_count(seed()), 0);
+    }
+
+    #[test]
+    fn pollination_wears_off() {
+        let pollination = Pollination {
+            partners: Vec::new(),
+            radius: 1,
+            required: true,
+        };
+        let mut pollination_state = PollinationState::new(&pollination);
+        assert!(!pollination_state.can_set_seed());
+
+        pollination_state.remaining = PollinationState::DURATION;
+        assert!(pollination_state.can_set_seed());
+
+        pollination_state.remaining = pollination_state
+            .remaining
+            .saturating_sub(PollinationState::DURATION);
+        assert!(!pollination_state.is_pollinated());
+
+        let optional = PollinationState::new(&Pollination {
+            required: false,
+            ..pollination
+        });
+        assert!(optional.can_set_seed());
+    }
+}
@@ -11,10 +11,12 @@ use crate::{
     asset_management::manifest::Id,
     geometry::{Facing, MapGeometry, VoxelPos},
     player_interaction::clipboard::ClipboardData,
+    simulation::time::Season,
     structures::{
         commands::StructureCommandsExt,
         structure_manifest::{Structure, StructureManifest},
     },
+    terrain::fertility::SoilFertility,
 };
 
 use super::energy::{Energy, EnergyPool, StartingEnergy};
@@ -70,9 +72,11 @@ pub(super) fn vegetative_spread(
         &mut VegetativeReproduction,
         &mut EnergyPool,
     )>,
+    terrain_query: Query<&SoilFertility>,
     map_geometry: Res<MapGeometry>,
     structure_manifest: Res<StructureManifest>,
     fixed_time: Res<FixedTime>,
+    season: Res<Season>,
     mut commands: Commands,
 ) {
     let mut rng = rand::thread_rng();
@@ -81,7 +85,19 @@ pub(super) fn vegetative_spread(
     for (&voxel_pos, &structure_id, mut vegetative_reproduction, mut energy_pool) in
         query.iter_mut()
     {
-        vegetative_reproduction.timer.tick(delta_time);
+        // Organisms spread less readily from poor soil
+        let fertility = map_geometry
+            .get_terrain(voxel_pos.hex)
+            .ok()
+            .and_then(|terrain_entity| terrain_query.get(terrain_entity).ok())
+            .map_or(SoilFertility::BASELINE, SoilFertility::value);
+        let reproduction_multiplier = structure_manifest
+            .seasonal_multipliers(structure_id, *season)
+            .reproduction_chance
+            * fertility;
+        vegetative_reproduction
+            .timer
+            .tick(delta_time.mul_f32(reproduction_multiplier.max(0.)));
         if !vegetative_reproduction.timer.finished() {
             continue;
         }
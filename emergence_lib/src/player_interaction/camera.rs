@@ -8,7 +8,10 @@ use bevy::core_pipeline::tonemapping::Tonemapping;
 use bevy::input::mouse::MouseMotion;
 use bevy::input::mouse::MouseWheel;
 use bevy::prelude::*;
+use bevy::render::camera::CameraProjection;
 use bevy_mod_raycast::RaycastSource;
+use hexx::Hex;
+use hexx::HexIterExt;
 use leafwing_input_manager::orientation::Rotation;
 use leafwing_input_manager::prelude::ActionState;
 
@@ -338,6 +341,13 @@ fn set_camera_focus(
             CurrentSelection::GhostStructure(entity)
             | CurrentSelection::Unit(entity)
             | CurrentSelection::Structure(entity) => Some(*tile_pos_query.get(*entity).unwrap()),
+            CurrentSelection::MultipleStructures(entities) => Some(VoxelPos {
+                hex: entities
+                    .iter()
+                    .map(|entity| tile_pos_query.get(*entity).unwrap().hex)
+                    .center(),
+                height: DiscreteHeight::ZERO,
+            }),
             CurrentSelection::Terrain(selected_tiles) => Some(VoxelPos {
                 hex: selected_tiles.center(),
                 height: DiscreteHeight::ZERO,
@@ -467,3 +477,31 @@ fn compute_camera_transform(
 
     transform
 }
+
+/// Returns the hexes on the map whose terrain is within the field of view of the camera.
+///
+/// Tiles hidden behind hills are still counted as visible.
+pub(crate) fn visible_hexes<'a>(
+    camera_transform: &GlobalTransform,
+    projection: &Projection,
+    map_geometry: &'a MapGeometry,
+) -> impl Iterator<Item = Hex> + 'a {
+    let view_projection =
+        projection.get_projection_matrix() * camera_transform.compute_matrix().inverse();
+
+    map_geometry.all_hexes().copied().filter(move |&hex| {
+        let terrain_pos = VoxelPos {
+            hex,
+            height: map_geometry.get_height(hex).unwrap_or_default(),
+        };
+
+        let clip_pos = view_projection * terrain_pos.top_of_tile().extend(1.0);
+        // Points behind the camera are not visible
+        if clip_pos.w <= 0. {
+            return false;
+        }
+
+        let normalized_device_pos = clip_pos.truncate() / clip_pos.w;
+        normalized_device_pos.x.abs() <= 1. && normalized_device_pos.y.abs() <= 1.
+    })
+}
@@ -33,6 +33,11 @@ impl Plugin for ClipboardPlugin {
                 rotate_selection
                     .in_set(InteractionSystem::SetClipboard)
                     .after(copy_selection),
+            )
+            .add_system(
+                mirror_selection
+                    .in_set(InteractionSystem::SetClipboard)
+                    .after(copy_selection),
             );
     }
 }
@@ -73,11 +78,11 @@ impl Tool {
 }
 
 /// The data copied via the clipboard for a single structure.
-#[derive(Debug, PartialEq, Eq, Clone)]
+#[derive(Debug, PartialEq, Clone)]
 pub(crate) struct ClipboardData {
     /// The identity of the structure.
     pub(crate) structure_id: Id<Structure>,
-    /// The orientation of the structure.
+    /// The orientation of the structure, including whether or not it is mirrored.
     pub(crate) facing: Facing,
     /// The recipe that this structure makes, if any
     pub(crate) active_recipe: ActiveRecipe,
@@ -172,6 +177,22 @@ impl Tool {
             *map = new_map;
         }
     }
+
+    /// Mirrors the contents of the clipboard across [`Facing::MIRROR_AXIS`].
+    ///
+    /// You must ensure that the contents are normalized first.
+    fn mirror(&mut self) {
+        if let Tool::Structures(map) = self {
+            let mut new_map = HashMap::with_capacity(map.capacity());
+
+            for (&original_pos, item) in map.iter_mut() {
+                item.facing.mirror();
+                new_map.insert(original_pos.mirrored(Facing::MIRROR_AXIS), item.clone());
+            }
+
+            *map = new_map;
+        }
+    }
 }
 
 /// Clears the clipboard when the correct actions are pressed
@@ -231,6 +252,15 @@ fn copy_selection(
                 *tool = Tool::Structures(map);
                 tool.normalize_positions();
             }
+            CurrentSelection::MultipleStructures(entities) => {
+                for entity in entities {
+                    let query_item = structure_query.get(*entity).unwrap();
+                    let voxel_pos = *query_item.voxel_pos;
+                    map.insert(voxel_pos, query_item.into());
+                }
+                *tool = Tool::Structures(map);
+                tool.normalize_positions();
+            }
             CurrentSelection::Terrain(selected_tiles) => {
                 // If there is no selection, just grab whatever's under the cursor
                 if selected_tiles.is_empty() {
@@ -289,3 +319,10 @@ fn rotate_selection(actions: Res<ActionState<PlayerAction>>, mut clipboard: ResM
         clipboard.rotate(true);
     }
 }
+
+/// Mirrors the contents of the clipboard based on player input
+fn mirror_selection(actions: Res<ActionState<PlayerAction>>, mut clipboard: ResMut<Tool>) {
+    if actions.just_pressed(PlayerAction::MirrorClipboard) {
+        clipboard.mirror();
+    }
+}
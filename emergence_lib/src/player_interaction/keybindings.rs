@@ -0,0 +1,342 @@
+//! Player-configurable keybindings, which are persisted to a config file.
+
+use std::{
+    env, fs, io,
+    path::{Path, PathBuf},
+};
+
+use bevy::prelude::*;
+use leafwing_input_manager::{prelude::InputMap, user_input::UserInput, Actionlike};
+use serde::{Deserialize, Serialize};
+
+use super::PlayerAction;
+
+/// Loads the player's keybindings on startup, and saves them whenever they are changed.
+pub(super) struct KeybindingsPlugin;
+
+impl Plugin for KeybindingsPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(PlayerAction::default_input_map())
+            .init_resource::<KeybindingsConfig>()
+            .add_startup_system(load_keybindings)
+            .add_system(
+                save_keybindings.run_if(
+                    resource_changed::<InputMap<PlayerAction>>()
+                        .and_then(not(resource_added::<InputMap<PlayerAction>>())),
+                ),
+            );
+    }
+}
+
+/// Controls where the player's keybindings are persisted.
+///
+/// Insert this before the [`KeybindingsPlugin`] is added to change where the keybindings are kept.
+#[derive(Resource, Debug, Clone, PartialEq, Eq)]
+pub(crate) struct KeybindingsConfig {
+    /// The path of the config file that the [`InputMap`] is loaded from and saved to.
+    ///
+    /// If this is [`None`], the default keybindings are used and changes are not saved.
+    pub(crate) path: Option<PathBuf>,
+}
+
+impl KeybindingsConfig {
+    /// The default keybindings are used each time the app is run, and changes are kept in memory only.
+    pub(crate) fn disabled() -> Self {
+        KeybindingsConfig { path: None }
+    }
+}
+
+impl Default for KeybindingsConfig {
+    fn default() -> Self {
+        KeybindingsConfig {
+            path: Some(config_dir().join("keybindings.json")),
+        }
+    }
+}
+
+/// The platform's per-user directory for this game's configuration files.
+///
+/// Falls back to the working directory if the platform's config directory cannot be determined.
+pub(crate) fn config_dir() -> PathBuf {
+    let base_dir = if cfg!(target_os = "windows") {
+        env::var_os("APPDATA").map(PathBuf::from)
+    } else if cfg!(target_os = "macos") {
+        env::var_os("HOME").map(|home| PathBuf::from(home).join("Library/Application Support"))
+    } else {
+        env::var_os("XDG_CONFIG_HOME")
+            .map(PathBuf::from)
+            .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))
+    };
+
+    match base_dir {
+        Some(base_dir) => base_dir.join("emergence"),
+        None => PathBuf::new(),
+    }
+}
+
+/// The inputs bound to a single [`PlayerAction`], as stored in the keybindings file.
+///
+/// Actions that are missing from the file keep their default bindings.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct SavedBinding {
+    /// The action that is bound.
+    action: PlayerAction,
+    /// The inputs that trigger `action`.
+    ///
+    /// If this is empty, the action is unbound.
+    inputs: Vec<UserInput>,
+}
+
+/// Changes the inputs bound to a [`PlayerAction`] at runtime.
+///
+/// Players can rebind actions with the console's `bind` command.
+pub(crate) trait Rebind {
+    /// Replaces all of the inputs bound to `action` with the single provided `input`.
+    fn rebind(&mut self, action: PlayerAction, input: impl Into<UserInput>);
+}
+
+impl Rebind for InputMap<PlayerAction> {
+    fn rebind(&mut self, action: PlayerAction, input: impl Into<UserInput>) {
+        self.clear_action(action.clone());
+        self.insert(input, action);
+    }
+}
+
+/// Loads the keybindings stored at `path`, merged over [`PlayerAction::default_input_map`].
+///
+/// Actions that are not listed in the file keep their default bindings.
+/// Falls back to the defaults entirely if the file does not exist or cannot be parsed.
+pub(crate) fn load_input_map(path: &Path) -> InputMap<PlayerAction> {
+    let mut input_map = PlayerAction::default_input_map();
+
+    let bytes = match fs::read(path) {
+        Ok(bytes) => bytes,
+        Err(_) => return input_map,
+    };
+
+    let saved_bindings: Vec<SavedBinding> = match serde_json::from_slice(&bytes) {
+        Ok(saved_bindings) => saved_bindings,
+        Err(json_error) => {
+            warn!(
+                "Could not parse keybindings at {}: {json_error}. Falling back to the defaults.",
+                path.display()
+            );
+            return input_map;
+        }
+    };
+
+    for SavedBinding { action, inputs } in saved_bindings {
+        input_map.clear_action(action.clone());
+        for input in inputs {
+            input_map.insert(input, action.clone());
+        }
+    }
+
+    input_map
+}
+
+/// Saves the keybindings to `path`, so they can be restored with [`load_input_map`].
+///
+/// Any missing parent directories are created.
+pub(crate) fn save_input_map(input_map: &InputMap<PlayerAction>, path: &Path) -> io::Result<()> {
+    let saved_bindings: Vec<SavedBinding> = PlayerAction::variants()
+        .map(|action| SavedBinding {
+            inputs: input_map.get(action.clone()).iter().cloned().collect(),
+            action,
+        })
+        .collect();
+
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    let bytes = serde_json::to_vec_pretty(&saved_bindings)?;
+    fs::write(path, bytes)
+}
+
+/// Replaces the default keybindings with those saved at [`KeybindingsConfig::path`].
+fn load_keybindings(config: Res<KeybindingsConfig>, mut input_map: ResMut<InputMap<PlayerAction>>) {
+    if let Some(path) = &config.path {
+        // Loading is not a change made by the player, so there is nothing new to save
+        *input_map.bypass_change_detection() = load_input_map(path);
+    }
+}
+
+/// Persists the keybindings whenever the player changes them.
+fn save_keybindings(input_map: Res<InputMap<PlayerAction>>, config: Res<KeybindingsConfig>) {
+    let Some(path) = &config.path else { return };
+
+    if let Err(io_error) = save_input_map(&input_map, path) {
+        warn!(
+            "Could not save keybindings to {}: {io_error}",
+            path.display()
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy::input::InputPlugin;
+    use leafwing_input_manager::{
+        prelude::{ActionState, InputManagerPlugin},
+        user_input::Modifier,
+    };
+
+    /// Returns whether `action` is pressed while all of the `keys` are held down.
+    fn triggers(
+        input_map: &InputMap<PlayerAction>,
+        keys: &[KeyCode],
+        action: PlayerAction,
+    ) -> bool {
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins)
+            .add_plugin(InputPlugin)
+            .add_plugin(InputManagerPlugin::<PlayerAction>::default())
+            .init_resource::<ActionState<PlayerAction>>()
+            .insert_resource(input_map.clone());
+
+        let mut keyboard_input = app.world.resource_mut::<Input<KeyCode>>();
+        for &key in keys {
+            keyboard_input.press(key);
+        }
+        app.update();
+
+        app.world
+            .resource::<ActionState<PlayerAction>>()
+            .pressed(action)
+    }
+
+    #[test]
+    fn rebinding_paste_to_enter_replaces_the_old_binding() {
+        let mut input_map = PlayerAction::default_input_map();
+        assert!(triggers(
+            &input_map,
+            &[KeyCode::LControl, KeyCode::V],
+            PlayerAction::Paste
+        ));
+        assert!(!triggers(
+            &input_map,
+            &[KeyCode::Return],
+            PlayerAction::Paste
+        ));
+
+        input_map.rebind(PlayerAction::Paste, KeyCode::Return);
+        assert!(triggers(
+            &input_map,
+            &[KeyCode::Return],
+            PlayerAction::Paste
+        ));
+        assert!(!triggers(
+            &input_map,
+            &[KeyCode::LControl, KeyCode::V],
+            PlayerAction::Paste
+        ));
+
+        // Other actions are unaffected
+        assert!(triggers(
+            &input_map,
+            &[KeyCode::LControl, KeyCode::C],
+            PlayerAction::Copy
+        ));
+    }
+
+    /// The inputs bound to each action, in order, as [`InputMap`]s cannot be compared directly.
+    fn bindings(input_map: &InputMap<PlayerAction>) -> Vec<Vec<UserInput>> {
+        PlayerAction::variants()
+            .map(|action| input_map.get(action).iter().cloned().collect())
+            .collect()
+    }
+
+    /// A path in the temp directory that no other test, or concurrent test run, will use.
+    fn unique_temp_path(name: &str) -> PathBuf {
+        env::temp_dir().join(format!(
+            "emergence_keybindings_{}_{name}.json",
+            std::process::id()
+        ))
+    }
+
+    #[test]
+    fn keybindings_survive_a_save_and_load() {
+        let path = unique_temp_path("round_trip");
+
+        let mut input_map = PlayerAction::default_input_map();
+        input_map.rebind(
+            PlayerAction::Paste,
+            UserInput::modified(Modifier::Shift, KeyCode::Return),
+        );
+        save_input_map(&input_map, &path).unwrap();
+
+        let loaded = load_input_map(&path);
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(bindings(&loaded), bindings(&input_map));
+    }
+
+    #[test]
+    fn missing_keybindings_fall_back_to_the_defaults() {
+        let path = unique_temp_path("missing");
+
+        assert_eq!(
+            bindings(&load_input_map(&path)),
+            bindings(&PlayerAction::default_input_map())
+        );
+    }
+
+    #[test]
+    fn partial_keybindings_are_merged_over_the_defaults() {
+        let path = unique_temp_path("partial");
+        let saved_bindings = vec![SavedBinding {
+            action: PlayerAction::Paste,
+            inputs: vec![KeyCode::Return.into()],
+        }];
+        fs::write(&path, serde_json::to_vec(&saved_bindings).unwrap()).unwrap();
+
+        let loaded = load_input_map(&path);
+        fs::remove_file(&path).unwrap();
+
+        assert!(triggers(&loaded, &[KeyCode::Return], PlayerAction::Paste));
+        assert!(!triggers(
+            &loaded,
+            &[KeyCode::LControl, KeyCode::V],
+            PlayerAction::Paste
+        ));
+        // Actions missing from the file keep their defaults
+        assert!(triggers(
+            &loaded,
+            &[KeyCode::LControl, KeyCode::C],
+            PlayerAction::Copy
+        ));
+    }
+
+    #[test]
+    fn keybindings_are_loaded_from_the_configured_path_on_startup() {
+        let path = unique_temp_path("startup");
+        let mut input_map = PlayerAction::default_input_map();
+        input_map.rebind(PlayerAction::Paste, KeyCode::Return);
+        save_input_map(&input_map, &path).unwrap();
+
+        let startup_app = |config: KeybindingsConfig| {
+            let mut app = App::new();
+            app.insert_resource(config).add_plugin(KeybindingsPlugin);
+            app.update();
+            app
+        };
+
+        let app = startup_app(KeybindingsConfig {
+            path: Some(path.clone()),
+        });
+        assert_eq!(
+            bindings(app.world.resource::<InputMap<PlayerAction>>()),
+            bindings(&input_map)
+        );
+
+        let app = startup_app(KeybindingsConfig::disabled());
+        assert_eq!(
+            bindings(app.world.resource::<InputMap<PlayerAction>>()),
+            bindings(&PlayerAction::default_input_map())
+        );
+
+        fs::remove_file(&path).unwrap();
+    }
+}
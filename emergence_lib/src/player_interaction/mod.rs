@@ -4,6 +4,7 @@ use crate::enum_iter::IterableEnum;
 use crate::{self as emergence_lib};
 use bevy::prelude::*;
 use emergence_macros::IterableEnum;
+use serde::{Deserialize, Serialize};
 
 use leafwing_input_manager::{
     prelude::{ActionState, DualAxis, InputManagerPlugin, InputMap, VirtualDPad},
@@ -15,6 +16,7 @@ use crate::world_gen::WorldGenState;
 
 pub(crate) mod camera;
 pub(crate) mod clipboard;
+pub(crate) mod keybindings;
 pub(crate) mod picking;
 pub(crate) mod selection;
 
@@ -25,7 +27,7 @@ impl Plugin for InteractionPlugin {
     fn build(&self, app: &mut App) {
         app.add_plugin(InputManagerPlugin::<PlayerAction>::default())
             .init_resource::<ActionState<PlayerAction>>()
-            .insert_resource(PlayerAction::default_input_map())
+            .add_plugin(keybindings::KeybindingsPlugin)
             .add_plugin(camera::CameraPlugin)
             .add_plugin(picking::PickingPlugin)
             .add_plugin(selection::SelectionPlugin)
@@ -40,6 +42,23 @@ impl Plugin for InteractionPlugin {
     }
 }
 
+/// Stops every [`PlayerAction`] other than those in `allowed` from firing, so that the player can type freely.
+///
+/// Keys that are held down stay consumed until they are released, even once this is no longer called.
+pub(crate) fn consume_player_actions(
+    actions: &mut ActionState<PlayerAction>,
+    allowed: &[PlayerAction],
+) {
+    for action in PlayerAction::variants() {
+        if !allowed
+            .iter()
+            .any(|allowed_action| allowed_action.index() == action.index())
+        {
+            actions.consume(action);
+        }
+    }
+}
+
 /// Public system sets for player interaction, used for system ordering and config
 #[derive(SystemSet, Clone, PartialEq, Eq, Hash, Debug, IterableEnum)]
 pub(crate) enum InteractionSystem {
@@ -64,10 +83,15 @@ pub struct PlayerModifiesWorld;
 /// Actions that the player can take to modify the game world or their view of it.
 ///
 /// This should only store actions that need a dedicated keybinding.
-#[derive(Actionlike, Clone, Debug)]
+/// These keybindings can be changed by the player: see the [`keybindings`] module.
+#[derive(Actionlike, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub(crate) enum PlayerAction {
     /// Pause or unpause the game.
     TogglePause,
+    /// Runs the simulation at the next faster speed.
+    SpeedUpSimulation,
+    /// Runs the simulation at the next slower speed, eventually pausing it.
+    SlowDownSimulation,
     /// When the clipboard is full, places the clipboard contents on the map.
     ///
     /// When the clipboard is empty, selects a tile or group of tiles.
@@ -86,6 +110,8 @@ pub(crate) enum PlayerAction {
     Area,
     /// Modifies the selection to cover a line between the start and end of the selection.
     Line,
+    /// Switches between the shapes drawn when dragging to select tiles.
+    CycleSelectionShape,
     /// Selects a structure from a wheel menu.
     SelectStructure,
     /// Select a terraforming tool from a wheel menu.
@@ -100,12 +126,24 @@ pub(crate) enum PlayerAction {
     ///
     /// If no structure is selected to build, zoning will be set to [`Zoning::None`](crate::construction::zoning::Zoning::None).
     Paste,
+    /// Like [`PlayerAction::Paste`], but clears the tile selection once zoning has been applied.
+    ///
+    /// If [`ZoningSettings::clear_selected_structure`](crate::construction::zoning::ZoningSettings) is set, the structure selected for zoning is cleared too.
+    ZoneAndClear,
     /// Sets the zoning of all currently selected tiles to [`Zoning::None`](crate::construction::zoning::Zoning::None).
     ClearZoning,
     /// Rotates the contents of the clipboard counterclockwise.
     RotateClipboardLeft,
     /// Rotates the contents of the clipboard clockwise.
     RotateClipboardRight,
+    /// Mirrors the contents of the clipboard, toggling whether each structure is mirrored.
+    MirrorClipboard,
+    /// Switches the selected structure(s) to their next recipe preset.
+    CycleRecipePreset,
+    /// Pauses or resumes the selected structure(s).
+    ToggleStructureActivity,
+    /// Pauses or resumes crafting at the selected structure(s), without stopping their deliveries.
+    ToggleCraftingPaused,
     /// Snaps the camera to the selected object
     CenterCameraOnSelection,
     /// Drag the camera with the cursor
@@ -136,6 +174,10 @@ pub(crate) enum PlayerAction {
     ToggleWaterTableOverlay,
     /// Show / hide the light overlay
     ToggleLightOverlay,
+    /// Writes an ASCII snapshot of the zoning layer to disk, for attaching to bug reports.
+    DumpZoningSnapshot,
+    /// Opens or closes the developer console.
+    ToggleConsole,
 }
 
 impl PlayerAction {
@@ -144,6 +186,9 @@ impl PlayerAction {
         use PlayerAction::*;
         match self {
             TogglePause => KeyCode::Space.into(),
+            // The main plus and minus keys already control the camera zoom
+            SpeedUpSimulation => KeyCode::NumpadAdd.into(),
+            SlowDownSimulation => KeyCode::NumpadSubtract.into(),
             UseTool => MouseButton::Left.into(),
             Deselect => MouseButton::Right.into(),
             // Plus and Equals are swapped. See: https://github.com/rust-windowing/winit/issues/2682
@@ -152,14 +197,20 @@ impl PlayerAction {
             Multiple => Modifier::Shift.into(),
             Area => Modifier::Control.into(),
             Line => Modifier::Alt.into(),
+            CycleSelectionShape => KeyCode::T.into(),
             SelectStructure => KeyCode::Key1.into(),
             SelectTerraform => KeyCode::Key2.into(),
             SelectAbility => KeyCode::Key3.into(),
             Copy => UserInput::modified(Modifier::Control, KeyCode::C),
             Paste => UserInput::modified(Modifier::Control, KeyCode::V),
+            ZoneAndClear => UserInput::modified(Modifier::Shift, KeyCode::Space),
             ClearZoning => KeyCode::Back.into(),
             RotateClipboardLeft => UserInput::modified(Modifier::Shift, KeyCode::R),
             RotateClipboardRight => KeyCode::R.into(),
+            MirrorClipboard => KeyCode::F.into(),
+            CycleRecipePreset => KeyCode::Tab.into(),
+            ToggleStructureActivity => KeyCode::P.into(),
+            ToggleCraftingPaused => UserInput::modified(Modifier::Shift, KeyCode::P),
             CenterCameraOnSelection => KeyCode::L.into(),
             DragCamera => MouseButton::Middle.into(),
             Pan => VirtualDPad::wasd().into(),
@@ -177,6 +228,8 @@ impl PlayerAction {
             ToggleStrongestSignalOverlay => KeyCode::F3.into(),
             ToggleWaterTableOverlay => KeyCode::F4.into(),
             ToggleLightOverlay => KeyCode::F5.into(),
+            DumpZoningSnapshot => KeyCode::F12.into(),
+            ToggleConsole => KeyCode::Grave.into(),
         }
     }
 
@@ -192,6 +245,8 @@ impl PlayerAction {
 
         match self {
             TogglePause => GamepadButtonType::Select.into(),
+            SpeedUpSimulation => UserInput::chord([camera_modifier, East]),
+            SlowDownSimulation => UserInput::chord([camera_modifier, West]),
             PlayerAction::UseTool => South.into(),
             Deselect => East.into(),
             Multiple => RightTrigger.into(),
@@ -199,14 +254,20 @@ impl PlayerAction {
             DecreaseSelectionRadius => UserInput::chord([radius_modifier, DPadDown]),
             Area => LeftTrigger.into(),
             Line => LeftTrigger2.into(),
+            CycleSelectionShape => UserInput::chord([radius_modifier, DPadRight]),
             Copy => West.into(),
             Paste => North.into(),
+            ZoneAndClear => UserInput::chord([camera_modifier, North]),
             ClearZoning => DPadUp.into(),
             SelectStructure => UserInput::chord([selection_modifier, West]),
             SelectTerraform => UserInput::chord([selection_modifier, North]),
             SelectAbility => UserInput::chord([selection_modifier, East]),
             RotateClipboardLeft => DPadLeft.into(),
             RotateClipboardRight => DPadRight.into(),
+            MirrorClipboard => UserInput::chord([radius_modifier, DPadLeft]),
+            CycleRecipePreset => UserInput::chord([selection_modifier, DPadRight]),
+            ToggleStructureActivity => UserInput::chord([selection_modifier, DPadLeft]),
+            ToggleCraftingPaused => UserInput::chord([selection_modifier, DPadDown]),
             CenterCameraOnSelection => GamepadButtonType::LeftThumb.into(),
             DragCamera => GamepadButtonType::RightThumb.into(),
             Pan => DualAxis::left_stick().into(),
@@ -223,10 +284,14 @@ impl PlayerAction {
             ToggleStrongestSignalOverlay => UserInput::chord([infovis_modifier, DPadRight]),
             ToggleWaterTableOverlay => UserInput::chord([infovis_modifier, DPadDown]),
             ToggleLightOverlay => UserInput::chord([infovis_modifier, DPadUp]),
+            DumpZoningSnapshot => UserInput::chord([infovis_modifier, Start]),
+            ToggleConsole => UserInput::chord([infovis_modifier, Select]),
         }
     }
 
     /// The default key bindings
+    ///
+    /// Used whenever the player has not saved their own keybindings.
     fn default_input_map() -> InputMap<PlayerAction> {
         let mut input_map = InputMap::default();
 
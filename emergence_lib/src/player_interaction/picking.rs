@@ -62,6 +62,16 @@ impl CursorPos {
         }
     }
 
+    /// Creates a new [`CursorPos`] with the given tile position, hovering over the provided structure.
+    #[cfg(test)]
+    pub(crate) fn hovering_structure(voxel_pos: VoxelPos, structure_entity: Entity) -> Self {
+        Self {
+            voxel_pos: Some(voxel_pos),
+            hovered_structure: Some(structure_entity),
+            ..Default::default()
+        }
+    }
+
     /// The position of the cursor in hex coordinates, if it is on the hex map.
     ///
     /// If the cursor is outside the map, this will return `None`.
@@ -1,17 +1,21 @@
 //! Tiles can be selected, serving as a building block for clipboard, inspection and zoning operations.
 
 use bevy::{prelude::*, utils::HashSet};
+use core::time::Duration;
 use emergence_macros::IterableEnum;
 use hexx::shapes::hexagon;
 use hexx::Hex;
 use hexx::HexIterExt;
 use leafwing_input_manager::prelude::ActionState;
 
+use crate::asset_management::manifest::Id;
 use crate::geometry::MapGeometry;
 use crate::geometry::VoxelPos;
+use crate::structures::structure_manifest::Structure;
 
 use crate as emergence_lib;
 
+use super::camera::visible_hexes;
 use super::clipboard::Tool;
 use super::{picking::CursorPos, InteractionSystem, PlayerAction};
 
@@ -22,12 +26,20 @@ impl Plugin for SelectionPlugin {
     fn build(&self, app: &mut App) {
         app.init_resource::<CurrentSelection>()
             .init_resource::<SelectionState>()
+            .init_resource::<SelectionShape>()
             .init_resource::<HoveredTiles>()
+            .add_system(cycle_selection_shape.before(set_selection))
             .add_system(
                 set_selection
                     .in_set(InteractionSystem::SelectTiles)
                     .after(InteractionSystem::ComputeCursorPos),
             )
+            .add_system(
+                select_structures_of_same_type
+                    .in_set(InteractionSystem::SelectTiles)
+                    .after(set_selection)
+                    .before(set_tile_interactions),
+            )
             .add_system(
                 set_tile_interactions
                     .in_set(InteractionSystem::SelectTiles)
@@ -47,7 +59,7 @@ pub(crate) struct SelectedTiles {
 
 impl SelectedTiles {
     /// Selects a single tile
-    pub(super) fn add_tile(&mut self, voxel_pos: VoxelPos) {
+    pub(crate) fn add_tile(&mut self, voxel_pos: VoxelPos) {
         self.selected.insert(voxel_pos.hex);
     }
 
@@ -152,12 +164,12 @@ impl SelectedTiles {
         selection_state: &SelectionState,
         map_geometry: &MapGeometry,
     ) -> HashSet<Hex> {
-        match selection_state.shape {
-            SelectionShape::Single => {
+        match selection_state.region {
+            SelectionRegion::Single => {
                 SelectedTiles::draw_hexagon(hovered_tile, selection_state.brush_size)
             }
-            SelectionShape::Area { center, radius } => SelectedTiles::draw_hexagon(center, radius),
-            SelectionShape::Line { start } => {
+            SelectionRegion::Area { center, radius } => SelectedTiles::draw_hexagon(center, radius),
+            SelectionRegion::Line { start } => {
                 SelectedTiles::draw_line(start, hovered_tile, selection_state.brush_size)
             }
         }
@@ -188,17 +200,17 @@ pub(crate) struct HoveredTiles {
 impl HoveredTiles {
     /// Updates the set of hovered actions based on the current cursor position and player inputs.
     fn update(&mut self, hovered_tile: VoxelPos, selection_state: &SelectionState) {
-        let hex_vec = match selection_state.shape {
-            SelectionShape::Single => {
+        let hex_vec = match selection_state.region {
+            SelectionRegion::Single => {
                 SelectedTiles::draw_hexagon(hovered_tile, selection_state.brush_size)
             }
-            SelectionShape::Area { center, radius } => {
+            SelectionRegion::Area { center, radius } => {
                 let mut vec = SelectedTiles::draw_ring(center, radius);
                 // Also show center of ring for clarity.
                 vec.push(hovered_tile.hex);
                 vec
             }
-            SelectionShape::Line { start } => {
+            SelectionRegion::Line { start } => {
                 SelectedTiles::draw_line(start, hovered_tile, selection_state.brush_size)
             }
         };
@@ -290,6 +302,10 @@ pub(crate) enum CurrentSelection {
     GhostStructure(Entity),
     /// A structure is selected
     Structure(Entity),
+    /// Several structures are selected at once.
+    ///
+    /// Actions like demolition are applied to all of them.
+    MultipleStructures(HashSet<Entity>),
     /// One or more tile is selected.
     ///
     /// Note that terraforming details are also displayed on the basis of the selected terrain.
@@ -302,6 +318,17 @@ pub(crate) enum CurrentSelection {
 }
 
 impl CurrentSelection {
+    /// Returns all of the structures that are currently selected.
+    ///
+    /// This is empty if no structures are selected.
+    pub(crate) fn selected_structures(&self) -> Vec<Entity> {
+        match self {
+            CurrentSelection::Structure(entity) => vec![*entity],
+            CurrentSelection::MultipleStructures(entities) => entities.iter().copied().collect(),
+            _ => Vec::new(),
+        }
+    }
+
     /// Returns the set of terrain tiles that should be affected by actions.
     pub(crate) fn relevant_tiles(&self, cursor_pos: &CursorPos) -> SelectedTiles {
         match self {
@@ -479,7 +506,9 @@ impl From<&CurrentSelection> for SelectionVariant {
     fn from(selection: &CurrentSelection) -> Self {
         match selection {
             CurrentSelection::GhostStructure(_) => Self::GhostStructure,
-            CurrentSelection::Structure(_) => Self::Structure,
+            CurrentSelection::Structure(_) | CurrentSelection::MultipleStructures(_) => {
+                Self::Structure
+            }
             CurrentSelection::Terrain(_) => Self::Terrain,
             CurrentSelection::Unit(_) => Self::Unit,
             CurrentSelection::None => Self::None,
@@ -490,8 +519,8 @@ impl From<&CurrentSelection> for SelectionVariant {
 /// Tracks what should be done with the selection (and hovered tiles) this frame.
 #[derive(Resource, Default, Debug)]
 struct SelectionState {
-    /// What is the shape of the selection?
-    shape: SelectionShape,
+    /// Which tiles are covered by the selection?
+    region: SelectionRegion,
     /// What should be done to the selection?
     action: SelectionAction,
     /// Should the selection be erased or modified?
@@ -512,9 +541,44 @@ enum SelectionAction {
     Deselect,
 }
 
-/// The shape of tiles to be selected.
+/// The shape drawn by dragging the cursor while selecting or deselecting tiles.
+///
+/// Holding [`PlayerAction::Line`] or [`PlayerAction::Area`] overrides this shape for the duration of the drag.
+#[derive(Resource, Default, Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum SelectionShape {
+    /// Tiles are selected under the brush as the cursor moves.
+    #[default]
+    Freehand,
+    /// The line of tiles between the start of the drag and the cursor.
+    Line,
+    /// The filled hexagon centered on the start of the drag, reaching out to the cursor.
+    Hexagon,
+}
+
+impl SelectionShape {
+    /// The next shape to use when cycling through the available shapes.
+    fn next(self) -> Self {
+        match self {
+            SelectionShape::Freehand => SelectionShape::Line,
+            SelectionShape::Line => SelectionShape::Hexagon,
+            SelectionShape::Hexagon => SelectionShape::Freehand,
+        }
+    }
+}
+
+/// Switches to the next [`SelectionShape`] when the corresponding action is pressed.
+fn cycle_selection_shape(
+    mut selection_shape: ResMut<SelectionShape>,
+    actions: Res<ActionState<PlayerAction>>,
+) {
+    if actions.just_pressed(PlayerAction::CycleSelectionShape) {
+        *selection_shape = selection_shape.next();
+    }
+}
+
+/// The region of tiles to be selected this frame.
 #[derive(Default, Debug, Clone, Copy)]
-enum SelectionShape {
+enum SelectionRegion {
     /// A single tile (or a large brush equivalent)
     #[default]
     Single,
@@ -539,34 +603,44 @@ impl SelectionState {
         tool: &Tool,
         actions: &ActionState<PlayerAction>,
         hovered_tile: VoxelPos,
+        selection_shape: SelectionShape,
     ) {
         use PlayerAction::*;
 
         self.multiple = actions.pressed(PlayerAction::Multiple);
 
-        self.shape = if actions.pressed(Line) {
-            let start = if let SelectionShape::Line { start } = self.shape {
+        // The selected shape only applies while dragging, including the frame on which the drag ends
+        let dragging = actions.pressed(UseTool)
+            || actions.just_released(UseTool)
+            || actions.pressed(Deselect)
+            || actions.just_released(Deselect);
+        let line = actions.pressed(Line) || (dragging && selection_shape == SelectionShape::Line);
+        let area =
+            actions.pressed(Area) || (dragging && selection_shape == SelectionShape::Hexagon);
+
+        self.region = if line {
+            let start = if let SelectionRegion::Line { start } = self.region {
                 start
             } else {
                 hovered_tile
             };
 
-            SelectionShape::Line { start }
-        } else if actions.pressed(Area) {
-            let center = if let SelectionShape::Area { center, .. } = self.shape {
+            SelectionRegion::Line { start }
+        } else if area {
+            let center = if let SelectionRegion::Area { center, .. } = self.region {
                 center
             } else {
                 hovered_tile
             };
             let radius = hovered_tile.hex.unsigned_distance_to(center.hex);
 
-            SelectionShape::Area { center, radius }
+            SelectionRegion::Area { center, radius }
         } else {
-            SelectionShape::Single
+            SelectionRegion::Single
         };
 
-        self.action = match self.shape {
-            SelectionShape::Single => {
+        self.action = match self.region {
+            SelectionRegion::Single => {
                 if actions.pressed(UseTool) {
                     SelectionAction::Select
                 // Don't repeatedly trigger deselect to avoid accidentally clearing selection
@@ -576,7 +650,7 @@ impl SelectionState {
                     SelectionAction::Preview
                 }
             }
-            SelectionShape::Area { .. } | SelectionShape::Line { .. } => {
+            SelectionRegion::Area { .. } | SelectionRegion::Line { .. } => {
                 // Trigger on just released in order to enable a drag-and-preview effect
                 if actions.just_released(UseTool) {
                     SelectionAction::Select
@@ -604,6 +678,7 @@ fn set_selection(
     actions: Res<ActionState<PlayerAction>>,
     mut hovered_tiles: ResMut<HoveredTiles>,
     mut selection_state: ResMut<SelectionState>,
+    selection_shape: Res<SelectionShape>,
     mut last_tile_selected: Local<Option<VoxelPos>>,
     map_geometry: Res<MapGeometry>,
 ) {
@@ -615,28 +690,28 @@ fn set_selection(
     let Some(hovered_tile) = cursor_pos.maybe_voxel_pos() else {return};
 
     // Compute how we should handle the selection based on the actions of the player
-    selection_state.compute(&tool, actions, hovered_tile);
+    selection_state.compute(&tool, actions, hovered_tile, *selection_shape);
 
     // Update hovered tiles
     hovered_tiles.update(hovered_tile, &selection_state);
 
     // Select and deselect tiles
-    match (selection_state.action, selection_state.shape) {
+    match (selection_state.action, selection_state.region) {
         // No need to do work here, hovered tiles are always computed
         (SelectionAction::Preview, _) => (),
-        (SelectionAction::Select, SelectionShape::Line { .. }) => {
+        (SelectionAction::Select, SelectionRegion::Line { .. }) => {
             *current_selection =
                 current_selection.select_terrain(hovered_tile, &selection_state, map_geometry);
             // Let players chain lines head to tail nicely
-            selection_state.shape = SelectionShape::Line {
+            selection_state.region = SelectionRegion::Line {
                 start: hovered_tile,
             };
         }
-        (SelectionAction::Select, SelectionShape::Area { .. }) => {
+        (SelectionAction::Select, SelectionRegion::Area { .. }) => {
             *current_selection =
                 current_selection.select_terrain(hovered_tile, &selection_state, map_geometry);
         }
-        (SelectionAction::Select, SelectionShape::Single) => {
+        (SelectionAction::Select, SelectionRegion::Single) => {
             // If we can compare them, do
             let same_tile_as_last_time = if let (Some(last_pos), Some(current_pos)) =
                 (*last_tile_selected, cursor_pos.maybe_voxel_pos())
@@ -662,7 +737,7 @@ fn set_selection(
                 )
             }
         }
-        (SelectionAction::Deselect, SelectionShape::Area { .. } | SelectionShape::Single) => {
+        (SelectionAction::Deselect, SelectionRegion::Area { .. } | SelectionRegion::Single) => {
             match &mut *current_selection {
                 CurrentSelection::Terrain(ref mut selected_tiles) => {
                     if let Some(hovered_tile) = cursor_pos.maybe_voxel_pos() {
@@ -676,7 +751,7 @@ fn set_selection(
                 _ => *current_selection = CurrentSelection::None,
             }
         }
-        (SelectionAction::Deselect, SelectionShape::Line { .. }) => {
+        (SelectionAction::Deselect, SelectionRegion::Line { .. }) => {
             match &mut *current_selection {
                 CurrentSelection::Terrain(ref mut selected_tiles) => {
                     if let Some(hovered_tile) = cursor_pos.maybe_voxel_pos() {
@@ -691,13 +766,76 @@ fn set_selection(
             }
 
             // Let players chain lines head to tail nicely
-            selection_state.shape = SelectionShape::Line {
+            selection_state.region = SelectionRegion::Line {
                 start: hovered_tile,
             };
         }
     }
 }
 
+/// The maximum time between two clicks for them to count as a double-click.
+const DOUBLE_CLICK_WINDOW: Duration = Duration::from_millis(500);
+
+/// Ctrl-clicking a structure selects all structures of the same type that are visible to the camera.
+///
+/// Ctrl-clicking a structure of the same type again in quick succession extends the selection to the entire map.
+// A ctrl-click is an area selection that was released before the cursor left the starting tile.
+fn select_structures_of_same_type(
+    selection_state: Res<SelectionState>,
+    actions: Res<ActionState<PlayerAction>>,
+    cursor_pos: Res<CursorPos>,
+    mut current_selection: ResMut<CurrentSelection>,
+    camera_query: Query<(&GlobalTransform, &Projection), With<Camera3d>>,
+    structure_query: Query<&Id<Structure>>,
+    map_geometry: Res<MapGeometry>,
+    time: Res<Time>,
+    mut last_click: Local<Option<(Id<Structure>, Duration)>>,
+) {
+    let SelectionAction::Select = selection_state.action else { return };
+    let SelectionRegion::Area { radius: 0, .. } = selection_state.region else { return };
+    // Clicks made with the hexagon selection shape are not ctrl-clicks
+    if !actions.pressed(PlayerAction::Area) {
+        return;
+    }
+    let Some(clicked_entity) = cursor_pos.maybe_structure() else { return };
+    let Ok(&structure_id) = structure_query.get(clicked_entity) else { return };
+
+    let now = time.elapsed();
+    let double_clicked = match *last_click {
+        Some((last_structure_id, last_time)) => {
+            last_structure_id == structure_id && now - last_time <= DOUBLE_CLICK_WINDOW
+        }
+        None => false,
+    };
+    // Triple-clicks should not count as a second double-click
+    *last_click = match double_clicked {
+        true => None,
+        false => Some((structure_id, now)),
+    };
+
+    let candidate_hexes: Vec<Hex> = if double_clicked {
+        map_geometry.all_hexes().copied().collect()
+    } else {
+        let Ok((camera_transform, projection)) = camera_query.get_single() else { return };
+        visible_hexes(camera_transform, projection, &map_geometry).collect()
+    };
+
+    let mut selected_structures = HashSet::new();
+    // The clicked structure should always be selected, even if its center is just off-screen
+    selected_structures.insert(clicked_entity);
+
+    for hex in candidate_hexes {
+        let voxel_pos = map_geometry.on_top_of_terrain(hex);
+        if let Some(structure_entity) = map_geometry.get_structure(voxel_pos) {
+            if structure_query.get(structure_entity).ok() == Some(&structure_id) {
+                selected_structures.insert(structure_entity);
+            }
+        }
+    }
+
+    *current_selection = CurrentSelection::MultipleStructures(selected_structures);
+}
+
 /// Set tile interactions based on hover and selection state
 pub(super) fn set_tile_interactions(
     current_selection: Res<CurrentSelection>,
@@ -720,16 +858,25 @@ pub(super) fn set_tile_interactions(
 
 #[cfg(test)]
 mod tests {
-    use bevy::utils::HashSet;
+    use bevy::{prelude::*, utils::HashSet};
+    use hexx::{shapes::hexagon, Hex};
+    use leafwing_input_manager::prelude::ActionState;
 
-    use super::SelectedTiles;
+    use super::{
+        select_structures_of_same_type, SelectedTiles, SelectionAction, SelectionRegion,
+        SelectionShape, SelectionState,
+    };
     use crate::{
+        asset_management::manifest::Id,
         enum_iter::IterableEnum,
-        geometry::VoxelPos,
+        geometry::{Facing, MapGeometry, VoxelPos},
         player_interaction::{
+            clipboard::Tool,
             picking::CursorPos,
             selection::{CurrentSelection, SelectionVariant},
+            PlayerAction,
         },
+        structures::{structure_manifest::Structure, Footprint},
     };
 
     #[test]
@@ -863,4 +1010,131 @@ mod tests {
             }
         }
     }
+
+    /// Spawns a structure of the provided type on top of the terrain at `hex`.
+    fn spawn_structure(
+        world: &mut World,
+        map_geometry: &mut MapGeometry,
+        structure_name: &str,
+        hex: Hex,
+    ) -> Entity {
+        let voxel_pos = map_geometry.on_top_of_terrain(hex);
+        let structure_id: Id<Structure> = Id::from_name(structure_name.to_string());
+        let entity = world.spawn((structure_id, voxel_pos)).id();
+        map_geometry
+            .add_structure(
+                voxel_pos,
+                Facing::default(),
+                &Footprint::single(),
+                false,
+                false,
+                entity,
+            )
+            .unwrap();
+        entity
+    }
+
+    #[test]
+    fn ctrl_click_selects_visible_structures_of_same_type() {
+        let mut app = App::new();
+        let mut map_geometry = MapGeometry::new(&mut app.world, 10);
+
+        let clicked = spawn_structure(&mut app.world, &mut map_geometry, "wall", Hex::ZERO);
+        let visible = spawn_structure(&mut app.world, &mut map_geometry, "wall", Hex::new(1, 0));
+        let offscreen = spawn_structure(&mut app.world, &mut map_geometry, "wall", Hex::new(10, 0));
+        let other_type =
+            spawn_structure(&mut app.world, &mut map_geometry, "tower", Hex::new(0, 1));
+
+        // A narrow camera looking down at the origin, which cannot see the edge of the map
+        app.world.spawn((
+            Camera3d::default(),
+            GlobalTransform::from(
+                Transform::from_xyz(0., 30., 30.).looking_at(Vec3::ZERO, Vec3::Y),
+            ),
+            Projection::Perspective(PerspectiveProjection {
+                fov: 0.2,
+                ..Default::default()
+            }),
+        ));
+
+        let clicked_pos = map_geometry.on_top_of_terrain(Hex::ZERO);
+        let mut actions = ActionState::<PlayerAction>::default();
+        actions.press(PlayerAction::Area);
+        app.insert_resource(map_geometry)
+            .insert_resource(actions)
+            .insert_resource(CursorPos::hovering_structure(clicked_pos, clicked))
+            .insert_resource(SelectionState {
+                region: SelectionRegion::Area {
+                    center: clicked_pos,
+                    radius: 0,
+                },
+                action: SelectionAction::Select,
+                multiple: false,
+                brush_size: 0,
+            })
+            .init_resource::<CurrentSelection>()
+            .init_resource::<Time>()
+            .add_system(select_structures_of_same_type);
+
+        app.update();
+
+        let current_selection = app.world.resource::<CurrentSelection>();
+        let selected: HashSet<Entity> = current_selection
+            .selected_structures()
+            .into_iter()
+            .collect();
+        assert_eq!(selected, HashSet::from_iter([clicked, visible]));
+        assert!(!selected.contains(&offscreen));
+        assert!(!selected.contains(&other_type));
+    }
+
+    /// Drags the cursor from `start` to `end` using the provided `selection_shape`,
+    /// returning the tiles that are selected when the drag is released.
+    fn drag_selection(selection_shape: SelectionShape, start: Hex, end: Hex) -> HashSet<Hex> {
+        let map_geometry = MapGeometry::new(&mut World::new(), 5);
+        let start = VoxelPos::from_xy(start.x, start.y);
+        let end = VoxelPos::from_xy(end.x, end.y);
+        let mut selection_state = SelectionState::default();
+        let mut actions = ActionState::<PlayerAction>::default();
+
+        actions.press(PlayerAction::UseTool);
+        selection_state.compute(&Tool::None, &actions, start, selection_shape);
+        assert!(matches!(selection_state.action, SelectionAction::Preview));
+
+        actions.release(PlayerAction::UseTool);
+        selection_state.compute(&Tool::None, &actions, end, selection_shape);
+        assert!(matches!(selection_state.action, SelectionAction::Select));
+
+        SelectedTiles::default().compute_selection_region(end, &selection_state, &map_geometry)
+    }
+
+    #[test]
+    fn line_shape_selects_tiles_between_drag_start_and_cursor() {
+        let selected = drag_selection(SelectionShape::Line, Hex::new(-1, 0), Hex::new(2, 0));
+
+        let expected = HashSet::from_iter([
+            Hex::new(-1, 0),
+            Hex::new(0, 0),
+            Hex::new(1, 0),
+            Hex::new(2, 0),
+        ]);
+        assert_eq!(selected, expected);
+    }
+
+    #[test]
+    fn hexagon_shape_selects_tiles_within_drag_distance() {
+        let selected = drag_selection(SelectionShape::Hexagon, Hex::new(1, 1), Hex::new(3, 1));
+
+        let expected: HashSet<Hex> = hexagon(Hex::new(1, 1), 2).collect();
+        assert_eq!(expected.len(), 19);
+        assert_eq!(selected, expected);
+    }
+
+    #[test]
+    fn selection_shapes_cycle_back_to_freehand() {
+        let shape = SelectionShape::Freehand;
+        assert_eq!(shape.next(), SelectionShape::Line);
+        assert_eq!(shape.next().next(), SelectionShape::Hexagon);
+        assert_eq!(shape.next().next().next(), SelectionShape::Freehand);
+    }
 }
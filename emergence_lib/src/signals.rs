@@ -6,6 +6,7 @@
 use crate as emergence_lib;
 use crate::construction::ghosts::WorkplaceId;
 use crate::crafting::item_tags::ItemKind;
+use crate::enum_iter::IterableEnum;
 use crate::items::item_manifest::ItemManifest;
 use crate::structures::structure_manifest::{Structure, StructureManifest};
 use crate::terrain::terrain_manifest::TerrainManifest;
@@ -18,12 +19,14 @@ use emergence_macros::IterableEnum;
 use itertools::Itertools;
 use rand::seq::SliceRandom;
 use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
 use std::ops::{Div, DivAssign, MulAssign};
 
 use crate::asset_management::manifest::Id;
 use crate::geometry::{Facing, Height, MapGeometry, VoxelPos};
 use crate::simulation::SimulationSet;
 use crate::units::goals::Goal;
+use hexx::{shapes::hexagon, Hex};
 
 /// The fraction of signals in each cell that will move to each of 6 neighbors each frame.
 ///
@@ -39,13 +42,20 @@ pub(crate) struct SignalsPlugin;
 
 impl Plugin for SignalsPlugin {
     fn build(&self, app: &mut App) {
-        app.init_resource::<Signals>().add_systems(
-            (emit_signals, diffuse_signals, degrade_signals)
-                .chain()
-                .in_set(ManageSignals)
-                .in_set(SimulationSet)
-                .in_schedule(CoreSchedule::FixedUpdate),
-        );
+        app.init_resource::<Signals>()
+            .init_resource::<LogisticsPriorities>()
+            .add_systems(
+                (
+                    emit_signals,
+                    emit_hazard_signals,
+                    diffuse_signals,
+                    degrade_signals,
+                )
+                    .chain()
+                    .in_set(ManageSignals)
+                    .in_set(SimulationSet)
+                    .in_schedule(CoreSchedule::FixedUpdate),
+            );
     }
 }
 
@@ -58,17 +68,72 @@ pub(crate) struct ManageSignals;
 pub struct Signals {
     /// The spatialized map for each signal
     maps: HashMap<SignalType, SignalMap>,
+    /// Controls how each [`Hex`] is stored in the dense arrays of each [`SignalMap`].
+    ///
+    /// This is shared between all signal maps, which are always the same size.
+    layout: HexLayout,
+    /// The unbounded signals emitted by each structure or ghost during the most recent call to [`emit_signals`],
+    /// along with where they were emitted and how strong they were.
+    ///
+    /// This is used to clean up after structures once they are despawned.
+    structure_emissions: HashMap<Entity, Vec<(SignalType, VoxelPos, SignalStrength)>>,
+    /// The signals emitted by sources with a maximum range, which are stored apart from `maps`.
+    ///
+    /// Signal types without an entry have no such sources.
+    bounded: HashMap<SignalType, BoundedSignals>,
+    /// Which tiles send signal into each tile during diffusion, as computed by [`HexLayout::diffusion_sources`].
+    ///
+    /// These only change when the navigation graph or the layout does, so they are cached between calls to [`Signals::diffuse`].
+    diffusion_sources: DiffusionSources,
+}
+
+/// The cached result of [`HexLayout::diffusion_sources`].
+#[derive(Debug, Default)]
+struct DiffusionSources {
+    /// The [`MapGeometry::walkability_version`] and layout that `sources` were computed for.
+    ///
+    /// If this is [`None`], the sources have never been computed.
+    computed_for: Option<(u64, HexLayout)>,
+    /// The tiles that send signal into each tile, indexed by the layout.
+    sources: Vec<Vec<usize>>,
+}
+
+impl DiffusionSources {
+    /// Recomputes the sources if the navigation graph of `map_geometry` or the `layout` changed since they were last computed.
+    ///
+    /// Returns `true` if the sources were recomputed.
+    fn refresh(&mut self, layout: HexLayout, map_geometry: &MapGeometry) -> bool {
+        let key = (map_geometry.walkability_version(), layout);
+        if self.computed_for == Some(key) {
+            return false;
+        }
+
+        self.sources = layout.diffusion_sources(map_geometry);
+        self.computed_for = Some(key);
+        true
+    }
 }
 
 impl Signals {
     /// Returns the signal strength of `signal_type` at the given `voxel_pos`.
     ///
+    /// Signals from sources with a maximum range are only counted within range of that source.
     /// Missing values will be filled with [`SignalStrength::ZERO`].
     pub fn get(&self, signal_type: SignalType, voxel_pos: VoxelPos) -> SignalStrength {
-        match self.maps.get(&signal_type) {
-            Some(map) => map.get(voxel_pos),
+        let unbounded = match (
+            self.maps.get(&signal_type),
+            self.layout.index(voxel_pos.hex),
+        ) {
+            (Some(map), Some(index)) => map.get(index),
+            _ => SignalStrength::ZERO,
+        };
+
+        let bounded = match self.bounded.get(&signal_type) {
+            Some(bounded) => bounded.get(voxel_pos.hex),
             None => SignalStrength::ZERO,
-        }
+        };
+
+        unbounded + bounded
     }
 
     /// Returns `true` if any of the provided `signal_types` are detectable at the given `voxel_pos`.
@@ -85,14 +150,99 @@ impl Signals {
         voxel_pos: VoxelPos,
         signal_strength: SignalStrength,
     ) {
-        match self.maps.get_mut(&signal_type) {
-            Some(map) => map.add_signal(voxel_pos, signal_strength),
-            None => {
-                let mut new_map = SignalMap::default();
-                new_map.add_signal(voxel_pos, signal_strength);
-                self.maps.insert(signal_type, new_map);
+        self.grow_to(Hex::ZERO.unsigned_distance_to(voxel_pos.hex));
+        let index = self
+            .layout
+            .index(voxel_pos.hex)
+            .expect("The layout was just grown to fit this hex");
+
+        let layout = self.layout;
+        self.maps
+            .entry(signal_type)
+            .or_insert_with(|| SignalMap::new(layout))
+            .add_signal(index, signal_strength);
+    }
+
+    /// Adds the signals of `emitter` at `voxel_pos`, splitting their strength evenly across `n_tiles`.
+    ///
+    /// If the emitter has a [`Emitter::max_range`], its signals cannot be detected beyond that distance from `voxel_pos`,
+    /// regardless of any other sources of the same signal.
+    pub(crate) fn add_emission(&mut self, voxel_pos: VoxelPos, emitter: &Emitter, n_tiles: usize) {
+        for &(signal_type, signal_strength) in &emitter.signals {
+            let signal_strength = signal_strength / n_tiles as f32;
+
+            match emitter.max_range {
+                None => self.add_signal(signal_type, voxel_pos, signal_strength),
+                Some(max_range) => {
+                    // Make sure that this signal type can be found when listing the signals present
+                    let layout = self.layout;
+                    self.maps
+                        .entry(signal_type)
+                        .or_insert_with(|| SignalMap::new(layout));
+
+                    self.bounded.entry(signal_type).or_default().add_signal(
+                        voxel_pos.hex,
+                        max_range,
+                        signal_strength,
+                    );
+                }
+            }
+        }
+    }
+
+    /// Removes every source with a maximum range that was not emitted from since the last call.
+    ///
+    /// This should be called once all signals have been emitted for this tick.
+    fn remove_silent_sources(&mut self) {
+        for bounded in self.bounded.values_mut() {
+            bounded.remove_silent_sources();
+        }
+
+        self.bounded
+            .retain(|_, bounded| !bounded.sources.is_empty());
+    }
+
+    /// Subtracts the signal last emitted by the despawned `structure_entity` from the tiles it was emitted at.
+    ///
+    /// Signals emitted by other sources on the same tiles are untouched.
+    /// Signals that have already diffused to other tiles are left to degrade naturally,
+    /// while sources with a maximum range are dropped automatically once they stop emitting.
+    pub(crate) fn remove_emissions_from(&mut self, structure_entity: Entity) {
+        let Some(emissions) = self.structure_emissions.remove(&structure_entity) else { return };
+
+        for (signal_type, voxel_pos, signal_strength) in emissions {
+            if let (Some(map), Some(index)) = (
+                self.maps.get_mut(&signal_type),
+                self.layout.index(voxel_pos.hex),
+            ) {
+                map.current[index] -= signal_strength;
+            }
+        }
+    }
+
+    /// Expands the storage of every signal map so that it covers all hexes within `radius` of the origin.
+    ///
+    /// Existing signal values are preserved; this does nothing if the maps are already large enough.
+    fn grow_to(&mut self, radius: u32) {
+        if radius <= self.layout.radius {
+            return;
+        }
+
+        let old_layout = self.layout;
+        let new_layout = HexLayout { radius };
+
+        for signal_map in self.maps.values_mut() {
+            let mut new_map = SignalMap::new(new_layout);
+            for hex in hexagon(Hex::ZERO, old_layout.radius) {
+                // Both layouts contain this hex, since the new radius is strictly larger
+                let old_index = old_layout.index(hex).unwrap();
+                let new_index = new_layout.index(hex).unwrap();
+                new_map.current[new_index] = signal_map.current[old_index];
             }
+            *signal_map = new_map;
         }
+
+        self.layout = new_layout;
     }
 
     /// Returns the complete set of signals at the given `voxel_pos`.
@@ -131,8 +281,16 @@ impl Signals {
         strongest_signal.map(|signal_type| (signal_type, strongest_strength))
     }
 
+    /// Should a unit at `voxel_pos` refuse to step into deep water?
+    ///
+    /// Units that cannot swim avoid deep water, but those that are already out of their depth must keep moving to reach the shore.
+    fn avoids_deep_water(voxel_pos: VoxelPos, map_geometry: &MapGeometry, can_swim: bool) -> bool {
+        !can_swim && !map_geometry.is_deep_water(voxel_pos.hex)
+    }
+
     /// Returns the adjacent, empty tile position that contains the highest sum signal strength that can be used to meet the provided `goal`.
     ///
+    /// Units that cannot swim will not step into deep water, unless they are already out of their depth.
     /// If no suitable tile exists, [`None`] will be returned instead.
     pub(crate) fn upstream(
         &self,
@@ -140,13 +298,22 @@ impl Signals {
         goal: &Goal,
         item_manifest: &ItemManifest,
         map_geometry: &MapGeometry,
+        can_swim: bool,
     ) -> Option<VoxelPos> {
         let mut best_choice: Option<VoxelPos> = None;
         let mut best_score = SignalStrength::ZERO;
+        let avoid_deep_water = Self::avoids_deep_water(voxel_pos, map_geometry, can_swim);
+        let avoided_kinds = Avoidance::avoided_item_kinds(goal, item_manifest);
 
         for (possible_tile, current_score) in
             self.relevant_neighboring_signals(voxel_pos, goal, item_manifest, map_geometry)
         {
+            if avoid_deep_water && map_geometry.is_deep_water(possible_tile.hex) {
+                continue;
+            }
+
+            // Avoided tiles are less attractive, so units will route around them
+            let current_score = current_score - self.path_cost(possible_tile, &avoided_kinds);
             if current_score > best_score {
                 best_score = current_score;
                 best_choice = Some(possible_tile);
@@ -166,6 +333,7 @@ impl Signals {
 
     /// Returns the adjacent, empty tile position that contains the lowest sum signal strength that can be used to meet the provided `goal`.
     ///
+    /// Units that cannot swim will not step into deep water, unless they are already out of their depth.
     /// If no suitable tile exists, [`None`] will be returned instead.
     pub(crate) fn downstream(
         &self,
@@ -173,13 +341,22 @@ impl Signals {
         goal: &Goal,
         item_manifest: &ItemManifest,
         map_geometry: &MapGeometry,
+        can_swim: bool,
     ) -> Option<VoxelPos> {
         let mut best_choice: Option<VoxelPos> = None;
         let mut best_score = SignalStrength::INFINITY;
+        let avoid_deep_water = Self::avoids_deep_water(voxel_pos, map_geometry, can_swim);
+        let avoided_kinds = Avoidance::avoided_item_kinds(goal, item_manifest);
 
         for (possible_tile, current_score) in
             self.relevant_neighboring_signals(voxel_pos, goal, item_manifest, map_geometry)
         {
+            if avoid_deep_water && map_geometry.is_deep_water(possible_tile.hex) {
+                continue;
+            }
+
+            // Fleeing units should not run into something even worse
+            let current_score = current_score + self.path_cost(possible_tile, &avoided_kinds);
             if current_score < best_score {
                 best_score = current_score;
                 best_choice = Some(possible_tile);
@@ -197,6 +374,24 @@ impl Signals {
         }
     }
 
+    /// Returns the cost of moving through `voxel_pos`, based on the [`SignalType::Avoid`] signals there.
+    ///
+    /// Hazards are always costly, while [`Avoidance::Item`] only counts for the `avoided_kinds`,
+    /// as returned by [`Avoidance::avoided_item_kinds`] for the unit's goal.
+    pub(crate) fn path_cost(
+        &self,
+        voxel_pos: VoxelPos,
+        avoided_kinds: &[ItemKind],
+    ) -> SignalStrength {
+        let mut cost = self.get(SignalType::Avoid(Avoidance::Hazard), voxel_pos);
+
+        for &item_kind in avoided_kinds {
+            cost += self.get(SignalType::Avoid(Avoidance::Item(item_kind)), voxel_pos);
+        }
+
+        cost
+    }
+
     /// Returns the strength of goal-relevant signals in neighboring tiles.
     fn relevant_neighboring_signals(
         &self,
@@ -241,6 +436,9 @@ impl Signals {
             Goal::Avoid(unit_id) => {
                 self.neighboring_signals(SignalType::Unit(*unit_id), voxel_pos, map_geometry)
             }
+            Goal::Rest { .. } => {
+                self.neighboring_signals(SignalType::Rest, voxel_pos, map_geometry)
+            }
             Goal::Demolish(structure_id) => self.neighboring_signals(
                 SignalType::Demolish(*structure_id),
                 voxel_pos,
@@ -250,6 +448,8 @@ impl Signals {
     }
 
     /// Returns the signal strength of the type `signal_type` in `voxel_pos` and its 6 surrounding neighbors.
+    ///
+    /// Neighbors that are already full of units are skipped, so units path around them.
     fn neighboring_signals(
         &self,
         signal_type: SignalType,
@@ -259,7 +459,10 @@ impl Signals {
         let mut signal_strength_map = HashMap::with_capacity(7);
 
         signal_strength_map.insert(voxel_pos, self.get(signal_type, voxel_pos));
-        for neighbor in map_geometry.walkable_neighbors(voxel_pos) {
+        for neighbor in map_geometry
+            .walkable_neighbors(voxel_pos)
+            .filter(|&neighbor| map_geometry.has_room_for_unit(neighbor))
+        {
             signal_strength_map.insert(neighbor, self.get(signal_type, neighbor));
         }
 
@@ -267,39 +470,43 @@ impl Signals {
     }
 
     /// Diffuses signals from one cell into the next
+    ///
+    /// Each tile is computed independently from the previous state of the map,
+    /// so the results are identical no matter how many threads are used.
     pub fn diffuse(&mut self, map_geometry: &MapGeometry, diffusion_fraction: f32) {
         assert!((0.0..=1.0 / 6.0).contains(&diffusion_fraction));
 
+        self.grow_to(map_geometry.radius);
+        let sources_changed = self.diffusion_sources.refresh(self.layout, map_geometry);
+        let sources = &self.diffusion_sources.sources;
+
         self.maps
             .par_iter_mut()
             .for_each(|(_signal_type, signal_map)| {
-                for (&occupied_tile, original_strength) in signal_map
-                    .current
-                    .iter()
-                    .filter(|(_, &strength)| strength != SignalStrength::ZERO)
-                {
-                    let amount_to_send_to_each_neighbor = *original_strength * diffusion_fraction;
-
-                    for neighbor in map_geometry.walkable_neighbors(occupied_tile) {
-                        signal_map
-                            .pending_addition
-                            .push((neighbor, amount_to_send_to_each_neighbor));
-                    }
-                    signal_map.pending_removal.push((
-                        occupied_tile,
-                        // Signal that goes out of bounds or into an impassable tile is lost
-                        // This is both a simplification and a performance optimization
-                        // But it also has a gameplay effect: it makes circuitous routes less efficient
-                        amount_to_send_to_each_neighbor * 6.0,
-                    ));
-                }
+                signal_map.diffuse(sources, diffusion_fraction);
+            });
 
-                // We cannot do this in one step, as we need to avoid bizarre iteration order dependencies
-                signal_map.apply_pending_removals();
-                signal_map.apply_pending_additions();
+        let layout = self.layout;
+        self.bounded
+            .par_iter_mut()
+            .for_each(|(_signal_type, bounded)| {
+                bounded.diffuse(layout, sources, sources_changed, diffusion_fraction);
             });
     }
 
+    /// Degrades signals, allowing them to approach an asymptotically constant level.
+    pub fn degrade(&mut self) {
+        self.maps.par_iter_mut().for_each(|(_, signal_map)| {
+            signal_map.degrade();
+        });
+
+        self.bounded.par_iter_mut().for_each(|(_, bounded)| {
+            for source in bounded.sources.values_mut() {
+                source.map.degrade();
+            }
+        });
+    }
+
     /// Returns a random signal type present in the map.
     pub(crate) fn random_signal_type(&self) -> Option<SignalType> {
         let mut rng = rand::thread_rng();
@@ -325,6 +532,35 @@ impl LocalSignals {
             .collect()
     }
 
+    /// Returns how strongly the goal generated by `signal_type` should be discouraged at this tile.
+    ///
+    /// Hazards discourage every goal, while [`Avoidance::Item`] follows the same rule as [`Signals::path_cost`]:
+    /// it only discourages the goals described by [`Avoidance::avoided_item_kinds`].
+    /// This is subtracted from the signal's strength when weighting goals.
+    pub(crate) fn avoidance(
+        &self,
+        signal_type: SignalType,
+        item_manifest: &ItemManifest,
+    ) -> SignalStrength {
+        let avoided_kinds = Goal::try_from(signal_type)
+            .map(|goal| Avoidance::avoided_item_kinds(&goal, item_manifest))
+            .unwrap_or_default();
+
+        let mut avoidance = self
+            .map
+            .get(&SignalType::Avoid(Avoidance::Hazard))
+            .copied()
+            .unwrap_or_default();
+
+        for item_kind in avoided_kinds {
+            if let Some(&strength) = self.map.get(&SignalType::Avoid(Avoidance::Item(item_kind))) {
+                avoidance += strength;
+            }
+        }
+
+        avoidance
+    }
+
     /// The pretty formatting for this type.
     pub(crate) fn display(
         &self,
@@ -355,69 +591,324 @@ impl LocalSignals {
     }
 }
 
-/// Stores the [`SignalStrength`] of the given [`SignalType`] at each [`VoxelPos`].
+/// Assigns each [`Hex`] within `radius` of the origin a unique position in a dense array.
+///
+/// Hexes are stored row by row (in order of increasing `y`), and by increasing `x` within each row.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+struct HexLayout {
+    /// The number of tiles from the center to the edge of the stored area.
+    radius: u32,
+}
+
+impl HexLayout {
+    /// The number of hexes covered by this layout.
+    fn len(&self) -> usize {
+        let radius = self.radius as usize;
+        3 * radius * (radius + 1) + 1
+    }
+
+    /// Returns the position of `hex` in the dense array, if it is covered by this layout.
+    fn index(&self, hex: Hex) -> Option<usize> {
+        if Hex::ZERO.unsigned_distance_to(hex) > self.radius {
+            return None;
+        }
+
+        let radius = self.radius as i32;
+        // Rows grow by one hex each until the middle row, then shrink by one hex each
+        let row = hex.y + radius;
+        let row_start = if row <= radius {
+            row * (radius + 1) + row * (row - 1) / 2
+        } else {
+            let rows_past_middle = row - radius;
+            radius * (radius + 1) + radius * (radius - 1) / 2 + rows_past_middle * (3 * radius + 1)
+                - rows_past_middle * (radius + row - 1) / 2
+        };
+        let min_x = (-radius).max(-radius - hex.y);
+
+        Some((row_start + hex.x - min_x) as usize)
+    }
+
+    /// Computes which tiles send signal into each tile during diffusion.
+    ///
+    /// The returned list is indexed by this layout, and each entry is sorted by index.
+    /// Summing in this fixed order keeps the floating point results independent of thread count.
+    fn diffusion_sources(&self, map_geometry: &MapGeometry) -> Vec<Vec<usize>> {
+        let mut sources = vec![Vec::new(); self.len()];
+
+        for voxel_pos in map_geometry.walkable_positions() {
+            let Some(source) = self.index(voxel_pos.hex) else { continue };
+
+            for neighbor in map_geometry.walkable_neighbors(voxel_pos) {
+                if let Some(destination) = self.index(neighbor.hex) {
+                    sources[destination].push(source);
+                }
+            }
+        }
+
+        // Multiple walkable voxels can share the same hex, but each hex only sends its signal once.
+        for tile_sources in sources.iter_mut() {
+            tile_sources.sort_unstable();
+            tile_sources.dedup();
+        }
+
+        sources
+    }
+
+    /// Computes which tiles send signal into each tile during diffusion, for a layout centered on `center`.
+    ///
+    /// Tiles outside of this layout never send signal into it.
+    /// `sources` must be generated by [`HexLayout::diffusion_sources`] for the `layout` of the whole map.
+    fn local_diffusion_sources(
+        &self,
+        center: Hex,
+        layout: HexLayout,
+        sources: &[Vec<usize>],
+    ) -> Vec<Vec<usize>> {
+        let mut local_sources = vec![Vec::new(); self.len()];
+
+        for local_hex in hexagon(Hex::ZERO, self.radius) {
+            let Some(destination) = layout.index(center + local_hex) else { continue };
+            let local_destination = self.index(local_hex).unwrap();
+
+            for local_neighbor in local_hex.all_neighbors() {
+                let (Some(local_source), Some(source)) = (
+                    self.index(local_neighbor),
+                    layout.index(center + local_neighbor),
+                ) else { continue };
+
+                if sources[destination].binary_search(&source).is_ok() {
+                    local_sources[local_destination].push(local_source);
+                }
+            }
+        }
+
+        // Keep the summation order fixed, as for the whole map
+        for tile_sources in local_sources.iter_mut() {
+            tile_sources.sort_unstable();
+        }
+
+        local_sources
+    }
+}
+
+/// The signals of a single [`SignalType`] emitted by sources with a maximum range.
+///
+/// Each source spreads its signal in a [`SignalMap`] of its own that only covers the tiles in range,
+/// so the signal of one source never leaks into the range of another.
+#[derive(Debug, Default)]
+struct BoundedSignals {
+    /// The signal spread by each source, keyed by the tile it is emitted from and its maximum range.
+    sources: HashMap<(Hex, u32), BoundedSource>,
+    /// The keys of the sources whose range covers each tile.
+    reach: HashMap<Hex, Vec<(Hex, u32)>>,
+}
+
+impl BoundedSignals {
+    /// Returns the total signal strength of every source in range of `hex`.
+    fn get(&self, hex: Hex) -> SignalStrength {
+        let Some(keys) = self.reach.get(&hex) else {
+            return SignalStrength::ZERO;
+        };
+
+        let mut strength = SignalStrength::ZERO;
+        for &(center, max_range) in keys {
+            let source = &self.sources[&(center, max_range)];
+            if let Some(index) = source.layout.index(hex - center) {
+                strength += source.map.get(index);
+            }
+        }
+
+        strength
+    }
+
+    /// Adds `signal_strength` at `center`, to the source with the provided `max_range`.
+    fn add_signal(&mut self, center: Hex, max_range: u32, signal_strength: SignalStrength) {
+        let key = (center, max_range);
+        let source = self.sources.entry(key).or_insert_with(|| {
+            for hex in hexagon(center, max_range) {
+                self.reach.entry(hex).or_default().push(key);
+            }
+
+            BoundedSource::new(max_range)
+        });
+
+        let index = source
+            .layout
+            .index(Hex::ZERO)
+            .expect("The center is always covered");
+        source.map.add_signal(index, signal_strength);
+        source.emitted = true;
+    }
+
+    /// Removes every source that was not emitted from since the last call.
+    fn remove_silent_sources(&mut self) {
+        let mut removed = Vec::new();
+        self.sources.retain(|&key, source| {
+            let emitted = source.emitted;
+            if !emitted {
+                removed.push(key);
+            }
+
+            // Sources must be emitted from again before the next call to be kept
+            source.emitted = false;
+            emitted
+        });
+
+        for (center, max_range) in removed {
+            for hex in hexagon(center, max_range) {
+                if let Some(keys) = self.reach.get_mut(&hex) {
+                    keys.retain(|&key| key != (center, max_range));
+                    if keys.is_empty() {
+                        self.reach.remove(&hex);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Diffuses the signal of each source within its range.
+    ///
+    /// `sources` must be generated by [`HexLayout::diffusion_sources`] for the `layout` of the whole map.
+    /// The local sources of each bounded source are cached, and only recomputed if `sources_changed` is `true`.
+    fn diffuse(
+        &mut self,
+        layout: HexLayout,
+        sources: &[Vec<usize>],
+        sources_changed: bool,
+        diffusion_fraction: f32,
+    ) {
+        for (&(center, _max_range), source) in self.sources.iter_mut() {
+            if sources_changed || source.local_sources.is_none() {
+                source.local_sources = Some(
+                    source
+                        .layout
+                        .local_diffusion_sources(center, layout, sources),
+                );
+            }
+
+            // Just filled in if it was missing
+            let local_sources = source.local_sources.as_ref().unwrap();
+            source.map.diffuse(local_sources, diffusion_fraction);
+        }
+    }
+}
+
+/// The signal spread by a single source with a maximum range.
+#[derive(Debug)]
+struct BoundedSource {
+    /// Covers every tile within range, relative to the tile that the signal is emitted from.
+    layout: HexLayout,
+    /// The signal strength at each tile in range.
+    map: SignalMap,
+    /// Has this source been emitted from since the last call to [`BoundedSignals::remove_silent_sources`]?
+    emitted: bool,
+    /// Which tiles in range send signal into each other, as computed by [`HexLayout::local_diffusion_sources`].
+    ///
+    /// This is [`None`] until the source is first diffused.
+    local_sources: Option<Vec<Vec<usize>>>,
+}
+
+impl BoundedSource {
+    /// Creates a source with no signal, covering every tile within `max_range`.
+    fn new(max_range: u32) -> Self {
+        let layout = HexLayout { radius: max_range };
+
+        BoundedSource {
+            layout,
+            map: SignalMap::new(layout),
+            emitted: false,
+            local_sources: None,
+        }
+    }
+}
+
+/// Stores the [`SignalStrength`] of the given [`SignalType`] at each [`Hex`].
+///
+/// Values are stored densely, in the order defined by the [`HexLayout`] of the owning [`Signals`].
 #[derive(Debug, Default)]
 struct SignalMap {
     /// The current amount of signal at each location.
-    current: HashMap<VoxelPos, SignalStrength>,
-    /// The amount of signal that will be added to each location at the end of the frame.
-    pending_addition: Vec<(VoxelPos, SignalStrength)>,
-    /// The amount of signal that will be removed from each location at the end of the frame.
-    pending_removal: Vec<(VoxelPos, SignalStrength)>,
+    current: Vec<SignalStrength>,
+    /// The buffer that diffusion writes into, before being swapped with `current`.
+    ///
+    /// The contents of this buffer are meaningless outside of [`SignalMap::diffuse`].
+    next: Vec<SignalStrength>,
 }
 
 impl SignalMap {
-    /// Returns the signal strength at the given [`VoxelPos`].
-    ///
-    /// Missing values will be filled with [`SignalStrength::ZERO`].
-    fn get(&self, voxel_pos: VoxelPos) -> SignalStrength {
-        *self
-            .current
-            .get(&voxel_pos)
-            .unwrap_or(&SignalStrength::ZERO)
+    /// Creates an empty signal map that covers every hex in the provided `layout`.
+    fn new(layout: HexLayout) -> Self {
+        SignalMap {
+            current: vec![SignalStrength::ZERO; layout.len()],
+            next: vec![SignalStrength::ZERO; layout.len()],
+        }
     }
 
-    /// Returns a mutable reference to the signal strength at the given [`VoxelPos`].
+    /// Returns the signal strength at the given `index`.
     ///
-    /// Missing values will be inserted with [`SignalStrength::ZERO`].
-    fn get_mut(&mut self, voxel_pos: VoxelPos) -> &mut SignalStrength {
+    /// Missing values will be filled with [`SignalStrength::ZERO`].
+    fn get(&self, index: usize) -> SignalStrength {
         self.current
-            .entry(voxel_pos)
-            .or_insert(SignalStrength::ZERO)
+            .get(index)
+            .copied()
+            .unwrap_or(SignalStrength::ZERO)
     }
 
-    /// Adds the `signal_strength` to the signal at `voxel_pos`.
-    fn add_signal(&mut self, voxel_pos: VoxelPos, signal_strength: SignalStrength) {
-        *self.get_mut(voxel_pos) += signal_strength
+    /// Adds the `signal_strength` to the signal at `index`.
+    fn add_signal(&mut self, index: usize, signal_strength: SignalStrength) {
+        self.current[index] += signal_strength;
     }
 
-    /// Applies all pending additions to the current signal map.
-    ///
-    /// This clears the pending addition map.
-    fn apply_pending_additions(&mut self) {
-        for (voxel_pos, signal_strength) in self.pending_addition.drain(..) {
-            self.current
-                .entry(voxel_pos)
-                .and_modify(|current_strength| {
-                    *current_strength += signal_strength;
-                })
-                .or_insert(signal_strength);
-        }
+    /// Degrades the signal at every tile, allowing it to approach an asymptotically constant level.
+    fn degrade(&mut self) {
+        /// The fraction of signal that will decay at each step.
+        ///
+        /// Higher values lead to faster decay and improved signal responsiveness.
+        /// This must always be between 0 and 1.
+        const DEGRADATION_FRACTION: f32 = 0.01;
+
+        /// The value below which decayed signals are eliminated completely
+        ///
+        /// Increasing this value will:
+        ///  - increase computational costs
+        ///  - increase the range at which tasks can be detected
+        ///  - increase the amount of time units will wait around for more production
+        const EPSILON_STRENGTH: SignalStrength = SignalStrength(1e-8);
+
+        self.current.par_iter_mut().for_each(|signal_strength| {
+            let new_strength = *signal_strength * (1. - DEGRADATION_FRACTION);
+
+            *signal_strength = if new_strength > EPSILON_STRENGTH {
+                new_strength
+            } else {
+                SignalStrength::ZERO
+            };
+        });
     }
 
-    /// Applies all pending removals to the current signal map.
+    /// Spreads signal from each tile to the tiles listed in `sources`, then swaps the buffers.
     ///
-    /// This clears the pending removal map.
-    fn apply_pending_removals(&mut self) {
-        for (voxel_pos, signal_strength) in self.pending_removal.drain(..) {
-            // We deliberately do not insert a zero or negative signal strength here if the entry is missing
-            // That would either be useless or a bug respectively.
-            self.current
-                .entry(voxel_pos)
-                .and_modify(|current_strength| {
-                    *current_strength -= signal_strength;
-                });
-        }
+    /// `sources` must be generated by [`HexLayout::diffusion_sources`] for the layout this map was created with.
+    fn diffuse(&mut self, sources: &[Vec<usize>], diffusion_fraction: f32) {
+        let current = &self.current;
+
+        self.next
+            .par_iter_mut()
+            .enumerate()
+            .for_each(|(index, next_strength)| {
+                // Signal that goes out of bounds or into an impassable tile is lost
+                // This is both a simplification and a performance optimization
+                // But it also has a gameplay effect: it makes circuitous routes less efficient
+                let mut strength = current[index] - current[index] * diffusion_fraction * 6.0;
+
+                for &source in &sources[index] {
+                    strength += current[source] * diffusion_fraction;
+                }
+
+                *next_strength = strength;
+            });
+
+        std::mem::swap(&mut self.current, &mut self.next);
     }
 }
 
@@ -442,6 +933,34 @@ pub enum SignalType {
     Stores(ItemKind),
     /// Has a unit of this type.
     Unit(Id<Unit>),
+    /// Stay away from here.
+    ///
+    /// Unlike every other signal type, this repels units rather than attracting them.
+    Avoid(Avoidance),
+    /// There's room to rest here.
+    Rest,
+}
+
+/// The reason that a [`SignalType::Avoid`] signal is being emitted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Avoidance {
+    /// Don't bring items of this type here, as there's no space for them.
+    Item(ItemKind),
+    /// Something dangerous is here, which units should route around.
+    Hazard,
+}
+
+impl Avoidance {
+    /// Returns the item kinds whose [`Avoidance::Item`] signals deter units pursuing `goal`.
+    ///
+    /// These signals are emitted by full storage, so they only deter units that are trying to store an item.
+    /// Every kind that refers to the same items as the stored kind counts, as given by [`ItemManifest::matching_kinds`].
+    pub(crate) fn avoided_item_kinds(goal: &Goal, item_manifest: &ItemManifest) -> Vec<ItemKind> {
+        match goal {
+            Goal::Store(item_kind) => item_manifest.matching_kinds(*item_kind),
+            _ => Vec::new(),
+        }
+    }
 }
 
 impl SignalType {
@@ -484,6 +1003,9 @@ impl SignalType {
     }
 
     /// The pretty formatting for this type
+    ///
+    /// Workplaces and units are named using the terrain and unit manifests;
+    /// everything else is formatted by [`SignalType::describe`].
     pub(crate) fn display(
         &self,
         item_manifest: &ItemManifest,
@@ -492,28 +1014,63 @@ impl SignalType {
         unit_manifest: &UnitManifest,
     ) -> String {
         match self {
-            SignalType::Push(item_kind) => {
-                format!("Push({})", item_manifest.name_of_kind(*item_kind))
-            }
-            SignalType::Pull(item_kind) => {
-                format!("Pull({})", item_manifest.name_of_kind(*item_kind))
-            }
             SignalType::Work(workplace_id) => {
                 format!(
-                    "Work({})",
+                    "Work at {}",
                     workplace_id.name(structure_manifest, terrain_manifest)
                 )
             }
+            SignalType::Unit(unit_id) => format!("Unit {}", unit_manifest.name(*unit_id)),
+            _ => self.describe(item_manifest, structure_manifest),
+        }
+    }
+
+    /// Describes this signal in plain words, for use in logs, overlays and test assertions.
+    ///
+    /// Ids are resolved to their names where possible, falling back to their raw values if they are not in the manifest.
+    /// Terraforming workplaces and units are described without their names,
+    /// as these require manifests that are rarely at hand while debugging logistics.
+    pub(crate) fn describe(
+        &self,
+        item_manifest: &ItemManifest,
+        structure_manifest: &StructureManifest,
+    ) -> String {
+        let item_kind_name = |item_kind: ItemKind| match item_kind {
+            ItemKind::Single(item_id) => item_manifest
+                .try_name(item_id)
+                .map(str::to_string)
+                .unwrap_or_else(|| format!("{item_id:?}")),
+            ItemKind::Tag(tag) => format!("{tag} items"),
+        };
+        let structure_name = |structure_id: Id<Structure>| {
+            structure_manifest
+                .try_name(structure_id)
+                .map(str::to_string)
+                .unwrap_or_else(|| format!("{structure_id:?}"))
+        };
+
+        match self {
+            SignalType::Push(item_kind) => format!("Push {}", item_kind_name(*item_kind)),
+            SignalType::Pull(item_kind) => format!("Pull {}", item_kind_name(*item_kind)),
+            SignalType::Work(WorkplaceId::Structure(structure_id)) => {
+                format!("Work at {}", structure_name(*structure_id))
+            }
+            SignalType::Work(WorkplaceId::Terrain(terraforming_action)) => {
+                format!("Work at {terraforming_action:?} terraforming")
+            }
             SignalType::Demolish(structure_id) => {
-                format!("Demolish({})", structure_manifest.name(*structure_id))
+                format!("Demolish {}", structure_name(*structure_id))
             }
             SignalType::Contains(item_kind) => {
-                format!("Contains({})", item_manifest.name_of_kind(*item_kind))
+                format!("Contains {}", item_kind_name(*item_kind))
             }
-            SignalType::Stores(item_kind) => {
-                format!("Stores({})", item_manifest.name_of_kind(*item_kind))
+            SignalType::Stores(item_kind) => format!("Stores {}", item_kind_name(*item_kind)),
+            SignalType::Unit(unit_id) => format!("Unit {unit_id:?}"),
+            SignalType::Avoid(Avoidance::Item(item_kind)) => {
+                format!("Avoid {}", item_kind_name(*item_kind))
             }
-            SignalType::Unit(unit_id) => format!("Unit({})", unit_manifest.name(*unit_id)),
+            SignalType::Avoid(Avoidance::Hazard) => "Avoid hazards".to_string(),
+            SignalType::Rest => "Rest".to_string(),
         }
     }
 }
@@ -541,6 +1098,10 @@ pub(crate) enum SignalKind {
     Stores,
     /// Has a unit of this type.
     Unit,
+    /// Stay away from here.
+    Avoid,
+    /// There's room to rest here.
+    Rest,
 }
 
 impl From<SignalType> for SignalKind {
@@ -553,6 +1114,8 @@ impl From<SignalType> for SignalKind {
             SignalType::Contains(_) => SignalKind::Contains,
             SignalType::Stores(_) => SignalKind::Stores,
             SignalType::Unit(_) => SignalKind::Unit,
+            SignalType::Avoid(_) => SignalKind::Avoid,
+            SignalType::Rest => SignalKind::Rest,
         }
     }
 }
@@ -570,11 +1133,26 @@ impl SignalStrength {
     /// An infinitely strong signal.
     pub const INFINITY: SignalStrength = SignalStrength(f32::INFINITY);
 
+    /// The strength of the signal produced by a single item, when using [`SignalStrength::from_count`].
+    pub const SINGLE_ITEM: f32 = 10.;
+
     /// Creates a new [`SignalStrength`], ensuring that it has a minimum value of 0.
     pub fn new(value: f32) -> Self {
         SignalStrength(value.max(0.))
     }
 
+    /// Creates a [`SignalStrength`] for a pile of `count` items.
+    ///
+    /// Strength grows logarithmically with the count, so that huge piles don't produce absurd gradients:
+    /// each tenfold increase in items adds the same amount of strength.
+    pub fn from_count(count: u32) -> Self {
+        if count == 0 {
+            return SignalStrength::ZERO;
+        }
+
+        SignalStrength::new(Self::SINGLE_ITEM * (1. + (count as f32).ln()))
+    }
+
     /// The underlying value
     pub fn value(&self) -> f32 {
         self.0
@@ -644,6 +1222,10 @@ impl DivAssign<f32> for SignalStrength {
 pub(crate) struct Emitter {
     /// The list of signals to emit at a provided
     pub(crate) signals: Vec<(SignalType, SignalStrength)>,
+    /// The maximum distance, in tiles, at which these signals can be detected.
+    ///
+    /// If this is [`None`], the signals can be detected anywhere that they diffuse to.
+    pub(crate) max_range: Option<u32>,
 }
 
 impl Emitter {
@@ -674,23 +1256,93 @@ impl Emitter {
     }
 }
 
+/// The categories of structures that compete for the attention of haulers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, IterableEnum)]
+pub enum LogisticsCategory {
+    /// Ghosts and terraforming sites waiting on construction materials.
+    Construction,
+    /// Structures that craft items using recipes.
+    Crafting,
+    /// Logistic buildings, like absorbers and releasers, which move items around.
+    Logistics,
+    /// Structures that store items.
+    Storage,
+}
+
+/// Scales the strength of the signals emitted by each [`LogisticsCategory`].
+///
+/// When several structures pull on the same item, haulers will favor the category with the highest weight.
+#[derive(Resource, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LogisticsPriorities {
+    /// The multiplier applied to the signal strength of each category.
+    weights: HashMap<LogisticsCategory, f32>,
+}
+
+impl Default for LogisticsPriorities {
+    /// Every category is weighted equally, preserving the signal strengths set by each emitter.
+    fn default() -> Self {
+        LogisticsPriorities {
+            weights: LogisticsCategory::variants()
+                .map(|category| (category, 1.))
+                .collect(),
+        }
+    }
+}
+
+impl LogisticsPriorities {
+    /// The smallest allowable weight, which prevents haulers from serving that category at all.
+    pub const MIN_WEIGHT: f32 = 0.;
+
+    /// The largest allowable weight.
+    pub const MAX_WEIGHT: f32 = 10.;
+
+    /// Returns the weight of the provided `category`.
+    pub fn weight(&self, category: LogisticsCategory) -> f32 {
+        self.weights.get(&category).copied().unwrap_or(1.)
+    }
+
+    /// Sets the weight of the provided `category`.
+    ///
+    /// The weight is clamped between [`LogisticsPriorities::MIN_WEIGHT`] and [`LogisticsPriorities::MAX_WEIGHT`].
+    pub fn set_weight(&mut self, category: LogisticsCategory, weight: f32) {
+        let weight = if weight.is_nan() {
+            Self::MIN_WEIGHT
+        } else {
+            weight.clamp(Self::MIN_WEIGHT, Self::MAX_WEIGHT)
+        };
+
+        self.weights.insert(category, weight);
+    }
+
+    /// Returns the strength of a signal with a base strength of `base_strength` emitted by the provided `category`.
+    pub fn signal_strength(
+        &self,
+        category: LogisticsCategory,
+        base_strength: f32,
+    ) -> SignalStrength {
+        SignalStrength::new(base_strength * self.weight(category))
+    }
+}
+
 /// Emits signals from [`Emitter`] sources.
+///
+/// This runs serially before [`diffuse_signals`], adding directly to the current buffer of each signal map.
 fn emit_signals(
     mut signals: ResMut<Signals>,
-    emitter_query: Query<(&VoxelPos, &Emitter, Option<&Id<Structure>>, Option<&Facing>)>,
+    emitter_query: Query<(
+        Entity,
+        &VoxelPos,
+        &Emitter,
+        Option<&Id<Structure>>,
+        Option<&Facing>,
+    )>,
     structure_manifest: Res<StructureManifest>,
     terrain_query: Query<&WaterDepth>,
     map_geometry: Res<MapGeometry>,
 ) {
-    /// Emits signals that correspond to a single [`Emitter`].
-    fn emit(signals: &mut Signals, voxel_pos: VoxelPos, emitter: &Emitter, n_tiles: usize) {
-        for (signal_type, signal_strength) in &emitter.signals {
-            let signal_strength = *signal_strength / n_tiles as f32;
-            signals.add_signal(*signal_type, voxel_pos, signal_strength);
-        }
-    }
+    signals.structure_emissions.clear();
 
-    for (&center, emitter, maybe_structure_id, maybe_facing) in emitter_query.iter() {
+    for (entity, &center, emitter, maybe_structure_id, maybe_facing) in emitter_query.iter() {
         // When the water is too deep, disable the flooded buildings to avoid drowning units constantly
         if let Some(structure_id) = maybe_structure_id {
             let structure_data = structure_manifest.get(*structure_id);
@@ -711,15 +1363,46 @@ fn emit_signals(
 
                 let n_tiles = footprint.set.len();
 
+                let mut emissions = Vec::new();
                 for voxel_pos in footprint.normalized(facing, center) {
-                    emit(&mut signals, voxel_pos, emitter, n_tiles);
+                    signals.add_emission(voxel_pos, emitter, n_tiles);
+                    if emitter.max_range.is_none() {
+                        emissions.extend(emitter.signals.iter().map(
+                            |&(signal_type, signal_strength)| {
+                                (signal_type, voxel_pos, signal_strength / n_tiles as f32)
+                            },
+                        ));
+                    }
                 }
+                signals.structure_emissions.insert(entity, emissions);
             }
             None => {
-                emit(&mut signals, center, emitter, 1);
+                signals.add_emission(center, emitter, 1);
             }
         }
     }
+
+    signals.remove_silent_sources();
+}
+
+/// Something dangerous, which units should route around.
+///
+/// Hazards emit an [`Avoidance::Hazard`] signal from their position every tick, in addition to any [`Emitter`].
+#[derive(Component, Debug, Clone, Copy, PartialEq)]
+pub struct Hazard {
+    /// How strongly units are repelled.
+    pub strength: SignalStrength,
+}
+
+/// Emits [`Avoidance::Hazard`] signals from each [`Hazard`].
+fn emit_hazard_signals(mut signals: ResMut<Signals>, hazard_query: Query<(&VoxelPos, &Hazard)>) {
+    for (&voxel_pos, hazard) in hazard_query.iter() {
+        signals.add_signal(
+            SignalType::Avoid(Avoidance::Hazard),
+            voxel_pos,
+            hazard.strength,
+        );
+    }
 }
 
 /// Spreads signals between tiles.
@@ -729,42 +1412,15 @@ fn diffuse_signals(mut signals: ResMut<Signals>, map_geometry: Res<MapGeometry>)
 
 /// Degrades signals, allowing them to approach an asymptotically constant level.
 fn degrade_signals(mut signals: ResMut<Signals>) {
-    /// The fraction of signal that will decay at each step.
-    ///
-    /// Higher values lead to faster decay and improved signal responsiveness.
-    /// This must always be between 0 and 1.
-    const DEGRADATION_FRACTION: f32 = 0.01;
-
-    /// The value below which decayed signals are eliminated completely
-    ///
-    /// Increasing this value will:
-    ///  - increase computational costs
-    ///  - increase the range at which tasks can be detected
-    ///  - increase the amount of time units will wait around for more production
-    const EPSILON_STRENGTH: SignalStrength = SignalStrength(1e-8);
-
-    signals.maps.par_iter_mut().for_each(|(_, signal_map)| {
-        let mut tiles_to_clear: Vec<VoxelPos> = Vec::with_capacity(signal_map.current.len());
-
-        for (voxel_pos, signal_strength) in signal_map.current.iter_mut() {
-            let new_strength = *signal_strength * (1. - DEGRADATION_FRACTION);
-
-            if new_strength > EPSILON_STRENGTH {
-                *signal_strength = new_strength;
-            } else {
-                tiles_to_clear.push(*voxel_pos);
-            }
-        }
-
-        for tile_to_clear in tiles_to_clear {
-            signal_map.current.remove(&tile_to_clear);
-        }
-    });
+    signals.degrade();
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::items::item_manifest::ItemData;
+    use crate::{
+        items::item_manifest::ItemData,
+        structures::{clean_up_despawned_structures, structure_manifest::StructureData, Footprint},
+    };
 
     use super::*;
 
@@ -781,26 +1437,163 @@ mod tests {
         manifest.insert(
             "12345".to_string(),
             ItemData {
-                stack_size: 1,
-                compostable: false,
-                fluid: false,
                 buoyant: true,
-                seed: None,
+                ..ItemData::basic(1)
             },
         );
         manifest
     }
 
     #[test]
-    fn pending_additions_are_applied() {
-        let mut signal_map = SignalMap::default();
-        signal_map
-            .pending_addition
-            .push((VoxelPos::ZERO.above(), SignalStrength(1.)));
+    fn hex_layout_is_dense_and_unique() {
+        for radius in 0..6 {
+            let layout = HexLayout { radius };
+            let indexes: Vec<usize> = hexagon(Hex::ZERO, radius)
+                .map(|hex| layout.index(hex).unwrap())
+                .sorted()
+                .collect();
+
+            assert_eq!(indexes, (0..layout.len()).collect::<Vec<_>>());
+            assert_eq!(layout.index(Hex::new(radius as i32 + 1, 0)), None);
+        }
+    }
+
+    #[test]
+    fn growing_preserves_signals() {
+        let mut signals = Signals::default();
+        let near = VoxelPos::from_xy(1, -1);
+        let far = VoxelPos::from_xy(-4, 2);
+
+        signals.add_signal(SignalType::Pull(test_item()), near, SignalStrength(1.));
+        signals.add_signal(SignalType::Pull(test_item()), far, SignalStrength(2.));
+
+        assert_eq!(signals.layout.radius, 4);
+        assert_eq!(
+            signals.get(SignalType::Pull(test_item()), near),
+            SignalStrength(1.)
+        );
+        assert_eq!(
+            signals.get(SignalType::Pull(test_item()), far),
+            SignalStrength(2.)
+        );
+    }
+
+    /// Emits a [`SignalType::Pull`] signal from each of the `emissions` every tick for a while,
+    /// limited to their maximum range.
+    fn emit_and_diffuse(
+        emissions: &[(VoxelPos, Option<u32>)],
+        map_geometry: &MapGeometry,
+    ) -> Signals {
+        let mut signals = Signals::default();
+
+        for _ in 0..20 {
+            for &(voxel_pos, max_range) in emissions {
+                let emitter = Emitter {
+                    signals: vec![(SignalType::Pull(test_item()), SignalStrength(1.))],
+                    max_range,
+                };
+                signals.add_emission(voxel_pos, &emitter, 1);
+            }
+            signals.remove_silent_sources();
+            signals.diffuse(map_geometry, DIFFUSION_FRACTION);
+        }
+
+        signals
+    }
+
+    #[test]
+    fn unbounded_sources_do_not_lift_the_range_of_bounded_sources() {
+        let mut world = World::new();
+        let map_geometry = MapGeometry::new(&mut world, 8);
+        let pull = SignalType::Pull(test_item());
+        let bounded = (VoxelPos::from_xy(-6, 0), Some(2));
+        let unbounded = (VoxelPos::from_xy(6, 0), None);
+
+        let mixed = emit_and_diffuse(&[bounded, unbounded], &map_geometry);
+        let unbounded_only = emit_and_diffuse(&[unbounded], &map_geometry);
+
+        // Within range, both sources can be detected
+        let in_range = VoxelPos::from_xy(-5, 0);
+        assert!(mixed.get(pull, in_range) > unbounded_only.get(pull, in_range));
+
+        // Beyond range, only the unbounded source can be detected
+        for x in -3..=8 {
+            let voxel_pos = VoxelPos::from_xy(x, 0);
+            assert_eq!(
+                mixed.get(pull, voxel_pos),
+                unbounded_only.get(pull, voxel_pos)
+            );
+        }
+    }
 
-        assert_eq!(signal_map.get(VoxelPos::ZERO.above()), SignalStrength::ZERO);
-        signal_map.apply_pending_additions();
-        assert_eq!(signal_map.get(VoxelPos::ZERO.above()), SignalStrength(1.));
+    #[test]
+    fn bounded_sources_do_not_leak_into_the_range_of_other_sources() {
+        let mut world = World::new();
+        let map_geometry = MapGeometry::new(&mut world, 8);
+        let pull = SignalType::Pull(test_item());
+        let near = (VoxelPos::from_xy(-3, 0), Some(2));
+        let far = (VoxelPos::from_xy(3, 0), Some(5));
+
+        let both = emit_and_diffuse(&[near, far], &map_geometry);
+        let near_only = emit_and_diffuse(&[near], &map_geometry);
+
+        // These tiles are in range of the near source, but out of range of the far source
+        for x in -5..=-3 {
+            let voxel_pos = VoxelPos::from_xy(x, 0);
+            assert!(both.get(pull, voxel_pos) > SignalStrength::ZERO);
+            assert_eq!(both.get(pull, voxel_pos), near_only.get(pull, voxel_pos));
+        }
+    }
+
+    #[test]
+    fn silent_bounded_sources_are_removed() {
+        let mut world = World::new();
+        let map_geometry = MapGeometry::new(&mut world, 4);
+        let pull = SignalType::Pull(test_item());
+
+        let mut signals = emit_and_diffuse(&[(VoxelPos::ZERO, Some(2))], &map_geometry);
+        assert!(signals.get(pull, VoxelPos::ZERO) > SignalStrength::ZERO);
+
+        // Nothing was emitted since the last call
+        signals.remove_silent_sources();
+        assert!(signals.bounded.is_empty());
+        assert_eq!(signals.get(pull, VoxelPos::ZERO), SignalStrength::ZERO);
+    }
+
+    #[test]
+    fn diffusion_sources_are_cached_until_walkability_changes() {
+        let mut world = World::new();
+        let mut map_geometry = MapGeometry::new(&mut world, 2);
+        let mut signals = Signals::default();
+
+        signals.diffuse(&map_geometry, 0.1);
+        let cached_for = signals.diffusion_sources.computed_for;
+        assert!(cached_for.is_some());
+
+        // Nothing changed, so the cache is reused
+        signals.diffuse(&map_geometry, 0.1);
+        assert_eq!(signals.diffusion_sources.computed_for, cached_for);
+
+        // A structure that can't be walked on or through blocks diffusion into its tile
+        let voxel_pos = map_geometry.on_top_of_terrain(Hex::ZERO);
+        let structure_entity = world.spawn_empty().id();
+        map_geometry
+            .add_structure(
+                voxel_pos,
+                Facing::default(),
+                &Footprint::single(),
+                false,
+                false,
+                structure_entity,
+            )
+            .unwrap();
+
+        signals.diffuse(&map_geometry, 0.1);
+        assert_ne!(signals.diffusion_sources.computed_for, cached_for);
+        assert_eq!(
+            signals.diffusion_sources.sources,
+            signals.layout.diffusion_sources(&map_geometry)
+        );
     }
 
     #[test]
@@ -833,11 +1626,185 @@ mod tests {
             7,
             "Signal should have diffused to all 7 neigboring tiles"
         );
-        for (_, &signal_strength) in current_signals.iter() {
+        for &signal_strength in current_signals.iter() {
             assert!(signal_strength > SignalStrength::ZERO);
         }
     }
 
+    #[test]
+    fn diffusion_is_deterministic_across_thread_counts() {
+        /// Emits, diffuses and degrades signals for 500 ticks on a thread pool of the given size.
+        fn simulate(n_threads: usize) -> Vec<(SignalType, Vec<SignalStrength>)> {
+            let mut world = World::new();
+            let map_geometry = MapGeometry::new(&mut world, 10);
+            let mut signals = Signals::default();
+
+            let signal_types = [
+                SignalType::Pull(test_item()),
+                SignalType::Push(test_item()),
+                SignalType::Work(WorkplaceId::structure(test_structure())),
+            ];
+            let emitters = [
+                VoxelPos::ZERO.above(),
+                map_geometry.on_top_of_terrain(Hex::new(5, -3)),
+                map_geometry.on_top_of_terrain(Hex::new(-7, 2)),
+            ];
+
+            let thread_pool = rayon::ThreadPoolBuilder::new()
+                .num_threads(n_threads)
+                .build()
+                .unwrap();
+
+            thread_pool.install(|| {
+                for tick in 0..500 {
+                    for (i, &signal_type) in signal_types.iter().enumerate() {
+                        let strength = SignalStrength((tick % 7 + i) as f32 * 0.3);
+                        signals.add_signal(signal_type, emitters[i], strength);
+                    }
+
+                    signals.diffuse(&map_geometry, DIFFUSION_FRACTION);
+                    signals.degrade();
+                }
+            });
+
+            signal_types
+                .into_iter()
+                .map(|signal_type| (signal_type, signals.maps[&signal_type].current.clone()))
+                .collect()
+        }
+
+        let single_threaded = simulate(1);
+        let multi_threaded = simulate(8);
+
+        for ((signal_type, single), (_, multi)) in single_threaded.iter().zip(multi_threaded.iter())
+        {
+            assert!(single
+                .iter()
+                .any(|&strength| strength > SignalStrength::ZERO));
+            // Compare the raw bits: the results must be exactly identical, not just close
+            let single_bits: Vec<u32> = single.iter().map(|s| s.value().to_bits()).collect();
+            let multi_bits: Vec<u32> = multi.iter().map(|s| s.value().to_bits()).collect();
+            assert_eq!(single_bits, multi_bits, "{signal_type:?} diverged");
+        }
+    }
+
+    #[test]
+    fn despawned_emitters_stop_contributing_signals() {
+        let mut app = App::new();
+        let mut map_geometry = MapGeometry::new(&mut app.world, 0);
+        let terrain_entity = map_geometry.get_terrain(Hex::ZERO).unwrap();
+        app.world.entity_mut(terrain_entity).insert(WaterDepth::Dry);
+
+        let mut structure_manifest = StructureManifest::new();
+        structure_manifest.insert("67890".to_string(), StructureData::impassable());
+
+        let signal_type = SignalType::Pull(test_item());
+        let voxel_pos = map_geometry.on_top_of_terrain(Hex::ZERO);
+        let structure_entity = app
+            .world
+            .spawn((
+                test_structure(),
+                voxel_pos,
+                Facing::default(),
+                Emitter {
+                    signals: vec![(signal_type, SignalStrength(1.))],
+                    max_range: None,
+                },
+            ))
+            .id();
+        map_geometry
+            .add_structure(
+                voxel_pos,
+                Facing::default(),
+                &Footprint::single(),
+                false,
+                false,
+                structure_entity,
+            )
+            .unwrap();
+
+        app.insert_resource(map_geometry)
+            .insert_resource(structure_manifest)
+            .init_resource::<Signals>()
+            .add_systems(
+                (
+                    clean_up_despawned_structures,
+                    emit_signals,
+                    diffuse_signals,
+                    degrade_signals,
+                )
+                    .chain(),
+            );
+
+        app.update();
+        let signals = app.world.resource::<Signals>();
+        assert!(signals.get(signal_type, voxel_pos) > SignalStrength::ZERO);
+
+        // Despawn the structure directly, skipping the usual cleanup performed by `despawn_structure`
+        app.world.despawn(structure_entity);
+        app.update();
+
+        let signals = app.world.resource::<Signals>();
+        assert_eq!(signals.get(signal_type, voxel_pos), SignalStrength::ZERO);
+        let map_geometry = app.world.resource::<MapGeometry>();
+        assert_eq!(map_geometry.get_structure(voxel_pos), None);
+    }
+
+    #[test]
+    fn removing_emissions_keeps_other_emitters_on_the_same_tile() {
+        let mut app = App::new();
+        let mut map_geometry = MapGeometry::new(&mut app.world, 0);
+        let terrain_entity = map_geometry.get_terrain(Hex::ZERO).unwrap();
+        app.world.entity_mut(terrain_entity).insert(WaterDepth::Dry);
+
+        let mut structure_manifest = StructureManifest::new();
+        structure_manifest.insert("67890".to_string(), StructureData::impassable());
+
+        let signal_type = SignalType::Pull(test_item());
+        let voxel_pos = map_geometry.on_top_of_terrain(Hex::ZERO);
+        let structure_entity = app
+            .world
+            .spawn((
+                test_structure(),
+                voxel_pos,
+                Facing::default(),
+                Emitter {
+                    signals: vec![(signal_type, SignalStrength(1.))],
+                    max_range: None,
+                },
+            ))
+            .id();
+        app.world.spawn((
+            voxel_pos,
+            Emitter {
+                signals: vec![(signal_type, SignalStrength(2.))],
+                max_range: None,
+            },
+        ));
+        map_geometry
+            .add_structure(
+                voxel_pos,
+                Facing::default(),
+                &Footprint::single(),
+                false,
+                false,
+                structure_entity,
+            )
+            .unwrap();
+
+        app.insert_resource(map_geometry)
+            .insert_resource(structure_manifest)
+            .init_resource::<Signals>()
+            .add_system(emit_signals);
+
+        app.update();
+        let mut signals = app.world.resource_mut::<Signals>();
+        assert_eq!(signals.get(signal_type, voxel_pos), SignalStrength(3.));
+
+        signals.remove_emissions_from(structure_entity);
+        assert_eq!(signals.get(signal_type, voxel_pos), SignalStrength(2.));
+    }
+
     #[test]
     fn neighboring_signals_checks_origin_tile() {
         let mut signals = Signals::default();
@@ -876,7 +1843,8 @@ mod tests {
                 VoxelPos::ZERO.above(),
                 &Goal::Store(test_item()),
                 &item_manifest,
-                &map_geometry
+                &map_geometry,
+                true
             ),
             None
         );
@@ -885,7 +1853,8 @@ mod tests {
                 VoxelPos::ZERO.above(),
                 &Goal::Fetch(test_item()),
                 &item_manifest,
-                &map_geometry
+                &map_geometry,
+                true
             ),
             None
         );
@@ -894,7 +1863,8 @@ mod tests {
                 VoxelPos::ZERO.above(),
                 &Goal::Work(WorkplaceId::structure(test_structure())),
                 &item_manifest,
-                &map_geometry
+                &map_geometry,
+                true
             ),
             None
         );
@@ -903,7 +1873,8 @@ mod tests {
                 VoxelPos::ZERO.above(),
                 &Goal::default(),
                 &item_manifest,
-                &map_geometry
+                &map_geometry,
+                true
             ),
             None
         );
@@ -927,7 +1898,8 @@ mod tests {
                 VoxelPos::ZERO.above(),
                 &Goal::Store(test_item()),
                 &item_manifest,
-                &map_geometry
+                &map_geometry,
+                true
             ),
             None
         );
@@ -955,7 +1927,8 @@ mod tests {
                 VoxelPos::ZERO.above(),
                 &Goal::Fetch(test_item()),
                 &item_manifest,
-                &map_geometry
+                &map_geometry,
+                true
             ),
             None
         );
@@ -984,7 +1957,8 @@ mod tests {
                 VoxelPos::ZERO.above(),
                 &Goal::Store(test_item()),
                 &item_manifest,
-                &map_geometry
+                &map_geometry,
+                true
             ),
             None
         );
@@ -1006,7 +1980,8 @@ mod tests {
                 VoxelPos::ZERO.above(),
                 &Goal::Store(test_item()),
                 &item_manifest,
-                &map_geometry
+                &map_geometry,
+                true
             )
             .is_some());
     }
@@ -1033,11 +2008,204 @@ mod tests {
                 VoxelPos::ZERO.above(),
                 &Goal::Store(test_item()),
                 &item_manifest,
-                &map_geometry
+                &map_geometry,
+                true
             )
             .is_some());
     }
 
+    #[test]
+    fn haulers_prefer_destinations_without_avoid_signals() {
+        let mut signals = Signals::default();
+        let mut world = World::new();
+        let map_geometry = MapGeometry::new(&mut world, 3);
+        let item_manifest = test_manifest();
+
+        let hauler_pos = map_geometry.on_top_of_terrain(Hex::ZERO);
+        let plain_destination = map_geometry.on_top_of_terrain(Hex::new(2, 0));
+        let avoided_destination = map_geometry.on_top_of_terrain(Hex::new(-2, 0));
+
+        // Both destinations are equally attractive, but one of them also asks to be avoided
+        for _ in 0..20 {
+            signals.add_signal(
+                SignalType::Stores(test_item()),
+                plain_destination,
+                SignalStrength(10.),
+            );
+            signals.add_signal(
+                SignalType::Stores(test_item()),
+                avoided_destination,
+                SignalStrength(10.),
+            );
+            signals.add_signal(
+                SignalType::Avoid(Avoidance::Item(test_item())),
+                avoided_destination,
+                SignalStrength(10.),
+            );
+            signals.diffuse(&map_geometry, DIFFUSION_FRACTION);
+        }
+
+        assert_eq!(
+            signals.upstream(
+                hauler_pos,
+                &Goal::Store(test_item()),
+                &item_manifest,
+                &map_geometry,
+                true
+            ),
+            Some(map_geometry.on_top_of_terrain(Hex::new(1, 0)))
+        );
+    }
+
+    #[test]
+    fn full_storage_only_deters_units_storing_its_items() {
+        let mut signals = Signals::default();
+        let mut world = World::new();
+        let map_geometry = MapGeometry::new(&mut world, 1);
+        let item_manifest = test_manifest();
+
+        let storage_pos = map_geometry.on_top_of_terrain(Hex::ZERO);
+        signals.add_signal(
+            SignalType::Avoid(Avoidance::Item(test_item())),
+            storage_pos,
+            SignalStrength(10.),
+        );
+
+        let store = Avoidance::avoided_item_kinds(&Goal::Store(test_item()), &item_manifest);
+        let deliver = Avoidance::avoided_item_kinds(&Goal::Deliver(test_item()), &item_manifest);
+        assert_eq!(signals.path_cost(storage_pos, &store), SignalStrength(10.));
+        assert_eq!(
+            signals.path_cost(storage_pos, &deliver),
+            SignalStrength::ZERO
+        );
+
+        // Goal selection follows the same rule
+        let local_signals = signals.all_signals_at_position(storage_pos);
+        assert_eq!(
+            local_signals.avoidance(SignalType::Pull(test_item()), &item_manifest),
+            SignalStrength::ZERO
+        );
+    }
+
+    /// Covers each of the `hexes` with water too deep to wade through.
+    fn flood(map_geometry: &mut MapGeometry, hexes: impl IntoIterator<Item = Hex>) {
+        for hex in hexes {
+            map_geometry.set_underwater(hex, true);
+            map_geometry.set_deep_water(hex, true);
+        }
+    }
+
+    #[test]
+    fn non_swimmers_do_not_step_into_deep_water() {
+        let mut signals = Signals::default();
+        let mut world = World::new();
+        let mut map_geometry = MapGeometry::new(&mut world, 4);
+        let item_manifest = test_manifest();
+
+        let hauler_pos = map_geometry.on_top_of_terrain(Hex::ZERO);
+        let island_destination = map_geometry.on_top_of_terrain(Hex::new(-2, 0));
+        flood(&mut map_geometry, island_destination.hex.ring(1));
+
+        for _ in 0..20 {
+            signals.add_signal(
+                SignalType::Pull(test_item()),
+                island_destination,
+                SignalStrength(10.),
+            );
+            signals.diffuse(&map_geometry, DIFFUSION_FRACTION);
+        }
+
+        // Swimmers head straight for the island
+        assert_eq!(
+            signals.upstream(
+                hauler_pos,
+                &Goal::Store(test_item()),
+                &item_manifest,
+                &map_geometry,
+                true,
+            ),
+            Some(map_geometry.on_top_of_terrain(Hex::new(-1, 0)))
+        );
+
+        // Units that cannot swim still smell the island, but stay on dry land
+        let choice = signals
+            .upstream(
+                hauler_pos,
+                &Goal::Store(test_item()),
+                &item_manifest,
+                &map_geometry,
+                false,
+            )
+            .unwrap();
+        assert_ne!(choice, hauler_pos);
+        assert!(!map_geometry.is_deep_water(choice.hex));
+    }
+
+    #[test]
+    fn stranded_non_swimmers_can_still_move() {
+        let mut signals = Signals::default();
+        let mut world = World::new();
+        let mut map_geometry = MapGeometry::new(&mut world, 4);
+        let item_manifest = test_manifest();
+
+        let hauler_pos = map_geometry.on_top_of_terrain(Hex::ZERO);
+        let destination = map_geometry.on_top_of_terrain(Hex::new(3, 0));
+        flood(&mut map_geometry, hexagon(Hex::ZERO, 1));
+
+        for _ in 0..20 {
+            signals.add_signal(
+                SignalType::Pull(test_item()),
+                destination,
+                SignalStrength(10.),
+            );
+            signals.diffuse(&map_geometry, DIFFUSION_FRACTION);
+        }
+
+        assert_eq!(
+            signals.upstream(
+                hauler_pos,
+                &Goal::Store(test_item()),
+                &item_manifest,
+                &map_geometry,
+                false,
+            ),
+            Some(map_geometry.on_top_of_terrain(Hex::new(1, 0)))
+        );
+    }
+
+    #[test]
+    fn path_costs_increase_towards_hazards() {
+        let mut signals = Signals::default();
+        let mut world = World::new();
+        let map_geometry = MapGeometry::new(&mut world, 3);
+        let item_manifest = test_manifest();
+
+        let hazard_pos = map_geometry.on_top_of_terrain(Hex::new(3, 0));
+        for _ in 0..20 {
+            signals.add_signal(
+                SignalType::Avoid(Avoidance::Hazard),
+                hazard_pos,
+                SignalStrength(10.),
+            );
+            signals.diffuse(&map_geometry, DIFFUSION_FRACTION);
+        }
+
+        let avoided_kinds =
+            Avoidance::avoided_item_kinds(&Goal::Store(test_item()), &item_manifest);
+        let costs: Vec<SignalStrength> = (-3..=3)
+            .map(|q| {
+                signals.path_cost(
+                    map_geometry.on_top_of_terrain(Hex::new(q, 0)),
+                    &avoided_kinds,
+                )
+            })
+            .collect();
+
+        for pair in costs.windows(2) {
+            assert!(pair[0] < pair[1], "{costs:?}");
+        }
+    }
+
     #[test]
     fn item_signal_types_are_correct() {
         let item_kind = test_item();
@@ -1080,4 +2248,81 @@ mod tests {
             vec![SignalType::Pull(item_kind), SignalType::Stores(item_kind)]
         );
     }
+
+    #[test]
+    fn signal_strength_from_count_is_logarithmic() {
+        assert_eq!(SignalStrength::from_count(0), SignalStrength::ZERO);
+        assert_eq!(
+            SignalStrength::from_count(1).value(),
+            SignalStrength::SINGLE_ITEM
+        );
+
+        let strengths: Vec<f32> = [1, 10, 100, 1000]
+            .into_iter()
+            .map(|count| SignalStrength::from_count(count).value())
+            .collect();
+
+        for window in strengths.windows(3) {
+            assert!(window[0] < window[1]);
+            assert!((window[1] - window[0] - (window[2] - window[1])).abs() < 1e-2);
+        }
+    }
+
+    #[test]
+    fn logistics_weights_are_clamped() {
+        let mut priorities = LogisticsPriorities::default();
+        assert_eq!(priorities.weight(LogisticsCategory::Storage), 1.);
+
+        priorities.set_weight(LogisticsCategory::Storage, 100.);
+        assert_eq!(
+            priorities.weight(LogisticsCategory::Storage),
+            LogisticsPriorities::MAX_WEIGHT
+        );
+
+        priorities.set_weight(LogisticsCategory::Storage, -1.);
+        assert_eq!(
+            priorities.weight(LogisticsCategory::Storage),
+            LogisticsPriorities::MIN_WEIGHT
+        );
+
+        priorities.set_weight(LogisticsCategory::Storage, f32::NAN);
+        assert_eq!(
+            priorities.weight(LogisticsCategory::Storage),
+            LogisticsPriorities::MIN_WEIGHT
+        );
+    }
+
+    #[test]
+    fn signals_describe_themselves_with_resolved_names() {
+        let mut item_manifest = test_manifest();
+        item_manifest.insert(
+            "wood".to_string(),
+            ItemData {
+                compostable: true,
+                buoyant: true,
+                ..ItemData::basic(10)
+            },
+        );
+        let structure_manifest = StructureManifest::new();
+        let wood_id = Id::from_name("wood".to_string());
+
+        let describe =
+            |signal_type: SignalType| signal_type.describe(&item_manifest, &structure_manifest);
+
+        assert_eq!(
+            describe(SignalType::Pull(ItemKind::Single(wood_id))),
+            "Pull wood"
+        );
+        assert_eq!(
+            describe(SignalType::Avoid(Avoidance::Item(ItemKind::Tag(
+                crate::crafting::item_tags::ItemTag::Compostable
+            )))),
+            "Avoid Compostable items"
+        );
+        // Unknown ids still produce a description, rather than panicking
+        assert_eq!(
+            describe(SignalType::Demolish(test_structure())),
+            format!("Demolish {:?}", test_structure())
+        );
+    }
 }
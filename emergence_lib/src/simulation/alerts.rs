@@ -0,0 +1,272 @@
+//! Alerts tell players about problems that have persisted long enough to need their attention.
+//!
+//! Alerts are raised and resolved automatically by the systems that watch for each problem,
+//! and are stored in the [`AlertLog`] for display in the UI.
+
+use std::collections::VecDeque;
+
+use bevy::{prelude::*, utils::Duration, utils::HashMap};
+
+use crate::crafting::item_tags::ItemKind;
+
+use super::{
+    time::{Days, InGameTime},
+    SimulationSet,
+};
+
+/// A problem that the player should be told about.
+#[derive(Debug, Clone, PartialEq)]
+pub enum AlertKind {
+    /// A releaser has been unable to release its items.
+    ReleaserBlocked {
+        /// The blocked releaser.
+        entity: Entity,
+        /// When the releaser first became blocked.
+        since: Days,
+    },
+    /// An absorber has been unable to absorb the items around it, as its output is full.
+    AbsorberFull {
+        /// The full absorber.
+        entity: Entity,
+        /// When the absorber first became full.
+        since: Days,
+    },
+    /// A crafting structure has been waiting for its inputs.
+    CrafterStarved {
+        /// The starved crafting structure.
+        entity: Entity,
+        /// The input that the structure is waiting for.
+        missing: ItemKind,
+    },
+}
+
+impl AlertKind {
+    /// The entity that this alert is about.
+    pub fn entity(&self) -> Entity {
+        match self {
+            AlertKind::ReleaserBlocked { entity, .. }
+            | AlertKind::AbsorberFull { entity, .. }
+            | AlertKind::CrafterStarved { entity, .. } => *entity,
+        }
+    }
+
+    /// The category of problem that this alert reports.
+    pub fn category(&self) -> AlertCategory {
+        match self {
+            AlertKind::ReleaserBlocked { .. } => AlertCategory::ReleaserBlocked,
+            AlertKind::AbsorberFull { .. } => AlertCategory::AbsorberFull,
+            AlertKind::CrafterStarved { .. } => AlertCategory::CrafterStarved,
+        }
+    }
+}
+
+/// The variants of [`AlertKind`], without their data.
+///
+/// Each entity has at most one active alert of each category.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum AlertCategory {
+    /// See [`AlertKind::ReleaserBlocked`].
+    ReleaserBlocked,
+    /// See [`AlertKind::AbsorberFull`].
+    AbsorberFull,
+    /// See [`AlertKind::CrafterStarved`].
+    CrafterStarved,
+}
+
+/// A single entry in the [`AlertLog`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct Alert {
+    /// What the problem is.
+    pub kind: AlertKind,
+    /// When the alert was raised.
+    pub raised_at: Days,
+    /// When the problem went away.
+    ///
+    /// If this is [`None`], the alert is still active.
+    pub resolved_at: Option<Days>,
+}
+
+impl Alert {
+    /// Is the problem reported by this alert still ongoing?
+    pub fn is_active(&self) -> bool {
+        self.resolved_at.is_none()
+    }
+}
+
+/// Every alert raised recently, both active and resolved.
+#[derive(Resource, Debug)]
+pub struct AlertLog {
+    /// How long a problem must persist before an alert is raised.
+    pub threshold: Duration,
+    /// The most recent alerts, from oldest to newest.
+    alerts: VecDeque<Alert>,
+    /// When each problem that has not yet raised an alert was first observed.
+    pending: HashMap<(Entity, AlertCategory), Days>,
+}
+
+impl Default for AlertLog {
+    fn default() -> Self {
+        AlertLog {
+            threshold: Duration::from_secs(10),
+            alerts: VecDeque::new(),
+            pending: HashMap::default(),
+        }
+    }
+}
+
+impl AlertLog {
+    /// The maximum number of alerts remembered.
+    ///
+    /// Once this is exceeded, the oldest resolved alerts are forgotten.
+    pub const MAX_HISTORY: usize = 100;
+
+    /// The alerts whose problems are still ongoing, from oldest to newest.
+    pub fn active(&self) -> impl Iterator<Item = &Alert> {
+        self.alerts.iter().filter(|alert| alert.is_active())
+    }
+
+    /// Up to `limit` of the most recent alerts, active or resolved, from newest to oldest.
+    pub fn history(&self, limit: usize) -> impl Iterator<Item = &Alert> {
+        self.alerts.iter().rev().take(limit)
+    }
+
+    /// Records whether or not the problem of the provided `category` currently affects `entity`.
+    ///
+    /// Once the problem has persisted for the [`threshold`](Self::threshold),
+    /// an alert constructed by `make_kind` from the time the problem began is raised.
+    /// Only one alert is active for each entity and category at once.
+    /// When the problem goes away, any active alert is resolved.
+    pub(crate) fn observe(
+        &mut self,
+        entity: Entity,
+        category: AlertCategory,
+        problem: bool,
+        in_game_time: &InGameTime,
+        make_kind: impl FnOnce(Days) -> AlertKind,
+    ) {
+        let now = Days(in_game_time.elapsed_days());
+
+        if !problem {
+            self.pending.remove(&(entity, category));
+            self.resolve(entity, category, now);
+            return;
+        }
+
+        if self.active_alert(entity, category).is_some() {
+            return;
+        }
+
+        let since = *self.pending.entry((entity, category)).or_insert(now);
+        let elapsed_seconds = (now - since).0 * in_game_time.seconds_per_day();
+        if elapsed_seconds >= self.threshold.as_secs_f32() {
+            self.pending.remove(&(entity, category));
+            self.raise(make_kind(since), now);
+        }
+    }
+
+    /// Resolves every active alert about entities that no longer exist.
+    fn forget_entities(&mut self, exists: impl Fn(Entity) -> bool, now: Days) {
+        self.pending.retain(|(entity, _), _| exists(*entity));
+        for alert in self.alerts.iter_mut() {
+            if alert.is_active() && !exists(alert.kind.entity()) {
+                alert.resolved_at = Some(now);
+            }
+        }
+    }
+
+    /// Returns the active alert of the provided `category` about `entity`, if any.
+    fn active_alert(&mut self, entity: Entity, category: AlertCategory) -> Option<&mut Alert> {
+        self.alerts.iter_mut().find(|alert| {
+            alert.is_active() && alert.kind.entity() == entity && alert.kind.category() == category
+        })
+    }
+
+    /// Adds a new active alert, forgetting the oldest resolved alerts if there are too many.
+    fn raise(&mut self, kind: AlertKind, now: Days) {
+        self.alerts.push_back(Alert {
+            kind,
+            raised_at: now,
+            resolved_at: None,
+        });
+
+        while self.alerts.len() > Self::MAX_HISTORY {
+            let Some(index) = self.alerts.iter().position(|alert| !alert.is_active()) else {
+                break;
+            };
+            self.alerts.remove(index);
+        }
+    }
+
+    /// Resolves the active alert of the provided `category` about `entity`, if any.
+    fn resolve(&mut self, entity: Entity, category: AlertCategory, now: Days) {
+        if let Some(alert) = self.active_alert(entity, category) {
+            alert.resolved_at = Some(now);
+        }
+    }
+}
+
+/// Stores and maintains the [`AlertLog`].
+pub(super) struct AlertPlugin;
+
+impl Plugin for AlertPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<AlertLog>().add_system(
+            resolve_alerts_for_despawned_entities
+                .in_set(SimulationSet)
+                .in_schedule(CoreSchedule::FixedUpdate),
+        );
+    }
+}
+
+/// Resolves the alerts about entities that have been despawned, such as demolished structures.
+fn resolve_alerts_for_despawned_entities(
+    entity_query: Query<()>,
+    in_game_time: Res<InGameTime>,
+    mut alert_log: ResMut<AlertLog>,
+) {
+    let now = Days(in_game_time.elapsed_days());
+    alert_log.forget_entities(|entity| entity_query.contains(entity), now);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn releaser_blocked(
+        alert_log: &mut AlertLog,
+        entity: Entity,
+        blocked: bool,
+        in_game_time: &InGameTime,
+    ) {
+        alert_log.observe(
+            entity,
+            AlertCategory::ReleaserBlocked,
+            blocked,
+            in_game_time,
+            |since| AlertKind::ReleaserBlocked { entity, since },
+        );
+    }
+
+    #[test]
+    fn history_is_bounded_but_keeps_active_alerts() {
+        let mut alert_log = AlertLog {
+            threshold: Duration::ZERO,
+            ..Default::default()
+        };
+        let in_game_time = InGameTime::default();
+
+        let stuck = Entity::from_raw(0);
+        releaser_blocked(&mut alert_log, stuck, true, &in_game_time);
+
+        for i in 1..=(AlertLog::MAX_HISTORY as u32 * 2) {
+            let entity = Entity::from_raw(i);
+            releaser_blocked(&mut alert_log, entity, true, &in_game_time);
+            releaser_blocked(&mut alert_log, entity, false, &in_game_time);
+        }
+
+        assert_eq!(alert_log.history(usize::MAX).count(), AlertLog::MAX_HISTORY);
+        let active: Vec<&Alert> = alert_log.active().collect();
+        assert_eq!(active.len(), 1);
+        assert_eq!(active[0].kind.entity(), stuck);
+    }
+}
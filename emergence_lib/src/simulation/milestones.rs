@@ -0,0 +1,665 @@
+//! Milestones mark the progress of the colony, unlocking new structures and recipes as they are completed.
+//!
+//! The condition needed to complete each milestone is read from the [`MilestoneManifest`],
+//! and the progress made is saved to disk so that it is kept between sessions.
+
+use std::{
+    env,
+    fmt::Debug,
+    fs, io,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use bevy::{
+    prelude::*,
+    reflect::TypeUuid,
+    utils::{HashMap, HashSet},
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    asset_management::manifest::{
+        loader::{serialize_sorted, IsRawManifest, ManifestError},
+        plugin::ManifestPlugin,
+        Id, Manifest,
+    },
+    construction::ghosts::Ghost,
+    crafting::{
+        flow::ItemConsumed,
+        recipe::{ActiveRecipe, RecipeManifest},
+    },
+    geometry::{Facing, VoxelPos},
+    items::item_manifest::{Item, ItemManifest},
+    structures::{
+        logistic_buildings::ReleasesItems,
+        structure_manifest::{Structure, StructureManifest},
+        Footprint, Landmark,
+    },
+};
+
+use super::SimulationSet;
+
+/// The marker type for [`Id<Milestone>`](crate::asset_management::manifest::Id).
+#[derive(Reflect, FromReflect, Clone, Copy, PartialEq, Eq)]
+pub struct Milestone;
+
+/// Stores the condition needed to complete each milestone.
+pub type MilestoneManifest = Manifest<Milestone, MilestoneCondition>;
+
+impl MilestoneManifest {
+    /// Checks that every item and structure named in a milestone condition exists.
+    pub(crate) fn validate_conditions(
+        &self,
+        item_manifest: &ItemManifest,
+        structure_manifest: &StructureManifest,
+    ) -> Result<(), ManifestError> {
+        for (&milestone, condition) in self.data_map() {
+            let (field, reference, known) = match *condition {
+                MilestoneCondition::Export { item, .. } => (
+                    "item",
+                    item.to_string(),
+                    item_manifest.data_map().contains_key(&item),
+                ),
+                MilestoneCondition::PlaceNextToLandmark { structure } => (
+                    "structure",
+                    structure.to_string(),
+                    structure_manifest.data_map().contains_key(&structure),
+                ),
+            };
+
+            if !known {
+                return Err(ManifestError::UnknownReference {
+                    entry: self.name(milestone).to_string(),
+                    field: field.to_string(),
+                    reference,
+                });
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Checks that every entry of `manifest` only requires milestones that exist.
+    ///
+    /// The `required_milestone` of each entry is read using the provided function.
+    pub(crate) fn validate_required_milestones<T, Data: Debug>(
+        &self,
+        manifest: &Manifest<T, Data>,
+        required_milestone: impl Fn(&Data) -> Option<Id<Milestone>>,
+    ) -> Result<(), ManifestError> {
+        for (&id, data) in manifest.data_map() {
+            let Some(milestone) = required_milestone(data) else { continue };
+
+            if !self.data_map().contains_key(&milestone) {
+                return Err(ManifestError::UnknownReference {
+                    entry: manifest.name(id).to_string(),
+                    field: "required_milestone".to_string(),
+                    reference: milestone.to_string(),
+                });
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// A requirement that must be met to complete a milestone.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MilestoneCondition {
+    /// At least `count` of `item` must be released by logistic buildings.
+    Export {
+        /// The item to export.
+        item: Id<Item>,
+        /// The total number of items needed.
+        count: u32,
+    },
+    /// The `structure` must be built next to any landmark.
+    PlaceNextToLandmark {
+        /// The structure to build.
+        structure: Id<Structure>,
+    },
+}
+
+/// The unprocessed equivalent of [`MilestoneCondition`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RawMilestoneCondition {
+    /// At least `count` of `item` must be released by logistic buildings.
+    Export {
+        /// The name of the item to export.
+        item: String,
+        /// The total number of items needed.
+        count: u32,
+    },
+    /// The `structure` must be built next to any landmark.
+    PlaceNextToLandmark {
+        /// The name of the structure to build.
+        structure: String,
+    },
+}
+
+impl From<RawMilestoneCondition> for MilestoneCondition {
+    fn from(raw: RawMilestoneCondition) -> Self {
+        match raw {
+            RawMilestoneCondition::Export { item, count } => MilestoneCondition::Export {
+                item: Id::from_name(item),
+                count,
+            },
+            RawMilestoneCondition::PlaceNextToLandmark { structure } => {
+                MilestoneCondition::PlaceNextToLandmark {
+                    structure: Id::from_name(structure),
+                }
+            }
+        }
+    }
+}
+
+/// The [`MilestoneManifest`] as seen in the manifest file.
+#[derive(Debug, Clone, Serialize, Deserialize, TypeUuid, PartialEq)]
+#[uuid = "3f0c8a52-6d4e-4b7a-9e21-5c8b1d7f2a94"]
+pub struct RawMilestoneManifest {
+    /// The condition needed to complete each milestone.
+    ///
+    /// These are serialized in order of their names, to keep the output stable.
+    #[serde(serialize_with = "serialize_sorted")]
+    pub milestones: HashMap<String, RawMilestoneCondition>,
+}
+
+impl IsRawManifest for RawMilestoneManifest {
+    const EXTENSION: &'static str = "milestone_manifest.json";
+
+    type Marker = Milestone;
+    type Data = MilestoneCondition;
+
+    fn process(&self) -> Result<Manifest<Self::Marker, Self::Data>, ManifestError> {
+        let mut manifest = Manifest::new();
+
+        for (raw_id, raw_condition) in self.milestones.clone() {
+            manifest.try_insert(raw_id, raw_condition.into())?;
+        }
+
+        Ok(manifest)
+    }
+}
+
+/// Tracks which milestones have been completed, and the progress made towards the rest.
+///
+/// This is part of the game state: it is loaded on startup and saved as progress is made, see [`MilestoneSaveConfig`].
+#[derive(Resource, Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(from = "SavedMilestones", into = "SavedMilestones")]
+pub(crate) struct Milestones {
+    /// The milestones that have been completed.
+    completed: HashSet<Id<Milestone>>,
+    /// The total number of each item that has been released by logistic buildings.
+    exported: HashMap<Id<Item>, u32>,
+    /// The structures that have been built next to a landmark.
+    placed_next_to_landmark: HashSet<Id<Structure>>,
+}
+
+impl Milestones {
+    /// Has the milestone been completed?
+    pub(crate) fn is_complete(&self, milestone: Id<Milestone>) -> bool {
+        self.completed.contains(&milestone)
+    }
+
+    /// Is content gated behind `required_milestone` available?
+    ///
+    /// Content without a required milestone is always unlocked.
+    pub(crate) fn is_unlocked(&self, required_milestone: Option<Id<Milestone>>) -> bool {
+        match required_milestone {
+            Some(milestone) => self.is_complete(milestone),
+            None => true,
+        }
+    }
+
+    /// Can the structure `structure_id` be built?
+    pub(crate) fn structure_unlocked(
+        &self,
+        structure_id: Id<Structure>,
+        structure_manifest: &StructureManifest,
+    ) -> bool {
+        self.is_unlocked(structure_manifest.get(structure_id).required_milestone)
+    }
+
+    /// Can the `active_recipe` be selected?
+    ///
+    /// Random recipes are only unlocked once every recipe that they could roll is unlocked.
+    pub(crate) fn recipe_unlocked(
+        &self,
+        active_recipe: &ActiveRecipe,
+        recipe_manifest: &RecipeManifest,
+    ) -> bool {
+        active_recipe
+            .possible_recipes()
+            .into_iter()
+            .all(|recipe_id| self.is_unlocked(recipe_manifest.get(recipe_id).required_milestone))
+    }
+
+    /// Marks the milestone as completed.
+    ///
+    /// Returns `true` if it had not already been completed.
+    pub(crate) fn complete(&mut self, milestone: Id<Milestone>) -> bool {
+        self.completed.insert(milestone)
+    }
+
+    /// Is the `condition` currently satisfied?
+    fn condition_met(&self, condition: &MilestoneCondition) -> bool {
+        match *condition {
+            MilestoneCondition::Export { item, count } => {
+                self.exported.get(&item).copied().unwrap_or_default() >= count
+            }
+            MilestoneCondition::PlaceNextToLandmark { structure } => {
+                self.placed_next_to_landmark.contains(&structure)
+            }
+        }
+    }
+}
+
+/// The format that [`Milestones`] are saved in.
+///
+/// JSON objects can only have string keys, so the export counts are stored as a list of pairs.
+/// Everything is sorted, so that saving the same progress always produces the same file.
+#[derive(Serialize, Deserialize)]
+struct SavedMilestones {
+    /// The milestones that have been completed.
+    completed: Vec<Id<Milestone>>,
+    /// The total number of each item that has been released by logistic buildings.
+    exported: Vec<(Id<Item>, u32)>,
+    /// The structures that have been built next to a landmark.
+    placed_next_to_landmark: Vec<Id<Structure>>,
+}
+
+impl From<SavedMilestones> for Milestones {
+    fn from(saved: SavedMilestones) -> Self {
+        Milestones {
+            completed: saved.completed.into_iter().collect(),
+            exported: saved.exported.into_iter().collect(),
+            placed_next_to_landmark: saved.placed_next_to_landmark.into_iter().collect(),
+        }
+    }
+}
+
+impl From<Milestones> for SavedMilestones {
+    fn from(milestones: Milestones) -> Self {
+        let mut completed: Vec<_> = milestones.completed.into_iter().collect();
+        completed.sort();
+        let mut exported: Vec<_> = milestones.exported.into_iter().collect();
+        exported.sort();
+        let mut placed_next_to_landmark: Vec<_> =
+            milestones.placed_next_to_landmark.into_iter().collect();
+        placed_next_to_landmark.sort();
+
+        SavedMilestones {
+            completed,
+            exported,
+            placed_next_to_landmark,
+        }
+    }
+}
+
+/// Controls where the [`Milestones`] are saved.
+///
+/// Insert this before the [`MilestonePlugin`] is added to change where progress is kept.
+#[derive(Resource, Debug, Clone, PartialEq, Eq)]
+pub(crate) struct MilestoneSaveConfig {
+    /// The path of the save file that the [`Milestones`] are loaded from and saved to.
+    ///
+    /// If this is [`None`], progress is neither loaded nor saved.
+    pub(crate) path: Option<PathBuf>,
+    /// The shortest time between two saves, so that steady progress does not write to disk every tick.
+    ///
+    /// Completing a milestone is always saved straight away.
+    pub(crate) interval: Duration,
+}
+
+impl MilestoneSaveConfig {
+    /// Progress is kept in memory only, and starts from scratch each time the app is run.
+    pub(crate) fn disabled() -> Self {
+        MilestoneSaveConfig {
+            path: None,
+            ..Default::default()
+        }
+    }
+}
+
+impl Default for MilestoneSaveConfig {
+    fn default() -> Self {
+        MilestoneSaveConfig {
+            path: Some(data_dir().join("saves").join("milestones.json")),
+            interval: Duration::from_secs(30),
+        }
+    }
+}
+
+/// The platform's per-user directory for this game's save data.
+///
+/// Falls back to the working directory if the platform's data directory cannot be determined.
+fn data_dir() -> PathBuf {
+    let base_dir = if cfg!(target_os = "windows") {
+        env::var_os("APPDATA").map(PathBuf::from)
+    } else if cfg!(target_os = "macos") {
+        env::var_os("HOME").map(|home| PathBuf::from(home).join("Library/Application Support"))
+    } else {
+        env::var_os("XDG_DATA_HOME")
+            .map(PathBuf::from)
+            .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".local/share")))
+    };
+
+    match base_dir {
+        Some(base_dir) => base_dir.join("emergence"),
+        None => PathBuf::new(),
+    }
+}
+
+/// Loads the milestone progress saved at `path`.
+///
+/// Starts from scratch if the file does not exist or cannot be parsed.
+pub(crate) fn load_milestones(path: &Path) -> Milestones {
+    let bytes = match fs::read(path) {
+        Ok(bytes) => bytes,
+        Err(_) => return Milestones::default(),
+    };
+
+    match serde_json::from_slice(&bytes) {
+        Ok(milestones) => milestones,
+        Err(json_error) => {
+            warn!(
+                "Could not parse milestones at {}: {json_error}. Starting from scratch.",
+                path.display()
+            );
+            Milestones::default()
+        }
+    }
+}
+
+/// Saves the milestone progress to `path`, so it can be restored with [`load_milestones`].
+pub(crate) fn save_milestones(milestones: &Milestones, path: &Path) -> io::Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    let bytes = serde_json::to_vec_pretty(milestones)?;
+    fs::write(path, bytes)
+}
+
+/// An event sent whenever a milestone is completed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct MilestoneUnlocked {
+    /// The milestone that was completed.
+    pub(crate) milestone: Id<Milestone>,
+}
+
+/// Tracks, completes and saves milestones.
+pub(crate) struct MilestonePlugin;
+
+impl Plugin for MilestonePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugin(ManifestPlugin::<RawMilestoneManifest>::new())
+            .init_resource::<Milestones>()
+            .init_resource::<MilestoneSaveConfig>()
+            .add_event::<MilestoneUnlocked>()
+            .add_startup_system(load_saved_milestones)
+            .add_systems(
+                (
+                    record_exports,
+                    record_structures_next_to_landmarks,
+                    complete_milestones,
+                )
+                    .chain()
+                    .in_set(SimulationSet)
+                    .in_schedule(CoreSchedule::FixedUpdate),
+            )
+            .add_system(autosave_milestones);
+    }
+}
+
+/// Restores the progress saved at [`MilestoneSaveConfig::path`].
+fn load_saved_milestones(config: Res<MilestoneSaveConfig>, mut milestones: ResMut<Milestones>) {
+    if let Some(path) = &config.path {
+        *milestones = load_milestones(path);
+    }
+}
+
+/// Counts the items released by logistic buildings.
+fn record_exports(
+    mut consumed_events: EventReader<ItemConsumed>,
+    releaser_query: Query<(), With<ReleasesItems>>,
+    mut milestones: ResMut<Milestones>,
+) {
+    // Progress alone does not change which content is unlocked
+    let milestones = milestones.bypass_change_detection();
+
+    for event in consumed_events.iter() {
+        if releaser_query.contains(event.structure) {
+            *milestones
+                .exported
+                .entry(event.item_count.item_id)
+                .or_default() += event.item_count.count;
+        }
+    }
+}
+
+/// Records newly built structures that are next to a landmark.
+fn record_structures_next_to_landmarks(
+    new_structure_query: Query<
+        (&Id<Structure>, &VoxelPos, &Facing, &Footprint),
+        (Added<Id<Structure>>, Without<Ghost>, Without<Landmark>),
+    >,
+    landmark_query: Query<(&VoxelPos, &Facing, &Footprint), With<Landmark>>,
+    mut milestones: ResMut<Milestones>,
+) {
+    if new_structure_query.is_empty() {
+        return;
+    }
+
+    let landmark_hexes: HashSet<hexx::Hex> = landmark_query
+        .iter()
+        .flat_map(|(&center, &facing, footprint)| footprint.normalized(facing, center))
+        .map(|voxel_pos| voxel_pos.hex)
+        .collect();
+
+    let milestones = milestones.bypass_change_detection();
+    for (&structure_id, &center, &facing, footprint) in new_structure_query.iter() {
+        let next_to_landmark = footprint
+            .normalized(facing, center)
+            .into_iter()
+            .flat_map(|voxel_pos| voxel_pos.hex.all_neighbors())
+            .any(|neighbor| landmark_hexes.contains(&neighbor));
+
+        if next_to_landmark {
+            milestones.placed_next_to_landmark.insert(structure_id);
+        }
+    }
+}
+
+/// Completes every milestone whose condition has been met, sending a [`MilestoneUnlocked`] event for each.
+fn complete_milestones(
+    milestone_manifest: Res<MilestoneManifest>,
+    mut milestones: ResMut<Milestones>,
+    mut unlocked_events: EventWriter<MilestoneUnlocked>,
+) {
+    let newly_completed: Vec<Id<Milestone>> = milestone_manifest
+        .data_map()
+        .iter()
+        .filter(|(&milestone, condition)| {
+            !milestones.is_complete(milestone) && milestones.condition_met(condition)
+        })
+        .map(|(&milestone, _)| milestone)
+        .collect();
+
+    for milestone in newly_completed {
+        milestones.complete(milestone);
+        unlocked_events.send(MilestoneUnlocked { milestone });
+    }
+}
+
+/// Saves the [`Milestones`] as soon as one is completed, and every [`MilestoneSaveConfig::interval`] while progress is made.
+fn autosave_milestones(
+    milestones: Res<Milestones>,
+    config: Res<MilestoneSaveConfig>,
+    time: Res<Time>,
+    mut unlocked_events: EventReader<MilestoneUnlocked>,
+    mut since_last_save: Local<Duration>,
+    mut last_saved: Local<Milestones>,
+) {
+    let Some(path) = &config.path else { return };
+
+    *since_last_save += time.delta();
+    let just_unlocked = unlocked_events.iter().count() > 0;
+    if !just_unlocked && *since_last_save < config.interval {
+        return;
+    }
+
+    // Nothing to save, so avoid writing a file for games that have not made any progress
+    if *milestones == *last_saved {
+        return;
+    }
+
+    *since_last_save = Duration::ZERO;
+    match save_milestones(&milestones, path) {
+        Ok(()) => *last_saved = milestones.clone(),
+        Err(io_error) => warn!(
+            "Could not save milestones to {}: {io_error}",
+            path.display()
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{asset_management::manifest::ManifestBuilder, items::ItemCount};
+
+    #[test]
+    fn exporting_enough_items_unlocks_milestones() {
+        let mut app = App::new();
+        let milestone = Id::from_name("trade_routes".to_string());
+        let item_id = Id::from_name("acacia_leaf".to_string());
+
+        let milestone_manifest: MilestoneManifest = ManifestBuilder::new()
+            .add(
+                "trade_routes",
+                MilestoneCondition::Export {
+                    item: item_id,
+                    count: 5,
+                },
+            )
+            .build();
+
+        app.insert_resource(milestone_manifest)
+            .init_resource::<Milestones>()
+            .add_event::<ItemConsumed>()
+            .add_event::<MilestoneUnlocked>()
+            .add_systems((record_exports, complete_milestones).chain());
+
+        let releaser = app.world.spawn(ReleasesItems::default()).id();
+        let crafter = app.world.spawn_empty().id();
+
+        let send_consumed = |app: &mut App, structure: Entity, count: u32| {
+            app.world.send_event(ItemConsumed {
+                structure,
+                item_count: ItemCount::new(item_id, count),
+            });
+            app.update();
+        };
+
+        send_consumed(&mut app, releaser, 3);
+        // Items consumed by ordinary crafting are not exported
+        send_consumed(&mut app, crafter, 10);
+        assert!(!app.world.resource::<Milestones>().is_complete(milestone));
+
+        send_consumed(&mut app, releaser, 2);
+        assert!(app.world.resource::<Milestones>().is_complete(milestone));
+
+        let unlocked_events = app.world.resource::<Events<MilestoneUnlocked>>();
+        let mut reader = unlocked_events.get_reader();
+        assert_eq!(
+            reader.iter(unlocked_events).copied().collect::<Vec<_>>(),
+            vec![MilestoneUnlocked { milestone }]
+        );
+    }
+
+    #[test]
+    fn milestones_survive_saving_and_loading() {
+        let mut milestones = Milestones::default();
+        milestones.complete(Id::from_name("trade_routes".to_string()));
+        milestones
+            .exported
+            .insert(Id::from_name("acacia_leaf".to_string()), 7);
+        milestones
+            .placed_next_to_landmark
+            .insert(Id::from_name("acacia".to_string()));
+
+        let path = std::env::temp_dir().join(format!(
+            "emergence_milestones_{}_round_trip.json",
+            std::process::id()
+        ));
+        save_milestones(&milestones, &path).unwrap();
+        let loaded = load_milestones(&path);
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(loaded, milestones);
+    }
+
+    #[test]
+    fn saved_progress_is_loaded_from_the_configured_path_on_startup() {
+        let mut milestones = Milestones::default();
+        milestones.complete(Id::from_name("trade_routes".to_string()));
+
+        let path = std::env::temp_dir().join(format!(
+            "emergence_milestones_{}_startup.json",
+            std::process::id()
+        ));
+        save_milestones(&milestones, &path).unwrap();
+
+        let startup_app = |config: MilestoneSaveConfig| {
+            let mut app = App::new();
+            app.insert_resource(config)
+                .init_resource::<Milestones>()
+                .add_startup_system(load_saved_milestones);
+            app.update();
+            app
+        };
+
+        let app = startup_app(MilestoneSaveConfig {
+            path: Some(path.clone()),
+            ..Default::default()
+        });
+        assert_eq!(*app.world.resource::<Milestones>(), milestones);
+
+        let app = startup_app(MilestoneSaveConfig::disabled());
+        assert_eq!(*app.world.resource::<Milestones>(), Milestones::default());
+
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn required_milestones_must_exist() {
+        let milestone_manifest: MilestoneManifest = ManifestBuilder::new()
+            .add(
+                "first_harvest",
+                MilestoneCondition::PlaceNextToLandmark {
+                    structure: Id::from_name("acacia".to_string()),
+                },
+            )
+            .build();
+
+        let requirements: Manifest<Structure, Option<Id<Milestone>>> = ManifestBuilder::new()
+            .add("acacia", None)
+            .add("leuco", Some(Id::from_name("first_harvest".to_string())))
+            .build();
+        assert_eq!(
+            milestone_manifest.validate_required_milestones(&requirements, |&required| required),
+            Ok(())
+        );
+
+        let requirements: Manifest<Structure, Option<Id<Milestone>>> = ManifestBuilder::new()
+            .add("leuco", Some(Id::from_name("first_hravest".to_string())))
+            .build();
+        assert!(matches!(
+            milestone_manifest.validate_required_milestones(&requirements, |&required| required),
+            Err(ManifestError::UnknownReference { entry, field, .. })
+                if entry == "leuco" && field == "required_milestone"
+        ));
+    }
+}
@@ -2,6 +2,7 @@
 //!
 //! All plugins in this module should work without rendering.
 
+use crate::asset_management::manifest::load_state::ManifestLoadState;
 use crate::asset_management::AssetState;
 use crate::construction::ConstructionPlugin;
 use crate::crafting::CraftingPlugin;
@@ -9,7 +10,10 @@ use crate::geometry::sync_rotation_to_facing;
 use crate::light::LightPlugin;
 use crate::organisms::OrganismPlugin;
 use crate::signals::SignalsPlugin;
+use crate::simulation::alerts::AlertPlugin;
+use crate::simulation::milestones::MilestonePlugin;
 use crate::simulation::rng::GlobalRng;
+use crate::simulation::speed::{SimulationSpeed, SimulationSpeedPlugin};
 use crate::simulation::time::TemporalPlugin;
 use crate::simulation::weather::WeatherPlugin;
 use crate::structures::StructuresPlugin;
@@ -21,7 +25,10 @@ use bevy::core::FrameCount;
 use bevy::ecs::schedule::{LogLevel, ScheduleBuildSettings};
 use bevy::prelude::*;
 
+pub mod alerts;
+pub mod milestones;
 pub mod rng;
+pub mod speed;
 pub mod time;
 pub mod weather;
 
@@ -39,7 +46,7 @@ impl Plugin for SimulationPlugin {
             .edit_schedule(CoreSchedule::FixedUpdate, |schedule| {
                 schedule.configure_set(
                     SimulationSet
-                        .run_if(in_state(PauseState::Playing))
+                        .run_if(in_state(ManifestLoadState::Ready))
                         .run_if(in_state(AssetState::FullyLoaded))
                         .run_if(world_gen_ready)
                         .run_if(max_ticks_not_reached),
@@ -65,25 +72,19 @@ impl Plugin for SimulationPlugin {
             .add_plugin(TemporalPlugin)
             .add_plugin(LightPlugin)
             .add_plugin(WaterPlugin)
-            .add_plugin(WeatherPlugin);
+            .add_plugin(WeatherPlugin)
+            .add_plugin(AlertPlugin)
+            .add_plugin(MilestonePlugin)
+            .add_plugin(SimulationSpeedPlugin);
     }
 }
 
-/// Controls whether or not the game is paused.
-#[derive(States, Debug, PartialEq, Eq, Hash, Clone, Copy, Default)]
-enum PauseState {
-    /// Game logic is running.
-    #[default]
-    Playing,
-    /// Game logic is stopped.
-    Paused,
-}
-
 /// Simulation systems.
 ///
 /// These:
 /// - are run in [`CoreSchedule::FixedUpdate`]
-/// - only run in [`PauseState::Playing`]
+/// - only run while the [`SimulationSpeed`] is not [`SimulationSpeed::Paused`]
+/// - only run in [`ManifestLoadState::Ready`], so they never see missing or invalid manifests
 /// - only run in [`AssetState::FullyLoaded`]
 #[derive(SystemSet, PartialEq, Eq, Hash, Debug, Clone)]
 pub(crate) struct SimulationSet;
@@ -95,7 +96,9 @@ pub(crate) struct SimulationSet;
 struct TicksThisFrame {
     /// The number of ticks that have passed this frame.
     current: u8,
-    /// The maximum number of ticks that can pass in a frame.
+    /// The maximum number of ticks that can pass in a frame at [`SimulationSpeed::Normal`].
+    ///
+    /// Faster speeds scale this limit by their [`SimulationSpeed::multiplier`].
     max: u8,
 }
 
@@ -109,12 +112,17 @@ fn update_ticks_this_frame(mut ticks: ResMut<TicksThisFrame>, frame_count: Res<F
 }
 
 /// Stops the simulation from trying to simulate an ever-increasing number of ticks per frame if it falls behind.
-fn max_ticks_not_reached(frame_count: Res<FrameCount>, ticks: Res<TicksThisFrame>) -> bool {
+fn max_ticks_not_reached(
+    frame_count: Res<FrameCount>,
+    ticks: Res<TicksThisFrame>,
+    simulation_speed: Res<SimulationSpeed>,
+) -> bool {
     if frame_count.is_changed() {
         return true;
     }
 
-    ticks.current < ticks.max
+    let max_ticks = ticks.max as u32 * simulation_speed.multiplier().max(1);
+    (ticks.current as u32) < max_ticks
 }
 
 /// Ensures that simulation systems do not run until world gen is ready for them.
@@ -0,0 +1,215 @@
+//! Lets the player pause the simulation, or run it faster than real time.
+//!
+//! Faster speeds never change the length of a simulation tick:
+//! instead, more ticks are run each frame, so the simulation plays out identically at every speed.
+
+use bevy::{prelude::*, utils::Duration};
+use leafwing_input_manager::prelude::ActionState;
+
+use crate::player_interaction::PlayerAction;
+
+use super::SimulationSet;
+
+/// How quickly the simulation is running, relative to real time.
+#[derive(Resource, Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SimulationSpeed {
+    /// No simulation ticks are run, but the player can still interact with the world.
+    Paused,
+    /// One tick of [`FixedTime::period`] for each period of real time.
+    #[default]
+    Normal,
+    /// Twice as many ticks as [`SimulationSpeed::Normal`].
+    Fast2x,
+    /// Four times as many ticks as [`SimulationSpeed::Normal`].
+    Fast4x,
+}
+
+impl SimulationSpeed {
+    /// The number of simulation ticks run for each tick's worth of real time.
+    pub fn multiplier(&self) -> u32 {
+        match self {
+            SimulationSpeed::Paused => 0,
+            SimulationSpeed::Normal => 1,
+            SimulationSpeed::Fast2x => 2,
+            SimulationSpeed::Fast4x => 4,
+        }
+    }
+
+    /// Is the simulation paused?
+    pub fn is_paused(&self) -> bool {
+        *self == SimulationSpeed::Paused
+    }
+
+    /// The next faster speed, if any.
+    pub fn faster(&self) -> Self {
+        match self {
+            SimulationSpeed::Paused => SimulationSpeed::Normal,
+            SimulationSpeed::Normal => SimulationSpeed::Fast2x,
+            SimulationSpeed::Fast2x | SimulationSpeed::Fast4x => SimulationSpeed::Fast4x,
+        }
+    }
+
+    /// The next slower speed, if any.
+    pub fn slower(&self) -> Self {
+        match self {
+            SimulationSpeed::Fast4x => SimulationSpeed::Fast2x,
+            SimulationSpeed::Fast2x => SimulationSpeed::Normal,
+            SimulationSpeed::Normal | SimulationSpeed::Paused => SimulationSpeed::Paused,
+        }
+    }
+}
+
+/// Controls the [`SimulationSpeed`].
+pub(super) struct SimulationSpeedPlugin;
+
+impl Plugin for SimulationSpeedPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<SimulationSpeed>()
+            .add_system(change_simulation_speed)
+            // Real time has been measured in `CoreSet::First`,
+            // and the fixed update loop has not yet started
+            .add_system(accelerate_fixed_time.in_base_set(CoreSet::PreUpdate))
+            .edit_schedule(CoreSchedule::FixedUpdate, |schedule| {
+                schedule.configure_set(SimulationSet.run_if(simulation_running));
+            });
+    }
+}
+
+/// Is the simulation running?
+pub(crate) fn simulation_running(simulation_speed: Res<SimulationSpeed>) -> bool {
+    !simulation_speed.is_paused()
+}
+
+/// Pauses, unpauses and changes the speed of the simulation when prompted by player input.
+///
+/// Unpausing returns to the speed that the simulation was running at before it was paused.
+fn change_simulation_speed(
+    mut simulation_speed: ResMut<SimulationSpeed>,
+    player_actions: Res<ActionState<PlayerAction>>,
+    mut speed_before_pause: Local<SimulationSpeed>,
+) {
+    if player_actions.just_pressed(PlayerAction::TogglePause) {
+        if simulation_speed.is_paused() {
+            *simulation_speed = *speed_before_pause;
+        } else {
+            *speed_before_pause = *simulation_speed;
+            *simulation_speed = SimulationSpeed::Paused;
+        }
+    }
+
+    if player_actions.just_pressed(PlayerAction::SpeedUpSimulation) {
+        let faster = simulation_speed.faster();
+        simulation_speed.set_if_neq(faster);
+    }
+
+    if player_actions.just_pressed(PlayerAction::SlowDownSimulation) {
+        let slower = simulation_speed.slower();
+        simulation_speed.set_if_neq(slower);
+    }
+}
+
+/// Feeds extra time into the [`FixedTime`] accumulator when running faster than real time.
+///
+/// The fixed update loop then runs more ticks this frame, each of exactly [`FixedTime::period`].
+fn accelerate_fixed_time(
+    simulation_speed: Res<SimulationSpeed>,
+    time: Res<Time>,
+    mut fixed_time: ResMut<FixedTime>,
+) {
+    let extra_ticks = simulation_speed.multiplier().saturating_sub(1);
+    if extra_ticks > 0 {
+        let extra_time: Duration = time.delta() * extra_ticks;
+        fixed_time.tick(extra_time);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::simulation::time::{advance_in_game_time, InGameTime};
+    use bevy::{
+        time::{TimePlugin, TimeUpdateStrategy},
+        utils::Instant,
+    };
+
+    /// Counts the simulation ticks that have been run.
+    #[derive(Resource, Debug, Default, Clone, Copy, PartialEq, Eq)]
+    struct TicksRun(u32);
+
+    /// Increments [`TicksRun`].
+    fn count_ticks(mut ticks_run: ResMut<TicksRun>) {
+        ticks_run.0 += 1;
+    }
+
+    /// Creates an app with a simple simulation, where each frame takes one tick's worth of real time.
+    ///
+    /// The first frame is run while paused, so that no ticks depend on how the clock starts up.
+    fn simulation_app(simulation_speed: SimulationSpeed) -> App {
+        let fixed_time = FixedTime::new_from_secs(1.0 / 30.);
+        let mut app = App::new();
+
+        app.add_plugin(TimePlugin)
+            .insert_resource(TimeUpdateStrategy::ManualInstant(Instant::now()))
+            .insert_resource(fixed_time)
+            .insert_resource(SimulationSpeed::Paused)
+            .init_resource::<InGameTime>()
+            .init_resource::<TicksRun>()
+            .add_system(accelerate_fixed_time.in_base_set(CoreSet::PreUpdate))
+            .edit_schedule(CoreSchedule::FixedUpdate, |schedule| {
+                schedule.configure_set(SimulationSet.run_if(simulation_running));
+            })
+            .add_systems(
+                (advance_in_game_time, count_ticks)
+                    .chain()
+                    .in_set(SimulationSet)
+                    .in_schedule(CoreSchedule::FixedUpdate),
+            );
+
+        run_frame(&mut app);
+        app.insert_resource(simulation_speed);
+        app
+    }
+
+    /// Advances the clock by one tick's worth of real time, then runs a single frame.
+    ///
+    /// `TimeUpdateStrategy::ManualDuration` measures its duration from the current instant,
+    /// so the clock is advanced by hand to keep each frame exactly one tick long.
+    fn run_frame(app: &mut App) {
+        let period = app.world.resource::<FixedTime>().period;
+        if let TimeUpdateStrategy::ManualInstant(instant) =
+            &mut *app.world.resource_mut::<TimeUpdateStrategy>()
+        {
+            *instant += period;
+        }
+
+        app.update();
+    }
+
+    /// Runs `frames` frames of the app, returning the end state of the simulation.
+    fn run_frames(mut app: App, frames: u32) -> (TicksRun, InGameTime) {
+        for _ in 0..frames {
+            run_frame(&mut app);
+        }
+
+        (
+            *app.world.resource::<TicksRun>(),
+            app.world.resource::<InGameTime>().clone(),
+        )
+    }
+
+    #[test]
+    fn fast_forwarding_matches_normal_speed() {
+        let normal = run_frames(simulation_app(SimulationSpeed::Normal), 100);
+        let fast = run_frames(simulation_app(SimulationSpeed::Fast4x), 25);
+
+        assert_eq!(normal.0, TicksRun(100));
+        assert_eq!(normal, fast);
+    }
+
+    #[test]
+    fn paused_simulations_do_not_advance() {
+        let paused = run_frames(simulation_app(SimulationSpeed::Paused), 30);
+
+        assert_eq!(paused, (TicksRun(0), InGameTime::default()));
+    }
+}
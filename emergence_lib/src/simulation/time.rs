@@ -7,25 +7,27 @@ use bevy::prelude::*;
 use derive_more::{Add, AddAssign, Display, Sub, SubAssign};
 use leafwing_abilities::pool::MaxPoolLessThanZero;
 use leafwing_abilities::prelude::Pool;
-use leafwing_input_manager::prelude::ActionState;
 use serde::{Deserialize, Serialize};
 
+use crate::asset_management::manifest::Id;
+use crate::geometry::{MapGeometry, VoxelPos};
 use crate::graphics::lighting::{Moon, Sun};
 use crate::organisms::lifecycle::Lifecycle;
-use crate::player_interaction::PlayerAction;
+use crate::structures::structure_manifest::{Structure, StructureManifest};
+use crate::terrain::fertility::SoilFertility;
 
-use super::{PauseState, SimulationSet};
+use super::SimulationSet;
 
 /// Introduces temporal variation into the environment.
 pub(crate) struct TemporalPlugin;
 
 impl Plugin for TemporalPlugin {
     fn build(&self, app: &mut App) {
-        app.add_state::<PauseState>()
-            .insert_resource(FixedTime::new_from_secs(1.0 / 30.))
+        app.insert_resource(FixedTime::new_from_secs(1.0 / 30.))
             .add_systems(
                 (
                     advance_in_game_time,
+                    advance_season,
                     move_celestial_bodies,
                     record_elapsed_time_for_lifecycles,
                 )
@@ -33,18 +35,20 @@ impl Plugin for TemporalPlugin {
                     .in_set(SimulationSet)
                     .in_schedule(CoreSchedule::FixedUpdate),
             )
-            .add_system(pause_game)
-            .init_resource::<InGameTime>();
+            .init_resource::<InGameTime>()
+            .init_resource::<Season>();
     }
 }
 
 /// Stores the in game time.
-#[derive(Resource)]
+#[derive(Resource, Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct InGameTime {
     /// How much time has elapsed, in units of in-game days.
     elapsed_time: Days,
     /// The number of wall-clock seconds that should elapse per complete in-game day.
     seconds_per_day: f32,
+    /// The number of in-game days that each [`Season`] lasts.
+    days_per_season: Days,
 }
 
 /// A duration of time, in in-game days.
@@ -108,6 +112,47 @@ impl TimeOfDay {
     }
 }
 
+/// The current season of the year.
+///
+/// Seasons advance in order, each lasting for [`InGameTime::days_per_season`].
+#[derive(
+    Resource, Debug, Display, Default, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize,
+)]
+pub enum Season {
+    /// The year begins in spring.
+    #[default]
+    Spring,
+    /// The warmest season.
+    Summer,
+    /// The season of harvest.
+    Autumn,
+    /// The coldest season.
+    Winter,
+}
+
+impl Season {
+    /// The season that follows this one.
+    pub fn next(&self) -> Self {
+        match self {
+            Season::Spring => Season::Summer,
+            Season::Summer => Season::Autumn,
+            Season::Autumn => Season::Winter,
+            Season::Winter => Season::Spring,
+        }
+    }
+
+    /// Returns the season for the provided total elapsed time, given the length of each season.
+    pub fn from_elapsed_time(elapsed_time: Days, days_per_season: Days) -> Self {
+        let n_seasons_elapsed = (elapsed_time.0 / days_per_season.0).floor() as u64;
+        match n_seasons_elapsed % 4 {
+            0 => Season::Spring,
+            1 => Season::Summer,
+            2 => Season::Autumn,
+            _ => Season::Winter,
+        }
+    }
+}
+
 impl InGameTime {
     /// How many days have elapsed total?
     pub fn elapsed_days(&self) -> f32 {
@@ -141,6 +186,27 @@ impl InGameTime {
     pub fn seconds_per_day(&self) -> f32 {
         self.seconds_per_day
     }
+
+    /// Returns the configured number of days that each [`Season`] lasts.
+    pub fn days_per_season(&self) -> Days {
+        self.days_per_season
+    }
+
+    /// Sets the number of days that each [`Season`] lasts.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `days_per_season` is not strictly positive.
+    pub fn set_days_per_season(&mut self, days_per_season: Days) {
+        assert!(days_per_season > Days::ZERO);
+
+        self.days_per_season = days_per_season;
+    }
+
+    /// Which season is it?
+    pub fn season(&self) -> Season {
+        Season::from_elapsed_time(self.elapsed_time, self.days_per_season)
+    }
 }
 
 impl Display for InGameTime {
@@ -160,6 +226,7 @@ impl Default for InGameTime {
         InGameTime {
             elapsed_time: Days(0.0),
             seconds_per_day: 300.,
+            days_per_season: Days(3.0),
         }
     }
 }
@@ -170,6 +237,11 @@ pub fn advance_in_game_time(time: Res<FixedTime>, mut in_game_time: ResMut<InGam
     in_game_time.elapsed_time += delta;
 }
 
+/// Updates the current [`Season`] to match the in-game time.
+fn advance_season(in_game_time: Res<InGameTime>, mut season: ResMut<Season>) {
+    season.set_if_neq(in_game_time.season());
+}
+
 /// Moves the sun and moon based on the in-game time
 fn move_celestial_bodies(
     mut sun_query: Query<&mut Visibility, (With<Sun>, Without<Moon>)>,
@@ -191,20 +263,6 @@ fn move_celestial_bodies(
     }
 }
 
-/// Pauses and unpauses the game when prompted by player input
-fn pause_game(
-    current_pause_state: Res<State<PauseState>>,
-    mut next_pause_state: ResMut<NextState<PauseState>>,
-    player_actions: Res<ActionState<PlayerAction>>,
-) {
-    if player_actions.just_pressed(PlayerAction::TogglePause) {
-        next_pause_state.set(match current_pause_state.0 {
-            PauseState::Paused => PauseState::Playing,
-            PauseState::Playing => PauseState::Paused,
-        });
-    }
-}
-
 /// A [`Pool`] of [`Days`], which builds up and will eventually be filled (at which point some event will occur).
 #[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
 pub struct TimePool {
@@ -279,13 +337,178 @@ impl Pool for TimePool {
 }
 
 /// Advances life cycles accorded to elapsed in-game time
+///
+/// Structures grow at a rate determined by the current [`Season`] and the [`SoilFertility`] of the tile they are on.
 fn record_elapsed_time_for_lifecycles(
-    mut query: Query<&mut Lifecycle>,
+    mut query: Query<(&mut Lifecycle, Option<&Id<Structure>>, Option<&VoxelPos>)>,
+    terrain_query: Query<&SoilFertility>,
     in_game_time: Res<InGameTime>,
     fixed_time: Res<FixedTime>,
+    season: Res<Season>,
+    structure_manifest: Res<StructureManifest>,
+    map_geometry: Res<MapGeometry>,
 ) {
-    for mut lifecycle in query.iter_mut() {
+    for (mut lifecycle, maybe_structure_id, maybe_voxel_pos) in query.iter_mut() {
         let delta_days = Days(fixed_time.period.as_secs_f32() / in_game_time.seconds_per_day);
-        lifecycle.record_elapsed_time(delta_days);
+
+        let growth_multiplier = match maybe_structure_id {
+            Some(&structure_id) => {
+                let fertility = maybe_voxel_pos
+                    .and_then(|voxel_pos| map_geometry.get_terrain(voxel_pos.hex).ok())
+                    .and_then(|terrain_entity| terrain_query.get(terrain_entity).ok())
+                    .map_or(SoilFertility::BASELINE, SoilFertility::value);
+
+                structure_manifest
+                    .seasonal_multipliers(structure_id, *season)
+                    .growth
+                    * fertility
+            }
+            None => 1.0,
+        };
+
+        if growth_multiplier > 0. {
+            lifecycle.record_elapsed_time(delta_days * growth_multiplier);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        asset_management::manifest::Manifest,
+        organisms::{lifecycle::LifePath, OrganismId, SeasonalModifiers, SeasonalMultipliers},
+        structures::structure_manifest::StructureData,
+    };
+    use hexx::Hex;
+
+    #[test]
+    fn seasons_cycle_in_order() {
+        let days_per_season = Days(2.);
+
+        assert_eq!(
+            Season::from_elapsed_time(Days(0.), days_per_season),
+            Season::Spring
+        );
+        assert_eq!(
+            Season::from_elapsed_time(Days(2.5), days_per_season),
+            Season::Summer
+        );
+        assert_eq!(
+            Season::from_elapsed_time(Days(7.9), days_per_season),
+            Season::Winter
+        );
+        assert_eq!(
+            Season::from_elapsed_time(Days(8.), days_per_season),
+            Season::Spring
+        );
+        assert_eq!(Season::Winter.next(), Season::Spring);
+    }
+
+    #[test]
+    fn winter_dormancy_halts_growth_until_spring() {
+        let mut structure_data = StructureData::organism("sapling");
+        structure_data
+            .organism_variety
+            .as_mut()
+            .unwrap()
+            .seasonal_modifiers = SeasonalModifiers::from_iter([(
+            Season::Winter,
+            SeasonalMultipliers {
+                growth: 0.0,
+                ..SeasonalMultipliers::NEUTRAL
+            },
+        )]);
+
+        let mut structure_manifest: StructureManifest = Manifest::new();
+        structure_manifest.insert("sapling".to_string(), structure_data);
+
+        let mut app = App::new();
+        let map_geometry = MapGeometry::new(&mut app.world, 1);
+        app.insert_resource(map_geometry)
+            .insert_resource(FixedTime::new_from_secs(1.0 / 30.))
+            .init_resource::<InGameTime>()
+            .insert_resource(Season::Winter)
+            .insert_resource(structure_manifest)
+            .add_system(record_elapsed_time_for_lifecycles);
+
+        let initial_lifecycle = Lifecycle::new(vec![LifePath {
+            new_form: OrganismId::Structure(Id::from_name("tree".to_string())),
+            energy_required: None,
+            time_required: Some(TimePool::simple(1.)),
+        }]);
+
+        let entity = app
+            .world
+            .spawn((
+                Id::<Structure>::from_name("sapling".to_string()),
+                initial_lifecycle.clone(),
+            ))
+            .id();
+
+        for _ in 0..10 {
+            app.update();
+        }
+
+        let lifecycle = app.world.get::<Lifecycle>(entity).unwrap();
+        assert_eq!(*lifecycle, initial_lifecycle);
+
+        *app.world.resource_mut::<Season>() = Season::Spring;
+        app.update();
+
+        let lifecycle = app.world.get::<Lifecycle>(entity).unwrap();
+        assert_ne!(*lifecycle, initial_lifecycle);
+    }
+
+    #[test]
+    fn organisms_grow_faster_in_fertile_soil() {
+        let mut structure_manifest: StructureManifest = Manifest::new();
+        structure_manifest.insert("sapling".to_string(), StructureData::organism("sapling"));
+
+        let mut app = App::new();
+        let map_geometry = MapGeometry::new(&mut app.world, 1);
+
+        let mut fertile = SoilFertility::default();
+        fertile.enrich(1.);
+        let mut poor = SoilFertility::default();
+        poor.deplete(0.5);
+
+        let mut spawn_sapling = |hex: Hex, soil_fertility: SoilFertility| {
+            let terrain_entity = map_geometry.get_terrain(hex).unwrap();
+            app.world.entity_mut(terrain_entity).insert(soil_fertility);
+
+            app.world
+                .spawn((
+                    Id::<Structure>::from_name("sapling".to_string()),
+                    map_geometry.on_top_of_terrain(hex),
+                    Lifecycle::new(vec![LifePath {
+                        new_form: OrganismId::Structure(Id::from_name("tree".to_string())),
+                        energy_required: None,
+                        time_required: Some(TimePool::simple(1000.)),
+                    }]),
+                ))
+                .id()
+        };
+        let fertile_sapling = spawn_sapling(Hex::new(1, 0), fertile);
+        let poor_sapling = spawn_sapling(Hex::new(-1, 0), poor);
+
+        app.insert_resource(map_geometry)
+            .insert_resource(FixedTime::new_from_secs(1.0 / 30.))
+            .init_resource::<InGameTime>()
+            .init_resource::<Season>()
+            .insert_resource(structure_manifest)
+            .add_system(record_elapsed_time_for_lifecycles);
+
+        for _ in 0..10 {
+            app.update();
+        }
+
+        let growth = |entity: Entity| {
+            let lifecycle = app.world.get::<Lifecycle>(entity).unwrap();
+            lifecycle.life_paths()[0].time_required.unwrap().current().0
+        };
+
+        assert!(growth(poor_sapling) > 0.);
+        assert!(growth(fertile_sapling) > growth(poor_sapling) * 3.);
     }
 }
@@ -0,0 +1,61 @@
+//! Warning beacons mark dangerous areas, so that units route around them.
+//!
+//! Beacons are defined as a custom structure kind, rather than a [`StructureKind`](super::structure_manifest::StructureKind) variant,
+//! and serve as an example of how to extend structures through the [`StructureKindRegistry`](super::custom_kinds::StructureKindRegistry).
+
+use bevy::{ecs::system::EntityCommands, prelude::*};
+use serde::Deserialize;
+
+use crate::signals::{Hazard, SignalStrength};
+
+use super::custom_kinds::{Manifests, StructureKindExtension};
+
+/// The name of the custom structure kind used for warning beacons in the manifest.
+pub(crate) const BEACON_KIND: &str = "beacon";
+
+/// The [`StructureKindExtension`] for warning beacons, which repel units by marking their tile as a [`Hazard`].
+pub(crate) struct WarningBeacon;
+
+impl WarningBeacon {
+    /// The strength of the signal emitted by beacons that do not specify one.
+    const DEFAULT_STRENGTH: f32 = 10.;
+}
+
+/// The data for a warning beacon, as seen in the manifest.
+#[derive(Debug, Deserialize)]
+struct RawWarningBeacon {
+    /// How strongly units are repelled.
+    #[serde(default = "default_strength")]
+    strength: f32,
+}
+
+/// The strength used when a manifest omits it.
+fn default_strength() -> f32 {
+    WarningBeacon::DEFAULT_STRENGTH
+}
+
+impl StructureKindExtension for WarningBeacon {
+    fn insert_components(
+        &self,
+        entity: &mut EntityCommands,
+        data: &serde_json::Value,
+        _manifests: &Manifests,
+    ) {
+        let strength = match data {
+            serde_json::Value::Null => WarningBeacon::DEFAULT_STRENGTH,
+            _ => {
+                match serde_json::from_value::<RawWarningBeacon>(data.clone()) {
+                    Ok(raw_beacon) => raw_beacon.strength,
+                    Err(error) => {
+                        warn!("Invalid warning beacon data {data}: {error}. Using the default strength.");
+                        WarningBeacon::DEFAULT_STRENGTH
+                    }
+                }
+            }
+        };
+
+        entity.insert(Hazard {
+            strength: SignalStrength::new(strength),
+        });
+    }
+}
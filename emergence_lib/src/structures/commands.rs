@@ -1,29 +1,59 @@
 //! Methods to use [`Commands`] to manipulate structures.
 
-use bevy::{ecs::system::Command, prelude::*};
+use bevy::{
+    ecs::system::{Command, CommandQueue},
+    prelude::*,
+};
+use hexx::Hex;
+use itertools::Itertools;
 use leafwing_abilities::prelude::Pool;
 
 use crate::{
     asset_management::manifest::Id,
-    construction::ghosts::{GhostHandles, GhostKind, GhostStructureBundle, StructurePreviewBundle},
+    construction::{
+        demolition::Salvage,
+        ghosts::{
+            ConstructionPhase, GhostHandles, GhostKind, GhostStructureBundle,
+            StructurePreviewBundle,
+        },
+        harvest::HarvestsMaterials,
+        zoning::Obstruction,
+    },
     crafting::{
+        flow::{FlowStats, ThroughputTracker},
         inventories::{InputInventory, OutputInventory, StorageInventory},
-        recipe::RecipeManifest,
+        item_tags::ItemTag,
+        recipe::{ActiveRecipe, RecipeEffect, RecipeManifest},
         CraftingBundle,
     },
     geometry::{Facing, MapGeometry, VoxelPos},
     graphics::InheritedMaterial,
-    items::{inventory::Inventory, item_manifest::ItemManifest},
-    organisms::{energy::StartingEnergy, OrganismBundle},
+    items::{
+        errors::AddOneItemError, inventory::Inventory, item_manifest::ItemManifest, ItemCount,
+    },
+    litter::{Litter, LitterCommandsExt},
+    organisms::{
+        energy::StartingEnergy, life_stages::Remains, pollination::PollinationState, OrganismBundle,
+    },
     player_interaction::clipboard::ClipboardData,
     signals::Emitter,
+    simulation::time::Days,
+    units::age::Age,
+    water::{WaterConfig, WaterDepth, WaterVolume},
 };
 
 use super::{
-    logistic_buildings::{AbsorbsItems, ReleasesItems},
+    composters::Composts,
+    custom_kinds::{Manifests, StructureKindRegistry},
+    incinerators::{Incinerates, INCINERATOR_SLOT_COUNT},
+    logistic_buildings::{AbsorbsItems, LogisticStatus, PumpsWater, ReleasesItems},
+    nests::Nest,
+    placement_rules::PlacementRules,
+    request_posts::RequestPost,
+    storage_groups::MergesWithNeighbors,
     structure_assets::StructureHandles,
     structure_manifest::{Structure, StructureKind, StructureManifest},
-    Landmark, StructureBundle,
+    Footprint, Landmark, StructureBundle,
 };
 
 /// An extension trait for [`Commands`] for working with structures.
@@ -38,11 +68,35 @@ pub(crate) trait StructureCommandsExt {
         starting_energy: StartingEnergy,
     );
 
+    /// Spawns a structure defined by `data` at `voxel_pos` as part of world generation.
+    ///
+    /// Unlike [`StructureCommandsExt::spawn_structure`], storage structures are filled with their manifest-defined starting inventory.
+    /// Has no effect if the tile position is already occupied by an existing structure.
+    fn generate_structure(
+        &mut self,
+        voxel_pos: VoxelPos,
+        data: ClipboardData,
+        starting_energy: StartingEnergy,
+    );
+
     /// Despawns any structure at the provided `voxel_pos`.
     ///
     /// Has no effect if the tile position is already empty.
     fn despawn_structure(&mut self, voxel_pos: VoxelPos);
 
+    /// Kills the organism structure at the provided `voxel_pos`, leaving its [`Remains`] behind.
+    ///
+    /// Water held in its inventories soaks back into the soil beneath it,
+    /// while its other items are dropped as litter on and around its footprint.
+    /// Has no effect if the tile position is already empty.
+    fn kill_organism_structure(&mut self, voxel_pos: VoxelPos);
+
+    /// Demolishes any structure at the provided `voxel_pos`, despawning it.
+    ///
+    /// Any [`Salvage`] it holds is dropped as litter on and around its footprint.
+    /// Has no effect if the tile position is already empty.
+    fn demolish_structure(&mut self, voxel_pos: VoxelPos);
+
     /// Spawns a ghost with data defined by `data` at `voxel_pos`.
     ///
     /// Replaces any existing ghost.
@@ -53,10 +107,21 @@ pub(crate) trait StructureCommandsExt {
     /// Has no effect if the tile position is already empty.
     fn despawn_ghost_structure(&mut self, voxel_pos: VoxelPos);
 
+    /// Cancels construction of any ghost at the provided `voxel_pos`, despawning it.
+    ///
+    /// Any construction materials that were already delivered are dropped as litter on and around its footprint.
+    /// Has no effect if the tile position is already empty.
+    fn cancel_ghost_structure(&mut self, voxel_pos: VoxelPos);
+
     /// Spawns a preview with data defined by `item` at `voxel_pos`.
     ///
     /// Replaces any existing preview.
     fn spawn_preview_structure(&mut self, voxel_pos: VoxelPos, data: ClipboardData);
+
+    /// Applies the provided `effect` to the tile that `structure_entity` is found on.
+    ///
+    /// If the terrain height changes, the structure is moved with it.
+    fn apply_recipe_effect(&mut self, structure_entity: Entity, effect: RecipeEffect);
 }
 
 impl<'w, 's> StructureCommandsExt for Commands<'w, 's> {
@@ -70,6 +135,21 @@ impl<'w, 's> StructureCommandsExt for Commands<'w, 's> {
             center: voxel_pos,
             data,
             starting_energy,
+            generated: false,
+        });
+    }
+
+    fn generate_structure(
+        &mut self,
+        voxel_pos: VoxelPos,
+        data: ClipboardData,
+        starting_energy: StartingEnergy,
+    ) {
+        self.add(SpawnStructureCommand {
+            center: voxel_pos,
+            data,
+            starting_energy,
+            generated: true,
         });
     }
 
@@ -77,6 +157,14 @@ impl<'w, 's> StructureCommandsExt for Commands<'w, 's> {
         self.add(DespawnStructureCommand { center: voxel_pos });
     }
 
+    fn kill_organism_structure(&mut self, voxel_pos: VoxelPos) {
+        self.add(KillOrganismStructureCommand { center: voxel_pos });
+    }
+
+    fn demolish_structure(&mut self, voxel_pos: VoxelPos) {
+        self.add(DemolishStructureCommand { center: voxel_pos });
+    }
+
     fn spawn_ghost_structure(&mut self, voxel_pos: VoxelPos, data: ClipboardData) {
         self.add(SpawnStructureGhostCommand {
             center: voxel_pos,
@@ -88,12 +176,23 @@ impl<'w, 's> StructureCommandsExt for Commands<'w, 's> {
         self.add(DespawnGhostCommand { voxel_pos });
     }
 
+    fn cancel_ghost_structure(&mut self, voxel_pos: VoxelPos) {
+        self.add(CancelGhostCommand { voxel_pos });
+    }
+
     fn spawn_preview_structure(&mut self, voxel_pos: VoxelPos, data: ClipboardData) {
         self.add(SpawnStructurePreviewCommand {
             center: voxel_pos,
             data,
         });
     }
+
+    fn apply_recipe_effect(&mut self, structure_entity: Entity, effect: RecipeEffect) {
+        self.add(ApplyRecipeEffectCommand {
+            structure_entity,
+            effect,
+        });
+    }
 }
 
 /// A [`Command`] used to spawn a structure via [`StructureCommandsExt`].
@@ -104,19 +203,83 @@ struct SpawnStructureCommand {
     data: ClipboardData,
     /// The amount of energy to give the organism.
     starting_energy: StartingEnergy,
+    /// Is this structure being placed by world generation, rather than built or grown?
+    generated: bool,
 }
 
 impl Command for SpawnStructureCommand {
     fn write(self, world: &mut World) {
+        // Just give up if the structure no longer fits.
+        let _ = self.place(world);
+    }
+}
+
+/// A structure could not be placed via [`place_structure`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum PlacementError {
+    /// The structure is not defined in the [`StructureManifest`].
+    UnknownStructure,
+    /// The center of the structure is outside of the map.
+    OutOfBounds,
+    /// Some of the voxels needed by the structure's footprint are already occupied.
+    SpaceOccupied,
+    /// One of the [`PlacementRule`](super::placement_rules::PlacementRule)s for this structure was broken.
+    RuleViolated(&'static str),
+    /// The structure is fixed in place, and cannot be moved via [`move_structure`](super::relocation::move_structure).
+    Immovable,
+}
+
+/// Immediately places the structure `structure_id` at `center`, as if it had been generated with the world.
+///
+/// The components needed by its [`StructureKind`] are attached, and the [`MapGeometry`] is updated.
+/// This is the entry point for scenarios and tests that need to set up many structures without going through player input.
+pub(crate) fn place_structure(
+    world: &mut World,
+    structure_id: Id<Structure>,
+    center: VoxelPos,
+    facing: Facing,
+) -> Result<Entity, PlacementError> {
+    let structure_manifest = world.resource::<StructureManifest>();
+    if !structure_manifest.data_map().contains_key(&structure_id) {
+        return Err(PlacementError::UnknownStructure);
+    }
+
+    let structure_data = structure_manifest.get(structure_id);
+    let starting_energy = match structure_data.organism_variety {
+        Some(_) => StartingEnergy::Full,
+        None => StartingEnergy::NotAnOrganism,
+    };
+
+    SpawnStructureCommand {
+        center,
+        data: ClipboardData {
+            structure_id,
+            facing,
+            active_recipe: structure_data.starting_recipe().clone(),
+        },
+        starting_energy,
+        generated: true,
+    }
+    .place(world)
+}
+
+impl SpawnStructureCommand {
+    /// Validates and spawns the structure, returning the newly spawned entity.
+    ///
+    /// Every structure, whether built, grown or generated, is spawned through this method.
+    fn place(self, world: &mut World) -> Result<Entity, PlacementError> {
         let geometry = world.resource::<MapGeometry>();
         // Check that the tile is within the bounds of the map
         if !geometry.is_valid(self.center.hex) {
-            return;
+            return Err(PlacementError::OutOfBounds);
         }
 
         let structure_id = self.data.structure_id;
 
         let manifest = world.resource::<StructureManifest>();
+        if !manifest.data_map().contains_key(&structure_id) {
+            return Err(PlacementError::UnknownStructure);
+        }
         let structure_data = manifest.get(structure_id).clone();
 
         // Check that the tiles needed are appropriate.
@@ -125,11 +288,23 @@ impl Command for SpawnStructureCommand {
             .is_space_available(self.center, &structure_data.footprint, self.data.facing)
             .is_err()
         {
-            // Just give up if the terrain is wrong.
-            return;
+            return Err(PlacementError::SpaceOccupied);
         }
 
         let map_geometry = world.resource::<MapGeometry>();
+        if let Some(placement_rules) = world.get_resource::<PlacementRules>() {
+            placement_rules
+                .check_structure(
+                    world.resource::<StructureManifest>(),
+                    structure_id,
+                    self.center,
+                    self.data.facing,
+                    map_geometry,
+                    |terrain_entity| world.get::<WaterDepth>(terrain_entity).copied(),
+                )
+                .map_err(PlacementError::RuleViolated)?;
+        }
+
         let world_pos = structure_data
             .footprint
             .world_pos(self.data.facing, self.center, map_geometry)
@@ -189,61 +364,207 @@ impl Command for SpawnStructureCommand {
                     energy_pool,
                     organism_details.lifecycle.clone(),
                 ));
+
+            if let Some(pollination) = &organism_details.pollination {
+                world
+                    .entity_mut(structure_entity)
+                    .insert(PollinationState::new(pollination));
+            }
+
+            if let Some(life_span) = &organism_details.life_span {
+                world
+                    .entity_mut(structure_entity)
+                    .insert((Age::newborn(life_span.max_age), life_span.stage(Days::ZERO)));
+            }
         };
 
         match structure_data.kind {
             StructureKind::Storage {
                 max_slot_count,
                 reserved_for,
+                starting_inventory,
+                merge_adjacent,
             } => {
+                let mut storage_inventory = StorageInventory::new(max_slot_count, reserved_for);
+
+                if self.generated && !starting_inventory.is_empty() {
+                    let item_manifest = world.resource::<ItemManifest>();
+                    // Starting inventories are checked against the storage capacity when the manifest is loaded
+                    storage_inventory
+                        .add_items_all_or_nothing(&starting_inventory, item_manifest)
+                        .expect("Starting inventory must fit in the storage structure");
+                }
+
                 world
                     .entity_mut(structure_entity)
-                    .insert(StorageInventory::new(max_slot_count, reserved_for))
+                    .insert(storage_inventory)
                     .insert(Emitter::default());
+
+                if merge_adjacent {
+                    world
+                        .entity_mut(structure_entity)
+                        .insert(MergesWithNeighbors);
+                }
             }
-            StructureKind::Crafting { starting_recipe } => {
-                world.resource_scope(|world, recipe_manifest: Mut<RecipeManifest>| {
-                    world.resource_scope(|world, item_manifest: Mut<ItemManifest>| {
-                        world.resource_scope(|world, structure_manifest: Mut<StructureManifest>| {
-                            let crafting_bundle = CraftingBundle::new(
-                                structure_id,
-                                starting_recipe,
-                                &recipe_manifest,
-                                &item_manifest,
-                                &structure_manifest,
-                            );
-
-                            world.entity_mut(structure_entity).insert(crafting_bundle);
-                        })
+            StructureKind::Crafting {
+                starting_recipe, ..
+            } => world.resource_scope(|world, recipe_manifest: Mut<RecipeManifest>| {
+                world.resource_scope(|world, item_manifest: Mut<ItemManifest>| {
+                    world.resource_scope(|world, structure_manifest: Mut<StructureManifest>| {
+                        let crafting_bundle = CraftingBundle::new(
+                            structure_id,
+                            starting_recipe,
+                            &recipe_manifest,
+                            &item_manifest,
+                            &structure_manifest,
+                        );
+
+                        world.entity_mut(structure_entity).insert(crafting_bundle);
                     })
                 })
-            }
+            }),
             StructureKind::Path => {}
             StructureKind::Landmark => {
                 world.entity_mut(structure_entity).insert(Landmark);
             }
-            StructureKind::Absorber => {
+            StructureKind::Absorber {
+                overflow_to_adjacent,
+            } => {
                 world
                     .entity_mut(structure_entity)
-                    .insert(AbsorbsItems)
+                    .insert(AbsorbsItems {
+                        overflow_to_adjacent,
+                    })
                     .insert(OutputInventory::default())
-                    .insert(Emitter::default());
+                    .insert(Emitter::default())
+                    .insert(FlowStats::default())
+                    .insert(ThroughputTracker::default())
+                    .insert(LogisticStatus::default());
             }
-            StructureKind::Releaser => {
+            StructureKind::Releaser {
+                release_threshold,
+                released_item,
+            } => {
+                let releases_items = ReleasesItems { release_threshold };
+
                 world
                     .entity_mut(structure_entity)
-                    .insert(ReleasesItems)
+                    .insert(releases_items)
                     .insert(InputInventory::Exact {
                         // TODO: let this be configured by the user using the UI
+                        // The buffer must be large enough to hold a full batch
                         inventory: Inventory::empty_from_item(
-                            Id::from_name("crab_egg".to_string()),
-                            1,
+                            released_item,
+                            releases_items.batch_size(),
                         ),
                     })
+                    .insert(Emitter::default())
+                    .insert(FlowStats::default())
+                    .insert(ThroughputTracker::default())
+                    .insert(LogisticStatus::default());
+            }
+            StructureKind::WellPump { filled_item } => {
+                let item_manifest = world.resource::<ItemManifest>();
+                // Pumps that fill other items are rejected when the manifest is loaded, but a hot reload can still introduce them
+                match item_manifest
+                    .data_map()
+                    .get(&filled_item)
+                    .and_then(|item_data| item_data.water_contents)
+                {
+                    Some(water_contents) => {
+                        let mut input_inventory = Inventory::new(1, None);
+                        input_inventory
+                            .add_empty_slot(water_contents.empty_container, item_manifest);
+                        let mut output_inventory = Inventory::new(1, None);
+                        output_inventory.add_empty_slot(filled_item, item_manifest);
+
+                        world
+                            .entity_mut(structure_entity)
+                            .insert(PumpsWater { filled_item })
+                            .insert(InputInventory::Exact {
+                                inventory: input_inventory,
+                            })
+                            .insert(OutputInventory {
+                                inventory: output_inventory,
+                            })
+                            .insert(Emitter::default())
+                            .insert(FlowStats::default());
+                    }
+                    None => error!(
+                        "Well pumps can only fill items that hold water, but item {filled_item} does not: its components were skipped."
+                    ),
+                }
+            }
+            StructureKind::Composter { radius, rate } => {
+                world
+                    .entity_mut(structure_entity)
+                    .insert(Composts { radius, rate })
+                    .insert(InputInventory::Tagged {
+                        tag: ItemTag::Compostable,
+                        exclude: Vec::new(),
+                        inventory: Inventory::new(1, None),
+                    })
+                    .insert(Emitter::default())
+                    .insert(FlowStats::default());
+            }
+            StructureKind::Incinerator {
+                throughput,
+                accepts,
+                precious,
+            } => {
+                world
+                    .entity_mut(structure_entity)
+                    .insert(Incinerates { throughput })
+                    .insert(InputInventory::Filtered {
+                        accepts,
+                        refuses: precious,
+                        inventory: Inventory::new(INCINERATOR_SLOT_COUNT, None),
+                    })
+                    .insert(Emitter::default())
+                    .insert(FlowStats::default());
+            }
+            StructureKind::Nest {
+                rest_rate,
+                capacity,
+            } => {
+                world
+                    .entity_mut(structure_entity)
+                    .insert(Nest::new(rest_rate, capacity))
                     .insert(Emitter::default());
             }
+            StructureKind::RequestPost { requested, count } => {
+                let request_post = RequestPost { requested, count };
+                let buffer = request_post.buffer(world.resource::<ItemManifest>());
+
+                world
+                    .entity_mut(structure_entity)
+                    .insert(request_post)
+                    .insert(buffer)
+                    .insert(Emitter::default());
+            }
+            StructureKind::Custom { kind, data } => {
+                let mut command_queue = CommandQueue::default();
+                let mut commands = Commands::new(&mut command_queue, world);
+                let registry = world.resource::<StructureKindRegistry>();
+                // Unregistered kinds are rejected when the manifest is loaded, but a hot reload can still introduce them
+                match registry.get(&kind) {
+                    Some(handler) => handler.insert_components(
+                        &mut commands.entity(structure_entity),
+                        &data,
+                        &Manifests::from_world(world),
+                    ),
+                    None => error!(
+                        "No handler is registered for the custom structure kind \"{kind}\": its components were skipped."
+                    ),
+                }
+                command_queue.apply(world);
+            }
         }
 
+        world
+            .entity_mut(structure_entity)
+            .insert(structure_data.tags);
+
         // TODO: yeet StructureKind and just do this everywhere
         if let Some(vegetative_reproduction) = structure_data.vegetative_reproduction {
             world
@@ -263,6 +584,9 @@ impl Command for SpawnStructureCommand {
                 structure_entity,
             )
             .unwrap();
+        geometry.set_occupant_limit(structure_entity, structure_data.max_occupants);
+
+        Ok(structure_entity)
     }
 }
 
@@ -294,11 +618,137 @@ impl Command for DespawnStructureCommand {
         }
 
         let structure_entity = maybe_entity.unwrap();
+        geometry.set_occupant_limit(structure_entity, None);
         // Make sure to despawn all children, which represent the meshes stored in the loaded gltf scene.
         world.entity_mut(structure_entity).despawn_recursive();
     }
 }
 
+/// A [`Command`] used to kill an organism structure via [`StructureCommandsExt`].
+struct KillOrganismStructureCommand {
+    /// The tile position at which the organism to be killed is found.
+    center: VoxelPos,
+}
+
+impl Command for KillOrganismStructureCommand {
+    fn write(self, world: &mut World) {
+        let map_geometry = world.resource::<MapGeometry>();
+        let Some(structure_entity) = map_geometry.get_structure(self.center) else { return };
+        let entity_ref = world.entity(structure_entity);
+        let Some(&structure_id) = entity_ref.get::<Id<Structure>>() else { return };
+        let center = *entity_ref.get::<VoxelPos>().unwrap();
+        let facing = *entity_ref.get::<Facing>().unwrap();
+
+        let held_items: Vec<ItemCount> = entity_ref
+            .get::<InputInventory>()
+            .map(|input_inventory| input_inventory.inventory())
+            .into_iter()
+            .chain(entity_ref.get::<OutputInventory>().map(|output| &**output))
+            .flat_map(|inventory| inventory.iter())
+            .filter(|item_slot| !item_slot.is_empty())
+            .map(|item_slot| item_slot.item_count())
+            .collect();
+
+        let structure_manifest = world.resource::<StructureManifest>();
+        let structure_data = structure_manifest.get(structure_id);
+        let footprint = structure_data.footprint.clone();
+        let remains = structure_data
+            .organism_variety
+            .as_ref()
+            .and_then(|organism_variety| organism_variety.remains.clone());
+
+        // Clear the way for the remains structure
+        DespawnStructureCommand { center }.write(world);
+
+        // Water drawn up by the roots soaks back into the soil
+        let (water, mut litter): (Vec<ItemCount>, Vec<ItemCount>) = held_items
+            .into_iter()
+            .partition(|item_count| item_count.item_id == Id::water());
+        let water_items: u32 = water.iter().map(|item_count| item_count.count).sum();
+        if water_items > 0 {
+            let volume = world.resource::<WaterConfig>().items_to_tiles(water_items);
+            let terrain_entity = world
+                .resource::<MapGeometry>()
+                .get_terrain(center.hex)
+                .unwrap();
+            if let Some(mut water_volume) = world.get_mut::<WaterVolume>(terrain_entity) {
+                water_volume.add(volume);
+            }
+        }
+
+        if let Some(remains) = &remains {
+            litter.extend(remains.items.iter().cloned());
+        }
+        scatter_items_as_litter(world, center, facing, &footprint, litter);
+
+        let Some(Remains {
+            structure: Some(remains_structure_id),
+            salvage,
+            ..
+        }) = remains
+        else { return };
+        let structure_manifest = world.resource::<StructureManifest>();
+        let starting_energy = match structure_manifest
+            .get(remains_structure_id)
+            .organism_variety
+        {
+            Some(_) => StartingEnergy::Full,
+            None => StartingEnergy::NotAnOrganism,
+        };
+        let data = ClipboardData {
+            structure_id: remains_structure_id,
+            facing,
+            active_recipe: structure_manifest
+                .get(remains_structure_id)
+                .starting_recipe()
+                .clone(),
+        };
+
+        // Remains are placed directly, so even structures that players cannot build are allowed
+        let Ok(remains_entity) = (SpawnStructureCommand {
+            center,
+            data,
+            starting_energy,
+            generated: false,
+        })
+        .place(world) else { return };
+
+        if !salvage.is_empty() {
+            world
+                .entity_mut(remains_entity)
+                .insert(Salvage { items: salvage });
+        }
+    }
+}
+
+/// A [`Command`] used to demolish a structure via [`StructureCommandsExt`].
+struct DemolishStructureCommand {
+    /// The tile position at which the structure to be demolished is found.
+    center: VoxelPos,
+}
+
+impl Command for DemolishStructureCommand {
+    fn write(self, world: &mut World) {
+        let map_geometry = world.resource::<MapGeometry>();
+        let Some(structure_entity) = map_geometry.get_structure(self.center) else { return };
+        let entity_ref = world.entity(structure_entity);
+        let Some(&structure_id) = entity_ref.get::<Id<Structure>>() else { return };
+        let center = *entity_ref.get::<VoxelPos>().unwrap();
+        let facing = *entity_ref.get::<Facing>().unwrap();
+        let salvage = entity_ref
+            .get::<Salvage>()
+            .map(|salvage| salvage.items.clone())
+            .unwrap_or_default();
+        let footprint = world
+            .resource::<StructureManifest>()
+            .footprint(structure_id)
+            .clone();
+
+        DespawnStructureCommand { center }.write(world);
+        scatter_items_as_litter(world, center, facing, &footprint, salvage);
+    }
+}
+
 /// A [`Command`] used to spawn a ghost via [`StructureCommandsExt`].
 struct SpawnStructureGhostCommand {
     /// The tile position at which to spawn the structure.
@@ -318,69 +768,105 @@ impl Command for SpawnStructureGhostCommand {
         }
 
         let manifest = world.resource::<StructureManifest>();
+
+        // Structures that cannot be constructed (such as landmarks) cannot have ghosts
+        if manifest.construction_data(structure_id).is_none() {
+            warn!(
+                "Structure {} cannot be constructed, so no ghost was spawned.",
+                manifest.name(structure_id)
+            );
+            return;
+        }
+
         let footprint = manifest.footprint(structure_id).clone();
         let structure_data = manifest.get(structure_id);
         let facing = self.data.facing;
 
+        // Structures zoned on terrain of the wrong height must wait for it to be terraformed
+        let construction_phase = match manifest
+            .construction_data(structure_id)
+            .unwrap()
+            .terraforming_needed(&footprint, facing, self.center, map_geometry)
+            .is_empty()
+        {
+            true => ConstructionPhase::Building,
+            false => ConstructionPhase::Terraforming,
+        };
+
         let world_pos = structure_data
             .footprint
             .world_pos(self.data.facing, self.center, map_geometry)
             .unwrap_or_default();
 
         // Check that the tiles needed are appropriate.
+        // Existing ghosts are replaced, so they don't count as obstacles.
         if map_geometry
-            .is_space_available(self.center, &footprint, facing)
-            .is_ok()
+            .is_space_available_for_ghost(self.center, &footprint, facing)
+            .is_err()
         {
             return;
         }
 
-        // Remove any existing ghosts
+        // Zoning the same ghost again leaves it (and any materials delivered to it) untouched
         let map_geometry = world.resource::<MapGeometry>();
+        if let Some(existing_ghost) = map_geometry.get_ghost_structure(self.center) {
+            let existing_ghost = world.entity(existing_ghost);
+            if existing_ghost.get::<VoxelPos>() == Some(&self.center)
+                && existing_ghost.get::<Id<Structure>>() == Some(&structure_id)
+                && existing_ghost.get::<Facing>() == Some(&facing)
+                && existing_ghost.get::<ActiveRecipe>() == Some(&self.data.active_recipe)
+            {
+                return;
+            }
+        }
 
-        let mut existing_ghosts: Vec<Entity> = Vec::new();
+        // Cancel any other ghosts in the way, refunding their delivered materials
+        let mut existing_ghost_centers: Vec<VoxelPos> = Vec::new();
         for voxel_pos in footprint.normalized(facing, self.center) {
             if let Some(ghost_entity) = map_geometry.get_ghost_structure(voxel_pos) {
-                existing_ghosts.push(ghost_entity);
+                let center = *world.entity(ghost_entity).get::<VoxelPos>().unwrap();
+                if !existing_ghost_centers.contains(&center) {
+                    existing_ghost_centers.push(center);
+                }
             }
         }
 
-        for ghost_entity in existing_ghosts {
-            let facing = *world.entity(ghost_entity).get::<Facing>().unwrap();
-            let center = *world.entity(ghost_entity).get::<VoxelPos>().unwrap();
-            let structure_id = *world.entity(ghost_entity).get::<Id<Structure>>().unwrap();
-
-            let structure_manifest = world.resource::<StructureManifest>();
-            let footprint = structure_manifest.footprint(structure_id).clone();
-
-            world.entity_mut(ghost_entity).despawn_recursive();
-            let mut map_geometry = world.resource_mut::<MapGeometry>();
-            map_geometry.remove_ghost_structure(center, &footprint, facing);
+        for center in existing_ghost_centers {
+            CancelGhostCommand { voxel_pos: center }.write(world);
         }
 
         let structure_manifest = world.resource::<StructureManifest>();
 
         // Spawn a ghost
-        let ghost_handles = world.resource::<GhostHandles>();
-        let structure_handles = world.resource::<StructureHandles>();
-
-        // TODO: vary this with the footprint and height of the structure
-        let picking_mesh = structure_handles.picking_mesh.clone_weak();
-        let scene_handle = structure_handles
-            .scenes
-            .get(&structure_id)
-            .unwrap()
-            .clone_weak();
-        let ghostly_handle = ghost_handles.get_material(GhostKind::Ghost);
-        let inherited_material = InheritedMaterial(ghostly_handle.clone_weak());
+        // These assets won't exist in headless tests, so we fall back to the default handles.
+        let (picking_mesh, scene_handle) = match world.get_resource::<StructureHandles>() {
+            Some(structure_handles) => (
+                // TODO: vary this with the footprint and height of the structure
+                structure_handles.picking_mesh.clone_weak(),
+                structure_handles
+                    .scenes
+                    .get(&structure_id)
+                    .unwrap()
+                    .clone_weak(),
+            ),
+            None => (Handle::default(), Handle::default()),
+        };
+        let inherited_material = match world.get_resource::<GhostHandles>() {
+            Some(ghost_handles) => {
+                InheritedMaterial(ghost_handles.get_material(GhostKind::Ghost).clone_weak())
+            }
+            None => InheritedMaterial(Handle::default()),
+        };
 
         let facing = self.data.facing;
+        let harvest_radius = structure_manifest.harvest_radius(structure_id);
 
         let ghost_entity = world
             .spawn(GhostStructureBundle::new(
                 self.center,
                 self.data,
                 structure_manifest,
+                construction_phase,
                 picking_mesh,
                 scene_handle,
                 inherited_material,
@@ -388,6 +874,12 @@ impl Command for SpawnStructureGhostCommand {
             ))
             .id();
 
+        if let Some(radius) = harvest_radius {
+            world
+                .entity_mut(ghost_entity)
+                .insert(HarvestsMaterials { radius });
+        }
+
         // Update the index to reflect the new state
         world.resource_scope(|world, mut map_geometry: Mut<MapGeometry>| {
             let structure_manifest = world.resource::<StructureManifest>();
@@ -426,6 +918,134 @@ impl Command for DespawnGhostCommand {
     }
 }
 
+/// A [`Command`] used to cancel the construction of a ghost via [`StructureCommandsExt`].
+struct CancelGhostCommand {
+    /// The tile position at which the ghost to be cancelled is found.
+    voxel_pos: VoxelPos,
+}
+
+impl Command for CancelGhostCommand {
+    fn write(self, world: &mut World) {
+        let map_geometry = world.resource::<MapGeometry>();
+        let Some(ghost_entity) = map_geometry.get_ghost_structure(self.voxel_pos) else { return };
+
+        let facing = *world.entity(ghost_entity).get::<Facing>().unwrap();
+        let center = *world.entity(ghost_entity).get::<VoxelPos>().unwrap();
+        let structure_id = *world.entity(ghost_entity).get::<Id<Structure>>().unwrap();
+        let delivered_items: Vec<ItemCount> = world
+            .entity(ghost_entity)
+            .get::<InputInventory>()
+            .map(|input_inventory| {
+                input_inventory
+                    .iter()
+                    .filter(|item_slot| !item_slot.is_empty())
+                    .map(|item_slot| item_slot.item_count())
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        // Deregister the ghost first, so that the tiles it occupied are available again.
+        DespawnGhostCommand {
+            voxel_pos: self.voxel_pos,
+        }
+        .write(world);
+
+        let footprint = world
+            .resource::<StructureManifest>()
+            .footprint(structure_id)
+            .clone();
+        scatter_items_as_litter(world, center, facing, &footprint, delivered_items);
+    }
+}
+
+/// Drops `items` as litter on the tiles covered by `footprint`, starting at its `center`,
+/// then overflowing onto walkable neighbors.
+///
+/// Anything that couldn't fit in the nearby litter is dropped loose at the `center`.
+fn scatter_items_as_litter(
+    world: &mut World,
+    center: VoxelPos,
+    facing: Facing,
+    footprint: &Footprint,
+    items: Vec<ItemCount>,
+) {
+    if items.is_empty() {
+        return;
+    }
+
+    let map_geometry = world.resource::<MapGeometry>();
+
+    // Prefer the tiles under the footprint, starting at its center, then overflow onto walkable neighbors
+    let footprint_hexes: Vec<Hex> = std::iter::once(center.hex)
+        .chain(
+            footprint
+                .normalized(facing, center)
+                .into_iter()
+                .map(|voxel_pos| voxel_pos.hex),
+        )
+        .unique()
+        .collect();
+    let neighboring_hexes = footprint_hexes.iter().flat_map(|&hex| {
+        map_geometry
+            .walkable_neighbors(map_geometry.on_top_of_terrain(hex))
+            .map(|voxel_pos| voxel_pos.hex)
+    });
+    let litter_entities: Vec<Entity> = footprint_hexes
+        .iter()
+        .copied()
+        .chain(neighboring_hexes)
+        .unique()
+        .filter_map(|hex| map_geometry.get_terrain(hex).ok())
+        .collect();
+
+    world.resource_scope(|world, item_manifest: Mut<ItemManifest>| {
+        let mut leftovers = Vec::new();
+
+        for item_count in items {
+            let mut remaining = item_count.clone();
+
+            // Keep each stack together if at all possible
+            for &litter_entity in &litter_entities {
+                let Some(mut litter) = world.get_mut::<Litter>(litter_entity) else { continue };
+                if litter
+                    .add_item_all_or_nothing(&remaining, &item_manifest)
+                    .is_ok()
+                {
+                    remaining.count = 0;
+                    break;
+                }
+            }
+
+            // Otherwise, split the stack across as many tiles as needed
+            for &litter_entity in &litter_entities {
+                if remaining.count == 0 {
+                    break;
+                }
+
+                let Some(mut litter) = world.get_mut::<Litter>(litter_entity) else { continue };
+                remaining.count = match litter.try_add_item(&remaining, &item_manifest) {
+                    Ok(()) => 0,
+                    Err(AddOneItemError { excess_count }) => excess_count.count,
+                };
+            }
+
+            if remaining.count > 0 {
+                leftovers.push(remaining);
+            }
+        }
+
+        // Anything that couldn't fit in the nearby litter is dropped loose at the center
+        let mut command_queue = CommandQueue::default();
+        let mut commands = Commands::new(&mut command_queue, world);
+        for item_count in leftovers {
+            for _ in 0..item_count.count {
+                commands.spawn_litter(center, item_count.item_id);
+            }
+        }
+        command_queue.apply(world);
+    });
+}
+
 /// A [`Command`] used to spawn a preview via [`StructureCommandsExt`].
 struct SpawnStructurePreviewCommand {
     /// The tile position at which to spawn the structure.
@@ -457,9 +1077,55 @@ impl Command for SpawnStructurePreviewCommand {
             .unwrap_or_default();
 
         // Check that the tiles needed are appropriate.
-        let forbidden = geometry
-            .is_space_available(self.center, &structure_data.footprint, self.data.facing)
-            .is_err();
+        let breaks_rules = world
+            .get_resource::<PlacementRules>()
+            .map(|placement_rules| {
+                placement_rules
+                    .check_structure(
+                        manifest,
+                        structure_id,
+                        self.center,
+                        self.data.facing,
+                        geometry,
+                        |terrain_entity| world.get::<WaterDepth>(terrain_entity).copied(),
+                    )
+                    .is_err()
+            })
+            .unwrap_or_default();
+        let obstruction = Obstruction::new(
+            self.center,
+            &structure_data.footprint,
+            self.data.facing,
+            geometry,
+            |structure_entity| {
+                let entity_ref = world.entity(structure_entity);
+                match (
+                    entity_ref.get::<Id<Structure>>(),
+                    entity_ref.get::<VoxelPos>(),
+                    entity_ref.get::<Facing>(),
+                ) {
+                    (Some(&structure_id), Some(&center), Some(&facing)) => {
+                        Obstruction::can_demolish(
+                            structure_id,
+                            center,
+                            facing,
+                            entity_ref.contains::<Landmark>() && !entity_ref.contains::<Salvage>(),
+                            &self.data,
+                            self.center,
+                        )
+                    }
+                    _ => false,
+                }
+            },
+        );
+        let forbidden = breaks_rules || obstruction == Obstruction::Blocked;
+        let wrong_moisture = !manifest.moisture_permits(
+            structure_id,
+            self.center,
+            self.data.facing,
+            geometry,
+            |terrain_entity| world.get::<WaterDepth>(terrain_entity).copied(),
+        );
 
         // Fetch the scene and material to use
         let structure_handles = world.resource::<StructureHandles>();
@@ -469,9 +1135,13 @@ impl Command for SpawnStructurePreviewCommand {
             .unwrap()
             .clone_weak();
 
-        let ghost_kind = match forbidden {
-            true => GhostKind::ForbiddenPreview,
-            false => GhostKind::Preview,
+        // Occupied tiles are the more fundamental problem, so they take priority
+        let ghost_kind = match (forbidden, wrong_moisture, obstruction) {
+            (true, _, _) => GhostKind::ForbiddenPreview,
+            (false, true, _) => GhostKind::WrongMoisturePreview,
+            (false, false, Obstruction::Ghosts) => GhostKind::ReplacePreview,
+            (false, false, Obstruction::Structures(_)) => GhostKind::RebuildPreview,
+            (false, false, _) => GhostKind::Preview,
         };
 
         let ghost_handles = world.resource::<GhostHandles>();
@@ -489,3 +1159,298 @@ impl Command for SpawnStructurePreviewCommand {
         ));
     }
 }
+
+/// A [`Command`] used to apply a [`RecipeEffect`] via [`StructureCommandsExt`].
+struct ApplyRecipeEffectCommand {
+    /// The structure whose recipe was completed.
+    structure_entity: Entity,
+    /// The change to make to the world.
+    effect: RecipeEffect,
+}
+
+impl Command for ApplyRecipeEffectCommand {
+    fn write(self, world: &mut World) {
+        let Some(&center) = world.get::<VoxelPos>(self.structure_entity) else { return };
+        let map_geometry = world.resource::<MapGeometry>();
+        let Ok(terrain_entity) = map_geometry.get_terrain(center.hex) else { return };
+        let old_height = map_geometry.get_height(center.hex).unwrap();
+
+        let (new_height, new_center) = match self.effect {
+            RecipeEffect::AddWater(volume) => {
+                if let Some(mut water_volume) = world.get_mut::<WaterVolume>(terrain_entity) {
+                    water_volume.add(volume);
+                }
+                return;
+            }
+            RecipeEffect::RaiseTerrain => (old_height.above(), center.above()),
+            RecipeEffect::LowerTerrain => (old_height.below(), center.below()),
+        };
+
+        // We're already at the top or bottom of the world
+        if new_height == old_height {
+            return;
+        }
+
+        let facing = *world.get::<Facing>(self.structure_entity).unwrap();
+        let structure_id = *world.get::<Id<Structure>>(self.structure_entity).unwrap();
+        let structure_manifest = world.resource::<StructureManifest>();
+        let structure_data = structure_manifest.get(structure_id).clone();
+        let footprint = &structure_data.footprint;
+
+        // The structure must be removed from the index before the terrain moves,
+        // as raising the terrain would otherwise overwrite it.
+        let mut map_geometry = world.resource_mut::<MapGeometry>();
+        map_geometry.remove_structure(center, footprint, facing);
+        map_geometry.update_height(center.hex, new_height);
+
+        let (final_height, final_center) = match map_geometry.add_structure(
+            new_center,
+            facing,
+            footprint,
+            structure_data.can_walk_on_roof,
+            structure_data.can_walk_through,
+            self.structure_entity,
+        ) {
+            Ok(()) => (new_height, new_center),
+            Err(_) => {
+                warn!(
+                    "Could not apply {} to the structure at {center}: the space was blocked.",
+                    self.effect
+                );
+                // Put everything back the way it was
+                map_geometry.update_height(center.hex, old_height);
+                map_geometry
+                    .add_structure(
+                        center,
+                        facing,
+                        footprint,
+                        structure_data.can_walk_on_roof,
+                        structure_data.can_walk_through,
+                        self.structure_entity,
+                    )
+                    .unwrap();
+                (old_height, center)
+            }
+        };
+
+        let world_pos = footprint.world_pos(facing, final_center, &map_geometry);
+
+        if let Some(mut terrain_pos) = world.get_mut::<VoxelPos>(terrain_entity) {
+            terrain_pos.height = final_height;
+        }
+
+        let mut structure_entity_mut = world.entity_mut(self.structure_entity);
+        if let Some(mut structure_pos) = structure_entity_mut.get_mut::<VoxelPos>() {
+            *structure_pos = final_center;
+        }
+        if let (Some(mut transform), Some(world_pos)) =
+            (structure_entity_mut.get_mut::<Transform>(), world_pos)
+        {
+            transform.translation = world_pos;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        asset_management::manifest::ManifestBuilder,
+        crafting::{
+            inventories::CraftingState,
+            recipe::{ActiveRecipe, RecipeData, RecipeInput, RecipeOutput},
+        },
+        geometry::DiscreteHeight,
+        items::item_manifest::ItemData,
+        structures::structure_manifest::{
+            RawStructureData, RawStructureKind, StructureData, StructureTags,
+        },
+    };
+
+    /// Spawns a seed bin that starts with 10 seeds using `spawn`, and returns how many seeds it holds.
+    fn seeds_in_spawned_bin(spawn: impl FnOnce(&mut Commands, VoxelPos, ClipboardData)) -> u32 {
+        let mut world = World::new();
+        let map_geometry = MapGeometry::new(&mut world, 0);
+        let voxel_pos = map_geometry.on_top_of_terrain(Hex::ZERO);
+
+        let seed = Id::from_name("acacia_seed".to_string());
+        let item_manifest: ItemManifest = ManifestBuilder::new()
+            .add(
+                "acacia_seed",
+                ItemData {
+                    compostable: true,
+                    buoyant: true,
+                    ..ItemData::basic(10)
+                },
+            )
+            .build();
+
+        let kind = RawStructureKind::Storage {
+            max_slot_count: 1,
+            reserved_for: None,
+            starting_inventory: vec![("acacia_seed".to_string(), 10)],
+            merge_adjacent: false,
+        };
+        let structure_manifest: StructureManifest = ManifestBuilder::new()
+            .add(
+                "seed_bin",
+                StructureData {
+                    kind: kind.into(),
+                    ..StructureData::impassable()
+                },
+            )
+            .build();
+        let clipboard_data = ClipboardData::generate_from_id(
+            Id::from_name("seed_bin".to_string()),
+            &structure_manifest,
+        );
+
+        world.insert_resource(map_geometry);
+        world.insert_resource(item_manifest);
+        world.insert_resource(structure_manifest);
+
+        let mut command_queue = CommandQueue::default();
+        let mut commands = Commands::new(&mut command_queue, &world);
+        spawn(&mut commands, voxel_pos, clipboard_data);
+        command_queue.apply(&mut world);
+
+        let mut storage_query = world.query::<&StorageInventory>();
+        storage_query.single(&world).item_count(seed)
+    }
+
+    #[test]
+    fn generated_storage_starts_with_its_starting_inventory() {
+        let seeds = seeds_in_spawned_bin(|commands, voxel_pos, clipboard_data| {
+            commands.generate_structure(voxel_pos, clipboard_data, StartingEnergy::NotAnOrganism)
+        });
+
+        assert_eq!(seeds, 10);
+    }
+
+    #[test]
+    fn player_built_storage_starts_empty() {
+        let seeds = seeds_in_spawned_bin(|commands, voxel_pos, clipboard_data| {
+            commands.spawn_structure(voxel_pos, clipboard_data, StartingEnergy::NotAnOrganism)
+        });
+
+        assert_eq!(seeds, 0);
+    }
+
+    #[test]
+    fn manifest_tags_are_added_to_spawned_structures() {
+        let mut world = World::new();
+        let map_geometry = MapGeometry::new(&mut world, 0);
+        let voxel_pos = map_geometry.on_top_of_terrain(Hex::ZERO);
+
+        let raw_data = RawStructureData {
+            tags: vec!["flammable".to_string()],
+            ..RawStructureData::passable()
+        };
+        let structure_manifest: StructureManifest = ManifestBuilder::new()
+            .add("hay_bale", StructureData::from(raw_data))
+            .build();
+        let clipboard_data = ClipboardData::generate_from_id(
+            Id::from_name("hay_bale".to_string()),
+            &structure_manifest,
+        );
+
+        world.insert_resource(map_geometry);
+        world.insert_resource(structure_manifest);
+
+        let mut command_queue = CommandQueue::default();
+        let mut commands = Commands::new(&mut command_queue, &world);
+        commands.spawn_structure(voxel_pos, clipboard_data, StartingEnergy::NotAnOrganism);
+        command_queue.apply(&mut world);
+
+        let mut tags_query = world.query::<&StructureTags>();
+        let tags = tags_query.single(&world);
+        assert!(tags.contains(Id::from_name("flammable".to_string())));
+        assert!(!tags.contains(Id::from_name("sacred".to_string())));
+    }
+
+    #[test]
+    fn placed_crafting_structures_have_crafting_components() {
+        let mut world = World::new();
+        let map_geometry = MapGeometry::new(&mut world, 1);
+        let center = map_geometry.on_top_of_terrain(Hex::ZERO);
+
+        let leaf = Id::from_name("leaf".to_string());
+        let item_manifest: ItemManifest = ManifestBuilder::new()
+            .add(
+                "leaf",
+                ItemData {
+                    compostable: true,
+                    buoyant: true,
+                    ..ItemData::basic(10)
+                },
+            )
+            .build();
+        let recipe_manifest: RecipeManifest = ManifestBuilder::new()
+            .add(
+                "grow_leaf",
+                RecipeData::basic(
+                    RecipeInput::EMPTY,
+                    RecipeOutput::Deterministic(vec![ItemCount::one(leaf)]),
+                ),
+            )
+            .build();
+        let starting_recipe = ActiveRecipe::new(Id::from_name("grow_leaf".to_string()));
+        let structure_manifest: StructureManifest = ManifestBuilder::new()
+            .add(
+                "leaf_press",
+                StructureData {
+                    kind: StructureKind::Crafting {
+                        starting_recipe: starting_recipe.clone(),
+                        input_buffer_slots: None,
+                        output_buffer_slots: None,
+                    },
+                    ..StructureData::impassable()
+                },
+            )
+            .build();
+
+        world.insert_resource(map_geometry);
+        world.insert_resource(item_manifest);
+        world.insert_resource(recipe_manifest);
+        world.insert_resource(structure_manifest);
+
+        let structure_id = Id::from_name("leaf_press".to_string());
+        let structure_entity =
+            place_structure(&mut world, structure_id, center, Facing::default()).unwrap();
+
+        let entity_ref = world.entity(structure_entity);
+        assert_eq!(entity_ref.get::<Id<Structure>>(), Some(&structure_id));
+        assert_eq!(entity_ref.get::<ActiveRecipe>(), Some(&starting_recipe));
+        assert!(entity_ref.contains::<InputInventory>());
+        assert!(entity_ref.contains::<OutputInventory>());
+        assert!(entity_ref.contains::<CraftingState>());
+        assert!(entity_ref.contains::<Emitter>());
+        assert!(entity_ref.contains::<FlowStats>());
+        assert!(entity_ref.contains::<ThroughputTracker>());
+
+        let map_geometry = world.resource::<MapGeometry>();
+        assert_eq!(map_geometry.get_structure(center), Some(structure_entity));
+
+        // Invalid placements are rejected without spawning anything
+        assert_eq!(
+            place_structure(&mut world, structure_id, center, Facing::default()),
+            Err(PlacementError::SpaceOccupied)
+        );
+        let off_the_map = VoxelPos {
+            hex: Hex::new(10, 0),
+            height: DiscreteHeight::ZERO,
+        };
+        assert_eq!(
+            place_structure(&mut world, structure_id, off_the_map, Facing::default()),
+            Err(PlacementError::OutOfBounds)
+        );
+        let unknown_id = Id::from_name("unknown".to_string());
+        assert_eq!(
+            place_structure(&mut world, unknown_id, center, Facing::default()),
+            Err(PlacementError::UnknownStructure)
+        );
+
+        let mut structure_query = world.query::<&Id<Structure>>();
+        assert_eq!(structure_query.iter(&world).count(), 1);
+    }
+}
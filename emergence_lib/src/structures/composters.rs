@@ -0,0 +1,260 @@
+//! Composters turn compostable items into fertile soil for the plants around them.
+
+use bevy::prelude::*;
+use hexx::shapes::hexagon;
+
+use crate::{
+    crafting::{
+        flow::{record_item_flow, ItemConsumed},
+        inventories::InputInventory,
+        item_tags::{ItemKind, ItemTag},
+        recipe::RecipeInput,
+    },
+    geometry::{MapGeometry, VoxelPos},
+    items::{item_manifest::ItemManifest, ItemCount},
+    signals::{Emitter, LogisticsCategory, LogisticsPriorities, SignalType},
+    simulation::SimulationSet,
+    terrain::fertility::SoilFertility,
+};
+
+use super::StructureActivity;
+
+/// A building that composts items, enriching the soil around it.
+#[derive(Component, Debug, Clone, Copy, PartialEq)]
+pub(crate) struct Composts {
+    /// Every tile within this many tiles of the composter is enriched.
+    pub(crate) radius: u32,
+    /// The [`SoilFertility`] added to each enriched tile for every item composted.
+    pub(crate) rate: f32,
+}
+
+/// Logic that controls how composters enrich the soil.
+pub(super) struct CompostingPlugin;
+
+impl Plugin for CompostingPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            (compost_items, composter_signals.after(compost_items))
+                // Items composted should be counted on the same tick
+                .before(record_item_flow)
+                .in_set(SimulationSet)
+                .in_schedule(CoreSchedule::FixedUpdate),
+        );
+    }
+}
+
+/// Composts every item in the input inventory of each composter, enriching the soil in range.
+fn compost_items(
+    mut composter_query: Query<(
+        Entity,
+        &VoxelPos,
+        &Composts,
+        &mut InputInventory,
+        Option<&StructureActivity>,
+    )>,
+    mut fertility_query: Query<&mut SoilFertility>,
+    item_manifest: Res<ItemManifest>,
+    map_geometry: Res<MapGeometry>,
+    mut consumed_events: EventWriter<ItemConsumed>,
+) {
+    for (structure_entity, voxel_pos, composts, mut input_inventory, maybe_activity) in
+        composter_query.iter_mut()
+    {
+        if StructureActivity::is_paused(maybe_activity) {
+            continue;
+        }
+
+        let item_counts: Vec<ItemCount> = input_inventory
+            .iter()
+            .filter(|item_slot| !item_slot.is_empty())
+            .map(|item_slot| item_slot.item_count())
+            .collect();
+        let n_composted: u32 = item_counts.iter().map(|item_count| item_count.count).sum();
+        if n_composted == 0 {
+            continue;
+        }
+
+        // We only consume items that we just counted, so this cannot fail
+        let consumed = input_inventory
+            .consume_items(&RecipeInput::Exact(item_counts), &[], &item_manifest)
+            .unwrap();
+        // Free up the slots so that other kinds of compostable items can be delivered
+        input_inventory.clear_empty_slots();
+
+        for hex in hexagon(voxel_pos.hex, composts.radius) {
+            let Ok(terrain_entity) = map_geometry.get_terrain(hex) else { continue };
+            if let Ok(mut soil_fertility) = fertility_query.get_mut(terrain_entity) {
+                soil_fertility.enrich(composts.rate * n_composted as f32);
+            }
+        }
+
+        consumed_events.send_batch(consumed.into_iter().map(|item_count| ItemConsumed {
+            structure: structure_entity,
+            item_count,
+        }));
+    }
+}
+
+/// Sets the emitters for composters, which pull in any compostable item.
+pub(super) fn composter_signals(
+    mut composter_query: Query<
+        (&mut Emitter, &InputInventory, Option<&StructureActivity>),
+        With<Composts>,
+    >,
+    priorities: Res<LogisticsPriorities>,
+) {
+    /// Controls how strong the signal is for composters.
+    const COMPOSTER_SIGNAL_STRENGTH: f32 = 10.;
+
+    let signal_strength =
+        priorities.signal_strength(LogisticsCategory::Crafting, COMPOSTER_SIGNAL_STRENGTH);
+
+    for (mut emitter, input_inventory, maybe_activity) in composter_query.iter_mut() {
+        emitter.signals.clear();
+        if StructureActivity::is_paused(maybe_activity) {
+            continue;
+        }
+
+        if !input_inventory.inventory().is_full() {
+            let signal_type = SignalType::Pull(ItemKind::Tag(ItemTag::Compostable));
+            emitter.signals.push((signal_type, signal_strength));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        asset_management::manifest::{Id, Manifest},
+        items::{
+            inventory::Inventory,
+            item_manifest::{Item, ItemData},
+        },
+        simulation::time::InGameTime,
+        terrain::fertility::decay_soil_fertility,
+    };
+    use hexx::Hex;
+
+    fn leaf() -> Id<Item> {
+        Id::from_name("leaf".to_string())
+    }
+
+    fn item_manifest() -> ItemManifest {
+        let mut manifest = Manifest::new();
+        manifest.insert(
+            "leaf".to_string(),
+            ItemData {
+                compostable: true,
+                buoyant: true,
+                ..ItemData::basic(10)
+            },
+        );
+        manifest
+    }
+
+    /// Creates an app with a composter of radius 1 at the origin of a radius 3 map.
+    fn composter_app() -> (App, Entity) {
+        let mut app = App::new();
+        let map_geometry = MapGeometry::new(&mut app.world, 3);
+        for &hex in map_geometry.all_hexes() {
+            let terrain_entity = map_geometry.get_terrain(hex).unwrap();
+            app.world
+                .entity_mut(terrain_entity)
+                .insert(SoilFertility::default());
+        }
+
+        let composter_entity = app
+            .world
+            .spawn((
+                VoxelPos::ZERO,
+                Composts {
+                    radius: 1,
+                    rate: 0.1,
+                },
+                InputInventory::Tagged {
+                    tag: ItemTag::Compostable,
+                    exclude: Vec::new(),
+                    inventory: Inventory::new(1, None),
+                },
+            ))
+            .id();
+
+        app.insert_resource(map_geometry)
+            .insert_resource(item_manifest())
+            .insert_resource(FixedTime::new_from_secs(1.0 / 30.))
+            .init_resource::<InGameTime>()
+            .add_event::<ItemConsumed>()
+            .add_systems((compost_items, decay_soil_fertility.before(compost_items)));
+
+        (app, composter_entity)
+    }
+
+    fn feed_leaves(app: &mut App, composter_entity: Entity, count: u32) {
+        let item_manifest = item_manifest();
+        app.world
+            .get_mut::<InputInventory>(composter_entity)
+            .unwrap()
+            .fill_with_items(&ItemCount::new(leaf(), count), &item_manifest)
+            .unwrap();
+    }
+
+    fn fertility(app: &App, hex: Hex) -> f32 {
+        let terrain_entity = app
+            .world
+            .resource::<MapGeometry>()
+            .get_terrain(hex)
+            .unwrap();
+        app.world
+            .get::<SoilFertility>(terrain_entity)
+            .unwrap()
+            .value()
+    }
+
+    #[test]
+    fn feeding_a_composter_enriches_tiles_in_range() {
+        let (mut app, composter_entity) = composter_app();
+
+        feed_leaves(&mut app, composter_entity, 3);
+        app.update();
+
+        let input_inventory = app.world.get::<InputInventory>(composter_entity).unwrap();
+        assert!(input_inventory.is_empty());
+
+        for hex in [Hex::ZERO, Hex::new(1, 0), Hex::new(0, -1)] {
+            assert!(fertility(&app, hex) > SoilFertility::BASELINE);
+        }
+        for hex in [Hex::new(2, 0), Hex::new(-2, 1), Hex::new(3, -3)] {
+            assert_eq!(fertility(&app, hex), SoilFertility::BASELINE);
+        }
+    }
+
+    #[test]
+    fn fertility_saturates_at_the_cap() {
+        let (mut app, composter_entity) = composter_app();
+
+        for _ in 0..10 {
+            feed_leaves(&mut app, composter_entity, 10);
+            app.update();
+        }
+
+        assert_eq!(fertility(&app, Hex::ZERO), SoilFertility::MAX);
+    }
+
+    #[test]
+    fn fertility_decays_once_feeding_stops() {
+        let (mut app, composter_entity) = composter_app();
+
+        feed_leaves(&mut app, composter_entity, 5);
+        app.update();
+        let enriched = fertility(&app, Hex::ZERO);
+        assert!(enriched > SoilFertility::BASELINE);
+
+        for _ in 0..10 {
+            app.update();
+        }
+        let decayed = fertility(&app, Hex::ZERO);
+        assert!(decayed < enriched);
+        assert!(decayed > SoilFertility::BASELINE);
+    }
+}
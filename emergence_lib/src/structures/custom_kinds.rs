@@ -0,0 +1,298 @@
+//! Lets other crates define new kinds of structures, without adding a variant to [`StructureKind`].
+//!
+//! Each custom kind is identified by a string, and is handled by a [`StructureKindExtension`] stored in the [`StructureKindRegistry`].
+//! Manifests opt in using [`RawStructureKind::Custom`](super::structure_manifest::RawStructureKind::Custom),
+//! whose free-form data is passed to the handler whenever a structure of that kind is spawned.
+
+use bevy::{ecs::system::EntityCommands, prelude::*, utils::HashMap};
+
+use crate::{
+    asset_management::manifest::loader::ManifestError, crafting::recipe::RecipeManifest,
+    items::item_manifest::ItemManifest,
+};
+
+use super::{
+    beacons::{WarningBeacon, BEACON_KIND},
+    structure_manifest::{StructureKind, StructureManifest},
+};
+
+/// The manifests available to a [`StructureKindExtension`] while it sets up a structure.
+#[derive(Clone, Copy)]
+pub struct Manifests<'a> {
+    /// The data for each item.
+    pub item_manifest: &'a ItemManifest,
+    /// The data for each recipe.
+    pub recipe_manifest: &'a RecipeManifest,
+    /// The data for each structure.
+    pub structure_manifest: &'a StructureManifest,
+}
+
+impl<'a> Manifests<'a> {
+    /// Borrows each manifest from the `world`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if any of the manifests have not been loaded.
+    pub fn from_world(world: &'a World) -> Self {
+        Manifests {
+            item_manifest: world.resource::<ItemManifest>(),
+            recipe_manifest: world.resource::<RecipeManifest>(),
+            structure_manifest: world.resource::<StructureManifest>(),
+        }
+    }
+}
+
+/// Defines the behavior of a custom kind of structure.
+///
+/// Handlers are registered with [`StructureKindAppExt::register_structure_kind`].
+pub trait StructureKindExtension: Send + Sync + 'static {
+    /// Adds the components needed by this kind of structure to the newly spawned `entity`.
+    ///
+    /// `data` is the free-form data supplied by the structure's manifest entry.
+    /// Malformed data should be handled gracefully, as it is not checked when the manifest is loaded.
+    fn insert_components(
+        &self,
+        entity: &mut EntityCommands,
+        data: &serde_json::Value,
+        manifests: &Manifests,
+    );
+}
+
+/// Stores the handler for each custom kind of structure, indexed by the name used in the manifest.
+///
+/// The built-in custom kinds are registered by default.
+#[derive(Resource)]
+pub struct StructureKindRegistry {
+    /// The handler for each registered kind.
+    handlers: HashMap<String, Box<dyn StructureKindExtension>>,
+}
+
+impl Default for StructureKindRegistry {
+    fn default() -> Self {
+        let mut registry = StructureKindRegistry {
+            handlers: HashMap::default(),
+        };
+        registry.register(BEACON_KIND, WarningBeacon);
+        registry
+    }
+}
+
+impl StructureKindRegistry {
+    /// Registers `handler` for the custom structure kind named `kind`.
+    ///
+    /// If a handler was already registered for this kind, it is replaced.
+    pub fn register(&mut self, kind: impl Into<String>, handler: impl StructureKindExtension) {
+        self.handlers.insert(kind.into(), Box::new(handler));
+    }
+
+    /// Returns the handler for the custom structure kind named `kind`, if one was registered.
+    pub fn get(&self, kind: &str) -> Option<&dyn StructureKindExtension> {
+        self.handlers.get(kind).map(|handler| handler.as_ref())
+    }
+
+    /// Has a handler been registered for the custom structure kind named `kind`?
+    pub fn contains(&self, kind: &str) -> bool {
+        self.handlers.contains_key(kind)
+    }
+}
+
+impl StructureManifest {
+    /// Checks that every custom structure kind used in this manifest has been registered.
+    ///
+    /// This cannot be done while processing the raw manifest, as plugins can register kinds at any point while the app is built.
+    pub(crate) fn validate_custom_kinds(
+        &self,
+        registry: &StructureKindRegistry,
+    ) -> Result<(), ManifestError> {
+        for (&structure_id, structure_data) in self.data_map() {
+            let StructureKind::Custom { kind, .. } = &structure_data.kind else { continue };
+
+            if !registry.contains(kind) {
+                return Err(ManifestError::UnknownReference {
+                    entry: self.name(structure_id).to_string(),
+                    field: "kind".to_string(),
+                    reference: kind.clone(),
+                });
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// An [`App`] extension trait for adding custom kinds of structures.
+pub trait StructureKindAppExt {
+    /// Registers `handler` for the custom structure kind named `kind`.
+    fn register_structure_kind(
+        &mut self,
+        kind: impl Into<String>,
+        handler: impl StructureKindExtension,
+    ) -> &mut Self;
+}
+
+impl StructureKindAppExt for App {
+    fn register_structure_kind(
+        &mut self,
+        kind: impl Into<String>,
+        handler: impl StructureKindExtension,
+    ) -> &mut Self {
+        self.init_resource::<StructureKindRegistry>();
+        self.world
+            .resource_mut::<StructureKindRegistry>()
+            .register(kind, handler);
+
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        asset_management::manifest::{Id, ManifestBuilder},
+        geometry::{Facing, MapGeometry},
+        signals::{Hazard, SignalStrength},
+        structures::{
+            commands::place_structure,
+            structure_manifest::{RawStructureKind, Structure, StructureData},
+        },
+    };
+    use hexx::Hex;
+
+    /// Marks structures spawned by the [`DummyKind`] handler.
+    #[derive(Component, Debug, PartialEq)]
+    struct Dummy(u64);
+
+    /// A custom kind that copies the `value` field of its data into a [`Dummy`] component.
+    struct DummyKind;
+
+    impl StructureKindExtension for DummyKind {
+        fn insert_components(
+            &self,
+            entity: &mut EntityCommands,
+            data: &serde_json::Value,
+            manifests: &Manifests,
+        ) {
+            // Handlers can see every manifest, including the one defining them
+            assert!(manifests
+                .structure_manifest
+                .data_map()
+                .contains_key(&Id::from_name("dummy_structure".to_string())));
+
+            entity.insert(Dummy(data["value"].as_u64().unwrap()));
+        }
+    }
+
+    fn custom_structure_manifest(kind: &str, data: serde_json::Value) -> StructureManifest {
+        ManifestBuilder::new()
+            .add(
+                "dummy_structure",
+                StructureData {
+                    kind: StructureKind::Custom {
+                        kind: kind.to_string(),
+                        data,
+                    },
+                    ..StructureData::impassable()
+                },
+            )
+            .build()
+    }
+
+    fn spawn_custom_structure(
+        registry: StructureKindRegistry,
+        kind: &str,
+        data: serde_json::Value,
+    ) -> (World, Entity) {
+        let mut world = World::new();
+        let map_geometry = MapGeometry::new(&mut world, 1);
+        let voxel_pos = map_geometry.on_top_of_terrain(Hex::ZERO);
+        world.insert_resource(map_geometry);
+        world.insert_resource(ItemManifest::new());
+        world.insert_resource(RecipeManifest::new());
+        world.insert_resource(custom_structure_manifest(kind, data));
+        world.insert_resource(registry);
+
+        let structure_id: Id<Structure> = Id::from_name("dummy_structure".to_string());
+        let structure_entity =
+            place_structure(&mut world, structure_id, voxel_pos, Facing::default()).unwrap();
+        (world, structure_entity)
+    }
+
+    #[test]
+    fn custom_kinds_can_be_deserialized() {
+        let raw_kind: RawStructureKind =
+            serde_json::from_str(r#"{"Custom": {"kind": "beacon", "data": {"strength": 5.0}}}"#)
+                .unwrap();
+        assert_eq!(
+            StructureKind::from(raw_kind),
+            StructureKind::Custom {
+                kind: "beacon".to_string(),
+                data: serde_json::json!({"strength": 5.0}),
+            }
+        );
+
+        // The data is optional
+        let raw_kind: RawStructureKind =
+            serde_json::from_str(r#"{"Custom": {"kind": "beacon"}}"#).unwrap();
+        assert_eq!(
+            raw_kind,
+            RawStructureKind::Custom {
+                kind: "beacon".to_string(),
+                data: serde_json::Value::Null,
+            }
+        );
+    }
+
+    #[test]
+    fn unregistered_custom_kinds_are_invalid() {
+        let manifest = custom_structure_manifest("dummy", serde_json::Value::Null);
+        let mut registry = StructureKindRegistry::default();
+        assert_eq!(
+            manifest.validate_custom_kinds(&registry),
+            Err(ManifestError::UnknownReference {
+                entry: "dummy_structure".to_string(),
+                field: "kind".to_string(),
+                reference: "dummy".to_string(),
+            })
+        );
+
+        registry.register("dummy", DummyKind);
+        assert_eq!(manifest.validate_custom_kinds(&registry), Ok(()));
+    }
+
+    #[test]
+    fn custom_kinds_are_spawned_through_their_handler() {
+        let mut app = App::new();
+        app.register_structure_kind("dummy", DummyKind);
+        let registry = app
+            .world
+            .remove_resource::<StructureKindRegistry>()
+            .unwrap();
+
+        let (world, structure_entity) =
+            spawn_custom_structure(registry, "dummy", serde_json::json!({"value": 42}));
+        assert_eq!(world.get::<Dummy>(structure_entity), Some(&Dummy(42)));
+    }
+
+    #[test]
+    fn beacons_are_a_built_in_custom_kind() {
+        let (world, structure_entity) = spawn_custom_structure(
+            StructureKindRegistry::default(),
+            BEACON_KIND,
+            serde_json::json!({"strength": 5.0}),
+        );
+        assert_eq!(
+            world.get::<Hazard>(structure_entity),
+            Some(&Hazard {
+                strength: SignalStrength::new(5.0)
+            })
+        );
+
+        let (world, structure_entity) = spawn_custom_structure(
+            StructureKindRegistry::default(),
+            BEACON_KIND,
+            serde_json::Value::Null,
+        );
+        assert!(world.get::<Hazard>(structure_entity).is_some());
+    }
+}
@@ -0,0 +1,178 @@
+//! Structures that spend too long in water deeper than they can tolerate are damaged by flooding.
+
+use bevy::prelude::*;
+use std::time::Duration;
+
+use crate::{
+    asset_management::manifest::Id,
+    construction::ghosts::{Ghost, Preview},
+    geometry::{Facing, MapGeometry, VoxelPos},
+    water::WaterDepth,
+};
+
+use super::structure_manifest::{Structure, StructureManifest};
+
+/// How long a structure can continuously spend in water that is too deep before it is marked as [`FloodDamaged`].
+pub(crate) const FLOOD_DAMAGE_THRESHOLD: Duration = Duration::from_secs(30);
+
+/// The fraction of their usual speed that [`FloodDamaged`] structures craft at.
+pub(crate) const FLOOD_DAMAGE_CRAFT_SPEED: f32 = 0.5;
+
+/// A marker component for structures that have spent too long in water deeper than their
+/// [`MoistureRange`](super::structure_manifest::MoistureRange) allows.
+///
+/// Damaged structures craft at [`FLOOD_DAMAGE_CRAFT_SPEED`] of their usual speed.
+/// Flooding never destroys a structure outright: changing water levels should not retroactively undo construction.
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct FloodDamaged;
+
+/// Tracks how long a structure has continuously been in water that is too deep.
+///
+/// This is removed as soon as the water recedes.
+#[derive(Component, Debug, Clone, Default, PartialEq, Eq)]
+pub(crate) struct FloodExposure {
+    /// The time spent flooded so far.
+    time_flooded: Duration,
+}
+
+/// Marks structures that have been flooded for longer than [`FLOOD_DAMAGE_THRESHOLD`] as [`FloodDamaged`].
+pub(super) fn track_flood_damage(
+    fixed_time: Res<FixedTime>,
+    mut structure_query: Query<
+        (
+            Entity,
+            &Id<Structure>,
+            &VoxelPos,
+            &Facing,
+            Option<&mut FloodExposure>,
+        ),
+        (Without<FloodDamaged>, Without<Ghost>, Without<Preview>),
+    >,
+    water_depth_query: Query<&WaterDepth>,
+    structure_manifest: Res<StructureManifest>,
+    map_geometry: Res<MapGeometry>,
+    mut commands: Commands,
+) {
+    for (entity, &structure_id, &center, &facing, maybe_exposure) in structure_query.iter_mut() {
+        let allowed_moisture = match structure_manifest.get(structure_id).allowed_moisture {
+            Some(allowed_moisture) => allowed_moisture,
+            None => continue,
+        };
+
+        let flooded = structure_manifest
+            .footprint(structure_id)
+            .normalized(facing, center)
+            .into_iter()
+            .any(|voxel_pos| {
+                map_geometry
+                    .get_terrain(voxel_pos.hex)
+                    .ok()
+                    .and_then(|terrain_entity| water_depth_query.get(terrain_entity).ok())
+                    .map_or(false, |water_depth| allowed_moisture.too_deep(water_depth))
+            });
+
+        match (flooded, maybe_exposure) {
+            (true, Some(mut exposure)) => {
+                exposure.time_flooded += fixed_time.period;
+                if exposure.time_flooded >= FLOOD_DAMAGE_THRESHOLD {
+                    commands
+                        .entity(entity)
+                        .remove::<FloodExposure>()
+                        .insert(FloodDamaged);
+                }
+            }
+            (true, None) => {
+                commands.entity(entity).insert(FloodExposure {
+                    time_flooded: fixed_time.period,
+                });
+            }
+            (false, Some(_)) => {
+                commands.entity(entity).remove::<FloodExposure>();
+            }
+            (false, None) => (),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        asset_management::manifest::Manifest,
+        geometry::Height,
+        structures::structure_manifest::{MoistureRange, StructureData},
+    };
+    use hexx::Hex;
+
+    /// Creates an app with a single granary, which cannot tolerate any standing water.
+    fn granary_app() -> (App, Entity, Entity) {
+        let mut app = App::new();
+        let map_geometry = MapGeometry::new(&mut app.world, 0);
+        let terrain_entity = map_geometry.get_terrain(Hex::ZERO).unwrap();
+        app.world.entity_mut(terrain_entity).insert(WaterDepth::Dry);
+
+        let mut structure_manifest: StructureManifest = Manifest::new();
+        structure_manifest.insert(
+            "granary".to_string(),
+            StructureData {
+                allowed_moisture: Some(MoistureRange {
+                    min_depth: Height::ZERO,
+                    max_depth: Height::ZERO,
+                    max_water_table_depth: None,
+                }),
+                ..StructureData::impassable()
+            },
+        );
+
+        let granary_entity = app
+            .world
+            .spawn((
+                Id::<Structure>::from_name("granary".to_string()),
+                map_geometry.on_top_of_terrain(Hex::ZERO),
+                Facing::default(),
+            ))
+            .id();
+
+        app.insert_resource(map_geometry)
+            .insert_resource(structure_manifest)
+            .insert_resource(FixedTime::new_from_secs(1.))
+            .add_system(track_flood_damage);
+
+        (app, granary_entity, terrain_entity)
+    }
+
+    #[test]
+    fn sustained_flooding_damages_structures() {
+        let (mut app, granary_entity, terrain_entity) = granary_app();
+        *app.world.get_mut::<WaterDepth>(terrain_entity).unwrap() = WaterDepth::Flooded(Height(1.));
+
+        for _ in 0..FLOOD_DAMAGE_THRESHOLD.as_secs() - 1 {
+            app.update();
+        }
+        assert!(app.world.get::<FloodDamaged>(granary_entity).is_none());
+
+        app.update();
+        assert!(app.world.get::<FloodDamaged>(granary_entity).is_some());
+        // The structure itself is left standing
+        assert!(app.world.get_entity(granary_entity).is_some());
+    }
+
+    #[test]
+    fn receding_water_resets_flood_exposure() {
+        let (mut app, granary_entity, terrain_entity) = granary_app();
+
+        for water_depth in [WaterDepth::Flooded(Height(1.)), WaterDepth::Dry] {
+            *app.world.get_mut::<WaterDepth>(terrain_entity).unwrap() = water_depth;
+            for _ in 0..FLOOD_DAMAGE_THRESHOLD.as_secs() - 1 {
+                app.update();
+            }
+        }
+        assert!(app.world.get::<FloodExposure>(granary_entity).is_none());
+
+        *app.world.get_mut::<WaterDepth>(terrain_entity).unwrap() = WaterDepth::Flooded(Height(1.));
+        for _ in 0..FLOOD_DAMAGE_THRESHOLD.as_secs() - 1 {
+            app.update();
+        }
+        assert!(app.world.get::<FloodDamaged>(granary_entity).is_none());
+    }
+}
@@ -0,0 +1,234 @@
+//! Applies changes to the [`StructureManifest`] to structures that have already been placed.
+//!
+//! When the manifest asset is modified on disk, the manifest resource is replaced wholesale.
+//! Only fields that can be changed without rebuilding a structure are copied onto live entities:
+//! changes that would invalidate the map geometry, such as a new footprint, are logged and otherwise ignored
+//! until the structure is rebuilt.
+
+use bevy::{prelude::*, utils::HashMap};
+
+use crate::{
+    asset_management::manifest::Id,
+    construction::ghosts::{Ghost, Preview},
+    crafting::workers::WorkersPresent,
+};
+
+use super::structure_manifest::{Structure, StructureData, StructureManifest, StructureTags};
+
+/// Keeps placed structures in sync with the [`StructureManifest`] as it is reloaded.
+pub(super) struct StructureHotReloadPlugin;
+
+impl Plugin for StructureHotReloadPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system(
+            apply_structure_manifest_changes
+                .run_if(resource_exists_and_changed::<StructureManifest>()),
+        );
+    }
+}
+
+/// The fields of a [`StructureData`] that changed when the manifest was reloaded.
+#[derive(Debug, Default, Clone, PartialEq)]
+struct StructureDataChanges {
+    /// The new maximum number of workers, if it changed.
+    max_workers: Option<u8>,
+    /// The new tags, if they changed.
+    tags: Option<StructureTags>,
+    /// The names of fields that changed, but cannot be applied to structures that have already been placed.
+    incompatible: Vec<&'static str>,
+}
+
+impl StructureDataChanges {
+    /// Compares the `old` and `new` data for a single structure type.
+    fn diff(old: &StructureData, new: &StructureData) -> Self {
+        let mut changes = StructureDataChanges::default();
+
+        if old.max_workers != new.max_workers {
+            changes.max_workers = Some(new.max_workers);
+        }
+
+        if old.tags != new.tags {
+            changes.tags = Some(new.tags.clone());
+        }
+
+        // These are baked into the map geometry and the components of each structure when it is spawned
+        if old.footprint != new.footprint {
+            changes.incompatible.push("footprint");
+        }
+        if old.kind != new.kind {
+            changes.incompatible.push("kind");
+        }
+        if old.can_walk_through != new.can_walk_through {
+            changes.incompatible.push("can_walk_through");
+        }
+        if old.can_walk_on_roof != new.can_walk_on_roof {
+            changes.incompatible.push("can_walk_on_roof");
+        }
+        if old.max_occupants != new.max_occupants {
+            changes.incompatible.push("max_occupants");
+        }
+
+        changes
+    }
+
+    /// Are there any changes that can be applied to live structures?
+    fn has_compatible_changes(&self) -> bool {
+        self.max_workers.is_some() || self.tags.is_some()
+    }
+}
+
+/// Copies compatible changes in the [`StructureManifest`] onto every placed structure of the changed types.
+///
+/// The previous version of each entry is remembered between runs, so that only fields which actually changed are touched.
+/// Ghosts and previews are left alone, as their components are derived from their construction data instead.
+fn apply_structure_manifest_changes(
+    structure_manifest: Res<StructureManifest>,
+    mut previous: Local<Option<HashMap<Id<Structure>, StructureData>>>,
+    mut structure_query: Query<
+        (
+            &Id<Structure>,
+            Option<&mut WorkersPresent>,
+            Option<&mut StructureTags>,
+        ),
+        (Without<Ghost>, Without<Preview>),
+    >,
+) {
+    let current = structure_manifest.data_map().clone();
+    // The first version of the manifest was used to spawn everything, so there is nothing to update
+    let Some(previous) = previous.replace(current) else { return };
+
+    let mut changes_by_id: HashMap<Id<Structure>, StructureDataChanges> = HashMap::default();
+    for (&structure_id, old_data) in previous.iter() {
+        let structure_name = structure_manifest
+            .try_name(structure_id)
+            .map(str::to_string)
+            .unwrap_or_else(|| format!("{structure_id:?}"));
+
+        let Some(new_data) = structure_manifest.data_map().get(&structure_id) else {
+            warn!("Structure {structure_name} was removed from the manifest: placed copies keep their old data.");
+            continue;
+        };
+
+        let changes = StructureDataChanges::diff(old_data, new_data);
+        if !changes.incompatible.is_empty() {
+            warn!(
+                "Changes to {} of structure {structure_name} cannot be applied to placed copies, and only affect new ones.",
+                changes.incompatible.join(", ")
+            );
+        }
+
+        if changes.has_compatible_changes() {
+            info!("Updating placed copies of structure {structure_name}.");
+            changes_by_id.insert(structure_id, changes);
+        }
+    }
+
+    if changes_by_id.is_empty() {
+        return;
+    }
+
+    for (structure_id, maybe_workers_present, maybe_tags) in structure_query.iter_mut() {
+        let Some(changes) = changes_by_id.get(structure_id) else { continue };
+
+        if let (Some(max_workers), Some(mut workers_present)) =
+            (changes.max_workers, maybe_workers_present)
+        {
+            workers_present.set_allowed(max_workers);
+        }
+
+        if let (Some(tags), Some(mut current_tags)) = (&changes.tags, maybe_tags) {
+            current_tags.set_if_neq(tags.clone());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::structures::Footprint;
+
+    fn mill() -> Id<Structure> {
+        Id::from_name("mill".to_string())
+    }
+
+    fn mill_data(max_workers: u8, footprint: Footprint) -> StructureData {
+        StructureData {
+            max_workers,
+            footprint,
+            ..StructureData::impassable()
+        }
+    }
+
+    /// Creates an app with two placed mills, and a ghost of a third, all allowing a single worker.
+    ///
+    /// Returns the app, the placed mills and the ghost.
+    fn mill_app() -> (App, Vec<Entity>, Entity) {
+        let mut app = App::new();
+        let mut structure_manifest = StructureManifest::new();
+        structure_manifest.insert("mill".to_string(), mill_data(1, Footprint::single()));
+
+        let mills = (0..2)
+            .map(|_| {
+                app.world
+                    .spawn((mill(), WorkersPresent::new(1), Footprint::single()))
+                    .id()
+            })
+            .collect();
+        let ghost = app
+            .world
+            .spawn((mill(), Ghost, WorkersPresent::new(1)))
+            .id();
+
+        app.insert_resource(structure_manifest)
+            .add_plugin(StructureHotReloadPlugin);
+        app.update();
+
+        (app, mills, ghost)
+    }
+
+    fn allowed_workers(app: &App, entity: Entity) -> u8 {
+        app.world.get::<WorkersPresent>(entity).unwrap().allowed()
+    }
+
+    #[test]
+    fn reloading_max_workers_updates_placed_structures() {
+        let (mut app, mills, ghost) = mill_app();
+
+        app.world
+            .resource_mut::<StructureManifest>()
+            .insert("mill".to_string(), mill_data(4, Footprint::single()));
+        app.update();
+
+        for mill_entity in mills {
+            assert_eq!(allowed_workers(&app, mill_entity), 4);
+        }
+        // Construction is staffed according to the construction data, not the finished structure
+        assert_eq!(allowed_workers(&app, ghost), 1);
+    }
+
+    #[test]
+    fn incompatible_changes_are_not_applied_to_placed_structures() {
+        let (mut app, mills, _) = mill_app();
+
+        app.world
+            .resource_mut::<StructureManifest>()
+            .insert("mill".to_string(), mill_data(2, Footprint::hexagon(1)));
+        app.update();
+
+        for mill_entity in mills {
+            assert_eq!(
+                app.world.get::<Footprint>(mill_entity),
+                Some(&Footprint::single())
+            );
+            // Compatible changes in the same reload still go through
+            assert_eq!(allowed_workers(&app, mill_entity), 2);
+        }
+
+        // New structures are built with the reloaded footprint
+        let structure_manifest = app.world.resource::<StructureManifest>();
+        assert_eq!(
+            structure_manifest.get(mill()).footprint,
+            Footprint::hexagon(1)
+        );
+    }
+}
@@ -0,0 +1,309 @@
+//! Incinerators destroy the items delivered to them, acting as a sink for unwanted goods.
+//!
+//! Items carrying the incinerator's precious tag are refused, so valuable goods cannot be burned by accident.
+
+use bevy::prelude::*;
+
+use crate::{
+    crafting::{
+        flow::{record_item_flow, FlowStats, ItemConsumed},
+        inventories::InputInventory,
+        item_tags::{ItemKind, ItemTag},
+        recipe::RecipeInput,
+    },
+    items::{item_manifest::ItemManifest, ItemCount},
+    signals::{Emitter, LogisticsCategory, LogisticsPriorities, SignalType},
+    simulation::SimulationSet,
+};
+
+use super::StructureActivity;
+
+/// The number of slots in the input inventory of each incinerator.
+pub(crate) const INCINERATOR_SLOT_COUNT: usize = 1;
+
+/// A building that destroys the items in its input inventory.
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Incinerates {
+    /// The maximum number of items destroyed each fixed update.
+    pub(crate) throughput: u32,
+}
+
+/// Logic that controls how incinerators destroy items.
+pub(super) struct IncinerationPlugin;
+
+impl Plugin for IncinerationPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            (
+                incinerate_items,
+                incinerator_signals.after(incinerate_items),
+            )
+                // Items destroyed should be counted on the same tick
+                .before(record_item_flow)
+                .in_set(SimulationSet)
+                .in_schedule(CoreSchedule::FixedUpdate),
+        );
+    }
+}
+
+/// Destroys up to `throughput` items from the input inventory of each incinerator.
+fn incinerate_items(
+    mut incinerator_query: Query<(
+        Entity,
+        &Incinerates,
+        &mut InputInventory,
+        &mut FlowStats,
+        Option<&StructureActivity>,
+    )>,
+    item_manifest: Res<ItemManifest>,
+    mut consumed_events: EventWriter<ItemConsumed>,
+) {
+    for (structure_entity, incinerates, mut input_inventory, mut flow_stats, maybe_activity) in
+        incinerator_query.iter_mut()
+    {
+        if StructureActivity::is_paused(maybe_activity) {
+            continue;
+        }
+
+        let mut remaining = incinerates.throughput;
+        let mut item_counts: Vec<ItemCount> = Vec::new();
+        for item_slot in input_inventory.iter() {
+            let count = item_slot.count().min(remaining);
+            if count == 0 {
+                continue;
+            }
+            remaining -= count;
+
+            // Merge slots of the same item, so that each item is only removed once
+            match item_counts
+                .iter_mut()
+                .find(|item_count| item_count.item_id == item_slot.item_id())
+            {
+                Some(item_count) => item_count.count += count,
+                None => item_counts.push(ItemCount::new(item_slot.item_id(), count)),
+            }
+        }
+
+        let n_destroyed: u32 = item_counts.iter().map(|item_count| item_count.count).sum();
+        if n_destroyed == 0 {
+            continue;
+        }
+
+        // We only consume items that we just counted, so this cannot fail
+        let consumed = input_inventory
+            .consume_items(&RecipeInput::Exact(item_counts), &[], &item_manifest)
+            .unwrap();
+        // Free up the slots so that other kinds of items can be delivered
+        input_inventory.clear_empty_slots();
+        flow_stats.record_destroyed(n_destroyed);
+
+        consumed_events.send_batch(consumed.into_iter().map(|item_count| ItemConsumed {
+            structure: structure_entity,
+            item_count,
+        }));
+    }
+}
+
+/// Returns the kinds of item that an incinerator with the provided `input_inventory` should pull in.
+///
+/// If the incinerator protects a precious tag, or accepts any item at all,
+/// each permitted item is pulled individually so that precious items are never requested.
+fn pulled_kinds(input_inventory: &InputInventory, item_manifest: &ItemManifest) -> Vec<ItemKind> {
+    let InputInventory::Filtered {
+        accepts, refuses, ..
+    } = input_inventory
+    else {
+        return Vec::new();
+    };
+
+    if let (Some(kind), None) = (accepts, refuses) {
+        return vec![*kind];
+    }
+
+    item_manifest
+        .variants()
+        // Fluids cannot be delivered by units, so we don't emit signals for them
+        .filter(|&item_id| !item_manifest.has_tag(item_id, ItemTag::Fluid))
+        .filter(|&item_id| input_inventory.filter_allows(item_id, item_manifest))
+        .map(ItemKind::Single)
+        .collect()
+}
+
+/// Sets the emitters for incinerators, which pull in any item they accept.
+pub(super) fn incinerator_signals(
+    mut incinerator_query: Query<
+        (&mut Emitter, &InputInventory, Option<&StructureActivity>),
+        With<Incinerates>,
+    >,
+    item_manifest: Res<ItemManifest>,
+    priorities: Res<LogisticsPriorities>,
+) {
+    /// Controls how strong the signal is for incinerators.
+    const INCINERATOR_SIGNAL_STRENGTH: f32 = 5.;
+
+    let signal_strength =
+        priorities.signal_strength(LogisticsCategory::Crafting, INCINERATOR_SIGNAL_STRENGTH);
+
+    for (mut emitter, input_inventory, maybe_activity) in incinerator_query.iter_mut() {
+        emitter.signals.clear();
+        if StructureActivity::is_paused(maybe_activity) {
+            continue;
+        }
+
+        if !input_inventory.inventory().is_full() {
+            for item_kind in pulled_kinds(input_inventory, &item_manifest) {
+                emitter
+                    .signals
+                    .push((SignalType::Pull(item_kind), signal_strength));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        asset_management::manifest::{Id, Manifest},
+        crafting::{
+            flow::{ItemFlowStats, ItemProduced},
+            inventories::AddToInputError,
+        },
+        items::{
+            inventory::Inventory,
+            item_manifest::{Item, ItemData},
+        },
+        organisms::OrganismId,
+    };
+
+    fn ash() -> Id<Item> {
+        Id::from_name("ash".to_string())
+    }
+
+    fn acorn() -> Id<Item> {
+        Id::from_name("acorn".to_string())
+    }
+
+    fn item_data(compostable: bool, seed: bool) -> ItemData {
+        ItemData {
+            compostable,
+            seed: seed.then(|| OrganismId::Structure(Id::from_name("oak".to_string()))),
+            ..ItemData::basic(10)
+        }
+    }
+
+    fn item_manifest() -> ItemManifest {
+        let mut manifest = Manifest::new();
+        manifest.insert("ash".to_string(), item_data(true, false));
+        manifest.insert("acorn".to_string(), item_data(true, true));
+        manifest
+    }
+
+    /// Creates an app with a single incinerator that accepts compostable items but protects seeds.
+    fn incinerator_app(throughput: u32) -> (App, Entity) {
+        let mut app = App::new();
+
+        let incinerator_entity = app
+            .world
+            .spawn((
+                Incinerates { throughput },
+                InputInventory::Filtered {
+                    accepts: Some(ItemKind::Tag(ItemTag::Compostable)),
+                    refuses: Some(ItemTag::Seed),
+                    inventory: Inventory::new(INCINERATOR_SLOT_COUNT, None),
+                },
+                FlowStats::default(),
+                Emitter::default(),
+            ))
+            .id();
+
+        app.insert_resource(item_manifest())
+            .insert_resource(FixedTime::new_from_secs(1.))
+            .init_resource::<LogisticsPriorities>()
+            .init_resource::<ItemFlowStats>()
+            .add_event::<ItemConsumed>()
+            .add_event::<ItemProduced>()
+            .add_systems((incinerate_items, incinerator_signals, record_item_flow).chain());
+
+        (app, incinerator_entity)
+    }
+
+    fn feed_ash(app: &mut App, incinerator_entity: Entity, count: u32) {
+        app.world
+            .get_mut::<InputInventory>(incinerator_entity)
+            .unwrap()
+            .fill_with_items(&ItemCount::new(ash(), count), &item_manifest())
+            .unwrap();
+    }
+
+    fn ash_remaining(app: &App, incinerator_entity: Entity) -> u32 {
+        app.world
+            .get::<InputInventory>(incinerator_entity)
+            .unwrap()
+            .inventory()
+            .item_count(ash())
+    }
+
+    #[test]
+    fn incinerators_destroy_at_most_their_throughput_each_tick() {
+        let (mut app, incinerator_entity) = incinerator_app(3);
+        feed_ash(&mut app, incinerator_entity, 10);
+
+        app.update();
+        assert_eq!(ash_remaining(&app, incinerator_entity), 7);
+
+        app.update();
+        assert_eq!(ash_remaining(&app, incinerator_entity), 4);
+
+        // The last partial batch is destroyed, and the emptied slot is freed up
+        for _ in 0..2 {
+            app.update();
+        }
+        let input_inventory = app.world.get::<InputInventory>(incinerator_entity).unwrap();
+        assert!(input_inventory.is_empty());
+    }
+
+    #[test]
+    fn precious_items_are_refused_even_when_they_match() {
+        let (mut app, incinerator_entity) = incinerator_app(3);
+        let item_manifest = item_manifest();
+
+        // Acorns are compostable, but they are also seeds
+        let mut input_inventory = app
+            .world
+            .get_mut::<InputInventory>(incinerator_entity)
+            .unwrap();
+        assert!(!input_inventory.currently_accepts(acorn(), &item_manifest));
+        assert!(matches!(
+            input_inventory.fill_with_items(&ItemCount::one(acorn()), &item_manifest),
+            Err(AddToInputError::IncorrectItemTags)
+        ));
+        assert!(input_inventory.currently_accepts(ash(), &item_manifest));
+
+        app.update();
+
+        let emitter = app.world.get::<Emitter>(incinerator_entity).unwrap();
+        let pulled: Vec<&SignalType> = emitter.signals.iter().map(|(signal, _)| signal).collect();
+        assert_eq!(pulled, vec![&SignalType::Pull(ItemKind::Single(ash()))]);
+    }
+
+    #[test]
+    fn destroyed_items_are_counted_in_flow_stats() {
+        let (mut app, incinerator_entity) = incinerator_app(4);
+        feed_ash(&mut app, incinerator_entity, 6);
+
+        app.update();
+        let flow_stats = app.world.get::<FlowStats>(incinerator_entity).unwrap();
+        assert_eq!(flow_stats.destroyed, 4);
+        assert!(flow_stats.consumed_per_min() > 0.);
+
+        app.update();
+        let flow_stats = app.world.get::<FlowStats>(incinerator_entity).unwrap();
+        assert_eq!(flow_stats.destroyed, 6);
+
+        // Nothing is left to burn
+        app.update();
+        let flow_stats = app.world.get::<FlowStats>(incinerator_entity).unwrap();
+        assert_eq!(flow_stats.destroyed, 6);
+    }
+}
@@ -1,120 +1,676 @@
 //! Logic for buildings that move items around.
 
+use core::fmt::Display;
+
 use bevy::prelude::*;
+use hexx::Direction;
 
 use crate::{
+    asset_management::manifest::Id,
     crafting::{
-        inventories::{InputInventory, OutputInventory},
-        item_tags::ItemKind,
+        flow::{record_item_flow, FlowStats, ItemConsumed, ItemProduced},
+        inventories::{InputInventory, OutputInventory, StorageInventory},
+        item_tags::{ItemKind, ItemTag},
         recipe::RecipeInput,
     },
-    geometry::{Facing, Height, MapGeometry, VoxelPos},
-    items::item_manifest::ItemManifest,
-    litter::Litter,
-    signals::{Emitter, SignalStrength, SignalType},
-    simulation::SimulationSet,
-    water::WaterDepth,
+    geometry::{DiscreteHeight, Facing, Height, MapGeometry, Volume, VoxelPos},
+    items::{
+        errors::AddOneItemError,
+        item_manifest::{Item, ItemManifest},
+        ItemCount,
+    },
+    litter::{update_litter_index, Litter, LitterIndex},
+    signals::{
+        Emitter, LogisticsCategory, LogisticsPriorities, SignalStrength, SignalType, Signals,
+    },
+    simulation::{
+        alerts::{AlertCategory, AlertKind, AlertLog},
+        time::InGameTime,
+        SimulationSet,
+    },
+    units::{
+        actions::{DeliveryMode, Purpose},
+        item_interaction::{count_in_flight_items, InFlightItems},
+    },
+    water::{WaterDepth, WaterVolume},
 };
 
-use super::Footprint;
+use super::{Footprint, StructureActivity};
 
 /// A building that spits out items.
-#[derive(Component)]
-pub(crate) struct ReleasesItems;
+#[derive(Component, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub(crate) struct ReleasesItems {
+    /// The number of items that must accumulate in a slot before they are released as a single batch.
+    ///
+    /// If this is [`None`], items are released as soon as they arrive.
+    pub(crate) release_threshold: Option<u32>,
+}
+
+impl ReleasesItems {
+    /// The minimum number of items that must be buffered in a slot before any are released.
+    pub(crate) fn batch_size(&self) -> u32 {
+        self.release_threshold.unwrap_or(1).max(1)
+    }
+}
+
+/// Limits the distance at which the logistic signals of a building can be detected, so that logistics stays local.
+///
+/// Logistic buildings without this component use [`SignalRange::default`].
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SignalRange {
+    /// The maximum distance, in tiles, at which signals can be detected.
+    pub max_range: u32,
+}
+
+impl Default for SignalRange {
+    fn default() -> Self {
+        SignalRange { max_range: 12 }
+    }
+}
 
 /// A building that takes in items.
-#[derive(Component)]
-pub(crate) struct AbsorbsItems;
+#[derive(Component, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub(crate) struct AbsorbsItems {
+    /// Should full stacks be pushed into adjacent storage, so that absorption can continue?
+    pub(crate) overflow_to_adjacent: bool,
+}
+
+/// A building that fills empty water containers with the surface water of the tile it stands on.
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct PumpsWater {
+    /// The filled water container produced.
+    pub(crate) filled_item: Id<Item>,
+}
+
+/// Is this logistic building currently moving items?
+///
+/// This is updated each tick by the systems that move items, and is intended for diagnosing jammed logistics.
+#[derive(Component, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum LogisticStatus {
+    /// The building has nothing to move.
+    #[default]
+    Idle,
+    /// The building moved items this tick.
+    Flowing,
+    /// The building has items to move, but cannot move any of them.
+    Blocked {
+        /// Why the items could not be moved.
+        reason: BlockedReason,
+    },
+}
+
+/// Why a logistic building is [`LogisticStatus::Blocked`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BlockedReason {
+    /// The litter on the tile that items are released onto is full.
+    LitterFull,
+    /// The building's own output inventory is full.
+    OutputFull,
+    /// The tile that items are released onto is too far above the building.
+    TooSteep,
+}
+
+impl LogisticStatus {
+    /// Is this building unable to move the items it holds?
+    ///
+    /// Blocked buildings stop advertising for haulers, since any work they ask for could not be completed.
+    pub(crate) fn is_blocked(maybe_status: Option<&LogisticStatus>) -> bool {
+        matches!(maybe_status, Some(LogisticStatus::Blocked { .. }))
+    }
+}
+
+impl Display for LogisticStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LogisticStatus::Idle => write!(f, "Idle"),
+            LogisticStatus::Flowing => write!(f, "Flowing"),
+            LogisticStatus::Blocked {
+                reason: BlockedReason::LitterFull,
+            } => write!(f, "Blocked (litter is full)"),
+            LogisticStatus::Blocked {
+                reason: BlockedReason::OutputFull,
+            } => write!(f, "Blocked (output is full)"),
+            LogisticStatus::Blocked {
+                reason: BlockedReason::TooSteep,
+            } => write!(f, "Blocked (release tile is too high)"),
+        }
+    }
+}
 
 /// Logic that controls how items are moved around by structures.
-pub(super) struct LogisticsPlugin;
+pub(crate) struct LogisticsPlugin;
 
 impl Plugin for LogisticsPlugin {
     fn build(&self, app: &mut App) {
         app.add_systems(
-            (release_items, absorb_items, logistic_buildings_signals)
+            (
+                release_items,
+                absorb_items.after(update_litter_index),
+                overflow_to_adjacent_storage.after(absorb_items),
+                pump_water,
+                // Signals respond to blockages on the same tick that they are detected
+                logistic_buildings_signals
+                    .after(release_items)
+                    .after(absorb_items)
+                    // Changes to in-flight items are only reported for a single tick
+                    .after(count_in_flight_items),
+                raise_logistics_alerts
+                    .after(release_items)
+                    .after(absorb_items),
+            )
+                // Items moved by logistic buildings should be counted on the same tick
+                .before(record_item_flow)
                 .in_set(SimulationSet)
                 .in_schedule(CoreSchedule::FixedUpdate),
         );
     }
 }
 
+/// The highest that a releaser can lift items onto the tile in front of it.
+const MAX_RELEASE_RISE: DiscreteHeight = DiscreteHeight::ONE;
+
+/// Fragile items dropped further than this onto the tile in front of a releaser may break.
+const FRAGILE_DROP_THRESHOLD: DiscreteHeight = DiscreteHeight(2);
+
+/// The fraction of fragile items that break when dropped further than [`FRAGILE_DROP_THRESHOLD`].
+const FRAGILE_BREAKAGE_FRACTION: f32 = 0.5;
+
+/// Returns the facing that points a releaser of `released_item` at the strongest [`SignalType::Pull`] signal for that item.
+///
+/// Each direction is tried in turn, measuring the signal on the tile that items would be released onto.
+/// Mirrored structures stay mirrored: only the direction is changed.
+/// If nothing nearby is pulling harder than at the current `facing`, it is returned unchanged.
+pub(crate) fn face_strongest_pull(
+    released_item: Id<Item>,
+    center: VoxelPos,
+    footprint: &Footprint,
+    facing: Facing,
+    signals: &Signals,
+    item_manifest: &ItemManifest,
+) -> Facing {
+    let signal_types = SignalType::item_signal_types(
+        ItemKind::Single(released_item),
+        item_manifest,
+        DeliveryMode::DropOff,
+        Purpose::Intrinsic,
+    );
+
+    let pull_strength = |candidate: Facing| {
+        let release_pos = (center + footprint.edge_tile(candidate)).neighbor(candidate.direction);
+        signal_types
+            .iter()
+            .fold(SignalStrength::ZERO, |total, &signal_type| {
+                total + signals.get(signal_type, release_pos)
+            })
+    };
+
+    let mut best_facing = facing;
+    let mut best_strength = pull_strength(facing);
+    for direction in Direction::ALL_DIRECTIONS {
+        let candidate = Facing {
+            direction,
+            mirrored: facing.mirrored,
+        };
+        let strength = pull_strength(candidate);
+        if strength > best_strength {
+            best_facing = candidate;
+            best_strength = strength;
+        }
+    }
+
+    best_facing
+}
+
 /// Causes buildings that emit items to place them in the litter in front of them.
+///
+/// Items are held until the number buffered in a slot reaches the [`ReleasesItems::batch_size`],
+/// at which point as much of the batch as will fit is released at once.
+/// Any items that don't fit stay in the buffer and count towards the next batch.
+///
+/// Filled water containers released onto a tile without surface water are poured out instead,
+/// adding their water to the tile and leaving their empty containers behind as litter.
+///
+/// Items can be dropped down cliffs, but cannot be lifted more than [`MAX_RELEASE_RISE`].
+/// Some of the [`ItemTag::Fragile`] items dropped more than [`FRAGILE_DROP_THRESHOLD`] are destroyed on landing.
 fn release_items(
-    mut structure_query: Query<(&VoxelPos, &Facing, &mut InputInventory), With<ReleasesItems>>,
+    mut structure_query: Query<(
+        Entity,
+        &VoxelPos,
+        &Facing,
+        &Footprint,
+        &ReleasesItems,
+        &mut InputInventory,
+        Option<&StructureActivity>,
+        Option<&mut LogisticStatus>,
+        Option<&mut FlowStats>,
+    )>,
     mut litter_query: Query<&mut Litter>,
+    mut water_query: Query<(&WaterDepth, &mut WaterVolume)>,
     item_manifest: Res<ItemManifest>,
     map_geometry: Res<MapGeometry>,
+    mut consumed_events: EventWriter<ItemConsumed>,
 ) {
-    for (structure_pos, structure_facing, mut input_inventory) in structure_query.iter_mut() {
-        let voxel_pos = structure_pos.neighbor(structure_facing.direction);
+    for (
+        structure_entity,
+        structure_pos,
+        structure_facing,
+        footprint,
+        releases_items,
+        mut input_inventory,
+        maybe_activity,
+        maybe_status,
+        mut maybe_flow_stats,
+    ) in structure_query.iter_mut()
+    {
+        // Paused releasers hold on to everything in their buffer
+        if StructureActivity::is_paused(maybe_activity) {
+            if let Some(mut status) = maybe_status {
+                status.set_if_neq(LogisticStatus::Idle);
+            }
+            continue;
+        }
+
+        // Items are released in front of the footprint, rather than inside of it
+        let edge_pos = *structure_pos + footprint.edge_tile(*structure_facing);
+        let voxel_pos = edge_pos.neighbor(structure_facing.direction);
 
         let litter_entity = map_geometry.get_terrain(voxel_pos.hex).unwrap();
         let mut litter = litter_query.get_mut(litter_entity).unwrap();
 
+        let base_height = map_geometry
+            .get_height(structure_pos.hex)
+            .unwrap_or_default();
+        let target_height = map_geometry.get_height(voxel_pos.hex).unwrap_or_default();
+        let too_steep = target_height - base_height > MAX_RELEASE_RISE;
+        let drop = base_height - target_height;
+
+        let mut status = LogisticStatus::Idle;
         let cloned_inventory = input_inventory.clone();
         for item_slot in cloned_inventory.iter() {
-            let item_count = item_slot.item_count();
+            let buffered_count = item_slot.count();
 
-            if litter
-                .contents
-                .add_item_all_or_nothing(&item_count, &item_manifest)
-                .is_ok()
+            // Keep accumulating items until we have a full batch
+            if buffered_count == 0 || buffered_count < releases_items.batch_size() {
+                continue;
+            }
+
+            if too_steep {
+                status = LogisticStatus::Blocked {
+                    reason: BlockedReason::TooSteep,
+                };
+                continue;
+            }
+
+            let broken_count = if drop > FRAGILE_DROP_THRESHOLD
+                && item_manifest.has_tag(item_slot.item_id(), ItemTag::Fragile)
             {
-                let recipe_input = RecipeInput::Exact(vec![item_count]);
-                input_inventory
-                    .consume_items(&recipe_input, &item_manifest)
+                (buffered_count as f32 * FRAGILE_BREAKAGE_FRACTION).floor() as u32
+            } else {
+                0
+            };
+            let landed_count = buffered_count - broken_count;
+
+            let is_dry = water_query
+                .get(litter_entity)
+                .map_or(false, |(water_depth, _)| {
+                    water_depth.surface_water_depth() == Height::ZERO
+                });
+            let poured_water = item_manifest
+                .get(item_slot.item_id())
+                .water_contents
+                .filter(|_| is_dry);
+
+            let item_count = match poured_water {
+                Some(water_contents) => {
+                    ItemCount::new(water_contents.empty_container, landed_count)
+                }
+                None => ItemCount::new(item_slot.item_id(), landed_count),
+            };
+            let landed_count = match litter.try_add_item(&item_count, &item_manifest) {
+                Ok(()) => landed_count,
+                Err(AddOneItemError { excess_count }) => landed_count - excess_count.count,
+            };
+
+            // Nothing breaks unless the batch actually goes over the edge
+            if landed_count == 0 {
+                if status == LogisticStatus::Idle {
+                    status = LogisticStatus::Blocked {
+                        reason: BlockedReason::LitterFull,
+                    };
+                }
+            } else {
+                status = LogisticStatus::Flowing;
+                let released_count = landed_count + broken_count;
+
+                if broken_count > 0 {
+                    if let Some(flow_stats) = maybe_flow_stats.as_mut() {
+                        flow_stats.record_destroyed(broken_count);
+                    }
+                }
+
+                if let Some(water_contents) = poured_water {
+                    let (_, mut water_volume) = water_query.get_mut(litter_entity).unwrap();
+                    water_volume.add(water_contents.volume * released_count as f32);
+                }
+
+                let recipe_input =
+                    RecipeInput::Exact(vec![ItemCount::new(item_slot.item_id(), released_count)]);
+                let consumed = input_inventory
+                    .consume_items(&recipe_input, &[], &item_manifest)
                     .unwrap();
+
+                consumed_events.send_batch(consumed.into_iter().map(|item_count| ItemConsumed {
+                    structure: structure_entity,
+                    item_count,
+                }));
             }
         }
+
+        if let Some(mut current_status) = maybe_status {
+            current_status.set_if_neq(status);
+        }
     }
 }
 
 /// Absorb litter into the inventory of buildings that absorb items.
 fn absorb_items(
-    mut structure_query: Query<(&VoxelPos, &Footprint, &mut OutputInventory), With<AbsorbsItems>>,
+    mut structure_query: Query<
+        (
+            Entity,
+            &VoxelPos,
+            &Footprint,
+            &mut OutputInventory,
+            Option<&StructureActivity>,
+            Option<&mut LogisticStatus>,
+        ),
+        With<AbsorbsItems>,
+    >,
     mut litter_query: Query<&mut Litter>,
     item_manifest: Res<ItemManifest>,
     water_depth_query: Query<&WaterDepth>,
     map_geometry: Res<MapGeometry>,
+    litter_index: Res<LitterIndex>,
+    mut produced_events: EventWriter<ItemProduced>,
 ) {
-    for (&voxel_pos, footprint, mut output_inventory) in structure_query.iter_mut() {
+    for (
+        structure_entity,
+        &voxel_pos,
+        footprint,
+        mut output_inventory,
+        maybe_activity,
+        maybe_status,
+    ) in structure_query.iter_mut()
+    {
+        if StructureActivity::is_paused(maybe_activity) {
+            if let Some(mut status) = maybe_status {
+                status.set_if_neq(LogisticStatus::Idle);
+            }
+            continue;
+        }
+
         output_inventory.clear_empty_slots();
 
-        if output_inventory.is_full() {
+        // Most tiles have no litter on them, so skip looking up their contents entirely
+        if !litter_index.has_litter(voxel_pos.hex) {
+            if let Some(mut status) = maybe_status {
+                status.set_if_neq(LogisticStatus::Idle);
+            }
             continue;
         }
 
         let litter_entity = map_geometry.get_terrain(voxel_pos.hex).unwrap();
         let mut litter = litter_query.get_mut(litter_entity).unwrap();
 
-        let on_ground = litter.contents.clone();
+        // Nothing to absorb, so nothing can be stuck
+        let status = if litter.contents.is_empty() {
+            LogisticStatus::Idle
+        } else if output_inventory.is_full() {
+            LogisticStatus::Blocked {
+                reason: BlockedReason::OutputFull,
+            }
+        } else {
+            let mut absorbed_any = false;
+            let on_ground = litter.contents.clone();
 
-        for item_slot in on_ground.iter() {
-            let item_count = item_slot.item_count();
+            for item_slot in on_ground.iter() {
+                let item_count = item_slot.item_count();
 
-            if output_inventory
-                .add_item_all_or_nothing(&item_count, &item_manifest)
-                .is_ok()
-            {
-                litter.contents.try_remove_item(&item_count).unwrap();
+                if output_inventory
+                    .add_item_all_or_nothing(&item_count, &item_manifest)
+                    .is_ok()
+                {
+                    litter.contents.try_remove_item(&item_count).unwrap();
+                    absorbed_any = true;
+                    produced_events.send(ItemProduced {
+                        structure: structure_entity,
+                        item_count,
+                    });
+                }
             }
+
+            // Only absorb floating items if the structure is tall enough.
+            let terrain_entity = map_geometry.get_terrain(voxel_pos.hex).unwrap();
+            let water_depth = water_depth_query.get(terrain_entity).unwrap();
+
+            if Height::from(footprint.max_height()) > water_depth.surface_water_depth() {
+                let floating = litter.contents.clone();
+                for item_slot in floating.iter() {
+                    let item_count = item_slot.item_count();
+
+                    if output_inventory
+                        .add_item_all_or_nothing(&item_count, &item_manifest)
+                        .is_ok()
+                    {
+                        litter.contents.try_remove_item(&item_count).unwrap();
+                        absorbed_any = true;
+                        produced_events.send(ItemProduced {
+                            structure: structure_entity,
+                            item_count,
+                        });
+                    }
+                }
+            }
+
+            match absorbed_any {
+                true => LogisticStatus::Flowing,
+                false => LogisticStatus::Blocked {
+                    reason: BlockedReason::OutputFull,
+                },
+            }
+        };
+
+        if let Some(mut current_status) = maybe_status {
+            current_status.set_if_neq(status);
+        }
+    }
+}
+
+/// Raises alerts for releasers and absorbers that have been blocked for too long,
+/// and resolves them once items can flow again.
+fn raise_logistics_alerts(
+    status_query: Query<(
+        Entity,
+        &LogisticStatus,
+        Option<&ReleasesItems>,
+        Option<&AbsorbsItems>,
+    )>,
+    in_game_time: Res<InGameTime>,
+    mut alert_log: ResMut<AlertLog>,
+) {
+    for (entity, status, maybe_releaser, maybe_absorber) in status_query.iter() {
+        let blocked = matches!(status, LogisticStatus::Blocked { .. });
+
+        if maybe_releaser.is_some() {
+            alert_log.observe(
+                entity,
+                AlertCategory::ReleaserBlocked,
+                blocked,
+                &in_game_time,
+                |since| AlertKind::ReleaserBlocked { entity, since },
+            );
+        }
+
+        if maybe_absorber.is_some() {
+            alert_log.observe(
+                entity,
+                AlertCategory::AbsorberFull,
+                blocked,
+                &in_game_time,
+                |since| AlertKind::AbsorberFull { entity, since },
+            );
+        }
+    }
+}
+
+/// Fills the empty water containers of well pumps with the surface water beneath them.
+///
+/// Each container holds a fixed volume of water, so the number filled each tick is limited by the depth of the surface water.
+/// The water used is removed from the tile.
+fn pump_water(
+    mut pump_query: Query<(
+        Entity,
+        &VoxelPos,
+        &PumpsWater,
+        &mut InputInventory,
+        &mut OutputInventory,
+        Option<&StructureActivity>,
+    )>,
+    mut water_query: Query<(&WaterDepth, &mut WaterVolume)>,
+    item_manifest: Res<ItemManifest>,
+    map_geometry: Res<MapGeometry>,
+    mut consumed_events: EventWriter<ItemConsumed>,
+    mut produced_events: EventWriter<ItemProduced>,
+) {
+    for (
+        structure_entity,
+        voxel_pos,
+        pumps_water,
+        mut input_inventory,
+        mut output_inventory,
+        maybe_activity,
+    ) in pump_query.iter_mut()
+    {
+        if StructureActivity::is_paused(maybe_activity) {
+            continue;
+        }
+
+        let filled_item = pumps_water.filled_item;
+        let Some(water_contents) = item_manifest.get(filled_item).water_contents else { continue };
+        if water_contents.volume <= Volume::ZERO {
+            continue;
         }
 
-        // Only absorb floating items if the structure is tall enough.
         let terrain_entity = map_geometry.get_terrain(voxel_pos.hex).unwrap();
-        let water_depth = water_depth_query.get(terrain_entity).unwrap();
+        let Ok((water_depth, mut water_volume)) = water_query.get_mut(terrain_entity) else { continue };
 
-        if Height::from(footprint.max_height()) > water_depth.surface_water_depth() {
-            let floating = litter.contents.clone();
-            for item_slot in floating.iter() {
-                let item_count = item_slot.item_count();
+        // Only water above the soil surface can be pumped
+        let surface_water =
+            Volume::from_height(water_depth.surface_water_depth()).min(water_volume.volume());
+        let fillable_count = (surface_water / water_contents.volume).floor() as u32;
 
-                if output_inventory
+        let fill_count = fillable_count
+            .min(
+                input_inventory
+                    .inventory()
+                    .item_count(water_contents.empty_container),
+            )
+            .min(output_inventory.remaining_space_for_item(filled_item, &item_manifest));
+
+        if fill_count == 0 {
+            continue;
+        }
+
+        let recipe_input = RecipeInput::Exact(vec![ItemCount::new(
+            water_contents.empty_container,
+            fill_count,
+        )]);
+        // We checked that there are enough empty containers above
+        let consumed = input_inventory
+            .consume_items(&recipe_input, &[], &item_manifest)
+            .unwrap();
+        let filled = ItemCount::new(filled_item, fill_count);
+        // We checked that there is enough space above
+        output_inventory
+            .add_item_all_or_nothing(&filled, &item_manifest)
+            .unwrap();
+        water_volume.remove(water_contents.volume * fill_count as f32);
+
+        consumed_events.send_batch(consumed.into_iter().map(|item_count| ItemConsumed {
+            structure: structure_entity,
+            item_count,
+        }));
+        produced_events.send(ItemProduced {
+            structure: structure_entity,
+            item_count: filled,
+        });
+    }
+}
+
+/// Moves full stacks out of absorbers into adjacent storage, freeing up space to absorb more items.
+///
+/// This only applies to absorbers with [`AbsorbsItems::overflow_to_adjacent`] set, once their own inventory is full.
+/// Each stack is moved in its entirety into the nearest storage that can fit it, or not at all.
+fn overflow_to_adjacent_storage(
+    mut absorber_query: Query<(
+        Entity,
+        &VoxelPos,
+        &Facing,
+        &Footprint,
+        &AbsorbsItems,
+        &mut OutputInventory,
+        Option<&StructureActivity>,
+    )>,
+    mut storage_query: Query<&mut StorageInventory>,
+    item_manifest: Res<ItemManifest>,
+    map_geometry: Res<MapGeometry>,
+) {
+    for (
+        absorber_entity,
+        &voxel_pos,
+        &facing,
+        footprint,
+        absorbs_items,
+        mut output_inventory,
+        maybe_activity,
+    ) in absorber_query.iter_mut()
+    {
+        if !absorbs_items.overflow_to_adjacent || StructureActivity::is_paused(maybe_activity) {
+            continue;
+        }
+
+        if !output_inventory.is_full() {
+            continue;
+        }
+
+        // Large absorbers reach out from the front edge of their footprint
+        let edge_pos = voxel_pos + footprint.edge_tile(facing);
+        let adjacent_structures: Vec<Entity> = map_geometry
+            .structures_in_radius(edge_pos, 1)
+            .into_iter()
+            .map(|(_, structure_entity)| structure_entity)
+            .filter(|&structure_entity| structure_entity != absorber_entity)
+            .collect();
+
+        let full_stacks: Vec<ItemCount> = output_inventory
+            .iter()
+            .filter(|item_slot| item_slot.is_full())
+            .map(|item_slot| item_slot.item_count())
+            .collect();
+
+        for item_count in full_stacks {
+            for &structure_entity in &adjacent_structures {
+                let Ok(mut storage_inventory) = storage_query.get_mut(structure_entity) else { continue };
+
+                // This respects any reservations on the storage inventory
+                if storage_inventory
                     .add_item_all_or_nothing(&item_count, &item_manifest)
                     .is_ok()
                 {
-                    litter.contents.try_remove_item(&item_count).unwrap();
+                    // This stack was counted from the inventory above, so it must still be there
+                    output_inventory
+                        .remove_item_all_or_nothing(&item_count)
+                        .unwrap();
+                    break;
                 }
             }
         }
@@ -122,40 +678,133 @@ fn absorb_items(
 }
 
 /// Sets the emitters for logistic buildings.
-fn logistic_buildings_signals(
+///
+/// Signals can only be detected within the [`SignalRange`] of each building.
+/// Releasers and absorbers that are [`LogisticStatus::Blocked`] fall silent until they can move items again.
+/// Signals only depend on the inventory, activity, range and status of each building,
+/// so buildings where none of these have changed keep the signals they emitted last time.
+/// Every building is refreshed whenever the [`LogisticsPriorities`] change,
+/// and each building is refreshed whenever the [`InFlightItems`] being carried to it change.
+pub(crate) fn logistic_buildings_signals(
     mut release_query: Query<
-        (&mut Emitter, &mut InputInventory),
-        (With<ReleasesItems>, Without<AbsorbsItems>),
+        (
+            Entity,
+            &mut Emitter,
+            &InputInventory,
+            Option<&StructureActivity>,
+            Option<&SignalRange>,
+            Option<&LogisticStatus>,
+        ),
+        (
+            With<ReleasesItems>,
+            Without<AbsorbsItems>,
+            Without<PumpsWater>,
+        ),
     >,
     mut absorb_query: Query<
-        (&mut Emitter, &mut OutputInventory),
-        (With<AbsorbsItems>, Without<ReleasesItems>),
+        (
+            Entity,
+            &mut Emitter,
+            &OutputInventory,
+            Option<&StructureActivity>,
+            Option<&SignalRange>,
+            Option<&LogisticStatus>,
+        ),
+        (
+            With<AbsorbsItems>,
+            Without<ReleasesItems>,
+            Without<PumpsWater>,
+        ),
     >,
+    mut pump_query: Query<
+        (
+            Entity,
+            &mut Emitter,
+            &InputInventory,
+            &OutputInventory,
+            Option<&StructureActivity>,
+            Option<&SignalRange>,
+        ),
+        (
+            With<PumpsWater>,
+            Without<ReleasesItems>,
+            Without<AbsorbsItems>,
+        ),
+    >,
+    outdated_query: Query<
+        (),
+        Or<(
+            Changed<InputInventory>,
+            Changed<OutputInventory>,
+            Changed<StructureActivity>,
+            Changed<SignalRange>,
+            Changed<LogisticStatus>,
+        )>,
+    >,
+    priorities: Res<LogisticsPriorities>,
+    in_flight_items: Res<InFlightItems>,
 ) {
     /// Controls how strong the signal is for logistic buildings.
     const LOGISTIC_SIGNAL_STRENGTH: f32 = 10.;
 
-    let signal_strength = SignalStrength::new(LOGISTIC_SIGNAL_STRENGTH);
+    let refresh_all = priorities.is_changed();
+    let is_outdated = |entity: Entity| {
+        refresh_all || outdated_query.contains(entity) || in_flight_items.claims_changed(entity)
+    };
+
+    let signal_strength =
+        priorities.signal_strength(LogisticsCategory::Logistics, LOGISTIC_SIGNAL_STRENGTH);
+    for (entity, mut emitter, input_inventory, maybe_activity, maybe_range, maybe_status) in
+        release_query.iter_mut()
+    {
+        if !is_outdated(entity) {
+            continue;
+        }
 
-    for (mut emitter, input_inventory) in release_query.iter_mut() {
         emitter.signals.clear();
-        for item_slot in input_inventory.iter() {
-            if !item_slot.is_full() {
-                let item_kind = match *input_inventory {
-                    InputInventory::Exact { .. } => ItemKind::Single(item_slot.item_id()),
-                    InputInventory::Tagged { tag, .. } => ItemKind::Tag(tag),
-                };
+        emitter.max_range = Some(maybe_range.copied().unwrap_or_default().max_range);
+        // Items delivered to a blocked releaser would just pile up in its buffer
+        if StructureActivity::is_paused(maybe_activity) || LogisticStatus::is_blocked(maybe_status)
+        {
+            continue;
+        }
+
+        for (item_kind, _) in input_inventory.grouped() {
+            // Items that are already being carried here shouldn't attract more haulers
+            let wants_more = match item_kind {
+                ItemKind::Single(item_id) => {
+                    let remaining_space = input_inventory
+                        .inventory()
+                        .remaining_reserved_space_for_item(item_id);
+                    in_flight_items.claimed(entity, item_id) < remaining_space
+                }
+                ItemKind::Tag(_) => !input_inventory.inventory().is_full(),
+            };
 
+            if wants_more {
                 // This should be a Pull signal, rather than a Stores signal to
                 // ensure that goods can be continuously harvested and shipped.
+                // We keep pulling until the buffer is full, even when holding items for a batch release.
                 let signal_type: SignalType = SignalType::Pull(item_kind);
                 emitter.signals.push((signal_type, signal_strength));
             }
         }
     }
 
-    for (mut emitter, output_inventory) in absorb_query.iter_mut() {
+    for (entity, mut emitter, output_inventory, maybe_activity, maybe_range, maybe_status) in
+        absorb_query.iter_mut()
+    {
+        if !is_outdated(entity) {
+            continue;
+        }
+
         emitter.signals.clear();
+        emitter.max_range = Some(maybe_range.copied().unwrap_or_default().max_range);
+        if StructureActivity::is_paused(maybe_activity) || LogisticStatus::is_blocked(maybe_status)
+        {
+            continue;
+        }
+
         for item_slot in output_inventory.iter() {
             if !item_slot.is_full() {
                 let item_kind = ItemKind::Single(item_slot.item_id());
@@ -167,4 +816,1103 @@ fn logistic_buildings_signals(
             }
         }
     }
+
+    for (entity, mut emitter, input_inventory, output_inventory, maybe_activity, maybe_range) in
+        pump_query.iter_mut()
+    {
+        if !is_outdated(entity) {
+            continue;
+        }
+
+        emitter.signals.clear();
+        emitter.max_range = Some(maybe_range.copied().unwrap_or_default().max_range);
+        if StructureActivity::is_paused(maybe_activity) {
+            continue;
+        }
+
+        for (item_kind, _) in input_inventory.grouped() {
+            let ItemKind::Single(item_id) = item_kind else { continue };
+
+            // Items that are already being carried here shouldn't attract more haulers
+            let remaining_space = input_inventory
+                .inventory()
+                .remaining_reserved_space_for_item(item_id);
+            if in_flight_items.claimed(entity, item_id) < remaining_space {
+                emitter
+                    .signals
+                    .push((SignalType::Pull(item_kind), signal_strength));
+            }
+        }
+
+        for item_slot in output_inventory.iter() {
+            let item_kind = ItemKind::Single(item_slot.item_id());
+
+            if item_slot.is_full() {
+                emitter
+                    .signals
+                    .push((SignalType::Push(item_kind), signal_strength));
+            } else if !item_slot.is_empty() {
+                emitter
+                    .signals
+                    .push((SignalType::Contains(item_kind), signal_strength));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        asset_management::manifest::{Id, Manifest},
+        construction::ghosts::{ghost_structure_signals, ConstructionPhase, Ghost},
+        crafting::{inventories::CraftingState, workers::WorkersPresent},
+        items::{
+            inventory::Inventory,
+            item_manifest::{Item, ItemData, WaterContents},
+        },
+        signals::{Signals, DIFFUSION_FRACTION},
+        simulation::alerts::Alert,
+        structures::structure_manifest::Structure,
+        testing::harness::{
+            absorber, batch_releaser, egg_bin, overflowing_absorber, releaser,
+            SimulationTestHarness,
+        },
+        units::{goals::Goal, item_interaction::UnitInventory},
+    };
+    use bevy::utils::Duration;
+    use hexx::{Direction, Hex};
+
+    fn egg() -> Id<Item> {
+        Id::from_name("egg".to_string())
+    }
+
+    fn item_manifest() -> ItemManifest {
+        let mut manifest = Manifest::new();
+        manifest.insert("egg".to_string(), ItemData::basic(10));
+        manifest
+    }
+
+    /// Creates an app with a single releaser at the origin, and returns the releaser and litter entities.
+    fn releaser_app(release_threshold: Option<u32>) -> (App, Entity, Entity) {
+        let mut app = App::new();
+        let map_geometry = MapGeometry::new(&mut app.world, 1);
+
+        let facing = Facing::default();
+        let litter_pos = VoxelPos::ZERO.neighbor(facing.direction);
+        let litter_entity = map_geometry.get_terrain(litter_pos.hex).unwrap();
+        app.world
+            .entity_mut(litter_entity)
+            .insert(Litter::default());
+
+        let releases_items = ReleasesItems { release_threshold };
+        let releaser_entity = app
+            .world
+            .spawn((
+                VoxelPos::ZERO,
+                facing,
+                Footprint::single(),
+                releases_items,
+                InputInventory::Exact {
+                    inventory: Inventory::empty_from_item(egg(), releases_items.batch_size()),
+                },
+                LogisticStatus::default(),
+            ))
+            .id();
+
+        app.insert_resource(map_geometry)
+            .insert_resource(item_manifest())
+            .add_event::<ItemConsumed>()
+            .add_system(release_items);
+
+        (app, releaser_entity, litter_entity)
+    }
+
+    fn vase() -> Id<Item> {
+        Id::from_name("vase".to_string())
+    }
+
+    /// Creates an app with a releaser standing on terrain of height `base`,
+    /// which releases batches of four fragile vases onto terrain of height `target`.
+    ///
+    /// Returns the releaser and litter entities.
+    fn cliff_releaser_app(base: u8, target: u8) -> (App, Entity, Entity) {
+        let mut app = App::new();
+        let mut map_geometry = MapGeometry::new(&mut app.world, 1);
+
+        let facing = Facing::default();
+        let litter_hex = Hex::ZERO.neighbor(facing.direction);
+        map_geometry.update_height(Hex::ZERO, DiscreteHeight(base));
+        map_geometry.update_height(litter_hex, DiscreteHeight(target));
+
+        let litter_entity = map_geometry.get_terrain(litter_hex).unwrap();
+        app.world
+            .entity_mut(litter_entity)
+            .insert(Litter::default());
+
+        let mut item_manifest = item_manifest();
+        item_manifest.insert(
+            "vase".to_string(),
+            ItemData {
+                fragile: true,
+                ..ItemData::basic(10)
+            },
+        );
+
+        let releases_items = ReleasesItems {
+            release_threshold: Some(4),
+        };
+        let mut inventory = Inventory::empty_from_item(vase(), releases_items.batch_size());
+        inventory
+            .add_item_all_or_nothing(&ItemCount::new(vase(), 4), &item_manifest)
+            .unwrap();
+        let releaser_entity = app
+            .world
+            .spawn((
+                map_geometry.on_top_of_terrain(Hex::ZERO),
+                facing,
+                Footprint::single(),
+                releases_items,
+                InputInventory::Exact { inventory },
+                LogisticStatus::default(),
+                FlowStats::default(),
+            ))
+            .id();
+
+        app.insert_resource(map_geometry)
+            .insert_resource(item_manifest)
+            .add_event::<ItemConsumed>()
+            .add_system(release_items);
+
+        (app, releaser_entity, litter_entity)
+    }
+
+    fn feed_one_egg(app: &mut App, releaser_entity: Entity) {
+        let item_manifest = item_manifest();
+        let mut input_inventory = app
+            .world
+            .get_mut::<InputInventory>(releaser_entity)
+            .unwrap();
+        input_inventory
+            .fill_with_items(&ItemCount::one(egg()), &item_manifest)
+            .unwrap();
+    }
+
+    fn littered_eggs(app: &App, litter_entity: Entity) -> u32 {
+        let litter = app.world.get::<Litter>(litter_entity).unwrap();
+        litter.contents.item_count(egg())
+    }
+
+    #[test]
+    fn releaser_without_threshold_releases_immediately() {
+        let mut harness = SimulationTestHarness::new(1);
+        let releaser_entity = harness.spawn_structure(releaser(), Hex::ZERO, Facing::default());
+        let litter_hex = Hex::ZERO.neighbor(Facing::default().direction);
+
+        for n in 1..=3 {
+            harness.fill_input(releaser_entity, egg(), 1);
+            harness.tick(1);
+            assert_eq!(harness.litter_count(litter_hex, egg()), n);
+        }
+    }
+
+    #[test]
+    fn releaser_with_threshold_releases_in_batches() {
+        let mut harness = SimulationTestHarness::new(1);
+        let releaser_entity =
+            harness.spawn_structure(batch_releaser(), Hex::ZERO, Facing::default());
+        let litter_hex = Hex::ZERO.neighbor(Facing::default().direction);
+
+        for _ in 0..4 {
+            harness.fill_input(releaser_entity, egg(), 1);
+            harness.tick(1);
+            assert_eq!(harness.litter_count(litter_hex, egg()), 0);
+        }
+
+        harness.fill_input(releaser_entity, egg(), 1);
+        harness.tick(1);
+        assert_eq!(harness.litter_count(litter_hex, egg()), 5);
+
+        let input_inventory = harness
+            .app
+            .world
+            .get::<InputInventory>(releaser_entity)
+            .unwrap();
+        assert_eq!(input_inventory.inventory().item_count(egg()), 0);
+    }
+
+    #[test]
+    fn releaser_carries_over_items_that_do_not_fit() {
+        let mut harness = SimulationTestHarness::new(1);
+        let releaser_entity =
+            harness.spawn_structure(batch_releaser(), Hex::ZERO, Facing::default());
+        let litter_hex = Hex::ZERO.neighbor(Facing::default().direction);
+
+        // Leave only enough room in the litter for three more eggs
+        harness.add_litter(litter_hex, egg(), 27);
+
+        harness.fill_input(releaser_entity, egg(), 5);
+        harness.tick(1);
+
+        assert_eq!(harness.litter_count(litter_hex, egg()), 30);
+        let input_inventory = harness
+            .app
+            .world
+            .get::<InputInventory>(releaser_entity)
+            .unwrap();
+        assert_eq!(input_inventory.inventory().item_count(egg()), 2);
+    }
+
+    #[test]
+    fn long_releasers_release_in_front_of_their_footprint() {
+        use bevy::utils::HashSet;
+
+        let footprint = Footprint {
+            set: HashSet::from_iter(vec![
+                VoxelPos::ZERO,
+                VoxelPos::from_xy(1, 0),
+                VoxelPos::from_xy(2, 0),
+            ]),
+        };
+
+        for direction in Direction::ALL_DIRECTIONS {
+            let mut app = App::new();
+            let map_geometry = MapGeometry::new(&mut app.world, 4);
+            for &hex in map_geometry.all_hexes() {
+                let terrain_entity = map_geometry.get_terrain(hex).unwrap();
+                app.world
+                    .entity_mut(terrain_entity)
+                    .insert(Litter::default());
+            }
+
+            let facing = Facing {
+                direction,
+                mirrored: false,
+            };
+            let normalized = footprint.normalized(facing, VoxelPos::ZERO);
+            let releaser_entity = app
+                .world
+                .spawn((
+                    VoxelPos::ZERO,
+                    facing,
+                    footprint.clone(),
+                    ReleasesItems::default(),
+                    InputInventory::Exact {
+                        inventory: Inventory::empty_from_item(egg(), 1),
+                    },
+                ))
+                .id();
+
+            app.insert_resource(map_geometry)
+                .insert_resource(item_manifest())
+                .add_event::<ItemConsumed>()
+                .add_system(release_items);
+
+            feed_one_egg(&mut app, releaser_entity);
+            app.update();
+
+            let map_geometry = app.world.resource::<MapGeometry>();
+            let littered_hexes: Vec<Hex> = map_geometry
+                .all_hexes()
+                .copied()
+                .filter(|&hex| {
+                    let terrain_entity = map_geometry.get_terrain(hex).unwrap();
+                    littered_eggs(&app, terrain_entity) > 0
+                })
+                .collect();
+
+            assert_eq!(littered_hexes.len(), 1, "{facing}");
+            let littered_pos = VoxelPos::from_xy(littered_hexes[0].x, littered_hexes[0].y);
+            assert!(
+                !normalized.contains(&littered_pos),
+                "{facing}: the egg was released inside the footprint"
+            );
+            assert!(
+                normalized
+                    .iter()
+                    .any(|tile| tile.neighbor(direction) == littered_pos),
+                "{facing}: the egg was not released next to the footprint"
+            );
+        }
+    }
+
+    #[test]
+    fn releaser_is_blocked_by_full_litter_until_space_opens() {
+        let mut harness = SimulationTestHarness::new(1);
+        let releaser_entity = harness.spawn_structure(releaser(), Hex::ZERO, Facing::default());
+        let litter_hex = Hex::ZERO.neighbor(Facing::default().direction);
+        let status = |harness: &SimulationTestHarness| {
+            *harness
+                .app
+                .world
+                .get::<LogisticStatus>(releaser_entity)
+                .unwrap()
+        };
+
+        harness.tick(1);
+        assert_eq!(status(&harness), LogisticStatus::Idle);
+
+        harness.add_litter(litter_hex, egg(), 30);
+        harness.fill_input(releaser_entity, egg(), 1);
+        harness.tick(1);
+        assert_eq!(
+            status(&harness),
+            LogisticStatus::Blocked {
+                reason: BlockedReason::LitterFull
+            }
+        );
+        assert_eq!(harness.litter_count(litter_hex, egg()), 30);
+
+        // Clear space downstream
+        harness.remove_litter(litter_hex, egg(), 5);
+
+        harness.tick(1);
+        assert_eq!(status(&harness), LogisticStatus::Flowing);
+        assert_eq!(harness.litter_count(litter_hex, egg()), 26);
+
+        harness.tick(1);
+        assert_eq!(status(&harness), LogisticStatus::Idle);
+    }
+
+    #[test]
+    fn blocked_releaser_raises_one_alert_that_resolves_once_unblocked() {
+        use crate::simulation::time::advance_in_game_time;
+
+        let (mut app, releaser_entity, litter_entity) = releaser_app(None);
+        let mut alert_log = AlertLog::default();
+        alert_log.threshold = Duration::from_secs(1);
+        app.insert_resource(FixedTime::new_from_secs(0.5))
+            .init_resource::<InGameTime>()
+            .insert_resource(alert_log)
+            .add_systems((
+                advance_in_game_time.before(raise_logistics_alerts),
+                raise_logistics_alerts.after(release_items),
+            ));
+
+        let item_manifest = item_manifest();
+        app.world
+            .get_mut::<Litter>(litter_entity)
+            .unwrap()
+            .contents
+            .add_item_all_or_nothing(&ItemCount::new(egg(), 10), &item_manifest)
+            .unwrap();
+        feed_one_egg(&mut app, releaser_entity);
+
+        // Not blocked for long enough yet
+        app.update();
+        app.update();
+        assert_eq!(app.world.resource::<AlertLog>().active().count(), 0);
+
+        // Alerts are only raised once, no matter how long the releaser stays blocked
+        for _ in 0..10 {
+            app.update();
+        }
+        let alert_log = app.world.resource::<AlertLog>();
+        let active: Vec<&Alert> = alert_log.active().collect();
+        assert_eq!(active.len(), 1);
+        assert!(matches!(
+            active[0].kind,
+            AlertKind::ReleaserBlocked { entity, .. } if entity == releaser_entity
+        ));
+
+        app.world
+            .get_mut::<Litter>(litter_entity)
+            .unwrap()
+            .contents
+            .try_remove_item(&ItemCount::new(egg(), 5))
+            .unwrap();
+        app.update();
+
+        let alert_log = app.world.resource::<AlertLog>();
+        assert_eq!(alert_log.active().count(), 0);
+        let history: Vec<&Alert> = alert_log.history(10).collect();
+        assert_eq!(history.len(), 1);
+        assert!(history[0].resolved_at.is_some());
+    }
+
+    #[test]
+    fn paused_releaser_holds_items_and_stays_quiet_until_resumed() {
+        use crate::structures::{toggle_structure_activity, ToggleStructureActivity};
+
+        let (mut app, releaser_entity, litter_entity) = releaser_app(Some(3));
+        app.init_resource::<LogisticsPriorities>()
+            .init_resource::<InFlightItems>()
+            .add_event::<ToggleStructureActivity>()
+            .add_system(toggle_structure_activity.before(release_items))
+            .add_system(logistic_buildings_signals.after(release_items));
+        app.world
+            .entity_mut(releaser_entity)
+            .insert((Emitter::default(), StructureActivity::Active));
+
+        // Pause the releaser partway through filling its buffer
+        feed_one_egg(&mut app, releaser_entity);
+        app.update();
+        app.world.send_event(ToggleStructureActivity {
+            structure: releaser_entity,
+        });
+        app.update();
+        assert_eq!(
+            *app.world.get::<StructureActivity>(releaser_entity).unwrap(),
+            StructureActivity::Paused
+        );
+
+        for _ in 0..2 {
+            feed_one_egg(&mut app, releaser_entity);
+        }
+        for _ in 0..5 {
+            app.update();
+        }
+
+        assert_eq!(littered_eggs(&app, litter_entity), 0);
+        let input_inventory = app.world.get::<InputInventory>(releaser_entity).unwrap();
+        assert_eq!(input_inventory.inventory().item_count(egg()), 3);
+        let emitter = app.world.get::<Emitter>(releaser_entity).unwrap();
+        assert!(emitter.signals.is_empty());
+
+        // Once resumed, the full batch is released and the releaser asks for more
+        app.world.send_event(ToggleStructureActivity {
+            structure: releaser_entity,
+        });
+        app.update();
+
+        assert_eq!(littered_eggs(&app, litter_entity), 3);
+        let emitter = app.world.get::<Emitter>(releaser_entity).unwrap();
+        assert!(!emitter.signals.is_empty());
+    }
+
+    #[test]
+    fn idle_releasers_keep_their_signals_until_their_inventory_changes() {
+        let (mut app, releaser_entity, _) = releaser_app(Some(3));
+        app.init_resource::<LogisticsPriorities>()
+            .init_resource::<InFlightItems>()
+            .add_system(logistic_buildings_signals.after(release_items));
+        app.world
+            .entity_mut(releaser_entity)
+            .insert(Emitter::default());
+
+        let pull_egg = SignalType::Pull(ItemKind::Single(egg()));
+        let emits_pull = |app: &App| {
+            let emitter = app.world.get::<Emitter>(releaser_entity).unwrap();
+            emitter
+                .signals
+                .iter()
+                .any(|&(signal_type, _)| signal_type == pull_egg)
+        };
+
+        app.update();
+        assert!(emits_pull(&app));
+
+        // Nothing has changed, so the (now stale) signals are left alone
+        app.world
+            .get_mut::<Emitter>(releaser_entity)
+            .unwrap()
+            .signals
+            .clear();
+        for _ in 0..3 {
+            app.update();
+            assert!(!emits_pull(&app));
+        }
+
+        // Changing the inventory refreshes the signals on the very next tick
+        feed_one_egg(&mut app, releaser_entity);
+        app.update();
+        assert!(emits_pull(&app));
+    }
+
+    #[test]
+    fn idle_releasers_are_not_recomputed_while_haulers_deliver_elsewhere() {
+        /// The number of idle releasers to simulate.
+        const N_RELEASERS: i32 = 1000;
+        /// The number of haulers delivering eggs to the releasers.
+        const N_HAULERS: usize = 10;
+
+        /// The number of emitters that were recomputed during the last tick.
+        #[derive(Resource, Default)]
+        struct Recomputed(usize);
+
+        /// Walks every hauler one tile further along the row of releasers.
+        fn walk_haulers(mut hauler_query: Query<&mut VoxelPos, With<Goal>>) {
+            for mut voxel_pos in hauler_query.iter_mut() {
+                voxel_pos.hex.x += 1;
+            }
+        }
+
+        fn count_recomputed(
+            emitter_query: Query<(), Changed<Emitter>>,
+            mut recomputed: ResMut<Recomputed>,
+        ) {
+            recomputed.0 = emitter_query.iter().count();
+        }
+
+        let mut app = App::new();
+        for x in 0..N_RELEASERS {
+            app.world.spawn((
+                ReleasesItems::default(),
+                Emitter::default(),
+                InputInventory::Exact {
+                    inventory: Inventory::empty_from_item(egg(), 10),
+                },
+                VoxelPos::from_xy(x, 0),
+            ));
+        }
+        // Together, the haulers carry enough eggs to fill the two releasers closest to them
+        for _ in 0..N_HAULERS {
+            app.world.spawn((
+                VoxelPos::from_xy(0, 1),
+                Goal::Deliver(ItemKind::Single(egg())),
+                UnitInventory::holding(ItemCount::new(egg(), 2)),
+            ));
+        }
+
+        app.insert_resource(item_manifest())
+            .init_resource::<LogisticsPriorities>()
+            .init_resource::<InFlightItems>()
+            .init_resource::<Recomputed>()
+            .add_systems(
+                (
+                    walk_haulers,
+                    count_in_flight_items,
+                    logistic_buildings_signals,
+                    count_recomputed,
+                )
+                    .chain(),
+            );
+
+        // Every releaser computes its signals once
+        app.update();
+        assert_eq!(app.world.resource::<Recomputed>().0, N_RELEASERS as usize);
+
+        // After that, only the releasers that the haulers walk towards or away from are recomputed
+        for _ in 0..10 {
+            app.update();
+            let recomputed = app.world.resource::<Recomputed>().0;
+            assert!(
+                (1..=2 * N_HAULERS).contains(&recomputed),
+                "{recomputed} of {N_RELEASERS} releasers were recomputed"
+            );
+        }
+    }
+
+    #[test]
+    fn blocked_releasers_stop_pulling_until_unblocked() {
+        let (mut app, releaser_entity, litter_entity) = releaser_app(None);
+        app.init_resource::<LogisticsPriorities>()
+            .init_resource::<InFlightItems>()
+            .add_system(logistic_buildings_signals.after(release_items));
+        app.world
+            .entity_mut(releaser_entity)
+            .insert(Emitter::default());
+
+        let pull_egg = SignalType::Pull(ItemKind::Single(egg()));
+        let emits_pull = |app: &App| {
+            let emitter = app.world.get::<Emitter>(releaser_entity).unwrap();
+            emitter
+                .signals
+                .iter()
+                .any(|&(signal_type, _)| signal_type == pull_egg)
+        };
+
+        app.update();
+        assert!(emits_pull(&app));
+
+        // Fill up the tile in front of the releaser
+        let item_manifest = item_manifest();
+        app.world
+            .get_mut::<Litter>(litter_entity)
+            .unwrap()
+            .contents
+            .add_item_all_or_nothing(&ItemCount::new(egg(), 10), &item_manifest)
+            .unwrap();
+        feed_one_egg(&mut app, releaser_entity);
+        app.update();
+        assert!(matches!(
+            app.world.get::<LogisticStatus>(releaser_entity),
+            Some(LogisticStatus::Blocked { .. })
+        ));
+        assert!(!emits_pull(&app));
+
+        // Clearing the tile lets the buffered egg out, and the releaser asks for more
+        app.world
+            .get_mut::<Litter>(litter_entity)
+            .unwrap()
+            .contents
+            .try_remove_item(&ItemCount::new(egg(), 5))
+            .unwrap();
+        app.update();
+        assert_eq!(
+            app.world.get::<LogisticStatus>(releaser_entity),
+            Some(&LogisticStatus::Flowing)
+        );
+        assert!(emits_pull(&app));
+    }
+
+    /// Returns the tile that a hauler at `hauler_pos` carrying an egg would step towards next, if any.
+    fn try_hauler_step(app: &mut App, hauler_pos: VoxelPos) -> Option<VoxelPos> {
+        app.update();
+
+        let mut signals = Signals::default();
+        let mut emitter_query = app.world.query::<(&VoxelPos, &Emitter)>();
+        for (&voxel_pos, emitter) in emitter_query.iter(&app.world) {
+            signals.add_emission(voxel_pos, emitter, 1);
+        }
+
+        let map_geometry = app.world.resource::<MapGeometry>();
+        for _ in 0..20 {
+            signals.diffuse(map_geometry, DIFFUSION_FRACTION);
+        }
+
+        signals.upstream(
+            hauler_pos,
+            &Goal::Deliver(ItemKind::Single(egg())),
+            &item_manifest(),
+            map_geometry,
+            true,
+        )
+    }
+
+    /// Returns the tile that a hauler at `hauler_pos` carrying an egg would step towards next.
+    fn hauler_step(app: &mut App, hauler_pos: VoxelPos) -> VoxelPos {
+        try_hauler_step(app, hauler_pos).unwrap()
+    }
+
+    #[test]
+    fn haulers_beyond_signal_range_feel_no_pull() {
+        let mut app = App::new();
+        let map_geometry = MapGeometry::new(&mut app.world, 4);
+
+        let releaser_hex = Hex::new(-3, 0);
+        let nearby_hauler = map_geometry.on_top_of_terrain(Hex::new(-1, 0));
+        let distant_hauler = map_geometry.on_top_of_terrain(Hex::new(3, 0));
+
+        app.world.spawn((
+            ReleasesItems::default(),
+            SignalRange { max_range: 3 },
+            map_geometry.on_top_of_terrain(releaser_hex),
+            Facing::default(),
+            Emitter::default(),
+            InputInventory::Exact {
+                inventory: Inventory::empty_from_item(egg(), 1),
+            },
+        ));
+
+        app.insert_resource(map_geometry)
+            .init_resource::<LogisticsPriorities>()
+            .init_resource::<InFlightItems>()
+            .add_system(logistic_buildings_signals);
+
+        let step = try_hauler_step(&mut app, nearby_hauler).unwrap();
+        assert_eq!(step.hex.unsigned_distance_to(releaser_hex), 1);
+
+        assert_eq!(try_hauler_step(&mut app, distant_hauler), None);
+    }
+
+    #[test]
+    fn logistics_priorities_control_competing_pulls() {
+        let mut app = App::new();
+        let map_geometry = MapGeometry::new(&mut app.world, 3);
+
+        let hauler_pos = map_geometry.on_top_of_terrain(Hex::ZERO);
+        let ghost_hex = Hex::new(2, 0);
+        let releaser_hex = Hex::new(-2, 0);
+
+        // A ghost and a releaser, equally far away, both waiting on an egg
+        app.world.spawn((
+            Ghost,
+            Id::<Structure>::from_name("nest".to_string()),
+            map_geometry.on_top_of_terrain(ghost_hex),
+            Emitter::default(),
+            CraftingState::NeedsInput,
+            ConstructionPhase::Building,
+            InputInventory::Exact {
+                inventory: Inventory::empty_from_item(egg(), 1),
+            },
+            WorkersPresent::new(1),
+        ));
+        app.world.spawn((
+            ReleasesItems::default(),
+            map_geometry.on_top_of_terrain(releaser_hex),
+            Facing::default(),
+            Emitter::default(),
+            InputInventory::Exact {
+                inventory: Inventory::empty_from_item(egg(), 1),
+            },
+        ));
+
+        app.insert_resource(map_geometry)
+            .init_resource::<LogisticsPriorities>()
+            .init_resource::<InFlightItems>()
+            .add_systems((ghost_structure_signals, logistic_buildings_signals));
+
+        // By default, construction sites pull much harder than logistic buildings
+        let step = hauler_step(&mut app, hauler_pos);
+        assert!(
+            step.hex.unsigned_distance_to(ghost_hex) < step.hex.unsigned_distance_to(releaser_hex)
+        );
+
+        app.world
+            .resource_mut::<LogisticsPriorities>()
+            .set_weight(LogisticsCategory::Construction, 0.01);
+
+        let step = hauler_step(&mut app, hauler_pos);
+        assert!(
+            step.hex.unsigned_distance_to(releaser_hex) < step.hex.unsigned_distance_to(ghost_hex)
+        );
+    }
+
+    /// Creates a harness with an absorber at the origin, standing on a pile of 25 eggs,
+    /// next to an empty storage reserved for eggs.
+    ///
+    /// Returns the harness, the absorber entity and the storage entity.
+    fn absorber_harness(absorber_id: Id<Structure>) -> (SimulationTestHarness, Entity, Entity) {
+        let mut harness = SimulationTestHarness::new(1);
+        harness.add_litter(Hex::ZERO, egg(), 25);
+
+        let absorber_entity = harness.spawn_structure(absorber_id, Hex::ZERO, Facing::default());
+        let storage_hex = Hex::ZERO.neighbor(Facing::default().direction);
+        let storage_entity = harness.spawn_structure(egg_bin(), storage_hex, Facing::default());
+
+        (harness, absorber_entity, storage_entity)
+    }
+
+    #[test]
+    fn absorber_overflows_into_adjacent_storage() {
+        let (mut harness, absorber_entity, storage_entity) =
+            absorber_harness(overflowing_absorber());
+
+        harness.tick(3);
+
+        // Absorption continued past the single stack that the absorber can hold
+        assert_eq!(harness.litter_count(Hex::ZERO, egg()), 0);
+        let world = &harness.app.world;
+        let storage_inventory = world.get::<StorageInventory>(storage_entity).unwrap();
+        assert_eq!(storage_inventory.item_count(egg()), 20);
+        let output_inventory = world.get::<OutputInventory>(absorber_entity).unwrap();
+        assert_eq!(output_inventory.item_count(egg()), 5);
+    }
+
+    #[test]
+    fn absorber_without_overflow_stalls_when_full() {
+        let (mut harness, absorber_entity, storage_entity) = absorber_harness(absorber());
+
+        harness.tick(3);
+
+        assert_eq!(harness.litter_count(Hex::ZERO, egg()), 15);
+        let world = &harness.app.world;
+        let storage_inventory = world.get::<StorageInventory>(storage_entity).unwrap();
+        assert_eq!(storage_inventory.item_count(egg()), 0);
+        let output_inventory = world.get::<OutputInventory>(absorber_entity).unwrap();
+        assert_eq!(output_inventory.item_count(egg()), 10);
+        assert_eq!(
+            world.get::<LogisticStatus>(absorber_entity),
+            Some(&LogisticStatus::Blocked {
+                reason: BlockedReason::OutputFull
+            })
+        );
+    }
+
+    fn empty_pouch() -> Id<Item> {
+        Id::from_name("empty_pouch".to_string())
+    }
+
+    fn water_pouch() -> Id<Item> {
+        Id::from_name("water_pouch".to_string())
+    }
+
+    fn pouch_manifest() -> ItemManifest {
+        let pouch = |water_contents| ItemData {
+            water_contents,
+            ..ItemData::basic(4)
+        };
+
+        let mut manifest = Manifest::new();
+        manifest.insert("empty_pouch".to_string(), pouch(None));
+        manifest.insert(
+            "water_pouch".to_string(),
+            pouch(Some(WaterContents {
+                volume: Volume(0.25),
+                empty_container: empty_pouch(),
+            })),
+        );
+        manifest
+    }
+
+    /// The total volume of water on the map, including the water held in pouches.
+    fn total_water(app: &mut App) -> Volume {
+        let mut total = Volume::ZERO;
+        for water_volume in app.world.query::<&WaterVolume>().iter(&app.world) {
+            total += water_volume.volume();
+        }
+
+        let mut inventory_query = app
+            .world
+            .query::<(Option<&InputInventory>, Option<&OutputInventory>)>();
+        for (maybe_input, maybe_output) in inventory_query.iter(&app.world) {
+            let full_pouches = maybe_input
+                .map_or(0, |input| input.inventory().item_count(water_pouch()))
+                + maybe_output.map_or(0, |output| output.item_count(water_pouch()));
+            total += Volume(0.25) * full_pouches as f32;
+        }
+
+        total
+    }
+
+    #[test]
+    fn pumping_and_pouring_water_conserves_water_and_pouches() {
+        let mut app = App::new();
+        let map_geometry = MapGeometry::new(&mut app.world, 2);
+        let item_manifest = pouch_manifest();
+
+        let facing = Facing::default();
+        let pump_pos = VoxelPos::ZERO;
+        let releaser_pos = pump_pos.neighbor(facing.direction);
+        let target_pos = releaser_pos.neighbor(facing.direction);
+
+        for hex in map_geometry.all_hexes().copied().collect::<Vec<_>>() {
+            let terrain_entity = map_geometry.get_terrain(hex).unwrap();
+            let (water_depth, water_volume) = if hex == pump_pos.hex {
+                (WaterDepth::Flooded(Height(1.5)), Volume(2.))
+            } else {
+                (WaterDepth::Dry, Volume::ZERO)
+            };
+            app.world.entity_mut(terrain_entity).insert((
+                water_depth,
+                WaterVolume::new(water_volume),
+                Litter::default(),
+            ));
+        }
+        let target_entity = map_geometry.get_terrain(target_pos.hex).unwrap();
+
+        let mut output_inventory = Inventory::new(1, None);
+        output_inventory.add_empty_slot(water_pouch(), &item_manifest);
+        let pump_entity = app
+            .world
+            .spawn((
+                pump_pos,
+                PumpsWater {
+                    filled_item: water_pouch(),
+                },
+                InputInventory::Exact {
+                    inventory: Inventory::full_from_item(empty_pouch(), 4),
+                },
+                OutputInventory {
+                    inventory: output_inventory,
+                },
+            ))
+            .id();
+        let releaser_entity = app
+            .world
+            .spawn((
+                releaser_pos,
+                facing,
+                Footprint::single(),
+                ReleasesItems::default(),
+                InputInventory::Exact {
+                    inventory: Inventory::empty_from_item(water_pouch(), 4),
+                },
+            ))
+            .id();
+
+        app.insert_resource(map_geometry)
+            .insert_resource(item_manifest)
+            .add_event::<ItemConsumed>()
+            .add_event::<ItemProduced>()
+            .add_system(pump_water)
+            .add_system(release_items.after(pump_water));
+
+        let initial_water = total_water(&mut app);
+        let pouch_count = |app: &mut App| {
+            let mut count = 0;
+            let mut query = app.world.query::<&Litter>();
+            for litter in query.iter(&app.world) {
+                count += litter.contents.item_count(empty_pouch());
+            }
+            let mut query = app
+                .world
+                .query::<(Option<&InputInventory>, Option<&OutputInventory>)>();
+            for (maybe_input, maybe_output) in query.iter(&app.world) {
+                for item_id in [empty_pouch(), water_pouch()] {
+                    count += maybe_input.map_or(0, |input| input.inventory().item_count(item_id));
+                    count += maybe_output.map_or(0, |output| output.item_count(item_id));
+                }
+            }
+            count
+        };
+
+        // The first two cycles drain all of the surface water, after which nothing more can be pumped
+        for expected_filled in [4, 4, 0] {
+            app.update();
+            assert_eq!(total_water(&mut app), initial_water);
+            assert_eq!(pouch_count(&mut app), 4);
+
+            // Haul the filled pouches from the pump to the releaser
+            let item_manifest = pouch_manifest();
+            let filled = app
+                .world
+                .get::<OutputInventory>(pump_entity)
+                .unwrap()
+                .item_count(water_pouch());
+            assert_eq!(filled, expected_filled);
+            let filled = ItemCount::new(water_pouch(), filled);
+            app.world
+                .get_mut::<OutputInventory>(pump_entity)
+                .unwrap()
+                .remove_item_all_or_nothing(&filled)
+                .unwrap();
+            app.world
+                .get_mut::<InputInventory>(releaser_entity)
+                .unwrap()
+                .fill_with_items(&filled, &item_manifest)
+                .unwrap();
+
+            // The pouches are poured out onto the dry tile in front of the releaser
+            app.update();
+            assert_eq!(total_water(&mut app), initial_water);
+            assert_eq!(pouch_count(&mut app), 4);
+
+            // Haul the empty pouches back to the pump
+            let mut litter = app.world.get_mut::<Litter>(target_entity).unwrap();
+            let empties = ItemCount::new(empty_pouch(), litter.contents.item_count(empty_pouch()));
+            litter
+                .contents
+                .remove_item_all_or_nothing(&empties)
+                .unwrap();
+            app.world
+                .get_mut::<InputInventory>(pump_entity)
+                .unwrap()
+                .fill_with_items(&empties, &item_manifest)
+                .unwrap();
+        }
+
+        let target_water = app.world.get::<WaterVolume>(target_entity).unwrap();
+        assert_eq!(target_water.volume(), Volume(2.));
+    }
+
+    #[test]
+    fn releasers_drop_items_down_cliffs() {
+        // (height difference, vases landed, vases broken)
+        for (drop, landed, broken) in [(0, 4, 0), (1, 4, 0), (3, 2, 2)] {
+            let (mut app, releaser_entity, litter_entity) = cliff_releaser_app(3, 3 - drop);
+            app.update();
+
+            let litter = app.world.get::<Litter>(litter_entity).unwrap();
+            assert_eq!(litter.contents.item_count(vase()), landed, "drop of {drop}");
+
+            let releaser = app.world.entity(releaser_entity);
+            assert_eq!(
+                releaser.get::<FlowStats>().unwrap().destroyed,
+                broken,
+                "drop of {drop}"
+            );
+            assert_eq!(
+                releaser
+                    .get::<InputInventory>()
+                    .unwrap()
+                    .inventory()
+                    .item_count(vase()),
+                0
+            );
+            assert_eq!(
+                *releaser.get::<LogisticStatus>().unwrap(),
+                LogisticStatus::Flowing
+            );
+        }
+    }
+
+    #[test]
+    fn releasers_cannot_lift_items_up_cliffs() {
+        // (height difference, vases landed, status)
+        for (rise, landed, expected_status) in [
+            (1, 4, LogisticStatus::Flowing),
+            (
+                3,
+                0,
+                LogisticStatus::Blocked {
+                    reason: BlockedReason::TooSteep,
+                },
+            ),
+        ] {
+            let (mut app, releaser_entity, litter_entity) = cliff_releaser_app(0, rise);
+            app.update();
+
+            let litter = app.world.get::<Litter>(litter_entity).unwrap();
+            assert_eq!(litter.contents.item_count(vase()), landed, "rise of {rise}");
+
+            let releaser = app.world.entity(releaser_entity);
+            assert_eq!(
+                *releaser.get::<LogisticStatus>().unwrap(),
+                expected_status,
+                "rise of {rise}"
+            );
+            // Blocked releasers keep hold of their items
+            assert_eq!(
+                releaser
+                    .get::<InputInventory>()
+                    .unwrap()
+                    .inventory()
+                    .item_count(vase()),
+                4 - landed
+            );
+        }
+    }
+
+    #[test]
+    fn long_releasers_face_the_strongest_pull_past_their_edge() {
+        let footprint = Footprint::line(3);
+        let mut signals = Signals::default();
+        let facing = Facing {
+            direction: Direction::Bottom,
+            mirrored: false,
+        };
+        let release_pos = (VoxelPos::ZERO + footprint.edge_tile(facing)).neighbor(facing.direction);
+        signals.add_signal(
+            SignalType::Pull(ItemKind::Single(egg())),
+            release_pos,
+            SignalStrength::new(5.),
+        );
+        // Weaker pulls elsewhere are ignored
+        signals.add_signal(
+            SignalType::Pull(ItemKind::Single(egg())),
+            VoxelPos::ZERO.neighbor(Direction::Top),
+            SignalStrength::new(1.),
+        );
+
+        assert_eq!(
+            face_strongest_pull(
+                egg(),
+                VoxelPos::ZERO,
+                &footprint,
+                Facing::default(),
+                &signals,
+                &item_manifest(),
+            ),
+            facing
+        );
+    }
+
+    #[test]
+    fn releasers_keep_their_facing_without_a_pull() {
+        let mut signals = Signals::default();
+        // Pulls for other items don't count
+        signals.add_signal(
+            SignalType::Pull(ItemKind::Single(Id::from_name("acorn".to_string()))),
+            VoxelPos::ZERO.neighbor(Direction::Bottom),
+            SignalStrength::new(5.),
+        );
+
+        let facing = Facing {
+            direction: Direction::TopLeft,
+            mirrored: true,
+        };
+        assert_eq!(
+            face_strongest_pull(
+                egg(),
+                VoxelPos::ZERO,
+                &Footprint::single(),
+                facing,
+                &signals,
+                &item_manifest(),
+            ),
+            facing
+        );
+    }
 }
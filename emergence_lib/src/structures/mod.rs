@@ -3,9 +3,12 @@
 //! Typically, these will produce and transform resources (much like machines in other factory builders),
 //! but they can also be used for defense, research, reproduction, storage and more exotic effects.
 
+use std::fmt::Display;
+
 use bevy::{prelude::*, utils::HashSet};
 use bevy_mod_raycast::RaycastMesh;
-use hexx::{shapes::hexagon, Hex};
+use hexx::{shapes::hexagon, Direction, Hex};
+use leafwing_input_manager::prelude::ActionState;
 use serde::{Deserialize, Serialize};
 
 use crate::{
@@ -13,18 +16,47 @@ use crate::{
         manifest::{plugin::ManifestPlugin, Id},
         AssetCollectionExt,
     },
+    crafting::inventories::StorageInventory,
     geometry::{DiscreteHeight, Facing, Height, MapGeometry, VoxelPos},
-    player_interaction::{clipboard::ClipboardData, selection::ObjectInteraction},
+    items::item_manifest::Item,
+    player_interaction::{
+        clipboard::ClipboardData,
+        selection::{CurrentSelection, ObjectInteraction},
+        InteractionSystem, PlayerAction, PlayerModifiesWorld,
+    },
+    signals::{ManageSignals, Signals},
+    simulation::SimulationSet,
 };
 
 use self::{
+    composters::CompostingPlugin,
+    custom_kinds::StructureKindRegistry,
+    flood_damage::track_flood_damage,
+    hot_reload::StructureHotReloadPlugin,
+    incinerators::IncinerationPlugin,
     logistic_buildings::LogisticsPlugin,
+    nests::NestPlugin,
+    placement_rules::PlacementRules,
+    relocation::RelocationPlugin,
+    request_posts::RequestPostPlugin,
+    storage_groups::StorageGroupsPlugin,
     structure_assets::StructureHandles,
-    structure_manifest::{RawStructureManifest, Structure},
+    structure_manifest::{RawStructureManifest, Structure, StructureKind},
 };
 
+pub(crate) mod beacons;
 pub(crate) mod commands;
+pub(crate) mod composters;
+pub mod custom_kinds;
+pub(crate) mod flood_damage;
+pub(crate) mod hot_reload;
+pub(crate) mod incinerators;
 pub(crate) mod logistic_buildings;
+pub(crate) mod nests;
+pub(crate) mod placement_rules;
+pub(crate) mod relocation;
+pub(crate) mod request_posts;
+pub(crate) mod storage_groups;
 mod structure_assets;
 pub mod structure_manifest;
 
@@ -35,7 +67,157 @@ impl Plugin for StructuresPlugin {
     fn build(&self, app: &mut App) {
         app.add_plugin(ManifestPlugin::<RawStructureManifest>::new())
             .add_plugin(LogisticsPlugin)
-            .add_asset_collection::<StructureHandles>();
+            .add_plugin(CompostingPlugin)
+            .add_plugin(IncinerationPlugin)
+            .add_plugin(StructureHotReloadPlugin)
+            .add_plugin(NestPlugin)
+            .add_plugin(RelocationPlugin)
+            .add_plugin(RequestPostPlugin)
+            .add_plugin(StorageGroupsPlugin)
+            .init_resource::<PlacementRules>()
+            .init_resource::<StructureKindRegistry>()
+            .add_asset_collection::<StructureHandles>()
+            .add_event::<ToggleStructureActivity>()
+            .add_event::<SetStockFloor>()
+            .add_system(
+                track_flood_damage
+                    .in_set(SimulationSet)
+                    .in_schedule(CoreSchedule::FixedUpdate),
+            )
+            .add_system(
+                clean_up_despawned_structures
+                    .before(ManageSignals)
+                    .in_set(SimulationSet)
+                    .in_schedule(CoreSchedule::FixedUpdate),
+            )
+            .add_systems(
+                (
+                    toggle_selected_structure_activity,
+                    toggle_structure_activity,
+                )
+                    .chain()
+                    .in_set(PlayerModifiesWorld)
+                    .after(InteractionSystem::SelectTiles),
+            )
+            .add_system(set_stock_floor.in_set(PlayerModifiesWorld));
+    }
+}
+
+/// Removes the traces left behind by structures that have been despawned.
+///
+/// Structures despawned by [`StructureCommandsExt::despawn_structure`](commands::StructureCommandsExt::despawn_structure) are already removed from the [`MapGeometry`],
+/// but those despawned in other ways would otherwise leave stale entries behind.
+/// Any signals that the structure was emitting are removed from its tiles, rather than lingering until they degrade.
+pub(crate) fn clean_up_despawned_structures(
+    mut removed_structures: RemovedComponents<Id<Structure>>,
+    mut map_geometry: ResMut<MapGeometry>,
+    mut signals: ResMut<Signals>,
+) {
+    for structure_entity in removed_structures.iter() {
+        map_geometry.remove_structure_entity(structure_entity);
+        signals.remove_emissions_from(structure_entity);
+    }
+}
+
+/// Is this structure currently doing its job?
+///
+/// Paused structures neither work nor emit signals, but otherwise stay in place.
+#[derive(Component, Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub(crate) enum StructureActivity {
+    /// The structure is working as normal.
+    #[default]
+    Active,
+    /// The structure has been paused by the player.
+    Paused,
+}
+
+impl StructureActivity {
+    /// Is the structure paused?
+    ///
+    /// Structures without a [`StructureActivity`] component are always active.
+    pub(crate) fn is_paused(activity: Option<&StructureActivity>) -> bool {
+        activity == Some(&StructureActivity::Paused)
+    }
+
+    /// Returns the opposite activity state.
+    pub(crate) fn toggled(self) -> Self {
+        match self {
+            StructureActivity::Active => StructureActivity::Paused,
+            StructureActivity::Paused => StructureActivity::Active,
+        }
+    }
+}
+
+impl Display for StructureActivity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let str = match self {
+            StructureActivity::Active => "Active",
+            StructureActivity::Paused => "Paused",
+        };
+
+        write!(f, "{str}")
+    }
+}
+
+/// An event that switches a structure between [`StructureActivity::Active`] and [`StructureActivity::Paused`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct ToggleStructureActivity {
+    /// The structure to pause or resume.
+    pub(crate) structure: Entity,
+}
+
+/// Sends a [`ToggleStructureActivity`] event for each selected structure when the player asks for it.
+fn toggle_selected_structure_activity(
+    player_actions: Res<ActionState<PlayerAction>>,
+    current_selection: Res<CurrentSelection>,
+    mut toggle_events: EventWriter<ToggleStructureActivity>,
+) {
+    if !player_actions.just_pressed(PlayerAction::ToggleStructureActivity) {
+        return;
+    }
+
+    toggle_events.send_batch(
+        current_selection
+            .selected_structures()
+            .into_iter()
+            .map(|structure| ToggleStructureActivity { structure }),
+    );
+}
+
+/// Pauses or resumes structures in response to [`ToggleStructureActivity`] events.
+fn toggle_structure_activity(
+    mut toggle_events: EventReader<ToggleStructureActivity>,
+    mut activity_query: Query<&mut StructureActivity>,
+) {
+    for event in toggle_events.iter() {
+        if let Ok(mut activity) = activity_query.get_mut(event.structure) {
+            *activity = activity.toggled();
+        }
+    }
+}
+
+/// An event that sets the number of an item that haulers must leave behind in a storage structure.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct SetStockFloor {
+    /// The storage structure to modify.
+    pub(crate) structure: Entity,
+    /// The item to reserve.
+    pub(crate) item_id: Id<Item>,
+    /// The number of items that must be left in storage.
+    ///
+    /// A floor of 0 removes the limit.
+    pub(crate) floor: u32,
+}
+
+/// Updates the stock floors of storage structures in response to [`SetStockFloor`] events.
+pub(crate) fn set_stock_floor(
+    mut stock_floor_events: EventReader<SetStockFloor>,
+    mut storage_query: Query<&mut StorageInventory>,
+) {
+    for event in stock_floor_events.iter() {
+        if let Ok(mut storage_inventory) = storage_query.get_mut(event.structure) {
+            storage_inventory.set_stock_floor(event.item_id, event.floor);
+        }
     }
 }
 
@@ -54,6 +236,8 @@ struct StructureBundle {
     raycast_mesh: RaycastMesh<Structure>,
     /// How is this structure being interacted with
     object_interaction: ObjectInteraction,
+    /// Is this structure working, or has it been paused?
+    activity: StructureActivity,
     /// The mesh used for raycasting
     picking_mesh: Handle<Mesh>,
     /// The child scene that contains the gltF model used
@@ -77,6 +261,7 @@ impl StructureBundle {
             voxel_pos,
             raycast_mesh: RaycastMesh::default(),
             object_interaction: ObjectInteraction::None,
+            activity: StructureActivity::Active,
             picking_mesh,
             scene_bundle: SceneBundle {
                 scene: scene_handle,
@@ -103,6 +288,9 @@ impl Default for Footprint {
 }
 
 impl Footprint {
+    /// The number of storage slots that fit on a single tile of a [`Footprint::from_kind`] footprint.
+    pub const SLOTS_PER_TILE: usize = 4;
+
     /// A footprint that occupies a single tile.
     pub fn single() -> Self {
         Self {
@@ -123,6 +311,67 @@ impl Footprint {
         Footprint { set }
     }
 
+    /// A footprint that occupies a `side` by `side` rhombus of tiles, extending from the origin.
+    ///
+    /// This is the hexagonal equivalent of a square, so a `side` of 2 covers four tiles.
+    pub fn rhombus(side: u32) -> Self {
+        let side = side as i32;
+        let mut set = HashSet::new();
+        for x in 0..side {
+            for y in 0..side {
+                set.insert(VoxelPos::from_xy(x, y));
+            }
+        }
+
+        Footprint { set }
+    }
+
+    /// A footprint that occupies a straight line of `length` tiles, extending from the origin along the x axis.
+    pub fn line(length: u32) -> Self {
+        let set = (0..length as i32)
+            .map(|x| VoxelPos::from_xy(x, 0))
+            .collect();
+
+        Footprint { set }
+    }
+
+    /// A footprint that occupies a triangle of tiles with sides `side` tiles long, extending from the origin.
+    ///
+    /// A `side` of 2 covers three tiles.
+    pub fn triangle(side: u32) -> Self {
+        let side = side as i32;
+        let mut set = HashSet::new();
+        for x in 0..side {
+            for y in 0..side - x {
+                set.insert(VoxelPos::from_xy(x, y));
+            }
+        }
+
+        Footprint { set }
+    }
+
+    /// Computes the default footprint of a structure based on the parameters of its [`StructureKind`].
+    ///
+    /// Storage structures grow by one [`Footprint::rhombus`] side each time their capacity passes
+    /// the next square number of [`Footprint::SLOTS_PER_TILE`]-slot tiles,
+    /// so a 4-slot storage is 1x1 and a 16-slot storage is 2x2.
+    /// All other structures occupy a single tile.
+    pub fn from_kind(kind: &StructureKind) -> Self {
+        match kind {
+            StructureKind::Storage { max_slot_count, .. } => {
+                let tiles_needed =
+                    ((max_slot_count + Self::SLOTS_PER_TILE - 1) / Self::SLOTS_PER_TILE).max(1);
+                let mut side = 1;
+                while side * side < tiles_needed {
+                    side += 1;
+                }
+
+                Footprint::rhombus(side as u32)
+            }
+            _ => Footprint::single(),
+        }
+    }
+
     /// Computes the set of tiles that this footprint occupies in world space, when centered at `center`.
     fn in_world_space(&self, center: VoxelPos) -> HashSet<VoxelPos> {
         self.set
@@ -141,12 +390,51 @@ impl Footprint {
         Footprint { set }
     }
 
-    /// Returns this footprint after correcting for offset and rotation.
+    /// Mirrors the footprint across the line through the origin along the `axis` direction.
+    fn mirrored(&self, axis: Direction) -> Self {
+        let mut set = HashSet::new();
+        for &voxel_pos in self.set.iter() {
+            set.insert(voxel_pos.mirrored(axis));
+        }
+
+        Footprint { set }
+    }
+
+    /// Returns this footprint after correcting for offset, mirroring and rotation.
+    ///
+    /// Mirrored footprints are always reflected across [`Facing::MIRROR_AXIS`] before they are rotated.
     pub(crate) fn normalized(&self, facing: Facing, center: VoxelPos) -> HashSet<VoxelPos> {
-        let rotated = self.rotated(facing);
+        let rotated = match facing.mirrored {
+            true => self.mirrored(Facing::MIRROR_AXIS).rotated(facing),
+            false => self.rotated(facing),
+        };
         rotated.in_world_space(center)
     }
 
+    /// Returns the offset of the tile in this footprint that lies furthest in the direction of `facing`, after normalization.
+    ///
+    /// Structures that act on the tile in front of them, such as releasers, should use the neighbor of this tile.
+    /// Ties are broken in favor of the tile closest to the origin, so single-tile footprints always return [`VoxelPos::ZERO`].
+    pub(crate) fn edge_tile(&self, facing: Facing) -> VoxelPos {
+        let forward = Hex::ZERO.neighbor(facing.direction);
+        // The dot product in cube coordinates measures how far along `forward` each tile lies
+        let projection = |hex: Hex| {
+            hex.x * forward.x + hex.y * forward.y + (-hex.x - hex.y) * (-forward.x - forward.y)
+        };
+
+        self.normalized(facing, VoxelPos::ZERO)
+            .into_iter()
+            .max_by_key(|voxel_pos| {
+                (
+                    projection(voxel_pos.hex),
+                    -(voxel_pos.hex.unsigned_distance_to(Hex::ZERO) as i32),
+                    voxel_pos.hex.x,
+                    voxel_pos.hex.y,
+                )
+            })
+            .unwrap_or(VoxelPos::ZERO)
+    }
+
     /// Returns the highest height of tiles in this footprint after normalization.
     ///
     /// Returns [`Height::ZERO`] if the footprint is empty or no valid tiles are found.
@@ -192,6 +480,62 @@ impl Footprint {
     }
 }
 
+/// The unprocessed equivalent of [`Footprint`].
+///
+/// Manifests can either list every tile explicitly, or name a common [`FootprintShape`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum RawFootprint {
+    /// The exact set of tiles taken up by the structure.
+    Explicit(Footprint),
+    /// A named shape, which is expanded into its tiles when the manifest is processed.
+    Shape(FootprintShape),
+}
+
+impl From<Footprint> for RawFootprint {
+    fn from(footprint: Footprint) -> Self {
+        RawFootprint::Explicit(footprint)
+    }
+}
+
+impl RawFootprint {
+    /// Expands this into the set of tiles that it covers.
+    ///
+    /// If the parameters of the shape are invalid, a description of the problem is returned instead.
+    pub fn expand(&self) -> Result<Footprint, String> {
+        match self {
+            RawFootprint::Explicit(footprint) => Ok(footprint.clone()),
+            RawFootprint::Shape(FootprintShape::Hexagon(0)) => {
+                Err("hexagon footprints must have a radius of at least 1".to_string())
+            }
+            RawFootprint::Shape(FootprintShape::Hexagon(radius)) => Ok(Footprint::hexagon(*radius)),
+            RawFootprint::Shape(FootprintShape::Line { length: 0 }) => {
+                Err("line footprints must have a length of at least 1".to_string())
+            }
+            RawFootprint::Shape(FootprintShape::Line { length }) => Ok(Footprint::line(*length)),
+            RawFootprint::Shape(FootprintShape::Triangle(0)) => {
+                Err("triangle footprints must have a side of at least 1".to_string())
+            }
+            RawFootprint::Shape(FootprintShape::Triangle(side)) => Ok(Footprint::triangle(*side)),
+        }
+    }
+}
+
+/// A common footprint shape, which can be named in manifests instead of listing every tile.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FootprintShape {
+    /// A solid hexagon with the provided radius, as in [`Footprint::hexagon`].
+    Hexagon(u32),
+    /// A straight line of tiles, as in [`Footprint::line`].
+    Line {
+        /// The number of tiles in the line.
+        length: u32,
+    },
+    /// A triangle with the provided side length, as in [`Footprint::triangle`].
+    Triangle(u32),
+}
+
 /// A special structure used to create interest in the game world.
 ///
 /// Landmarks cannot be created or destroyed by players.
@@ -215,6 +559,103 @@ mod tests {
         Footprint { set }
     }
 
+    /// A footprint that occupies a line of three adjacent tiles, beginning at the origin and moving right.
+    fn three_tile_footprint() -> Footprint {
+        Footprint {
+            set: HashSet::from_iter(vec![
+                VoxelPos::ZERO,
+                VoxelPos::from_xy(1, 0),
+                VoxelPos::from_xy(2, 0),
+            ]),
+        }
+    }
+
+    #[test]
+    fn named_footprint_shapes_are_expanded() {
+        let expand = |json: &str| {
+            serde_json::from_str::<RawFootprint>(json)
+                .unwrap()
+                .expand()
+                .unwrap()
+        };
+
+        assert_eq!(expand(r#"{ "hexagon": 1 }"#), Footprint::hexagon(1));
+        assert_eq!(expand(r#"{ "hexagon": 1 }"#).set.len(), 7);
+
+        assert_eq!(
+            expand(r#"{ "line": { "length": 3 } }"#),
+            three_tile_footprint()
+        );
+
+        let triangle = expand(r#"{ "triangle": 2 }"#);
+        assert_eq!(
+            triangle.set,
+            HashSet::from_iter(vec![
+                VoxelPos::ZERO,
+                VoxelPos::from_xy(1, 0),
+                VoxelPos::from_xy(0, 1),
+            ])
+        );
+        assert_eq!(expand(r#"{ "triangle": 3 }"#).set.len(), 6);
+    }
+
+    #[test]
+    fn degenerate_footprint_shapes_are_rejected() {
+        for json in [
+            r#"{ "hexagon": 0 }"#,
+            r#"{ "line": { "length": 0 } }"#,
+            r#"{ "triangle": 0 }"#,
+        ] {
+            let raw_footprint: RawFootprint = serde_json::from_str(json).unwrap();
+            assert!(raw_footprint.expand().is_err(), "{json}");
+        }
+    }
+
+    #[test]
+    fn explicit_footprints_round_trip() {
+        let raw_footprint = RawFootprint::from(two_tile_footprint());
+
+        let json = serde_json::to_string(&raw_footprint).unwrap();
+        let deserialized: RawFootprint = serde_json::from_str(&json).unwrap();
+
+        assert_eq!(deserialized, raw_footprint);
+        assert_eq!(deserialized.expand().unwrap(), two_tile_footprint());
+    }
+
+    #[test]
+    fn edge_tile_of_single_tile_footprint_is_the_origin() {
+        for direction in Direction::ALL_DIRECTIONS {
+            for mirrored in [false, true] {
+                let facing = Facing {
+                    direction,
+                    mirrored,
+                };
+                assert_eq!(Footprint::single().edge_tile(facing), VoxelPos::ZERO);
+            }
+        }
+    }
+
+    #[test]
+    fn edge_tile_of_long_footprint_faces_outward() {
+        let footprint = three_tile_footprint();
+
+        for direction in Direction::ALL_DIRECTIONS {
+            let facing = Facing {
+                direction,
+                mirrored: false,
+            };
+            let normalized = footprint.normalized(facing, VoxelPos::ZERO);
+            let edge_tile = footprint.edge_tile(facing);
+            let output_tile = edge_tile.neighbor(direction);
+
+            assert!(normalized.contains(&edge_tile), "{facing}: {edge_tile:?}");
+            assert!(
+                !normalized.contains(&output_tile),
+                "{facing}: {output_tile:?} is inside the footprint"
+            );
+        }
+    }
+
     #[test]
     fn hexagon_footprint_matches() {
         let footprint = Footprint::hexagon(1);
@@ -389,4 +830,67 @@ mod tests {
 
         assert_eq!(footprint.rotated(facing), footprint);
     }
+
+    /// An asymmetric footprint with no axis of mirror symmetry.
+    fn l_shaped_footprint() -> Footprint {
+        let mut set = HashSet::new();
+        set.insert(VoxelPos::ZERO);
+        set.insert(VoxelPos::from_xy(1, 0));
+        set.insert(VoxelPos::from_xy(2, 0));
+        set.insert(VoxelPos::from_xy(2, -1));
+
+        Footprint { set }
+    }
+
+    #[test]
+    fn footprint_mirrored_twice_is_unchanged() {
+        let footprint = l_shaped_footprint();
+
+        for axis in Direction::ALL_DIRECTIONS {
+            let mirrored = footprint.mirrored(axis);
+            assert_ne!(mirrored, footprint);
+            assert_eq!(mirrored.mirrored(axis), footprint);
+        }
+    }
+
+    #[test]
+    fn mirroring_then_rotating_matches_mirroring_across_the_rotated_axis() {
+        let footprint = l_shaped_footprint();
+        let axis_pos = VoxelPos::ZERO.neighbor(Facing::MIRROR_AXIS);
+
+        for direction in Direction::ALL_DIRECTIONS {
+            let facing = Facing {
+                direction,
+                mirrored: true,
+            };
+
+            // Rotating the footprint also rotates the axis it was mirrored across
+            let rotated_axis_pos = axis_pos.rotated(facing);
+            let rotated_axis = Direction::ALL_DIRECTIONS
+                .into_iter()
+                .find(|&axis| VoxelPos::ZERO.neighbor(axis) == rotated_axis_pos)
+                .unwrap();
+            let expected = footprint.rotated(facing).mirrored(rotated_axis);
+
+            assert_eq!(footprint.normalized(facing, VoxelPos::ZERO), expected.set);
+        }
+    }
+
+    #[test]
+    fn mirrored_facings_mirror_before_rotating() {
+        let footprint = l_shaped_footprint();
+        let mut facing = Facing::default();
+        facing.rotate_clockwise();
+        facing.mirrored = true;
+
+        let mirror_then_rotate = footprint.mirrored(Facing::MIRROR_AXIS).rotated(facing);
+        let rotate_then_mirror = footprint.rotated(facing).mirrored(Facing::MIRROR_AXIS);
+        assert_ne!(mirror_then_rotate, rotate_then_mirror);
+
+        let center = VoxelPos::from_xy(3, -2);
+        assert_eq!(
+            footprint.normalized(facing, center),
+            mirror_then_rotate.in_world_space(center)
+        );
+    }
 }
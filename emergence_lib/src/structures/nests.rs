@@ -0,0 +1,102 @@
+//! Nests are where tired units go to rest and recover their stamina.
+
+use bevy::{prelude::*, utils::HashSet};
+
+use crate::{
+    signals::{Emitter, SignalStrength, SignalType},
+    simulation::SimulationSet,
+};
+
+use super::StructureActivity;
+
+/// A structure where tired units can rest.
+#[derive(Component, Debug, Clone, PartialEq)]
+pub(crate) struct Nest {
+    /// The stamina restored to each resting unit every second.
+    rest_rate: f32,
+    /// The maximum number of units that can rest here at once.
+    capacity: u8,
+    /// The units currently resting here.
+    occupants: HashSet<Entity>,
+}
+
+impl Nest {
+    /// The strength of the [`SignalType::Rest`] signal emitted by nests with free space.
+    const SIGNAL_STRENGTH: f32 = 10.;
+
+    /// Creates a new, empty nest.
+    pub(crate) fn new(rest_rate: f32, capacity: u8) -> Self {
+        Nest {
+            rest_rate,
+            capacity,
+            occupants: HashSet::new(),
+        }
+    }
+
+    /// The stamina restored to each resting unit every second.
+    pub(crate) fn rest_rate(&self) -> f32 {
+        self.rest_rate
+    }
+
+    /// Is there space for another unit to rest here?
+    pub(crate) fn has_room(&self) -> bool {
+        self.occupants.len() < self.capacity as usize
+    }
+
+    /// Is `unit_entity` currently resting here?
+    pub(crate) fn is_occupied_by(&self, unit_entity: Entity) -> bool {
+        self.occupants.contains(&unit_entity)
+    }
+
+    /// Lets `unit_entity` rest here if there is room.
+    ///
+    /// Returns `true` if the unit is now resting here.
+    pub(crate) fn admit(&mut self, unit_entity: Entity) -> bool {
+        if self.is_occupied_by(unit_entity) {
+            return true;
+        }
+
+        if self.has_room() {
+            self.occupants.insert(unit_entity);
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Removes all units from the nest.
+    ///
+    /// Occupancy is recomputed each tick, so units that wander away free up their space.
+    pub(crate) fn clear_occupants(&mut self) {
+        self.occupants.clear();
+    }
+}
+
+/// Logic that lets nests advertise their free space.
+pub(super) struct NestPlugin;
+
+impl Plugin for NestPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system(
+            nest_signals
+                .in_set(SimulationSet)
+                .in_schedule(CoreSchedule::FixedUpdate),
+        );
+    }
+}
+
+/// Sets the emitters for nests, which attract tired units while they have free space.
+fn nest_signals(mut nest_query: Query<(&mut Emitter, &Nest, Option<&StructureActivity>)>) {
+    for (mut emitter, nest, maybe_activity) in nest_query.iter_mut() {
+        emitter.signals.clear();
+        if StructureActivity::is_paused(maybe_activity) {
+            continue;
+        }
+
+        if nest.has_room() {
+            emitter
+                .signals
+                .push((SignalType::Rest, SignalStrength::new(Nest::SIGNAL_STRENGTH)));
+        }
+    }
+}
@@ -0,0 +1,204 @@
+//! Structure-specific constraints on where structures can be placed.
+//!
+//! Each [`PlacementRule`] is attached to a [`StructureTag`]:
+//! every structure whose manifest entry carries that tag must satisfy the rule before it can be placed.
+
+use bevy::{prelude::*, utils::HashMap};
+
+use crate::{
+    asset_management::manifest::Id,
+    geometry::{Facing, MapGeometry, VoxelPos},
+    water::WaterDepth,
+};
+
+use super::{
+    structure_manifest::{Structure, StructureManifest, StructureTag, StructureTags},
+    Footprint,
+};
+
+/// Everything a [`PlacementRule`] needs to know about a proposed placement.
+pub(crate) struct PlacementContext<'a> {
+    /// The center of the structure.
+    pub(crate) center: VoxelPos,
+    /// The orientation of the structure.
+    pub(crate) facing: Facing,
+    /// The footprint of the structure, before it is rotated by `facing`.
+    pub(crate) footprint: &'a Footprint,
+    /// The map the structure would be placed on.
+    pub(crate) map_geometry: &'a MapGeometry,
+    /// Looks up the [`WaterDepth`] of a terrain entity.
+    ///
+    /// Returns [`None`] if no water information is available.
+    pub(crate) water_depth: &'a dyn Fn(Entity) -> Option<WaterDepth>,
+}
+
+impl<'a> PlacementContext<'a> {
+    /// The tiles covered by the structure.
+    pub(crate) fn covered_hexes(&self) -> Vec<hexx::Hex> {
+        self.footprint
+            .normalized(self.facing, self.center)
+            .into_iter()
+            .map(|voxel_pos| voxel_pos.hex)
+            .collect()
+    }
+
+    /// The [`WaterDepth`] at `hex`, treating tiles without any water information as [`WaterDepth::Dry`].
+    pub(crate) fn water_depth_at(&self, hex: hexx::Hex) -> WaterDepth {
+        self.map_geometry
+            .get_terrain(hex)
+            .ok()
+            .and_then(self.water_depth)
+            .unwrap_or_default()
+    }
+}
+
+/// A constraint on where structures can be placed.
+pub(crate) trait PlacementRule: Send + Sync + 'static {
+    /// Checks the proposed placement, returning the reason it is not allowed if it breaks this rule.
+    fn check(&self, context: &PlacementContext) -> Result<(), &'static str>;
+}
+
+/// The [`PlacementRule`]s that apply to each [`StructureTag`].
+#[derive(Resource)]
+pub(crate) struct PlacementRules {
+    /// The rules to check for structures with each tag.
+    rules: HashMap<Id<StructureTag>, Vec<Box<dyn PlacementRule>>>,
+}
+
+impl Default for PlacementRules {
+    fn default() -> Self {
+        let mut placement_rules = PlacementRules {
+            rules: HashMap::default(),
+        };
+        placement_rules.add_rule(Id::from_name("shoreline".to_string()), NeedsAdjacentWater);
+        placement_rules
+    }
+}
+
+impl PlacementRules {
+    /// Requires every structure tagged with `tag` to satisfy `rule`.
+    pub(crate) fn add_rule(&mut self, tag: Id<StructureTag>, rule: impl PlacementRule) {
+        self.rules.entry(tag).or_default().push(Box::new(rule));
+    }
+
+    /// Checks every rule that applies to a structure with the provided `tags`.
+    ///
+    /// Returns the reason given by the first rule that is broken, if any.
+    pub(crate) fn placement_check(
+        &self,
+        tags: &StructureTags,
+        context: &PlacementContext,
+    ) -> Result<(), &'static str> {
+        tags.0
+            .iter()
+            .filter_map(|tag| self.rules.get(tag))
+            .flatten()
+            .try_for_each(|rule| rule.check(context))
+    }
+
+    /// Checks every rule that applies to `structure_id` when placed at `center` with the provided `facing`.
+    pub(crate) fn check_structure(
+        &self,
+        structure_manifest: &StructureManifest,
+        structure_id: Id<Structure>,
+        center: VoxelPos,
+        facing: Facing,
+        map_geometry: &MapGeometry,
+        water_depth: impl Fn(Entity) -> Option<WaterDepth>,
+    ) -> Result<(), &'static str> {
+        let structure_data = structure_manifest.get(structure_id);
+        let context = PlacementContext {
+            center,
+            facing,
+            footprint: &structure_data.footprint,
+            map_geometry,
+            water_depth: &water_depth,
+        };
+
+        self.placement_check(&structure_data.tags, &context)
+    }
+}
+
+/// Structures must be next to a tile with surface water, like docks at the shore.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct NeedsAdjacentWater;
+
+impl PlacementRule for NeedsAdjacentWater {
+    fn check(&self, context: &PlacementContext) -> Result<(), &'static str> {
+        let covered_hexes = context.covered_hexes();
+
+        let next_to_water = covered_hexes
+            .iter()
+            .flat_map(|hex| hex.all_neighbors())
+            .filter(|neighbor| !covered_hexes.contains(neighbor))
+            .any(|neighbor| {
+                matches!(context.water_depth_at(neighbor), WaterDepth::Flooded(depth) if depth.0 > 0.)
+            });
+
+        match next_to_water {
+            true => Ok(()),
+            false => Err("must be built next to water"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::geometry::Height;
+    use hexx::Hex;
+
+    fn dock_tags() -> StructureTags {
+        StructureTags(
+            [Id::from_name("shoreline".to_string())]
+                .into_iter()
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn docks_are_rejected_inland_and_allowed_at_the_shore() {
+        let mut world = World::new();
+        let map_geometry = MapGeometry::new(&mut world, 3);
+
+        // A lake on the eastern edge of the map
+        let lake = Hex::new(3, 0);
+        let lake_entity = map_geometry.get_terrain(lake).unwrap();
+        let water_depth = |terrain_entity: Entity| match terrain_entity == lake_entity {
+            true => Some(WaterDepth::Flooded(Height(1.))),
+            false => Some(WaterDepth::Dry),
+        };
+
+        let placement_rules = PlacementRules::default();
+        let footprint = Footprint::single();
+        let check_at = |hex: Hex| {
+            let context = PlacementContext {
+                center: map_geometry.on_top_of_terrain(hex),
+                facing: Facing::default(),
+                footprint: &footprint,
+                map_geometry: &map_geometry,
+                water_depth: &water_depth,
+            };
+            placement_rules.placement_check(&dock_tags(), &context)
+        };
+
+        assert_eq!(
+            check_at(Hex::new(-2, 0)),
+            Err("must be built next to water")
+        );
+        assert_eq!(check_at(Hex::new(2, 0)), Ok(()));
+
+        // Structures without the tag are unaffected
+        let context = PlacementContext {
+            center: map_geometry.on_top_of_terrain(Hex::new(-2, 0)),
+            facing: Facing::default(),
+            footprint: &footprint,
+            map_geometry: &map_geometry,
+            water_depth: &water_depth,
+        };
+        assert_eq!(
+            placement_rules.placement_check(&StructureTags::default(), &context),
+            Ok(())
+        );
+    }
+}
@@ -0,0 +1,767 @@
+//! Moving structures that have already been placed, without losing their contents.
+//!
+//! Relocation is requested with a [`RelocateStructure`] command.
+//! Units must first do some work at the structure, drawn in by its work signal,
+//! after which the structure is picked up and set down at its destination in a single step by [`move_structure`].
+//! Calling [`move_structure`] directly skips the work entirely, moving the structure immediately.
+
+use bevy::{ecs::system::Command, prelude::*};
+use std::time::Duration;
+
+use crate::{
+    asset_management::manifest::Id,
+    construction::ghosts::WorkplaceId,
+    crafting::{
+        flow::ThroughputTracker, set_crafting_emitter, set_storage_emitter, workers::WorkersPresent,
+    },
+    geometry::{Facing, MapGeometry, VoxelPos},
+    organisms::Organism,
+    signals::{
+        Emitter, LogisticsCategory, LogisticsPriorities, ManageSignals, SignalType, Signals,
+    },
+    simulation::SimulationSet,
+    water::WaterDepth,
+};
+
+use super::{
+    commands::PlacementError,
+    composters::composter_signals,
+    incinerators::incinerator_signals,
+    logistic_buildings::logistic_buildings_signals,
+    placement_rules::PlacementRules,
+    structure_manifest::{Structure, StructureManifest},
+    Landmark,
+};
+
+/// Controls how much labor it takes to relocate a structure.
+#[derive(Resource, Debug, Clone, PartialEq)]
+pub(crate) struct RelocationConfig {
+    /// The amount of work that a single unit must do before the structure is moved.
+    ///
+    /// If this is zero, structures are moved as soon as relocation is requested.
+    pub(crate) work_required: Duration,
+    /// The number of units that can work on a relocation at once.
+    pub(crate) workers_allowed: u8,
+}
+
+impl Default for RelocationConfig {
+    fn default() -> Self {
+        RelocationConfig {
+            work_required: Duration::from_secs(10),
+            workers_allowed: 2,
+        }
+    }
+}
+
+/// A structure could not be relocated via [`RelocateStructure`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum RelocationError {
+    /// The entity is not a structure.
+    NotAStructure,
+    /// Landmarks are part of the world, and cannot be moved by players.
+    Landmark,
+    /// Organisms are rooted in place.
+    Organism,
+    /// The destination is outside of the map.
+    OutOfBounds,
+    /// Some of the voxels needed at the destination are occupied by something other than the structure itself.
+    SpaceOccupied,
+    /// One of the [`PlacementRule`](super::placement_rules::PlacementRule)s for this structure forbids the destination.
+    RuleViolated(&'static str),
+}
+
+impl From<RelocationError> for PlacementError {
+    fn from(error: RelocationError) -> Self {
+        match error {
+            RelocationError::NotAStructure => PlacementError::UnknownStructure,
+            RelocationError::Landmark | RelocationError::Organism => PlacementError::Immovable,
+            RelocationError::OutOfBounds => PlacementError::OutOfBounds,
+            RelocationError::SpaceOccupied => PlacementError::SpaceOccupied,
+            RelocationError::RuleViolated(rule) => PlacementError::RuleViolated(rule),
+        }
+    }
+}
+
+/// Immediately moves the structure `structure_entity` so that it is centered on `center` and faces `facing`.
+///
+/// Unlike [`RelocateStructure`], no work is needed: this is the entry point for scenarios, tests and tools.
+/// Relocations requested by the player are carried out through here too, once their work is done.
+/// The destination is validated just like a newly placed structure, except that the structure's current footprint never blocks it.
+/// Its inventories, recipe and crafting progress are all kept intact, but its [`ThroughputTracker`] is reset.
+pub(crate) fn move_structure(
+    world: &mut World,
+    structure_entity: Entity,
+    center: VoxelPos,
+    facing: Facing,
+) -> Result<(), PlacementError> {
+    RelocateStructure {
+        structure_entity,
+        center,
+        facing,
+    }
+    .validate(world)?;
+
+    let entity_ref = world.entity(structure_entity);
+    let structure_id = *entity_ref.get::<Id<Structure>>().unwrap();
+    let old_center = *entity_ref.get::<VoxelPos>().unwrap();
+    let old_facing = *entity_ref.get::<Facing>().unwrap();
+    let structure_data = world.resource::<StructureManifest>().get(structure_id);
+    let footprint = structure_data.footprint.clone();
+    let can_walk_on_roof = structure_data.can_walk_on_roof;
+    let can_walk_through = structure_data.can_walk_through;
+    let max_occupants = structure_data.max_occupants;
+
+    let mut map_geometry = world.resource_mut::<MapGeometry>();
+    // This also forgets the occupant limit, which must be restored wherever the structure ends up
+    map_geometry.remove_structure_entity(structure_entity);
+    if map_geometry
+        .add_structure(
+            center,
+            facing,
+            &footprint,
+            can_walk_on_roof,
+            can_walk_through,
+            structure_entity,
+        )
+        .is_err()
+    {
+        // Put everything back the way it was
+        map_geometry
+            .add_structure(
+                old_center,
+                old_facing,
+                &footprint,
+                can_walk_on_roof,
+                can_walk_through,
+                structure_entity,
+            )
+            .unwrap();
+        map_geometry.set_occupant_limit(structure_entity, max_occupants);
+        return Err(PlacementError::SpaceOccupied);
+    }
+    map_geometry.set_occupant_limit(structure_entity, max_occupants);
+    let world_pos = footprint.world_pos(facing, center, &map_geometry);
+
+    // The signals emitted at the old location should not linger there
+    if let Some(mut signals) = world.get_resource_mut::<Signals>() {
+        signals.remove_emissions_from(structure_entity);
+    }
+
+    let mut entity_mut = world.entity_mut(structure_entity);
+    entity_mut.insert((center, facing));
+    if let (Some(mut transform), Some(world_pos)) = (entity_mut.get_mut::<Transform>(), world_pos) {
+        transform.translation = world_pos;
+    }
+
+    // Throughput at the old location says little about how the structure will do here
+    if let Some(mut throughput_tracker) = entity_mut.get_mut::<ThroughputTracker>() {
+        throughput_tracker.reset();
+    }
+
+    Ok(())
+}
+
+/// A structure that will be moved once enough work has been done.
+#[derive(Component, Debug, Clone, PartialEq)]
+pub(crate) struct PendingRelocation {
+    /// The center of the structure after the move.
+    pub(crate) destination: VoxelPos,
+    /// The orientation of the structure after the move.
+    pub(crate) facing: Facing,
+    /// The amount of work that must still be done by a single unit before the structure is moved.
+    pub(crate) work_remaining: Duration,
+    /// The number of workers allowed at the structure before relocation was requested.
+    ///
+    /// This is [`None`] if the structure had no [`WorkersPresent`] of its own.
+    previous_workers_allowed: Option<u8>,
+}
+
+/// Logic that controls how structures are relocated.
+pub(super) struct RelocationPlugin;
+
+impl Plugin for RelocationPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<RelocationConfig>().add_systems(
+            (
+                progress_relocations,
+                relocation_signals
+                    // Most emitters are reset each tick, which would wipe out the work signal
+                    .after(set_crafting_emitter)
+                    .after(set_storage_emitter)
+                    .after(logistic_buildings_signals)
+                    .after(composter_signals)
+                    .after(incinerator_signals)
+                    .before(ManageSignals),
+            )
+                .in_set(SimulationSet)
+                .in_schedule(CoreSchedule::FixedUpdate),
+        );
+    }
+}
+
+/// A [`Command`] that moves a structure to a new `center` and `facing`.
+///
+/// Its inventories, recipe and crafting progress are all kept intact.
+/// Unless the [`RelocationConfig`] requires no work, the move is delayed until units have done enough work at the structure.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct RelocateStructure {
+    /// The structure to move.
+    pub(crate) structure_entity: Entity,
+    /// The center of the structure after the move.
+    pub(crate) center: VoxelPos,
+    /// The orientation of the structure after the move.
+    pub(crate) facing: Facing,
+}
+
+impl Command for RelocateStructure {
+    fn write(self, world: &mut World) {
+        if let Err(error) = self.request(world) {
+            warn!(
+                "Could not relocate {:?} to {}: {error:?}",
+                self.structure_entity, self.center
+            );
+        }
+    }
+}
+
+impl RelocateStructure {
+    /// Validates the destination, then either moves the structure or waits for the work to be done.
+    ///
+    /// A new request replaces any relocation that was already pending for the same structure.
+    pub(crate) fn request(self, world: &mut World) -> Result<(), PlacementError> {
+        self.validate(world)?;
+
+        let config = world
+            .get_resource::<RelocationConfig>()
+            .cloned()
+            .unwrap_or_default();
+        if config.work_required.is_zero() {
+            return move_structure(world, self.structure_entity, self.center, self.facing);
+        }
+
+        let mut entity_mut = world.entity_mut(self.structure_entity);
+        let previous_workers_allowed = match entity_mut.get::<PendingRelocation>() {
+            Some(pending_relocation) => pending_relocation.previous_workers_allowed,
+            None => entity_mut
+                .get::<WorkersPresent>()
+                .map(WorkersPresent::allowed),
+        };
+
+        match entity_mut.get_mut::<WorkersPresent>() {
+            Some(mut workers_present) => {
+                let allowed = workers_present.allowed().max(config.workers_allowed);
+                workers_present.set_allowed(allowed);
+            }
+            None => {
+                entity_mut.insert(WorkersPresent::new(config.workers_allowed));
+            }
+        }
+
+        entity_mut.insert(PendingRelocation {
+            destination: self.center,
+            facing: self.facing,
+            work_remaining: config.work_required,
+            previous_workers_allowed,
+        });
+
+        Ok(())
+    }
+
+    /// Checks that the structure can be moved, and that its footprint fits at the destination.
+    ///
+    /// Voxels occupied by the structure itself do not block the move, so structures can be nudged or rotated in place.
+    fn validate(&self, world: &World) -> Result<(), RelocationError> {
+        let Some(entity_ref) = world.get_entity(self.structure_entity) else {
+            return Err(RelocationError::NotAStructure);
+        };
+        let Some(&structure_id) = entity_ref.get::<Id<Structure>>() else {
+            return Err(RelocationError::NotAStructure);
+        };
+
+        if entity_ref.contains::<Landmark>() {
+            return Err(RelocationError::Landmark);
+        }
+
+        if entity_ref.contains::<Organism>() {
+            return Err(RelocationError::Organism);
+        }
+
+        let map_geometry = world.resource::<MapGeometry>();
+        if !map_geometry.is_valid(self.center.hex) {
+            return Err(RelocationError::OutOfBounds);
+        }
+
+        let structure_manifest = world.resource::<StructureManifest>();
+        let footprint = &structure_manifest.get(structure_id).footprint;
+        let blocked = footprint
+            .normalized(self.facing, self.center)
+            .into_iter()
+            .any(|voxel_pos| {
+                !map_geometry.is_valid(voxel_pos.hex)
+                    || (map_geometry.is_voxel_clear(voxel_pos).is_err()
+                        && map_geometry.get_structure(voxel_pos) != Some(self.structure_entity))
+            });
+
+        if blocked {
+            return Err(RelocationError::SpaceOccupied);
+        }
+
+        match world.get_resource::<PlacementRules>() {
+            Some(placement_rules) => placement_rules
+                .check_structure(
+                    structure_manifest,
+                    structure_id,
+                    self.center,
+                    self.facing,
+                    map_geometry,
+                    |terrain_entity| world.get::<WaterDepth>(terrain_entity).copied(),
+                )
+                .map_err(RelocationError::RuleViolated),
+            None => Ok(()),
+        }
+    }
+}
+
+/// A [`Command`] that moves a structure once the work on its [`PendingRelocation`] is done.
+struct FinishRelocation {
+    /// The structure to move.
+    structure_entity: Entity,
+}
+
+impl Command for FinishRelocation {
+    fn write(self, world: &mut World) {
+        let Some(mut entity_mut) = world.get_entity_mut(self.structure_entity) else { return };
+        let Some(pending_relocation) = entity_mut.take::<PendingRelocation>() else { return };
+
+        // Only the workers needed by the structure itself are kept
+        match pending_relocation.previous_workers_allowed {
+            Some(allowed) => {
+                if let Some(mut workers_present) = entity_mut.get_mut::<WorkersPresent>() {
+                    workers_present.set_allowed(allowed);
+                }
+            }
+            None => {
+                entity_mut.remove::<WorkersPresent>();
+            }
+        }
+
+        // The destination may have been filled while the work was being done
+        if let Err(error) = move_structure(
+            world,
+            self.structure_entity,
+            pending_relocation.destination,
+            pending_relocation.facing,
+        ) {
+            warn!(
+                "Could not relocate {:?} to {}: {error:?}",
+                self.structure_entity, pending_relocation.destination
+            );
+        }
+    }
+}
+
+/// Counts down the work remaining on each [`PendingRelocation`], moving the structure once it is done.
+fn progress_relocations(
+    mut relocation_query: Query<(Entity, &mut PendingRelocation, &WorkersPresent)>,
+    fixed_time: Res<FixedTime>,
+    mut commands: Commands,
+) {
+    for (structure_entity, mut pending_relocation, workers_present) in relocation_query.iter_mut() {
+        let work_done = fixed_time
+            .period
+            .mul_f32(workers_present.effective_workers());
+        pending_relocation.work_remaining =
+            pending_relocation.work_remaining.saturating_sub(work_done);
+
+        if pending_relocation.work_remaining.is_zero() {
+            commands.add(FinishRelocation { structure_entity });
+        }
+    }
+}
+
+/// Draws units to structures that are waiting to be relocated, so that they can do the work needed.
+fn relocation_signals(
+    mut relocation_query: Query<(&mut Emitter, &Id<Structure>), With<PendingRelocation>>,
+    priorities: Res<LogisticsPriorities>,
+) {
+    /// Controls how strong the signal is for structures waiting to be relocated.
+    const RELOCATION_SIGNAL_STRENGTH: f32 = 100.;
+
+    let signal_strength =
+        priorities.signal_strength(LogisticsCategory::Construction, RELOCATION_SIGNAL_STRENGTH);
+
+    for (mut emitter, &structure_id) in relocation_query.iter_mut() {
+        let signal_type = SignalType::Work(WorkplaceId::structure(structure_id));
+        // Not every emitter is reset each tick, so avoid stacking up duplicate signals
+        emitter
+            .signals
+            .retain(|(existing, _)| *existing != signal_type);
+        emitter.signals.push((signal_type, signal_strength));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        asset_management::manifest::ManifestBuilder,
+        crafting::{
+            inventories::{CraftingState, InputInventory, OutputInventory, StorageInventory},
+            recipe::{ActiveRecipe, RecipeData, RecipeInput, RecipeManifest, RecipeOutput},
+        },
+        items::{
+            item_manifest::{Item, ItemData, ItemManifest},
+            ItemCount,
+        },
+        structures::{
+            commands::place_structure,
+            structure_manifest::{StructureData, StructureKind},
+            Footprint,
+        },
+    };
+    use hexx::Hex;
+
+    fn grain() -> Id<Item> {
+        Id::from_name("grain".to_string())
+    }
+
+    fn flour() -> Id<Item> {
+        Id::from_name("flour".to_string())
+    }
+
+    fn mill() -> Id<Structure> {
+        Id::from_name("mill".to_string())
+    }
+
+    fn hut() -> Id<Structure> {
+        Id::from_name("hut".to_string())
+    }
+
+    fn boulder() -> Id<Structure> {
+        Id::from_name("boulder".to_string())
+    }
+
+    fn long_mill() -> Id<Structure> {
+        Id::from_name("long_mill".to_string())
+    }
+
+    fn plaza() -> Id<Structure> {
+        Id::from_name("plaza".to_string())
+    }
+
+    /// Creates a world with a mill that grinds grain into flour, a storage hut and a boulder available to place.
+    ///
+    /// The long mill is identical to the mill, but covers three tiles in a line.
+    /// The plaza can be walked through by up to two units at once.
+    fn relocation_world(relocation_config: RelocationConfig) -> World {
+        let mut world = World::new();
+        let map_geometry = MapGeometry::new(&mut world, 3);
+
+        let item_data = ItemData::basic(10);
+        let item_manifest: ItemManifest = ManifestBuilder::new()
+            .add("grain", item_data.clone())
+            .add("flour", item_data)
+            .build();
+
+        let recipe_manifest: RecipeManifest = ManifestBuilder::new()
+            .add(
+                "grind",
+                RecipeData {
+                    craft_time: Duration::from_secs(10),
+                    ..RecipeData::basic(
+                        RecipeInput::Exact(vec![ItemCount::one(grain())]),
+                        RecipeOutput::Deterministic(vec![ItemCount::one(flour())]),
+                    )
+                },
+            )
+            .build();
+
+        let structure = |kind| StructureData {
+            kind,
+            ..StructureData::impassable()
+        };
+        let structure_manifest: StructureManifest = ManifestBuilder::new()
+            .add(
+                "mill",
+                structure(StructureKind::Crafting {
+                    starting_recipe: ActiveRecipe::new(Id::from_name("grind".to_string())),
+                    input_buffer_slots: None,
+                    output_buffer_slots: None,
+                }),
+            )
+            .add(
+                "hut",
+                structure(StructureKind::Storage {
+                    max_slot_count: 1,
+                    reserved_for: None,
+                    starting_inventory: Vec::new(),
+                    merge_adjacent: false,
+                }),
+            )
+            .add("boulder", structure(StructureKind::Landmark))
+            .add(
+                "long_mill",
+                StructureData {
+                    footprint: Footprint::line(3),
+                    ..structure(StructureKind::Crafting {
+                        starting_recipe: ActiveRecipe::new(Id::from_name("grind".to_string())),
+                        input_buffer_slots: None,
+                        output_buffer_slots: None,
+                    })
+                },
+            )
+            .add(
+                "plaza",
+                StructureData {
+                    max_occupants: Some(2),
+                    ..StructureData::passable()
+                },
+            )
+            .build();
+
+        world.insert_resource(map_geometry);
+        world.insert_resource(item_manifest);
+        world.insert_resource(recipe_manifest);
+        world.insert_resource(structure_manifest);
+        world.insert_resource(relocation_config);
+        world
+    }
+
+    fn on_top_of_terrain(world: &World, hex: Hex) -> VoxelPos {
+        world.resource::<MapGeometry>().on_top_of_terrain(hex)
+    }
+
+    #[test]
+    fn relocated_structures_keep_their_occupant_limit() {
+        let mut world = relocation_world(RelocationConfig {
+            work_required: Duration::ZERO,
+            ..Default::default()
+        });
+        let origin = on_top_of_terrain(&world, Hex::ZERO);
+        let destination = on_top_of_terrain(&world, Hex::new(2, 0));
+        let blocked = on_top_of_terrain(&world, Hex::new(-2, 0));
+        let plaza_entity = place_structure(&mut world, plaza(), origin, Facing::default()).unwrap();
+        place_structure(&mut world, boulder(), blocked, Facing::default()).unwrap();
+        assert_eq!(
+            world.resource::<MapGeometry>().max_occupants(origin),
+            Some(2)
+        );
+
+        assert_eq!(
+            move_structure(&mut world, plaza_entity, destination, Facing::default()),
+            Ok(())
+        );
+        let map_geometry = world.resource::<MapGeometry>();
+        assert_eq!(map_geometry.max_occupants(destination), Some(2));
+        assert_eq!(map_geometry.max_occupants(origin), None);
+
+        // A rejected move leaves the limit where it was
+        assert!(move_structure(&mut world, plaza_entity, blocked, Facing::default()).is_err());
+        assert_eq!(
+            world.resource::<MapGeometry>().max_occupants(destination),
+            Some(2)
+        );
+    }
+
+    #[test]
+    fn relocated_crafters_keep_their_progress_and_inventories() {
+        let mut world = relocation_world(RelocationConfig {
+            work_required: Duration::ZERO,
+            ..Default::default()
+        });
+        let origin = on_top_of_terrain(&world, Hex::ZERO);
+        let destination = on_top_of_terrain(&world, Hex::new(2, 0));
+        let mill_entity = place_structure(&mut world, mill(), origin, Facing::default()).unwrap();
+
+        // Stop the mill part of the way through grinding, with items in both inventories
+        let crafting_state = CraftingState::InProgress {
+            progress: Duration::from_secs(4),
+            required: Duration::from_secs(10),
+        };
+        world.resource_scope(|world, item_manifest: Mut<ItemManifest>| {
+            let mut mill_mut = world.entity_mut(mill_entity);
+            mill_mut
+                .get_mut::<InputInventory>()
+                .unwrap()
+                .fill_with_items(&ItemCount::new(grain(), 2), &item_manifest)
+                .unwrap();
+            mill_mut
+                .get_mut::<OutputInventory>()
+                .unwrap()
+                .add_item_all_or_nothing(&ItemCount::new(flour(), 3), &item_manifest)
+                .unwrap();
+            *mill_mut.get_mut::<CraftingState>().unwrap() = crafting_state.clone();
+        });
+
+        let relocate_structure = RelocateStructure {
+            structure_entity: mill_entity,
+            center: destination,
+            facing: Facing::default(),
+        };
+        assert_eq!(relocate_structure.request(&mut world), Ok(()));
+
+        let map_geometry = world.resource::<MapGeometry>();
+        assert_eq!(map_geometry.get_structure(origin), None);
+        assert_eq!(map_geometry.get_structure(destination), Some(mill_entity));
+
+        let mill_ref = world.entity(mill_entity);
+        assert_eq!(mill_ref.get::<VoxelPos>(), Some(&destination));
+        assert_eq!(mill_ref.get::<CraftingState>(), Some(&crafting_state));
+        assert_eq!(
+            mill_ref.get::<ActiveRecipe>(),
+            Some(&ActiveRecipe::new(Id::from_name("grind".to_string())))
+        );
+        let input_inventory = mill_ref.get::<InputInventory>().unwrap();
+        assert_eq!(input_inventory.inventory().item_count(grain()), 2);
+        let output_inventory = mill_ref.get::<OutputInventory>().unwrap();
+        assert_eq!(output_inventory.item_count(flour()), 3);
+    }
+
+    #[test]
+    fn move_structure_moves_immediately_onto_its_own_footprint() {
+        // Relocation normally needs work, which move_structure skips
+        let mut world = relocation_world(RelocationConfig::default());
+        let origin = on_top_of_terrain(&world, Hex::ZERO);
+        // Overlaps two of the three tiles the long mill started on
+        let destination = on_top_of_terrain(&world, Hex::new(1, 0));
+        let mill_entity =
+            place_structure(&mut world, long_mill(), origin, Facing::default()).unwrap();
+        world
+            .get_mut::<ThroughputTracker>(mill_entity)
+            .unwrap()
+            .record(Duration::from_secs(1), 4);
+
+        assert_eq!(
+            move_structure(&mut world, mill_entity, destination, Facing::default()),
+            Ok(())
+        );
+
+        let footprint = world
+            .resource::<StructureManifest>()
+            .get(long_mill())
+            .footprint
+            .clone();
+        let old_voxels = footprint.normalized(Facing::default(), origin);
+        let new_voxels = footprint.normalized(Facing::default(), destination);
+        let map_geometry = world.resource::<MapGeometry>();
+        for voxel_pos in &new_voxels {
+            assert_eq!(map_geometry.get_structure(*voxel_pos), Some(mill_entity));
+        }
+        for voxel_pos in old_voxels.difference(&new_voxels) {
+            assert_eq!(map_geometry.get_structure(*voxel_pos), None);
+        }
+
+        let mill_ref = world.entity(mill_entity);
+        assert_eq!(mill_ref.get::<VoxelPos>(), Some(&destination));
+        assert!(mill_ref.get::<PendingRelocation>().is_none());
+        // The old throughput no longer applies
+        assert_eq!(
+            mill_ref.get::<ThroughputTracker>(),
+            Some(&ThroughputTracker::default())
+        );
+    }
+
+    #[test]
+    fn relocating_onto_an_occupied_footprint_fails_cleanly() {
+        let mut world = relocation_world(RelocationConfig {
+            work_required: Duration::ZERO,
+            ..Default::default()
+        });
+        let origin = on_top_of_terrain(&world, Hex::ZERO);
+        let occupied = on_top_of_terrain(&world, Hex::new(1, 0));
+        let hut_entity = place_structure(&mut world, hut(), origin, Facing::default()).unwrap();
+        let neighbor_entity =
+            place_structure(&mut world, hut(), occupied, Facing::default()).unwrap();
+
+        let relocate_structure = RelocateStructure {
+            structure_entity: hut_entity,
+            center: occupied,
+            facing: Facing::default(),
+        };
+        assert_eq!(
+            relocate_structure.request(&mut world),
+            Err(PlacementError::SpaceOccupied)
+        );
+
+        // Nothing was moved
+        let map_geometry = world.resource::<MapGeometry>();
+        assert_eq!(map_geometry.get_structure(origin), Some(hut_entity));
+        assert_eq!(map_geometry.get_structure(occupied), Some(neighbor_entity));
+        assert_eq!(world.get::<VoxelPos>(hut_entity), Some(&origin));
+        assert!(world.get::<PendingRelocation>(hut_entity).is_none());
+    }
+
+    #[test]
+    fn landmarks_refuse_to_be_relocated() {
+        let mut world = relocation_world(RelocationConfig::default());
+        let origin = on_top_of_terrain(&world, Hex::ZERO);
+        let destination = on_top_of_terrain(&world, Hex::new(2, 0));
+        let boulder_entity =
+            place_structure(&mut world, boulder(), origin, Facing::default()).unwrap();
+
+        let relocate_structure = RelocateStructure {
+            structure_entity: boulder_entity,
+            center: destination,
+            facing: Facing::default(),
+        };
+        assert_eq!(
+            relocate_structure.request(&mut world),
+            Err(PlacementError::Immovable)
+        );
+        assert_eq!(world.get::<VoxelPos>(boulder_entity), Some(&origin));
+    }
+
+    #[test]
+    fn structures_only_move_once_the_work_is_done() {
+        let mut world = relocation_world(RelocationConfig {
+            work_required: Duration::from_secs(2),
+            workers_allowed: 1,
+        });
+        world.insert_resource(FixedTime::new_from_secs(1.));
+        let origin = on_top_of_terrain(&world, Hex::ZERO);
+        let destination = on_top_of_terrain(&world, Hex::new(-2, 0));
+        let hut_entity = place_structure(&mut world, hut(), origin, Facing::default()).unwrap();
+        world
+            .resource_scope(|world, item_manifest: Mut<ItemManifest>| {
+                world
+                    .get_mut::<StorageInventory>(hut_entity)
+                    .unwrap()
+                    .add_item_all_or_nothing(&ItemCount::new(grain(), 5), &item_manifest)
+            })
+            .unwrap();
+
+        let relocate_structure = RelocateStructure {
+            structure_entity: hut_entity,
+            center: destination,
+            facing: Facing::default(),
+        };
+        assert_eq!(relocate_structure.request(&mut world), Ok(()));
+        assert_eq!(world.get::<VoxelPos>(hut_entity), Some(&origin));
+
+        let mut schedule = Schedule::new();
+        schedule.add_system(progress_relocations);
+
+        // Without anyone to do the work, the structure stays put
+        schedule.run(&mut world);
+        assert_eq!(world.get::<VoxelPos>(hut_entity), Some(&origin));
+
+        let worker_entity = world.spawn_empty().id();
+        world
+            .get_mut::<WorkersPresent>(hut_entity)
+            .unwrap()
+            .add_worker(worker_entity)
+            .unwrap();
+
+        schedule.run(&mut world);
+        assert_eq!(world.get::<VoxelPos>(hut_entity), Some(&origin));
+
+        schedule.run(&mut world);
+        assert_eq!(world.get::<VoxelPos>(hut_entity), Some(&destination));
+        assert!(world.get::<PendingRelocation>(hut_entity).is_none());
+        // The hut had no workers of its own
+        assert!(world.get::<WorkersPresent>(hut_entity).is_none());
+
+        let storage_inventory = world.get::<StorageInventory>(hut_entity).unwrap();
+        assert_eq!(storage_inventory.item_count(grain()), 5);
+    }
+}
@@ -0,0 +1,272 @@
+//! Request posts are lightweight markers that ask for a fixed number of items to be brought to them.
+//!
+//! Unlike absorbers or crafting structures, they do nothing with the items they receive:
+//! the post simply pulls until its request is met, then falls silent.
+
+use bevy::prelude::*;
+
+use crate::{
+    crafting::{inventories::InputInventory, item_tags::ItemKind},
+    items::{inventory::Inventory, item_manifest::ItemManifest, slot::ItemSlot},
+    signals::{Emitter, LogisticsCategory, LogisticsPriorities, SignalType},
+    simulation::SimulationSet,
+    units::item_interaction::{count_in_flight_items, InFlightItems},
+};
+
+use super::StructureActivity;
+
+/// A structure that pulls in items until it holds `count` items matching `requested`.
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct RequestPost {
+    /// The kind of item requested.
+    pub(crate) requested: ItemKind,
+    /// The number of matching items wanted.
+    pub(crate) count: u32,
+}
+
+impl RequestPost {
+    /// Creates an empty buffer with room for the requested items.
+    ///
+    /// Requests for a single item get slots whose capacities add up to exactly `count`.
+    /// Requests for a tag are sized using the smallest stack of any matching item,
+    /// so they may have room for a few more items than were requested.
+    pub(crate) fn buffer(&self, item_manifest: &ItemManifest) -> InputInventory {
+        let inventory = match self.requested {
+            ItemKind::Single(item_id) => {
+                let stack_size = item_manifest.get(item_id).stack_size.max(1);
+                let partial_stack = self.count % stack_size;
+
+                (0..self.count / stack_size)
+                    .map(|_| ItemSlot::empty(item_id, stack_size))
+                    .chain((partial_stack > 0).then(|| ItemSlot::empty(item_id, partial_stack)))
+                    .collect()
+            }
+            ItemKind::Tag(tag) => {
+                let smallest_stack = item_manifest
+                    .variants()
+                    .filter(|&item_id| item_manifest.has_tag(item_id, tag))
+                    .map(|item_id| item_manifest.get(item_id).stack_size)
+                    .min()
+                    .unwrap_or(1)
+                    .max(1);
+
+                Inventory::new(
+                    ((self.count + smallest_stack - 1) / smallest_stack) as usize,
+                    None,
+                )
+            }
+        };
+
+        InputInventory::Filtered {
+            accepts: Some(self.requested),
+            refuses: None,
+            inventory,
+        }
+    }
+
+    /// The number of items still needed to satisfy this request, given the items already delivered.
+    pub(crate) fn remaining(
+        &self,
+        input_inventory: &InputInventory,
+        item_manifest: &ItemManifest,
+    ) -> u32 {
+        let delivered: u32 = input_inventory
+            .iter()
+            .filter(|item_slot| self.requested.matches(item_slot.item_id(), item_manifest))
+            .map(|item_slot| item_slot.count())
+            .sum();
+
+        self.count.saturating_sub(delivered)
+    }
+}
+
+/// Logic that controls how request posts ask for items.
+pub(super) struct RequestPostPlugin;
+
+impl Plugin for RequestPostPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system(
+            request_post_signals
+                // Changes to in-flight items are only reported for a single tick
+                .after(count_in_flight_items)
+                .in_set(SimulationSet)
+                .in_schedule(CoreSchedule::FixedUpdate),
+        );
+    }
+}
+
+/// Sets the emitters for request posts, which pull in the requested item until they are satisfied.
+///
+/// Items that are already being carried to a request post count towards its request.
+fn request_post_signals(
+    mut request_post_query: Query<(
+        Entity,
+        &mut Emitter,
+        &RequestPost,
+        &InputInventory,
+        Option<&StructureActivity>,
+    )>,
+    item_manifest: Res<ItemManifest>,
+    priorities: Res<LogisticsPriorities>,
+    in_flight_items: Res<InFlightItems>,
+) {
+    /// Controls how strong the signal is for request posts.
+    const REQUEST_POST_SIGNAL_STRENGTH: f32 = 5.;
+
+    let signal_strength =
+        priorities.signal_strength(LogisticsCategory::Logistics, REQUEST_POST_SIGNAL_STRENGTH);
+
+    for (entity, mut emitter, request_post, input_inventory, maybe_activity) in
+        request_post_query.iter_mut()
+    {
+        emitter.signals.clear();
+        if StructureActivity::is_paused(maybe_activity) {
+            continue;
+        }
+
+        let remaining = request_post.remaining(input_inventory, &item_manifest);
+        let in_flight =
+            in_flight_items.claimed_kind(entity, request_post.requested, &item_manifest);
+
+        if in_flight < remaining && !input_inventory.inventory().is_full() {
+            emitter
+                .signals
+                .push((SignalType::Pull(request_post.requested), signal_strength));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        asset_management::manifest::{Id, Manifest},
+        geometry::VoxelPos,
+        items::{
+            item_manifest::{Item, ItemData},
+            ItemCount,
+        },
+        units::{goals::Goal, item_interaction::UnitInventory},
+    };
+
+    fn wood() -> Id<Item> {
+        Id::from_name("wood".to_string())
+    }
+
+    fn item_manifest() -> ItemManifest {
+        let mut manifest = Manifest::new();
+        manifest.insert("wood".to_string(), ItemData::basic(10));
+        manifest
+    }
+
+    fn is_pulling(app: &App, request_post_entity: Entity) -> bool {
+        app.world
+            .get::<Emitter>(request_post_entity)
+            .unwrap()
+            .signals
+            .iter()
+            .any(|(signal_type, _)| *signal_type == SignalType::Pull(ItemKind::Single(wood())))
+    }
+
+    fn deliver_wood(app: &mut App, request_post_entity: Entity, count: u32) {
+        app.world
+            .get_mut::<InputInventory>(request_post_entity)
+            .unwrap()
+            .fill_with_items(&ItemCount::new(wood(), count), &item_manifest())
+            .unwrap();
+    }
+
+    #[test]
+    fn request_posts_pull_until_satisfied() {
+        let mut app = App::new();
+        let request_post = RequestPost {
+            requested: ItemKind::Single(wood()),
+            count: 5,
+        };
+
+        let request_post_entity = app
+            .world
+            .spawn((
+                request_post,
+                request_post.buffer(&item_manifest()),
+                Emitter::default(),
+            ))
+            .id();
+
+        app.insert_resource(item_manifest())
+            .init_resource::<LogisticsPriorities>()
+            .init_resource::<InFlightItems>()
+            .add_system(request_post_signals);
+
+        app.update();
+        assert!(is_pulling(&app, request_post_entity));
+
+        deliver_wood(&mut app, request_post_entity, 3);
+        app.update();
+        assert!(is_pulling(&app, request_post_entity));
+
+        deliver_wood(&mut app, request_post_entity, 2);
+        app.update();
+        assert!(!is_pulling(&app, request_post_entity));
+
+        let input_inventory = app
+            .world
+            .get::<InputInventory>(request_post_entity)
+            .unwrap();
+        assert_eq!(input_inventory.inventory().item_count(wood()), 5);
+    }
+
+    #[test]
+    fn buffers_are_sized_to_the_request() {
+        let item_manifest = item_manifest();
+        let request_post = RequestPost {
+            requested: ItemKind::Single(wood()),
+            count: 25,
+        };
+
+        let buffer = request_post.buffer(&item_manifest);
+        assert_eq!(buffer.inventory().max_slot_count(), 3);
+        assert_eq!(buffer.remaining_space_for_item(wood(), &item_manifest), 25);
+    }
+
+    #[test]
+    fn items_in_flight_count_towards_the_request() {
+        let mut app = App::new();
+        let request_post = RequestPost {
+            requested: ItemKind::Single(wood()),
+            count: 5,
+        };
+
+        let request_post_entity = app
+            .world
+            .spawn((
+                request_post,
+                request_post.buffer(&item_manifest()),
+                Emitter::default(),
+                VoxelPos::from_xy(0, 0),
+            ))
+            .id();
+        let hauler_entity = app
+            .world
+            .spawn((
+                VoxelPos::from_xy(3, 0),
+                Goal::Deliver(ItemKind::Single(wood())),
+                UnitInventory::holding(ItemCount::new(wood(), 5)),
+            ))
+            .id();
+
+        app.insert_resource(item_manifest())
+            .init_resource::<LogisticsPriorities>()
+            .init_resource::<InFlightItems>()
+            .add_systems((count_in_flight_items, request_post_signals).chain());
+
+        // The hauler is already bringing everything that was asked for
+        app.update();
+        assert!(!is_pulling(&app, request_post_entity));
+
+        // Once the hauler gives up, the request post asks for wood again
+        app.world.despawn(hauler_entity);
+        app.update();
+        assert!(is_pulling(&app, request_post_entity));
+    }
+}
@@ -0,0 +1,423 @@
+//! Adjacent storage structures of the same type can merge into a single logical warehouse.
+//!
+//! Members of a [`StorageGroup`] share their capacity:
+//! logistics treats the group as one target, and deliveries that do not fit in one member overflow into the next.
+
+use bevy::{
+    prelude::*,
+    utils::{HashMap, HashSet},
+};
+
+use crate::{
+    asset_management::manifest::Id,
+    crafting::inventories::StorageInventory,
+    geometry::{Facing, MapGeometry, VoxelPos},
+    items::item_manifest::{Item, ItemManifest},
+    simulation::SimulationSet,
+};
+
+use super::{structure_manifest::Structure, Footprint};
+
+/// Marks storage structures that merge with adjacent copies of themselves into a [`StorageGroup`].
+#[derive(Component, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub(crate) struct MergesWithNeighbors;
+
+/// A set of adjacent storage structures that act as a single logical warehouse.
+///
+/// Every member is the same type of structure, and reserves its inventory for the same item (if any).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct StorageGroup {
+    /// The storage structures in this group, sorted to give a stable overflow order.
+    members: Vec<Entity>,
+}
+
+impl StorageGroup {
+    /// The storage structures in this group.
+    pub(crate) fn members(&self) -> &[Entity] {
+        &self.members
+    }
+}
+
+/// The [`StorageGroup`]s that have currently been formed.
+#[derive(Resource, Debug, Default, Clone, PartialEq, Eq)]
+pub(crate) struct StorageGroups {
+    /// Each group with at least two members.
+    groups: Vec<StorageGroup>,
+    /// The index of the group that each storage structure belongs to.
+    membership: HashMap<Entity, usize>,
+    /// The item that each merging storage structure was reserved for when the groups were formed.
+    ///
+    /// Groups only need to be re-formed when storages are added, removed or change their reservation.
+    reservations: HashMap<Entity, Option<Id<Item>>>,
+}
+
+impl StorageGroups {
+    /// The group that `storage_entity` belongs to, if any.
+    pub(crate) fn group_of(&self, storage_entity: Entity) -> Option<&StorageGroup> {
+        let &index = self.membership.get(&storage_entity)?;
+        self.groups.get(index)
+    }
+
+    /// The storage structures that items delivered to or withdrawn from `storage_entity` should be moved through, in order.
+    ///
+    /// This always starts with `storage_entity` itself, followed by the rest of its group.
+    pub(crate) fn deposit_order(&self, storage_entity: Entity) -> Vec<Entity> {
+        let mut order = vec![storage_entity];
+        if let Some(group) = self.group_of(storage_entity) {
+            order.extend(
+                group
+                    .members()
+                    .iter()
+                    .copied()
+                    .filter(|&member| member != storage_entity),
+            );
+        }
+
+        order
+    }
+
+    /// The number of `item_id` that can still be added to `storage_entity` and the rest of its group.
+    pub(crate) fn remaining_space_for_item<'a>(
+        &self,
+        storage_entity: Entity,
+        item_id: Id<Item>,
+        item_manifest: &ItemManifest,
+        storage_inventory: impl Fn(Entity) -> Option<&'a StorageInventory>,
+    ) -> u32 {
+        self.deposit_order(storage_entity)
+            .into_iter()
+            .filter_map(storage_inventory)
+            .map(|inventory| inventory.remaining_space_for_item(item_id, item_manifest))
+            .sum()
+    }
+}
+
+#[cfg(test)]
+impl StorageGroups {
+    /// Forms a single group out of `members`, wherever they are.
+    pub(crate) fn single_group(mut members: Vec<Entity>) -> Self {
+        members.sort();
+        StorageGroups {
+            membership: members.iter().map(|&member| (member, 0)).collect(),
+            groups: vec![StorageGroup { members }],
+            reservations: HashMap::default(),
+        }
+    }
+}
+
+/// Forms and dissolves [`StorageGroup`]s as storage structures are built and removed.
+pub(super) struct StorageGroupsPlugin;
+
+impl Plugin for StorageGroupsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<StorageGroups>().add_system(
+            update_storage_groups
+                .in_set(SimulationSet)
+                .in_schedule(CoreSchedule::FixedUpdate),
+        );
+    }
+}
+
+/// Groups together adjacent storage structures that merge with their neighbors.
+///
+/// Storages only merge with copies of the same structure that share their item reservation,
+/// so every member of a group accepts exactly the same items.
+/// Groups are only re-formed when a merging storage is added, moved, removed or changes its reservation.
+fn update_storage_groups(
+    storage_query: Query<
+        (
+            Entity,
+            &Id<Structure>,
+            &VoxelPos,
+            &Facing,
+            &Footprint,
+            &StorageInventory,
+        ),
+        With<MergesWithNeighbors>,
+    >,
+    changed_query: Query<
+        (Entity, &StorageInventory),
+        (With<MergesWithNeighbors>, Changed<StorageInventory>),
+    >,
+    moved_query: Query<
+        (),
+        (
+            With<MergesWithNeighbors>,
+            Or<(Changed<VoxelPos>, Changed<Facing>)>,
+        ),
+    >,
+    mut removed_storages: RemovedComponents<MergesWithNeighbors>,
+    map_geometry: Res<MapGeometry>,
+    mut storage_groups: ResMut<StorageGroups>,
+) {
+    // Newly added storages have no recorded reservation yet
+    let reservation_changed = changed_query.iter().any(|(entity, storage_inventory)| {
+        storage_groups.reservations.get(&entity) != Some(&storage_inventory.reserved_for())
+    });
+    // Relocated storages may have left their old neighbors behind
+    let storage_moved = !moved_query.is_empty();
+    let storage_removed = removed_storages.iter().count() > 0;
+    if !reservation_changed && !storage_moved && !storage_removed {
+        return;
+    }
+
+    let mut storage_entities: Vec<Entity> =
+        storage_query.iter().map(|(entity, ..)| entity).collect();
+    storage_entities.sort();
+
+    let mut new_groups = StorageGroups {
+        reservations: storage_query
+            .iter()
+            .map(|(entity, .., storage_inventory)| (entity, storage_inventory.reserved_for()))
+            .collect(),
+        ..Default::default()
+    };
+    let mut visited: HashSet<Entity> = HashSet::new();
+
+    for starting_entity in storage_entities {
+        if !visited.insert(starting_entity) {
+            continue;
+        }
+
+        let (_, &structure_id, .., starting_inventory) =
+            storage_query.get(starting_entity).unwrap();
+        let reserved_for = starting_inventory.reserved_for();
+
+        let mut members = vec![starting_entity];
+        let mut frontier = vec![starting_entity];
+        while let Some(current_entity) = frontier.pop() {
+            let (_, _, &center, &facing, footprint, _) = storage_query.get(current_entity).unwrap();
+
+            for voxel_pos in footprint.normalized(facing, center) {
+                for neighbor in voxel_pos.all_neighbors() {
+                    let Some(neighbor_entity) = map_geometry.get_structure(neighbor) else { continue };
+                    if visited.contains(&neighbor_entity) {
+                        continue;
+                    }
+
+                    let Ok((_, &neighbor_id, .., neighbor_inventory)) =
+                        storage_query.get(neighbor_entity)
+                    else { continue };
+                    if neighbor_id == structure_id
+                        && neighbor_inventory.reserved_for() == reserved_for
+                    {
+                        visited.insert(neighbor_entity);
+                        members.push(neighbor_entity);
+                        frontier.push(neighbor_entity);
+                    }
+                }
+            }
+        }
+
+        // A lone storage is not a group
+        if members.len() < 2 {
+            continue;
+        }
+
+        members.sort();
+        let index = new_groups.groups.len();
+        for &member in &members {
+            new_groups.membership.insert(member, index);
+        }
+        new_groups.groups.push(StorageGroup { members });
+    }
+
+    storage_groups.set_if_neq(new_groups);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::items::{item_manifest::ItemData, ItemCount};
+    use hexx::Hex;
+
+    fn item_manifest() -> ItemManifest {
+        let mut item_manifest = ItemManifest::new();
+        item_manifest.insert("acacia_seed".to_string(), ItemData::basic(5));
+        item_manifest
+    }
+
+    fn seed() -> Id<Item> {
+        Id::from_name("acacia_seed".to_string())
+    }
+
+    /// Spawns a bin for seeds that merges with its neighbors, on top of the terrain at `hex`.
+    fn spawn_bin(app: &mut App, map_geometry: &mut MapGeometry, hex: Hex) -> Entity {
+        let center = map_geometry.on_top_of_terrain(hex);
+        let entity = app
+            .world
+            .spawn((
+                Id::<Structure>::from_name("bin".to_string()),
+                center,
+                Facing::default(),
+                Footprint::single(),
+                StorageInventory::new(1, Some(seed())),
+                MergesWithNeighbors,
+            ))
+            .id();
+        map_geometry
+            .add_structure(
+                center,
+                Facing::default(),
+                &Footprint::single(),
+                false,
+                false,
+                entity,
+            )
+            .unwrap();
+        entity
+    }
+
+    #[test]
+    fn adjacent_storages_share_capacity_and_overflow() {
+        let mut app = App::new();
+        let mut map_geometry = MapGeometry::new(&mut app.world, 3);
+        let item_manifest = item_manifest();
+
+        // Three bins in a row, and one isolated bin that should be left alone
+        let row: Vec<Entity> = [Hex::new(0, 0), Hex::new(1, 0), Hex::new(2, 0)]
+            .into_iter()
+            .map(|hex| spawn_bin(&mut app, &mut map_geometry, hex))
+            .collect();
+        let isolated = spawn_bin(&mut app, &mut map_geometry, Hex::new(-3, 0));
+
+        app.insert_resource(map_geometry)
+            .init_resource::<StorageGroups>()
+            .add_system(update_storage_groups);
+        app.update();
+
+        let storage_groups = app.world.resource::<StorageGroups>().clone();
+        assert!(storage_groups.group_of(isolated).is_none());
+        let mut expected_members = row.clone();
+        expected_members.sort();
+        assert_eq!(
+            storage_groups.group_of(row[0]).unwrap().members(),
+            &expected_members[..]
+        );
+
+        // The group reports the combined capacity of all three bins
+        let stored_count = |app: &App| -> u32 {
+            row.iter()
+                .map(|&entity| {
+                    app.world
+                        .get::<StorageInventory>(entity)
+                        .unwrap()
+                        .item_count(seed())
+                })
+                .sum()
+        };
+        let remaining_space =
+            storage_groups.remaining_space_for_item(row[1], seed(), &item_manifest, |entity| {
+                app.world.get::<StorageInventory>(entity)
+            });
+        assert_eq!(remaining_space + stored_count(&app), 15);
+
+        // Seven seeds overflow from the first bin into the next one in the group
+        let mut remaining = ItemCount::new(seed(), 7);
+        for member in storage_groups.deposit_order(row[0]) {
+            let mut storage_inventory = app.world.get_mut::<StorageInventory>(member).unwrap();
+            let space = storage_inventory.remaining_space_for_item(seed(), &item_manifest);
+            let added = remaining.count.min(space);
+            storage_inventory
+                .add_item_all_or_nothing(&ItemCount::new(seed(), added), &item_manifest)
+                .unwrap();
+            remaining.count -= added;
+        }
+
+        assert_eq!(remaining.count, 0);
+        assert_eq!(stored_count(&app), 7);
+        assert_eq!(
+            app.world
+                .get::<StorageInventory>(row[0])
+                .unwrap()
+                .item_count(seed()),
+            5
+        );
+        let remaining_space =
+            storage_groups.remaining_space_for_item(row[2], seed(), &item_manifest, |entity| {
+                app.world.get::<StorageInventory>(entity)
+            });
+        assert_eq!(remaining_space, 8);
+        assert_eq!(remaining_space + stored_count(&app), 15);
+
+        // Storing items does not re-form the groups
+        app.world.insert_resource(StorageGroups {
+            reservations: storage_groups.reservations.clone(),
+            ..Default::default()
+        });
+        app.world
+            .get_mut::<StorageInventory>(row[1])
+            .unwrap()
+            .add_item_all_or_nothing(&ItemCount::new(seed(), 1), &item_manifest)
+            .unwrap();
+        app.update();
+        assert!(app
+            .world
+            .resource::<StorageGroups>()
+            .group_of(row[0])
+            .is_none());
+
+        // Removing a storage does
+        app.world.despawn(isolated);
+        app.update();
+        assert_eq!(
+            app.world
+                .resource::<StorageGroups>()
+                .group_of(row[0])
+                .unwrap()
+                .members(),
+            &expected_members[..]
+        );
+    }
+
+    #[test]
+    fn moved_storages_leave_their_group() {
+        let mut app = App::new();
+        let mut map_geometry = MapGeometry::new(&mut app.world, 3);
+        let row: Vec<Entity> = [Hex::new(0, 0), Hex::new(1, 0), Hex::new(2, 0)]
+            .into_iter()
+            .map(|hex| spawn_bin(&mut app, &mut map_geometry, hex))
+            .collect();
+
+        app.insert_resource(map_geometry)
+            .init_resource::<StorageGroups>()
+            .add_system(update_storage_groups);
+        app.update();
+        assert_eq!(
+            app.world
+                .resource::<StorageGroups>()
+                .group_of(row[0])
+                .unwrap()
+                .members()
+                .len(),
+            3
+        );
+
+        // Move the last bin away from the rest of the row, without changing its inventory
+        let mut map_geometry = app.world.resource_mut::<MapGeometry>();
+        let destination = map_geometry.on_top_of_terrain(Hex::new(2, -2));
+        map_geometry.remove_structure_entity(row[2]);
+        map_geometry
+            .add_structure(
+                destination,
+                Facing::default(),
+                &Footprint::single(),
+                false,
+                false,
+                row[2],
+            )
+            .unwrap();
+        app.world.entity_mut(row[2]).insert(destination);
+        app.update();
+
+        let storage_groups = app.world.resource::<StorageGroups>();
+        let mut expected_members = [row[0], row[1]];
+        expected_members.sort();
+        assert_eq!(
+            storage_groups.group_of(row[0]).unwrap().members(),
+            &expected_members[..]
+        );
+        assert!(storage_groups.group_of(row[2]).is_none());
+        assert_eq!(storage_groups.deposit_order(row[1]).len(), 2);
+    }
+}
@@ -1,27 +1,62 @@
 //! Defines write-only data for each variety of structure.
 
 use crate::{
-    asset_management::manifest::{loader::IsRawManifest, Id, Manifest},
+    asset_management::manifest::{
+        loader::{serialize_sorted, IsRawManifest, ManifestError},
+        Id, Manifest,
+    },
     construction::{ConstructionData, ConstructionStrategy, RawConstructionStrategy},
-    crafting::recipe::{ActiveRecipe, RawActiveRecipe},
-    items::item_manifest::Item,
+    crafting::{
+        item_tags::{ItemKind, ItemTag, RawItemKind},
+        recipe::{ActiveRecipe, RawActiveRecipe, RecipeManifest},
+    },
+    geometry::{Facing, Height, MapGeometry, VoxelPos},
+    items::{
+        errors::AddManyItemsError,
+        inventory::Inventory,
+        item_manifest::{Item, ItemManifest},
+        ItemCount,
+    },
     organisms::{
         vegetative_reproduction::{RawVegetativeReproduction, VegetativeReproduction},
-        OrganismId, OrganismVariety, RawOrganismVariety,
+        OrganismId, OrganismVariety, RawOrganismId, RawOrganismVariety, SeasonalMultipliers,
     },
-    water::roots::RootZone,
+    simulation::{
+        milestones::{Milestone, Milestones},
+        time::Season,
+    },
+    units::unit_manifest::UnitManifest,
+    water::{roots::RootZone, WaterDepth},
 };
 use bevy::{
+    prelude::{Component, Entity},
     reflect::{FromReflect, Reflect, TypeUuid},
-    utils::HashMap,
+    utils::{HashMap, HashSet},
 };
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Deserializer, Serialize};
 
-use super::Footprint;
+use super::{Footprint, RawFootprint};
 
 /// The marker type for [`Id<Structure>`](super::Id).
 #[derive(Reflect, FromReflect, Clone, Copy, PartialEq, Eq)]
 pub struct Structure;
+/// The marker type for [`Id<StructureTag>`](super::Id).
+#[derive(Reflect, FromReflect, Clone, Copy, PartialEq, Eq)]
+pub struct StructureTag;
+
+/// Arbitrary labels attached to a structure by its manifest entry, such as "flammable" or "sacred".
+///
+/// The tags have no built-in meaning: they exist so that systems and other data can single out groups of structures.
+#[derive(Component, Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StructureTags(pub HashSet<Id<StructureTag>>);
+
+impl StructureTags {
+    /// Does this structure have the provided `tag`?
+    pub fn contains(&self, tag: Id<StructureTag>) -> bool {
+        self.0.contains(&tag)
+    }
+}
+
 /// Stores the read-only definitions for all structures.
 pub type StructureManifest = Manifest<Structure, StructureData>;
 
@@ -35,19 +70,143 @@ impl StructureManifest {
         match initial_strategy {
             ConstructionStrategy::Seedling(seedling_id) => self.construction_data(*seedling_id),
             ConstructionStrategy::Direct(data) => Some(data),
+            ConstructionStrategy::Harvest { required, .. } => Some(required),
             ConstructionStrategy::Landmark { .. } => None,
         }
     }
 
+    /// Fetches the radius within which a given structure type gathers its construction materials from the litter.
+    ///
+    /// If the structure uses a seedling, this will recursively fetch the radius for the seedling.
+    /// If the structure's materials are delivered by haulers instead, this will return [`None`].
+    pub fn harvest_radius(&self, structure_id: Id<Structure>) -> Option<u32> {
+        match self.get(structure_id).construction_strategy {
+            ConstructionStrategy::Seedling(seedling_id) => self.harvest_radius(seedling_id),
+            ConstructionStrategy::Harvest { radius, .. } => Some(radius),
+            ConstructionStrategy::Direct(..) | ConstructionStrategy::Landmark => None,
+        }
+    }
+
     /// Fetches the [`Footprint`] for the initial form of a given structure type.
     pub fn footprint(&self, structure_id: Id<Structure>) -> &Footprint {
         let strategy = &self.get(structure_id).construction_strategy;
         match strategy {
             ConstructionStrategy::Seedling(seedling_id) => self.footprint(*seedling_id),
-            ConstructionStrategy::Direct(..) | ConstructionStrategy::Landmark => {
-                &self.get(structure_id).footprint
+            ConstructionStrategy::Direct(..)
+            | ConstructionStrategy::Harvest { .. }
+            | ConstructionStrategy::Landmark => &self.get(structure_id).footprint,
+        }
+    }
+
+    /// Fetches the [`SeasonalMultipliers`] that apply to a given structure type during the provided `season`.
+    ///
+    /// Structures that are not organisms are unaffected by the seasons.
+    pub fn seasonal_multipliers(
+        &self,
+        structure_id: Id<Structure>,
+        season: Season,
+    ) -> SeasonalMultipliers {
+        match &self.get(structure_id).organism_variety {
+            Some(organism_variety) => organism_variety.seasonal_modifiers.get(season),
+            None => SeasonalMultipliers::NEUTRAL,
+        }
+    }
+
+    /// Is the water on every tile of the footprint of `structure_id` within its [`MoistureRange`]?
+    ///
+    /// The [`WaterDepth`] of each terrain entity is fetched using `water_depth`;
+    /// tiles without any water information are treated as [`WaterDepth::Dry`].
+    /// Structures without an [`allowed_moisture`](StructureData::allowed_moisture) can be placed anywhere.
+    pub(crate) fn moisture_permits(
+        &self,
+        structure_id: Id<Structure>,
+        center: VoxelPos,
+        facing: Facing,
+        map_geometry: &MapGeometry,
+        water_depth: impl Fn(Entity) -> Option<WaterDepth>,
+    ) -> bool {
+        let allowed_moisture = match self.get(structure_id).allowed_moisture {
+            Some(allowed_moisture) => allowed_moisture,
+            None => return true,
+        };
+
+        self.footprint(structure_id)
+            .normalized(facing, center)
+            .into_iter()
+            .all(|voxel_pos| {
+                let water_depth = map_geometry
+                    .get_terrain(voxel_pos.hex)
+                    .ok()
+                    .and_then(&water_depth)
+                    .unwrap_or_default();
+
+                allowed_moisture.permits(&water_depth)
+            })
+    }
+}
+
+/// The range of water depths that a structure can be built in.
+///
+/// All bounds are inclusive.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct MoistureRange {
+    /// The shallowest allowable surface water.
+    pub min_depth: Height,
+    /// The deepest allowable surface water.
+    pub max_depth: Height,
+    /// The furthest below the soil surface that the water table can be, for structures that need moist soil.
+    ///
+    /// Flooded tiles always have moist soil, while dry tiles never do.
+    /// If this is [`None`], the soil can be as dry as it likes.
+    #[serde(default)]
+    pub max_water_table_depth: Option<Height>,
+}
+
+impl MoistureRange {
+    /// Can a structure with this range be built on a tile with the provided `water_depth`?
+    pub fn permits(&self, water_depth: &WaterDepth) -> bool {
+        let depth = water_depth.surface_water_depth();
+        let soil_is_moist = match (self.max_water_table_depth, water_depth) {
+            (None, _) | (Some(_), WaterDepth::Flooded(..)) => true,
+            (Some(_), WaterDepth::Dry) => false,
+            (Some(max_water_table_depth), WaterDepth::Underground(water_table_depth)) => {
+                *water_table_depth <= max_water_table_depth
             }
+        };
+
+        self.min_depth <= depth && depth <= self.max_depth && soil_is_moist
+    }
+
+    /// Checks that every bound is a finite, non-negative height, and that the range is not empty.
+    fn validate(&self) -> Result<(), String> {
+        let bounds = [
+            Some(self.min_depth),
+            Some(self.max_depth),
+            self.max_water_table_depth,
+        ];
+        if let Some(Height(bound)) = bounds
+            .into_iter()
+            .flatten()
+            .find(|&Height(bound)| !bound.is_finite() || bound < 0.)
+        {
+            return Err(format!(
+                "moisture bounds must be finite and non-negative, but found {bound}"
+            ));
+        }
+
+        if self.min_depth > self.max_depth {
+            return Err(format!(
+                "the minimum water depth ({}) is deeper than the maximum ({})",
+                self.min_depth, self.max_depth
+            ));
         }
+
+        Ok(())
+    }
+
+    /// Is the surface water at a tile with the provided `water_depth` deeper than this range allows?
+    pub fn too_deep(&self, water_depth: &WaterDepth) -> bool {
+        water_depth.surface_water_depth() > self.max_depth
     }
 }
 
@@ -74,6 +233,24 @@ pub struct StructureData {
     pub can_walk_through: bool,
     /// Can units walk on top of this structure?
     pub can_walk_on_roof: bool,
+    /// The maximum number of units that can stand on or inside each tile of this structure at once.
+    ///
+    /// If this is [`None`], any number of units can share a tile.
+    pub max_occupants: Option<u8>,
+    /// The recipes that players can quickly cycle between on this structure.
+    ///
+    /// The first preset is used as the starting recipe.
+    pub recipe_presets: Vec<ActiveRecipe>,
+    /// The depths of surface water that this structure can be built in.
+    ///
+    /// If this is [`None`], the structure can be built regardless of moisture.
+    pub allowed_moisture: Option<MoistureRange>,
+    /// The tags given to each copy of this structure.
+    pub tags: StructureTags,
+    /// The milestone that must be completed before this structure can be built.
+    ///
+    /// If this is [`None`], the structure is available from the start.
+    pub required_milestone: Option<Id<Milestone>>,
 }
 
 #[cfg(test)]
@@ -90,6 +267,11 @@ impl StructureData {
             root_zone: None,
             can_walk_through: true,
             can_walk_on_roof: false,
+            max_occupants: None,
+            recipe_presets: Vec::new(),
+            allowed_moisture: None,
+            tags: StructureTags::default(),
+            required_milestone: None,
         }
     }
 
@@ -105,6 +287,11 @@ impl StructureData {
             root_zone: None,
             can_walk_through: true,
             can_walk_on_roof: false,
+            max_occupants: None,
+            recipe_presets: Vec::new(),
+            allowed_moisture: None,
+            tags: StructureTags::default(),
+            required_milestone: None,
         }
     }
 
@@ -120,6 +307,11 @@ impl StructureData {
             root_zone: None,
             can_walk_through: false,
             can_walk_on_roof: false,
+            max_occupants: None,
+            recipe_presets: Vec::new(),
+            allowed_moisture: None,
+            tags: StructureTags::default(),
+            required_milestone: None,
         }
     }
 }
@@ -132,6 +324,7 @@ pub struct RawStructureData {
     /// What base variety of structure is this?
     ///
     /// Determines the components that this structure gets.
+    #[serde(deserialize_with = "deserialize_raw_structure_kind")]
     pub kind: RawStructureKind,
     /// How new copies of this structure can be built
     pub construction_strategy: RawConstructionStrategy,
@@ -140,33 +333,99 @@ pub struct RawStructureData {
     /// The maximum number of workers that can work at this structure at once.
     pub max_workers: u8,
     /// The tiles taken up by this building.
-    pub footprint: Option<Footprint>,
+    pub footprint: Option<RawFootprint>,
     /// The set of tiles that this structure can reach with its roots.
     pub root_zone: Option<RootZone>,
     /// Can units pass through the voxels occupied by this tile?
     pub can_walk_through: bool,
     /// Can units walk on top of this structure?
     pub can_walk_on_roof: bool,
+    /// The maximum number of units that can stand on or inside each tile of this structure at once.
+    #[serde(default)]
+    pub max_occupants: Option<u8>,
+    /// The recipes that players can quickly cycle between on this structure.
+    #[serde(default)]
+    pub recipe_presets: Vec<RawActiveRecipe>,
+    /// The depths of surface water that this structure can be built in.
+    #[serde(default)]
+    pub allowed_moisture: Option<MoistureRange>,
+    /// The names of the tags given to each copy of this structure.
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// The name of the milestone that must be completed before this structure can be built.
+    #[serde(default)]
+    pub required_milestone: Option<String>,
+}
+
+#[cfg(test)]
+impl RawStructureData {
+    /// A landmark that can be walked through, with no other properties.
+    ///
+    /// Tests override the properties that they care about using struct update syntax.
+    pub fn passable() -> Self {
+        RawStructureData {
+            organism_variety: None,
+            kind: RawStructureKind::Path,
+            construction_strategy: RawConstructionStrategy::Landmark,
+            vegetative_reproduction: None,
+            max_workers: 1,
+            footprint: None,
+            root_zone: None,
+            can_walk_through: true,
+            can_walk_on_roof: false,
+            max_occupants: None,
+            recipe_presets: Vec::new(),
+            allowed_moisture: None,
+            tags: Vec::new(),
+            required_milestone: None,
+        }
+    }
 }
 
 impl From<RawStructureData> for StructureData {
     fn from(raw: RawStructureData) -> Self {
+        let recipe_presets: Vec<ActiveRecipe> =
+            raw.recipe_presets.into_iter().map(Into::into).collect();
+
+        let mut kind: StructureKind = raw.kind.into();
+        // The first preset is used as the starting recipe
+        if let (
+            StructureKind::Crafting {
+                starting_recipe, ..
+            },
+            Some(first_preset),
+        ) = (&mut kind, recipe_presets.first())
+        {
+            *starting_recipe = first_preset.clone();
+        }
+
+        // Invalid shapes are rejected when the manifest is processed
+        let footprint = raw
+            .footprint
+            .and_then(|raw_footprint| raw_footprint.expand().ok())
+            .unwrap_or_else(|| Footprint::from_kind(&kind));
+
         Self {
             organism_variety: raw.organism_variety.map(Into::into),
-            kind: raw.kind.into(),
+            kind,
             construction_strategy: raw.construction_strategy.into(),
             vegetative_reproduction: raw.vegetative_reproduction.map(Into::into),
             max_workers: raw.max_workers,
-            footprint: raw.footprint.unwrap_or_default(),
+            footprint,
             root_zone: raw.root_zone,
             can_walk_through: raw.can_walk_through,
             can_walk_on_roof: raw.can_walk_on_roof,
+            max_occupants: raw.max_occupants,
+            recipe_presets,
+            allowed_moisture: raw.allowed_moisture,
+            tags: StructureTags(raw.tags.into_iter().map(Id::from_name).collect()),
+            required_milestone: raw.required_milestone.map(Id::from_name),
         }
     }
 }
 
 /// What set of components should this structure have?
-#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum StructureKind {
     /// Stores items.
     Storage {
@@ -174,24 +433,94 @@ pub enum StructureKind {
         max_slot_count: usize,
         /// Is any item allowed here, or just one?
         reserved_for: Option<Id<Item>>,
+        /// The items stored here when the structure is placed by world generation.
+        ///
+        /// Copies built by the player always start empty.
+        starting_inventory: Vec<ItemCount>,
+        /// Does this storage merge with adjacent copies of itself into a single [`StorageGroup`](super::storage_groups::StorageGroup)?
+        merge_adjacent: bool,
     },
     /// Crafts items, turning inputs into outputs.
     Crafting {
         /// Does this structure start with a recipe pre-selected?
         starting_recipe: ActiveRecipe,
+        /// The number of slots in the input inventory.
+        ///
+        /// Extra slots hold more of the recipe's inputs; the recipe's own needs are never cut short.
+        /// If this is [`None`], there is exactly one slot for each input of the recipe.
+        input_buffer_slots: Option<usize>,
+        /// The number of slots in the output inventory.
+        ///
+        /// Extra slots hold more of the recipe's outputs; the recipe's own needs are never cut short.
+        /// If this is [`None`], there is exactly one slot for each output of the recipe.
+        output_buffer_slots: Option<usize>,
     },
     /// A structure that can be walked over.
     Path,
     /// A structure that is used to define a special element of the world.
     Landmark,
     /// A structure that spits out items.
-    Releaser,
+    Releaser {
+        /// The number of items that must accumulate before they are released as a single batch.
+        ///
+        /// If this is [`None`], items are released as soon as they arrive.
+        release_threshold: Option<u32>,
+        /// The type of item that is released.
+        released_item: Id<Item>,
+    },
     /// A structure that takes in items.
-    Absorber,
+    Absorber {
+        /// Should full stacks be pushed into adjacent storage, so that absorption can continue?
+        overflow_to_adjacent: bool,
+    },
+    /// A structure that fills empty water containers with the surface water of the tile it stands on.
+    WellPump {
+        /// The filled water container produced.
+        ///
+        /// The empty containers consumed are set by its [`WaterContents`](crate::items::item_manifest::WaterContents).
+        filled_item: Id<Item>,
+    },
+    /// A structure that composts compostable items, enriching the soil around it.
+    Composter {
+        /// The composter enriches every tile within this many tiles of itself.
+        radius: u32,
+        /// The [`SoilFertility`](crate::terrain::fertility::SoilFertility) added to each enriched tile for every item composted.
+        rate: f32,
+    },
+    /// A structure that destroys the items delivered to it.
+    Incinerator {
+        /// The maximum number of items destroyed each fixed update.
+        throughput: u32,
+        /// The kind of item destroyed, or [`None`] if any item is accepted.
+        accepts: Option<ItemKind>,
+        /// Items with this tag are never accepted, even if they match `accepts`.
+        precious: Option<ItemTag>,
+    },
+    /// A structure where tired units can rest.
+    Nest {
+        /// The stamina restored to each resting unit every second.
+        rest_rate: f32,
+        /// The maximum number of units that can rest here at once.
+        capacity: u8,
+    },
+    /// A structure that asks for items to be brought to it, until enough have been delivered.
+    RequestPost {
+        /// The kind of item requested.
+        requested: ItemKind,
+        /// The number of matching items wanted.
+        count: u32,
+    },
+    /// A kind of structure defined outside of this enum, whose components are added by a registered [`StructureKindExtension`](super::custom_kinds::StructureKindExtension).
+    Custom {
+        /// The name that the kind was registered under in the [`StructureKindRegistry`](super::custom_kinds::StructureKindRegistry).
+        kind: String,
+        /// Free-form data passed to the kind's handler when the structure is spawned.
+        data: serde_json::Value,
+    },
 }
 
 /// The unprocessed equivalent of [`StructureKind`].
-#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum RawStructureKind {
     /// Stores items.
     Storage {
@@ -199,20 +528,126 @@ pub enum RawStructureKind {
         max_slot_count: usize,
         /// Is any item allowed here, or just one?
         reserved_for: Option<String>,
+        /// The name and number of each item stored here when the structure is placed by world generation.
+        #[serde(default)]
+        starting_inventory: Vec<(String, u32)>,
+        /// Does this storage merge with adjacent copies of itself into a single logical warehouse?
+        ///
+        /// If this is omitted, each copy is kept separate.
+        #[serde(default)]
+        merge_adjacent: bool,
     },
     /// Crafts items, turning inputs into outputs.
     Crafting {
         /// Does this structure start with a recipe pre-selected?
         starting_recipe: RawActiveRecipe,
+        /// The number of slots in the input inventory, allowing extra inputs to be stocked ahead of time.
+        ///
+        /// If this is omitted, there is exactly one slot for each input of the recipe.
+        #[serde(default)]
+        input_buffer_slots: Option<usize>,
+        /// The number of slots in the output inventory, allowing extra outputs to pile up before crafting stalls.
+        ///
+        /// If this is omitted, there is exactly one slot for each output of the recipe.
+        #[serde(default)]
+        output_buffer_slots: Option<usize>,
     },
     /// A structure that can be walked over.
     Path,
     /// A structure that is used to define a special element of the world.
     Landmark,
     /// A structure that spits out items.
-    Releaser,
+    Releaser {
+        /// The number of items that must accumulate before they are released as a single batch.
+        ///
+        /// If this is omitted, items are released as soon as they arrive.
+        #[serde(default)]
+        release_threshold: Option<u32>,
+        /// The name of the type of item that is released.
+        ///
+        /// If this is omitted, crab eggs are released.
+        #[serde(default)]
+        released_item: Option<String>,
+    },
     /// A structure that takes in items.
-    Absorber,
+    Absorber {
+        /// Should full stacks be pushed into adjacent storage, so that absorption can continue?
+        ///
+        /// If this is omitted, the absorber stops once its own inventory is full.
+        #[serde(default)]
+        overflow_to_adjacent: bool,
+    },
+    /// A structure that fills empty water containers with the surface water of the tile it stands on.
+    WellPump {
+        /// The name of the filled water container produced.
+        filled_item: String,
+    },
+    /// A structure that composts compostable items, enriching the soil around it.
+    Composter {
+        /// The composter enriches every tile within this many tiles of itself.
+        radius: u32,
+        /// The soil fertility added to each enriched tile for every item composted.
+        rate: f32,
+    },
+    /// A structure that destroys the items delivered to it.
+    Incinerator {
+        /// The maximum number of items destroyed each fixed update.
+        throughput: u32,
+        /// The kind of item destroyed.
+        ///
+        /// If this is omitted, any item is accepted.
+        #[serde(default)]
+        accepts: Option<RawItemKind>,
+        /// Items with this tag are never accepted, even if they match `accepts`.
+        ///
+        /// If this is omitted, nothing is protected.
+        #[serde(default)]
+        precious: Option<ItemTag>,
+    },
+    /// A structure where tired units can rest.
+    Nest {
+        /// The stamina restored to each resting unit every second.
+        rest_rate: f32,
+        /// The maximum number of units that can rest here at once.
+        capacity: u8,
+    },
+    /// A structure that asks for items to be brought to it, until enough have been delivered.
+    RequestPost {
+        /// The kind of item requested.
+        requested: RawItemKind,
+        /// The number of matching items wanted.
+        count: u32,
+    },
+    /// A kind of structure added by a plugin, rather than built into the game.
+    Custom {
+        /// The name that the kind was registered under.
+        kind: String,
+        /// Free-form data passed to the kind's handler when the structure is spawned.
+        ///
+        /// If this is omitted, the handler receives `null`.
+        #[serde(default)]
+        data: serde_json::Value,
+    },
+}
+
+/// Deserializes a [`RawStructureKind`], also accepting the bare names that were used before some kinds gained fields.
+///
+/// `"Releaser"` and `"Absorber"` are read as a releaser or absorber with every field left at its default.
+fn deserialize_raw_structure_kind<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<RawStructureKind, D::Error> {
+    let value = serde_json::Value::deserialize(deserializer)?;
+
+    match value.as_str() {
+        Some("Releaser") => Ok(RawStructureKind::Releaser {
+            release_threshold: None,
+            released_item: None,
+        }),
+        Some("Absorber") => Ok(RawStructureKind::Absorber {
+            overflow_to_adjacent: false,
+        }),
+        _ => RawStructureKind::deserialize(value).map_err(serde::de::Error::custom),
+    }
 }
 
 impl From<RawStructureKind> for StructureKind {
@@ -221,17 +656,79 @@ impl From<RawStructureKind> for StructureKind {
             RawStructureKind::Storage {
                 max_slot_count,
                 reserved_for,
-            } => Self::Storage {
-                max_slot_count,
-                reserved_for: reserved_for.map(Id::from_name),
-            },
-            RawStructureKind::Crafting { starting_recipe } => Self::Crafting {
+                starting_inventory,
+                merge_adjacent,
+            } => {
+                // Merge any repeated entries, so that each item appears only once
+                let mut merged_inventory: Vec<ItemCount> = Vec::new();
+                for (item_name, count) in starting_inventory {
+                    let item_id = Id::from_name(item_name);
+                    match merged_inventory
+                        .iter_mut()
+                        .find(|item_count| item_count.item_id == item_id)
+                    {
+                        Some(item_count) => item_count.count += count,
+                        None => merged_inventory.push(ItemCount::new(item_id, count)),
+                    }
+                }
+
+                Self::Storage {
+                    max_slot_count,
+                    reserved_for: reserved_for.map(Id::from_name),
+                    starting_inventory: merged_inventory,
+                    merge_adjacent,
+                }
+            }
+            RawStructureKind::Crafting {
+                starting_recipe,
+                input_buffer_slots,
+                output_buffer_slots,
+            } => Self::Crafting {
                 starting_recipe: starting_recipe.into(),
+                input_buffer_slots,
+                output_buffer_slots,
             },
             RawStructureKind::Path => Self::Path,
             RawStructureKind::Landmark => Self::Landmark,
-            RawStructureKind::Releaser => Self::Releaser,
-            RawStructureKind::Absorber => Self::Absorber,
+            RawStructureKind::Releaser {
+                release_threshold,
+                released_item,
+            } => Self::Releaser {
+                release_threshold,
+                released_item: Id::from_name(
+                    released_item.unwrap_or_else(|| "crab_egg".to_string()),
+                ),
+            },
+            RawStructureKind::Absorber {
+                overflow_to_adjacent,
+            } => Self::Absorber {
+                overflow_to_adjacent,
+            },
+            RawStructureKind::WellPump { filled_item } => Self::WellPump {
+                filled_item: Id::from_name(filled_item),
+            },
+            RawStructureKind::Composter { radius, rate } => Self::Composter { radius, rate },
+            RawStructureKind::Incinerator {
+                throughput,
+                accepts,
+                precious,
+            } => Self::Incinerator {
+                throughput,
+                accepts: accepts.map(Into::into),
+                precious,
+            },
+            RawStructureKind::Nest {
+                rest_rate,
+                capacity,
+            } => Self::Nest {
+                rest_rate,
+                capacity,
+            },
+            RawStructureKind::RequestPost { requested, count } => Self::RequestPost {
+                requested: requested.into(),
+                count,
+            },
+            RawStructureKind::Custom { kind, data } => Self::Custom { kind, data },
         }
     }
 }
@@ -241,19 +738,52 @@ impl StructureData {
     ///
     /// If no starting recipe is set, [`ActiveRecipe::NONE`] will be returned.
     pub fn starting_recipe(&self) -> &ActiveRecipe {
-        if let StructureKind::Crafting { starting_recipe } = &self.kind {
+        if let StructureKind::Crafting {
+            starting_recipe, ..
+        } = &self.kind
+        {
             starting_recipe
         } else {
             &ActiveRecipe::NONE
         }
     }
+
+    /// Returns the recipe preset that follows `current_recipe`, wrapping around at the end of the list.
+    ///
+    /// If `current_recipe` is not one of the presets, the first preset is returned.
+    /// Returns [`None`] if this structure has no recipe presets.
+    pub(crate) fn next_recipe_preset(
+        &self,
+        current_recipe: &ActiveRecipe,
+    ) -> Option<&ActiveRecipe> {
+        let next_index = match self
+            .recipe_presets
+            .iter()
+            .position(|preset| preset == current_recipe)
+        {
+            Some(index) => (index + 1) % self.recipe_presets.len(),
+            None => 0,
+        };
+
+        self.recipe_presets.get(next_index)
+    }
 }
 
 impl StructureManifest {
     /// Returns the list of [`Id<Structure>`] where [`StructureData`]'s `prototypical` field is `true`.
     ///
     /// These should be used to populate menus and other player-facing tools.
-    pub(crate) fn prototypes(&self) -> impl IntoIterator<Item = Id<Structure>> + '_ {
+    /// Structures whose required milestone has not yet been completed are excluded.
+    pub(crate) fn prototypes<'a>(
+        &'a self,
+        milestones: &'a Milestones,
+    ) -> impl IntoIterator<Item = Id<Structure>> + 'a {
+        self.all_prototypes()
+            .filter(|&id| milestones.is_unlocked(self.get(id).required_milestone))
+    }
+
+    /// Returns every prototypical [`Id<Structure>`], regardless of whether or not it has been unlocked.
+    fn all_prototypes(&self) -> impl Iterator<Item = Id<Structure>> + '_ {
         self.data_map()
             .iter()
             .filter(|(id, data)| match &data.organism_variety {
@@ -264,12 +794,363 @@ impl StructureManifest {
             .map(|(id, _v)| *id)
     }
 
+    /// Checks that the starting inventory of every storage structure refers to known items and fits in its storage.
+    ///
+    /// This cannot be done while processing the raw manifest, as it depends on the [`ItemManifest`].
+    pub(crate) fn validate_starting_inventories(
+        &self,
+        item_manifest: &ItemManifest,
+    ) -> Result<(), ManifestError> {
+        for (&structure_id, structure_data) in self.data_map() {
+            let (max_slot_count, reserved_for, starting_inventory) = match structure_data.kind {
+                StructureKind::Storage {
+                    max_slot_count,
+                    reserved_for,
+                    ref starting_inventory,
+                    ..
+                } => (max_slot_count, reserved_for, starting_inventory),
+                _ => continue,
+            };
+
+            for item_count in starting_inventory {
+                if !item_manifest.data_map().contains_key(&item_count.item_id) {
+                    return Err(ManifestError::UnknownReference {
+                        entry: self.name(structure_id).to_string(),
+                        field: "starting_inventory".to_string(),
+                        reference: item_count.item_id.to_string(),
+                    });
+                }
+            }
+
+            let mut inventory = Inventory::new(max_slot_count, reserved_for);
+            if let Err(AddManyItemsError { excess_counts }) =
+                inventory.add_items_all_or_nothing(starting_inventory, item_manifest)
+            {
+                return Err(ManifestError::InventoryOverflow {
+                    entry: self.name(structure_id).to_string(),
+                    excess: excess_counts
+                        .iter()
+                        .map(|item_count| item_count.count)
+                        .sum(),
+                });
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Checks that the items released by releasers and filled by well pumps are known items,
+    /// and that well pumps only fill items that hold water.
+    ///
+    /// This cannot be done while processing the raw manifest, as it depends on the [`ItemManifest`].
+    pub(crate) fn validate_logistic_items(
+        &self,
+        item_manifest: &ItemManifest,
+    ) -> Result<(), ManifestError> {
+        for (&structure_id, structure_data) in self.data_map() {
+            let name = self.name(structure_id);
+            let known_item = |field: &str, item_id: Id<Item>| {
+                item_manifest.data_map().get(&item_id).ok_or_else(|| {
+                    ManifestError::UnknownReference {
+                        entry: name.to_string(),
+                        field: field.to_string(),
+                        reference: item_id.to_string(),
+                    }
+                })
+            };
+
+            match structure_data.kind {
+                StructureKind::Releaser { released_item, .. } => {
+                    known_item("released_item", released_item)?;
+                }
+                StructureKind::WellPump { filled_item } => {
+                    if known_item("filled_item", filled_item)?
+                        .water_contents
+                        .is_none()
+                    {
+                        return Err(ManifestError::MalformedStructure {
+                            structure: name.to_string(),
+                            reason: format!(
+                                "well pumps can only fill items that hold water, but {} does not",
+                                item_manifest.name(filled_item)
+                            ),
+                        });
+                    }
+                }
+                _ => (),
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Checks that every recipe a structure can craft exists,
+    /// and that random recipe choices have valid weights and can share a single input inventory.
+    ///
+    /// This cannot be done while processing the raw manifest, as it depends on the [`RecipeManifest`].
+    pub(crate) fn validate_recipe_choices(
+        &self,
+        recipe_manifest: &RecipeManifest,
+    ) -> Result<(), ManifestError> {
+        /// Checks a single, possibly nested, recipe choice made by the structure named `structure`.
+        fn validate_choice(
+            structure: &str,
+            active_recipe: &ActiveRecipe,
+            recipe_manifest: &RecipeManifest,
+        ) -> Result<(), ManifestError> {
+            let malformed = |reason: String| ManifestError::MalformedStructure {
+                structure: structure.to_string(),
+                reason,
+            };
+
+            match active_recipe {
+                ActiveRecipe::Fixed(None) => (),
+                ActiveRecipe::Fixed(Some(recipe_id)) => {
+                    if !recipe_manifest.data_map().contains_key(recipe_id) {
+                        return Err(ManifestError::UnknownReference {
+                            entry: structure.to_string(),
+                            field: "recipe".to_string(),
+                            reference: recipe_id.to_string(),
+                        });
+                    }
+                }
+                ActiveRecipe::Random { choices, .. } => {
+                    if choices.is_empty() {
+                        return Err(malformed(
+                            "random recipes must have at least one choice".to_string(),
+                        ));
+                    }
+
+                    for (choice, weight) in choices {
+                        if !weight.is_finite() || *weight < 0. {
+                            return Err(malformed(format!(
+                                "recipe weights must be finite and non-negative, but found {weight}"
+                            )));
+                        }
+
+                        validate_choice(structure, choice, recipe_manifest)?;
+                    }
+                }
+            }
+
+            Ok(())
+        }
+
+        for (&structure_id, structure_data) in self.data_map() {
+            let name = self.name(structure_id);
+            let starting_recipe = match structure_data.kind {
+                StructureKind::Crafting {
+                    ref starting_recipe,
+                    ..
+                } => Some(starting_recipe),
+                _ => None,
+            };
+
+            for active_recipe in starting_recipe
+                .into_iter()
+                .chain(&structure_data.recipe_presets)
+            {
+                validate_choice(name, active_recipe, recipe_manifest)?;
+
+                if active_recipe.is_random()
+                    && !recipe_manifest.can_share_inputs(&active_recipe.possible_recipes())
+                {
+                    return Err(ManifestError::MalformedStructure {
+                        structure: name.to_string(),
+                        reason: "recipes chosen at random must either all accept the same flexible inputs, or have none".to_string(),
+                    });
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Checks that the items left behind by dead structure organisms, and salvaged from their remains, are known items.
+    ///
+    /// This cannot be done while processing the raw manifest, as it depends on the [`ItemManifest`].
+    pub(crate) fn validate_remains(
+        &self,
+        item_manifest: &ItemManifest,
+    ) -> Result<(), ManifestError> {
+        for (&structure_id, structure_data) in self.data_map() {
+            let Some(remains) = structure_data
+                .organism_variety
+                .as_ref()
+                .and_then(|organism_variety| organism_variety.remains.as_ref())
+            else { continue };
+
+            for item_count in remains.items.iter().chain(remains.salvage.iter()) {
+                if !item_manifest.data_map().contains_key(&item_count.item_id) {
+                    return Err(ManifestError::UnknownReference {
+                        entry: self.name(structure_id).to_string(),
+                        field: "remains".to_string(),
+                        reference: item_count.item_id.to_string(),
+                    });
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Checks that every pollination partner of a structure or unit refers to a known structure or unit.
+    ///
+    /// This cannot be done while processing the raw manifests, as partners can come from either manifest.
+    pub(crate) fn validate_pollination_partners(
+        &self,
+        unit_manifest: &UnitManifest,
+    ) -> Result<(), ManifestError> {
+        let structure_varieties = self.data_map().iter().filter_map(|(&structure_id, data)| {
+            data.organism_variety
+                .as_ref()
+                .map(|organism_variety| (self.name(structure_id), organism_variety))
+        });
+        let unit_varieties = unit_manifest
+            .data_map()
+            .iter()
+            .map(|(&unit_id, data)| (unit_manifest.name(unit_id), &data.organism_variety));
+
+        for (name, organism_variety) in structure_varieties.chain(unit_varieties) {
+            let Some(pollination) = &organism_variety.pollination else { continue };
+
+            for partner in &pollination.partners {
+                let known = match partner {
+                    OrganismId::Structure(structure_id) => {
+                        self.data_map().contains_key(structure_id)
+                    }
+                    OrganismId::Unit(unit_id) => unit_manifest.data_map().contains_key(unit_id),
+                };
+
+                if !known {
+                    return Err(ManifestError::UnknownReference {
+                        entry: name.to_string(),
+                        field: "pollination".to_string(),
+                        reference: partner.to_string(),
+                    });
+                }
+            }
+        }
+
+        Ok(())
+    }
+
     /// Returns the names of all structures where [`StructureData`]'s `prototypical` field is `true`.
     ///
     /// These should be used to populate menus and other player-facing tools.
     pub(crate) fn prototype_names(&self) -> impl IntoIterator<Item = &str> {
-        let prototypes = self.prototypes();
-        prototypes.into_iter().map(|id| self.name(id))
+        self.all_prototypes().map(|id| self.name(id))
+    }
+}
+
+/// How well a structure's name matches a [`StructureSearch`] query.
+///
+/// Higher scores are better matches.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub(crate) struct MatchScore(pub(crate) u32);
+
+impl MatchScore {
+    /// Awarded for each character of the query that is found in the name.
+    const PER_CHARACTER: u32 = 1;
+    /// Awarded when a character immediately follows the previously matched character.
+    const CONSECUTIVE: u32 = 2;
+    /// Awarded when a character matches the start of a word.
+    const WORD_START: u32 = 3;
+    /// Awarded when the whole query is a prefix of the name.
+    const PREFIX: u32 = 20;
+    /// Awarded when the whole query is exactly one of the words in the name.
+    const WHOLE_WORD: u32 = 15;
+
+    /// Scores how well `name` matches `query`, ignoring case.
+    ///
+    /// Every character of the query must appear in the name, in order, or [`None`] is returned.
+    /// Words are separated by underscores or spaces.
+    pub(crate) fn compute(query: &str, name: &str) -> Option<MatchScore> {
+        let query = query.to_lowercase();
+        let name = name.to_lowercase();
+        let is_separator = |c: char| c == '_' || c == ' ';
+
+        let mut score = 0;
+        let mut previous_match: Option<usize> = None;
+        let mut name_chars = name.chars().enumerate().peekable();
+        let mut previous_char: Option<char> = None;
+
+        for query_char in query.chars() {
+            loop {
+                let (index, name_char) = name_chars.next()?;
+                let at_word_start = previous_char.map_or(true, is_separator);
+                previous_char = Some(name_char);
+
+                if name_char == query_char {
+                    score += Self::PER_CHARACTER;
+                    if at_word_start {
+                        score += Self::WORD_START;
+                    }
+                    if previous_match.map_or(false, |previous| previous + 1 == index) {
+                        score += Self::CONSECUTIVE;
+                    }
+                    previous_match = Some(index);
+                    break;
+                }
+            }
+        }
+
+        if name.starts_with(&query) {
+            score += Self::PREFIX;
+        }
+
+        if name.split(is_separator).any(|word| word == query) {
+            score += Self::WHOLE_WORD;
+        }
+
+        Some(MatchScore(score))
+    }
+}
+
+/// Finds structures that players can build by name, for menus with too many structures to browse.
+///
+/// Only the same structures as [`StructureManifest::prototypes`] are ever returned.
+pub(crate) struct StructureSearch<'a> {
+    /// The manifest that names are looked up in.
+    structure_manifest: &'a StructureManifest,
+    /// The structures that can be found, sorted by name.
+    candidates: Vec<Id<Structure>>,
+}
+
+impl<'a> StructureSearch<'a> {
+    /// Prepares to search the structures in `structure_manifest` that have been unlocked by `milestones`.
+    pub(crate) fn new(structure_manifest: &'a StructureManifest, milestones: &Milestones) -> Self {
+        let mut candidates: Vec<Id<Structure>> = structure_manifest
+            .prototypes(milestones)
+            .into_iter()
+            .collect();
+        candidates.sort_by_key(|&id| structure_manifest.name(id));
+
+        StructureSearch {
+            structure_manifest,
+            candidates,
+        }
+    }
+
+    /// Returns up to `limit` structures whose names fuzzily match `query`, best matches first.
+    ///
+    /// Matching ignores case, and only requires the characters of the query to appear in order.
+    /// Prefixes and whole words are preferred, and ties are broken alphabetically.
+    /// An empty query matches everything equally.
+    pub(crate) fn search(&self, query: &str, limit: usize) -> Vec<(Id<Structure>, MatchScore)> {
+        let mut matches: Vec<(Id<Structure>, MatchScore)> = self
+            .candidates
+            .iter()
+            .filter_map(|&id| {
+                MatchScore::compute(query, self.structure_manifest.name(id))
+                    .map(|score| (id, score))
+            })
+            .collect();
+
+        // The sort is stable, so equal scores stay in alphabetical order
+        matches.sort_by(|(_, a), (_, b)| b.cmp(a));
+        matches.truncate(limit);
+        matches
     }
 }
 
@@ -278,6 +1159,9 @@ impl StructureManifest {
 #[uuid = "77ddfe49-be99-4fea-bbba-0c085821f6b8"]
 pub struct RawStructureManifest {
     /// The data for each structure.
+    ///
+    /// These are serialized in order of their names, to keep the output stable.
+    #[serde(serialize_with = "serialize_sorted")]
     pub structure_types: HashMap<String, RawStructureData>,
 }
 
@@ -287,15 +1171,669 @@ impl IsRawManifest for RawStructureManifest {
     type Marker = Structure;
     type Data = StructureData;
 
-    fn process(&self) -> Manifest<Self::Marker, Self::Data> {
+    fn process(&self) -> Result<Manifest<Self::Marker, Self::Data>, ManifestError> {
         let mut manifest = Manifest::new();
 
         for (raw_id, raw_data) in self.structure_types.clone() {
-            let data = raw_data.into();
+            if let RawConstructionStrategy::Seedling(ref seedling_name) =
+                raw_data.construction_strategy
+            {
+                if !self.structure_types.contains_key(seedling_name) {
+                    return Err(ManifestError::UnknownReference {
+                        entry: raw_id,
+                        field: "construction_strategy".to_string(),
+                        reference: seedling_name.clone(),
+                    });
+                }
+            }
+
+            if let Some(organism_variety) = &raw_data.organism_variety {
+                for life_path in &organism_variety.lifecycle.life_paths {
+                    if let RawOrganismId::Structure(ref new_form) = life_path.new_form {
+                        if !self.structure_types.contains_key(new_form) {
+                            return Err(ManifestError::UnknownReference {
+                                entry: raw_id,
+                                field: "lifecycle".to_string(),
+                                reference: new_form.clone(),
+                            });
+                        }
+                    }
+                }
 
-            manifest.insert(raw_id, data)
+                if let Some(remains_structure) = organism_variety
+                    .remains
+                    .as_ref()
+                    .and_then(|remains| remains.structure.as_ref())
+                {
+                    if !self.structure_types.contains_key(remains_structure) {
+                        return Err(ManifestError::UnknownReference {
+                            entry: raw_id,
+                            field: "remains".to_string(),
+                            reference: remains_structure.clone(),
+                        });
+                    }
+                }
+            }
+
+            if let Some(Err(reason)) = raw_data.footprint.as_ref().map(RawFootprint::expand) {
+                return Err(ManifestError::MalformedStructure {
+                    structure: raw_id,
+                    reason,
+                });
+            }
+
+            if let Some(Err(reason)) = raw_data
+                .allowed_moisture
+                .as_ref()
+                .map(MoistureRange::validate)
+            {
+                return Err(ManifestError::MalformedStructure {
+                    structure: raw_id,
+                    reason,
+                });
+            }
+
+            let pollination = raw_data
+                .organism_variety
+                .as_ref()
+                .and_then(|organism_variety| {
+                    organism_variety
+                        .resolve_pollination(|partner| self.structure_types.contains_key(partner))
+                });
+
+            let mut data: StructureData = raw_data.into();
+            if let Some(organism_variety) = &mut data.organism_variety {
+                organism_variety.pollination = pollination;
+            }
+
+            manifest.try_insert(raw_id, data)?;
         }
 
-        manifest
+        Ok(manifest)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        crafting::recipe::{RecipeData, RecipeInput, RecipeOutput},
+        items::item_manifest::ItemData,
+        organisms::{
+            energy::EnergyPool,
+            life_stages::RemainsData,
+            lifecycle::{RawLifePath, RawLifecycle},
+            pollination::RawPollination,
+            SeasonalModifiers,
+        },
+        structures::FootprintShape,
+        units::{basic_needs::Diet, unit_manifest::UnitData},
+    };
+    use std::time::Duration;
+
+    /// A tree that grows from a seedling, which is the structure that is actually constructed.
+    fn tree_manifest() -> StructureManifest {
+        let seedling_construction = ConstructionData {
+            work: Some(Duration::from_secs(3)),
+            ..Default::default()
+        };
+
+        let seedling = StructureData {
+            construction_strategy: ConstructionStrategy::Direct(seedling_construction),
+            ..StructureData::organism("acacia_seed")
+        };
+
+        let tree = StructureData {
+            construction_strategy: ConstructionStrategy::Seedling(Id::from_name(
+                "acacia_seed".to_string(),
+            )),
+            ..StructureData::organism("acacia")
+        };
+
+        [
+            ("acacia_seed".to_string(), seedling),
+            ("acacia".to_string(), tree),
+        ]
+        .into_iter()
+        .collect()
+    }
+
+    #[test]
+    fn manifests_can_be_built_in_code() {
+        let manifest = tree_manifest();
+        let seedling_id = Id::from_name("acacia_seed".to_string());
+        let tree_id = Id::from_name("acacia".to_string());
+
+        assert_eq!(manifest.len(), 2);
+        assert_eq!(manifest.name(tree_id), "acacia");
+
+        let expected = manifest.construction_data(seedling_id).unwrap();
+        assert_eq!(expected.work, Some(Duration::from_secs(3)));
+        // Trees are built by planting their seedling
+        assert_eq!(manifest.construction_data(tree_id), Some(expected));
+    }
+
+    #[test]
+    fn gated_structures_are_not_prototypes_until_unlocked() {
+        let milestone = Id::from_name("first_harvest".to_string());
+        let silo_id = Id::from_name("silo".to_string());
+        let path_id = Id::from_name("path".to_string());
+
+        let manifest: StructureManifest = [
+            ("path".to_string(), StructureData::passable()),
+            (
+                "silo".to_string(),
+                StructureData {
+                    required_milestone: Some(milestone),
+                    ..StructureData::impassable()
+                },
+            ),
+        ]
+        .into_iter()
+        .collect();
+
+        let mut milestones = Milestones::default();
+        let prototypes: Vec<_> = manifest.prototypes(&milestones).into_iter().collect();
+        assert_eq!(prototypes, vec![path_id]);
+
+        assert!(milestones.complete(milestone));
+        let prototypes: HashSet<_> = manifest.prototypes(&milestones).into_iter().collect();
+        assert_eq!(prototypes, HashSet::from_iter([path_id, silo_id]));
+    }
+
+    fn search_manifest() -> StructureManifest {
+        let landmark = StructureData {
+            kind: StructureKind::Landmark,
+            ..StructureData::impassable()
+        };
+
+        [
+            ("plant_pot".to_string(), StructureData::impassable()),
+            ("spatha".to_string(), StructureData::impassable()),
+            ("path".to_string(), StructureData::passable()),
+            ("patio_ruins".to_string(), landmark),
+            ("storage".to_string(), StructureData::impassable()),
+        ]
+        .into_iter()
+        .collect()
+    }
+
+    fn search_names<'a>(manifest: &'a StructureManifest, query: &str, limit: usize) -> Vec<&'a str> {
+        StructureSearch::new(manifest, &Milestones::default())
+            .search(query, limit)
+            .into_iter()
+            .map(|(id, _)| manifest.name(id))
+            .collect()
+    }
+
+    #[test]
+    fn structure_search_prefers_prefixes_over_scattered_matches() {
+        let manifest = search_manifest();
+
+        assert_eq!(
+            search_names(&manifest, "PaT", 10),
+            vec!["path", "spatha", "plant_pot"]
+        );
+        assert_eq!(search_names(&manifest, "pat", 1), vec!["path"]);
+        // Matches may span several words
+        assert_eq!(search_names(&manifest, "pot", 10), vec!["plant_pot"]);
+        assert_eq!(search_names(&manifest, "xyz", 10), Vec::<&str>::new());
+    }
+
+    #[test]
+    fn structure_search_never_returns_landmarks() {
+        let manifest = search_manifest();
+
+        assert!(!search_names(&manifest, "patio", 10).contains(&"patio_ruins"));
+        assert_eq!(
+            search_names(&manifest, "", 10),
+            vec!["path", "plant_pot", "spatha", "storage"]
+        );
+    }
+
+    #[test]
+    fn manifests_can_be_collected_from_an_iterator() {
+        let built = tree_manifest();
+        let collected: StructureManifest = built
+            .data_map()
+            .iter()
+            .map(|(&id, data)| (built.name(id).to_string(), data.clone()))
+            .collect();
+
+        assert_eq!(collected.name_map(), built.name_map());
+        for id in built.variants() {
+            assert_eq!(collected.construction_data(id), built.construction_data(id));
+        }
+    }
+
+    fn seed_manifest() -> ItemManifest {
+        [(
+            "acacia_seed".to_string(),
+            ItemData {
+                compostable: true,
+                buoyant: true,
+                ..ItemData::basic(5)
+            },
+        )]
+        .into_iter()
+        .collect()
+    }
+
+    /// A seed bin that starts with the provided raw inventory.
+    fn seed_bin(starting_inventory: Vec<(String, u32)>) -> StructureManifest {
+        let kind = RawStructureKind::Storage {
+            max_slot_count: 2,
+            reserved_for: None,
+            starting_inventory,
+            merge_adjacent: false,
+        };
+
+        [(
+            "seed_bin".to_string(),
+            StructureData {
+                kind: kind.into(),
+                ..StructureData::impassable()
+            },
+        )]
+        .into_iter()
+        .collect()
+    }
+
+    #[test]
+    fn repeated_starting_items_are_merged() {
+        let kind: StructureKind = RawStructureKind::Storage {
+            max_slot_count: 2,
+            reserved_for: None,
+            starting_inventory: vec![
+                ("acacia_seed".to_string(), 3),
+                ("acacia_seed".to_string(), 4),
+            ],
+            merge_adjacent: false,
+        }
+        .into();
+
+        match kind {
+            StructureKind::Storage {
+                starting_inventory, ..
+            } => assert_eq!(
+                starting_inventory,
+                vec![ItemCount::new(Id::from_name("acacia_seed".to_string()), 7)]
+            ),
+            _ => panic!("Expected a storage structure"),
+        }
+    }
+
+    #[test]
+    fn starting_inventory_that_fits_is_valid() {
+        let manifest = seed_bin(vec![("acacia_seed".to_string(), 10)]);
+        assert_eq!(
+            manifest.validate_starting_inventories(&seed_manifest()),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn starting_inventory_with_unknown_item_is_invalid() {
+        let manifest = seed_bin(vec![("pinecone".to_string(), 1)]);
+
+        assert!(matches!(
+            manifest.validate_starting_inventories(&seed_manifest()),
+            Err(ManifestError::UnknownReference { entry, .. }) if entry == "seed_bin"
+        ));
+    }
+
+    #[test]
+    fn starting_inventory_that_overflows_is_invalid() {
+        // Two slots of five seeds each can only hold ten
+        let manifest = seed_bin(vec![("acacia_seed".to_string(), 12)]);
+
+        assert_eq!(
+            manifest.validate_starting_inventories(&seed_manifest()),
+            Err(ManifestError::InventoryOverflow {
+                entry: "seed_bin".to_string(),
+                excess: 2,
+            })
+        );
+    }
+
+    #[test]
+    fn releasers_and_pumps_must_use_known_items() {
+        let manifest_with_kind = |kind: StructureKind| -> StructureManifest {
+            [(
+                "chute".to_string(),
+                StructureData {
+                    kind,
+                    ..StructureData::passable()
+                },
+            )]
+            .into_iter()
+            .collect()
+        };
+        let acacia_seed = Id::from_name("acacia_seed".to_string());
+
+        let releaser = manifest_with_kind(StructureKind::Releaser {
+            release_threshold: None,
+            released_item: acacia_seed,
+        });
+        assert_eq!(releaser.validate_logistic_items(&seed_manifest()), Ok(()));
+
+        let releaser = manifest_with_kind(StructureKind::Releaser {
+            release_threshold: None,
+            released_item: Id::from_name("pinecone".to_string()),
+        });
+        assert!(matches!(
+            releaser.validate_logistic_items(&seed_manifest()),
+            Err(ManifestError::UnknownReference { entry, field, .. })
+                if entry == "chute" && field == "released_item"
+        ));
+
+        // Seeds don't hold water
+        let pump = manifest_with_kind(StructureKind::WellPump {
+            filled_item: acacia_seed,
+        });
+        assert!(matches!(
+            pump.validate_logistic_items(&seed_manifest()),
+            Err(ManifestError::MalformedStructure { structure, .. }) if structure == "chute"
+        ));
+    }
+
+    #[test]
+    fn pollination_partners_resolve_to_structures_or_units() {
+        let raw_variety = |partners: &[&str]| RawOrganismVariety {
+            prototypical_form: RawOrganismId::structure("flower"),
+            lifecycle: RawLifecycle::STATIC,
+            energy_pool: EnergyPool::default(),
+            seasonal_modifiers: SeasonalModifiers::default(),
+            life_span: None,
+            remains: None,
+            pollination: Some(RawPollination {
+                partners: partners.iter().map(|name| name.to_string()).collect(),
+                radius: 2,
+                required: true,
+            }),
+        };
+        let raw_manifest = |partners: &[&str]| RawStructureManifest {
+            structure_types: [
+                (
+                    "flower".to_string(),
+                    RawStructureData {
+                        organism_variety: Some(raw_variety(partners)),
+                        ..RawStructureData::passable()
+                    },
+                ),
+                ("hive".to_string(), RawStructureData::passable()),
+            ]
+            .into_iter()
+            .collect(),
+        };
+
+        let unit_manifest: UnitManifest = [(
+            "bee".to_string(),
+            UnitData::simple("bee", Diet::simple("nectar")),
+        )]
+        .into_iter()
+        .collect();
+
+        let manifest = raw_manifest(&["hive", "bee"]).process().unwrap();
+        let flower_id = Id::from_name("flower".to_string());
+        let pollination = manifest
+            .get(flower_id)
+            .organism_variety
+            .as_ref()
+            .unwrap()
+            .pollination
+            .as_ref()
+            .unwrap();
+        assert_eq!(
+            pollination.partners,
+            vec![
+                OrganismId::Structure(Id::from_name("hive".to_string())),
+                OrganismId::Unit(Id::from_name("bee".to_string())),
+            ]
+        );
+        assert_eq!(
+            manifest.validate_pollination_partners(&unit_manifest),
+            Ok(())
+        );
+
+        let dangling = raw_manifest(&["wasp"]).process().unwrap();
+        assert!(matches!(
+            dangling.validate_pollination_partners(&unit_manifest),
+            Err(ManifestError::UnknownReference { entry, field, .. })
+                if entry == "flower" && field == "pollination"
+        ));
+    }
+
+    #[test]
+    fn lifecycle_forms_and_remains_must_exist() {
+        let raw_manifest = |new_form: &str, remains: RemainsData| RawStructureManifest {
+            structure_types: [(
+                "sapling".to_string(),
+                RawStructureData {
+                    organism_variety: Some(RawOrganismVariety {
+                        prototypical_form: RawOrganismId::structure("sapling"),
+                        lifecycle: RawLifecycle::new(vec![RawLifePath {
+                            new_form: RawOrganismId::structure(new_form),
+                            energy_required: None,
+                            time_required: Some(1.),
+                        }]),
+                        energy_pool: EnergyPool::default(),
+                        seasonal_modifiers: SeasonalModifiers::default(),
+                        life_span: None,
+                        remains: Some(remains),
+                        pollination: None,
+                    }),
+                    ..RawStructureData::passable()
+                },
+            )]
+            .into_iter()
+            .collect(),
+        };
+
+        let remains = |structure: Option<&str>, item: &str| RemainsData {
+            structure: structure.map(str::to_string),
+            items: vec![(item.to_string(), 1)],
+            salvage: Vec::new(),
+        };
+
+        // Organisms may grow into, or leave behind, another copy of themselves
+        let manifest = raw_manifest("sapling", remains(Some("sapling"), "acacia_seed"))
+            .process()
+            .unwrap();
+        assert_eq!(manifest.validate_remains(&seed_manifest()), Ok(()));
+
+        assert!(matches!(
+            raw_manifest("tree", remains(None, "acacia_seed")).process(),
+            Err(ManifestError::UnknownReference { entry, field, reference })
+                if entry == "sapling" && field == "lifecycle" && reference == "tree"
+        ));
+        assert!(matches!(
+            raw_manifest("sapling", remains(Some("stump"), "acacia_seed")).process(),
+            Err(ManifestError::UnknownReference { entry, field, reference })
+                if entry == "sapling" && field == "remains" && reference == "stump"
+        ));
+
+        let manifest = raw_manifest("sapling", remains(None, "acorn"))
+            .process()
+            .unwrap();
+        assert!(matches!(
+            manifest.validate_remains(&seed_manifest()),
+            Err(ManifestError::UnknownReference { entry, field, .. })
+                if entry == "sapling" && field == "remains"
+        ));
+    }
+
+    #[test]
+    fn first_recipe_preset_is_the_starting_recipe() {
+        let raw = RawStructureData {
+            kind: RawStructureKind::Crafting {
+                starting_recipe: RawActiveRecipe::new("acacia_leaf_production"),
+                input_buffer_slots: None,
+                output_buffer_slots: None,
+            },
+            recipe_presets: vec![
+                RawActiveRecipe::new("mulch_production"),
+                RawActiveRecipe::new("acacia_leaf_production"),
+            ],
+            ..RawStructureData::passable()
+        };
+
+        let data: StructureData = raw.into();
+        let mulch = ActiveRecipe::new(Id::from_name("mulch_production".to_string()));
+
+        assert_eq!(data.starting_recipe(), &mulch);
+        assert_eq!(data.recipe_presets.len(), 2);
+    }
+
+    #[test]
+    fn random_recipe_choices_are_validated() {
+        let recipe = |inputs| RecipeData::basic(inputs, RecipeOutput::EMPTY);
+        let recipe_manifest: RecipeManifest = [
+            ("leaf_production".to_string(), recipe(RecipeInput::EMPTY)),
+            (
+                "compost".to_string(),
+                recipe(RecipeInput::Flexible {
+                    tag: ItemTag::Compostable,
+                    count: 1,
+                    exclude: Vec::new(),
+                }),
+            ),
+        ]
+        .into_iter()
+        .collect();
+        let choice =
+            |name: &str, weight| (ActiveRecipe::new(Id::from_name(name.to_string())), weight);
+
+        let manifest_with_recipe = |starting_recipe| -> StructureManifest {
+            [(
+                "tree".to_string(),
+                StructureData {
+                    kind: StructureKind::Crafting {
+                        starting_recipe,
+                        input_buffer_slots: None,
+                        output_buffer_slots: None,
+                    },
+                    ..StructureData::organism("tree")
+                },
+            )]
+            .into_iter()
+            .collect()
+        };
+
+        let valid = manifest_with_recipe(ActiveRecipe::random(vec![
+            choice("leaf_production", 3.),
+            (ActiveRecipe::NONE, 1.),
+        ]));
+        assert_eq!(valid.validate_recipe_choices(&recipe_manifest), Ok(()));
+
+        let unknown =
+            manifest_with_recipe(ActiveRecipe::random(vec![choice("flower_production", 1.)]));
+        assert!(matches!(
+            unknown.validate_recipe_choices(&recipe_manifest),
+            Err(ManifestError::UnknownReference { entry, field, .. })
+                if entry == "tree" && field == "recipe"
+        ));
+
+        let malformed = [
+            ActiveRecipe::random(Vec::new()),
+            ActiveRecipe::random(vec![choice("leaf_production", -1.)]),
+            ActiveRecipe::random(vec![choice("leaf_production", f32::NAN)]),
+            // Flexible and exact inputs cannot share an input inventory
+            ActiveRecipe::random(vec![choice("leaf_production", 1.), choice("compost", 1.)]),
+        ];
+        for active_recipe in malformed {
+            assert!(matches!(
+                manifest_with_recipe(active_recipe).validate_recipe_choices(&recipe_manifest),
+                Err(ManifestError::MalformedStructure { structure, .. }) if structure == "tree"
+            ));
+        }
+
+        // Presets are checked too
+        let presets: StructureManifest = [(
+            "tree".to_string(),
+            StructureData {
+                recipe_presets: vec![ActiveRecipe::random(Vec::new())],
+                ..StructureData::organism("tree")
+            },
+        )]
+        .into_iter()
+        .collect();
+        assert!(presets.validate_recipe_choices(&recipe_manifest).is_err());
+    }
+
+    #[test]
+    fn storage_footprint_grows_with_slot_count() {
+        let storage = |max_slot_count| RawStructureData {
+            kind: RawStructureKind::Storage {
+                max_slot_count,
+                reserved_for: None,
+                starting_inventory: Vec::new(),
+                merge_adjacent: false,
+            },
+            ..RawStructureData::passable()
+        };
+
+        let small: StructureData = storage(4).into();
+        let large: StructureData = storage(16).into();
+
+        assert_eq!(small.footprint, Footprint::single());
+        assert_eq!(large.footprint, Footprint::rhombus(2));
+        assert_ne!(small.footprint, large.footprint);
+        assert_eq!(large.footprint.set.len(), 4);
+    }
+
+    #[test]
+    fn degenerate_footprint_shapes_fail_processing() {
+        let raw_manifest = RawStructureManifest {
+            structure_types: [(
+                "dock".to_string(),
+                RawStructureData {
+                    footprint: Some(RawFootprint::Shape(FootprintShape::Line { length: 0 })),
+                    ..RawStructureData::passable()
+                },
+            )]
+            .into_iter()
+            .collect(),
+        };
+
+        assert!(matches!(
+            raw_manifest.process(),
+            Err(ManifestError::MalformedStructure { structure, .. }) if structure == "dock"
+        ));
+    }
+
+    #[test]
+    fn empty_moisture_ranges_fail_processing() {
+        let raw_manifest = |allowed_moisture| RawStructureManifest {
+            structure_types: [(
+                "reed_bed".to_string(),
+                RawStructureData {
+                    allowed_moisture: Some(allowed_moisture),
+                    ..RawStructureData::passable()
+                },
+            )]
+            .into_iter()
+            .collect(),
+        };
+        let moisture_range = |min_depth, max_depth, max_water_table_depth: Option<f32>| MoistureRange {
+            min_depth: Height(min_depth),
+            max_depth: Height(max_depth),
+            max_water_table_depth: max_water_table_depth.map(Height),
+        };
+
+        assert!(raw_manifest(moisture_range(0.5, 0.5, Some(1.)))
+            .process()
+            .is_ok());
+
+        for allowed_moisture in [
+            moisture_range(1., 0.5, None),
+            moisture_range(0., f32::INFINITY, None),
+            moisture_range(0., 1., Some(-1.)),
+        ] {
+            assert!(matches!(
+                raw_manifest(allowed_moisture).process(),
+                Err(ManifestError::MalformedStructure { structure, .. }) if structure == "reed_bed"
+            ));
+        }
     }
 }
@@ -10,7 +10,10 @@ use crate::{
     asset_management::manifest::Id,
     construction::{terraform::TerraformingAction, zoning::Zoning},
     geometry::{MapGeometry, VoxelPos},
-    terrain::{terrain_assets::TerrainHandles, terrain_manifest::Terrain},
+    terrain::{
+        terrain_assets::TerrainHandles,
+        terrain_manifest::{Terrain, TerrainManifest},
+    },
 };
 
 /// An extension trait for [`Commands`] for working with terrain.
@@ -46,6 +49,7 @@ impl Command for ApplyTerraformingCommand {
         let mut system_state = SystemState::<(
             ResMut<MapGeometry>,
             Res<TerrainHandles>,
+            Option<Res<TerrainManifest>>,
             Query<(
                 &mut Id<Terrain>,
                 &mut Zoning,
@@ -54,7 +58,8 @@ impl Command for ApplyTerraformingCommand {
             )>,
         )>::new(world);
 
-        let (mut map_geometry, terrain_handles, mut terrain_query) = system_state.get_mut(world);
+        let (mut map_geometry, terrain_handles, maybe_terrain_manifest, mut terrain_query) =
+            system_state.get_mut(world);
 
         let terrain_entity = map_geometry.get_terrain(self.voxel_pos.hex).unwrap();
 
@@ -78,9 +83,18 @@ impl Command for ApplyTerraformingCommand {
                 .get(&changed_terrain_id)
                 .unwrap()
                 .clone_weak();
+
+            // The new terrain type may allow a different number of units on the tile
+            let max_occupants = maybe_terrain_manifest.and_then(|terrain_manifest| {
+                terrain_manifest.get(changed_terrain_id).max_occupants
+            });
+            map_geometry.set_occupant_limit(terrain_entity, max_occupants);
         }
 
         map_geometry.update_height(voxel_pos.hex, voxel_pos.height);
-        *zoning = Zoning::None;
+        // Terraforming can also be requested by ghosts that need flatter ground, which must keep their zoning
+        if matches!(*zoning, Zoning::Terraform(..)) {
+            *zoning = Zoning::None;
+        }
     }
 }
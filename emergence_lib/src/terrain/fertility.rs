@@ -0,0 +1,168 @@
+//! Soil fertility speeds up the growth of plants, is depleted by harvests, and is replenished by composting.
+
+use bevy::prelude::*;
+
+use crate::{
+    crafting::inventories::CraftingState,
+    geometry::{MapGeometry, VoxelPos},
+    organisms::Organism,
+    simulation::time::InGameTime,
+};
+
+/// How fertile the soil of a terrain tile is.
+///
+/// This multiplies the growth rate, crafting speed and vegetative spread of any organisms growing on the tile.
+/// Fertility slowly returns to the [`SoilFertility::BASELINE`] from above or below.
+#[derive(Component, Debug, Clone, Copy, PartialEq)]
+pub(crate) struct SoilFertility {
+    /// The current growth multiplier.
+    value: f32,
+}
+
+impl SoilFertility {
+    /// The fertility of soil that has not been enriched.
+    pub(crate) const BASELINE: f32 = 1.0;
+
+    /// The highest fertility that soil can reach.
+    pub(crate) const MAX: f32 = 2.0;
+
+    /// The lowest fertility that soil can be depleted to.
+    pub(crate) const MIN: f32 = 0.25;
+
+    /// The fertility lost from a tile each time an organism growing on it completes a recipe.
+    pub(crate) const DEPLETION_PER_HARVEST: f32 = 0.05;
+
+    /// The fraction of the difference from the [`SoilFertility::BASELINE`] that is recovered each in-game day.
+    pub(crate) const DECAY_PER_DAY: f32 = 0.1;
+
+    /// The multiplier applied to the growth of organisms on this tile.
+    pub(crate) fn value(&self) -> f32 {
+        self.value
+    }
+
+    /// Increases the fertility by `amount`, saturating at [`SoilFertility::MAX`].
+    pub(crate) fn enrich(&mut self, amount: f32) {
+        self.value = (self.value + amount).min(Self::MAX);
+    }
+
+    /// Decreases the fertility by `amount`, saturating at [`SoilFertility::MIN`].
+    pub(crate) fn deplete(&mut self, amount: f32) {
+        self.value = (self.value - amount).max(Self::MIN);
+    }
+
+    /// Moves the fertility back towards the [`SoilFertility::BASELINE`] over `elapsed_days`.
+    pub(crate) fn decay(&mut self, elapsed_days: f32) {
+        /// Below this difference, the fertility snaps to the baseline.
+        const EPSILON: f32 = 1e-3;
+
+        let excess = self.value - Self::BASELINE;
+        let remaining_fraction = (1. - Self::DECAY_PER_DAY).powf(elapsed_days);
+        let remaining_excess = excess * remaining_fraction;
+
+        self.value = match remaining_excess.abs() < EPSILON {
+            true => Self::BASELINE,
+            false => Self::BASELINE + remaining_excess,
+        };
+    }
+}
+
+impl Default for SoilFertility {
+    fn default() -> Self {
+        SoilFertility {
+            value: Self::BASELINE,
+        }
+    }
+}
+
+/// Slowly returns enriched or depleted soil to its baseline fertility.
+pub(crate) fn decay_soil_fertility(
+    mut terrain_query: Query<&mut SoilFertility>,
+    fixed_time: Res<FixedTime>,
+    in_game_time: Res<InGameTime>,
+) {
+    let elapsed_days = fixed_time.period.as_secs_f32() / in_game_time.seconds_per_day();
+
+    for mut soil_fertility in terrain_query.iter_mut() {
+        // Avoid triggering change detection on the vast majority of tiles
+        if soil_fertility.value != SoilFertility::BASELINE {
+            soil_fertility.decay(elapsed_days);
+        }
+    }
+}
+
+/// Organisms deplete the soil they are growing in each time they complete a recipe.
+pub(crate) fn deplete_soil_fertility(
+    organism_query: Query<(&VoxelPos, &CraftingState), With<Organism>>,
+    mut terrain_query: Query<&mut SoilFertility>,
+    map_geometry: Res<MapGeometry>,
+) {
+    for (voxel_pos, crafting_state) in organism_query.iter() {
+        if *crafting_state != CraftingState::RecipeComplete {
+            continue;
+        }
+
+        let Ok(terrain_entity) = map_geometry.get_terrain(voxel_pos.hex) else { continue };
+        if let Ok(mut soil_fertility) = terrain_query.get_mut(terrain_entity) {
+            soil_fertility.deplete(SoilFertility::DEPLETION_PER_HARVEST);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hexx::Hex;
+
+    #[test]
+    fn repeated_harvests_deplete_fertility_until_it_regenerates() {
+        let mut app = App::new();
+        let map_geometry = MapGeometry::new(&mut app.world, 0);
+        let terrain_entity = map_geometry.get_terrain(Hex::ZERO).unwrap();
+        app.world
+            .entity_mut(terrain_entity)
+            .insert(SoilFertility::default());
+        let organism_entity = app
+            .world
+            .spawn((
+                map_geometry.on_top_of_terrain(Hex::ZERO),
+                Organism,
+                CraftingState::RecipeComplete,
+            ))
+            .id();
+
+        app.insert_resource(map_geometry)
+            .add_system(deplete_soil_fertility);
+
+        let fertility = |app: &App| {
+            app.world
+                .get::<SoilFertility>(terrain_entity)
+                .unwrap()
+                .value()
+        };
+
+        let mut previous = fertility(&app);
+        for _ in 0..5 {
+            app.update();
+            let current = fertility(&app);
+            assert!(current < previous);
+            previous = current;
+        }
+
+        // Harvesting forever can never strip the soil bare
+        for _ in 0..100 {
+            app.update();
+        }
+        assert_eq!(fertility(&app), SoilFertility::MIN);
+
+        // Crafting that has not completed does not deplete the soil
+        *app.world.get_mut::<CraftingState>(organism_entity).unwrap() = CraftingState::NeedsInput;
+        app.update();
+        assert_eq!(fertility(&app), SoilFertility::MIN);
+
+        // Left alone, depleted soil recovers towards the baseline
+        let mut soil_fertility = *app.world.get::<SoilFertility>(terrain_entity).unwrap();
+        soil_fertility.decay(1.);
+        assert!(soil_fertility.value() > SoilFertility::MIN);
+        assert!(soil_fertility.value() < SoilFertility::BASELINE);
+    }
+}
@@ -14,14 +14,16 @@ use crate::signals::Emitter;
 use crate::simulation::SimulationSet;
 use crate::water::{WaterBundle, WaterSet};
 
+use self::fertility::{decay_soil_fertility, SoilFertility};
 use self::terrain_assets::TerrainHandles;
 use self::terrain_manifest::{RawTerrainManifest, Terrain, TerrainManifest};
 use crate::litter::{
     carry_floating_litter_with_current, clear_empty_litter, make_litter_float, set_litter_emitters,
-    LitterEmitters,
+    spread_litter, update_litter_index, LitterEmitters, LitterIndex,
 };
 
 pub(crate) mod commands;
+pub(crate) mod fertility;
 pub(crate) mod terrain_assets;
 pub mod terrain_manifest;
 
@@ -32,6 +34,7 @@ impl Plugin for TerrainPlugin {
     fn build(&self, app: &mut App) {
         app.add_plugin(ManifestPlugin::<RawTerrainManifest>::new())
             .add_asset_collection::<TerrainHandles>()
+            .init_resource::<LitterIndex>()
             .add_systems(
                 (
                     respond_to_height_changes,
@@ -44,9 +47,15 @@ impl Plugin for TerrainPlugin {
                     // but we also want to clean up after because we may have condensed litter inventories by drifting
                     clear_empty_litter.before(carry_floating_litter_with_current),
                     clear_empty_litter.after(carry_floating_litter_with_current),
+                    update_litter_index.after(carry_floating_litter_with_current),
+                    spread_litter
+                        .after(carry_floating_litter_with_current)
+                        .after(update_litter_index),
                     set_litter_emitters
                         .after(carry_floating_litter_with_current)
+                        .after(spread_litter)
                         .in_set(LitterEmitters),
+                    decay_soil_fertility,
                 )
                     .in_set(SimulationSet)
                     .in_schedule(CoreSchedule::FixedUpdate),
@@ -79,6 +88,8 @@ pub(crate) struct TerrainBundle {
     received_light: ReceivedLight,
     /// The components used to track the water table at this tile.
     water_bundle: WaterBundle,
+    /// How fertile the soil of this tile is.
+    soil_fertility: SoilFertility,
 }
 
 impl TerrainBundle {
@@ -116,6 +127,7 @@ impl TerrainBundle {
                 soil_water_flow_rate: terrain_data.soil_water_flow_rate,
                 ..Default::default()
             },
+            soil_fertility: SoilFertility::default(),
         }
     }
 
@@ -133,6 +145,7 @@ impl TerrainBundle {
             shade: Shade::default(),
             received_light: ReceivedLight::default(),
             water_bundle: WaterBundle::default(),
+            soil_fertility: SoilFertility::default(),
         }
     }
 }
@@ -7,7 +7,10 @@ use bevy::{
 use serde::{Deserialize, Serialize};
 
 use crate::{
-    asset_management::manifest::{loader::IsRawManifest, Manifest},
+    asset_management::manifest::{
+        loader::{IsRawManifest, ManifestError},
+        Manifest,
+    },
     water::{
         water_dynamics::{SoilWaterEvaporationRate, SoilWaterFlowRate},
         SoilWaterCapacity,
@@ -35,17 +38,32 @@ pub struct TerrainData {
     ///
     /// This is relative to empty space, which has a capacity of 1.0.
     /// Generally this value should be between 0.05 and 0.5.
+    ///
+    /// If this is missing from the manifest, [`SoilWaterCapacity::default`] is used.
+    #[serde(default)]
     pub soil_water_capacity: SoilWaterCapacity,
     /// The relative rate at which water flows through this terrain type.
     ///
     /// This is relative to empty space, which has a flow rate of 1.0.
     /// Generally this value should be between 0.05 and 0.3.
+    /// This controls how quickly soil water drains into neighboring tiles.
+    ///
+    /// If this is missing from the manifest, [`SoilWaterFlowRate::default`] is used.
+    #[serde(default)]
     pub soil_water_flow_rate: SoilWaterFlowRate,
     /// The evaporation rate of water from this terrain type.
     ///
     /// This is relative to empty space, which has an evaporation rate of 1.0.
     /// Generally this value should be between 0.05 and 0.5.
+    ///
+    /// If this is missing from the manifest, [`SoilWaterEvaporationRate::default`] is used.
+    #[serde(default)]
     pub soil_water_evaporation_rate: SoilWaterEvaporationRate,
+    /// The maximum number of units that can stand on each tile of this terrain type at once.
+    ///
+    /// If this is missing from the manifest, any number of units can share a tile.
+    #[serde(default)]
+    pub max_occupants: Option<u8>,
 }
 
 impl Default for TerrainData {
@@ -55,6 +73,7 @@ impl Default for TerrainData {
             soil_water_capacity: SoilWaterCapacity::default(),
             soil_water_flow_rate: SoilWaterFlowRate::default(),
             soil_water_evaporation_rate: SoilWaterEvaporationRate::default(),
+            max_occupants: None,
         }
     }
 }
@@ -73,14 +92,14 @@ impl IsRawManifest for RawTerrainManifest {
     type Marker = Terrain;
     type Data = TerrainData;
 
-    fn process(&self) -> Manifest<Self::Marker, Self::Data> {
+    fn process(&self) -> Result<Manifest<Self::Marker, Self::Data>, ManifestError> {
         let mut manifest = Manifest::new();
 
         for (raw_id, raw_data) in self.terrain_types.clone() {
             // No additional preprocessing is needed.
-            manifest.insert(raw_id, raw_data)
+            manifest.try_insert(raw_id, raw_data)?;
         }
 
-        manifest
+        Ok(manifest)
     }
 }
@@ -0,0 +1,78 @@
+//! A bare [`App`] for testing crafting structures without the rest of the simulation.
+//!
+//! Unlike the [`SimulationTestHarness`](super::harness::SimulationTestHarness), no systems are added:
+//! each test adds exactly the systems it is testing.
+
+use bevy::prelude::*;
+use hexx::Hex;
+
+use crate::{
+    asset_management::manifest::Id,
+    crafting::{
+        flow::{ItemConsumed, ItemFlowStats, ItemProduced},
+        power::PowerGrid,
+        recipe::{ActiveRecipe, RecipeManifest},
+        CraftingBundle,
+    },
+    geometry::{Facing, MapGeometry},
+    items::item_manifest::ItemManifest,
+    light::shade::ReceivedLight,
+    simulation::rng::GlobalRng,
+    structures::structure_manifest::{Structure, StructureManifest},
+};
+
+/// Sets up a lit map of the provided radius, with the resources and events that crafting systems need.
+///
+/// Time advances by one second each update.
+pub(crate) fn crafter_app(
+    map_radius: u32,
+    recipe_manifest: RecipeManifest,
+    structure_manifest: StructureManifest,
+    item_manifest: ItemManifest,
+) -> App {
+    let mut app = App::new();
+    let map_geometry = MapGeometry::new(&mut app.world, map_radius);
+    for &hex in map_geometry.all_hexes() {
+        let terrain_entity = map_geometry.get_terrain(hex).unwrap();
+        app.world
+            .entity_mut(terrain_entity)
+            .insert(ReceivedLight::default());
+    }
+
+    app.insert_resource(map_geometry)
+        .insert_resource(recipe_manifest)
+        .insert_resource(structure_manifest)
+        .insert_resource(item_manifest)
+        .insert_resource(FixedTime::new_from_secs(1.))
+        .add_event::<ItemProduced>()
+        .add_event::<ItemConsumed>()
+        .init_resource::<PowerGrid>()
+        .init_resource::<ItemFlowStats>()
+        .insert_resource(GlobalRng::new(0));
+
+    app
+}
+
+/// Spawns a crafter of type `structure_id` on top of the terrain at `hex`, crafting `active_recipe`.
+///
+/// The crafter is not added to the [`MapGeometry`], so several crafters may share a tile.
+pub(crate) fn spawn_crafter(
+    app: &mut App,
+    structure_id: Id<Structure>,
+    active_recipe: ActiveRecipe,
+    hex: Hex,
+) -> Entity {
+    let world = &app.world;
+    let crafting_bundle = CraftingBundle::new(
+        structure_id,
+        active_recipe,
+        world.resource::<RecipeManifest>(),
+        world.resource::<ItemManifest>(),
+        world.resource::<StructureManifest>(),
+    );
+    let center = world.resource::<MapGeometry>().on_top_of_terrain(hex);
+
+    app.world
+        .spawn((center, Facing::default(), structure_id, crafting_bundle))
+        .id()
+}
@@ -0,0 +1,363 @@
+//! A headless harness for running the simulation deterministically in tests.
+//!
+//! The harness runs the logistics, crafting and signal systems on a small map,
+//! with manifests built in memory rather than loaded from disk.
+//! Nothing is rendered, no input is read, and time only advances when [`SimulationTestHarness::tick`] is called.
+
+use bevy::{prelude::*, utils::HashMap};
+use hexx::Hex;
+
+use crate::{
+    asset_management::manifest::{Id, ManifestBuilder},
+    crafting::{
+        inventories::{InputInventory, OutputInventory, StorageInventory},
+        recipe::RecipeManifest,
+        CraftingSimulationPlugin,
+    },
+    geometry::{Facing, MapGeometry},
+    items::{
+        item_manifest::{Item, ItemData, ItemManifest},
+        ItemCount,
+    },
+    light::shade::ReceivedLight,
+    litter::{update_litter_index, Litter, LitterIndex},
+    signals::SignalsPlugin,
+    simulation::{alerts::AlertLog, rng::GlobalRng, time::InGameTime, SimulationSet},
+    structures::{
+        commands::place_structure,
+        logistic_buildings::LogisticsPlugin,
+        storage_groups::StorageGroups,
+        structure_manifest::{Structure, StructureData, StructureKind, StructureManifest},
+    },
+    units::item_interaction::{InFlightItems, UnitInventory},
+    water::{WaterDepth, WaterVolume},
+};
+
+/// The seed used by [`SimulationTestHarness::new`].
+const DEFAULT_SEED: u64 = 0;
+
+/// The number of slots in the litter inventory of each tile.
+///
+/// This is large enough to hold a few stacks, so that absorbers have something to work through.
+const LITTER_SLOTS: usize = 3;
+
+/// The only item in the fixture manifest, which stacks up to 10.
+pub(crate) fn egg() -> Id<Item> {
+    Id::from_name("egg".to_string())
+}
+
+/// Releases each egg as soon as it arrives.
+pub(crate) fn releaser() -> Id<Structure> {
+    Id::from_name("releaser".to_string())
+}
+
+/// Releases eggs in batches of 5.
+pub(crate) fn batch_releaser() -> Id<Structure> {
+    Id::from_name("batch_releaser".to_string())
+}
+
+/// Absorbs the litter beneath it until its single slot is full.
+pub(crate) fn absorber() -> Id<Structure> {
+    Id::from_name("absorber".to_string())
+}
+
+/// Absorbs the litter beneath it, pushing full stacks into adjacent storage.
+pub(crate) fn overflowing_absorber() -> Id<Structure> {
+    Id::from_name("overflowing_absorber".to_string())
+}
+
+/// Stores up to three stacks of eggs.
+pub(crate) fn egg_bin() -> Id<Structure> {
+    Id::from_name("egg_bin".to_string())
+}
+
+/// The items available in the harness.
+fn item_manifest() -> ItemManifest {
+    ManifestBuilder::new()
+        .add("egg", ItemData::basic(10))
+        .build()
+}
+
+/// The structures available in the harness.
+fn structure_manifest() -> StructureManifest {
+    let structure = |kind| StructureData {
+        kind,
+        ..StructureData::impassable()
+    };
+
+    ManifestBuilder::new()
+        .add(
+            "releaser",
+            structure(StructureKind::Releaser {
+                release_threshold: None,
+                released_item: egg(),
+            }),
+        )
+        .add(
+            "batch_releaser",
+            structure(StructureKind::Releaser {
+                release_threshold: Some(5),
+                released_item: egg(),
+            }),
+        )
+        .add(
+            "absorber",
+            structure(StructureKind::Absorber {
+                overflow_to_adjacent: false,
+            }),
+        )
+        .add(
+            "overflowing_absorber",
+            structure(StructureKind::Absorber {
+                overflow_to_adjacent: true,
+            }),
+        )
+        .add(
+            "egg_bin",
+            structure(StructureKind::Storage {
+                max_slot_count: 3,
+                reserved_for: Some(egg()),
+                starting_inventory: Vec::new(),
+                merge_adjacent: false,
+            }),
+        )
+        .build()
+}
+
+/// A minimal [`App`] that runs the simulation one fixed tick at a time.
+///
+/// After every tick, the harness checks that no items were created or destroyed:
+/// items can only enter or leave the world through the harness's own helper methods.
+pub(crate) struct SimulationTestHarness {
+    /// The app being simulated.
+    pub(crate) app: App,
+    /// The number of each item that should exist in the world.
+    expected_totals: HashMap<Id<Item>, u32>,
+}
+
+impl SimulationTestHarness {
+    /// Creates a harness with a flat, dry map of the provided radius, seeded with the default seed.
+    pub(crate) fn new(map_radius: u32) -> Self {
+        Self::with_seed(map_radius, DEFAULT_SEED)
+    }
+
+    /// Creates a harness with a flat, dry map of the provided radius.
+    ///
+    /// All randomness drawn from the [`GlobalRng`] is controlled by `seed`.
+    pub(crate) fn with_seed(map_radius: u32, seed: u64) -> Self {
+        let mut app = App::new();
+        let map_geometry = MapGeometry::new(&mut app.world, map_radius);
+        for &hex in map_geometry.all_hexes() {
+            let terrain_entity = map_geometry.get_terrain(hex).unwrap();
+            app.world.entity_mut(terrain_entity).insert((
+                Litter {
+                    contents: StorageInventory::new(LITTER_SLOTS, None),
+                },
+                WaterDepth::Dry,
+                WaterVolume::default(),
+                ReceivedLight::default(),
+            ));
+        }
+
+        let recipe_manifest: RecipeManifest = ManifestBuilder::new().build();
+
+        app.insert_resource(map_geometry)
+            .insert_resource(item_manifest())
+            .insert_resource(recipe_manifest)
+            .insert_resource(structure_manifest())
+            .insert_resource(GlobalRng::new(seed))
+            .insert_resource(FixedTime::new_from_secs(1.))
+            .init_resource::<InGameTime>()
+            .init_resource::<AlertLog>()
+            .init_resource::<InFlightItems>()
+            .init_resource::<LitterIndex>()
+            .init_resource::<StorageGroups>()
+            .add_plugin(SignalsPlugin)
+            .add_plugin(CraftingSimulationPlugin)
+            .add_plugin(LogisticsPlugin)
+            .add_system(
+                update_litter_index
+                    .in_set(SimulationSet)
+                    .in_schedule(CoreSchedule::FixedUpdate),
+            );
+
+        SimulationTestHarness {
+            app,
+            expected_totals: HashMap::default(),
+        }
+    }
+
+    /// Places the structure `structure_id` on top of the terrain at `hex`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the structure cannot be placed there.
+    pub(crate) fn spawn_structure(
+        &mut self,
+        structure_id: Id<Structure>,
+        hex: Hex,
+        facing: Facing,
+    ) -> Entity {
+        let center = self
+            .app
+            .world
+            .resource::<MapGeometry>()
+            .on_top_of_terrain(hex);
+        let structure_entity = place_structure(&mut self.app.world, structure_id, center, facing)
+            .expect("The structure must fit where it is placed");
+
+        // Generated storage may start with items in it
+        let mut query = self.app.world.query::<&StorageInventory>();
+        if let Ok(storage_inventory) = query.get(&self.app.world, structure_entity) {
+            let starting_items: Vec<ItemCount> = storage_inventory
+                .iter()
+                .map(|item_slot| item_slot.item_count())
+                .collect();
+            for item_count in starting_items {
+                self.expect_change(item_count.item_id, item_count.count as i64);
+            }
+        }
+
+        structure_entity
+    }
+
+    /// Drops `count` items of type `item_id` as litter on the tile at `hex`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the items do not fit.
+    pub(crate) fn add_litter(&mut self, hex: Hex, item_id: Id<Item>, count: u32) {
+        let item_count = ItemCount::new(item_id, count);
+        let terrain_entity = self.terrain_entity(hex);
+        self.app
+            .world
+            .resource_scope(|world, item_manifest: Mut<ItemManifest>| {
+                world
+                    .get_mut::<Litter>(terrain_entity)
+                    .unwrap()
+                    .contents
+                    .add_item_all_or_nothing(&item_count, &item_manifest)
+                    .expect("The litter must have space for the items");
+            });
+
+        self.expect_change(item_id, count as i64);
+    }
+
+    /// Removes `count` items of type `item_id` from the litter on the tile at `hex`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if there are not enough items to remove.
+    pub(crate) fn remove_litter(&mut self, hex: Hex, item_id: Id<Item>, count: u32) {
+        let terrain_entity = self.terrain_entity(hex);
+        self.app
+            .world
+            .get_mut::<Litter>(terrain_entity)
+            .unwrap()
+            .contents
+            .try_remove_item(&ItemCount::new(item_id, count))
+            .expect("The litter must contain the items");
+
+        self.expect_change(item_id, -(count as i64));
+    }
+
+    /// The number of items of type `item_id` littered on the tile at `hex`.
+    pub(crate) fn litter_count(&self, hex: Hex, item_id: Id<Item>) -> u32 {
+        let terrain_entity = self.terrain_entity(hex);
+        let litter = self.app.world.get::<Litter>(terrain_entity).unwrap();
+        litter.contents.item_count(item_id)
+    }
+
+    /// Delivers `count` items of type `item_id` to the input inventory of `structure_entity`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the structure cannot accept the items.
+    pub(crate) fn fill_input(&mut self, structure_entity: Entity, item_id: Id<Item>, count: u32) {
+        self.app
+            .world
+            .resource_scope(|world, item_manifest: Mut<ItemManifest>| {
+                world
+                    .get_mut::<InputInventory>(structure_entity)
+                    .expect("The structure must have an input inventory")
+                    .fill_with_items(&ItemCount::new(item_id, count), &item_manifest)
+                    .expect("The input inventory must accept the items");
+            });
+
+        self.expect_change(item_id, count as i64);
+    }
+
+    /// Runs the simulation for `n` fixed ticks.
+    ///
+    /// # Panics
+    ///
+    /// Panics if any tick creates or destroys items.
+    pub(crate) fn tick(&mut self, n: u32) {
+        for _ in 0..n {
+            self.app.world.run_schedule(CoreSchedule::FixedUpdate);
+            self.assert_items_conserved();
+        }
+    }
+
+    /// Records that the harness added (or removed, if negative) `delta` items of type `item_id`.
+    fn expect_change(&mut self, item_id: Id<Item>, delta: i64) {
+        let expected = self.expected_totals.entry(item_id).or_default();
+        *expected = (*expected as i64 + delta) as u32;
+    }
+
+    /// The terrain entity at `hex`, which holds the litter for that tile.
+    fn terrain_entity(&self, hex: Hex) -> Entity {
+        self.app
+            .world
+            .resource::<MapGeometry>()
+            .get_terrain(hex)
+            .unwrap()
+    }
+
+    /// Counts every item in the world, wherever it is stored.
+    fn item_totals(&mut self) -> HashMap<Id<Item>, u32> {
+        let world = &mut self.app.world;
+        let mut totals: HashMap<Id<Item>, u32> = HashMap::default();
+        let mut add = |item_count: ItemCount| {
+            if item_count.count > 0 {
+                *totals.entry(item_count.item_id).or_default() += item_count.count;
+            }
+        };
+
+        for inventory in world.query::<&InputInventory>().iter(world) {
+            inventory.iter().for_each(|slot| add(slot.item_count()));
+        }
+        for inventory in world.query::<&OutputInventory>().iter(world) {
+            inventory.iter().for_each(|slot| add(slot.item_count()));
+        }
+        for inventory in world.query::<&StorageInventory>().iter(world) {
+            inventory.iter().for_each(|slot| add(slot.item_count()));
+        }
+        for litter in world.query::<&Litter>().iter(world) {
+            litter
+                .contents
+                .iter()
+                .for_each(|slot| add(slot.item_count()));
+        }
+        for unit_inventory in world.query::<&UnitInventory>().iter(world) {
+            if let Some(held_items) = unit_inventory.held_items() {
+                add(held_items);
+            }
+        }
+
+        totals
+    }
+
+    /// Checks that the items in the world match the items that the harness has added and removed.
+    fn assert_items_conserved(&mut self) {
+        let mut actual = self.item_totals();
+        let mut expected = self.expected_totals.clone();
+        actual.retain(|_, count| *count > 0);
+        expected.retain(|_, count| *count > 0);
+
+        assert_eq!(
+            actual, expected,
+            "Items were created or destroyed during a simulation tick"
+        );
+    }
+}
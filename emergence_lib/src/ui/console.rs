@@ -0,0 +1,892 @@
+//! A developer console for spawning structures and items, inspecting the world while debugging and rebinding keys.
+//!
+//! Press [`PlayerAction::ToggleConsole`] to open the console, type a command and press enter to run it.
+//! Commands are parsed into a [`ConsoleCommand`] by [`parse_console_command`], which does not touch the UI,
+//! and then run against the [`World`] by [`ConsoleCommand::execute`].
+
+use std::fmt::Display;
+
+use bevy::{ecs::system::CommandQueue, prelude::*};
+use hexx::{Direction, Hex};
+use leafwing_input_manager::{
+    plugin::InputManagerSystem,
+    prelude::{ActionState, InputMap},
+    Actionlike,
+};
+
+use crate::{
+    asset_management::manifest::Id,
+    crafting::flow::ItemFlowStats,
+    geometry::{Facing, Height, MapGeometry, Volume, VoxelPos},
+    items::{
+        item_manifest::{Item, ItemManifest},
+        totals::ResourceTotals,
+    },
+    litter::LitterCommandsExt,
+    player_interaction::{consume_player_actions, keybindings::Rebind, PlayerAction},
+    structures::{
+        commands::place_structure,
+        structure_manifest::{MatchScore, Structure, StructureManifest},
+    },
+    water::{SoilWaterCapacity, WaterVolume},
+};
+
+use super::FiraSansFontFamily;
+
+/// Adds the developer console to the UI.
+pub(super) struct ConsolePlugin;
+
+impl Plugin for ConsolePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Console>()
+            .add_startup_system(spawn_console)
+            .add_systems((toggle_console, type_in_console, update_console_text).chain())
+            .add_system(
+                run_console_commands
+                    .after(type_in_console)
+                    .before(update_console_text)
+                    .run_if(commands_submitted),
+            )
+            .add_system(
+                block_actions_while_typing
+                    .in_base_set(CoreSet::PreUpdate)
+                    .after(InputManagerSystem::Update)
+                    .run_if(console_open),
+            );
+    }
+}
+
+/// The maximum number of close matches suggested for an unknown name.
+const MAX_SUGGESTIONS: usize = 3;
+
+/// The maximum number of lines of history shown in the console.
+const MAX_HISTORY: usize = 12;
+
+/// The maximum number of items that can be dropped by a single `give item` command.
+///
+/// Each item is spawned as its own litter entity, so very large counts would stall the game.
+const MAX_GIVEN_ITEMS: u32 = 100;
+
+/// A command that can be run from the console.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum ConsoleCommand {
+    /// `spawn structure <name> <q> <r> [facing]`: places a structure immediately, as if it had been generated.
+    SpawnStructure {
+        /// The structure to spawn.
+        structure_id: Id<Structure>,
+        /// The tile at the center of the structure.
+        hex: Hex,
+        /// The direction the structure faces.
+        facing: Facing,
+    },
+    /// `give item <name> <count> at <q> <r>`: drops items as litter on a tile.
+    GiveItem {
+        /// The item to drop.
+        item_id: Id<Item>,
+        /// The number of items to drop.
+        count: u32,
+        /// The tile to drop the items on.
+        hex: Hex,
+    },
+    /// `set water <q> <r> <depth>`: floods a tile with surface water of the provided depth.
+    SetWater {
+        /// The tile to flood.
+        hex: Hex,
+        /// The depth of the surface water.
+        ///
+        /// A depth of zero leaves the soil saturated, but not flooded.
+        depth: Height,
+    },
+    /// `stats items`: lists the number of each item in the world, and how quickly it is being produced and consumed.
+    ItemStats,
+    /// `bind <action> <key>`: replaces the keybindings for a [`PlayerAction`] with a single key.
+    ///
+    /// The new keybindings are saved, just like any other change to them.
+    Bind {
+        /// The action to rebind.
+        action: PlayerAction,
+        /// The key that will trigger the action.
+        key: KeyCode,
+    },
+}
+
+/// A console command could not be parsed or run.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum ConsoleError {
+    /// Nothing was typed.
+    Empty,
+    /// The command is not recognized.
+    UnknownCommand(String),
+    /// The command was recognized, but its arguments were not.
+    Usage(&'static str),
+    /// An argument that should be a number could not be parsed.
+    InvalidNumber(String),
+    /// More items were requested than can be given at once.
+    TooManyItems(u32),
+    /// An argument that should be a facing could not be parsed.
+    InvalidFacing(String),
+    /// No structure has the provided name.
+    UnknownStructure {
+        /// The name that was typed.
+        name: String,
+        /// The names of similar structures.
+        suggestions: Vec<String>,
+    },
+    /// No item has the provided name.
+    UnknownItem {
+        /// The name that was typed.
+        name: String,
+        /// The names of similar items.
+        suggestions: Vec<String>,
+    },
+    /// No [`PlayerAction`] has the provided name.
+    UnknownAction {
+        /// The name that was typed.
+        name: String,
+        /// The names of similar actions.
+        suggestions: Vec<String>,
+    },
+    /// No [`KeyCode`] has the provided name.
+    UnknownKey(String),
+    /// The tile is outside of the map.
+    OutOfBounds(Hex),
+    /// The command was valid, but could not be carried out.
+    Failed(String),
+}
+
+impl Display for ConsoleError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        /// Writes the list of suggestions, if there are any.
+        fn write_suggestions(
+            f: &mut std::fmt::Formatter<'_>,
+            suggestions: &[String],
+        ) -> std::fmt::Result {
+            match suggestions.is_empty() {
+                true => Ok(()),
+                false => write!(f, " Did you mean: {}?", suggestions.join(", ")),
+            }
+        }
+
+        match self {
+            ConsoleError::Empty => write!(f, "Type a command, such as `stats items`."),
+            ConsoleError::UnknownCommand(command) => write!(f, "Unknown command `{command}`."),
+            ConsoleError::Usage(usage) => write!(f, "Usage: {usage}"),
+            ConsoleError::InvalidNumber(argument) => write!(f, "`{argument}` is not a number."),
+            ConsoleError::TooManyItems(count) => write!(
+                f,
+                "Cannot give {count} items at once: the limit is {MAX_GIVEN_ITEMS}."
+            ),
+            ConsoleError::InvalidFacing(argument) => write!(
+                f,
+                "`{argument}` is not a facing: use top, top_right, bottom_right, bottom, bottom_left or top_left."
+            ),
+            ConsoleError::UnknownStructure { name, suggestions } => {
+                write!(f, "Unknown structure `{name}`.")?;
+                write_suggestions(f, suggestions)
+            }
+            ConsoleError::UnknownItem { name, suggestions } => {
+                write!(f, "Unknown item `{name}`.")?;
+                write_suggestions(f, suggestions)
+            }
+            ConsoleError::UnknownAction { name, suggestions } => {
+                write!(f, "Unknown action `{name}`.")?;
+                write_suggestions(f, suggestions)
+            }
+            ConsoleError::UnknownKey(name) => write!(
+                f,
+                "Unknown key `{name}`: use names such as Return, Space, V or F1."
+            ),
+            ConsoleError::OutOfBounds(hex) => {
+                write!(f, "({}, {}) is outside of the map.", hex.x, hex.y)
+            }
+            ConsoleError::Failed(reason) => write!(f, "{reason}"),
+        }
+    }
+}
+
+/// The usage of `spawn structure`.
+const SPAWN_USAGE: &str = "spawn structure <name> <q> <r> [facing]";
+/// The usage of `give item`.
+const GIVE_USAGE: &str = "give item <name> <count> at <q> <r>";
+/// The usage of `set water`.
+const SET_WATER_USAGE: &str = "set water <q> <r> <depth>";
+/// The usage of `stats items`.
+const STATS_USAGE: &str = "stats items";
+/// The usage of `bind`.
+const BIND_USAGE: &str = "bind <action> <key>";
+
+/// Parses a line typed into the console into a [`ConsoleCommand`].
+///
+/// Names are looked up in the manifests, and unknown names are reported along with the closest matches.
+pub(crate) fn parse_console_command(
+    input: &str,
+    structure_manifest: &StructureManifest,
+    item_manifest: &ItemManifest,
+) -> Result<ConsoleCommand, ConsoleError> {
+    let words: Vec<&str> = input.split_whitespace().collect();
+
+    match words[..] {
+        [] => Err(ConsoleError::Empty),
+        ["spawn", "structure", name, q, r] => Ok(ConsoleCommand::SpawnStructure {
+            structure_id: parse_structure(name, structure_manifest)?,
+            hex: Hex::new(parse_number(q)?, parse_number(r)?),
+            facing: Facing::default(),
+        }),
+        ["spawn", "structure", name, q, r, facing] => Ok(ConsoleCommand::SpawnStructure {
+            structure_id: parse_structure(name, structure_manifest)?,
+            hex: Hex::new(parse_number(q)?, parse_number(r)?),
+            facing: parse_facing(facing)?,
+        }),
+        ["spawn", ..] => Err(ConsoleError::Usage(SPAWN_USAGE)),
+        ["give", "item", name, count, "at", q, r] => {
+            let count: u32 = parse_number(count)?;
+            if count > MAX_GIVEN_ITEMS {
+                return Err(ConsoleError::TooManyItems(count));
+            }
+
+            Ok(ConsoleCommand::GiveItem {
+                item_id: parse_item(name, item_manifest)?,
+                count,
+                hex: Hex::new(parse_number(q)?, parse_number(r)?),
+            })
+        }
+        ["give", ..] => Err(ConsoleError::Usage(GIVE_USAGE)),
+        ["set", "water", q, r, depth] => {
+            let depth: f32 = parse_number(depth)?;
+            if depth < 0. {
+                return Err(ConsoleError::InvalidNumber(depth.to_string()));
+            }
+
+            Ok(ConsoleCommand::SetWater {
+                hex: Hex::new(parse_number(q)?, parse_number(r)?),
+                depth: Height(depth),
+            })
+        }
+        ["set", ..] => Err(ConsoleError::Usage(SET_WATER_USAGE)),
+        ["stats", "items"] => Ok(ConsoleCommand::ItemStats),
+        ["stats", ..] => Err(ConsoleError::Usage(STATS_USAGE)),
+        ["bind", action, key] => Ok(ConsoleCommand::Bind {
+            action: parse_action(action)?,
+            key: parse_key(key)?,
+        }),
+        ["bind", ..] => Err(ConsoleError::Usage(BIND_USAGE)),
+        [command, ..] => Err(ConsoleError::UnknownCommand(command.to_string())),
+    }
+}
+
+/// Parses a numeric argument.
+fn parse_number<T: std::str::FromStr>(argument: &str) -> Result<T, ConsoleError> {
+    argument
+        .parse()
+        .map_err(|_| ConsoleError::InvalidNumber(argument.to_string()))
+}
+
+/// Parses the direction that a structure should face.
+fn parse_facing(argument: &str) -> Result<Facing, ConsoleError> {
+    let direction = match argument {
+        "top" => Direction::Top,
+        "top_right" => Direction::TopRight,
+        "bottom_right" => Direction::BottomRight,
+        "bottom" => Direction::Bottom,
+        "bottom_left" => Direction::BottomLeft,
+        "top_left" => Direction::TopLeft,
+        _ => return Err(ConsoleError::InvalidFacing(argument.to_string())),
+    };
+
+    Ok(Facing {
+        direction,
+        ..Default::default()
+    })
+}
+
+/// Looks up the structure called `name`.
+fn parse_structure(
+    name: &str,
+    structure_manifest: &StructureManifest,
+) -> Result<Id<Structure>, ConsoleError> {
+    let structure_id = Id::from_name(name.to_string());
+    match structure_manifest.data_map().contains_key(&structure_id) {
+        true => Ok(structure_id),
+        false => Err(ConsoleError::UnknownStructure {
+            name: name.to_string(),
+            suggestions: suggestions(name, structure_manifest.names()),
+        }),
+    }
+}
+
+/// Looks up the item called `name`.
+fn parse_item(name: &str, item_manifest: &ItemManifest) -> Result<Id<Item>, ConsoleError> {
+    let item_id = Id::from_name(name.to_string());
+    match item_manifest.data_map().contains_key(&item_id) {
+        true => Ok(item_id),
+        false => Err(ConsoleError::UnknownItem {
+            name: name.to_string(),
+            suggestions: suggestions(name, item_manifest.names()),
+        }),
+    }
+}
+
+/// Looks up the [`PlayerAction`] whose [`action_name`] is `name`.
+fn parse_action(name: &str) -> Result<PlayerAction, ConsoleError> {
+    PlayerAction::variants()
+        .find(|action| action_name(action) == name)
+        .ok_or_else(|| {
+            let names: Vec<String> = PlayerAction::variants()
+                .map(|action| action_name(&action))
+                .collect();
+            ConsoleError::UnknownAction {
+                name: name.to_string(),
+                suggestions: suggestions(name, names.iter().map(String::as_str)),
+            }
+        })
+}
+
+/// The name of `action` as typed into the console, such as `zone_and_clear`.
+fn action_name(action: &PlayerAction) -> String {
+    let mut name = String::new();
+    for character in format!("{action:?}").chars() {
+        if character.is_uppercase() && !name.is_empty() {
+            name.push('_');
+        }
+        name.push(character.to_ascii_lowercase());
+    }
+
+    name
+}
+
+/// Looks up a [`KeyCode`] by the name of its variant.
+///
+/// The first letter may be typed in lower case, so `return` and `Return` both work.
+fn parse_key(name: &str) -> Result<KeyCode, ConsoleError> {
+    let mut characters = name.chars();
+    let capitalized: String = characters
+        .next()
+        .map(|first| first.to_uppercase().chain(characters).collect())
+        .unwrap_or_default();
+
+    let key = [name, capitalized.as_str()]
+        .into_iter()
+        .find_map(|candidate| {
+            serde_json::from_value(serde_json::Value::String(candidate.to_string())).ok()
+        });
+
+    key.ok_or_else(|| ConsoleError::UnknownKey(name.to_string()))
+}
+
+/// The names that best match `query`, using the same fuzzy matching as the structure search.
+///
+/// Ties are broken alphabetically.
+fn suggestions<'a>(query: &str, names: impl IntoIterator<Item = &'a str>) -> Vec<String> {
+    let mut matches: Vec<(MatchScore, &str)> = names
+        .into_iter()
+        .filter_map(|name| MatchScore::compute(query, name).map(|score| (score, name)))
+        .collect();
+
+    matches.sort_by(|(a_score, a_name), (b_score, b_name)| {
+        b_score.cmp(a_score).then_with(|| a_name.cmp(b_name))
+    });
+
+    matches
+        .into_iter()
+        .take(MAX_SUGGESTIONS)
+        .map(|(_score, name)| name.to_string())
+        .collect()
+}
+
+impl ConsoleCommand {
+    /// Runs this command, returning a message describing what was done.
+    pub(crate) fn execute(self, world: &mut World) -> Result<String, ConsoleError> {
+        match self {
+            ConsoleCommand::SpawnStructure {
+                structure_id,
+                hex,
+                facing,
+            } => {
+                let center = on_map(world, hex)?;
+                place_structure(world, structure_id, center, facing)
+                    .map_err(|error| ConsoleError::Failed(format!("Could not spawn: {error:?}")))?;
+
+                let name = world.resource::<StructureManifest>().name(structure_id);
+                Ok(format!("Spawned {name} at ({}, {}).", hex.x, hex.y))
+            }
+            ConsoleCommand::GiveItem {
+                item_id,
+                count,
+                hex,
+            } => {
+                let voxel_pos = on_map(world, hex)?;
+
+                let mut command_queue = CommandQueue::default();
+                let mut commands = Commands::new(&mut command_queue, world);
+                for _ in 0..count {
+                    commands.spawn_litter(voxel_pos, item_id);
+                }
+                command_queue.apply(world);
+
+                let name = world.resource::<ItemManifest>().name(item_id);
+                Ok(format!("Dropped {count} {name} at ({}, {}).", hex.x, hex.y))
+            }
+            ConsoleCommand::SetWater { hex, depth } => {
+                let voxel_pos = on_map(world, hex)?;
+                let terrain_entity = world
+                    .resource::<MapGeometry>()
+                    .get_terrain(hex)
+                    .map_err(|_| ConsoleError::OutOfBounds(hex))?;
+
+                // The soil must be saturated before any water can collect above it
+                let soil_water_capacity = world
+                    .get::<SoilWaterCapacity>(terrain_entity)
+                    .copied()
+                    .unwrap_or_default();
+                let soil_height = voxel_pos.below().height();
+                let volume = Volume::from_height(soil_height * soil_water_capacity.0)
+                    + Volume::from_height(depth);
+
+                let Some(mut water_volume) = world.get_mut::<WaterVolume>(terrain_entity) else {
+                    return Err(ConsoleError::Failed(
+                        "This tile cannot hold water.".to_string(),
+                    ));
+                };
+                *water_volume = WaterVolume::new(volume);
+
+                Ok(format!(
+                    "Set the water depth at ({}, {}) to {:.2}.",
+                    hex.x, hex.y, depth.0
+                ))
+            }
+            ConsoleCommand::ItemStats => {
+                let item_manifest = world.resource::<ItemManifest>();
+                let Some(item_flow_stats) = world.get_resource::<ItemFlowStats>() else {
+                    return Err(ConsoleError::Failed(
+                        "Item flows are not being recorded.".to_string(),
+                    ));
+                };
+                let resource_totals = world.get_resource::<ResourceTotals>();
+
+                // Items that are only sitting in storage have no flow, but are still worth listing
+                let mut item_ids: Vec<Id<Item>> = item_flow_stats
+                    .iter()
+                    .map(|(item_id, _flow_stats)| item_id)
+                    .chain(
+                        resource_totals
+                            .into_iter()
+                            .flat_map(|totals| totals.iter().map(|(item_id, _count)| item_id)),
+                    )
+                    .collect();
+                item_ids.sort_by_key(|&item_id| item_manifest.name(item_id));
+                item_ids.dedup();
+
+                let lines: Vec<String> = item_ids
+                    .into_iter()
+                    .map(|item_id| {
+                        let name = item_manifest.name(item_id);
+                        let (produced, consumed) = item_flow_stats
+                            .get(item_id)
+                            .map(|flow_stats| {
+                                (flow_stats.produced_per_min(), flow_stats.consumed_per_min())
+                            })
+                            .unwrap_or_default();
+                        let flow = format!("+{produced:.1}/min, -{consumed:.1}/min");
+
+                        match resource_totals {
+                            Some(totals) => format!("{name}: {} ({flow})", totals.total(item_id)),
+                            None => format!("{name}: {flow}"),
+                        }
+                    })
+                    .collect();
+
+                match lines.is_empty() {
+                    true => Ok("There are no items in the world.".to_string()),
+                    false => Ok(lines.join("\n")),
+                }
+            }
+            ConsoleCommand::Bind { action, key } => {
+                let Some(mut input_map) = world.get_resource_mut::<InputMap<PlayerAction>>() else {
+                    return Err(ConsoleError::Failed(
+                        "Keybindings have not been loaded.".to_string(),
+                    ));
+                };
+                input_map.rebind(action.clone(), key);
+
+                Ok(format!("Bound {} to {key:?}.", action_name(&action)))
+            }
+        }
+    }
+}
+
+/// The voxel on top of the terrain at `hex`, if it is on the map.
+fn on_map(world: &World, hex: Hex) -> Result<VoxelPos, ConsoleError> {
+    let map_geometry = world.resource::<MapGeometry>();
+    match map_geometry.is_valid(hex) {
+        true => Ok(map_geometry.on_top_of_terrain(hex)),
+        false => Err(ConsoleError::OutOfBounds(hex)),
+    }
+}
+
+/// The state of the developer console.
+#[derive(Resource, Debug, Default)]
+struct Console {
+    /// Is the console currently shown?
+    open: bool,
+    /// The text typed so far.
+    input: String,
+    /// Lines that have been submitted, but not yet run.
+    submitted: Vec<String>,
+    /// The commands that have been run, and their output.
+    history: Vec<String>,
+}
+
+/// Marks the panel containing the developer console.
+#[derive(Component, Debug)]
+struct ConsolePanel;
+
+/// Marks the text of the developer console.
+#[derive(Component, Debug)]
+struct ConsoleText;
+
+/// Spawns the (initially hidden) developer console.
+fn spawn_console(mut commands: Commands, fonts: Res<FiraSansFontFamily>) {
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    position: UiRect {
+                        left: Val::Px(0.),
+                        bottom: Val::Px(0.),
+                        ..default()
+                    },
+                    size: Size::new(Val::Percent(100.), Val::Auto),
+                    padding: UiRect::all(Val::Px(8.)),
+                    ..default()
+                },
+                background_color: BackgroundColor(Color::rgba(0., 0., 0., 0.8)),
+                visibility: Visibility::Hidden,
+                ..default()
+            },
+            ConsolePanel,
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                TextBundle::from_section(
+                    "",
+                    TextStyle {
+                        font: fonts.regular.clone_weak(),
+                        font_size: 18.,
+                        color: Color::WHITE,
+                    },
+                ),
+                ConsoleText,
+            ));
+        });
+}
+
+/// Shows or hides the console when [`PlayerAction::ToggleConsole`] is pressed.
+fn toggle_console(
+    player_actions: Res<ActionState<PlayerAction>>,
+    mut console: ResMut<Console>,
+    mut panel_query: Query<&mut Visibility, With<ConsolePanel>>,
+) {
+    if !player_actions.just_pressed(PlayerAction::ToggleConsole) {
+        return;
+    }
+
+    console.open = !console.open;
+    for mut visibility in panel_query.iter_mut() {
+        *visibility = match console.open {
+            true => Visibility::Visible,
+            false => Visibility::Hidden,
+        };
+    }
+}
+
+/// Is the developer console currently shown?
+fn console_open(console: Res<Console>) -> bool {
+    console.open
+}
+
+/// Stops keys typed into the console from also triggering their keybindings.
+///
+/// The key that toggles the console is left alone, so that it can still be closed.
+fn block_actions_while_typing(mut actions: ResMut<ActionState<PlayerAction>>) {
+    consume_player_actions(&mut actions, &[PlayerAction::ToggleConsole]);
+}
+
+/// Records the text typed while the console is open, submitting it when enter is pressed.
+///
+/// Characters typed while [`PlayerAction::ToggleConsole`] is held come from its keybinding, and are ignored.
+fn type_in_console(
+    mut received_characters: EventReader<ReceivedCharacter>,
+    player_actions: Res<ActionState<PlayerAction>>,
+    mut console: ResMut<Console>,
+) {
+    if !console.open || player_actions.pressed(PlayerAction::ToggleConsole) {
+        received_characters.clear();
+        return;
+    }
+
+    for received_character in received_characters.iter() {
+        match received_character.char {
+            // Backspace
+            '\u{8}' => {
+                console.input.pop();
+            }
+            '\r' | '\n' => {
+                let line = std::mem::take(&mut console.input);
+                console.submitted.push(line);
+            }
+            character if character.is_control() => (),
+            character => console.input.push(character),
+        }
+    }
+}
+
+/// Have any lines been submitted to the console that have not yet been run?
+fn commands_submitted(console: Res<Console>) -> bool {
+    !console.submitted.is_empty()
+}
+
+/// Parses and runs every line submitted to the console.
+fn run_console_commands(world: &mut World) {
+    let submitted = std::mem::take(&mut world.resource_mut::<Console>().submitted);
+
+    for line in submitted {
+        let parsed = match (
+            world.get_resource::<StructureManifest>(),
+            world.get_resource::<ItemManifest>(),
+        ) {
+            (Some(structure_manifest), Some(item_manifest)) => {
+                parse_console_command(&line, structure_manifest, item_manifest)
+            }
+            _ => Err(ConsoleError::Failed(
+                "The manifests have not loaded yet.".to_string(),
+            )),
+        };
+
+        let output = match parsed.and_then(|command| command.execute(world)) {
+            Ok(message) => message,
+            Err(error) => error.to_string(),
+        };
+
+        let mut console = world.resource_mut::<Console>();
+        console.history.push(format!("> {line}"));
+        console.history.extend(output.lines().map(str::to_string));
+    }
+}
+
+/// Displays the history and current input of the console.
+fn update_console_text(
+    mut console: ResMut<Console>,
+    mut text_query: Query<&mut Text, With<ConsoleText>>,
+) {
+    if !console.is_changed() {
+        return;
+    }
+
+    let excess = console.history.len().saturating_sub(MAX_HISTORY);
+    if excess > 0 {
+        console.history.drain(..excess);
+    }
+
+    let mut text = console.history.join("\n");
+    if !text.is_empty() {
+        text.push('\n');
+    }
+    text.push_str(&format!("> {}_", console.input));
+
+    for mut console_text in text_query.iter_mut() {
+        console_text.sections[0].value = text.clone();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        asset_management::manifest::Manifest, items::item_manifest::ItemData,
+        structures::structure_manifest::StructureData,
+    };
+    use leafwing_input_manager::user_input::UserInput;
+
+    fn structure_manifest() -> StructureManifest {
+        let mut structure_manifest: StructureManifest = Manifest::new();
+        structure_manifest.insert("acacia".to_string(), StructureData::organism("acacia"));
+        structure_manifest.insert("ant_hive".to_string(), StructureData::impassable());
+        structure_manifest.insert("path".to_string(), StructureData::passable());
+        structure_manifest
+    }
+
+    fn item_manifest() -> ItemManifest {
+        let mut item_manifest: ItemManifest = Manifest::new();
+        for name in ["acacia_leaf", "acacia_seed", "mulch"] {
+            item_manifest.insert(
+                name.to_string(),
+                ItemData {
+                    compostable: true,
+                    buoyant: true,
+                    ..ItemData::basic(10)
+                },
+            );
+        }
+        item_manifest
+    }
+
+    fn parse(input: &str) -> Result<ConsoleCommand, ConsoleError> {
+        parse_console_command(input, &structure_manifest(), &item_manifest())
+    }
+
+    #[test]
+    fn commands_are_parsed() {
+        assert_eq!(
+            parse("spawn structure ant_hive 1 -2 bottom_left"),
+            Ok(ConsoleCommand::SpawnStructure {
+                structure_id: Id::from_name("ant_hive".to_string()),
+                hex: Hex::new(1, -2),
+                facing: Facing {
+                    direction: Direction::BottomLeft,
+                    ..Default::default()
+                },
+            })
+        );
+        assert_eq!(
+            parse("  give item mulch 5 at 0 3 "),
+            Ok(ConsoleCommand::GiveItem {
+                item_id: Id::from_name("mulch".to_string()),
+                count: 5,
+                hex: Hex::new(0, 3),
+            })
+        );
+        assert_eq!(
+            parse("set water 2 2 0.5"),
+            Ok(ConsoleCommand::SetWater {
+                hex: Hex::new(2, 2),
+                depth: Height(0.5),
+            })
+        );
+        assert_eq!(parse("stats items"), Ok(ConsoleCommand::ItemStats));
+        assert_eq!(
+            parse("bind zone_and_clear return"),
+            Ok(ConsoleCommand::Bind {
+                action: PlayerAction::ZoneAndClear,
+                key: KeyCode::Return,
+            })
+        );
+    }
+
+    #[test]
+    fn malformed_commands_are_rejected() {
+        assert_eq!(parse(""), Err(ConsoleError::Empty));
+        assert_eq!(
+            parse("teleport 0 0"),
+            Err(ConsoleError::UnknownCommand("teleport".to_string()))
+        );
+        assert_eq!(
+            parse("spawn structure path 0"),
+            Err(ConsoleError::Usage(SPAWN_USAGE))
+        );
+        assert_eq!(
+            parse("give item mulch lots at 0 0"),
+            Err(ConsoleError::InvalidNumber("lots".to_string()))
+        );
+        assert_eq!(
+            parse("give item mulch 4000000000 at 0 0"),
+            Err(ConsoleError::TooManyItems(4_000_000_000))
+        );
+        assert_eq!(
+            parse("spawn structure path 0 0 sideways"),
+            Err(ConsoleError::InvalidFacing("sideways".to_string()))
+        );
+        assert!(matches!(
+            parse("set water 0 0 -1"),
+            Err(ConsoleError::InvalidNumber(_))
+        ));
+        assert_eq!(parse("bind paste"), Err(ConsoleError::Usage(BIND_USAGE)));
+        assert_eq!(
+            parse("bind paste enter"),
+            Err(ConsoleError::UnknownKey("enter".to_string()))
+        );
+        assert!(matches!(
+            parse("bind zone_and_clearr return"),
+            Err(ConsoleError::UnknownAction { .. })
+        ));
+    }
+
+    #[test]
+    fn unknown_names_suggest_close_matches() {
+        assert_eq!(
+            parse("spawn structure acaia 0 0"),
+            Err(ConsoleError::UnknownStructure {
+                name: "acaia".to_string(),
+                suggestions: vec!["acacia".to_string()],
+            })
+        );
+
+        let Err(ConsoleError::UnknownItem { suggestions, .. }) = parse("give item leaf 1 at 0 0")
+        else {
+            panic!("Expected an unknown item");
+        };
+        assert_eq!(suggestions, vec!["acacia_leaf".to_string()]);
+
+        let Err(ConsoleError::UnknownItem { suggestions, .. }) = parse("give item aca 1 at 0 0")
+        else {
+            panic!("Expected an unknown item");
+        };
+        assert_eq!(
+            suggestions,
+            vec!["acacia_leaf".to_string(), "acacia_seed".to_string()]
+        );
+    }
+
+    #[test]
+    fn spawn_command_places_the_structure() {
+        let mut world = World::new();
+        let map_geometry = MapGeometry::new(&mut world, 2);
+        world.insert_resource(map_geometry);
+        world.insert_resource(structure_manifest());
+        world.insert_resource(item_manifest());
+
+        let command = parse("spawn structure ant_hive 1 0").unwrap();
+        let message = command.clone().execute(&mut world).unwrap();
+        assert_eq!(message, "Spawned ant_hive at (1, 0).");
+
+        let map_geometry = world.resource::<MapGeometry>();
+        let voxel_pos = map_geometry.on_top_of_terrain(Hex::new(1, 0));
+        let structure_entity = map_geometry.get_structure(voxel_pos).unwrap();
+        assert_eq!(
+            world.get::<Id<Structure>>(structure_entity),
+            Some(&Id::from_name("ant_hive".to_string()))
+        );
+
+        // The tile is now occupied
+        assert!(matches!(
+            command.execute(&mut world),
+            Err(ConsoleError::Failed(_))
+        ));
+        assert_eq!(
+            parse("spawn structure ant_hive 5 0")
+                .unwrap()
+                .execute(&mut world),
+            Err(ConsoleError::OutOfBounds(Hex::new(5, 0)))
+        );
+    }
+
+    #[test]
+    fn bind_command_rebinds_and_marks_the_keybindings_for_saving() {
+        let mut world = World::new();
+        world.insert_resource(InputMap::new([(KeyCode::V, PlayerAction::Paste)]));
+        world.clear_trackers();
+
+        let message = parse("bind paste Return")
+            .unwrap()
+            .execute(&mut world)
+            .unwrap();
+        assert_eq!(message, "Bound paste to Return.");
+
+        let input_map = world.resource::<InputMap<PlayerAction>>();
+        let paste_inputs: Vec<UserInput> =
+            input_map.get(PlayerAction::Paste).iter().cloned().collect();
+        assert_eq!(paste_inputs, vec![UserInput::from(KeyCode::Return)]);
+        // Changed keybindings are saved by the keybindings plugin
+        assert!(world.is_resource_changed::<InputMap<PlayerAction>>());
+    }
+}
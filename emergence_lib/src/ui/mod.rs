@@ -5,6 +5,7 @@ use crate::{
     construction::terraform::TerraformingTool,
     structures::structure_manifest::Structure,
     ui::{
+        console::ConsolePlugin,
         cursor::CursorPlugin,
         overlay::OverlayMenuPlugin,
         production_statistics::ProductionStatisticsPlugin,
@@ -19,6 +20,7 @@ use crate::{
 use bevy::prelude::*;
 use bevy_screen_diagnostics::{ScreenDiagnosticsPlugin, ScreenFrameDiagnosticsPlugin};
 
+mod console;
 mod cursor;
 mod overlay;
 mod production_statistics;
@@ -65,7 +67,8 @@ impl Plugin for UiPlugin {
         .add_plugin(StatusPlugin)
         .add_plugin(OverlayMenuPlugin)
         .add_plugin(SelectStructurePlugin)
-        .add_plugin(SelectTerraformingPlugin);
+        .add_plugin(SelectTerraformingPlugin)
+        .add_plugin(ConsolePlugin);
     }
 }
 
@@ -1,16 +1,17 @@
 //! Displays information about population counts and production over time.
 
-use bevy::{prelude::*, utils::HashMap};
+use bevy::prelude::*;
 
 use crate::{
     asset_management::manifest::Id,
-    crafting::inventories::{InputInventory, OutputInventory, StorageInventory},
     geometry::Volume,
-    items::item_manifest::{Item, ItemManifest},
+    items::{item_manifest::ItemManifest, totals::ResourceTotals},
     light::TotalLight,
-    litter::Litter,
-    simulation::{time::InGameTime, weather::CurrentWeather},
-    units::{item_interaction::UnitInventory, unit_manifest::Unit},
+    simulation::{
+        time::{InGameTime, Season},
+        weather::CurrentWeather,
+    },
+    units::unit_manifest::Unit,
     water::WaterVolume,
     world_gen::WorldGenState,
 };
@@ -25,10 +26,7 @@ pub(super) struct ProductionStatisticsPlugin;
 impl Plugin for ProductionStatisticsPlugin {
     fn build(&self, app: &mut App) {
         app.init_resource::<Census>()
-            .init_resource::<ItemCount>()
-            .add_systems(
-                (census, update_item_count).distributive_run_if(in_state(WorldGenState::Complete)),
-            )
+            .add_system(census.run_if(in_state(WorldGenState::Complete)))
             .add_startup_system(spawn_production_statistics_menu)
             .add_system(update_production_statistics.run_if(in_state(WorldGenState::Complete)));
     }
@@ -77,11 +75,12 @@ fn spawn_production_statistics_menu(
 fn update_production_statistics(
     mut query: Query<&mut Text, With<ProductionStats>>,
     in_game_time: Res<InGameTime>,
+    season: Res<Season>,
     current_weather: Res<CurrentWeather>,
     total_light: Res<TotalLight>,
     water_volume_query: Query<&WaterVolume>,
     census: Res<Census>,
-    item_count: Res<ItemCount>,
+    resource_totals: Res<ResourceTotals>,
     item_manifest: Res<ItemManifest>,
 ) {
     let mut text = query.single_mut();
@@ -92,12 +91,12 @@ fn update_production_statistics(
 
     let average_water_volume = total_water_volume / water_volume_query.iter().len() as f32;
 
-    text.sections[0].value = format!("{}\n", *in_game_time);
+    text.sections[0].value = format!("{}\nSeason: {}\n", *in_game_time, *season);
     text.sections[1].value = format!("Weather: {}\n", current_weather.get());
     text.sections[2].value = format!("Light: {}\n", *total_light);
     text.sections[3].value = format!("{average_water_volume} average volume of water per tile \n",);
     text.sections[4].value = format!("{}\n", *census);
-    text.sections[5].value = format!("{}\n", item_count.display(&item_manifest));
+    text.sections[5].value = format!("{}\n", resource_totals.display(&item_manifest));
 }
 
 /// Tracks the population of organisms
@@ -117,67 +116,3 @@ impl Display for Census {
 fn census(mut census: ResMut<Census>, unit_query: Query<(), With<Id<Unit>>>) {
     census.total_units = unit_query.iter().len();
 }
-
-/// Counts the total number of items across all inventories of each type.
-#[derive(Debug, Resource, Default)]
-struct ItemCount {
-    /// The number of items of each type
-    map: HashMap<Id<Item>, u32>,
-}
-
-impl ItemCount {
-    /// Returns a human-readable string representation of the item count
-    fn display(&self, item_manifest: &ItemManifest) -> String {
-        let mut string = String::new();
-
-        for (item_id, count) in self.map.iter() {
-            let name = item_manifest.name(*item_id);
-            string.push_str(&format!("{name}: {count}\n"));
-        }
-
-        string
-    }
-}
-
-/// Count the total number of items across all inventories of each type.
-fn update_item_count(
-    mut item_count: ResMut<ItemCount>,
-    input_inventory_query: Query<&InputInventory>,
-    output_inventory_query: Query<&OutputInventory>,
-    storage_inventory_query: Query<&StorageInventory>,
-    unit_inventory_query: Query<&UnitInventory>,
-    litter_query: Query<&Litter>,
-) {
-    // Reset the item count
-    item_count.map.clear();
-
-    for inventory in input_inventory_query.iter() {
-        for item_slot in inventory.iter() {
-            *item_count.map.entry(item_slot.item_id()).or_default() += item_slot.count();
-        }
-    }
-
-    for inventory in output_inventory_query.iter() {
-        for item_slot in inventory.iter() {
-            *item_count.map.entry(item_slot.item_id()).or_default() += item_slot.count();
-        }
-    }
-
-    for inventory in storage_inventory_query.iter() {
-        for item_slot in inventory.iter() {
-            *item_count.map.entry(item_slot.item_id()).or_default() += item_slot.count();
-        }
-    }
-
-    for inventory in unit_inventory_query.iter() {
-        for item_id in inventory.iter() {
-            *item_count.map.entry(*item_id).or_default() += 1;
-        }
-    }
-
-    for litter in litter_query.iter() {
-        for item_slot in litter.contents.iter() {
-            *item_count.map.entry(item_slot.item_id()).or_default() += item_slot.count();
-        }
-    }
-}
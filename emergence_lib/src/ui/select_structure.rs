@@ -6,18 +6,21 @@ use crate::{
     graphics::palette::ui::{MENU_HIGHLIGHT_COLOR, MENU_NEUTRAL_COLOR},
     player_interaction::{
         clipboard::{ClipboardData, Tool},
-        PlayerAction,
+        consume_player_actions, PlayerAction,
     },
-    structures::structure_manifest::{Structure, StructureManifest},
+    simulation::milestones::Milestones,
+    structures::structure_manifest::{Structure, StructureManifest, StructureSearch},
 };
 
-use itertools::Itertools;
-
 use bevy::prelude::*;
+use leafwing_input_manager::{plugin::InputManagerSystem, prelude::ActionState};
 
-use super::wheel_menu::{
-    select_hex, spawn_hex_menu, AvailableChoices, Choice, HexMenu, HexMenuArrangement,
-    HexMenuElement, HexMenuError,
+use super::{
+    ui_assets::{Icons, UiElements},
+    wheel_menu::{
+        select_hex, spawn_hex_menu, spawn_hex_menu_at, AvailableChoices, Choice, HexMenu,
+        HexMenuArrangement, HexMenuElement, HexMenuError, HEX_MENU_CAPACITY,
+    },
 };
 
 /// Logic used to let users select the structure to build.
@@ -26,6 +29,7 @@ pub(super) struct SelectStructurePlugin;
 impl Plugin for SelectStructurePlugin {
     fn build(&self, app: &mut App) {
         app.init_resource::<AvailableChoices<Id<Structure>>>()
+            .init_resource::<StructureSearchQuery>()
             .add_systems(
                 (update_structure_choices, spawn_hex_menu::<Id<Structure>>)
                     .chain()
@@ -35,6 +39,17 @@ impl Plugin for SelectStructurePlugin {
                 select_hex
                     .pipe(handle_selection)
                     .run_if(resource_exists::<HexMenuArrangement<Id<Structure>>>()),
+            )
+            .add_system(
+                type_structure_search
+                    .before(update_structure_choices)
+                    .run_if(resource_exists::<HexMenuArrangement<Id<Structure>>>()),
+            )
+            .add_system(
+                block_actions_while_searching
+                    .in_base_set(CoreSet::PreUpdate)
+                    .after(InputManagerSystem::Update)
+                    .run_if(resource_exists::<HexMenuArrangement<Id<Structure>>>()),
             );
     }
 }
@@ -43,19 +58,93 @@ impl Choice for Id<Structure> {
     const ACTIVATION: PlayerAction = PlayerAction::SelectStructure;
 }
 
-/// Update the set of choices available to build whenever the structure manifest is updated
+/// The text typed by the player while the structure menu is open, used to filter its choices.
+#[derive(Resource, Debug, Default, Clone, PartialEq, Eq)]
+struct StructureSearchQuery(String);
+
+/// The structures that should be offered in the menu, given the player's `query`.
+///
+/// An empty query offers every structure, in the same alphabetical order used to break ties between search results.
+fn structure_choices(
+    structure_manifest: &StructureManifest,
+    milestones: &Milestones,
+    query: &StructureSearchQuery,
+) -> Vec<Id<Structure>> {
+    let limit = match query.0.is_empty() {
+        true => usize::MAX,
+        false => HEX_MENU_CAPACITY,
+    };
+
+    StructureSearch::new(structure_manifest, milestones)
+        .search(&query.0, limit)
+        .into_iter()
+        .map(|(structure_id, _score)| structure_id)
+        .collect()
+}
+
+/// Stops keys typed into the structure search from also triggering their keybindings.
+///
+/// The key that opened the menu is left alone, as releasing it makes the selection.
+fn block_actions_while_searching(mut actions: ResMut<ActionState<PlayerAction>>) {
+    consume_player_actions(&mut actions, &[Id::<Structure>::ACTIVATION]);
+}
+
+/// Update the set of choices available to build whenever the structure manifest, the completed milestones or the search query change
 fn update_structure_choices(
     mut available_choices: ResMut<AvailableChoices<Id<Structure>>>,
     structure_manifest: Res<StructureManifest>,
+    milestones: Res<Milestones>,
+    search_query: Res<StructureSearchQuery>,
+) {
+    if structure_manifest.is_changed() || milestones.is_changed() || search_query.is_changed() {
+        available_choices.choices =
+            structure_choices(&structure_manifest, &milestones, &search_query);
+    }
+}
+
+/// Filters the open structure menu by the text the player types, rebuilding the menu in place.
+fn type_structure_search(
+    mut received_characters: EventReader<ReceivedCharacter>,
+    mut search_query: ResMut<StructureSearchQuery>,
+    mut available_choices: ResMut<AvailableChoices<Id<Structure>>>,
+    structure_manifest: Res<StructureManifest>,
+    milestones: Res<Milestones>,
+    menu_query: Query<Entity, With<HexMenu>>,
+    arrangement: Res<HexMenuArrangement<Id<Structure>>>,
+    ui_elements: Res<UiElements>,
+    icons: Res<Icons<Id<Structure>>>,
+    mut commands: Commands,
 ) {
-    if structure_manifest.is_changed() {
-        // Sort to ensure a stable ordering
-        available_choices.choices = structure_manifest
-            .prototypes()
-            .into_iter()
-            .sorted()
-            .collect();
+    let mut query = search_query.0.clone();
+    for received_character in received_characters.iter() {
+        match received_character.char {
+            // Backspace
+            '\u{8}' => {
+                query.pop();
+            }
+            character if character.is_control() => (),
+            character => query.extend(character.to_lowercase()),
+        }
+    }
+
+    if query == search_query.0 {
+        return;
     }
+
+    search_query.0 = query;
+    available_choices.choices = structure_choices(&structure_manifest, &milestones, &search_query);
+
+    for entity in menu_query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+
+    spawn_hex_menu_at(
+        &mut commands,
+        arrangement.origin(),
+        &ui_elements,
+        &available_choices,
+        &icons,
+    );
 }
 
 /// Set the selected structure based on the results of the hex menu.
@@ -65,16 +154,22 @@ fn handle_selection(
     menu_query: Query<Entity, With<HexMenu>>,
     mut background_query: Query<&mut BackgroundColor, With<HexMenu>>,
     structure_manifest: Res<StructureManifest>,
+    mut search_query: ResMut<StructureSearchQuery>,
     commands: Commands,
     arrangement: Res<HexMenuArrangement<Id<Structure>>>,
 ) {
     /// Clean up the menu when we are done with it
-    fn cleanup(mut commands: Commands, menu_query: Query<Entity, With<HexMenu>>) {
+    fn cleanup(
+        mut commands: Commands,
+        menu_query: Query<Entity, With<HexMenu>>,
+        search_query: &mut StructureSearchQuery,
+    ) {
         for entity in menu_query.iter() {
             commands.entity(entity).despawn_recursive();
         }
 
         commands.remove_resource::<HexMenuArrangement<Id<Structure>>>();
+        search_query.0.clear();
     }
 
     match result {
@@ -90,7 +185,7 @@ fn handle_selection(
                 };
 
                 tool.set_to_structure(Some(structure_data));
-                cleanup(commands, menu_query);
+                cleanup(commands, menu_query, &mut search_query);
             } else {
                 for (&background_hex, &background_entity) in arrangement.background_map() {
                     if let Ok(mut background_color) = background_query.get_mut(background_entity) {
@@ -107,7 +202,7 @@ fn handle_selection(
             tool.set_to_structure(None);
 
             if complete {
-                cleanup(commands, menu_query);
+                cleanup(commands, menu_query, &mut search_query);
             } else {
                 for &background_entity in arrangement.background_map().values() {
                     if let Ok(mut background_color) = background_query.get_mut(background_entity) {
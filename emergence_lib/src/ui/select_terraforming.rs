@@ -56,7 +56,6 @@ fn update_terraforming_choices(
         #[allow(clippy::redundant_closure)]
         let terrain_choices = terrain_manifest
             .variants()
-            .into_iter()
             .sorted()
             .map(|terrain_id| TerraformingTool::Change(terrain_id));
         available_choices.choices.extend(terrain_choices);
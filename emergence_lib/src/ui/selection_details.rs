@@ -21,6 +21,7 @@ use crate::{
 
 use self::{
     ghost_structure_details::{GhostStructureDetails, GhostStructureDetailsQuery},
+    multiple_structure_details::MultipleStructureDetails,
     organism_details::{OrganismDetails, OrganismDetailsQuery},
     structure_details::{StructureDetails, StructureDetailsQuery},
     terrain_details::{TerrainDetails, TerrainDetailsQuery},
@@ -192,7 +193,7 @@ fn update_selection_details(
             terrain_style.display = Display::None;
             unit_style.display = Display::None;
         }
-        SelectionDetails::Structure(_) => {
+        SelectionDetails::Structure(_) | SelectionDetails::MultipleStructures(_) => {
             *parent_visibility = Visibility::Visible;
             ghost_structure_style.display = Display::None;
             structure_style.display = Display::Flex;
@@ -233,6 +234,10 @@ fn update_selection_details(
                 &unit_manifest,
             );
         }
+        SelectionDetails::MultipleStructures(details) => {
+            structure_text.sections[0].value =
+                details.display(&recipe_manifest, &structure_manifest);
+        }
         SelectionDetails::Terrain(details) => {
             terrain_text.sections[0].value = details.display(
                 &terrain_manifest,
@@ -282,7 +287,11 @@ pub(crate) enum SelectionDetails {
     /// A ghost of a structure is selected
     GhostStructure(GhostStructureDetails),
     /// A structure is selected
-    Structure(StructureDetails),
+    ///
+    /// This is boxed, as the copied flow statistics make the details much larger than any other variant.
+    Structure(Box<StructureDetails>),
+    /// Several structures are selected at once.
+    MultipleStructures(MultipleStructureDetails),
     /// A tile is selected.
     Terrain(TerrainDetails),
     /// A unit is selected
@@ -315,16 +324,30 @@ fn get_details(
                 structure_id: *ghost_query_item.structure_id,
                 input_inventory: ghost_query_item.input_inventory.clone(),
                 crafting_state: ghost_query_item.crafting_state.clone(),
+                construction_phase: *ghost_query_item.construction_phase,
                 active_recipe: ghost_query_item.active_recipe.clone(),
             })
         }
+        CurrentSelection::MultipleStructures(structure_entities) => {
+            // Members of the selection may have been demolished since it was made
+            let mut details = MultipleStructureDetails::default();
+            for structure_query_item in structure_query.iter_many(structure_entities) {
+                details.add(&structure_query_item);
+            }
+
+            match details.is_empty() {
+                true => SelectionDetails::None,
+                false => SelectionDetails::MultipleStructures(details),
+            }
+        }
         CurrentSelection::Structure(structure_entity) => {
-            let structure_query_item = structure_query.get(*structure_entity)?;
+            let structure_entity = *structure_entity;
+            let structure_query_item = structure_query.get(structure_entity)?;
 
             // Not all structures are organisms
             let maybe_organism_details =
                 organism_query
-                    .get(*structure_entity)
+                    .get(structure_entity)
                     .ok()
                     .map(|query_item| OrganismDetails {
                         prototypical_form: structure_manifest
@@ -337,21 +360,27 @@ fn get_details(
                         oxygen_pool: query_item.oxygen_pool.clone(),
                     });
 
-            SelectionDetails::Structure(StructureDetails {
+            SelectionDetails::Structure(Box::new(StructureDetails {
                 entity: structure_query_item.entity,
                 voxel_pos: *structure_query_item.voxel_pos,
                 structure_id: *structure_query_item.structure_id,
                 maybe_organism_details,
                 marked_for_removal: structure_query_item.marked_for_removal.is_some(),
+                activity: structure_query_item.activity.copied().unwrap_or_default(),
                 emitter: structure_query_item.emitter.cloned(),
                 storage_inventory: structure_query_item.storage_inventory.cloned(),
                 input_inventory: structure_query_item.input_inventory.cloned(),
                 output_inventory: structure_query_item.output_inventory.cloned(),
                 crafting_state: structure_query_item.crafting_state.cloned(),
+                crafting_paused: structure_query_item.crafting_paused.is_some(),
                 active_recipe: structure_query_item.active_recipe.cloned(),
                 workers_present: structure_query_item.workers_present.cloned(),
+                craft_speed: structure_query_item.craft_speed.cloned(),
+                flow_stats: structure_query_item.flow_stats.cloned(),
+                throughput_tracker: structure_query_item.throughput_tracker.cloned(),
+                logistic_status: structure_query_item.logistic_status.copied(),
                 vegetative_reproduction: structure_query_item.vegetative_reproduction.cloned(),
-            })
+            }))
         }
         CurrentSelection::Terrain(selected_tiles) => {
             // FIXME: display info about multiple tiles correctly
@@ -434,6 +463,7 @@ mod ghost_structure_details {
 
     use crate::{
         asset_management::manifest::Id,
+        construction::ghosts::ConstructionPhase,
         crafting::{
             inventories::{CraftingState, InputInventory},
             recipe::{ActiveRecipe, RecipeManifest},
@@ -457,6 +487,8 @@ mod ghost_structure_details {
         pub(super) input_inventory: &'static InputInventory,
         /// The ghost's progress through construction
         pub(crate) crafting_state: &'static CraftingState,
+        /// Is the terrain beneath the ghost still being terraformed?
+        pub(super) construction_phase: &'static ConstructionPhase,
         /// The signal emitter
         pub(super) emitter: &'static Emitter,
         /// The recipe that will be crafted when the structure is first built
@@ -476,6 +508,8 @@ mod ghost_structure_details {
         pub(super) input_inventory: InputInventory,
         /// The ghost's progress through construction
         pub(super) crafting_state: CraftingState,
+        /// Is the terrain beneath the ghost still being terraformed?
+        pub(super) construction_phase: ConstructionPhase,
         /// The recipe that will be crafted when the structure is first built
         pub(super) active_recipe: ActiveRecipe,
     }
@@ -492,6 +526,7 @@ mod ghost_structure_details {
             let structure_id = structure_manifest.name(self.structure_id);
             let voxel_pos = &self.voxel_pos;
             let crafting_state = &self.crafting_state;
+            let construction_phase = &self.construction_phase;
             let recipe = self.active_recipe.display(recipe_manifest);
             let construction_materials = self.input_inventory.display(item_manifest);
 
@@ -501,6 +536,7 @@ Tile: {voxel_pos}
 Ghost structure type: {structure_id}
 Recipe: {recipe}
 Construction materials: {construction_materials}
+Construction phase: {construction_phase}
 {crafting_state}"
             )
         }
@@ -569,21 +605,29 @@ Oxygen: {oxygen_pool}"
 /// Details for structures
 mod structure_details {
     use bevy::ecs::{prelude::*, query::WorldQuery};
+    use itertools::Itertools;
 
     use super::organism_details::OrganismDetails;
     use crate::{
         asset_management::manifest::Id,
         construction::demolition::MarkedForDemolition,
         crafting::{
+            flow::{FlowStats, ThroughputTracker},
             inventories::{CraftingState, InputInventory, OutputInventory, StorageInventory},
             recipe::{ActiveRecipe, RecipeManifest},
+            speed::CraftSpeed,
             workers::WorkersPresent,
+            CraftingPaused,
         },
         geometry::VoxelPos,
         items::item_manifest::ItemManifest,
         organisms::vegetative_reproduction::VegetativeReproduction,
         signals::Emitter,
-        structures::structure_manifest::{Structure, StructureManifest},
+        structures::{
+            logistic_buildings::LogisticStatus,
+            structure_manifest::{Structure, StructureManifest},
+            StructureActivity,
+        },
         terrain::terrain_manifest::TerrainManifest,
         units::unit_manifest::UnitManifest,
         water::emitters::WaterEmitter,
@@ -608,10 +652,22 @@ mod structure_details {
         pub(crate) active_recipe: Option<&'static ActiveRecipe>,
         /// The state of the ongoing crafting process.
         pub(crate) crafting_state: Option<&'static CraftingState>,
+        /// Has crafting been paused at this structure?
+        pub(super) crafting_paused: Option<&'static CraftingPaused>,
         /// The workers present at this structure.
         pub(crate) workers_present: Option<&'static WorkersPresent>,
+        /// How quickly this structure is crafting.
+        pub(crate) craft_speed: Option<&'static CraftSpeed>,
+        /// How quickly this structure is producing and consuming items.
+        pub(crate) flow_stats: Option<&'static FlowStats>,
+        /// The recent net flow of items through this structure.
+        pub(crate) throughput_tracker: Option<&'static ThroughputTracker>,
+        /// Is this logistic building moving items, or is it stuck?
+        pub(crate) logistic_status: Option<&'static LogisticStatus>,
         /// Is this structure marked for removal?
         pub(super) marked_for_removal: Option<&'static MarkedForDemolition>,
+        /// Is this structure working, or has it been paused?
+        pub(super) activity: Option<&'static StructureActivity>,
         /// What signals is this structure emitting?
         pub(crate) emitter: Option<&'static Emitter>,
         /// How much water is emitted by this structure?
@@ -633,6 +689,8 @@ mod structure_details {
         pub(crate) maybe_organism_details: Option<OrganismDetails>,
         /// Is this structure slated for removal?
         pub(crate) marked_for_removal: bool,
+        /// Is this structure working, or has it been paused?
+        pub(crate) activity: StructureActivity,
         /// What signals is this structure emitting?
         pub(crate) emitter: Option<Emitter>,
         /// The inventory for the input items.
@@ -645,8 +703,18 @@ mod structure_details {
         pub(crate) active_recipe: Option<ActiveRecipe>,
         /// The state of the ongoing crafting process.
         pub(crate) crafting_state: Option<CraftingState>,
+        /// Has crafting been paused at this structure?
+        pub(crate) crafting_paused: bool,
         /// The number of workers that are presently working on this.
         pub(crate) workers_present: Option<WorkersPresent>,
+        /// How quickly this structure is crafting, and why.
+        pub(crate) craft_speed: Option<CraftSpeed>,
+        /// How quickly this structure is producing and consuming items.
+        pub(crate) flow_stats: Option<FlowStats>,
+        /// The recent net flow of items through this structure.
+        pub(crate) throughput_tracker: Option<ThroughputTracker>,
+        /// Is this logistic building moving items, or is it stuck?
+        pub(crate) logistic_status: Option<LogisticStatus>,
         /// The vegetative reproduction strategy, if any.
         pub(crate) vegetative_reproduction: Option<VegetativeReproduction>,
     }
@@ -687,8 +755,22 @@ Height: {height}"
                 string += "\nMarked for removal!";
             }
 
+            string += &format!("\nActivity: {}", self.activity);
+
             if let Some(storage) = &self.storage_inventory {
                 string += &format!("\nStoring: {}", storage.display(item_manifest));
+
+                if !storage.stock_floor.is_empty() {
+                    let floors = storage
+                        .stock_floor
+                        .iter()
+                        .map(|(&item_id, floor)| {
+                            format!("{} ({floor})", item_manifest.name(item_id))
+                        })
+                        .sorted()
+                        .join(", ");
+                    string += &format!("\nStock floor: {floors}");
+                }
             }
 
             if let Some(input) = &self.input_inventory {
@@ -711,12 +793,42 @@ Height: {height}"
 
             if let Some(crafting_state) = &self.crafting_state {
                 string += &format!("\nCrafting state: {crafting_state}");
+                if self.crafting_paused {
+                    string += " (paused)";
+                }
             }
 
             if let Some(workers_present) = &self.workers_present {
                 string += &format!("\nWorkers present: {workers_present}");
             }
 
+            if let Some(craft_speed) = &self.craft_speed {
+                string += &format!("\nCrafting speed: {craft_speed}");
+                if let Some(recipe_id) = self
+                    .active_recipe
+                    .as_ref()
+                    .and_then(|active_recipe| *active_recipe.recipe_id())
+                {
+                    let craft_time = recipe_manifest.get(recipe_id).craft_time;
+                    string += &match craft_speed.craft_duration(craft_time) {
+                        Some(duration) => format!(" ({:.1} s per craft)", duration.as_secs_f32()),
+                        None => " (stalled)".to_string(),
+                    };
+                }
+            }
+
+            if let Some(flow_stats) = &self.flow_stats {
+                string += &format!("\nThroughput: {flow_stats}");
+            }
+
+            if let Some(throughput_tracker) = &self.throughput_tracker {
+                string += &format!("\nNet flow: {throughput_tracker}");
+            }
+
+            if let Some(logistic_status) = &self.logistic_status {
+                string += &format!("\nLogistics: {logistic_status}");
+            }
+
             if let Some(root_zone) = &structure_manifest.get(self.structure_id).root_zone {
                 string += &format!("\n{root_zone}",);
             }
@@ -734,6 +846,132 @@ Height: {height}"
     }
 }
 
+/// Details for several structures selected at once
+mod multiple_structure_details {
+    use bevy::utils::HashMap;
+    use itertools::Itertools;
+
+    use super::structure_details::StructureDetailsQueryItem;
+    use crate::{
+        asset_management::manifest::Id,
+        crafting::recipe::{Recipe, RecipeManifest},
+        structures::{
+            structure_manifest::{Structure, StructureManifest},
+            StructureActivity,
+        },
+    };
+
+    /// A summary of the structures in a multi-selection.
+    ///
+    /// Actions taken while several structures are selected apply to all of them,
+    /// so this shows what they have in common rather than the details of any one structure.
+    #[derive(Debug, Default)]
+    pub(crate) struct MultipleStructureDetails {
+        /// The number of selected structures of each type.
+        pub(crate) structure_counts: HashMap<Id<Structure>, usize>,
+        /// The number of selected crafting structures currently using each recipe.
+        pub(crate) recipe_counts: HashMap<Option<Id<Recipe>>, usize>,
+        /// The number of selected structures that have been paused.
+        pub(crate) n_paused: usize,
+        /// The number of selected structures whose crafting has been paused.
+        pub(crate) n_crafting_paused: usize,
+        /// The number of selected structures that are slated for removal.
+        pub(crate) n_marked_for_removal: usize,
+    }
+
+    impl MultipleStructureDetails {
+        /// Adds a selected structure to the summary.
+        pub(super) fn add(&mut self, structure_query_item: &StructureDetailsQueryItem) {
+            *self
+                .structure_counts
+                .entry(*structure_query_item.structure_id)
+                .or_default() += 1;
+
+            if let Some(active_recipe) = structure_query_item.active_recipe {
+                *self
+                    .recipe_counts
+                    .entry(*active_recipe.recipe_id())
+                    .or_default() += 1;
+            }
+
+            if StructureActivity::is_paused(structure_query_item.activity) {
+                self.n_paused += 1;
+            }
+
+            if structure_query_item.crafting_paused.is_some() {
+                self.n_crafting_paused += 1;
+            }
+
+            if structure_query_item.marked_for_removal.is_some() {
+                self.n_marked_for_removal += 1;
+            }
+        }
+
+        /// The total number of selected structures.
+        pub(crate) fn len(&self) -> usize {
+            self.structure_counts.values().sum()
+        }
+
+        /// Are no structures summarized?
+        pub(crate) fn is_empty(&self) -> bool {
+            self.structure_counts.is_empty()
+        }
+
+        /// The pretty formatting for this type
+        pub(crate) fn display(
+            &self,
+            recipe_manifest: &RecipeManifest,
+            structure_manifest: &StructureManifest,
+        ) -> String {
+            let structure_types = self
+                .structure_counts
+                .iter()
+                .map(|(&structure_id, count)| {
+                    format!("{} ({count})", structure_manifest.name(structure_id))
+                })
+                .sorted()
+                .join(", ");
+
+            let mut string = format!(
+                "Selected structures: {}
+Structure types: {structure_types}",
+                self.len()
+            );
+
+            if !self.recipe_counts.is_empty() {
+                let recipes = self
+                    .recipe_counts
+                    .iter()
+                    .map(|(maybe_recipe_id, count)| match maybe_recipe_id {
+                        Some(recipe_id) => {
+                            format!("{} ({count})", recipe_manifest.name(*recipe_id))
+                        }
+                        None => format!("None ({count})"),
+                    })
+                    .sorted()
+                    .join(", ");
+                string += &format!("\nRecipes: {recipes}");
+            }
+
+            if self.n_paused > 0 {
+                string += &format!("\nPaused: {}", self.n_paused);
+            }
+
+            if self.n_crafting_paused > 0 {
+                string += &format!("\nCrafting paused: {}", self.n_crafting_paused);
+            }
+
+            if self.n_marked_for_removal > 0 {
+                string += &format!("\nMarked for removal: {}", self.n_marked_for_removal);
+            }
+
+            string += "\nActions apply to every selected structure.";
+
+            string
+        }
+    }
+}
+
 /// Details for terrain
 mod terrain_details {
     use bevy::ecs::{prelude::*, query::WorldQuery};
@@ -207,6 +207,8 @@ impl FromWorld for Icons<GoalKind> {
             GoalKind::Breathe,
             asset_server.load("icons/goals/breathe.png"),
         );
+        // Resting units amble back to a nest and idle there, so they share the wandering icon
+        map.insert(GoalKind::Rest, asset_server.load("icons/goals/wander.png"));
 
         Icons { map }
     }
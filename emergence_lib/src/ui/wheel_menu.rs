@@ -14,6 +14,9 @@ use core::hash::Hash;
 
 use super::ui_assets::{Icons, UiElements, CHOICE_ICON_SIZE};
 
+/// The number of choices that fit in the two rings around the center of a hex menu.
+pub(super) const HEX_MENU_CAPACITY: usize = 18;
+
 /// A marker component for any element of a hex menu.
 #[derive(Component)]
 pub(super) struct HexMenu;
@@ -61,6 +64,11 @@ impl<D: Clone> HexMenuArrangement<D> {
     pub(super) fn background_map(&self) -> &HashMap<Hex, Entity> {
         &self.background_map
     }
+
+    /// The screen position that the menu is centered on.
+    pub(super) fn origin(&self) -> Vec2 {
+        self.layout.origin
+    }
 }
 
 /// The data corresponding to one element of the hex menu.
@@ -153,61 +161,77 @@ pub(super) fn spawn_hex_menu<D: Choice>(
 ) {
     if actions.just_pressed(D::ACTIVATION) {
         if let Some(cursor_pos) = cursor_pos.maybe_screen_pos() {
-            let mut arrangement = HexMenuArrangement {
-                content_map: HashMap::default(),
-                icon_map: HashMap::default(),
-                background_map: HashMap::default(),
-                layout: HexLayout {
-                    orientation: HexOrientation::Pointy,
-                    origin: cursor_pos,
-                    hex_size: Vec2 {
-                        x: CHOICE_ICON_SIZE,
-                        y: CHOICE_ICON_SIZE,
-                    },
-                },
-            };
-
-            // Any larger than this is quite unwieldy
-            let range = 3;
-
-            // Center is reserved for easy cancellation.
-            let mut hexes =
-                Hex::ZERO.custom_spiral_range(1..range, hexx::Direction::BottomRight, true);
-
-            let variants: Vec<_> = Vec::from_iter(available_choices.choices.iter().cloned());
-
-            for data in variants {
-                if let Some(hex) = hexes.next() {
-                    // Content
-                    arrangement.content_map.insert(hex, data.clone());
-                    // Icon
-                    let icon_entity = commands
-                        .spawn(HexMenuIconBundle::new(
-                            data,
-                            hex,
-                            &icons,
-                            &arrangement.layout,
-                        ))
-                        .id();
-                    arrangement.icon_map.insert(hex, icon_entity);
-                    // Background
-                    let background_entity = commands
-                        .spawn(HexMenuBackgroundBundle::new(
-                            hex,
-                            &arrangement.layout,
-                            &ui_elements.hex_menu_background,
-                        ))
-                        .id();
-                    arrangement.background_map.insert(hex, background_entity);
-                } else {
-                    // Just give up rather than panic if too many entries are found
-                    warn!("Too many entries in hex menu!");
-                }
-            }
+            spawn_hex_menu_at(
+                &mut commands,
+                cursor_pos,
+                &ui_elements,
+                &available_choices,
+                &icons,
+            );
+        }
+    }
+}
 
-            commands.insert_resource(arrangement);
+/// Spawns a hex menu of the `available_choices` centered on `origin`, in screen coordinates.
+pub(super) fn spawn_hex_menu_at<D: Choice>(
+    commands: &mut Commands,
+    origin: Vec2,
+    ui_elements: &UiElements,
+    available_choices: &AvailableChoices<D>,
+    icons: &Icons<D>,
+) {
+    let mut arrangement = HexMenuArrangement {
+        content_map: HashMap::default(),
+        icon_map: HashMap::default(),
+        background_map: HashMap::default(),
+        layout: HexLayout {
+            orientation: HexOrientation::Pointy,
+            origin,
+            hex_size: Vec2 {
+                x: CHOICE_ICON_SIZE,
+                y: CHOICE_ICON_SIZE,
+            },
+        },
+    };
+
+    // Any larger than this is quite unwieldy
+    let range = 3;
+
+    // Center is reserved for easy cancellation.
+    let mut hexes = Hex::ZERO.custom_spiral_range(1..range, hexx::Direction::BottomRight, true);
+
+    let variants: Vec<_> = Vec::from_iter(available_choices.choices.iter().cloned());
+
+    for data in variants {
+        if let Some(hex) = hexes.next() {
+            // Content
+            arrangement.content_map.insert(hex, data.clone());
+            // Icon
+            let icon_entity = commands
+                .spawn(HexMenuIconBundle::new(
+                    data,
+                    hex,
+                    icons,
+                    &arrangement.layout,
+                ))
+                .id();
+            arrangement.icon_map.insert(hex, icon_entity);
+            // Background
+            let background_entity = commands
+                .spawn(HexMenuBackgroundBundle::new(
+                    hex,
+                    &arrangement.layout,
+                    &ui_elements.hex_menu_background,
+                ))
+                .id();
+            arrangement.background_map.insert(hex, background_entity);
+        } else {
+            // Just give up rather than panic if too many entries are found
+            warn!("Too many entries in hex menu!");
         }
     }
+
+    commands.insert_resource(arrangement);
 }
 
 /// The icon stored presented in a hex menu
@@ -16,23 +16,26 @@ use crate::{
         terraform::TerraformingAction,
     },
     crafting::{
-        inventories::{
-            AddToInputError, CraftingState, InputInventory, OutputInventory, StorageInventory,
-        },
+        inventories::{CraftingState, InputInventory, OutputInventory, StorageInventory},
         item_tags::ItemKind,
         workers::WorkersPresent,
+        CraftingPaused,
     },
     geometry::{Facing, Height, MapGeometry, RotationDirection, VoxelPos},
-    items::{errors::AddOneItemError, item_manifest::ItemManifest, ItemCount},
+    items::item_manifest::ItemManifest,
     litter::{Litter, LitterCommandsExt},
     organisms::{energy::EnergyPool, lifecycle::Lifecycle},
     signals::{SignalType, Signals},
-    structures::{commands::StructureCommandsExt, structure_manifest::Structure},
+    structures::{
+        commands::StructureCommandsExt, nests::Nest, relocation::PendingRelocation,
+        storage_groups::StorageGroups, structure_manifest::Structure,
+    },
     terrain::terrain_manifest::{Terrain, TerrainManifest},
     water::WaterDepth,
 };
 
 use super::{
+    fatigue::nest_within_reach,
     goals::Goal,
     impatience::ImpatiencePool,
     item_interaction::UnitInventory,
@@ -51,10 +54,25 @@ pub(super) fn advance_action_timer(
     }
 }
 
+/// Recounts the units standing in each voxel, so units that have spawned or died are accounted for.
+///
+/// Units that move are tracked as they go, in [`finish_actions`].
+pub(super) fn count_occupants(
+    unit_query: Query<&VoxelPos, With<Id<Unit>>>,
+    mut map_geometry: ResMut<MapGeometry>,
+) {
+    // Occupancy is transient, and should not trigger systems that respond to changes in the map itself
+    map_geometry
+        .bypass_change_detection()
+        .count_occupants(unit_query.iter().copied());
+}
+
 /// Choose the unit's action for this turn
 pub(super) fn choose_actions(
     mut units_query: Query<
         (
+            Entity,
+            &Id<Unit>,
             &VoxelPos,
             &Facing,
             &Goal,
@@ -75,14 +93,19 @@ pub(super) fn choose_actions(
     terrain_query: Query<&Id<Terrain>>,
     litter_query: Query<&Litter>,
     water_depth_query: Query<&WaterDepth>,
+    nest_query: Query<&Nest>,
     terrain_manifest: Res<TerrainManifest>,
     item_manifest: Res<ItemManifest>,
+    unit_manifest: Res<UnitManifest>,
 ) {
     let rng = &mut thread_rng();
 
-    for (&unit_pos, facing, goal, mut current_action, unit_inventory) in units_query.iter_mut() {
+    for (unit_entity, &unit_id, &unit_pos, facing, goal, mut current_action, unit_inventory) in
+        units_query.iter_mut()
+    {
         if current_action.finished() {
             let previous_action = current_action.action.clone();
+            let can_swim = unit_manifest.get(unit_id).can_swim;
 
             *current_action = match goal {
                 // Drop whatever you're holding before wandering further
@@ -112,8 +135,8 @@ pub(super) fn choose_actions(
                 | Goal::Store(item_kind)
                 | Goal::Remove(item_kind) => {
                     // If we're holding the wrong thing, drop it.
-                    if unit_inventory.is_some()
-                        && !item_kind.matches(unit_inventory.unwrap(), &item_manifest)
+                    if unit_inventory.held_item.is_some()
+                        && !item_kind.matches(unit_inventory.held_item.unwrap(), &item_manifest)
                     {
                         CurrentAction::abandon(
                             previous_action,
@@ -139,6 +162,7 @@ pub(super) fn choose_actions(
                             &storage_inventory_query,
                             &litter_query,
                             &signals,
+                            can_swim,
                             rng,
                             &item_manifest,
                             &terrain_query,
@@ -177,6 +201,7 @@ pub(super) fn choose_actions(
                             &storage_inventory_query,
                             &litter_query,
                             &signals,
+                            can_swim,
                             rng,
                             &item_manifest,
                             &terrain_query,
@@ -191,6 +216,7 @@ pub(super) fn choose_actions(
                     facing,
                     &workplace_query,
                     &signals,
+                    can_swim,
                     rng,
                     &terrain_query,
                     &terrain_manifest,
@@ -203,6 +229,7 @@ pub(super) fn choose_actions(
                     facing,
                     &demolition_query,
                     &signals,
+                    can_swim,
                     rng,
                     &item_manifest,
                     &terrain_query,
@@ -214,6 +241,7 @@ pub(super) fn choose_actions(
                     unit_pos,
                     facing,
                     &signals,
+                    can_swim,
                     &item_manifest,
                     &terrain_query,
                     &terrain_manifest,
@@ -228,6 +256,32 @@ pub(super) fn choose_actions(
                     &map_geometry,
                     rng,
                 ),
+                // Put down whatever you're holding before settling in to rest
+                Goal::Rest { .. } => match unit_inventory.held_item {
+                    Some(_) => CurrentAction::abandon(
+                        previous_action,
+                        unit_pos,
+                        unit_inventory,
+                        &map_geometry,
+                        &terrain_manifest,
+                        &terrain_query,
+                        facing,
+                        rng,
+                    ),
+                    None => CurrentAction::find_nest(
+                        unit_entity,
+                        unit_pos,
+                        facing,
+                        goal,
+                        &nest_query,
+                        &signals,
+                        can_swim,
+                        &item_manifest,
+                        &terrain_query,
+                        &terrain_manifest,
+                        &map_geometry,
+                    ),
+                },
             }
         }
     }
@@ -266,13 +320,19 @@ pub(super) fn finish_actions(
             &mut Litter,
         )>,
     >,
-    mut workplace_query: Query<(&CraftingState, &mut WorkersPresent)>,
+    mut workplace_query: Query<(
+        Option<&CraftingState>,
+        &mut WorkersPresent,
+        Option<&PendingRelocation>,
+        Option<&CraftingPaused>,
+    )>,
     // This must be compatible with unit_query
     structure_query: Query<&VoxelPos, (With<Id<Structure>>, Without<Goal>)>,
     item_manifest: Res<ItemManifest>,
     unit_manifest: Res<UnitManifest>,
     signals: Res<Signals>,
-    map_geometry: Res<MapGeometry>,
+    mut map_geometry: ResMut<MapGeometry>,
+    storage_groups: Res<StorageGroups>,
     mut commands: Commands,
 ) {
     let item_manifest = &*item_manifest;
@@ -282,7 +342,7 @@ pub(super) fn finish_actions(
             // Take workers off of the job once actions complete
             if let Some(workplace_entity) = unit.action.action().workplace() {
                 if let Ok(workplace) = workplace_query.get_mut(workplace_entity) {
-                    let (.., mut workers_present) = workplace;
+                    let (_, mut workers_present, _, _) = workplace;
                     // FIXME: this isn't robust to units dying
                     workers_present.remove_worker(unit.entity);
                 }
@@ -313,7 +373,8 @@ pub(super) fn finish_actions(
                                     output_inventory.matching_item_id(*item_kind, item_manifest)
                                 } else if let Some(ref storage_inventory) = maybe_storage_inventory
                                 {
-                                    storage_inventory.matching_item_id(*item_kind, item_manifest)
+                                    storage_inventory
+                                        .withdrawable_item_id(*item_kind, item_manifest)
                                 } else if let Some(ref litter) = maybe_litter {
                                     litter.matching_item_id(*item_kind, item_manifest)
                                 } else {
@@ -322,48 +383,91 @@ pub(super) fn finish_actions(
                                 };
 
                                 if let Some(item_id) = maybe_item_id {
-                                    let item_count = ItemCount::new(item_id, 1);
+                                    let from_storage = maybe_output_inventory.is_none()
+                                        && maybe_storage_inventory.is_some();
+
+                                    // Storage can't be drawn below its stock floor
+                                    let withdrawal_limit =
+                                        match (&maybe_output_inventory, &maybe_storage_inventory) {
+                                            (None, Some(storage_inventory)) => {
+                                                storage_inventory.withdrawable_count(item_id)
+                                            }
+                                            _ => u32::MAX,
+                                        };
 
-                                    let transfer_result = match (
+                                    let source = match (
                                         &mut maybe_output_inventory,
                                         &mut maybe_storage_inventory,
                                         &mut maybe_litter,
                                     ) {
                                         (Some(ref mut output_inventory), _, _) => {
-                                            output_inventory.remove_item_all_or_nothing(&item_count)
+                                            &mut output_inventory.inventory
                                         }
                                         (_, Some(ref mut storage_inventory), _) => {
-                                            storage_inventory
-                                                .remove_item_all_or_nothing(&item_count)
+                                            &mut storage_inventory.inventory
                                         }
                                         (_, _, Some(ref mut litter)) => {
-                                            litter.remove_item_all_or_nothing(&item_count)
+                                            &mut litter.contents.inventory
                                         }
                                         // The entity must have either an output, storage or litter inventory
                                         _ => unreachable!(),
                                     };
 
-                                    // If our unit's all loaded, swap to delivering it
-                                    match transfer_result {
-                                        Ok(()) => {
-                                            unit.unit_inventory.held_item = Some(item_id);
-                                            if signals.detectable(
-                                                SignalType::item_signal_types(
-                                                    *item_kind,
-                                                    item_manifest,
-                                                    DeliveryMode::DropOff,
-                                                    Purpose::Instrumental,
-                                                ),
-                                                *unit.voxel_pos,
-                                            ) {
-                                                // If we can see any `Pull` signals of the right type, deliver the item.
-                                                Goal::Deliver(*item_kind)
-                                            } else {
-                                                // Otherwise, simply store it
-                                                Goal::Store(*item_kind)
+                                    // Grab as much as we can carry in a single trip
+                                    let full_carry_capacity =
+                                        unit_manifest.get(*unit.unit_id).carry_capacity;
+                                    let mut picked_up = unit.unit_inventory.pick_up_from(
+                                        source,
+                                        item_id,
+                                        full_carry_capacity.min(withdrawal_limit),
+                                    );
+
+                                    // Storage groups hand over whatever their other members hold, too
+                                    if from_storage {
+                                        for member in storage_groups
+                                            .deposit_order(*output_entity)
+                                            .into_iter()
+                                            .skip(1)
+                                        {
+                                            if unit.unit_inventory.held_count()
+                                                >= full_carry_capacity
+                                            {
+                                                break;
+                                            }
+
+                                            if let Ok((_, _, Some(mut member_inventory), _)) =
+                                                inventory_query.get_mut(member)
+                                            {
+                                                let member_limit = unit.unit_inventory.held_count()
+                                                    + member_inventory.withdrawable_count(item_id);
+                                                picked_up += unit.unit_inventory.pick_up_from(
+                                                    &mut member_inventory.inventory,
+                                                    item_id,
+                                                    full_carry_capacity.min(member_limit),
+                                                );
                                             }
                                         }
-                                        Err(..) => Goal::Fetch(*item_kind),
+                                    }
+
+                                    // If our unit's all loaded, swap to delivering it
+                                    if picked_up > 0 {
+                                        if signals.detectable(
+                                            SignalType::item_signal_types(
+                                                *item_kind,
+                                                item_manifest,
+                                                DeliveryMode::DropOff,
+                                                Purpose::Instrumental,
+                                            ),
+                                            *unit.voxel_pos,
+                                        ) {
+                                            // If we can see any `Pull` signals of the right type, deliver the item.
+                                            Goal::Deliver(*item_kind)
+                                        } else {
+                                            // Otherwise, simply store it
+                                            Goal::Store(*item_kind)
+                                        }
+                                    } else {
+                                        Goal::Fetch(*item_kind)
                                     }
                                 } else {
                                     unit.impatience.increment();
@@ -388,39 +492,59 @@ pub(super) fn finish_actions(
                             None => Goal::default(),
                             Some(held_item_id) => {
                                 if item_kind.matches(held_item_id, item_manifest) {
-                                    let item_count = ItemCount::new(held_item_id, 1);
-                                    let transfer_result = if let Some(mut input_inventory) =
-                                        maybe_input_inventory
-                                    {
-                                        input_inventory.fill_with_items(&item_count, item_manifest)
-                                    } else if let Some(mut storage_inventory) =
-                                        maybe_storage_inventory
-                                    {
-                                        let storage_result = storage_inventory
-                                            .add_item_all_or_nothing(&item_count, item_manifest);
-                                        match storage_result {
-                                            Ok(()) => Ok(()),
-                                            Err(AddOneItemError { excess_count }) => {
-                                                Err(AddToInputError::NotEnoughSpace {
-                                                    excess_count,
-                                                })
+                                    let mut deposited =
+                                        if let Some(mut input_inventory) = maybe_input_inventory {
+                                            if input_inventory
+                                                .currently_accepts(held_item_id, item_manifest)
+                                            {
+                                                unit.unit_inventory.deposit_into(
+                                                    input_inventory.inventory_mut(),
+                                                    item_manifest,
+                                                )
+                                            } else {
+                                                0
                                             }
+                                        } else if let Some(mut storage_inventory) =
+                                            maybe_storage_inventory
+                                        {
+                                            unit.unit_inventory.deposit_into(
+                                                &mut storage_inventory.inventory,
+                                                item_manifest,
+                                            )
+                                        } else {
+                                            unreachable!()
+                                        };
+
+                                    // Storage groups accept whatever did not fit into their other members
+                                    for member in storage_groups
+                                        .deposit_order(*input_entity)
+                                        .into_iter()
+                                        .skip(1)
+                                    {
+                                        if unit.unit_inventory.held_item.is_none() {
+                                            break;
                                         }
-                                    } else {
-                                        unreachable!()
-                                    };
 
-                                    // If our unit is unloaded, swap to wandering to find something else to do
-                                    match transfer_result {
-                                        Ok(()) => {
-                                            unit.unit_inventory.held_item = None;
-                                            Goal::default()
-                                        }
-                                        Err(..) => {
-                                            unit.impatience.increment();
-                                            Goal::Store(ItemKind::Single(held_item_id))
+                                        if let Ok((_, _, Some(mut member_inventory), _)) =
+                                            inventory_query.get_mut(member)
+                                        {
+                                            deposited += unit.unit_inventory.deposit_into(
+                                                &mut member_inventory.inventory,
+                                                item_manifest,
+                                            );
                                         }
                                     }
+
+                                    if deposited == 0 {
+                                        unit.impatience.increment();
+                                        Goal::Store(ItemKind::Single(held_item_id))
+                                    } else if unit.unit_inventory.held_item.is_none() {
+                                        // If our unit is unloaded, swap to wandering to find something else to do
+                                        Goal::default()
+                                    } else {
+                                        // Only part of the stack fit, so keep carrying the rest
+                                        unit.goal.clone()
+                                    }
                                 } else {
                                     // Somehow we're holding the wrong thing
                                     Goal::Store(ItemKind::Single(held_item_id))
@@ -441,17 +565,36 @@ pub(super) fn finish_actions(
                     if let Some(target_voxel) =
                         map_geometry.walkable_neighbor_in_direction(*unit.voxel_pos, direction)
                     {
-                        *unit.voxel_pos = target_voxel;
-                        unit.transform.translation = target_voxel.inside_voxel();
+                        // Another unit may have filled the tile since we started moving
+                        if map_geometry.has_room_for_unit(target_voxel) {
+                            // Occupancy is transient, and should not trigger systems that respond to changes in the map itself
+                            map_geometry
+                                .bypass_change_detection()
+                                .move_occupant(*unit.voxel_pos, target_voxel);
+                            *unit.voxel_pos = target_voxel;
+                            unit.transform.translation = target_voxel.inside_voxel();
+                        } else {
+                            unit.impatience.increment();
+                        }
                     }
                 }
                 UnitAction::Work { structure_entity } => {
                     let mut success = false;
 
-                    if let Ok((CraftingState::InProgress { .. }, workers_present)) =
-                        workplace_query.get_mut(*structure_entity)
+                    if let Ok((
+                        maybe_crafting_state,
+                        workers_present,
+                        maybe_relocation,
+                        maybe_crafting_paused,
+                    )) = workplace_query.get_mut(*structure_entity)
                     {
-                        if workers_present.needs_more() {
+                        let needs_work = workplace_needs_work(
+                            maybe_crafting_state,
+                            maybe_crafting_paused,
+                            maybe_relocation,
+                        );
+
+                        if needs_work && workers_present.needs_more() {
                             success = true;
                         }
                     }
@@ -464,7 +607,7 @@ pub(super) fn finish_actions(
                     if let Ok(&structure_tile_pos) = structure_query.get(*structure_entity) {
                         // FIXME: this doesn't work for structures that don't cover the origin of their footprint
                         // TODO: this should probably take time and use work?
-                        commands.despawn_structure(structure_tile_pos);
+                        commands.demolish_structure(structure_tile_pos);
                     }
 
                     // Whether we succeeded or failed, pick something else to do
@@ -477,7 +620,7 @@ pub(super) fn finish_actions(
                         let diet = &unit_data.diet;
 
                         if diet.item_kind().matches(held_item, item_manifest) {
-                            unit.unit_inventory.held_item = None;
+                            unit.unit_inventory.remove(1);
 
                             let proposed = unit.energy_pool.current() + diet.energy();
                             unit.energy_pool.set_current(proposed);
@@ -486,9 +629,10 @@ pub(super) fn finish_actions(
                     }
                 }
                 UnitAction::Abandon => {
-                    if let Some(held_item) = unit.unit_inventory.held_item {
-                        commands.spawn_litter(*unit.voxel_pos, held_item);
-                        unit.unit_inventory.held_item = None;
+                    if let Some(held_items) = unit.unit_inventory.take_all() {
+                        for _ in 0..held_items.count {
+                            commands.spawn_litter(*unit.voxel_pos, held_items.item_id);
+                        }
                     } else {
                         unit.impatience.increment();
                     }
@@ -703,6 +847,7 @@ impl CurrentAction {
         storage_inventory_query: &Query<&StorageInventory>,
         litter_query: &Query<&Litter>,
         signals: &Signals,
+        can_swim: bool,
         rng: &mut ThreadRng,
         item_manifest: &ItemManifest,
         terrain_query: &Query<&Id<Terrain>>,
@@ -729,7 +874,9 @@ impl CurrentAction {
 
                         if let Ok(storage_inventory) = storage_inventory_query.get(candidate) {
                             if storage_inventory.is_full()
-                                && storage_inventory.contains_kind(item_kind, item_manifest)
+                                && storage_inventory
+                                    .withdrawable_item_id(item_kind, item_manifest)
+                                    .is_some()
                             {
                                 candidates.push((candidate, voxel_pos));
                             }
@@ -743,7 +890,10 @@ impl CurrentAction {
                         }
 
                         if let Ok(storage_inventory) = storage_inventory_query.get(candidate) {
-                            if storage_inventory.contains_kind(item_kind, item_manifest) {
+                            if storage_inventory
+                                .withdrawable_item_id(item_kind, item_manifest)
+                                .is_some()
+                            {
                                 candidates.push((candidate, voxel_pos));
                             }
                         }
@@ -791,7 +941,8 @@ impl CurrentAction {
                     CurrentAction::dropoff(item_kind, *entity, facing, unit_pos, *voxel_pos)
                 }
             }
-        } else if let Some(upstream) = signals.upstream(unit_pos, goal, item_manifest, map_geometry)
+        } else if let Some(upstream) =
+            signals.upstream(unit_pos, goal, item_manifest, map_geometry, can_swim)
         {
             CurrentAction::move_or_spin(
                 unit_pos,
@@ -813,6 +964,7 @@ impl CurrentAction {
         facing: &Facing,
         workplace_query: &WorkplaceQuery,
         signals: &Signals,
+        can_swim: bool,
         rng: &mut ThreadRng,
         terrain_query: &Query<&Id<Terrain>>,
         terrain_manifest: &TerrainManifest,
@@ -855,6 +1007,7 @@ impl CurrentAction {
                 &Goal::Work(workplace_id),
                 item_manifest,
                 map_geometry,
+                can_swim,
             ) {
                 CurrentAction::move_or_spin(
                     unit_pos,
@@ -877,6 +1030,7 @@ impl CurrentAction {
         facing: &Facing,
         demolition_query: &DemolitionQuery,
         signals: &Signals,
+        can_swim: bool,
         rng: &mut ThreadRng,
         item_manifest: &ItemManifest,
         terrain_query: &Query<&Id<Terrain>>,
@@ -920,6 +1074,7 @@ impl CurrentAction {
                 &Goal::Demolish(structure_id),
                 item_manifest,
                 map_geometry,
+                can_swim,
             ) {
                 CurrentAction::move_or_spin(
                     unit_pos,
@@ -974,13 +1129,14 @@ impl CurrentAction {
         current_tile: VoxelPos,
         facing: &Facing,
         signals: &Signals,
+        can_swim: bool,
         item_manifest: &ItemManifest,
         terrain_query: &Query<&Id<Terrain>>,
         terrain_manifest: &TerrainManifest,
         map_geometry: &MapGeometry,
     ) -> Self {
         if let Some(target_tile) =
-            signals.downstream(current_tile, goal, item_manifest, map_geometry)
+            signals.downstream(current_tile, goal, item_manifest, map_geometry, can_swim)
         {
             CurrentAction::move_or_spin(
                 current_tile,
@@ -1008,17 +1164,30 @@ impl CurrentAction {
         const PATH_MULTIPLIER: f32 = 1.5;
 
         let target_tile = current_tile.neighbor(facing.direction);
-        let entity_standing_on = map_geometry.get_terrain(current_tile.hex).unwrap();
         let walking_speed = if map_geometry.get_structure(current_tile).is_some() {
             PATH_MULTIPLIER
         } else {
-            let terrain_standing_on = terrain_query.get(entity_standing_on).unwrap();
-            terrain_manifest.get(*terrain_standing_on).walking_speed
+            // Units walking on a roof are standing on a structure, rather than the terrain below it
+            match map_geometry
+                .get_voxel(current_tile.below())
+                .and_then(|entity| terrain_query.get(entity).ok())
+            {
+                Some(terrain_standing_on) => {
+                    terrain_manifest.get(*terrain_standing_on).walking_speed
+                }
+                None => 1.,
+            }
         };
 
         let walking_duration = UnitAction::MoveForward.duration().as_secs_f32() / walking_speed;
 
-        if map_geometry.is_passable(current_tile, target_tile) {
+        let has_room = map_geometry
+            .walkable_neighbor_in_direction(current_tile, facing.direction)
+            .map_or(true, |destination| {
+                map_geometry.has_room_for_unit(destination)
+            });
+
+        if map_geometry.is_passable(current_tile, target_tile) && has_room {
             CurrentAction {
                 action: UnitAction::MoveForward,
                 timer: Timer::from_seconds(walking_duration, TimerMode::Once),
@@ -1171,6 +1340,7 @@ impl CurrentAction {
         current_tile: VoxelPos,
         facing: &Facing,
         signals: &Signals,
+        can_swim: bool,
         item_manifest: &ItemManifest,
         terrain_query: &Query<&Id<Terrain>>,
         terrain_manifest: &TerrainManifest,
@@ -1195,6 +1365,7 @@ impl CurrentAction {
                     current_tile,
                     facing,
                     signals,
+                    can_swim,
                     item_manifest,
                     terrain_query,
                     terrain_manifest,
@@ -1251,9 +1422,51 @@ impl CurrentAction {
             CurrentAction::random_spin(rng)
         }
     }
+
+    /// Attempts to move next to a [`Nest`] with space to rest in.
+    ///
+    /// Units that are already next to one simply wait there while they recover.
+    fn find_nest(
+        unit_entity: Entity,
+        unit_pos: VoxelPos,
+        facing: &Facing,
+        goal: &Goal,
+        nest_query: &Query<&Nest>,
+        signals: &Signals,
+        can_swim: bool,
+        item_manifest: &ItemManifest,
+        terrain_query: &Query<&Id<Terrain>>,
+        terrain_manifest: &TerrainManifest,
+        map_geometry: &MapGeometry,
+    ) -> Self {
+        let nest_nearby = nest_within_reach(unit_pos, map_geometry, |structure_entity| {
+            nest_query.get(structure_entity).map_or(false, |nest| {
+                nest.has_room() || nest.is_occupied_by(unit_entity)
+            })
+        });
+
+        if nest_nearby.is_some() {
+            CurrentAction::idle()
+        } else if let Some(upstream) =
+            signals.upstream(unit_pos, goal, item_manifest, map_geometry, can_swim)
+        {
+            CurrentAction::move_or_spin(
+                unit_pos,
+                upstream,
+                facing,
+                terrain_query,
+                terrain_manifest,
+                map_geometry,
+            )
+        } else {
+            CurrentAction::idle()
+        }
+    }
 }
 
 /// A query about the [`CraftingState`] of a structure that might need work done.
+///
+/// Structures waiting on a [`PendingRelocation`] need work done too, even if they do not craft.
 #[derive(SystemParam)]
 pub(crate) struct WorkplaceQuery<'w, 's> {
     /// The contained query type.
@@ -1261,9 +1474,11 @@ pub(crate) struct WorkplaceQuery<'w, 's> {
         'w,
         's,
         (
-            &'static CraftingState,
+            Option<&'static CraftingState>,
             AnyOf<(&'static Id<Structure>, &'static TerraformingAction)>,
             &'static WorkersPresent,
+            Option<&'static PendingRelocation>,
+            Option<&'static CraftingPaused>,
         ),
     >,
 }
@@ -1286,24 +1501,42 @@ impl<'w, 's> WorkplaceQuery<'w, 's> {
 
         let entity = map_geometry.get_workplace(target)?;
 
-        let (found_crafting_state, ids, workers_present) = self.query.get(entity).ok()?;
+        let (maybe_crafting_state, ids, workers_present, maybe_relocation, maybe_crafting_paused) =
+            self.query.get(entity).ok()?;
 
         if workplace_id != WorkplaceId::new(ids) {
             return None;
         }
 
-        if let CraftingState::InProgress { .. } = found_crafting_state {
-            if workers_present.needs_more() {
-                Some(entity)
-            } else {
-                None
-            }
+        let needs_work = workplace_needs_work(
+            maybe_crafting_state,
+            maybe_crafting_paused,
+            maybe_relocation,
+        );
+
+        if needs_work && workers_present.needs_more() {
+            Some(entity)
         } else {
             None
         }
     }
 }
 
+/// Does a workplace in this state need units to work there?
+///
+/// Crafting only needs work while a recipe is in progress and has not been paused by the player,
+/// but structures waiting to be relocated always do.
+fn workplace_needs_work(
+    maybe_crafting_state: Option<&CraftingState>,
+    maybe_crafting_paused: Option<&CraftingPaused>,
+    maybe_relocation: Option<&PendingRelocation>,
+) -> bool {
+    let crafting_needs_work = maybe_crafting_paused.is_none()
+        && matches!(maybe_crafting_state, Some(CraftingState::InProgress { .. }));
+
+    crafting_needs_work || maybe_relocation.is_some()
+}
+
 /// Are units attempting to find or deliver items?
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub(crate) enum DeliveryMode {
@@ -1325,3 +1558,186 @@ pub(crate) enum Purpose {
     /// This will take / place items from storage.
     Instrumental,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        asset_management::manifest::Manifest,
+        crafting::item_tags::ItemTag,
+        items::{item_manifest::ItemData, ItemCount},
+        units::{basic_needs::Diet, unit_manifest::UnitData},
+    };
+    use hexx::Hex;
+
+    fn compostable() -> ItemData {
+        ItemData {
+            compostable: true,
+            ..ItemData::basic(10)
+        }
+    }
+
+    /// Has a unit that can carry ten items finish picking up `item_kind` from the `storage_inventory`.
+    ///
+    /// Returns the items that the unit ended up holding, and the storage inventory afterwards.
+    fn pick_up_from_storage(
+        storage_inventory: StorageInventory,
+        item_kind: ItemKind,
+    ) -> (Option<ItemCount>, StorageInventory) {
+        let (held_items, mut storage_inventories) =
+            pick_up_from_storage_group(vec![storage_inventory], item_kind);
+        (held_items, storage_inventories.remove(0))
+    }
+
+    /// Has a unit that can carry ten items finish picking up `item_kind` from the first of `storage_inventories`,
+    /// which form a single storage group.
+    ///
+    /// Returns the items that the unit ended up holding, and the storage inventories afterwards.
+    fn pick_up_from_storage_group(
+        storage_inventories: Vec<StorageInventory>,
+        item_kind: ItemKind,
+    ) -> (Option<ItemCount>, Vec<StorageInventory>) {
+        let mut item_manifest: ItemManifest = Manifest::new();
+        item_manifest.insert("seed".to_string(), compostable());
+        item_manifest.insert("leaf".to_string(), compostable());
+
+        let mut unit_manifest: UnitManifest = Manifest::new();
+        let mut unit_data = UnitData::simple("ant", Diet::simple("leaf"));
+        unit_data.carry_capacity = 10;
+        let lifecycle = unit_data.organism_variety.lifecycle.clone();
+        let energy_pool = unit_data.organism_variety.energy_pool.clone();
+        unit_manifest.insert("ant".to_string(), unit_data);
+
+        let mut app = App::new();
+        let map_geometry = MapGeometry::new(&mut app.world, 0);
+        let storage_entities: Vec<Entity> = storage_inventories
+            .into_iter()
+            .map(|storage_inventory| app.world.spawn(storage_inventory).id())
+            .collect();
+        let storage_groups = match storage_entities.len() {
+            1 => StorageGroups::default(),
+            _ => StorageGroups::single_group(storage_entities.clone()),
+        };
+
+        let mut timer = Timer::new(Duration::ZERO, TimerMode::Once);
+        timer.tick(Duration::ZERO);
+        let unit_entity = app
+            .world
+            .spawn((
+                Id::<Unit>::from_name("ant".to_string()),
+                Goal::Fetch(item_kind),
+                CurrentAction {
+                    action: UnitAction::PickUp {
+                        item_kind,
+                        output_entity: storage_entities[0],
+                    },
+                    timer,
+                    just_started: false,
+                },
+                lifecycle,
+                UnitInventory::default(),
+                Transform::default(),
+                map_geometry.on_top_of_terrain(Hex::ZERO),
+                energy_pool,
+                ImpatiencePool::new(10),
+                Facing::default(),
+            ))
+            .id();
+
+        app.insert_resource(item_manifest)
+            .insert_resource(unit_manifest)
+            .insert_resource(map_geometry)
+            .insert_resource(storage_groups)
+            .init_resource::<Signals>()
+            .add_system(finish_actions);
+        app.update();
+
+        let held_items = app
+            .world
+            .get::<UnitInventory>(unit_entity)
+            .unwrap()
+            .held_items();
+        let storage_inventories = storage_entities
+            .into_iter()
+            .map(|storage_entity| app.world.entity_mut(storage_entity).take().unwrap())
+            .collect();
+        (held_items, storage_inventories)
+    }
+
+    #[test]
+    fn haulers_leave_the_stock_floor_behind() {
+        let seed = Id::from_name("seed".to_string());
+        let item_manifest: ItemManifest =
+            [("seed".to_string(), compostable())].into_iter().collect();
+
+        let mut storage_inventory = StorageInventory::new(1, Some(seed));
+        storage_inventory
+            .add_item_all_or_nothing(&ItemCount::new(seed, 7), &item_manifest)
+            .unwrap();
+        storage_inventory.set_stock_floor(seed, 5);
+
+        let (held_items, storage_inventory) =
+            pick_up_from_storage(storage_inventory, ItemKind::Single(seed));
+        assert_eq!(held_items, Some(ItemCount::new(seed, 2)));
+        assert_eq!(storage_inventory.item_count(seed), 5);
+    }
+
+    #[test]
+    fn tagged_pickups_skip_items_at_their_stock_floor() {
+        let seed = Id::from_name("seed".to_string());
+        let leaf = Id::from_name("leaf".to_string());
+        let item_manifest: ItemManifest = [
+            ("seed".to_string(), compostable()),
+            ("leaf".to_string(), compostable()),
+        ]
+        .into_iter()
+        .collect();
+
+        let mut storage_inventory = StorageInventory::new(2, None);
+        storage_inventory
+            .add_item_all_or_nothing(&ItemCount::new(seed, 4), &item_manifest)
+            .unwrap();
+        storage_inventory
+            .add_item_all_or_nothing(&ItemCount::new(leaf, 3), &item_manifest)
+            .unwrap();
+        storage_inventory.set_stock_floor(seed, 4);
+
+        let (held_items, storage_inventory) =
+            pick_up_from_storage(storage_inventory, ItemKind::Tag(ItemTag::Compostable));
+        assert_eq!(held_items, Some(ItemCount::new(leaf, 3)));
+        assert_eq!(storage_inventory.item_count(seed), 4);
+    }
+
+    #[test]
+    fn haulers_withdraw_from_the_whole_storage_group() {
+        let seed = Id::from_name("seed".to_string());
+        let item_manifest: ItemManifest =
+            [("seed".to_string(), compostable())].into_iter().collect();
+        let bin = |count, stock_floor| {
+            let mut storage_inventory = StorageInventory::new(1, Some(seed));
+            storage_inventory
+                .add_item_all_or_nothing(&ItemCount::new(seed, count), &item_manifest)
+                .unwrap();
+            storage_inventory.set_stock_floor(seed, stock_floor);
+            storage_inventory
+        };
+
+        let (held_items, storage_inventories) = pick_up_from_storage_group(
+            vec![bin(3, 0), bin(4, 2), bin(10, 0)],
+            ItemKind::Single(seed),
+        );
+        assert_eq!(held_items, Some(ItemCount::new(seed, 10)));
+
+        let remaining: u32 = storage_inventories
+            .iter()
+            .map(|storage_inventory| storage_inventory.item_count(seed))
+            .sum();
+        assert_eq!(remaining, 7);
+        assert_eq!(storage_inventories[0].item_count(seed), 0);
+        // Stock floors are respected in every member
+        assert!(storage_inventories
+            .iter()
+            .all(|storage_inventory| storage_inventory.item_count(seed)
+                >= storage_inventory.stock_floor(seed)));
+    }
+}
@@ -9,14 +9,19 @@ use bevy::prelude::*;
 use rand::Rng;
 use serde::{Deserialize, Serialize};
 
-use crate::simulation::time::{Days, InGameTime};
+use crate::{
+    asset_management::manifest::Id,
+    simulation::time::{Days, InGameTime},
+};
 
-/// The age of a unit, in in-game days.
+use super::unit_manifest::Unit;
+
+/// The age of an organism, in in-game days.
 #[derive(Component, Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct Age {
-    /// The current age of the unit.
+    /// The current age of the organism.
     current: Days,
-    /// The maximum age of the unit.
+    /// The maximum age of the organism.
     max: Days,
 }
 
@@ -46,6 +51,11 @@ impl Age {
     pub fn max(&self) -> Days {
         self.max
     }
+
+    /// Grows older by `delta_days`.
+    pub(crate) fn advance(&mut self, delta_days: Days) {
+        self.current += delta_days;
+    }
 }
 
 impl Display for Age {
@@ -55,17 +65,19 @@ impl Display for Age {
 }
 
 /// Advances the age of all units by the elapsed time and kills them if they are too old.
+///
+/// Structures age separately, as they pass through their [`LifeStage`](crate::organisms::life_stages::LifeStage)s.
 pub(super) fn aging(
     mut commands: Commands,
     fixed_time: Res<FixedTime>,
     in_game_time: Res<InGameTime>,
-    mut query: Query<(&mut Age, Entity)>,
+    mut query: Query<(&mut Age, Entity), With<Id<Unit>>>,
 ) {
     let delta_time = fixed_time.period.as_secs_f32();
     let delta_days = Days(delta_time / in_game_time.seconds_per_day());
 
     for (mut age, entity) in query.iter_mut() {
-        age.current += delta_days;
+        age.advance(delta_days);
 
         if age.current > age.max {
             commands.entity(entity).despawn_recursive();
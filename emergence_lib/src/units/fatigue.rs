@@ -0,0 +1,335 @@
+//! Units tire as they work, and must rest before they can work at full speed again.
+//!
+//! Stamina is tracked separately from the [`EnergyPool`](crate::organisms::energy::EnergyPool) used for hunger:
+//! a well-fed unit can still be exhausted.
+
+use bevy::prelude::*;
+
+use crate::{
+    asset_management::manifest::Id,
+    crafting::workers::WorkersPresent,
+    geometry::{MapGeometry, VoxelPos},
+    structures::nests::Nest,
+};
+
+use super::{
+    actions::{CurrentAction, UnitAction},
+    goals::Goal,
+    unit_manifest::{StaminaData, Unit},
+};
+
+/// How rested a unit is.
+///
+/// Stamina is drained by working, and restored by idling or resting at a [`Nest`].
+/// Units whose [`UnitData`](super::unit_manifest::UnitData) has no [`StaminaData`] never tire.
+#[derive(Component, Debug, Clone, PartialEq)]
+pub(crate) struct Stamina {
+    /// The current stamina.
+    current: f32,
+    /// How quickly this unit tires and recovers, if it tires at all.
+    data: Option<StaminaData>,
+}
+
+impl Stamina {
+    /// The fraction of maximum stamina at or below which units abandon their work to rest.
+    const EXHAUSTION_THRESHOLD: f32 = 0.2;
+
+    /// Creates a full stamina pool for a unit with the provided `data`.
+    pub(crate) fn new(data: Option<StaminaData>) -> Self {
+        Stamina {
+            current: data.map_or(0., |data| data.max),
+            data,
+        }
+    }
+
+    /// The current stamina as a fraction of the maximum.
+    ///
+    /// This is also how effectively the unit works.
+    pub(crate) fn fraction(&self) -> f32 {
+        match self.data {
+            None => 1.,
+            Some(data) if data.max > 0. => self.current / data.max,
+            Some(_) => 0.,
+        }
+    }
+
+    /// Is this unit too tired to keep working?
+    pub(crate) fn is_exhausted(&self) -> bool {
+        self.data.is_some() && self.fraction() <= Self::EXHAUSTION_THRESHOLD
+    }
+
+    /// Is this unit fully rested?
+    pub(crate) fn is_rested(&self) -> bool {
+        self.data.map_or(true, |data| self.current >= data.max)
+    }
+
+    /// Spends the stamina needed to work for `delta_time` seconds.
+    pub(crate) fn work(&mut self, delta_time: f32) {
+        let Some(data) = self.data else { return };
+
+        self.current = (self.current - data.work_cost * delta_time).max(0.);
+    }
+
+    /// Recovers stamina at `rate` per second for `delta_time` seconds, up to the maximum.
+    pub(crate) fn recover(&mut self, rate: f32, delta_time: f32) {
+        let Some(data) = self.data else { return };
+
+        self.current = (self.current + rate * delta_time).min(data.max);
+    }
+
+    /// Recovers stamina for `delta_time` seconds spent idling away from a nest.
+    pub(crate) fn recover_idle(&mut self, delta_time: f32) {
+        let Some(data) = self.data else { return };
+
+        self.recover(data.idle_recovery, delta_time);
+    }
+}
+
+/// Returns the [`Nest`] that a unit at `unit_pos` can rest at, if any.
+///
+/// Units can rest at nests on their own tile or on any neighboring tile.
+pub(super) fn nest_within_reach(
+    unit_pos: VoxelPos,
+    map_geometry: &MapGeometry,
+    is_available: impl Fn(Entity) -> bool,
+) -> Option<Entity> {
+    std::iter::once(unit_pos)
+        .chain(unit_pos.all_neighbors())
+        .filter_map(|voxel_pos| map_geometry.get_structure(voxel_pos))
+        .find(|&structure_entity| is_available(structure_entity))
+}
+
+/// Drains the stamina of working units, and restores the stamina of idle and resting units.
+pub(super) fn manage_stamina(
+    mut unit_query: Query<(Entity, &VoxelPos, &Goal, &CurrentAction, &mut Stamina), With<Id<Unit>>>,
+    mut nest_query: Query<&mut Nest>,
+    map_geometry: Res<MapGeometry>,
+    fixed_time: Res<FixedTime>,
+) {
+    let delta_time = fixed_time.period.as_secs_f32();
+
+    // Nest occupancy is recomputed from scratch each tick
+    for mut nest in nest_query.iter_mut() {
+        nest.clear_occupants();
+    }
+
+    for (unit_entity, &unit_pos, goal, current_action, mut stamina) in unit_query.iter_mut() {
+        if matches!(current_action.action(), UnitAction::Work { .. }) {
+            stamina.work(delta_time);
+            continue;
+        }
+
+        let maybe_nest = match goal {
+            Goal::Rest { .. } => nest_within_reach(unit_pos, &map_geometry, |structure_entity| {
+                nest_query
+                    .get(structure_entity)
+                    .map_or(false, |nest| nest.has_room())
+            }),
+            _ => None,
+        };
+
+        match maybe_nest {
+            Some(nest_entity) => {
+                let mut nest = nest_query.get_mut(nest_entity).unwrap();
+                nest.admit(unit_entity);
+                stamina.recover(nest.rest_rate(), delta_time);
+            }
+            None => stamina.recover_idle(delta_time),
+        }
+    }
+}
+
+/// Sends exhausted units off to rest, and sends them back to work once they have recovered.
+pub(super) fn check_for_fatigue(
+    mut unit_query: Query<(Entity, &mut Goal, &mut CurrentAction, &Stamina)>,
+    mut workplace_query: Query<&mut WorkersPresent>,
+) {
+    for (unit_entity, mut goal, mut current_action, stamina) in unit_query.iter_mut() {
+        match *goal {
+            Goal::Rest { resume } => {
+                if stamina.is_rested() {
+                    *goal = match resume {
+                        Some(workplace_id) => Goal::Work(workplace_id),
+                        None => Goal::default(),
+                    };
+                }
+            }
+            _ if stamina.is_exhausted() => {
+                let resume = match *goal {
+                    Goal::Work(workplace_id) => Some(workplace_id),
+                    _ => None,
+                };
+
+                // Stop working immediately, freeing up the space for someone else
+                if let UnitAction::Work { structure_entity } = *current_action.action() {
+                    if let Ok(mut workers_present) = workplace_query.get_mut(structure_entity) {
+                        workers_present.remove_worker(unit_entity);
+                    }
+                    *current_action = CurrentAction::idle();
+                }
+
+                *goal = Goal::Rest { resume };
+            }
+            _ => (),
+        }
+    }
+}
+
+/// Scales how effectively each worker works by how rested they are.
+pub(super) fn update_worker_efficiency(
+    mut workplace_query: Query<&mut WorkersPresent>,
+    stamina_query: Query<&Stamina>,
+) {
+    for mut workers_present in workplace_query.iter_mut() {
+        // Only flag the workplace as changed if an efficiency actually changed
+        let changed = workers_present
+            .bypass_change_detection()
+            .update_efficiencies(|worker_entity| {
+                stamina_query.get(worker_entity).ok().map(Stamina::fraction)
+            });
+
+        if changed {
+            workers_present.set_changed();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        construction::ghosts::WorkplaceId,
+        geometry::Facing,
+        structures::{structure_manifest::Structure, Footprint},
+    };
+    use hexx::Hex;
+
+    fn ant_stamina() -> StaminaData {
+        StaminaData {
+            max: 100.,
+            work_cost: 2.,
+            idle_recovery: 0.5,
+        }
+    }
+
+    #[test]
+    fn units_without_stamina_data_never_tire() {
+        let mut stamina = Stamina::new(None);
+        stamina.work(1000.);
+
+        assert!(!stamina.is_exhausted());
+        assert!(stamina.is_rested());
+        assert_eq!(stamina.fraction(), 1.);
+    }
+
+    #[test]
+    fn exhausted_workers_rest_at_nests_and_return_to_work() {
+        let mut app = App::new();
+        let mut map_geometry = MapGeometry::new(&mut app.world, 3);
+
+        let workplace_id = WorkplaceId::Structure(Id::from_name("mill".to_string()));
+        let mut workers_present = WorkersPresent::new(1);
+        let workplace_entity = app.world.spawn_empty().id();
+
+        let unit_pos = map_geometry.on_top_of_terrain(Hex::new(0, 0));
+        let unit_entity = app
+            .world
+            .spawn((
+                Id::<Unit>::from_name("ant".to_string()),
+                unit_pos,
+                Goal::Work(workplace_id),
+                CurrentAction::work(workplace_entity),
+                Stamina::new(Some(ant_stamina())),
+            ))
+            .id();
+        workers_present.add_worker(unit_entity).unwrap();
+        app.world
+            .entity_mut(workplace_entity)
+            .insert(workers_present);
+
+        let nest_pos = map_geometry.on_top_of_terrain(Hex::new(1, 0));
+        let nest_entity = app
+            .world
+            .spawn((
+                Id::<Structure>::from_name("nest".to_string()),
+                Nest::new(20., 1),
+            ))
+            .id();
+        map_geometry
+            .add_structure(
+                nest_pos,
+                Facing::default(),
+                &Footprint::single(),
+                false,
+                false,
+                nest_entity,
+            )
+            .unwrap();
+
+        app.insert_resource(map_geometry)
+            .insert_resource(FixedTime::new_from_secs(1.))
+            .add_systems((manage_stamina, check_for_fatigue, update_worker_efficiency).chain());
+
+        // Work until exhausted
+        let mut ticks_worked = 0;
+        while matches!(app.world.get::<Goal>(unit_entity).unwrap(), Goal::Work(_)) {
+            app.update();
+            ticks_worked += 1;
+            assert!(ticks_worked < 100, "The unit never got tired");
+
+            let workers_present = app.world.get::<WorkersPresent>(workplace_entity).unwrap();
+            if workers_present.current() > 0 {
+                // Tired workers work less effectively
+                assert!(workers_present.effective_workers() < 1.);
+            }
+        }
+
+        assert_eq!(
+            app.world.get::<Goal>(unit_entity).unwrap(),
+            &Goal::Rest {
+                resume: Some(workplace_id)
+            }
+        );
+        assert!(app
+            .world
+            .get::<Stamina>(unit_entity)
+            .unwrap()
+            .is_exhausted());
+        // The unit has left its workplace
+        let workers_present = app.world.get::<WorkersPresent>(workplace_entity).unwrap();
+        assert_eq!(workers_present.current(), 0);
+        assert!(matches!(
+            app.world
+                .get::<CurrentAction>(unit_entity)
+                .unwrap()
+                .action(),
+            UnitAction::Idle
+        ));
+
+        // Rest next to the nest until recovered
+        app.update();
+        assert!(app
+            .world
+            .get::<Nest>(nest_entity)
+            .unwrap()
+            .is_occupied_by(unit_entity));
+
+        let mut ticks_rested = 0;
+        while matches!(
+            app.world.get::<Goal>(unit_entity).unwrap(),
+            Goal::Rest { .. }
+        ) {
+            app.update();
+            ticks_rested += 1;
+            assert!(ticks_rested < 100, "The unit never recovered");
+        }
+
+        // Resting at a nest is much faster than working was
+        assert!(ticks_rested < ticks_worked);
+        assert!(app.world.get::<Stamina>(unit_entity).unwrap().is_rested());
+        assert_eq!(
+            app.world.get::<Goal>(unit_entity).unwrap(),
+            &Goal::Work(workplace_id)
+        );
+    }
+}
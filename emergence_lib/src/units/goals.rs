@@ -62,6 +62,11 @@ pub(crate) enum Goal {
     Breathe,
     /// Trying to avoid a specific unit.
     Avoid(Id<Unit>),
+    /// Too tired to keep working, so trying to rest at a nest.
+    Rest {
+        /// The workplace to return to once rested, if any.
+        resume: Option<WorkplaceId>,
+    },
 }
 
 /// The data-less version of [`Goal`].
@@ -87,6 +92,8 @@ pub(crate) enum GoalKind {
     Avoid,
     /// Trying to get to oxygen.
     Breathe,
+    /// Trying to rest.
+    Rest,
 }
 
 impl From<&Goal> for GoalKind {
@@ -102,6 +109,7 @@ impl From<&Goal> for GoalKind {
             Goal::Eat(_) => GoalKind::Eat,
             Goal::Avoid(_) => GoalKind::Avoid,
             Goal::Breathe => GoalKind::Breathe,
+            Goal::Rest { .. } => GoalKind::Rest,
         }
     }
 }
@@ -129,6 +137,10 @@ impl TryFrom<SignalType> for Goal {
             SignalType::Contains(_) => Err(()),
             SignalType::Stores(_) => Err(()),
             SignalType::Unit(unit) => Ok(Goal::Avoid(unit)),
+            // Repulsion modifies other goals, rather than creating its own
+            SignalType::Avoid(_) => Err(()),
+            // Units only look for somewhere to rest once they are tired
+            SignalType::Rest => Err(()),
         }
     }
 }
@@ -147,6 +159,7 @@ impl Goal {
             Goal::Eat(_) => Some(DeliveryMode::PickUp),
             Goal::Avoid(_) => None,
             Goal::Breathe => None,
+            Goal::Rest { .. } => None,
         }
     }
 
@@ -163,6 +176,7 @@ impl Goal {
             Goal::Eat(_) => Purpose::Instrumental,
             Goal::Breathe => Purpose::Instrumental,
             Goal::Avoid(_) => Purpose::Instrumental,
+            Goal::Rest { .. } => Purpose::Instrumental,
         }
     }
 
@@ -195,6 +209,7 @@ impl Goal {
             Goal::Eat(item_kind) => format!("Eat {}", item_manifest.name_of_kind(*item_kind)),
             Goal::Avoid(unit) => format!("Avoid {}", unit_manifest.name(*unit)),
             Goal::Breathe => "Breathe".to_string(),
+            Goal::Rest { .. } => "Rest".to_string(),
         }
     }
 }
@@ -254,6 +269,7 @@ pub(super) fn choose_goal(
                 wandering_behavior,
                 rng,
                 &signals,
+                &item_manifest,
             );
 
             // Reset impatience when we choose a new goal
@@ -273,6 +289,7 @@ fn compute_new_goal(
     wandering_behavior: &WanderingBehavior,
     rng: &mut ThreadRng,
     signals: &Signals,
+    item_manifest: &ItemManifest,
 ) -> Goal {
     // When we first get a wandering goal, pick a number of actions to take before picking a new goal.
     if remaining_actions.is_none() {
@@ -302,11 +319,12 @@ fn compute_new_goal(
         }
     });
 
-    if let Ok(goal_weights) = WeightedIndex::new(
-        goal_relevant_signals
-            .iter()
-            .map(|(_type, strength)| strength.value()),
-    ) {
+    // Avoid signals count against the goals they relate to
+    if let Ok(goal_weights) = WeightedIndex::new(goal_relevant_signals.iter().map(
+        |&(&signal_type, &strength)| {
+            (strength - current_signals.avoidance(signal_type, item_manifest)).value()
+        },
+    )) {
         let selected_goal_index = goal_weights.sample(rng);
         if let Some(selected_signal) = goal_relevant_signals.get(selected_goal_index) {
             let selected_signal_type = *selected_signal.0;
@@ -1,26 +1,468 @@
 //! Holding, using and carrying items.
 
-use bevy::prelude::*;
+use bevy::{
+    prelude::*,
+    utils::{HashMap, HashSet},
+};
 
 use crate::{
     asset_management::manifest::Id,
-    items::item_manifest::{Item, ItemManifest},
+    construction::demolition::MarkedForDemolition,
+    crafting::{
+        inventories::{InputInventory, StorageInventory},
+        item_tags::ItemKind,
+    },
+    geometry::VoxelPos,
+    items::{
+        inventory::Inventory,
+        item_manifest::{Item, ItemManifest},
+        ItemCount,
+    },
 };
 
+use super::goals::Goal;
+
 /// The item(s) that a unit is carrying.
-#[derive(Component, Default, Clone, Debug, Deref, DerefMut)]
+///
+/// Units can carry a stack of several items at once, but all of them must be of the same type.
+#[derive(Component, Default, Clone, Debug)]
 pub(crate) struct UnitInventory {
-    /// The single item the unit is currently holding
+    /// The type of item the unit is currently holding
     pub(crate) held_item: Option<Id<Item>>,
+    /// The number of items of type `held_item` that the unit is holding
+    ///
+    /// This is always 0 when `held_item` is `None`.
+    held_count: u32,
 }
 
 impl UnitInventory {
+    /// Creates a new inventory holding the provided stack of items.
+    #[cfg(test)]
+    pub(crate) fn holding(item_count: ItemCount) -> Self {
+        if item_count.count == 0 {
+            return UnitInventory::default();
+        }
+
+        UnitInventory {
+            held_item: Some(item_count.item_id),
+            held_count: item_count.count,
+        }
+    }
+
+    /// The number of items that the unit is holding.
+    pub(crate) fn held_count(&self) -> u32 {
+        self.held_count
+    }
+
+    /// The stack of items that the unit is holding, if any.
+    pub(crate) fn held_items(&self) -> Option<ItemCount> {
+        self.held_item
+            .map(|item_id| ItemCount::new(item_id, self.held_count))
+    }
+
+    /// Picks up as many items of type `item_id` from the `source` inventory as possible,
+    /// carrying at most `carry_capacity` items in total.
+    ///
+    /// Returns the number of items picked up.
+    /// Nothing is picked up if the unit is already holding a different type of item.
+    pub(crate) fn pick_up_from(
+        &mut self,
+        source: &mut Inventory,
+        item_id: Id<Item>,
+        carry_capacity: u32,
+    ) -> u32 {
+        if self.held_item.is_some() && self.held_item != Some(item_id) {
+            return 0;
+        }
+
+        let free_capacity = carry_capacity.saturating_sub(self.held_count);
+        let count = free_capacity.min(source.item_count(item_id));
+        if count == 0 {
+            return 0;
+        }
+
+        // We just checked that there are enough items available
+        source
+            .remove_item_all_or_nothing(&ItemCount::new(item_id, count))
+            .unwrap();
+
+        self.held_item = Some(item_id);
+        self.held_count += count;
+        count
+    }
+
+    /// Deposits as many of the held items into the `destination` inventory as will fit.
+    ///
+    /// Any items that do not fit are kept.
+    /// Returns the number of items deposited.
+    pub(crate) fn deposit_into(
+        &mut self,
+        destination: &mut Inventory,
+        item_manifest: &ItemManifest,
+    ) -> u32 {
+        let Some(item_id) = self.held_item else {
+            return 0;
+        };
+
+        let count = self
+            .held_count
+            .min(destination.remaining_space_for_item(item_id, item_manifest));
+        if count == 0 {
+            return 0;
+        }
+
+        // We just checked that there is enough space available
+        destination
+            .add_item_all_or_nothing(&ItemCount::new(item_id, count), item_manifest)
+            .unwrap();
+
+        self.remove(count);
+        count
+    }
+
+    /// Removes up to `count` of the held items, emptying the unit's hands once nothing is left.
+    pub(crate) fn remove(&mut self, count: u32) {
+        self.held_count = self.held_count.saturating_sub(count);
+        if self.held_count == 0 {
+            self.held_item = None;
+        }
+    }
+
+    /// Removes and returns everything that the unit is holding.
+    pub(crate) fn take_all(&mut self) -> Option<ItemCount> {
+        let held_items = self.held_items();
+        *self = UnitInventory::default();
+        held_items
+    }
+
     /// Pretty foramtting for this type.
     pub(crate) fn display(&self, item_manifest: &ItemManifest) -> String {
         if let Some(item) = self.held_item {
-            item_manifest.name(item).to_string()
+            match self.held_count {
+                1 => item_manifest.name(item).to_string(),
+                count => format!("{} ({count})", item_manifest.name(item)),
+            }
         } else {
             "Nothing".to_string()
         }
     }
 }
+
+/// The items that units are currently carrying to each destination.
+///
+/// Emitters subtract the items being carried to them from their demand, so that new haulers are not dispatched
+/// for items that are already on their way.
+#[derive(Resource, Debug, Default, Clone, PartialEq)]
+pub(crate) struct InFlightItems {
+    /// The number of items of each type being carried to each destination.
+    claims: HashMap<Entity, HashMap<Id<Item>, u32>>,
+    /// The destinations whose claims changed the last time that the items were counted.
+    changed_destinations: HashSet<Entity>,
+}
+
+impl InFlightItems {
+    /// The number of items of type `item_id` that units are carrying to `destination`.
+    pub(crate) fn claimed(&self, destination: Entity, item_id: Id<Item>) -> u32 {
+        self.claims
+            .get(&destination)
+            .and_then(|claims| claims.get(&item_id))
+            .copied()
+            .unwrap_or_default()
+    }
+
+    /// The number of items matching `item_kind` that units are carrying to `destination`.
+    pub(crate) fn claimed_kind(
+        &self,
+        destination: Entity,
+        item_kind: ItemKind,
+        item_manifest: &ItemManifest,
+    ) -> u32 {
+        self.claims
+            .get(&destination)
+            .map(|claims| {
+                claims
+                    .iter()
+                    .filter(|(&item_id, _)| item_kind.matches(item_id, item_manifest))
+                    .map(|(_, &count)| count)
+                    .sum()
+            })
+            .unwrap_or_default()
+    }
+
+    /// Did the items being carried to `destination` change the last time that they were counted?
+    ///
+    /// Emitters that only depend on their own claims can skip recomputing their signals otherwise.
+    pub(crate) fn claims_changed(&self, destination: Entity) -> bool {
+        self.changed_destinations.contains(&destination)
+    }
+}
+
+/// Assigns the items that units are delivering or storing to the destinations that they are headed for.
+///
+/// Units follow signals rather than a fixed route, so each carried stack is assigned to the nearest
+/// destinations that still have room for it.
+/// Deliveries are headed for input inventories, including those of ghosts, while stored items are headed for storage.
+pub(crate) fn count_in_flight_items(
+    mut in_flight_items: ResMut<InFlightItems>,
+    unit_query: Query<(&VoxelPos, &Goal, &UnitInventory)>,
+    input_inventory_query: Query<
+        (Entity, &VoxelPos, &InputInventory),
+        Without<MarkedForDemolition>,
+    >,
+    storage_inventory_query: Query<(Entity, &VoxelPos, &StorageInventory)>,
+    item_manifest: Res<ItemManifest>,
+) {
+    let mut claims: HashMap<Entity, HashMap<Id<Item>, u32>> = HashMap::new();
+
+    for (&unit_pos, goal, unit_inventory) in unit_query.iter() {
+        let Some(held_items) = unit_inventory.held_items() else { continue };
+        let item_id = held_items.item_id;
+
+        let mut destinations: Vec<(u32, Entity, u32)> = match goal {
+            Goal::Deliver(_) => input_inventory_query
+                .iter()
+                .map(|(entity, &voxel_pos, input_inventory)| {
+                    let space = input_inventory.remaining_space_for_item(item_id, &item_manifest);
+                    (
+                        unit_pos.hex.unsigned_distance_to(voxel_pos.hex),
+                        entity,
+                        space,
+                    )
+                })
+                .collect(),
+            Goal::Store(_) => storage_inventory_query
+                .iter()
+                .map(|(entity, &voxel_pos, storage_inventory)| {
+                    let space = storage_inventory.remaining_space_for_item(item_id, &item_manifest);
+                    (
+                        unit_pos.hex.unsigned_distance_to(voxel_pos.hex),
+                        entity,
+                        space,
+                    )
+                })
+                .collect(),
+            _ => continue,
+        };
+        // Break ties between equally distant destinations consistently
+        destinations.sort_unstable();
+
+        let mut unassigned = held_items.count;
+        for (_, entity, space) in destinations {
+            if unassigned == 0 {
+                break;
+            }
+
+            let claimed = claims
+                .get(&entity)
+                .and_then(|claims| claims.get(&item_id))
+                .copied()
+                .unwrap_or_default();
+            let assigned = space.saturating_sub(claimed).min(unassigned);
+            if assigned > 0 {
+                *claims
+                    .entry(entity)
+                    .or_default()
+                    .entry(item_id)
+                    .or_default() += assigned;
+                unassigned -= assigned;
+            }
+        }
+    }
+
+    let changed_destinations = claims
+        .keys()
+        .chain(in_flight_items.claims.keys())
+        .filter(|&entity| claims.get(entity) != in_flight_items.claims.get(entity))
+        .copied()
+        .collect();
+
+    in_flight_items.set_if_neq(InFlightItems {
+        claims,
+        changed_destinations,
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{asset_management::manifest::Manifest, items::item_manifest::ItemData};
+
+    fn leaf() -> Id<Item> {
+        Id::from_name("leaf".to_string())
+    }
+
+    fn item_manifest() -> ItemManifest {
+        let mut manifest = Manifest::new();
+        manifest.insert(
+            "leaf".to_string(),
+            ItemData {
+                compostable: true,
+                buoyant: true,
+                ..ItemData::basic(10)
+            },
+        );
+        manifest
+    }
+
+    #[test]
+    fn unit_with_capacity_five_clears_twelve_item_pile_in_three_trips() {
+        let item_manifest = item_manifest();
+        let mut pile = Inventory::full_from_item(leaf(), 12);
+        let mut destination = Inventory::new(2, None);
+        let mut unit_inventory = UnitInventory::default();
+
+        let mut trips = 0;
+        while pile.item_count(leaf()) > 0 {
+            let picked_up = unit_inventory.pick_up_from(&mut pile, leaf(), 5);
+            assert!(picked_up > 0);
+            assert_eq!(unit_inventory.held_count(), picked_up);
+
+            let deposited = unit_inventory.deposit_into(&mut destination, &item_manifest);
+            assert_eq!(deposited, picked_up);
+            assert_eq!(unit_inventory.held_item, None);
+
+            trips += 1;
+        }
+
+        assert_eq!(trips, 3);
+        assert_eq!(destination.item_count(leaf()), 12);
+    }
+
+    #[test]
+    fn partial_deposits_keep_the_remainder() {
+        let item_manifest = item_manifest();
+        let mut destination = Inventory::empty_from_item(leaf(), 3);
+        let mut unit_inventory = UnitInventory::holding(ItemCount::new(leaf(), 5));
+
+        assert_eq!(
+            unit_inventory.deposit_into(&mut destination, &item_manifest),
+            3
+        );
+        assert_eq!(unit_inventory.held_items(), Some(ItemCount::new(leaf(), 2)));
+
+        // The destination is full, so nothing more is deposited
+        assert_eq!(
+            unit_inventory.deposit_into(&mut destination, &item_manifest),
+            0
+        );
+        assert_eq!(unit_inventory.held_count(), 2);
+    }
+
+    /// Spawns a unit at `voxel_pos` that is carrying `count` leaves while pursuing `goal`.
+    fn spawn_hauler(app: &mut App, voxel_pos: VoxelPos, goal: Goal, count: u32) -> Entity {
+        let unit_inventory = UnitInventory::holding(ItemCount::new(leaf(), count));
+        app.world.spawn((voxel_pos, goal, unit_inventory)).id()
+    }
+
+    /// Spawns a structure at `voxel_pos` that needs up to `capacity` leaves.
+    fn spawn_crafter(app: &mut App, voxel_pos: VoxelPos, capacity: u32) -> Entity {
+        let input_inventory = InputInventory::Exact {
+            inventory: Inventory::empty_from_item(leaf(), capacity),
+        };
+        app.world.spawn((voxel_pos, input_inventory)).id()
+    }
+
+    fn in_flight_app() -> App {
+        let mut app = App::new();
+        app.insert_resource(item_manifest())
+            .init_resource::<InFlightItems>()
+            .add_system(count_in_flight_items);
+        app
+    }
+
+    #[test]
+    fn deliveries_are_claimed_by_the_nearest_destinations_with_room() {
+        let mut app = in_flight_app();
+        let near = spawn_crafter(&mut app, VoxelPos::from_xy(1, 0), 3);
+        let far = spawn_crafter(&mut app, VoxelPos::from_xy(4, 0), 3);
+        let unreached = spawn_crafter(&mut app, VoxelPos::from_xy(8, 0), 3);
+        spawn_hauler(
+            &mut app,
+            VoxelPos::ZERO,
+            Goal::Deliver(ItemKind::Single(leaf())),
+            5,
+        );
+
+        app.update();
+
+        let in_flight_items = app.world.resource::<InFlightItems>();
+        assert_eq!(in_flight_items.claimed(near, leaf()), 3);
+        assert_eq!(in_flight_items.claimed(far, leaf()), 2);
+        assert_eq!(in_flight_items.claimed(unreached, leaf()), 0);
+    }
+
+    #[test]
+    fn each_carried_item_is_only_claimed_once() {
+        let mut app = in_flight_app();
+        let crafter = spawn_crafter(&mut app, VoxelPos::from_xy(1, 0), 3);
+        let storage = app
+            .world
+            .spawn((VoxelPos::from_xy(-1, 0), StorageInventory::new(1, None)))
+            .id();
+        for _ in 0..2 {
+            spawn_hauler(
+                &mut app,
+                VoxelPos::ZERO,
+                Goal::Deliver(ItemKind::Single(leaf())),
+                2,
+            );
+        }
+        // Items that are being stored are headed for storage, not crafters
+        spawn_hauler(
+            &mut app,
+            VoxelPos::ZERO,
+            Goal::Store(ItemKind::Single(leaf())),
+            4,
+        );
+        // Items that are being removed are not being delivered anywhere yet
+        spawn_hauler(
+            &mut app,
+            VoxelPos::ZERO,
+            Goal::Remove(ItemKind::Single(leaf())),
+            1,
+        );
+
+        app.update();
+
+        let in_flight_items = app.world.resource::<InFlightItems>();
+        // Only the first three delivered leaves fit in the crafter: the last one is unclaimed
+        assert_eq!(in_flight_items.claimed(crafter, leaf()), 3);
+        assert_eq!(in_flight_items.claimed(storage, leaf()), 4);
+    }
+
+    #[test]
+    fn claims_are_released_once_items_are_delivered() {
+        let mut app = in_flight_app();
+        let crafter = spawn_crafter(&mut app, VoxelPos::from_xy(1, 0), 3);
+        let hauler = spawn_hauler(
+            &mut app,
+            VoxelPos::ZERO,
+            Goal::Deliver(ItemKind::Single(leaf())),
+            3,
+        );
+
+        app.update();
+        assert_eq!(
+            app.world
+                .resource::<InFlightItems>()
+                .claimed(crafter, leaf()),
+            3
+        );
+
+        let item_manifest = item_manifest();
+        let mut unit_inventory = app.world.get_mut::<UnitInventory>(hauler).unwrap();
+        let mut input_inventory = InputInventory::Exact {
+            inventory: Inventory::empty_from_item(leaf(), 3),
+        };
+        unit_inventory.deposit_into(input_inventory.inventory_mut(), &item_manifest);
+        app.world.entity_mut(crafter).insert(input_inventory);
+
+        app.update();
+        assert_eq!(
+            app.world
+                .resource::<InFlightItems>()
+                .claimed(crafter, leaf()),
+            0
+        );
+    }
+}
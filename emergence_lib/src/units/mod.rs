@@ -18,9 +18,10 @@ use serde::{Deserialize, Serialize};
 use self::{
     actions::CurrentAction,
     age::Age,
+    fatigue::Stamina,
     goals::Goal,
     impatience::ImpatiencePool,
-    item_interaction::UnitInventory,
+    item_interaction::{InFlightItems, UnitInventory},
     unit_assets::UnitHandles,
     unit_manifest::{RawUnitManifest, Unit, UnitData},
 };
@@ -30,6 +31,7 @@ use crate::organisms::OrganismBundle;
 pub(crate) mod actions;
 pub mod age;
 pub mod basic_needs;
+pub(crate) mod fatigue;
 pub(crate) mod goals;
 pub(crate) mod impatience;
 pub(crate) mod item_interaction;
@@ -88,6 +90,8 @@ pub(crate) struct UnitBundle {
     impatience: ImpatiencePool,
     /// What is the unit currently doing.
     current_action: CurrentAction,
+    /// How rested the unit is.
+    stamina: Stamina,
     /// What is the unit currently holding, if anything?
     held_item: UnitInventory,
     /// What signals is this unit emitting?
@@ -129,12 +133,14 @@ impl UnitBundle {
             current_goal: Goal::default(),
             impatience: ImpatiencePool::new(unit_data.max_impatience),
             current_action: CurrentAction::default(),
+            stamina: Stamina::new(unit_data.stamina),
             held_item: UnitInventory::default(),
             emitter: Emitter {
                 signals: vec![(
                     SignalType::Unit(unit_id),
                     SignalStrength::new(Self::UNIT_EMITTER_STRENGTH),
                 )],
+                max_range: None,
             },
             age: Age::newborn(unit_data.max_age),
             organism_bundle: OrganismBundle::new(
@@ -173,12 +179,14 @@ impl UnitBundle {
             current_goal: Goal::default(),
             impatience: ImpatiencePool::new(unit_data.max_impatience),
             current_action: CurrentAction::default(),
+            stamina: Stamina::new(unit_data.stamina),
             held_item: UnitInventory::default(),
             emitter: Emitter {
                 signals: vec![(
                     SignalType::Unit(unit_id),
                     SignalStrength::new(Self::UNIT_EMITTER_STRENGTH),
                 )],
+                max_range: None,
             },
             age,
             organism_bundle: OrganismBundle::new(energy_pool, unit_data.organism_variety.lifecycle),
@@ -211,12 +219,14 @@ impl UnitBundle {
             current_goal: Goal::default(),
             impatience: ImpatiencePool::new(unit_data.max_impatience),
             current_action: CurrentAction::default(),
+            stamina: Stamina::new(unit_data.stamina),
             held_item: UnitInventory::default(),
             emitter: Emitter {
                 signals: vec![(
                     SignalType::Unit(unit_id),
                     SignalStrength::new(Self::UNIT_EMITTER_STRENGTH),
                 )],
+                max_range: None,
             },
             age,
             organism_bundle: OrganismBundle::new(energy_pool, unit_data.organism_variety.lifecycle),
@@ -250,9 +260,11 @@ impl Plugin for UnitsPlugin {
     fn build(&self, app: &mut App) {
         app.add_plugin(ManifestPlugin::<RawUnitManifest>::new())
             .add_asset_collection::<UnitHandles>()
+            .init_resource::<InFlightItems>()
             .add_systems(
                 (
                     actions::advance_action_timer.in_set(UnitSystem::AdvanceTimers),
+                    actions::count_occupants.before(UnitSystem::Act),
                     actions::start_actions
                         .in_set(UnitSystem::Act)
                         .before(actions::finish_actions),
@@ -272,9 +284,16 @@ impl Plugin for UnitsPlugin {
                         .before(UnitSystem::ChooseNewAction)
                         // Make sure to overwrite any existing goal
                         .after(UnitSystem::ChooseGoal),
+                    // Tired units stop to rest, unless they're hungry or drowning
+                    fatigue::check_for_fatigue
+                        .after(UnitSystem::ChooseGoal)
+                        .before(basic_needs::check_for_hunger),
+                    fatigue::manage_stamina.after(UnitSystem::Act),
+                    fatigue::update_worker_efficiency.after(fatigue::manage_stamina),
                     // Oxygen is more important than hunger, so it should overwrite
                     basic_needs::check_for_oxygen.after(basic_needs::check_for_hunger),
                     age::aging,
+                    item_interaction::count_in_flight_items.after(UnitSystem::Act),
                 )
                     .in_set(SimulationSet)
                     .in_schedule(CoreSchedule::FixedUpdate),
@@ -7,7 +7,7 @@ use bevy::{
 use serde::{Deserialize, Serialize};
 
 use crate::{
-    asset_management::manifest::loader::IsRawManifest,
+    asset_management::manifest::loader::{IsRawManifest, ManifestError},
     organisms::{OrganismVariety, RawOrganismVariety},
     simulation::time::Days,
     units::{basic_needs::Diet, WanderingBehavior},
@@ -36,6 +36,55 @@ pub struct UnitData {
     ///
     /// This stores a [`WeightedIndex`](rand::distributions::WeightedIndex) to allow for multimodal distributions.
     pub wandering_behavior: WanderingBehavior,
+    /// How many items of a single type can units of this type carry at once?
+    pub carry_capacity: u32,
+    /// Can units of this type swim through water too deep to wade in?
+    ///
+    /// Units that cannot swim will not step into deep water, unless they are already stranded in it.
+    pub can_swim: bool,
+    /// How quickly units of this type tire while working, and recover while resting.
+    ///
+    /// If this is [`None`], units of this type never tire.
+    pub stamina: Option<StaminaData>,
+}
+
+/// How quickly a variety of unit tires while working, and recovers while resting.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub struct StaminaData {
+    /// The stamina of a fully rested unit.
+    pub max: f32,
+    /// The stamina spent for each second of work.
+    pub work_cost: f32,
+    /// The stamina restored for each second spent not working, away from a nest.
+    pub idle_recovery: f32,
+}
+
+impl StaminaData {
+    /// Checks that these values can be used, returning the reason that they cannot be otherwise.
+    fn validate(&self) -> Result<(), String> {
+        if !(self.max.is_finite() && self.max > 0.) {
+            return Err(format!(
+                "the maximum stamina must be positive (got {})",
+                self.max
+            ));
+        }
+
+        if !(self.work_cost.is_finite() && self.work_cost >= 0.) {
+            return Err(format!(
+                "the stamina cost of work cannot be negative (got {})",
+                self.work_cost
+            ));
+        }
+
+        if !(self.idle_recovery.is_finite() && self.idle_recovery >= 0.) {
+            return Err(format!(
+                "the idle stamina recovery cannot be negative (got {})",
+                self.idle_recovery
+            ));
+        }
+
+        Ok(())
+    }
 }
 
 impl UnitData {
@@ -48,6 +97,9 @@ impl UnitData {
             max_impatience: 10,
             max_age: Days(10.0),
             wandering_behavior: WanderingBehavior::default(),
+            carry_capacity: 1,
+            can_swim: true,
+            stamina: None,
         }
     }
 }
@@ -67,22 +119,63 @@ pub struct RawUnitData {
     ///
     /// This stores a [`WeightedIndex`](rand::distributions::WeightedIndex) to allow for multimodal distributions.
     pub wandering_behavior: WanderingBehavior,
+    /// How many items of a single type can units of this type carry at once?
+    #[serde(default = "RawUnitData::default_carry_capacity")]
+    pub carry_capacity: u32,
+    /// Can units of this type swim through water too deep to wade in?
+    ///
+    /// Units can swim unless this is set to `false`.
+    #[serde(default = "RawUnitData::default_can_swim")]
+    pub can_swim: bool,
+    /// How quickly units of this type tire while working, and recover while resting.
+    ///
+    /// If this is omitted, units of this type never tire.
+    #[serde(default)]
+    pub stamina: Option<StaminaData>,
+}
+
+impl RawUnitData {
+    /// The carry capacity used when none is specified: units carry a single item at a time.
+    fn default_carry_capacity() -> u32 {
+        1
+    }
+
+    /// Whether units can swim when this is not specified: units have always been able to cross deep water.
+    fn default_can_swim() -> bool {
+        true
+    }
+
+    /// Checks that these values can be used, returning the reason that they cannot be otherwise.
+    fn validate(&self) -> Result<(), String> {
+        if !(self.max_age.is_finite() && self.max_age > 0.) {
+            return Err(format!(
+                "the maximum age must be positive (got {})",
+                self.max_age
+            ));
+        }
+
+        if self.carry_capacity == 0 {
+            return Err("units must be able to carry at least one item".to_string());
+        }
+
+        match &self.stamina {
+            Some(stamina) => stamina.validate(),
+            None => Ok(()),
+        }
+    }
 }
 
 impl From<RawUnitData> for UnitData {
     fn from(raw: RawUnitData) -> Self {
-        assert!(
-            raw.max_age > 0.0,
-            "Unit max age must be positive (got {})",
-            raw.max_age
-        );
-
         Self {
             organism_variety: raw.organism_variety.into(),
             diet: raw.diet.into(),
             max_impatience: raw.max_impatience,
             max_age: Days(raw.max_age),
             wandering_behavior: raw.wandering_behavior,
+            carry_capacity: raw.carry_capacity,
+            can_swim: raw.can_swim,
+            stamina: raw.stamina,
         }
     }
 }
@@ -101,16 +194,27 @@ impl IsRawManifest for RawUnitManifest {
     type Marker = Unit;
     type Data = UnitData;
 
-    fn process(&self) -> Manifest<Self::Marker, Self::Data> {
+    fn process(&self) -> Result<Manifest<Self::Marker, Self::Data>, ManifestError> {
         let mut manifest = Manifest::new();
 
         for (raw_id, raw_data) in self.unit_types.clone() {
-            let data = raw_data.into();
+            if let Err(reason) = raw_data.validate() {
+                return Err(ManifestError::MalformedUnit {
+                    unit: raw_id,
+                    reason,
+                });
+            }
+
+            let pollination = raw_data
+                .organism_variety
+                .resolve_pollination(|partner| !self.unit_types.contains_key(partner));
+
+            let mut data: UnitData = raw_data.into();
+            data.organism_variety.pollination = pollination;
 
-            // No additional preprocessing is needed.
-            manifest.insert(raw_id, data)
+            manifest.try_insert(raw_id, data)?;
         }
 
-        manifest
+        Ok(manifest)
     }
 }
@@ -11,7 +11,7 @@ use bevy::prelude::*;
 use derive_more::{Add, AddAssign, Sub, SubAssign};
 use serde::{Deserialize, Serialize};
 
-use crate::geometry::{VoxelPos, MAP_LAYOUT};
+use crate::geometry::{MapGeometry, PlacementBitfield, VoxelPos, MAP_LAYOUT};
 use crate::simulation::time::Days;
 use crate::{
     asset_management::manifest::Id,
@@ -19,13 +19,14 @@ use crate::{
     items::item_manifest::{Item, ItemManifest},
     simulation::SimulationSet,
     structures::structure_manifest::StructureManifest,
+    terrain::terrain_manifest::{Terrain, TerrainManifest},
 };
 
 use self::ocean::{tides, Ocean, TideSettings};
 use self::water_dynamics::{SoilWaterEvaporationRate, SoilWaterFlowRate};
 use self::{
     emitters::{add_water_emitters, produce_water_from_emitters},
-    roots::draw_water_from_roots,
+    roots::{absorb_received_water, draw_water_from_roots},
     water_dynamics::{evaporation, horizontal_water_movement, precipitation},
 };
 
@@ -143,6 +144,12 @@ impl Plugin for WaterPlugin {
                         // This needs to respect pausing
                         .in_set(SimulationSet),
                 )
+                .add_system(
+                    sync_soil_properties
+                        .run_if(resource_exists::<TerrainManifest>())
+                        .before(WaterSet::VerticalWaterMovement)
+                        .in_set(SimulationSet),
+                )
                 .add_systems(
                     (
                         tides,
@@ -153,6 +160,7 @@ impl Plugin for WaterPlugin {
                         draw_water_from_roots
                             .run_if(resource_exists::<StructureManifest>())
                             .run_if(resource_exists::<ItemManifest>()),
+                        absorb_received_water.run_if(resource_exists::<ItemManifest>()),
                         evaporation,
                     )
                         .chain()
@@ -168,6 +176,11 @@ impl Plugin for WaterPlugin {
                 .add_system(horizontal_water_movement.in_set(WaterSet::HorizontalWaterMovement))
                 .add_systems(
                     (add_water_emitters, update_water_depth).in_set(WaterSet::Synchronization),
+                )
+                .add_system(
+                    mark_flooded_tiles
+                        .after(WaterSet::Synchronization)
+                        .in_set(SimulationSet),
                 );
         });
     }
@@ -392,6 +405,28 @@ pub fn update_water_depth(
     }
 }
 
+/// Records which tiles are covered by surface water in the [`MapGeometry`], for use in placement checks and pathfinding.
+///
+/// The [`MapGeometry`] is only modified when a tile floods or drains, to avoid triggering change detection every tick.
+pub(crate) fn mark_flooded_tiles(
+    query: Query<(&VoxelPos, &WaterDepth)>,
+    mut map_geometry: ResMut<MapGeometry>,
+) {
+    for (terrain_pos, water_depth) in query.iter() {
+        let flooded = water_depth.surface_water_depth() > Height::ZERO;
+        let deep = water_depth.surface_water_depth() > Height::WADING_DEPTH;
+        let previous_flags = map_geometry.placement_flags(terrain_pos.hex);
+
+        if flooded != previous_flags.contains(PlacementBitfield::UNDERWATER) {
+            map_geometry.set_underwater(terrain_pos.hex, flooded);
+        }
+
+        if deep != previous_flags.contains(PlacementBitfield::DEEP_WATER) {
+            map_geometry.set_deep_water(terrain_pos.hex, deep);
+        }
+    }
+}
+
 /// The rate and direction of lateral water flow.
 #[derive(Component, Debug, Default, PartialEq, Clone, Add, AddAssign, Sub, SubAssign)]
 pub struct FlowVelocity {
@@ -475,9 +510,41 @@ fn cache_water_volume(mut water_query: Query<(&WaterVolume, &mut PreviousWaterVo
     }
 }
 
+/// Copies the soil properties of each tile's terrain type from the [`TerrainManifest`] onto its water components.
+///
+/// This runs for tiles that were just spawned or changed terrain type, and for every tile when the manifest is reloaded,
+/// so water always behaves according to the current terrain of each tile.
+fn sync_soil_properties(
+    mut terrain_query: Query<(
+        Ref<Id<Terrain>>,
+        &mut SoilWaterCapacity,
+        &mut SoilWaterEvaporationRate,
+        &mut SoilWaterFlowRate,
+    )>,
+    terrain_manifest: Res<TerrainManifest>,
+) {
+    let manifest_changed = terrain_manifest.is_changed();
+
+    for (terrain_id, mut capacity, mut evaporation_rate, mut flow_rate) in terrain_query.iter_mut()
+    {
+        if !manifest_changed && !terrain_id.is_changed() {
+            continue;
+        }
+
+        let terrain_data = terrain_manifest.get(*terrain_id);
+        capacity.set_if_neq(terrain_data.soil_water_capacity);
+        evaporation_rate.set_if_neq(terrain_data.soil_water_evaporation_rate);
+        flow_rate.set_if_neq(terrain_data.soil_water_flow_rate);
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::{
+        light::shade::ReceivedLight, simulation::time::InGameTime,
+        terrain::terrain_manifest::TerrainData,
+    };
 
     #[test]
     fn water_depth_returns_dry_when_volume_is_zero() {
@@ -515,4 +582,64 @@ mod tests {
         );
         assert_eq!(water_depth, WaterDepth::Flooded(Height(0.5)));
     }
+
+    #[test]
+    fn terrain_types_lose_water_at_their_own_rates() {
+        let mut terrain_manifest = TerrainManifest::new();
+        terrain_manifest.insert(
+            "marsh".to_string(),
+            TerrainData {
+                soil_water_evaporation_rate: SoilWaterEvaporationRate(0.1),
+                ..Default::default()
+            },
+        );
+        terrain_manifest.insert(
+            "sand".to_string(),
+            TerrainData {
+                soil_water_evaporation_rate: SoilWaterEvaporationRate(0.9),
+                ..Default::default()
+            },
+        );
+
+        let mut app = App::new();
+        app.insert_resource(terrain_manifest)
+            .insert_resource(WaterConfig::IN_GAME)
+            .insert_resource(FixedTime::new_from_secs(1.0))
+            .init_resource::<InGameTime>()
+            .add_systems((sync_soil_properties, water_dynamics::evaporation).chain());
+
+        let mut spawn_tile = |terrain_name: &str| {
+            app.world
+                .spawn((
+                    Id::<Terrain>::from_name(terrain_name.to_string()),
+                    ReceivedLight::default(),
+                    WaterBundle {
+                        water_volume: WaterVolume::new(Volume(1.0)),
+                        // Soil properties only matter once the water has soaked into the ground
+                        water_depth: WaterDepth::Underground(Height(0.5)),
+                        ..Default::default()
+                    },
+                ))
+                .id()
+        };
+        let marsh = spawn_tile("marsh");
+        let sand = spawn_tile("sand");
+
+        for _ in 0..100 {
+            app.update();
+        }
+
+        let water_lost = |entity: Entity| {
+            let water_volume = app.world.get::<WaterVolume>(entity).unwrap();
+            Volume(1.0) - water_volume.volume()
+        };
+        let marsh_lost = water_lost(marsh);
+        let sand_lost = water_lost(sand);
+
+        assert!(marsh_lost > Volume::ZERO);
+        assert!(
+            sand_lost > marsh_lost * 5.0,
+            "Sand lost {sand_lost:?}, but marsh lost {marsh_lost:?}"
+        );
+    }
 }
@@ -10,9 +10,10 @@ use crate::{
     crafting::inventories::{CraftingState, InputInventory},
     geometry::{Height, MapGeometry, Volume, VoxelPos},
     items::{item_manifest::ItemManifest, ItemCount},
+    simulation::time::Season,
     structures::structure_manifest::{Structure, StructureManifest},
 };
-use bevy::prelude::*;
+use bevy::{prelude::*, utils::HashMap};
 
 use super::{WaterConfig, WaterDepth, WaterVolume};
 
@@ -68,26 +69,53 @@ impl Display for RootZone {
     }
 }
 
+/// The water drawn up by an organism's roots during the most recent tick.
+///
+/// When root zones overlap, the water in each shared tile is split between every organism drawing from it,
+/// in proportion to their demand.
+#[derive(Component, Debug, Clone, Copy, Default, PartialEq)]
+pub struct WaterReceived(pub Volume);
+
+/// The water that a single structure would like to draw from the water table this tick.
+struct WaterRequest {
+    /// The structure making the request.
+    entity: Entity,
+    /// The tiles that the structure's roots can reach.
+    tiles: Vec<Hex>,
+    /// The volume of water requested from each of those tiles.
+    water_per_tile: Volume,
+}
+
 /// Draws water from the water table if and only if the structure needs more water.
+///
+/// The demand of every structure is summed for each tile, and the available water is shared out proportionally.
+/// The water allocated to each structure is stored in its [`WaterReceived`] component.
 // PERF: we could store RootZone as a component on the structure at the cost of some memory.
 // This would give us faster lookups, but force duplication.
 pub(super) fn draw_water_from_roots(
     water_config: Res<WaterConfig>,
     mut structure_query: Query<(
+        Entity,
         &VoxelPos,
         &Id<Structure>,
         &CraftingState,
-        &mut InputInventory,
+        &InputInventory,
+        &mut WaterReceived,
     )>,
     water_depth_query: Query<&WaterDepth>,
     mut water_volume_query: Query<&mut WaterVolume>,
     structure_manifest: Res<StructureManifest>,
     item_manifest: Res<ItemManifest>,
     map_geometry: Res<MapGeometry>,
+    season: Res<Season>,
 ) {
-    // TODO: only do this during CraftingState::NeedsInput
-    for (&center, &structure_id, crafting_state, mut input_inventory) in structure_query.iter_mut()
+    let mut requests: Vec<WaterRequest> = Vec::new();
+
+    for (entity, &center, &structure_id, crafting_state, input_inventory, mut water_received) in
+        structure_query.iter_mut()
     {
+        water_received.set_if_neq(WaterReceived::default());
+
         if crafting_state != &CraftingState::NeedsInput {
             continue;
         };
@@ -100,34 +128,160 @@ pub(super) fn draw_water_from_roots(
             continue;
         };
 
-        let water_tiles_requested = water_config.items_to_tiles(water_items_requested);
+        let water_consumption_multiplier = structure_manifest
+            .seasonal_multipliers(structure_id, *season)
+            .water_consumption;
+        let water_tiles_requested = water_config.items_to_tiles(water_items_requested)
+            * water_consumption_multiplier.max(0.);
 
         let root_zone = match &structure_manifest.get(structure_id).root_zone {
             Some(root_zone) => root_zone,
             None => continue,
         };
 
-        let relevant_tiles = root_zone.relevant_tiles(center, &water_depth_query, &map_geometry);
-        let n = relevant_tiles.len() as f32;
-        let water_per_tile = water_tiles_requested / n;
+        let tiles = root_zone.relevant_tiles(center, &water_depth_query, &map_geometry);
+        if tiles.is_empty() {
+            continue;
+        }
 
-        let mut total_water = Volume::ZERO;
+        let water_per_tile = water_tiles_requested / tiles.len() as f32;
+        requests.push(WaterRequest {
+            entity,
+            tiles,
+            water_per_tile,
+        });
+    }
 
-        for voxel_pos in relevant_tiles {
-            let terrain_entity = map_geometry.get_terrain(voxel_pos).unwrap();
+    // Sum the demand in a fixed order, so the results don't depend on how entities happen to be iterated
+    requests.sort_by_key(|request| request.entity);
 
-            let mut water_volume = water_volume_query.get_mut(terrain_entity).unwrap();
+    let mut demand_per_tile: HashMap<Hex, Volume> = HashMap::new();
+    for request in &requests {
+        for &hex in &request.tiles {
+            *demand_per_tile.entry(hex).or_default() += request.water_per_tile;
+        }
+    }
+
+    // Never draw more water than a tile has: if demand exceeds supply, every request is scaled down by the same fraction
+    let mut fraction_supplied: HashMap<Hex, f32> = HashMap::new();
+    for (&hex, &demand) in demand_per_tile.iter() {
+        let terrain_entity = map_geometry.get_terrain(hex).unwrap();
+        let mut water_volume = water_volume_query.get_mut(terrain_entity).unwrap();
+
+        let drawn = water_volume.remove(demand);
+        let fraction = match demand > Volume::ZERO {
+            true => drawn.0 / demand.0,
+            false => 0.,
+        };
+        fraction_supplied.insert(hex, fraction);
+    }
 
-            // This can ever so slightly overdraw water, but that's fine.
-            // Accounting for this would significantly complicate the code.
-            // Pretend it's capillary action or something!
-            total_water += water_volume.remove(water_per_tile);
+    for request in requests {
+        let mut total_water = Volume::ZERO;
+        for hex in &request.tiles {
+            total_water += request.water_per_tile * fraction_supplied[hex];
+        }
+
+        let (.., mut water_received) = structure_query.get_mut(request.entity).unwrap();
+        water_received.0 = total_water;
+    }
+}
+
+/// Converts the [`WaterReceived`] by each structure into water items in its input inventory.
+pub(super) fn absorb_received_water(
+    water_config: Res<WaterConfig>,
+    mut structure_query: Query<(&WaterReceived, &mut InputInventory)>,
+    item_manifest: Res<ItemManifest>,
+) {
+    for (water_received, mut input_inventory) in structure_query.iter_mut() {
+        let water_items_produced = water_config.tiles_to_items(water_received.0);
+        if water_items_produced == 0 {
+            continue;
         }
 
-        let water_items_produced = water_config.tiles_to_items(total_water);
         let _ = input_inventory.fill_with_items(
             &ItemCount::new(Id::water(), water_items_produced),
             &item_manifest,
         );
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        items::{inventory::Inventory, item_manifest::ItemData},
+        structures::structure_manifest::StructureData,
+    };
+
+    #[test]
+    fn overlapping_root_zones_share_water() {
+        let mut app = App::new();
+        let map_geometry = MapGeometry::new(&mut app.world, 0);
+        let water_config = WaterConfig::IN_GAME;
+
+        // There's only enough water to fill one plant's inventory
+        let available_water = water_config.items_to_tiles(10);
+        let terrain_entity = map_geometry.get_terrain(Hex::ZERO).unwrap();
+        app.world.entity_mut(terrain_entity).insert((
+            WaterVolume::new(available_water),
+            WaterDepth::Underground(Height(0.5)),
+        ));
+
+        let structure_manifest: StructureManifest = [(
+            "plant".to_string(),
+            StructureData {
+                root_zone: Some(RootZone {
+                    max_depth: Height(1.),
+                    radius: 0,
+                }),
+                ..StructureData::organism("plant")
+            },
+        )]
+        .into_iter()
+        .collect();
+        let item_manifest: ItemManifest = [(
+            "water".to_string(),
+            ItemData {
+                fluid: true,
+                ..ItemData::basic(10)
+            },
+        )]
+        .into_iter()
+        .collect();
+
+        let plants: Vec<Entity> = (0..2)
+            .map(|_| {
+                app.world
+                    .spawn((
+                        map_geometry.on_top_of_terrain(Hex::ZERO),
+                        Id::<Structure>::from_name("plant".to_string()),
+                        CraftingState::NeedsInput,
+                        InputInventory::Exact {
+                            inventory: Inventory::empty_from_item(Id::water(), 10),
+                        },
+                        WaterReceived::default(),
+                    ))
+                    .id()
+            })
+            .collect();
+
+        app.insert_resource(map_geometry)
+            .insert_resource(water_config)
+            .insert_resource(structure_manifest)
+            .insert_resource(item_manifest)
+            .init_resource::<Season>()
+            .add_system(draw_water_from_roots);
+
+        app.update();
+
+        for plant in plants {
+            let water_received = app.world.get::<WaterReceived>(plant).unwrap();
+            assert!((water_received.0 - available_water / 2.).0.abs() < 1e-6);
+        }
+
+        // All of the water was drawn, but no more
+        let water_volume = app.world.get::<WaterVolume>(terrain_entity).unwrap();
+        assert_eq!(water_volume.volume(), Volume::ZERO);
+    }
+}
@@ -1038,6 +1038,70 @@ mod tests {
         }
     }
 
+    #[test]
+    fn tall_water_column_spreads_to_neighbors_and_conserves_water() {
+        let scenario = Scenario {
+            map_size: MapSize::Tiny,
+            map_shape: MapShape::Bedrock,
+            water_table_strategy: WaterTableStrategy::Dry,
+            water_config: WaterConfig {
+                lateral_flow_rate: 100.,
+                ..WaterConfig::NULL
+            },
+            weather: Weather::Clear,
+            simulated_duration: Duration::from_secs(5),
+        };
+
+        let mut app = water_testing_app(scenario);
+        let map_geometry = app.world.resource::<MapGeometry>();
+        let terrain_entity = map_geometry.get_terrain(Hex::ZERO).unwrap();
+        let neighbor_entities: Vec<Entity> = Hex::ZERO
+            .all_neighbors()
+            .into_iter()
+            .map(|hex| map_geometry.get_terrain(hex).unwrap())
+            .collect();
+
+        let starting_total_water = Volume(10.);
+        *app.world.get_mut::<WaterVolume>(terrain_entity).unwrap() =
+            WaterVolume::new(starting_total_water);
+
+        app.update();
+
+        let column_volume = app
+            .world
+            .get::<WaterVolume>(terrain_entity)
+            .unwrap()
+            .volume();
+        assert!(
+            column_volume < starting_total_water,
+            "The water column did not shrink, holding {column_volume:?}"
+        );
+
+        for neighbor_entity in neighbor_entities {
+            let neighbor_volume = app
+                .world
+                .get::<WaterVolume>(neighbor_entity)
+                .unwrap()
+                .volume();
+            assert!(
+                neighbor_volume > Volume::ZERO,
+                "Water did not spread to a neighboring tile"
+            );
+        }
+
+        let mut water_volume_query = app.world.query::<&WaterVolume>();
+        let mut final_total_water = Volume::ZERO;
+        for water_volume in water_volume_query.iter(&app.world) {
+            final_total_water += water_volume.volume();
+        }
+
+        let water_difference = final_total_water.abs_diff(starting_total_water);
+        assert!(
+            water_difference < EPSILON_VOLUME,
+            "Total water at the end ({final_total_water:?}) is not equal to the amount of water that we started with ({starting_total_water:?})"
+        );
+    }
+
     #[test]
     fn extremely_high_lateral_flow_conserves_water() {
         for map_size in MapSize::variants() {
@@ -47,7 +47,7 @@ pub(super) fn generate_organisms(
                         .is_space_available(voxel_pos, footprint, facing)
                         .is_ok()
                 {
-                    commands.spawn_structure(
+                    commands.generate_structure(
                         voxel_pos,
                         ClipboardData::generate_from_id(structure_id, &structure_manifest),
                         StartingEnergy::Random,
@@ -87,7 +87,7 @@ pub(super) fn randomize_starting_organisms(
     mut energy_pool_query: Query<&mut EnergyPool, Without<Id<Structure>>>,
     mut input_inventory_query: Query<&mut InputInventory>,
     mut output_inventory_query: Query<&mut OutputInventory>,
-    mut crafting_state_query: Query<(&mut CraftingState, &ActiveRecipe)>,
+    mut crafting_state_query: Query<(&mut CraftingState, &mut ActiveRecipe)>,
     recipe_manifest: Res<RecipeManifest>,
 ) {
     let rng = &mut thread_rng();
@@ -104,7 +104,12 @@ pub(super) fn randomize_starting_organisms(
         output_inventory.randomize(rng)
     }
 
-    for (mut crafting_state, active_recipe) in crafting_state_query.iter_mut() {
+    for (mut crafting_state, mut active_recipe) in crafting_state_query.iter_mut() {
+        // Random crafters have not rolled their first recipe yet
+        if active_recipe.is_random() && active_recipe.recipe_id().is_none() {
+            active_recipe.reroll(rng);
+        }
+
         if let Some(recipe_id) = active_recipe.recipe_id() {
             let recipe_data = recipe_manifest.get(*recipe_id);
             crafting_state.randomize(rng, recipe_data);
@@ -59,6 +59,10 @@ pub(crate) fn generate_terrain(world: &mut World) {
         let entity = map_geometry.get_terrain(hex).unwrap();
         let voxel_pos = VoxelPos { hex, height };
 
+        let max_occupants = world
+            .get_resource::<TerrainManifest>()
+            .and_then(|terrain_manifest| terrain_manifest.get(terrain_id).max_occupants);
+
         let terrain_bundle = if let Some(handles) = world.get_resource::<TerrainHandles>() {
             let terrain_manifest = world.resource::<TerrainManifest>();
             let scene_handle = handles.scenes.get(&terrain_id).unwrap().clone_weak();
@@ -89,6 +93,7 @@ pub(crate) fn generate_terrain(world: &mut World) {
         // Update the index of what terrain is where
         let mut map_geometry = world.resource_mut::<MapGeometry>();
         map_geometry.update_height(hex, height);
+        map_geometry.set_occupant_limit(entity, max_occupants);
     }
 }
 
@@ -117,7 +122,7 @@ pub(super) fn generate_landmarks(
                         .is_space_available(voxel_pos, footprint, facing)
                         .is_ok()
                 {
-                    commands.spawn_structure(
+                    commands.generate_structure(
                         voxel_pos,
                         ClipboardData::generate_from_id(structure_id, &structure_manifest),
                         StartingEnergy::NotAnOrganism,
@@ -0,0 +1,79 @@
+use std::path::PathBuf;
+
+use emergence_lib::asset_management::manifest::check::{check_manifests, ManifestReport};
+
+fn check_fixture(name: &str) -> (PathBuf, ManifestReport) {
+    let dir = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/fixtures/manifests")
+        .join(name);
+    let report = check_manifests(&dir);
+
+    (dir, report)
+}
+
+#[test]
+fn valid_manifests_pass() {
+    let (_, report) = check_fixture("valid");
+
+    assert_eq!(report.problems, Vec::new());
+    assert_eq!(report.exit_code(), 0);
+    assert_eq!(report.to_string(), "All manifests are valid.");
+}
+
+#[test]
+fn unknown_references_are_reported() {
+    let (dir, report) = check_fixture("unknown_spoilage_product");
+
+    assert_eq!(report.exit_code(), 1);
+    assert_eq!(report.problems.len(), 1);
+
+    let problem = &report.problems[0];
+    assert_eq!(problem.file, dir.join("base_game.item_manifest.json"));
+    assert_eq!(problem.entry.as_deref(), Some("leaf"));
+    assert_eq!(problem.field.as_deref(), Some("spoils_into"));
+    assert!(problem.error.contains("compost"));
+}
+
+#[test]
+fn cross_manifest_problems_are_reported() {
+    let (dir, report) = check_fixture("overflowing_starting_inventory");
+
+    assert_eq!(report.exit_code(), 1);
+    assert_eq!(report.problems.len(), 1);
+
+    let problem = &report.problems[0];
+    assert_eq!(problem.file, dir.join("base_game.structure_manifest.json"));
+    assert_eq!(problem.entry.as_deref(), Some("leaf_bin"));
+    assert_eq!(problem.field.as_deref(), Some("starting_inventory"));
+    assert!(problem.error.contains("5 items do not fit"));
+}
+
+#[test]
+fn unparsable_files_are_reported() {
+    let (dir, report) = check_fixture("malformed_json");
+
+    assert_eq!(report.exit_code(), 1);
+    assert_eq!(report.problems.len(), 1);
+
+    let problem = &report.problems[0];
+    assert_eq!(problem.file, dir.join("base_game.item_manifest.json"));
+    assert_eq!(problem.entry, None);
+    assert_eq!(problem.field, None);
+    assert!(problem.error.starts_with("could not parse file"));
+
+    let printed = report.to_string();
+    assert!(printed.starts_with("Found 1 manifest problem(s):"));
+    assert!(printed.contains("base_game.item_manifest.json | entry: - | field: -"));
+}
+
+#[test]
+fn missing_directories_report_every_file() {
+    let (_, report) = check_fixture("does_not_exist");
+
+    assert_eq!(report.exit_code(), 1);
+    assert_eq!(report.problems.len(), 6);
+    assert!(report
+        .problems
+        .iter()
+        .all(|problem| problem.error.starts_with("could not read file")));
+}
@@ -0,0 +1,90 @@
+use std::path::PathBuf;
+
+use emergence_lib::asset_management::manifest::{
+    graph::{manifest_graph_from_dir, ManifestEdgeKind, ManifestGraph, ManifestNode},
+    Id,
+};
+
+fn graph_fixture() -> ManifestGraph {
+    let dir = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/manifests/graph");
+
+    manifest_graph_from_dir(&dir).unwrap()
+}
+
+fn structure(name: &str) -> ManifestNode {
+    ManifestNode::Structure(Id::from_name(name.to_string()))
+}
+
+fn item(name: &str) -> ManifestNode {
+    ManifestNode::Item(Id::from_name(name.to_string()))
+}
+
+fn recipe(name: &str) -> ManifestNode {
+    ManifestNode::Recipe(Id::from_name(name.to_string()))
+}
+
+#[test]
+fn edges_follow_the_flow_of_materials() {
+    let graph = graph_fixture();
+
+    assert!(graph.has_edge(
+        structure("spring"),
+        item("water"),
+        ManifestEdgeKind::Produces
+    ));
+    assert!(graph.has_edge(item("water"), structure("plant"), ManifestEdgeKind::Builds));
+    assert!(graph.has_edge(
+        structure("plant"),
+        recipe("leaf_production"),
+        ManifestEdgeKind::Crafts
+    ));
+    assert!(graph.has_edge(
+        item("water"),
+        recipe("leaf_production"),
+        ManifestEdgeKind::Consumes
+    ));
+    assert!(graph.has_edge(
+        recipe("leaf_production"),
+        item("leaf"),
+        ManifestEdgeKind::Produces
+    ));
+    assert!(graph.has_edge(item("leaf"), structure("mill"), ManifestEdgeKind::Builds));
+
+    // Tagged inputs are expanded to every item with the tag
+    assert!(graph.has_edge(
+        item("leaf"),
+        recipe("pulp_production"),
+        ManifestEdgeKind::Consumes
+    ));
+    assert!(!graph.has_edge(
+        item("water"),
+        recipe("pulp_production"),
+        ManifestEdgeKind::Consumes
+    ));
+}
+
+#[test]
+fn structures_whose_materials_cannot_be_made_are_unbuildable() {
+    let graph = graph_fixture();
+
+    let unbuildable: Vec<String> = graph
+        .unbuildable_structures()
+        .into_iter()
+        .map(|structure_id| graph.name(ManifestNode::Structure(structure_id)))
+        .collect();
+
+    // Bricks are only made by the brick oven, which is itself built from bricks
+    assert_eq!(unbuildable, vec!["brick_oven", "wall"]);
+}
+
+#[test]
+fn dot_output_is_stable_and_labelled() {
+    let graph = graph_fixture();
+    let dot = graph.to_dot();
+
+    assert!(dot.starts_with("digraph manifests {\n"));
+    assert!(dot.ends_with("}\n"));
+    assert!(dot.contains("    \"structure:mill\" [shape=box];\n"));
+    assert!(dot.contains("    \"item:leaf\" -> \"structure:mill\" [label=\"builds\"];\n"));
+    assert_eq!(dot, graph_fixture().to_dot());
+}
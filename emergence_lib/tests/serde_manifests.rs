@@ -1,30 +1,34 @@
 use bevy::utils::HashMap;
 use emergence_lib::{
+    asset_management::manifest::loader::{parse_raw_manifest, IsRawManifest},
     construction::RawConstructionStrategy,
     crafting::{
         item_tags::ItemTag,
         recipe::{
             RawActiveRecipe, RawRecipeData, RawRecipeInput, RawRecipeManifest, RecipeConditions,
-            Threshold,
+            RecipeEffect, Threshold,
         },
     },
-    geometry::Height,
+    geometry::{Height, Volume},
     items::item_manifest::{RawItemData, RawItemManifest},
     light::Illuminance,
     organisms::{
         energy::{Energy, EnergyPool},
+        life_stages::{RawLifeSpan, RemainsData},
         lifecycle::{RawLifePath, RawLifecycle},
         vegetative_reproduction::RawVegetativeReproduction,
-        RawOrganismId, RawOrganismVariety,
+        RawOrganismId, RawOrganismVariety, SeasonalModifiers,
     },
     structures::{
-        structure_manifest::{RawStructureData, RawStructureKind, RawStructureManifest},
+        structure_manifest::{
+            MoistureRange, RawStructureData, RawStructureKind, RawStructureManifest,
+        },
         Footprint,
     },
     terrain::terrain_manifest::{RawTerrainManifest, TerrainData},
     units::{
         basic_needs::RawDiet,
-        unit_manifest::{RawUnitData, RawUnitManifest},
+        unit_manifest::{RawUnitData, RawUnitManifest, StaminaData},
         WanderingBehavior,
     },
     water::{
@@ -47,7 +51,12 @@ fn can_serialize_item_manifest() {
                     compostable: true,
                     fluid: false,
                     buoyant: true,
+                    fragile: false,
                     seed: None,
+                    shelf_life: None,
+                    spoils_into: None,
+                    water_contents: None,
+                    substitutes: Vec::new(),
                 },
             ),
             (
@@ -57,7 +66,12 @@ fn can_serialize_item_manifest() {
                     compostable: false,
                     fluid: false,
                     buoyant: false,
+                    fragile: false,
                     seed: Some(RawOrganismId::Structure("test_organism".to_string())),
+                    shelf_life: Some(30.),
+                    spoils_into: Some("test_item".to_string()),
+                    water_contents: None,
+                    substitutes: Vec::new(),
                 },
             ),
             (
@@ -67,10 +81,16 @@ fn can_serialize_item_manifest() {
                     compostable: false,
                     fluid: true,
                     buoyant: false,
+                    fragile: false,
                     seed: None,
+                    shelf_life: None,
+                    spoils_into: None,
+                    water_contents: None,
+                    substitutes: Vec::new(),
                 },
             ),
         ]),
+        tag_hierarchy: HashMap::from_iter([(ItemTag::Seed, vec![ItemTag::Compostable])]),
     };
 
     // Serialize it
@@ -95,6 +115,7 @@ fn can_serialize_terrain_manifest() {
                 soil_water_capacity: SoilWaterCapacity(0.3),
                 soil_water_flow_rate: SoilWaterFlowRate(0.1),
                 soil_water_evaporation_rate: SoilWaterEvaporationRate(0.2),
+                max_occupants: Some(4),
             },
         )]),
     };
@@ -110,6 +131,20 @@ fn can_serialize_terrain_manifest() {
     assert_eq!(raw_terrain_manifest, deserialized);
 }
 
+#[test]
+fn terrain_without_soil_properties_uses_defaults() {
+    let json = r#"{ "terrain_types": { "old_terrain": { "walking_speed": 0.8 } } }"#;
+    let raw_terrain_manifest: RawTerrainManifest = serde_json::from_str(json).unwrap();
+
+    assert_eq!(
+        raw_terrain_manifest.terrain_types["old_terrain"],
+        TerrainData {
+            walking_speed: 0.8,
+            ..Default::default()
+        }
+    );
+}
+
 #[test]
 fn can_serialize_unit_manifest() {
     // Create a new raw unit manifest
@@ -122,6 +157,10 @@ fn can_serialize_unit_manifest() {
                         prototypical_form: RawOrganismId::unit("ant"),
                         lifecycle: RawLifecycle::STATIC,
                         energy_pool: EnergyPool::new_full(Energy(100.), Energy(-1.)),
+                        seasonal_modifiers: SeasonalModifiers::default(),
+                        life_span: None,
+                        remains: None,
+                        pollination: None,
                     },
                     diet: RawDiet::new("leuco_chunk", 50.),
                     max_impatience: 10,
@@ -130,6 +169,13 @@ fn can_serialize_unit_manifest() {
                         (8, 0.2),
                         (16, 0.1),
                     ]),
+                    carry_capacity: 1,
+                    can_swim: false,
+                    stamina: Some(StaminaData {
+                        max: 100.,
+                        work_cost: 2.,
+                        idle_recovery: 0.5,
+                    }),
                     max_age: 10.,
                 },
             ),
@@ -140,10 +186,17 @@ fn can_serialize_unit_manifest() {
                         prototypical_form: RawOrganismId::unit("test_unit"),
                         lifecycle: RawLifecycle::STATIC,
                         energy_pool: EnergyPool::new_full(Energy(50.), Energy(0.)),
+                        seasonal_modifiers: SeasonalModifiers::default(),
+                        life_span: None,
+                        remains: None,
+                        pollination: None,
                     },
                     diet: RawDiet::new("acacia_leaf", 0.),
                     max_impatience: 0,
                     wandering_behavior: WanderingBehavior::from_iter([(0, 0.7), (16, 0.1)]),
+                    carry_capacity: 3,
+                    can_swim: true,
+                    stamina: None,
                     max_age: 0.2,
                 },
             ),
@@ -170,17 +223,21 @@ fn can_serialize_recipe_manifest() {
                 "mature_acacia_production".to_string(),
                 RawRecipeData {
                     inputs: RawRecipeInput::empty(),
+                    catalysts: Vec::new(),
                     outputs: HashMap::from_iter([
                         ("acacia_leaf".to_string(), 1.),
                         // Output can be stochastic
                         ("acacia_seed".to_string(), 0.1),
                     ]),
+                    effects: Vec::new(),
                     craft_time: 3.,
                     conditions: Some(RecipeConditions::new(
                         0,
                         Threshold::new(Illuminance::DimlyLit, Illuminance::BrightlyLit),
                     )),
                     energy: Some(Energy(20.)),
+                    required_milestone: None,
+                    draw_from_litter: false,
                 },
             ),
             (
@@ -189,24 +246,53 @@ fn can_serialize_recipe_manifest() {
                     inputs: RawRecipeInput::Flexible {
                         tag: ItemTag::Compostable,
                         count: 1,
+                        exclude: vec!["leuco_chunk".to_string()],
                     },
+                    catalysts: Vec::new(),
                     outputs: HashMap::from_iter([("leuco_chunk".to_string(), 1.)]),
+                    effects: Vec::new(),
                     craft_time: 2.,
                     conditions: None,
                     energy: Some(Energy(40.)),
+                    required_milestone: None,
+                    draw_from_litter: false,
                 },
             ),
             (
                 "ant_egg_production".to_string(),
                 RawRecipeData {
                     inputs: RawRecipeInput::single("leuco_chunk", 1),
+                    catalysts: Vec::new(),
                     outputs: HashMap::from_iter([("ant_egg".to_string(), 1.)]),
+                    effects: vec![
+                        RecipeEffect::LowerTerrain,
+                        RecipeEffect::AddWater(Volume(0.5)),
+                    ],
                     craft_time: 10.,
                     conditions: Some(RecipeConditions {
                         workers_required: 2,
                         allowable_light_range: None,
                     }),
                     energy: None,
+                    required_milestone: None,
+                    draw_from_litter: false,
+                },
+            ),
+            (
+                "fresh_leaf_production".to_string(),
+                RawRecipeData {
+                    inputs: RawRecipeInput::Fresh {
+                        inputs: HashMap::from_iter([("acacia_leaf".to_string(), 2)]),
+                        min_freshness: 0.5,
+                    },
+                    catalysts: Vec::new(),
+                    outputs: HashMap::from_iter([("leuco_chunk".to_string(), 1.)]),
+                    effects: Vec::new(),
+                    craft_time: 1.,
+                    conditions: None,
+                    energy: None,
+                    required_milestone: None,
+                    draw_from_litter: false,
                 },
             ),
         ]),
@@ -223,6 +309,50 @@ fn can_serialize_recipe_manifest() {
     assert_eq!(raw_recipe_manifest, deserialized);
 }
 
+#[test]
+fn generators_and_sinks_can_omit_inputs_and_outputs() {
+    let json = r#"{ "recipes": {
+        "sunlight": { "outputs": { "light": 1.0 }, "craft_time": 1.0 },
+        "incineration": { "inputs": { "Exact": { "trash": 1 } }, "craft_time": 1.0 }
+    } }"#;
+    let raw_recipe_manifest: RawRecipeManifest = serde_json::from_str(json).unwrap();
+
+    assert_eq!(
+        raw_recipe_manifest.recipes["sunlight"].inputs,
+        RawRecipeInput::empty()
+    );
+    assert!(raw_recipe_manifest.recipes["incineration"]
+        .outputs
+        .is_empty());
+}
+
+#[test]
+fn recipe_manifest_round_trips_through_export() {
+    let path = std::path::PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/fixtures/manifests/recipe_book/base_game.recipe_manifest.json");
+    let bytes = std::fs::read(path).unwrap();
+
+    // Import the recipe book
+    let imported = parse_raw_manifest::<RawRecipeManifest>(&bytes).unwrap();
+    let recipe_manifest = imported.process().unwrap();
+    assert_eq!(recipe_manifest.len(), 3);
+
+    // Export it, then import the export
+    let exported = imported.to_json().unwrap();
+    let reimported = parse_raw_manifest::<RawRecipeManifest>(exported.as_bytes()).unwrap();
+    assert_eq!(imported, reimported);
+    assert_eq!(reimported.process().unwrap().len(), recipe_manifest.len());
+
+    // Exporting again must not change a thing
+    assert_eq!(reimported.to_json().unwrap(), exported);
+
+    // Recipes are exported in order of their names
+    let brick = exported.find("\"brick_production\"").unwrap();
+    let compost = exported.find("\"compost_production\"").unwrap();
+    let leaf = exported.find("\"leaf_production\"").unwrap();
+    assert!(brick < compost && compost < leaf);
+}
+
 #[test]
 fn can_serialize_structure_manifest() {
     // Create a new raw structure manifest
@@ -235,20 +365,37 @@ fn can_serialize_structure_manifest() {
                         prototypical_form: RawOrganismId::structure("leuco"),
                         lifecycle: RawLifecycle::STATIC,
                         energy_pool: EnergyPool::new_full(Energy(100.), Energy(-1.)),
+                        seasonal_modifiers: SeasonalModifiers::default(),
+                        life_span: None,
+                        remains: None,
+                        pollination: None,
                     }),
                     kind: RawStructureKind::Crafting {
                         starting_recipe: RawActiveRecipe::new("leuco_chunk_production"),
+                        input_buffer_slots: None,
+                        output_buffer_slots: None,
                     },
                     construction_strategy: RawConstructionStrategy::Direct {
                         work: Some(3.),
                         materials: HashMap::from_iter([("leuco_chunk".to_string(), 1)]),
+                        required_terrain_height: None,
+                        allow_terraform: false,
                     },
                     max_workers: 6,
-                    footprint: Some(Footprint::single()),
+                    footprint: Some(Footprint::single().into()),
                     root_zone: None,
                     can_walk_on_roof: false,
+                    max_occupants: None,
                     can_walk_through: false,
                     vegetative_reproduction: None,
+                    recipe_presets: vec![RawActiveRecipe::new("leuco_chunk_production")],
+                    allowed_moisture: Some(MoistureRange {
+                        min_depth: Height(0.),
+                        max_depth: Height(0.5),
+                        max_water_table_depth: None,
+                    }),
+                    tags: Vec::new(),
+                    required_milestone: None,
                 },
             ),
             (
@@ -259,13 +406,20 @@ fn can_serialize_structure_manifest() {
                     construction_strategy: RawConstructionStrategy::Direct {
                         work: Some(3.),
                         materials: HashMap::new(),
+                        required_terrain_height: None,
+                        allow_terraform: false,
                     },
                     max_workers: 1,
-                    footprint: Some(Footprint::single()),
+                    footprint: Some(Footprint::single().into()),
                     root_zone: None,
                     can_walk_on_roof: false,
+                    max_occupants: None,
                     can_walk_through: true,
                     vegetative_reproduction: None,
+                    recipe_presets: Vec::new(),
+                    allowed_moisture: None,
+                    tags: Vec::new(),
+                    required_milestone: None,
                 },
             ),
             (
@@ -278,8 +432,13 @@ fn can_serialize_structure_manifest() {
                     footprint: None,
                     root_zone: None,
                     can_walk_on_roof: false,
+                    max_occupants: None,
                     can_walk_through: false,
                     vegetative_reproduction: None,
+                    recipe_presets: Vec::new(),
+                    allowed_moisture: None,
+                    tags: Vec::new(),
+                    required_milestone: None,
                 },
             ),
             (
@@ -293,20 +452,33 @@ fn can_serialize_structure_manifest() {
                             time_required: Some(1.),
                         }]),
                         energy_pool: EnergyPool::new_full(Energy(50.), Energy(-1.)),
+                        seasonal_modifiers: SeasonalModifiers::default(),
+                        life_span: None,
+                        remains: None,
+                        pollination: None,
                     }),
                     kind: RawStructureKind::Crafting {
                         starting_recipe: RawActiveRecipe::new("acacia_leaf_production"),
+                        input_buffer_slots: None,
+                        output_buffer_slots: None,
                     },
                     construction_strategy: RawConstructionStrategy::Direct {
                         work: None,
                         materials: HashMap::from_iter([("acacia_seed".to_string(), 1)]),
+                        required_terrain_height: None,
+                        allow_terraform: false,
                     },
                     max_workers: 1,
-                    footprint: Some(Footprint::single()),
+                    footprint: Some(Footprint::single().into()),
                     root_zone: None,
                     can_walk_on_roof: false,
+                    max_occupants: None,
                     can_walk_through: false,
                     vegetative_reproduction: None,
+                    recipe_presets: Vec::new(),
+                    allowed_moisture: None,
+                    tags: Vec::new(),
+                    required_milestone: None,
                 },
             ),
             (
@@ -316,25 +488,43 @@ fn can_serialize_structure_manifest() {
                         prototypical_form: RawOrganismId::structure("acacia"),
                         lifecycle: RawLifecycle::STATIC,
                         energy_pool: EnergyPool::new_full(Energy(300.), Energy(-1.)),
+                        seasonal_modifiers: SeasonalModifiers::default(),
+                        life_span: Some(RawLifeSpan {
+                            maturity: 0.,
+                            max_age: 30.,
+                        }),
+                        remains: Some(RemainsData {
+                            structure: None,
+                            items: vec![("acacia_leaf".to_string(), 3)],
+                            salvage: Vec::new(),
+                        }),
+                        pollination: None,
                     }),
                     kind: RawStructureKind::Crafting {
                         starting_recipe: RawActiveRecipe::new("acacia_leaf_production"),
+                        input_buffer_slots: None,
+                        output_buffer_slots: None,
                     },
                     construction_strategy: RawConstructionStrategy::Seedling(
                         "acacia_seedling".to_string(),
                     ),
                     max_workers: 6,
-                    footprint: Some(Footprint::single()),
+                    footprint: Some(Footprint::single().into()),
                     root_zone: Some(RootZone {
                         max_depth: Height(3.0),
                         radius: 2,
                     }),
                     can_walk_on_roof: false,
+                    max_occupants: None,
                     can_walk_through: false,
                     vegetative_reproduction: Some(RawVegetativeReproduction {
                         period: 10.,
                         energy_threshold: 30.,
                     }),
+                    recipe_presets: Vec::new(),
+                    allowed_moisture: None,
+                    tags: Vec::new(),
+                    required_milestone: None,
                 },
             ),
             (
@@ -343,17 +533,26 @@ fn can_serialize_structure_manifest() {
                     organism_variety: None,
                     kind: RawStructureKind::Crafting {
                         starting_recipe: RawActiveRecipe::new("ant_egg_production"),
+                        input_buffer_slots: None,
+                        output_buffer_slots: None,
                     },
                     construction_strategy: RawConstructionStrategy::Direct {
                         work: Some(10.),
                         materials: HashMap::new(),
+                        required_terrain_height: None,
+                        allow_terraform: false,
                     },
                     max_workers: 3,
-                    footprint: Some(Footprint::hexagon(1)),
+                    footprint: Some(Footprint::hexagon(1).into()),
                     root_zone: None,
                     can_walk_on_roof: false,
+                    max_occupants: None,
                     can_walk_through: false,
                     vegetative_reproduction: None,
+                    recipe_presets: Vec::new(),
+                    allowed_moisture: None,
+                    tags: Vec::new(),
+                    required_milestone: None,
                 },
             ),
             (
@@ -363,17 +562,26 @@ fn can_serialize_structure_manifest() {
                     kind: RawStructureKind::Storage {
                         max_slot_count: 3,
                         reserved_for: None,
+                        starting_inventory: vec![("leuco_chunk".to_string(), 2)],
+                        merge_adjacent: false,
                     },
                     construction_strategy: RawConstructionStrategy::Direct {
                         work: Some(10.),
                         materials: HashMap::from_iter([("leuco_chunk".to_string(), 1)]),
+                        required_terrain_height: None,
+                        allow_terraform: false,
                     },
                     max_workers: 6,
-                    footprint: Some(Footprint::single()),
+                    footprint: Some(Footprint::single().into()),
                     root_zone: None,
                     can_walk_on_roof: false,
+                    max_occupants: None,
                     can_walk_through: false,
                     vegetative_reproduction: None,
+                    recipe_presets: Vec::new(),
+                    allowed_moisture: None,
+                    tags: Vec::new(),
+                    required_milestone: None,
                 },
             ),
         ]),
@@ -389,3 +597,83 @@ fn can_serialize_structure_manifest() {
     // Check that the deserialized version is the same as the original
     assert_eq!(raw_structure_manifest, deserialized);
 }
+
+#[test]
+fn structure_manifest_serialization_is_stable() {
+    let structure = |name: &str| {
+        format!(
+            r#""{name}": {{
+                "kind": "Path",
+                "construction_strategy": "Landmark",
+                "max_workers": 1,
+                "can_walk_on_roof": true,
+                "can_walk_through": true
+            }}"#
+        )
+    };
+
+    // Entries can be provided in any order
+    let json = format!(
+        r#"{{ "structure_types": {{ {}, {}, {} }} }}"#,
+        structure("zebra_grass"),
+        structure("acacia"),
+        structure("moss"),
+    );
+    let raw_structure_manifest: RawStructureManifest = serde_json::from_str(&json).unwrap();
+
+    let first = serde_json::to_string(&raw_structure_manifest).unwrap();
+    let second = serde_json::to_string(&raw_structure_manifest).unwrap();
+    assert_eq!(first, second);
+
+    // A copy with a different internal layout still produces the same output
+    let round_tripped: RawStructureManifest = serde_json::from_str(&first).unwrap();
+    assert_eq!(first, serde_json::to_string(&round_tripped).unwrap());
+
+    // Entries are written in order of their names
+    let acacia = first.find("\"acacia\"").unwrap();
+    let moss = first.find("\"moss\"").unwrap();
+    let zebra_grass = first.find("\"zebra_grass\"").unwrap();
+    assert!(acacia < moss && moss < zebra_grass);
+}
+
+#[test]
+fn releasers_and_absorbers_can_omit_their_fields() {
+    let structure = |kind: &str| {
+        format!(
+            r#"{{ "structure_types": {{ "chute": {{
+                "kind": {kind},
+                "construction_strategy": "Landmark",
+                "max_workers": 1,
+                "can_walk_on_roof": false,
+                "can_walk_through": false
+            }} }} }}"#
+        )
+    };
+    let kind = |json: String| -> RawStructureKind {
+        let raw_structure_manifest: RawStructureManifest = serde_json::from_str(&json).unwrap();
+        raw_structure_manifest.structure_types["chute"].kind.clone()
+    };
+
+    let defaults = RawStructureKind::Releaser {
+        release_threshold: None,
+        released_item: None,
+    };
+    // Manifests written before releasers and absorbers had any fields still load
+    assert_eq!(kind(structure(r#""Releaser""#)), defaults);
+    assert_eq!(kind(structure(r#"{ "Releaser": {} }"#)), defaults);
+    assert_eq!(
+        kind(structure(r#"{ "Releaser": { "release_threshold": 5 } }"#)),
+        RawStructureKind::Releaser {
+            release_threshold: Some(5),
+            released_item: None,
+        }
+    );
+
+    let defaults = RawStructureKind::Absorber {
+        overflow_to_adjacent: false,
+    };
+    assert_eq!(kind(structure(r#""Absorber""#)), defaults);
+    assert_eq!(kind(structure(r#"{ "Absorber": {} }"#)), defaults);
+
+    assert_eq!(kind(structure(r#""Path""#)), RawStructureKind::Path);
+}